@@ -0,0 +1,448 @@
+//! Compare two sets of results, such as summaries of a pack run with two versions of a node model.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fs::{File, read_dir},
+    io::Read,
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use clap::Parser;
+use frog_cli::{
+    errors::ErrorLog,
+    table::{TableEntry, TableRow, make_table_entry, write_table},
+};
+use frogcore::sim_file::load_output;
+use serde::Serialize;
+
+const DEFAULT_METRICS: [&str; 6] = [
+    "global_reception_rate",
+    "avg_reception",
+    "global_latency",
+    "avg_avg_latency",
+    "total_transmissions",
+    "total_airtime",
+];
+
+/// Metrics containing any of these are better when lower.
+const LOWER_IS_BETTER: [&str; 5] = [
+    "latency",
+    "_score",
+    "transmissions",
+    "airtime",
+    "duty_cycle",
+];
+
+/// Differences smaller than this count as a tie.
+const TIE_TOLERANCE: f64 = 1e-9;
+
+#[derive(Parser, Debug)]
+#[command(version, about, long_about = None)]
+struct Args {
+    /// Summary CSV or a directory of summary CSVs and simulation output files
+    baseline: PathBuf,
+
+    /// Summary CSV or a directory of summary CSVs and simulation output files
+    candidate: PathBuf,
+
+    /// Columns to compare
+    #[arg(long, value_delimiter = ',', default_values_t = DEFAULT_METRICS.map(String::from))]
+    metric: Vec<String>,
+
+    /// Number of regressions to show for each metric
+    #[arg(long, default_value_t = 5)]
+    top: usize,
+
+    /// Write the delta of every matched row and metric to a CSV
+    #[arg(short, long)]
+    out: Option<PathBuf>,
+
+    /// Skip verifying simulation output files
+    #[arg(long)]
+    no_verify: bool,
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+
+    let load = |path: &Path| {
+        load_rows(path, args.no_verify).map_err(|e| format!("{}: {e}", path.display()))
+    };
+
+    let (baseline, candidate) = match (load(&args.baseline), load(&args.candidate)) {
+        (Ok(baseline), Ok(candidate)) => (baseline, candidate),
+        (Err(e), _) | (_, Err(e)) => {
+            eprintln!("<Error> {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let deltas = match compare_rows(&baseline, &candidate, &args.metric) {
+        Ok(deltas) => deltas,
+        Err(e) => {
+            eprintln!("<Error> {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let summaries = summarise(&deltas, &args.metric);
+    let matched = summaries.first().map(|x| x.matched).unwrap_or(0);
+    println!(
+        "Matched {matched} rows ({} baseline, {} candidate)",
+        baseline.len(),
+        candidate.len()
+    );
+    println!();
+    print_summaries(&summaries);
+
+    for metric in args.metric.iter() {
+        let regressions = biggest_regressions(&deltas, metric, args.top);
+        if regressions.is_empty() {
+            continue;
+        }
+
+        println!();
+        println!("Biggest regressions in {metric}:");
+        for x in regressions {
+            println!(
+                "  {:+.6} ({:.6} -> {:.6}) seed {} {}",
+                x.delta, x.baseline, x.candidate, x.seed, x.scenario_identity
+            );
+        }
+    }
+
+    if let Some(out) = args.out {
+        write_table(Some(out), &deltas);
+    }
+
+    ExitCode::SUCCESS
+}
+
+/// A row of a summary table by column name.
+type Row = HashMap<String, String>;
+
+/// Rows are matched by scenario identity and seed.
+type RowKey = (String, String);
+
+fn row_key(row: &Row) -> Option<RowKey> {
+    let scenario = row.get("scenario_identity")?;
+    // Aggregated summaries name the seed column `first_seed`
+    let seed = row.get("seed").or_else(|| row.get("first_seed"))?;
+    Some((scenario.clone(), seed.clone()))
+}
+
+fn read_rows(reader: impl Read) -> Result<Vec<Row>, csv::Error> {
+    csv::Reader::from_reader(reader).deserialize().collect()
+}
+
+fn entry_row(entry: &TableEntry) -> Result<Row, String> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer
+        .serialize(entry.csv_row())
+        .map_err(|e| e.to_string())?;
+    let bytes = writer.into_inner().map_err(|e| e.to_string())?;
+
+    let mut rows = read_rows(bytes.as_slice()).map_err(|e| e.to_string())?;
+    rows.pop().ok_or_else(|| "Empty table entry".to_owned())
+}
+
+/// Loads a summary CSV, or every summary CSV and simulation output file in a directory.
+fn load_rows(path: &Path, no_verify: bool) -> Result<Vec<(RowKey, Row)>, String> {
+    let rows = if path.is_file() {
+        read_rows(File::open(path).map_err(|e| e.to_string())?).map_err(|e| e.to_string())?
+    } else {
+        let mut paths: Vec<PathBuf> = read_dir(path)
+            .map_err(|e| e.to_string())?
+            .filter_map(|x| x.ok().map(|x| x.path()))
+            .filter(|x| x.is_file())
+            .collect();
+        paths.sort();
+
+        let mut rows = Vec::new();
+        for file in paths {
+            if file.extension().is_some_and(|x| x == "csv") {
+                let file_rows = File::open(&file)
+                    .map_err(csv::Error::from)
+                    .and_then(read_rows)
+                    .map_err(|e| format!("{}: {e}", file.display()))?;
+                rows.extend(file_rows);
+                continue;
+            }
+
+            match load_output(file.clone()) {
+                Ok(output) => rows.push(entry_row(&make_table_entry(
+                    no_verify,
+                    false,
+                    None,
+                    output,
+                    &ErrorLog::new(),
+                ))?),
+                Err(e) => eprintln!("<Warning> Skipping {}: {e}", file.display()),
+            }
+        }
+        rows
+    };
+
+    let mut keyed: Vec<(RowKey, Row)> = Vec::with_capacity(rows.len());
+    let mut seen = HashSet::new();
+    for row in rows {
+        let key = row_key(&row).ok_or("Rows need scenario_identity and seed columns")?;
+        if !seen.insert(key.clone()) {
+            eprintln!("<Warning> Duplicate row for seed {} ignored", key.1);
+            continue;
+        }
+        keyed.push((key, row));
+    }
+
+    Ok(keyed)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+enum Outcome {
+    Win,
+    Loss,
+    Tie,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct Delta {
+    scenario_identity: String,
+    seed: String,
+    metric: String,
+    baseline: f64,
+    candidate: f64,
+    delta: f64,
+    outcome: Outcome,
+}
+
+fn lower_is_better(metric: &str) -> bool {
+    LOWER_IS_BETTER.iter().any(|x| metric.contains(x))
+}
+
+fn outcome(metric: &str, baseline: f64, candidate: f64) -> Outcome {
+    // NaN means there was nothing to measure, such as the latency when no messages arrived
+    match (baseline.is_nan(), candidate.is_nan()) {
+        (true, true) => return Outcome::Tie,
+        (true, false) => return Outcome::Win,
+        (false, true) => return Outcome::Loss,
+        (false, false) => (),
+    }
+
+    let improvement = if lower_is_better(metric) {
+        baseline - candidate
+    } else {
+        candidate - baseline
+    };
+
+    if improvement.abs() <= TIE_TOLERANCE {
+        Outcome::Tie
+    } else if improvement > 0.0 {
+        Outcome::Win
+    } else {
+        Outcome::Loss
+    }
+}
+
+/// Deltas for every metric of every baseline row with a matching candidate row,
+/// in baseline order.
+fn compare_rows(
+    baseline: &[(RowKey, Row)],
+    candidate: &[(RowKey, Row)],
+    metrics: &[String],
+) -> Result<Vec<Delta>, String> {
+    let candidate: HashMap<&RowKey, &Row> = candidate.iter().map(|(k, v)| (k, v)).collect();
+
+    let value = |row: &Row, metric: &str, name: &str| -> Result<f64, String> {
+        let field = row
+            .get(metric)
+            .ok_or_else(|| format!("Metric {metric} not found in {name}"))?;
+        field
+            .parse()
+            .map_err(|_| format!("Metric {metric} has non numeric value {field:?} in {name}"))
+    };
+
+    let mut deltas = Vec::new();
+    for (key, base_row) in baseline {
+        let Some(cand_row) = candidate.get(key) else {
+            continue;
+        };
+
+        for metric in metrics {
+            let base = value(base_row, metric, "baseline")?;
+            let cand = value(cand_row, metric, "candidate")?;
+
+            deltas.push(Delta {
+                scenario_identity: key.0.clone(),
+                seed: key.1.clone(),
+                metric: metric.clone(),
+                baseline: base,
+                candidate: cand,
+                delta: cand - base,
+                outcome: outcome(metric, base, cand),
+            });
+        }
+    }
+
+    Ok(deltas)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct MetricSummary {
+    metric: String,
+    matched: usize,
+    wins: usize,
+    losses: usize,
+    ties: usize,
+    baseline_mean: f64,
+    candidate_mean: f64,
+    mean_delta: f64,
+}
+
+/// Means ignore NaN values.
+fn summarise(deltas: &[Delta], metrics: &[String]) -> Vec<MetricSummary> {
+    let finite_mean = |values: &mut dyn Iterator<Item = f64>| {
+        let (sum, count) = values
+            .filter(|x| !x.is_nan())
+            .fold((0.0, 0), |(sum, count), x| (sum + x, count + 1));
+        sum / count as f64
+    };
+
+    metrics
+        .iter()
+        .map(|metric| {
+            let rows: Vec<&Delta> = deltas.iter().filter(|x| &x.metric == metric).collect();
+            let count = |outcome| rows.iter().filter(|x| x.outcome == outcome).count();
+
+            MetricSummary {
+                metric: metric.clone(),
+                matched: rows.len(),
+                wins: count(Outcome::Win),
+                losses: count(Outcome::Loss),
+                ties: count(Outcome::Tie),
+                baseline_mean: finite_mean(&mut rows.iter().map(|x| x.baseline)),
+                candidate_mean: finite_mean(&mut rows.iter().map(|x| x.candidate)),
+                mean_delta: finite_mean(&mut rows.iter().map(|x| x.delta)),
+            }
+        })
+        .collect()
+}
+
+/// Losses for a metric, worst first.
+fn biggest_regressions<'a>(deltas: &'a [Delta], metric: &str, count: usize) -> Vec<&'a Delta> {
+    let badness = |x: &Delta| {
+        if x.delta.is_nan() {
+            f64::INFINITY
+        } else if lower_is_better(metric) {
+            x.delta
+        } else {
+            -x.delta
+        }
+    };
+
+    let mut losses: Vec<&Delta> = deltas
+        .iter()
+        .filter(|x| x.metric == metric && x.outcome == Outcome::Loss)
+        .collect();
+    losses.sort_by(|a, b| badness(b).total_cmp(&badness(a)));
+    losses.truncate(count);
+    losses
+}
+
+fn print_summaries(summaries: &[MetricSummary]) {
+    let width = summaries
+        .iter()
+        .map(|x| x.metric.len())
+        .max()
+        .unwrap_or(0)
+        .max("metric".len());
+
+    println!(
+        "{:<width$} {:>7} {:>6} {:>6} {:>6} {:>12} {:>12} {:>12}",
+        "metric", "matched", "wins", "losses", "ties", "baseline", "candidate", "mean delta"
+    );
+
+    for x in summaries {
+        println!(
+            "{:<width$} {:>7} {:>6} {:>6} {:>6} {:>12.6} {:>12.6} {:>+12.6}",
+            x.metric,
+            x.matched,
+            x.wins,
+            x.losses,
+            x.ties,
+            x.baseline_mean,
+            x.candidate_mean,
+            x.mean_delta
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASELINE: &str = "\
+scenario_identity,seed,global_reception_rate,global_latency
+a,1,0.5,10.0
+a,2,0.8,12.0
+b,1,0.9,NaN
+c,1,0.4,5.0
+";
+
+    const CANDIDATE: &str = "\
+scenario_identity,seed,global_reception_rate,global_latency
+a,2,0.6,9.0
+a,1,0.75,10.0
+b,1,0.9,4.0
+d,1,1.0,1.0
+";
+
+    fn keyed(text: &str) -> Vec<(RowKey, Row)> {
+        read_rows(text.as_bytes())
+            .unwrap()
+            .into_iter()
+            .map(|row| (row_key(&row).unwrap(), row))
+            .collect()
+    }
+
+    #[test]
+    fn deltas_between_csvs() {
+        let metrics = [
+            "global_reception_rate".to_owned(),
+            "global_latency".to_owned(),
+        ];
+        let deltas = compare_rows(&keyed(BASELINE), &keyed(CANDIDATE), &metrics).unwrap();
+
+        // Rows a/1, a/2 and b/1 match, each with both metrics
+        assert_eq!(deltas.len(), 6);
+
+        assert_eq!(deltas[0].seed, "1");
+        assert_eq!(deltas[0].metric, "global_reception_rate");
+        assert!((deltas[0].delta - 0.25).abs() < 1e-12);
+        assert_eq!(deltas[0].outcome, Outcome::Win);
+
+        // Lower latency is better
+        assert_eq!(deltas[3].metric, "global_latency");
+        assert!((deltas[3].delta + 3.0).abs() < 1e-12);
+        assert_eq!(deltas[3].outcome, Outcome::Win);
+        assert_eq!(deltas[2].outcome, Outcome::Loss);
+        assert_eq!(deltas[1].outcome, Outcome::Tie);
+
+        let summaries = summarise(&deltas, &metrics);
+        let reception = &summaries[0];
+        assert_eq!(
+            (reception.wins, reception.losses, reception.ties),
+            (1, 1, 1)
+        );
+        assert!((reception.mean_delta - 0.05 / 3.0).abs() < 1e-12);
+
+        // Latency of b/1 was NaN in the baseline so the mean delta only uses a/1 and a/2
+        let latency = &summaries[1];
+        assert_eq!((latency.wins, latency.losses, latency.ties), (2, 0, 1));
+        assert!((latency.mean_delta + 1.5).abs() < 1e-12);
+
+        let regressions = biggest_regressions(&deltas, "global_reception_rate", 5);
+        assert_eq!(regressions.len(), 1);
+        assert_eq!(regressions[0].seed, "2");
+    }
+}
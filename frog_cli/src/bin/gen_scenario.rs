@@ -6,6 +6,7 @@ use frogcore::{
         ScenarioIdentity,
         generation::{
             ScenarioGenerator::{self, RandomSquare},
+            SfAssignment,
             messaging::IndependentRandomMessaging,
             positioning::{IndependentPositionFrames, PathwayMovement},
         },
@@ -75,6 +76,7 @@ fn main() {
                     gateway_priority: 0.0,
                 },
                 directed: false,
+                reaction_times: None,
             },
             seed,
         }
@@ -91,6 +93,7 @@ fn main() {
                     broadcast_chance: 0.3,
                     gateway_priority: 0.0,
                 },
+                reaction_times: None,
             },
             seed,
         }
@@ -109,6 +112,7 @@ fn main() {
                     broadcast_chance: 0.3,
                     gateway_priority: 0.0,
                 },
+                reaction_times: None,
             },
             seed,
         }
@@ -145,6 +149,7 @@ fn main() {
                 )
                 .into(),
                 emergency_time: None,
+                reaction_times: None,
             },
             seed,
         }
@@ -172,6 +177,8 @@ fn main() {
                     .into(),
                 gateway_count: 2,
                 gateways_move: false,
+                reaction_times: None,
+                sf_assignment: SfAssignment::default(),
             },
             seed,
         }
@@ -0,0 +1,40 @@
+use std::{fs::File, io::BufReader, path::PathBuf};
+
+use clap::Parser;
+use frogcore::sim_file::{self, import_deployment};
+
+/// Turns a Meshtastic deployment's node list into a scenario without messages
+#[derive(Parser, Debug)]
+#[command()]
+struct Args {
+    /// Deployment JSON with the nodes' names, roles, positions and modem presets
+    deployment: PathBuf,
+
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    /// Use JSON instead of rust messagepack
+    #[arg(long)]
+    json: bool,
+}
+
+fn main() {
+    let args = Args::parse();
+    let output_file = args.output.unwrap_or("scenario.sim".into());
+
+    let file = File::open(&args.deployment).unwrap();
+    let scenario = match import_deployment(BufReader::new(file)) {
+        Ok(x) => x,
+        Err(e) => {
+            eprintln!("<Error> {e}");
+            std::process::exit(1);
+        }
+    };
+
+    println!(
+        "Imported {} nodes, {} gateways",
+        scenario.settings.len(),
+        scenario.settings.iter().filter(|x| x.is_gateway).count()
+    );
+    sim_file::write_file(output_file, scenario, !args.json).unwrap();
+}
@@ -0,0 +1,238 @@
+//! Print a quick summary of a scenario, or of every scenario in a simpack.
+
+use std::{path::PathBuf, process::ExitCode};
+
+use clap::Parser;
+use frogcore::{
+    node_location::NodeLocation,
+    scenario::{MovementIndicator, Scenario, ScenarioIdentity, modem_preset::ModemPreset},
+    sim_file::load_file,
+};
+use serde_json::Value;
+
+#[derive(Parser, Debug)]
+#[command(version, about, long_about = None)]
+struct Args {
+    /// Scenario, scenario identity or simpack file
+    file: PathBuf,
+
+    /// Show a single simpack entry in full rather than the whole pack
+    #[arg(long)]
+    index: Option<usize>,
+}
+
+enum Loaded {
+    Scenario(Box<Scenario>),
+    Pack(Vec<ScenarioIdentity>),
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+
+    let loaded = match load(args.file) {
+        Ok(loaded) => loaded,
+        Err(e) => {
+            eprintln!("<Error> {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let scenario = match (loaded, args.index) {
+        (Loaded::Scenario(scenario), _) => *scenario,
+        (Loaded::Pack(pack), None) => {
+            print_pack(&pack);
+            return ExitCode::SUCCESS;
+        }
+        (Loaded::Pack(pack), Some(index)) => match pack.into_iter().nth(index) {
+            Some(ScenarioIdentity::Custom) => {
+                eprintln!("<Error> Cannot create a custom scenario from its identity");
+                return ExitCode::FAILURE;
+            }
+            Some(identity) => identity.create(),
+            None => {
+                eprintln!("<Error> No simpack entry at index {index}");
+                return ExitCode::FAILURE;
+            }
+        },
+    };
+
+    print_scenario(&scenario);
+
+    if let Err(e) = scenario.validate() {
+        eprintln!("<Warning> Invalid scenario: {e}");
+    }
+
+    ExitCode::SUCCESS
+}
+
+fn load(path: PathBuf) -> Result<Loaded, String> {
+    if let Ok(scenario) = load_file::<Scenario>(path.clone()) {
+        return Ok(Loaded::Scenario(Box::new(scenario)));
+    }
+
+    if let Ok(identity) = load_file::<ScenarioIdentity>(path.clone()) {
+        return match identity {
+            ScenarioIdentity::Custom => {
+                Err("Cannot create a custom scenario from its identity".into())
+            }
+            generated => Ok(Loaded::Scenario(Box::new(generated.create()))),
+        };
+    }
+
+    load_file::<Vec<ScenarioIdentity>>(path)
+        .map(Loaded::Pack)
+        .map_err(|e| e.to_string())
+}
+
+fn print_scenario(scenario: &Scenario) {
+    let identity = match &scenario.identity {
+        ScenarioIdentity::Custom => "Custom".to_owned(),
+        generated => serde_json::to_string(generated).unwrap(),
+    };
+
+    println!("Scenario:  {identity}");
+    println!("Hash:      {:016x}", scenario.content_hash());
+    println!(
+        "Nodes:     {} ({} gateways)",
+        scenario.map.len(),
+        scenario.gateway_count()
+    );
+    println!("Area:      {}", area(scenario));
+    println!("Movement:  {}", movement(scenario));
+    println!("Messages:  {}", messages(scenario));
+    println!("Radio:     {}", radio(scenario));
+
+    let model = serde_json::to_value(&scenario.model).unwrap();
+    let Value::Object(model) = model else {
+        println!("Transmission model: {model}");
+        return;
+    };
+
+    for (name, params) in model {
+        println!("Transmission model: {name}");
+        if let Value::Object(params) = params {
+            for (param, value) in params {
+                println!("  {param}: {value}");
+            }
+        }
+    }
+}
+
+fn area(scenario: &Scenario) -> String {
+    let Some(bounds) = scenario.bounding_box() else {
+        return "-".to_owned();
+    };
+
+    let layout = match scenario.map {
+        NodeLocation::Graph(_) => " (graph layout)",
+        NodeLocation::Points(_) => "",
+    };
+
+    format!(
+        "{:.1} m x {:.1} m{layout}",
+        bounds.width().metres(),
+        bounds.height().metres()
+    )
+}
+
+fn movement(scenario: &Scenario) -> String {
+    let kind = match &scenario.map {
+        NodeLocation::Graph(_) => "Graph".to_owned(),
+        NodeLocation::Points(points) if points.data.len() > 1 => {
+            format!("Moving, {} timepoints", points.data.len())
+        }
+        NodeLocation::Points(_) => "Static".to_owned(),
+    };
+
+    let count = |indicator| {
+        scenario
+            .settings
+            .iter()
+            .filter(|x| x.movement_indicator == indicator)
+            .count()
+    };
+    let mobile = count(MovementIndicator::Mobile);
+    let stationary = count(MovementIndicator::Stationary);
+
+    if mobile + stationary == 0 {
+        kind
+    } else {
+        format!("{kind} ({mobile} mobile, {stationary} stationary)")
+    }
+}
+
+/// Nodes on each modem preset, most common first, then any on custom settings.
+fn radio(scenario: &Scenario) -> String {
+    let mut counts: Vec<(Option<ModemPreset>, usize)> = Vec::new();
+    for preset in scenario.settings.iter().map(|x| x.preset()) {
+        match counts.iter_mut().find(|x| x.0 == preset) {
+            Some(count) => count.1 += 1,
+            None => counts.push((preset, 1)),
+        }
+    }
+    counts.sort_by_key(|&(preset, count)| (preset.is_none(), std::cmp::Reverse(count)));
+
+    counts
+        .into_iter()
+        .map(|(preset, count)| {
+            let name = preset.map_or("Custom".to_owned(), |x| x.to_string());
+            format!("{name} ({count})")
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn messages(scenario: &Scenario) -> String {
+    let generations: u32 = scenario.messages.iter().map(|x| x.num_generations).sum();
+
+    let Some((start, end)) = scenario.message_window() else {
+        return "0".to_owned();
+    };
+
+    format!(
+        "{} ({generations} generations) over {:.1} s, from {:.1} s to {:.1} s",
+        scenario.messages.len(),
+        (end - start).seconds(),
+        start.seconds(),
+        end.seconds()
+    )
+}
+
+fn print_pack(pack: &[ScenarioIdentity]) {
+    println!(
+        "{:>6} {:<22} {:>20} {:>6} {:>8} {:>8} {:>10} {:>13}",
+        "index", "generator", "seed", "nodes", "gateways", "messages", "window (s)", "area (m)"
+    );
+
+    for (index, identity) in pack.iter().enumerate() {
+        let ScenarioIdentity::Generated { generator, seed } = identity else {
+            println!("{index:>6} Custom");
+            continue;
+        };
+
+        let name = match serde_json::to_value(generator) {
+            Ok(Value::Object(generator)) => generator.keys().next().cloned(),
+            _ => None,
+        }
+        .unwrap_or_default();
+
+        let scenario = identity.create();
+        let window = scenario
+            .message_window()
+            .map(|(start, end)| (end - start).seconds())
+            .unwrap_or(0.0);
+        let area = scenario
+            .bounding_box()
+            .map(|x| format!("{:.0}x{:.0}", x.width().metres(), x.height().metres()))
+            .unwrap_or_else(|| "-".to_owned());
+
+        println!(
+            "{index:>6} {name:<22} {seed:>20} {:>6} {:>8} {:>8} {window:>10.1} {area:>13}",
+            scenario.map.len(),
+            scenario.gateway_count(),
+            scenario.messages.len(),
+        );
+    }
+
+    println!("{} scenarios", pack.len());
+}
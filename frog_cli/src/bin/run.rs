@@ -0,0 +1,321 @@
+//! Run a single scenario and write the results to a file.
+
+use std::{
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use clap::Parser;
+use frogcore::{
+    analysis::{CompleteAnalysis, DistanceBin},
+    node::parse_model,
+    scenario::{Scenario, ScenarioIdentity},
+    sim_file::{SimFileError, load_file, write_output},
+    simulation::{data_structs::LogContent, run_simulation_with, RunOptions},
+    verification::verify_all,
+};
+use rand::{Rng, rng};
+use serde::de::DeserializeOwned;
+
+#[derive(Parser, Debug)]
+#[command(version, about, long_about = None)]
+struct Args {
+    /// Scenario, scenario identity or simpack file
+    scenario: PathBuf,
+
+    #[arg(long, default_value = "meshtastic")]
+    model: String,
+
+    /// Seed for the rng. A random seed will be used if not specified
+    #[arg(long)]
+    seed: Option<u64>,
+
+    /// Results file. Written as JSON if the extension is `json` otherwise rust messagepack
+    #[arg(short, long, default_value = "sim_output.json")]
+    out: PathBuf,
+
+    /// Entry to use when `scenario` is a simpack
+    #[arg(long)]
+    index: Option<usize>,
+
+    #[arg(long)]
+    no_node_logs: bool,
+
+    /// Check the model keeps to the simulator's contracts, failing if it doesn't
+    #[arg(long)]
+    strict: bool,
+
+    /// Log notifications that are dropped without the model hearing of them
+    #[arg(long)]
+    audit_notifications: bool,
+
+    /// Record the scenario's content hash in the results,
+    /// so reruns can tell if the scenario has been generated differently since
+    #[arg(long)]
+    scenario_hash: bool,
+
+    /// Write the mean latency of received wanted messages binned by sender to target distance
+    /// to this CSV. Distances are in metres, or hops for graph scenarios
+    #[arg(long)]
+    distance_latency: Option<PathBuf>,
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+
+    let Ok(model) = parse_model(&args.model) else {
+        eprintln!("<Error> Unknown model {}", args.model);
+        return ExitCode::FAILURE;
+    };
+
+    let scenario = match load_scenario(args.scenario, args.index) {
+        Ok(scenario) => scenario,
+        Err(e) => {
+            eprintln!("<Error> {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    if let Err(e) = scenario.validate() {
+        eprintln!("<Error> Invalid scenario: {e}");
+        return ExitCode::FAILURE;
+    }
+
+    let random_seed = args.seed.unwrap_or_else(|| rng().random());
+    let options = RunOptions {
+        node_logs: !args.no_node_logs,
+        strict: args.strict,
+        audit_notifications: args.audit_notifications,
+        ..RunOptions::default()
+    };
+    let mut output = run_simulation_with(random_seed, scenario.clone(), model.into(), options);
+
+    if args.scenario_hash {
+        output.complete_identity.scenario_hash = Some(scenario.content_hash());
+    }
+
+    let use_rmp = args.out.extension().is_none_or(|x| x != "json");
+    if let Err(e) = write_output(args.out, output.clone(), use_rmp) {
+        eprintln!("<Error> {e}");
+        return ExitCode::FAILURE;
+    }
+
+    let violations: Vec<_> = output
+        .logs
+        .iter()
+        .filter(|x| matches!(x.content, LogContent::ContractViolation { .. }))
+        .map(|x| format!("{} at {}", x.content, x.time))
+        .collect();
+
+    let analysis = CompleteAnalysis::new(output, scenario);
+    let reception = &analysis.reception_analysis;
+
+    if let Some(path) = args.distance_latency
+        && let Err(e) = write_distance_latency(&path, &analysis.distance_latency.bins)
+    {
+        eprintln!("<Error> Could not write {}: {e}", path.display());
+        return ExitCode::FAILURE;
+    }
+
+    println!("{model:?} with random seed {random_seed}");
+    println!(
+        "Reception Rate: avg({:.4})  min({:.4})  max({:.4})",
+        reception.average_reception_rate,
+        reception.min_reception_rate,
+        reception.max_reception_rate
+    );
+    println!(
+        "Average Latency: avg({:.4})  min({:.4})  max({:.4})",
+        reception.avg_avg_latency, reception.min_avg_latency, reception.max_avg_latency
+    );
+    println!(
+        "Total Transmissions: {}  Total Airtime: {:.4} (Simulation End Time: {:.4})",
+        analysis.transmissions.len(),
+        analysis.total_airtime,
+        analysis.end_time,
+    );
+
+    if !verify_all(&analysis) {
+        eprintln!("<Error> Verification failed");
+        return ExitCode::FAILURE;
+    }
+
+    if !violations.is_empty() {
+        for violation in violations {
+            eprintln!("<Error> {violation}");
+        }
+        return ExitCode::FAILURE;
+    }
+
+    ExitCode::SUCCESS
+}
+
+fn write_distance_latency(path: &Path, bins: &[DistanceBin]) -> Result<(), csv::Error> {
+    let mut writer = csv::Writer::from_path(path)?;
+    for bin in bins {
+        writer.serialize(bin)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Loads a scenario, a scenario identity, or entry `index` of a simpack.
+/// If the file is none of them, the error comes from the parser for the kind of file
+/// it looks like, as that is the one that says what is wrong with it.
+fn load_scenario(path: PathBuf, index: Option<usize>) -> Result<Scenario, String> {
+    let scenario_error = match load_file::<Scenario>(path.clone()) {
+        Ok(scenario) => return Ok(scenario),
+        Err(SimFileError::IOError(e)) => {
+            return Err(format!("Could not read {}: {e}", path.display()));
+        }
+        Err(e) => e.to_string(),
+    };
+
+    if let Ok(identity) = load_file::<ScenarioIdentity>(path.clone()) {
+        return create_identity(identity);
+    }
+
+    if let Ok(pack) = load_file::<Vec<ScenarioIdentity>>(path.clone()) {
+        return pack_entry(pack, index);
+    }
+
+    // `load_file` falls back to message pack when JSON doesn't parse, so its errors
+    // for a broken JSON file are about message pack. Parsing again gives the JSON error.
+    let bytes = std::fs::read(&path).unwrap_or_default();
+    Err(match json_shape(&bytes) {
+        Some(FileShape::Scenario) => {
+            format!("Invalid scenario file: {}", json_error::<Scenario>(&bytes))
+        }
+        Some(FileShape::Identity) => format!(
+            "Invalid scenario identity file: {}",
+            json_error::<ScenarioIdentity>(&bytes)
+        ),
+        Some(FileShape::Pack) => format!(
+            "Invalid simpack: {}",
+            json_error::<Vec<ScenarioIdentity>>(&bytes)
+        ),
+        None => format!("Invalid scenario file: {scenario_error}"),
+    })
+}
+
+#[derive(Debug, PartialEq)]
+enum FileShape {
+    Scenario,
+    Identity,
+    Pack,
+}
+
+/// Which kind of file `bytes` are meant to be, going by the start of their JSON.
+/// `None` if they aren't JSON.
+fn json_shape(bytes: &[u8]) -> Option<FileShape> {
+    match bytes.iter().find(|x| !x.is_ascii_whitespace())? {
+        b'[' => Some(FileShape::Pack),
+        b'"' => Some(FileShape::Identity),
+        b'{' => match serde_json::from_slice::<serde_json::Map<_, _>>(bytes) {
+            Ok(object) if object.len() == 1 && object.contains_key("Generated") => {
+                Some(FileShape::Identity)
+            }
+            _ => Some(FileShape::Scenario),
+        },
+        _ => None,
+    }
+}
+
+fn json_error<T: DeserializeOwned>(bytes: &[u8]) -> String {
+    serde_json::from_slice::<T>(bytes)
+        .err()
+        .map_or_else(String::new, |e| e.to_string())
+}
+
+fn pack_entry(pack: Vec<ScenarioIdentity>, index: Option<usize>) -> Result<Scenario, String> {
+    let Some(index) = index else {
+        return Err(format!(
+            "File is a simpack with {} entries, use --index to pick one",
+            pack.len()
+        ));
+    };
+
+    let identity = pack
+        .into_iter()
+        .nth(index)
+        .ok_or_else(|| format!("No simpack entry at index {index}"))?;
+
+    create_identity(identity)
+}
+
+fn create_identity(identity: ScenarioIdentity) -> Result<Scenario, String> {
+    match identity {
+        ScenarioIdentity::Custom => Err("Cannot create a custom scenario from its identity".into()),
+        generated => Ok(generated.create()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use frogcore::{sim_file::write_file, units::MINS};
+
+    fn identity(seed: u64) -> ScenarioIdentity {
+        ScenarioIdentity::for_test(5, 5, 1.0 * MINS, seed)
+    }
+
+    #[test]
+    fn scenarios_identities_and_packs_load() {
+        let dir = std::env::temp_dir().join(format!("frog_run_load_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        // Loaded scenarios keep the identity they were generated from
+        let path = dir.join("scenario.json");
+        write_file(path.clone(), identity(1).create(), false).unwrap();
+        assert_eq!(load_scenario(path, None).unwrap().identity, identity(1));
+
+        let path = dir.join("scenario.sim");
+        write_file(path.clone(), identity(1).create(), true).unwrap();
+        assert_eq!(load_scenario(path, None).unwrap().identity, identity(1));
+
+        let path = dir.join("identity.json");
+        write_file(path.clone(), identity(2), false).unwrap();
+        assert_eq!(load_scenario(path, None).unwrap().identity, identity(2));
+
+        let path = dir.join("pack.json");
+        write_file(path.clone(), vec![identity(3), identity(4)], false).unwrap();
+        assert_eq!(load_scenario(path.clone(), Some(1)).unwrap().identity, identity(4));
+
+        let error = load_scenario(path.clone(), None).unwrap_err();
+        assert!(error.contains("2 entries"), "{error}");
+        let error = load_scenario(path, Some(2)).unwrap_err();
+        assert_eq!(error, "No simpack entry at index 2");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn load_errors_come_from_the_matching_parser() {
+        let dir = std::env::temp_dir().join(format!("frog_run_errors_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let load = |name: &str, contents: &str| {
+            let path = dir.join(name);
+            std::fs::write(&path, contents).unwrap();
+            load_scenario(path, Some(0)).unwrap_err()
+        };
+
+        let mut scenario = serde_json::to_value(identity(1).create()).unwrap();
+        scenario.as_object_mut().unwrap().remove("messages");
+        let error = load("scenario.json", &scenario.to_string());
+        assert!(error.starts_with("Invalid scenario file"), "{error}");
+        assert!(error.contains("messages"), "{error}");
+
+        let error = load("identity.json", r#"{"Generated": {"seed": 1}}"#);
+        assert!(error.starts_with("Invalid scenario identity file"), "{error}");
+
+        let error = load("pack.json", r#"[{"Generated": {"seed": 1}}]"#);
+        assert!(error.starts_with("Invalid simpack"), "{error}");
+
+        let error = load("custom.json", r#""Custom""#);
+        assert!(error.starts_with("Cannot create a custom scenario"), "{error}");
+
+        let error = load_scenario(dir.join("missing.json"), None).unwrap_err();
+        assert!(error.starts_with("Could not read"), "{error}");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
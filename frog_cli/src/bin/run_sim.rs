@@ -11,9 +11,9 @@ use std::{
 use clap::Parser;
 use frogcore::{
     node::{parse_model, ModelSelection, MODEL_LIST},
-    scenario::ScenarioIdentity,
+    scenario::{Scenario, ScenarioIdentity},
     sim_file::{self, load_file},
-    simulation::run_simulation,
+    simulation::{run_simulation_with, RunOptions},
 };
 use rand::{rng, Rng};
 use rayon::prelude::*;
@@ -50,6 +50,11 @@ struct Args {
 
     #[arg(long)]
     json: bool,
+
+    /// Keep every packet each node sent and received in the output.
+    /// Makes outputs much larger
+    #[arg(long)]
+    trace: bool,
 }
 
 fn main() -> ExitCode {
@@ -73,6 +78,11 @@ fn main() -> ExitCode {
     });
 
     let quiet = args.quiet;
+    let options = RunOptions {
+        node_logs: true,
+        traces: args.trace,
+        ..RunOptions::default()
+    };
 
     let model_list = if args.all_models {
         MODEL_LIST.to_vec()
@@ -92,7 +102,7 @@ fn main() -> ExitCode {
             let sim_file = sim_file::load_file(input_path.clone())
                 .unwrap_or_else(|_| load_file::<ScenarioIdentity>(input_path.clone()).unwrap().create());
 
-            let output = run_simulation(random_seed, sim_file.clone(), model.into(), true);
+            let output = run_simulation_with(random_seed, sim_file, model.into(), options);
 
             let final_path = match (sim_count == 1, output_path.is_dir()) {
                 (true, true) => output_path.join(format!("{model:?}.sim")),
@@ -134,7 +144,7 @@ fn main() -> ExitCode {
                 }
             };
 
-            let sim_file = match sim_file::load_file(file.path()) {
+            let sim_file = match sim_file::load_file::<Scenario>(file.path()) {
                 Ok(loaded) => loaded,
                 Err(e) => {
                     eprintln!("<Warning> {e}");
@@ -151,7 +161,7 @@ fn main() -> ExitCode {
             }
 
             count.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
-            let output = run_simulation(random_seed, sim_file, model.into(), true);
+            let output = run_simulation_with(random_seed, sim_file, model.into(), options);
 
             let out_name = format!("output_{model:?}_{file_name}");
             let mut out = output_path.clone();
@@ -1,20 +1,37 @@
 use std::{
-    fs::{read_dir, File},
-    io::{self, Write},
+    collections::{HashMap, HashSet},
+    fs::{create_dir_all, File, OpenOptions},
+    io,
     path::PathBuf,
+    process::ExitCode,
+    sync::{Arc, Mutex},
+    time::Duration,
 };
 
 use clap::{arg, command, Parser};
+use frog_cli::{
+    errors::{ErrorLog, RunError},
+    filter::{sample_pack, PackFilter},
+    per_node::write_per_node,
+    progress::Progress,
+    report,
+    table::{
+        identity_string, load_result_files, make_table_entry, make_table_entry_with_scenario,
+        write_rows, CsvTableEntry, OutputFormat, TableEntry, TableRow,
+    },
+};
 use frogcore::{
-    analysis::{CompleteAnalysis, EmergencyResult},
-    node::{parse_model, MODEL_LIST},
-    scenario::{ScenarioIdentity, Scenario},
-    sim_file::{load_file, load_output, SimOutput},
-    simulation::run_simulation,
-    units::{Unit, SECONDS},
+    analysis::RunStatistic,
+    node::{parse_model, ModelSelection, MODEL_LIST},
+    scenario::{Scenario, ScenarioIdentity},
+    sim_file::{load_file, SimOutput},
+    simulation::{run_batch_map, RunLimits, RunOptions, SimJob},
+    units::Time,
+    utility::fnv1a,
+    verification::EU868_DUTY_CYCLE,
 };
-use rayon::iter::{IndexedParallelIterator, IntoParallelIterator, ParallelIterator};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
 
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
@@ -29,6 +46,40 @@ struct Args {
     #[arg(long)]
     models: Option<Vec<String>>,
 
+    /// Only run pack scenarios matching all of a comma separated list of predicates.
+    ///
+    /// A generator name such as `PathwaysOne` keeps scenarios from that generator.
+    /// `<field> <op> <value>` compares a generator field, where op is one of
+    /// `=`, `!=`, `<`, `<=`, `>` or `>=`.
+    /// Fields are found by name at any depth, so `message_count` matches `messaging.message_count`,
+    /// while dotted paths like `positioning.side_len` match exactly. `seed` is the scenario seed.
+    /// Quantities are compared in base units (metres, seconds, metres per second).
+    ///
+    /// Example: --filter "PathwaysOne, people_count > 30"
+    #[arg(long, verbatim_doc_comment)]
+    filter: Option<PackFilter>,
+
+    /// Run a random subset of this many pack scenarios, taken after `--filter`
+    #[arg(long)]
+    sample: Option<usize>,
+
+    /// Seed used to pick the `--sample` subset
+    #[arg(long, default_value_t = 0)]
+    sample_seed: u64,
+
+    /// Run each scenario in the pack this many times with different seeds.
+    /// When more than one, rows are aggregated into mean and standard deviation columns.
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u64).range(1..))]
+    repeat: u64,
+
+    /// Seed for the first repeat. Repeat `n` uses `base_seed + n`.
+    #[arg(long, default_value_t = 123456)]
+    base_seed: u64,
+
+    /// Also write the individual rows behind aggregated results to `<model>_runs.csv`.
+    #[arg(long)]
+    emit_runs: bool,
+
     #[arg(long)]
     range_start: Option<usize>,
 
@@ -38,6 +89,28 @@ struct Args {
     #[arg(long)]
     no_verify: bool,
 
+    /// Add a `max_duty_cycle` column with the highest proportion of any
+    /// one hour window a single node spent transmitting, along with the node and window start.
+    /// Runs going over the limit fail verification.
+    #[arg(long)]
+    duty_cycle: bool,
+
+    /// Duty cycle limit as a proportion, 0.01 (EU868) by default. Implies `--duty-cycle`.
+    #[arg(long)]
+    duty_cycle_limit: Option<f64>,
+
+    /// Stop any pack run that takes longer than this in wall-clock time, such as "90s" or
+    /// "5 min". Its row is kept with `timed_out` set and NaN metrics.
+    /// Without this runs are stopped after 30 minutes.
+    #[arg(long)]
+    per_run_timeout: Option<Time>,
+
+    /// Directory to record finished pack runs in.
+    /// Rerunning with the same directory skips runs that already finished.
+    /// The rerun must use the same pack, `--filter`, `--sample`, `--sample-seed` and seeds.
+    #[arg(long)]
+    checkpoint: Option<PathBuf>,
+
     /// Results file or directory containing results files
     #[arg(short, long)]
     results: Option<PathBuf>,
@@ -45,19 +118,65 @@ struct Args {
     #[arg(short, long)]
     output: Option<PathBuf>,
 
+    /// Also write a CSV of per node metrics for each run into this directory.
+    ///
+    /// Each file is named by the 64 bit FNV-1a hash of the run's scenario identity, model,
+    /// seed and simulation version serialised as JSON, written as 16 hex digits.
+    /// So the same run always writes to the same file, e.g. `0123456789abcdef.csv`.
+    #[arg(long, verbatim_doc_comment)]
+    per_node_output: Option<PathBuf>,
+
+    /// Format of the summary tables. JSON formats keep the scenario identity as an object
+    /// rather than a string
+    #[arg(long, value_enum, default_value_t = OutputFormat::Csv)]
+    output_format: OutputFormat,
+
     #[arg(short, long)]
     verbose: bool,
+
+    /// Don't show progress while running a pack
+    #[arg(short, long)]
+    quiet: bool,
+
+    /// Write every load and verification error to this file as a JSON list
+    #[arg(long)]
+    error_report: Option<PathBuf>,
+}
+
+/// Exits with 0 on success, 2 if any run failed verification, 3 if any file failed to load
+/// and 4 if both happened.
+fn main() -> ExitCode {
+    ExitCode::from(summarise(Args::parse()))
 }
 
-fn main() {
-    let args = Args::parse();
+/// Returns the exit code.
+fn summarise(args: Args) -> u8 {
+    let errors = ErrorLog::new();
 
     let maybe_pack_path = args.pack;
     let no_verify = args.no_verify;
+    let duty_cycle = args
+        .duty_cycle_limit
+        .or(args.duty_cycle.then_some(EU868_DUTY_CYCLE));
 
     let results_path = args.results.unwrap_or("sim_output.json".into());
     let verbose = args.verbose;
 
+    let mut per_node_output = args.per_node_output;
+    if let Some(dir) = &per_node_output
+        && let Err(e) = create_dir_all(dir)
+    {
+        errors.push(RunError::load(dir, e));
+        per_node_output = None;
+    }
+    let per_node = |results: &SimOutput, scenario: &Scenario| {
+        if let Some(dir) = &per_node_output
+            && let Err(e) = write_per_node(dir, results, scenario)
+        {
+            errors.push(RunError::load(dir, e));
+        }
+    };
+
     let model_list = match (args.range_start, args.range_end) {
         (None, None) => args
             .models
@@ -73,284 +192,943 @@ fn main() {
     };
 
     if let Some(pack_path) = maybe_pack_path {
-        let mut model_tables: Vec<(_, Vec<TableEntry>)> = model_list
-            .into_iter()
-            .map(|model| (model, Vec::new()))
+        let mut scenarios = match load_file::<Vec<ScenarioIdentity>>(pack_path.clone()) {
+            Ok(scenarios) => scenarios,
+            Err(e) => {
+                errors.push(RunError::load(pack_path, e));
+                return finish(&errors, args.error_report);
+            }
+        };
+        let pack_hash = fnv1a(&serde_json::to_vec(&scenarios).unwrap());
+
+        if let Some(filter) = &args.filter {
+            scenarios = filter.apply(scenarios);
+        }
+
+        if let Some(count) = args.sample {
+            scenarios = sample_pack(scenarios, count, args.sample_seed);
+        }
+        let seeds: Vec<u64> = (0..args.repeat)
+            .map(|n| args.base_seed.wrapping_add(n))
             .collect();
 
-        let scenarios = load_file::<Vec<ScenarioIdentity>>(pack_path).unwrap();
-
-        for (model, inner_table) in model_tables.iter_mut() {
-            scenarios
-                .clone()
-                .into_par_iter()
-                .map(|x| {
-                    let results = run_simulation(123456, x.create(), (*model).into(), false);
-                    make_table_entry(no_verify, verbose, results)
-                })
-                .collect_into_vec(inner_table);
-
-            eprintln!("Finished {model:?}");
-            let out_path = args
-                .output
-                .as_ref()
-                .map(|x| x.join(format!("{model:?}.csv")));
-            write_table(out_path, inner_table);
+        let limits = pack_limits(args.per_run_timeout);
+        let mut timed_out = 0;
+
+        let source = CheckpointSource {
+            pack_hash,
+            filter: args.filter.clone(),
+            sample: args.sample,
+            sample_seed: args.sample_seed,
+            seeds: seeds.clone(),
+        };
+        let checkpoint = match args.checkpoint {
+            Some(dir) => match Checkpoint::open(dir.clone(), &source) {
+                Ok(checkpoint) => Some(checkpoint),
+                Err(e) => {
+                    errors.push(RunError::load(dir, e));
+                    return finish(&errors, args.error_report);
+                }
+            },
+            None => None,
+        };
+
+        for model in model_list {
+            let progress = Progress::new(
+                format!("{model:?}"),
+                scenarios.len() * seeds.len(),
+                args.quiet,
+            );
+            let truncated = Mutex::new(Vec::new());
+            let entry = |scenario: &_, results: SimOutput| {
+                let identity = &results.complete_identity;
+                if let Some(reason) = identity.truncated {
+                    truncated.lock().unwrap().push(format!(
+                        "{} seed {}: {reason}",
+                        identity_string(&identity.scenario_identity),
+                        identity.simulation_seed
+                    ));
+                }
+
+                per_node(&results, scenario);
+                make_table_entry_with_scenario(
+                    no_verify, verbose, duty_cycle, results, scenario, &errors,
+                )
+            };
+
+            let runs = match &checkpoint {
+                Some(checkpoint) => run_pack_checkpointed(
+                    &scenarios,
+                    &seeds,
+                    model,
+                    limits,
+                    checkpoint,
+                    &progress,
+                    entry,
+                )
+                .expect("Failed to write checkpoint"),
+                None => run_pack(&scenarios, &seeds, model, limits, &progress, entry),
+            };
+
+            progress.finish();
+            timed_out += runs.iter().filter(|x| x.timed_out).count();
+            report!("Finished {model:?}");
+            for run in truncated.into_inner().unwrap() {
+                report!("Truncated {run}");
+            }
+            let format = args.output_format;
+            let out_path = |name: &str| {
+                args.output
+                    .as_ref()
+                    .map(|x| x.join(format!("{name}.{}", format.extension())))
+            };
+
+            if seeds.len() == 1 {
+                write_rows(out_path(&format!("{model:?}")), &runs, format);
+            } else {
+                write_rows(
+                    out_path(&format!("{model:?}")),
+                    &aggregate_runs(&runs, seeds.len()),
+                    format,
+                );
+
+                if args.emit_runs {
+                    write_rows(out_path(&format!("{model:?}_runs")), &runs, format);
+                }
+            }
+        }
+
+        if timed_out > 0 || args.per_run_timeout.is_some() {
+            report!("{timed_out} runs timed out");
         }
     } else {
         let mut table = Vec::new();
-        let simulations = load_result_files(results_path);
+        let simulations = load_result_files(results_path, &errors);
 
-        simulations
-            .into_iter()
-            .for_each(|x| table.push(make_table_entry(no_verify, verbose, x)));
+        simulations.into_iter().for_each(|x| {
+            let identity = &x.complete_identity;
+            if identity.scenario_identity == ScenarioIdentity::Custom {
+                errors.push(RunError::unusable(
+                    ScenarioIdentity::Custom,
+                    format!(
+                        "Skipped {} seed {} as its custom scenario can't be regenerated",
+                        identity.model_id, identity.simulation_seed
+                    ),
+                ));
+                return;
+            }
+
+            if per_node_output.is_some() {
+                per_node(&x, &identity.scenario_identity.create());
+            }
+            table.push(make_table_entry(no_verify, verbose, duty_cycle, x, &errors))
+        });
 
-        write_table(args.output, &table);
+        write_rows(args.output, &table, args.output_format);
     };
+
+    finish(&errors, args.error_report)
 }
 
-fn write_table(maybe_path: Option<PathBuf>, table: &Vec<TableEntry>) {
-    let write = if let Some(out_path) = maybe_path {
-        let file = File::create(out_path).unwrap();
-        Box::new(file) as Box<dyn Write>
-    } else {
-        Box::new(io::stdout())
-    };
+/// Writes the error report if asked for and returns the exit code.
+fn finish(errors: &ErrorLog, error_report: Option<PathBuf>) -> u8 {
+    if let Some(path) = error_report
+        && let Err(e) = errors.write_report(&path)
+    {
+        eprintln!("<Error> Failed to write error report: {e}");
+    }
 
-    let mut writer = csv::Writer::from_writer(write);
-    table.into_iter().for_each(|x| {
-        writer.serialize(x).unwrap();
-    });
-    writer.flush().unwrap();
+    errors.exit_code()
 }
 
-fn make_table_entry(no_verify: bool, verbose: bool, results: SimOutput) -> TableEntry {
-    let frogcore::sim_file::OutputIdentity {
-        scenario_identity: scenario,
-        model_id,
-        simulation_seed: random_seed,
-        sim_version,
-    } = &results.complete_identity;
+/// Runs every scenario with every seed, spreading the (scenario, seed) pairs across threads.
+/// Output is ordered by scenario then seed.
+fn run_pack<F>(
+    scenarios: &[ScenarioIdentity],
+    seeds: &[u64],
+    model: ModelSelection,
+    limits: RunLimits,
+    progress: &Progress,
+    entry: F,
+) -> Vec<TableEntry>
+where
+    F: Fn(&Scenario, SimOutput) -> TableEntry + Sync,
+{
+    let jobs: Vec<_> = scenarios
+        .iter()
+        .flat_map(|x| pack_jobs(x, seeds, model, limits))
+        .collect();
 
-    let scenario_file = scenario.create();
+    run_batch_map(jobs, None, |_| progress.inc(), |_, scenario, output| {
+        entry(scenario, output)
+    })
+}
 
-    let analysis = CompleteAnalysis::new(results.clone(), scenario_file.clone());
+/// Like [`run_pack`] but records each finished run in `checkpoint` and skips runs it already has.
+/// Output is ordered by scenario then seed, the same as [`run_pack`].
+fn run_pack_checkpointed<F>(
+    scenarios: &[ScenarioIdentity],
+    seeds: &[u64],
+    model: ModelSelection,
+    limits: RunLimits,
+    checkpoint: &Checkpoint,
+    progress: &Progress,
+    entry: F,
+) -> io::Result<Vec<TableEntry>>
+where
+    F: Fn(&Scenario, SimOutput) -> TableEntry + Sync,
+{
+    let model_name = format!("{model:?}");
+    let completed = checkpoint.completed(&model_name);
+    let recorder = checkpoint.recorder(&model_name, &completed)?;
+    progress.skip(completed.len());
 
-    let first_message = scenario_file
-        .messages
+    let (indices, jobs): (Vec<usize>, Vec<SimJob>) = scenarios
         .iter()
-        .map(|x| x.generate_time)
-        .min_by(|x, y| x.partial_cmp(&y).unwrap());
+        .enumerate()
+        .flat_map(|(index, x)| {
+            let pending: Vec<u64> = seeds
+                .iter()
+                .copied()
+                .filter(|seed| !completed.contains_key(&(index, *seed)))
+                .collect();
+            let jobs = pack_jobs(x, &pending, model, limits);
+            jobs.into_iter().map(move |job| (index, job))
+        })
+        .unzip();
 
-    let last_message = scenario_file
-        .messages
+    let finished = run_batch_map(
+        jobs,
+        None,
+        |_| progress.inc(),
+        |job, scenario, output| {
+            let entry = entry(scenario, output);
+            recorder
+                .record(indices[job], &entry)
+                .expect("Failed to write checkpoint");
+            (indices[job], entry)
+        },
+    );
+
+    let seed_position = |seed| seeds.iter().position(|x| *x == seed);
+
+    let mut rows: Vec<(usize, TableEntry)> = completed
+        .into_iter()
+        .map(|((index, _), entry)| (index, entry))
+        .chain(finished)
+        .collect();
+    rows.sort_by_key(|(index, entry)| (*index, seed_position(entry.seed)));
+
+    Ok(rows.into_iter().map(|(_, entry)| entry).collect())
+}
+
+/// Far more than any pack scenario should need, so only runaway models are cut short.
+const PACK_LIMITS: RunLimits = RunLimits {
+    max_events: Some(100_000_000),
+    max_wall_time: Some(Duration::from_secs(30 * 60)),
+};
+
+/// [`PACK_LIMITS`] with the wall-clock limit replaced by `per_run_timeout` if there is one.
+fn pack_limits(per_run_timeout: Option<Time>) -> RunLimits {
+    let timeout = per_run_timeout.map(|x| Duration::from_secs_f64(x.seconds().max(0.0)));
+    RunLimits {
+        max_wall_time: timeout.or(PACK_LIMITS.max_wall_time),
+        ..PACK_LIMITS
+    }
+}
+
+/// A job for each of `seeds` running `scenario`.
+/// The scenario is only generated once and shared between the jobs.
+fn pack_jobs(
+    scenario: &ScenarioIdentity,
+    seeds: &[u64],
+    model: ModelSelection,
+    limits: RunLimits,
+) -> Vec<SimJob> {
+    if seeds.is_empty() {
+        return Vec::new();
+    }
+
+    let scenario = Arc::new(scenario.create());
+    seeds
         .iter()
-        .map(|x| x.generate_time)
-        .max_by(|x, y| x.partial_cmp(&y).unwrap());
-
-    let messaging_time =
-        last_message.unwrap_or(0.0 * SECONDS) - first_message.unwrap_or(0.0 * SECONDS);
-    let messaging_time = messaging_time.seconds();
-
-    let pathloss_param = {
-        use frogcore::simulation::models::PairWiseCaptureEffect;
-        use frogcore::simulation::models::PathlossModel::*;
-        use frogcore::simulation::models::TransmissionModel::*;
-
-        let path_loss = match scenario_file.model {
-            PairWiseNormal(PairWiseCaptureEffect { ref path_loss, .. })
-            | PairWiseNone(PairWiseCaptureEffect { ref path_loss, .. })
-            | PairWiseUniform(PairWiseCaptureEffect { ref path_loss, .. }) => path_loss,
-        };
+        .map(|&seed| SimJob {
+            scenario: scenario.clone(),
+            model: model.into(),
+            seed,
+            options: RunOptions {
+                limits,
+                ..RunOptions::default()
+            },
+        })
+        .collect()
+}
+
+/// Finished pack runs kept on disk so an interrupted run can be resumed.
+///
+/// Each finished row is appended to `<model>.partial.csv`, prefixed with its scenario index,
+/// before `(model, scenario index, seed)` is appended to `manifest.csv`.
+/// Only rows listed in the manifest count as finished.
+/// What the runs were made from is kept in `source.json`, see [`CheckpointSource`].
+struct Checkpoint {
+    dir: PathBuf,
+    manifest: HashSet<(String, usize, u64)>,
+}
+
+/// The pack and settings a checkpoint's runs were made with.
+/// Scenario indices and seeds in the manifest only mean the same thing when all of these match.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct CheckpointSource {
+    /// Hash of the pack before it is filtered or sampled
+    pack_hash: u64,
+    filter: Option<PackFilter>,
+    sample: Option<usize>,
+    sample_seed: u64,
+    seeds: Vec<u64>,
+}
+
+impl CheckpointSource {
+    /// Name of the first setting that differs from `other`.
+    fn difference(&self, other: &CheckpointSource) -> Option<&'static str> {
+        [
+            ("pack", self.pack_hash == other.pack_hash),
+            ("--filter", self.filter == other.filter),
+            ("--sample", self.sample == other.sample),
+            ("--sample-seed", self.sample_seed == other.sample_seed),
+            ("seeds", self.seeds == other.seeds),
+        ]
+        .into_iter()
+        .find(|(_, same)| !same)
+        .map(|(name, _)| name)
+    }
+}
+
+#[derive(Debug, Error)]
+enum CheckpointError {
+    #[error(transparent)]
+    IOError(#[from] io::Error),
+    #[error(transparent)]
+    CsvError(#[from] csv::Error),
+    #[error("Checkpoint was made with a different {0}, so can't be resumed by this run")]
+    Mismatch(&'static str),
+}
+
+impl Checkpoint {
+    /// Opens the checkpoint in `dir`, creating it if needed.
+    /// Fails if it was started from a different `source`.
+    fn open(dir: PathBuf, source: &CheckpointSource) -> Result<Checkpoint, CheckpointError> {
+        create_dir_all(&dir)?;
 
-        match path_loss {
-            NoPathloss(_) => "None".to_owned(),
-            AdjustedFreeSpacePathLoss(free_space_path_loss, ..) => {
-                format!("Log {:.6}", free_space_path_loss.distance_exponent)
+        let source_path = dir.join("source.json");
+        match File::open(&source_path) {
+            Ok(file) => {
+                let existing: CheckpointSource =
+                    serde_json::from_reader(io::BufReader::new(file)).map_err(io::Error::from)?;
+                if let Some(name) = existing.difference(source) {
+                    return Err(CheckpointError::Mismatch(name));
+                }
             }
-            LinearPathLoss(linear_path_loss) => {
-                format!("Linear {:.6}", linear_path_loss.loss_rate.inner())
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                serde_json::to_writer_pretty(File::create(&source_path)?, source)
+                    .map_err(io::Error::from)?;
             }
+            Err(e) => return Err(e.into()),
         }
-    };
 
-    let entry = TableEntry {
-        scenario_identity: serde_json::to_string(scenario).unwrap(),
-        model_identity: model_id.clone(),
-        sim_version: sim_version.clone(),
-        seed: *random_seed,
-        avg_reception: analysis.reception_analysis.average_reception_rate,
-        min_reception: analysis.reception_analysis.min_reception_rate,
-        max_reception: analysis.reception_analysis.max_reception_rate,
-        total_transmissions: analysis.transmissions.len(),
-        total_airtime: analysis.total_airtime,
-        end_time: analysis.end_time,
-        avg_avg_latency: analysis.reception_analysis.avg_avg_latency.seconds(),
-        min_avg_latency: analysis.reception_analysis.min_avg_latency.seconds(),
-        max_avg_latency: analysis.reception_analysis.max_avg_latency.seconds(),
-        generated_messages: scenario_file.messages.len(),
-        messaging_time,
-        pathloss_param,
-        l120_score: analysis.reception_analysis.l120_score.seconds(),
-        l600_score: analysis.reception_analysis.l600_score.seconds(),
-        l6000_score: analysis.reception_analysis.l6000_score.seconds(),
-        all_packet_uniqueness: analysis.reception_analysis.all_packet_uniqueness,
-        message_packet_uniqueness: analysis.reception_analysis.message_packet_uniqueness,
-        phantom_uniqueness: analysis.reception_analysis.phantom_uniqueness,
-        message_reception_directness: analysis.reception_analysis.message_reception_directness,
-        reception_directness: analysis.reception_analysis.reception_directness,
-        message_reception_unique_directness: analysis
-            .reception_analysis
-            .message_reception_unique_directness,
-        reception_unique_directness: analysis.reception_analysis.reception_unique_directness,
-        message_transmission_directness: analysis
-            .reception_analysis
-            .message_transmission_directness,
-        transmission_directness: analysis.reception_analysis.transmission_directness,
-        message_transmission_unique_directness: analysis
-            .reception_analysis
-            .message_transmission_unique_directness,
-        transmission_unique_directness: analysis.reception_analysis.transmission_unique_directness,
-        emergency_result: analysis.reception_analysis.emergency_result,
-        transmission_sent_events: analysis.transmission_sent_events,
-        transmission_received_events: analysis.transmission_received_events,
-        transmission_blocked_events: analysis.transmission_blocked_events,
-        global_latency: analysis.reception_analysis.global_latency.seconds(),
-        global_reception_rate: analysis.reception_analysis.global_reception_rate,
-        t120_reception: analysis.reception_analysis.t120_reception,
-        t600_reception: analysis.reception_analysis.t600_reception,
-        t1800_reception: analysis.reception_analysis.t1800_reception,
-        t6000_reception: analysis.reception_analysis.t6000_reception,
-        gateway_latency: analysis.reception_analysis.gateway_latency.seconds(),
-        gateway_reception: analysis.reception_analysis.gateway_reception,
-    };
+        let manifest_path = dir.join("manifest.csv");
+        let manifest: HashSet<(String, usize, u64)> = match File::open(&manifest_path) {
+            Ok(file) => csv::ReaderBuilder::new()
+                .has_headers(false)
+                .from_reader(file)
+                .deserialize()
+                .map_while(Result::ok)
+                .collect(),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => HashSet::new(),
+            Err(e) => return Err(e.into()),
+        };
+
+        // Rewrite the manifest in case the last line was cut off
+        let mut writer = csv::WriterBuilder::new()
+            .has_headers(false)
+            .from_path(&manifest_path)?;
+        for line in manifest.iter() {
+            writer.serialize(line)?;
+        }
+        writer.flush()?;
 
-    if verbose {
-        printout(scenario_file.clone(), results);
+        Ok(Checkpoint { dir, manifest })
     }
 
-    if !no_verify && !frogcore::verification::verify_all(&analysis) {
-        eprintln!(
-            "<Error> Verification failed for {:#?}",
-            analysis.complete_identity
-        );
+    fn partial_path(&self, model: &str) -> PathBuf {
+        self.dir.join(format!("{model}.partial.csv"))
     }
 
-    entry
-}
+    /// Finished rows for a model keyed by scenario index and seed.
+    fn completed(&self, model: &str) -> HashMap<(usize, u64), TableEntry> {
+        let Ok(file) = File::open(self.partial_path(model)) else {
+            return HashMap::new();
+        };
 
-fn load_result_files(results_path: PathBuf) -> Vec<SimOutput> {
-    let mut sim_results: Vec<SimOutput> = Vec::new();
+        let mut completed = HashMap::new();
+        let mut reader = csv::ReaderBuilder::new()
+            .has_headers(false)
+            .flexible(true)
+            .from_reader(file);
 
-    if results_path.is_file() {
-        match load_output(results_path) {
-            Ok(loaded) => sim_results.push(loaded),
-            Err(e) => {
-                eprintln!("<Error> {e}");
-            }
-        }
-    } else {
-        for thing in read_dir(results_path).unwrap() {
-            let file = match thing {
-                Ok(file) => file,
-                Err(e) => {
-                    eprintln!("<Error> {e}");
-                    continue;
-                }
+        let model = model.to_owned();
+        for record in reader.records().map_while(Result::ok) {
+            let Some(Ok(index)) = record.get(0).map(str::parse::<usize>) else {
+                continue;
             };
 
-            match load_output(file.path()) {
-                Ok(loaded) => {
-                    sim_results.push(loaded);
-                }
-                Err(e) => {
-                    eprintln!("<Warning> {e}");
-                    continue;
-                }
+            let fields: csv::StringRecord = record.iter().skip(1).collect();
+            let Ok(entry) = fields
+                .deserialize::<CsvTableEntry>(None)
+                .map_err(|e| e.to_string())
+                .and_then(|x| x.parse_identity().map_err(|e| e.to_string()))
+            else {
+                continue;
             };
+
+            if self.manifest.contains(&(model.clone(), index, entry.seed)) {
+                completed.entry((index, entry.seed)).or_insert(entry);
+            }
+        }
+
+        completed
+    }
+
+    /// Rewrites the partial CSV for a model with only its finished rows and opens it for appending.
+    fn recorder(
+        &self,
+        model: &str,
+        completed: &HashMap<(usize, u64), TableEntry>,
+    ) -> io::Result<CheckpointRecorder> {
+        let mut partial = csv::WriterBuilder::new()
+            .has_headers(false)
+            .flexible(true)
+            .from_path(self.partial_path(model))?;
+        for ((index, _), entry) in completed.iter() {
+            write_partial_row(&mut partial, *index, entry)?;
         }
+        partial.flush()?;
+
+        let manifest = OpenOptions::new()
+            .append(true)
+            .open(self.dir.join("manifest.csv"))?;
+
+        Ok(CheckpointRecorder {
+            model: model.to_owned(),
+            partial: Mutex::new(partial),
+            manifest: Mutex::new(
+                csv::WriterBuilder::new()
+                    .has_headers(false)
+                    .from_writer(manifest),
+            ),
+        })
     }
-    sim_results
+}
+
+struct CheckpointRecorder {
+    model: String,
+    partial: Mutex<csv::Writer<File>>,
+    manifest: Mutex<csv::Writer<File>>,
+}
+
+impl CheckpointRecorder {
+    fn record(&self, index: usize, entry: &TableEntry) -> io::Result<()> {
+        {
+            let mut partial = self.partial.lock().unwrap();
+            write_partial_row(&mut partial, index, entry)?;
+            partial.flush()?;
+        }
+
+        let mut manifest = self.manifest.lock().unwrap();
+        manifest.serialize((&self.model, index, entry.seed))?;
+        manifest.flush()
+    }
+}
+
+fn write_partial_row(
+    writer: &mut csv::Writer<File>,
+    index: usize,
+    entry: &TableEntry,
+) -> csv::Result<()> {
+    writer.write_field(index.to_string())?;
+    writer.serialize(entry.csv_row())
+}
+
+/// Combines each group of `repeat` consecutive runs into a single row.
+/// Expects runs to be ordered as produced by [`run_pack`].
+fn aggregate_runs(runs: &[TableEntry], repeat: usize) -> Vec<AggregateEntry> {
+    runs.chunks(repeat)
+        .map(|group| {
+            let first = &group[0];
+            let stat = |metric: fn(&TableEntry) -> f64| {
+                let values: Vec<f64> = group.iter().map(metric).collect();
+                RunStatistic::new(&values)
+            };
+
+            let avg_reception = stat(|x| x.avg_reception);
+            let global_reception_rate = stat(|x| x.global_reception_rate);
+            let global_latency = stat(|x| x.global_latency);
+            let avg_avg_latency = stat(|x| x.avg_avg_latency);
+            let total_transmissions = stat(|x| x.total_transmissions as f64);
+            let total_airtime = stat(|x| x.total_airtime);
+            let gateway_reception = stat(|x| x.gateway_reception);
+            let gateway_latency = stat(|x| x.gateway_latency);
+            let top_sf_airtime_share = stat(|x| x.top_sf_airtime_share);
+            let uplink_reception = stat(|x| x.uplink_reception);
+            let uplink_latency = stat(|x| x.uplink_latency);
+            let busiest_gateway_share = stat(|x| x.busiest_gateway_share);
+
+            // Only over the runs that had messages with deadlines
+            let deadline_hit_rate = {
+                let values: Vec<f64> = group.iter().filter_map(|x| x.deadline_hit_rate).collect();
+                (!values.is_empty()).then(|| RunStatistic::new(&values))
+            };
+
+            AggregateEntry {
+                scenario_identity: first.scenario_identity.clone(),
+                model_identity: first.model_identity.clone(),
+                sim_version: first.sim_version.clone(),
+                runs: group.len(),
+                first_seed: first.seed,
+                pathloss_param: first.pathloss_param.clone(),
+                generated_messages: first.generated_messages,
+                avg_reception_mean: avg_reception.mean,
+                avg_reception_std: avg_reception.std,
+                global_reception_rate_mean: global_reception_rate.mean,
+                global_reception_rate_std: global_reception_rate.std,
+                global_latency_mean: global_latency.mean,
+                global_latency_std: global_latency.std,
+                avg_avg_latency_mean: avg_avg_latency.mean,
+                avg_avg_latency_std: avg_avg_latency.std,
+                total_transmissions_mean: total_transmissions.mean,
+                total_transmissions_std: total_transmissions.std,
+                total_airtime_mean: total_airtime.mean,
+                total_airtime_std: total_airtime.std,
+                gateway_reception_mean: gateway_reception.mean,
+                gateway_reception_std: gateway_reception.std,
+                gateway_latency_mean: gateway_latency.mean,
+                gateway_latency_std: gateway_latency.std,
+                top_sf_airtime_share_mean: top_sf_airtime_share.mean,
+                top_sf_airtime_share_std: top_sf_airtime_share.std,
+                uplink_reception_mean: uplink_reception.mean,
+                uplink_reception_std: uplink_reception.std,
+                uplink_latency_mean: uplink_latency.mean,
+                uplink_latency_std: uplink_latency.std,
+                busiest_gateway_share_mean: busiest_gateway_share.mean,
+                busiest_gateway_share_std: busiest_gateway_share.std,
+                deadline_hit_rate_mean: deadline_hit_rate.map(|x| x.mean),
+                deadline_hit_rate_std: deadline_hit_rate.map(|x| x.std),
+            }
+        })
+        .collect()
 }
 
 #[derive(Debug, Clone, Serialize)]
-struct TableEntry {
-    scenario_identity: String,
+struct AggregateEntry<S = ScenarioIdentity> {
+    scenario_identity: S,
     model_identity: String,
     sim_version: String,
-    seed: u64,
+    runs: usize,
+    first_seed: u64,
     pathloss_param: String,
     generated_messages: usize,
-    messaging_time: f64,
-    avg_reception: f64,
-    min_reception: f64,
-    max_reception: f64,
-    avg_avg_latency: f64,
-    min_avg_latency: f64,
-    max_avg_latency: f64,
-    total_transmissions: usize,
-    total_airtime: f64,
-    end_time: f64,
-    l120_score: f64,
-    l600_score: f64,
-    l6000_score: f64,
-    all_packet_uniqueness: f64,
-    message_packet_uniqueness: f64,
-    phantom_uniqueness: f64,
-
-    global_latency: f64,
-    global_reception_rate: f64,
-
-    t120_reception: f64,
-    t600_reception: f64,
-    t1800_reception: f64,
-    t6000_reception: f64,
-
-    message_reception_directness: f64,
-    reception_directness: f64,
-
-    message_reception_unique_directness: f64,
-    reception_unique_directness: f64,
-
-    message_transmission_directness: f64,
-    transmission_directness: f64,
-
-    message_transmission_unique_directness: f64,
-    transmission_unique_directness: f64,
-
-    emergency_result: EmergencyResult,
-
-    transmission_sent_events: usize,
-    transmission_received_events: usize,
-    transmission_blocked_events: usize,
-
-    gateway_latency: f64,
-    gateway_reception: f64,
+
+    avg_reception_mean: f64,
+    avg_reception_std: f64,
+    global_reception_rate_mean: f64,
+    global_reception_rate_std: f64,
+
+    global_latency_mean: f64,
+    global_latency_std: f64,
+    avg_avg_latency_mean: f64,
+    avg_avg_latency_std: f64,
+
+    total_transmissions_mean: f64,
+    total_transmissions_std: f64,
+    total_airtime_mean: f64,
+    total_airtime_std: f64,
+
+    gateway_reception_mean: f64,
+    gateway_reception_std: f64,
+    gateway_latency_mean: f64,
+    gateway_latency_std: f64,
+
+    top_sf_airtime_share_mean: f64,
+    top_sf_airtime_share_std: f64,
+
+    uplink_reception_mean: f64,
+    uplink_reception_std: f64,
+    uplink_latency_mean: f64,
+    uplink_latency_std: f64,
+
+    busiest_gateway_share_mean: f64,
+    busiest_gateway_share_std: f64,
+
+    deadline_hit_rate_mean: Option<f64>,
+    deadline_hit_rate_std: Option<f64>,
 }
 
-fn printout(scenario: Scenario, results: SimOutput) {
-    let frogcore::sim_file::OutputIdentity {
-        scenario_identity: scenario_id,
-        model_id,
-        simulation_seed: random_seed,
-        sim_version,
-    } = &results.complete_identity;
-
-    let analysis = CompleteAnalysis::new(results.clone(), scenario.clone());
-
-    println!();
-    println!("{scenario_id:?} with node model {model_id}");
-    println!("random seed: {random_seed}   simulation version: {sim_version}");
-    println!(
-        "Reception Rate: avg({:.4})  min({:.4})  max({:.4})",
-        analysis.reception_analysis.average_reception_rate,
-        analysis.reception_analysis.min_reception_rate,
-        analysis.reception_analysis.max_reception_rate
-    );
-    println!(
-        "Total Transmissions: {}  Total Airtime: {:.4} (Simulation End Time: {:.4})",
-        analysis.transmissions.len(),
-        analysis.total_airtime,
-        analysis.end_time,
-    );
+impl TableRow for AggregateEntry {
+    type Csv = AggregateEntry<String>;
+
+    fn csv_row(&self) -> AggregateEntry<String> {
+        let this = self.clone();
+        AggregateEntry {
+            scenario_identity: identity_string(&this.scenario_identity),
+            model_identity: this.model_identity,
+            sim_version: this.sim_version,
+            runs: this.runs,
+            first_seed: this.first_seed,
+            pathloss_param: this.pathloss_param,
+            generated_messages: this.generated_messages,
+            avg_reception_mean: this.avg_reception_mean,
+            avg_reception_std: this.avg_reception_std,
+            global_reception_rate_mean: this.global_reception_rate_mean,
+            global_reception_rate_std: this.global_reception_rate_std,
+            global_latency_mean: this.global_latency_mean,
+            global_latency_std: this.global_latency_std,
+            avg_avg_latency_mean: this.avg_avg_latency_mean,
+            avg_avg_latency_std: this.avg_avg_latency_std,
+            total_transmissions_mean: this.total_transmissions_mean,
+            total_transmissions_std: this.total_transmissions_std,
+            total_airtime_mean: this.total_airtime_mean,
+            total_airtime_std: this.total_airtime_std,
+            gateway_reception_mean: this.gateway_reception_mean,
+            gateway_reception_std: this.gateway_reception_std,
+            gateway_latency_mean: this.gateway_latency_mean,
+            gateway_latency_std: this.gateway_latency_std,
+            top_sf_airtime_share_mean: this.top_sf_airtime_share_mean,
+            top_sf_airtime_share_std: this.top_sf_airtime_share_std,
+            uplink_reception_mean: this.uplink_reception_mean,
+            uplink_reception_std: this.uplink_reception_std,
+            uplink_latency_mean: this.uplink_latency_mean,
+            uplink_latency_std: this.uplink_latency_std,
+            busiest_gateway_share_mean: this.busiest_gateway_share_mean,
+            busiest_gateway_share_std: this.busiest_gateway_share_std,
+            deadline_hit_rate_mean: this.deadline_hit_rate_mean,
+            deadline_hit_rate_std: this.deadline_hit_rate_std,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        panic::{AssertUnwindSafe, catch_unwind},
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+
+    use frog_cli::table::write_rows_to;
+    use frogcore::{
+        node::ModelSelection::{self, BasicFlood},
+        simulation::run_simulation,
+        units::MINS,
+    };
+
+    fn tiny_pack(seeds: impl IntoIterator<Item = u64>) -> Vec<ScenarioIdentity> {
+        seeds
+            .into_iter()
+            .map(|seed| ScenarioIdentity::for_test(5, 5, 1.0 * MINS, seed))
+            .collect()
+    }
+
+    fn source(seeds: &[u64]) -> CheckpointSource {
+        CheckpointSource {
+            pack_hash: 1,
+            filter: None,
+            sample: None,
+            sample_seed: 0,
+            seeds: seeds.to_vec(),
+        }
+    }
+
+    fn flood_entry(scenario: &Scenario, results: SimOutput) -> TableEntry {
+        let mut entry =
+            make_table_entry_with_scenario(true, false, None, results, scenario, &ErrorLog::new());
+        // Wall time differs between runs so would stop tables comparing equal
+        entry.sim_wall_ms = 0.0;
+        entry
+    }
+
+    fn csv_bytes<T: TableRow>(table: &[T]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        write_rows_to(&mut bytes, table, OutputFormat::Csv).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn repeat_row_counts() {
+        let scenarios = tiny_pack([1, 2]);
+        let progress = Progress::new("BasicFlood", 4, true);
+        let runs = run_pack(
+            &scenarios,
+            &[10, 11],
+            BasicFlood,
+            PACK_LIMITS,
+            &progress,
+            flood_entry,
+        );
+
+        assert_eq!(progress.completed(), progress.total());
+
+        assert_eq!(runs.len(), 4);
+        assert_eq!(runs[1].seed, 11);
+
+        let aggregated = aggregate_runs(&runs, 2);
+        assert_eq!(aggregated.len(), 2);
+        assert!(aggregated.iter().all(|x| x.runs == 2));
+    }
+
+    #[test]
+    fn zero_repeats_are_rejected() {
+        assert!(Args::try_parse_from(["summary", "--repeat", "0"]).is_err());
+        let args = Args::try_parse_from(["summary", "--repeat", "3"]).unwrap();
+        assert_eq!(args.repeat, 3);
+    }
+
+    #[test]
+    fn timed_out_runs_are_recorded() {
+        // Big enough to reach the first wall-clock check
+        let scenarios = vec![ScenarioIdentity::for_test(20, 50, 10.0 * MINS, 1)];
+
+        let limits = pack_limits(Some("1e-6 s".parse().unwrap()));
+        assert!(limits.max_wall_time.unwrap() < Duration::from_millis(1));
+
+        let progress = Progress::new("BasicFlood", 1, true);
+        let runs = run_pack(&scenarios, &[10], BasicFlood, limits, &progress, flood_entry);
+        assert!(runs[0].timed_out);
+        assert!(runs[0].avg_reception.is_nan());
+        assert!(runs[0].events_processed > 0);
+
+        let csv = String::from_utf8(csv_bytes(&runs)).unwrap();
+        let row: CsvTableEntry = csv::Reader::from_reader(csv.as_bytes())
+            .deserialize()
+            .next()
+            .unwrap()
+            .unwrap();
+        assert!(row.timed_out);
+        assert!(row.global_latency.is_nan());
+
+        let progress = Progress::new("BasicFlood", 1, true);
+        let runs = run_pack(&scenarios, &[10], BasicFlood, PACK_LIMITS, &progress, flood_entry);
+        assert!(!runs[0].timed_out);
+        assert!(!runs[0].avg_reception.is_nan());
+    }
+
+    #[test]
+    fn checkpoint_resume() {
+        let dir = std::env::temp_dir().join(format!("frog_checkpoint_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let scenarios = tiny_pack(1..=5);
+        let seeds = [10, 11];
+        let expected = run_pack(
+            &scenarios,
+            &seeds,
+            BasicFlood,
+            PACK_LIMITS,
+            &Progress::new("BasicFlood", 10, true),
+            flood_entry,
+        );
+
+        // Simulate the process dying after a few runs
+        let started = AtomicUsize::new(0);
+        let killed = catch_unwind(AssertUnwindSafe(|| {
+            let checkpoint = Checkpoint::open(dir.clone(), &source(&seeds)).unwrap();
+            let progress = Progress::new("BasicFlood", 10, true);
+            run_pack_checkpointed(
+                &scenarios,
+                &seeds,
+                BasicFlood,
+                PACK_LIMITS,
+                &checkpoint,
+                &progress,
+                |scenario, x| {
+                    if started.fetch_add(1, Ordering::SeqCst) >= 4 {
+                        panic!("killed");
+                    }
+                    flood_entry(scenario, x)
+                },
+            )
+        }));
+        assert!(killed.is_err());
+
+        let resumed_runs = AtomicUsize::new(0);
+        let checkpoint = Checkpoint::open(dir.clone(), &source(&seeds)).unwrap();
+        let progress = Progress::new("BasicFlood", 10, true);
+        let resumed = run_pack_checkpointed(
+            &scenarios,
+            &seeds,
+            BasicFlood,
+            PACK_LIMITS,
+            &checkpoint,
+            &progress,
+            |scenario, x| {
+                resumed_runs.fetch_add(1, Ordering::SeqCst);
+                flood_entry(scenario, x)
+            },
+        )
+        .unwrap();
+
+        assert_eq!(progress.completed(), progress.total());
+        assert!(resumed_runs.load(Ordering::SeqCst) <= expected.len() - 4);
+        assert_eq!(csv_bytes(&resumed), csv_bytes(&expected));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn checkpoint_source_mismatch() {
+        let dir = std::env::temp_dir().join(format!("frog_checkpoint_src_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let original = source(&[10, 11]);
+        Checkpoint::open(dir.clone(), &original).unwrap();
+        Checkpoint::open(dir.clone(), &original).unwrap();
+
+        let filter = Some("PathwaysOne".parse().unwrap());
+        let changed = [
+            ("pack", CheckpointSource { pack_hash: 2, ..original.clone() }),
+            ("--filter", CheckpointSource { filter, ..original.clone() }),
+            ("--sample", CheckpointSource { sample: Some(3), ..original.clone() }),
+            ("--sample-seed", CheckpointSource { sample_seed: 1, ..original.clone() }),
+            ("seeds", source(&[10, 12])),
+        ];
+        for (name, changed) in changed {
+            let result = Checkpoint::open(dir.clone(), &changed);
+            assert!(
+                matches!(result, Err(CheckpointError::Mismatch(x)) if x == name),
+                "{name} should not match"
+            );
+        }
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn exit_codes() {
+        use frog_cli::errors::{EXIT_BOTH, EXIT_LOAD, EXIT_SUCCESS, EXIT_VERIFICATION};
+        use frogcore::sim_file::write_output;
+
+        let dir = std::env::temp_dir().join(format!("frog_exit_codes_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let output = run_simulation(
+            10,
+            tiny_pack([1])[0].create(),
+            ModelSelection::BasicFlood.into(),
+            false,
+        );
+
+        // A second copy of a transmission overlaps the first
+        let mut overlapping = output.clone();
+        let mut copy = overlapping.transmissions[0].clone();
+        copy.id = overlapping.transmissions.len() as u32;
+        overlapping.transmissions.push(copy);
+
+        let mut custom = output.clone();
+        custom.complete_identity.scenario_identity = ScenarioIdentity::Custom;
+
+        let summarise_dir = |name: &str, files: &[(&str, Option<&SimOutput>)]| {
+            let results = dir.join(name);
+            create_dir_all(&results).unwrap();
+            for (file, output) in files {
+                let path = results.join(file);
+                match output {
+                    Some(output) => write_output(path, (*output).clone(), false).unwrap(),
+                    None => std::fs::write(path, "not a result").unwrap(),
+                }
+            }
+
+            let report = dir.join(format!("{name}_errors.json"));
+            let args = Args::parse_from([
+                "summary".as_ref(),
+                "--results".as_ref(),
+                results.as_os_str(),
+                "--output".as_ref(),
+                dir.join(format!("{name}.csv")).as_os_str(),
+                "--error-report".as_ref(),
+                report.as_os_str(),
+            ]);
+            let code = summarise(args);
+
+            let report: serde_json::Value =
+                serde_json::from_slice(&std::fs::read(report).unwrap()).unwrap();
+            (code, report.as_array().unwrap().len())
+        };
+
+        assert_eq!(
+            summarise_dir("good", &[("a.json", Some(&output))]),
+            (EXIT_SUCCESS, 0)
+        );
+        assert_eq!(
+            summarise_dir(
+                "verify",
+                &[("a.json", Some(&output)), ("b.json", Some(&overlapping))]
+            ),
+            (EXIT_VERIFICATION, 1)
+        );
+        assert_eq!(
+            summarise_dir("load", &[("a.json", Some(&output)), ("b.json", None)]),
+            (EXIT_LOAD, 1)
+        );
+        assert_eq!(
+            summarise_dir(
+                "both",
+                &[("a.json", Some(&overlapping)), ("b.json", None)]
+            ),
+            (EXIT_BOTH, 2)
+        );
+        assert_eq!(
+            summarise_dir("custom", &[("a.json", Some(&output)), ("b.json", Some(&custom))]),
+            (EXIT_LOAD, 1)
+        );
+
+        // The per node output directory can't be made where a file already is
+        let args = Args::parse_from([
+            "summary".as_ref(),
+            "--results".as_ref(),
+            dir.join("good").as_os_str(),
+            "--output".as_ref(),
+            dir.join("blocked.csv").as_os_str(),
+            "--per-node-output".as_ref(),
+            dir.join("good.csv").as_os_str(),
+        ]);
+        assert_eq!(summarise(args), EXIT_LOAD);
+
+        let duty_cycle = |extra: &[&str]| {
+            let table = dir.join("duty_cycle.csv");
+            let mut args: Vec<std::ffi::OsString> = vec![
+                "summary".into(),
+                "--results".into(),
+                dir.join("good").into(),
+                "--output".into(),
+                table.clone().into(),
+            ];
+            args.extend(extra.iter().map(Into::into));
+            let code = summarise(Args::parse_from(args));
+
+            let row: CsvTableEntry = csv::Reader::from_path(table)
+                .unwrap()
+                .deserialize()
+                .next()
+                .unwrap()
+                .unwrap();
+            (code, row)
+        };
+
+        let (code, row) = duty_cycle(&["--duty-cycle"]);
+        assert_eq!(code, EXIT_SUCCESS);
+        assert!(row.max_duty_cycle.unwrap() > 0.0);
+        assert!(row.duty_cycle_node.is_some() && row.duty_cycle_window_start.is_some());
+        // Any transmission at all goes over a limit of zero
+        assert_eq!(duty_cycle(&["--duty-cycle-limit", "0"]).0, EXIT_VERIFICATION);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
 }
@@ -0,0 +1,59 @@
+//! Expand a sweep spec into a simpack.
+
+use std::{path::PathBuf, process::ExitCode};
+
+use clap::Parser;
+use frog_cli::progress::Progress;
+use frogcore::{
+    scenario::generation::sweep::{SeedPolicy, load_sweep},
+    sim_file::write_file,
+};
+
+#[derive(Parser, Debug)]
+#[command(version, about, long_about = None)]
+struct Args {
+    /// Sweep spec. Read as TOML if the extension is `toml` otherwise JSON
+    spec: PathBuf,
+
+    /// Simpack file. Written as JSON if the extension is `json` otherwise rust messagepack
+    #[arg(short, long, default_value = "pack.json")]
+    out: PathBuf,
+
+    /// Overrides the seed policy of the spec with random seeds from this seed
+    #[arg(long)]
+    seed: Option<u64>,
+
+    /// Don't show progress
+    #[arg(short, long)]
+    quiet: bool,
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+
+    let mut spec = match load_sweep(args.spec) {
+        Ok(spec) => spec,
+        Err(e) => {
+            eprintln!("<Error> {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    if let Some(seed) = args.seed {
+        spec.seed = SeedPolicy::Random(seed);
+    }
+
+    let progress = Progress::new("Expanding", spec.len(), args.quiet);
+    let pack = spec.expand_with(|n| progress.inc_by(n));
+    progress.finish();
+
+    println!("{} scenarios ({} before skipping)", pack.len(), spec.len());
+
+    let use_rmp = args.out.extension().is_none_or(|x| x != "json");
+    if let Err(e) = write_file(args.out, pack, use_rmp) {
+        eprintln!("<Error> {e}");
+        return ExitCode::FAILURE;
+    }
+
+    ExitCode::SUCCESS
+}
@@ -0,0 +1,135 @@
+//! Errors collected while building summary tables.
+//!
+//! Work carries on past an error so one bad file or run doesn't lose the rest of the table,
+//! but the errors are kept so the command can exit with a code saying what went wrong.
+
+use std::{
+    fmt,
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use frogcore::scenario::ScenarioIdentity;
+use serde::Serialize;
+
+use crate::report;
+
+pub const EXIT_SUCCESS: u8 = 0;
+/// At least one run failed verification.
+pub const EXIT_VERIFICATION: u8 = 2;
+/// At least one file failed to load, parse or be written, or a result couldn't be used.
+pub const EXIT_LOAD: u8 = 3;
+/// Both verification and load errors happened.
+pub const EXIT_BOTH: u8 = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorKind {
+    Load,
+    Verification,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RunError {
+    pub kind: ErrorKind,
+    pub file: Option<PathBuf>,
+    pub scenario_identity: Option<ScenarioIdentity>,
+    /// Name of the failed verification check
+    pub check: Option<String>,
+    pub message: String,
+}
+
+impl RunError {
+    pub fn load(file: impl Into<PathBuf>, message: impl fmt::Display) -> RunError {
+        RunError {
+            kind: ErrorKind::Load,
+            file: Some(file.into()),
+            scenario_identity: None,
+            check: None,
+            message: message.to_string(),
+        }
+    }
+
+    /// A result that loaded but can't be summarised.
+    pub fn unusable(scenario_identity: ScenarioIdentity, message: impl fmt::Display) -> RunError {
+        RunError {
+            kind: ErrorKind::Load,
+            file: None,
+            scenario_identity: Some(scenario_identity),
+            check: None,
+            message: message.to_string(),
+        }
+    }
+
+    pub fn verification(
+        scenario_identity: ScenarioIdentity,
+        check: impl fmt::Debug,
+        message: impl fmt::Display,
+    ) -> RunError {
+        RunError {
+            kind: ErrorKind::Verification,
+            file: None,
+            scenario_identity: Some(scenario_identity),
+            check: Some(format!("{check:?}")),
+            message: message.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for RunError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(file) = &self.file {
+            write!(f, "{}: ", file.display())?;
+        }
+
+        match &self.check {
+            Some(check) => write!(f, "{check} failed: {}", self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+/// Collects errors from every thread of a run.
+#[derive(Debug, Default)]
+pub struct ErrorLog {
+    errors: Mutex<Vec<RunError>>,
+}
+
+impl ErrorLog {
+    pub fn new() -> ErrorLog {
+        ErrorLog::default()
+    }
+
+    /// Records the error and prints it to stderr.
+    pub fn push(&self, error: RunError) {
+        report!("<Error> {error}");
+        self.errors.lock().unwrap().push(error);
+    }
+
+    pub fn errors(&self) -> Vec<RunError> {
+        self.errors.lock().unwrap().clone()
+    }
+
+    /// See the `EXIT_` constants.
+    pub fn exit_code(&self) -> u8 {
+        let errors = self.errors.lock().unwrap();
+        let any = |kind| errors.iter().any(|x| x.kind == kind);
+
+        match (any(ErrorKind::Verification), any(ErrorKind::Load)) {
+            (false, false) => EXIT_SUCCESS,
+            (true, false) => EXIT_VERIFICATION,
+            (false, true) => EXIT_LOAD,
+            (true, true) => EXIT_BOTH,
+        }
+    }
+
+    /// Writes every error as a JSON list.
+    pub fn write_report(&self, path: &Path) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        serde_json::to_writer_pretty(&mut writer, &*self.errors.lock().unwrap())?;
+        writeln!(writer)?;
+        writer.flush()
+    }
+}
@@ -0,0 +1,263 @@
+//! Selecting part of a simpack.
+//!
+//! A filter is a comma separated list of predicates which must all hold:
+//!
+//! - `PathwaysOne` keeps scenarios made by that generator, the same as `generator = PathwaysOne`.
+//! - `people_count > 30` compares a field of the generator. Operators are
+//!   `=`, `!=`, `<`, `<=`, `>` and `>=`.
+//!
+//! Fields are found by name at any depth, so `message_count` finds `messaging.message_count`.
+//! A dotted path such as `positioning.side_len` only matches that exact field and `seed` is the
+//! seed of the scenario. Quantities such as lengths and times are compared in base units.
+//! Scenarios without the field never match.
+
+use std::{cmp::Ordering, str::FromStr};
+
+use frogcore::scenario::ScenarioIdentity;
+use rand::{SeedableRng, seq::index::sample};
+use rand_chacha::ChaCha12Rng;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use thiserror::Error;
+
+#[derive(Debug, Error, PartialEq)]
+pub enum FilterError {
+    #[error("Empty filter predicate")]
+    Empty,
+    #[error("Missing value in filter predicate \"{0}\"")]
+    MissingValue(String),
+    #[error("Invalid field \"{0}\" in filter predicate")]
+    InvalidField(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+/// Two character operators come first so `<=` is not read as `<`.
+const OPS: [(&str, Op); 6] = [
+    ("!=", Op::Ne),
+    ("<=", Op::Le),
+    (">=", Op::Ge),
+    ("=", Op::Eq),
+    ("<", Op::Lt),
+    (">", Op::Gt),
+];
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+enum Predicate {
+    Generator(String),
+    Field { path: String, op: Op, value: String },
+}
+
+/// See the [module docs](self) for the syntax.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PackFilter {
+    predicates: Vec<Predicate>,
+}
+
+impl FromStr for PackFilter {
+    type Err = FilterError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let predicates = s
+            .split(',')
+            .map(parse_predicate)
+            .collect::<Result<_, _>>()?;
+
+        Ok(PackFilter { predicates })
+    }
+}
+
+fn parse_predicate(text: &str) -> Result<Predicate, FilterError> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Err(FilterError::Empty);
+    }
+
+    let Some((index, symbol, op)) = OPS
+        .iter()
+        .find_map(|(symbol, op)| text.find(symbol).map(|index| (index, *symbol, *op)))
+    else {
+        return Ok(Predicate::Generator(text.to_owned()));
+    };
+
+    let path = text[..index].trim();
+    let value = text[index + symbol.len()..].trim();
+
+    if value.is_empty() {
+        return Err(FilterError::MissingValue(text.to_owned()));
+    }
+
+    let valid_path = !path.is_empty()
+        && path
+            .split('.')
+            .all(|x| !x.is_empty() && x.chars().all(|c| c.is_alphanumeric() || c == '_'));
+    if !valid_path {
+        return Err(FilterError::InvalidField(path.to_owned()));
+    }
+
+    if path == "generator" && op == Op::Eq {
+        return Ok(Predicate::Generator(value.to_owned()));
+    }
+
+    Ok(Predicate::Field {
+        path: path.to_owned(),
+        op,
+        value: value.to_owned(),
+    })
+}
+
+impl PackFilter {
+    pub fn matches(&self, identity: &ScenarioIdentity) -> bool {
+        let ScenarioIdentity::Generated { generator, seed } = identity else {
+            return false;
+        };
+
+        let Ok(Value::Object(generator)) = serde_json::to_value(generator) else {
+            return false;
+        };
+        let Some((name, fields)) = generator.iter().next() else {
+            return false;
+        };
+
+        self.predicates.iter().all(|predicate| match predicate {
+            Predicate::Generator(expected) => name == expected,
+            Predicate::Field { path, op, value } => {
+                let found = if path == "seed" {
+                    Some(Value::from(*seed))
+                } else {
+                    lookup(fields, path).cloned()
+                };
+
+                found.is_some_and(|found| compare(&found, *op, value))
+            }
+        })
+    }
+
+    pub fn apply(&self, pack: Vec<ScenarioIdentity>) -> Vec<ScenarioIdentity> {
+        pack.into_iter().filter(|x| self.matches(x)).collect()
+    }
+}
+
+/// Dotted paths are followed exactly, single names are searched for depth first.
+fn lookup<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    if path.contains('.') {
+        return path.split('.').try_fold(value, |value, key| value.get(key));
+    }
+
+    match value {
+        Value::Object(map) => map
+            .get(path)
+            .or_else(|| map.values().find_map(|x| lookup(x, path))),
+        _ => None,
+    }
+}
+
+fn compare(found: &Value, op: Op, expected: &str) -> bool {
+    let ordering = match (found.as_f64(), expected.parse::<f64>()) {
+        (Some(found), Ok(expected)) => found.partial_cmp(&expected),
+        _ => {
+            let found = match found {
+                Value::String(inner) => inner.clone(),
+                other => other.to_string(),
+            };
+            let equal = found == expected.trim_matches('"');
+            return match op {
+                Op::Eq => equal,
+                Op::Ne => !equal,
+                _ => false,
+            };
+        }
+    };
+
+    let Some(ordering) = ordering else {
+        return false;
+    };
+
+    match op {
+        Op::Eq => ordering == Ordering::Equal,
+        Op::Ne => ordering != Ordering::Equal,
+        Op::Lt => ordering == Ordering::Less,
+        Op::Le => ordering != Ordering::Greater,
+        Op::Gt => ordering == Ordering::Greater,
+        Op::Ge => ordering != Ordering::Less,
+    }
+}
+
+/// A random subset of `count` scenarios which keeps the order of the pack.
+/// The same seed always picks the same subset.
+pub fn sample_pack(pack: Vec<ScenarioIdentity>, count: usize, seed: u64) -> Vec<ScenarioIdentity> {
+    if count >= pack.len() {
+        return pack;
+    }
+
+    let mut rng = ChaCha12Rng::seed_from_u64(seed);
+    let mut chosen = sample(&mut rng, pack.len(), count).into_vec();
+    chosen.sort_unstable();
+
+    let mut chosen = chosen.into_iter().peekable();
+    pack.into_iter()
+        .enumerate()
+        .filter(|(index, _)| chosen.next_if_eq(index).is_some())
+        .map(|(_, x)| x)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use frogcore::sim_file::load_file;
+
+    use super::*;
+
+    fn fixture_pack() -> Vec<ScenarioIdentity> {
+        load_file(
+            concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/tests/fixtures/mixed_pack.json"
+            )
+            .into(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn filter_by_generator() {
+        let pack = fixture_pack();
+        assert_eq!(pack.len(), 9);
+
+        let filter: PackFilter = "RandomSquare".parse().unwrap();
+        let selected = filter.apply(pack.clone());
+        assert_eq!(selected.len(), 4);
+        assert!(selected.iter().all(|x| matches!(
+            x,
+            ScenarioIdentity::Generated {
+                generator: frogcore::scenario::generation::ScenarioGenerator::RandomSquare { .. },
+                ..
+            }
+        )));
+
+        let filter: PackFilter = "generator = PathwaysOne, people_count > 10"
+            .parse()
+            .unwrap();
+        assert_eq!(filter.apply(pack.clone()).len(), 1);
+
+        let filter: PackFilter = "message_count >= 100".parse().unwrap();
+        assert_eq!(filter.apply(pack.clone()).len(), 7);
+
+        assert_eq!(
+            "people_count >".parse::<PackFilter>(),
+            Err(FilterError::MissingValue("people_count >".into()))
+        );
+
+        let sampled = sample_pack(pack.clone(), 3, 7);
+        assert_eq!(sampled.len(), 3);
+        assert_eq!(sampled, sample_pack(pack, 3, 7));
+    }
+}
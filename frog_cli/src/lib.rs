@@ -0,0 +1,7 @@
+//! Code shared between the command line tools.
+
+pub mod errors;
+pub mod filter;
+pub mod per_node;
+pub mod progress;
+pub mod table;
@@ -0,0 +1,157 @@
+//! Per node metrics for a single run, written next to the summary tables
+//! so a regression at a few nodes isn't lost in the averages.
+
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+use frogcore::{
+    analysis::CompleteAnalysis,
+    scenario::Scenario,
+    sim_file::{OutputIdentity, SimOutput},
+    units::{SECONDS, Unit},
+};
+use serde::{Deserialize, Serialize};
+
+/// One node of a run.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PerNodeRow {
+    pub node_id: usize,
+    pub is_gateway: bool,
+    /// Proportion of the messages wanted by the node that it received
+    pub reception_rate: f64,
+    /// Mean latency of the wanted messages the node received, in seconds
+    pub avg_latency: f64,
+    pub transmissions: usize,
+    /// Seconds spent transmitting
+    pub airtime: f64,
+    /// Receptions at the node lost to collisions
+    pub blocked: usize,
+    /// SNR in dB over everything the node received, empty if it received nothing
+    /// or the output is from before SNRs were logged
+    pub mean_snr: Option<f64>,
+    pub min_snr: Option<f64>,
+    /// Position at the start of the run in metres, empty for graph maps
+    pub x: Option<f64>,
+    pub y: Option<f64>,
+}
+
+/// One row per node, in node id order.
+pub fn per_node_rows(analysis: &CompleteAnalysis, scenario: &Scenario) -> Vec<PerNodeRow> {
+    let reception = &analysis.reception_analysis;
+
+    (0..scenario.map.len())
+        .map(|id| {
+            let position = scenario.map.location(0.0 * SECONDS, id);
+            let links = analysis.link_snr.iter().filter(|x| x.receiver_id == id);
+            let receptions = links.clone().map(|x| x.receptions).sum::<usize>();
+            let snr_sum = links.clone().map(|x| x.mean_snr * x.receptions as f64).sum::<f64>();
+            PerNodeRow {
+                node_id: id,
+                is_gateway: scenario.settings[id].is_gateway,
+                reception_rate: reception.reception_rate[id],
+                avg_latency: reception.avg_latency_per_node[id].seconds(),
+                transmissions: analysis.transmissions_per_node[id],
+                airtime: analysis.airtime_per_node[id],
+                blocked: analysis.blocked_per_node[id],
+                mean_snr: (receptions > 0).then(|| snr_sum / receptions as f64),
+                min_snr: links.map(|x| x.min_snr).reduce(f64::min),
+                x: position.map(|x| x.x.inner()),
+                y: position.map(|x| x.y.inner()),
+            }
+        })
+        .collect()
+}
+
+/// 64 bit FNV-1a hash of the scenario identity, model, seed and simulation version
+/// serialised as JSON. Doesn't change between builds, unlike the standard library hashers.
+pub fn identity_hash(identity: &OutputIdentity) -> u64 {
+    let key = (
+        &identity.scenario_identity,
+        &identity.model_id,
+        identity.simulation_seed,
+        &identity.sim_version,
+    );
+    let json = serde_json::to_vec(&key).unwrap();
+
+    json.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// `<identity hash as 16 hex digits>.csv`, see [`identity_hash`].
+pub fn per_node_file_name(identity: &OutputIdentity) -> String {
+    format!("{:016x}.csv", identity_hash(identity))
+}
+
+/// Writes the per node CSV for a run into `dir`, returning its path.
+pub fn write_per_node(
+    dir: &Path,
+    results: &SimOutput,
+    scenario: &Scenario,
+) -> io::Result<PathBuf> {
+    let analysis = CompleteAnalysis::new(results.clone(), scenario.clone());
+    let path = dir.join(per_node_file_name(&results.complete_identity));
+
+    let mut writer = csv::Writer::from_path(&path)?;
+    for row in per_node_rows(&analysis, scenario) {
+        writer.serialize(row)?;
+    }
+    writer.flush()?;
+
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use frogcore::{
+        node::ModelSelection,
+        scenario::ScenarioIdentity,
+        sim_file::TruncationReason,
+        simulation::run_simulation,
+        units::MINS,
+    };
+
+    #[test]
+    fn one_row_per_node() {
+        let mut scenario = ScenarioIdentity::for_test(7, 10, 1.0 * MINS, 3).create();
+        scenario.settings[2].is_gateway = true;
+        scenario.settings[5].is_gateway = true;
+
+        let model = ModelSelection::BasicFlood.into();
+        let output = run_simulation(1, scenario.clone(), model, false);
+
+        let dir = std::env::temp_dir().join(format!("frog_per_node_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = write_per_node(&dir, &output, &scenario).unwrap();
+        assert_eq!(path, dir.join(per_node_file_name(&output.complete_identity)));
+
+        let rows: Vec<PerNodeRow> = csv::Reader::from_path(&path)
+            .unwrap()
+            .deserialize()
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(rows.len(), scenario.map.len());
+        for (id, row) in rows.iter().enumerate() {
+            assert_eq!(row.node_id, id);
+            assert_eq!(row.is_gateway, scenario.settings[id].is_gateway);
+        }
+        assert_eq!(
+            rows.iter().map(|x| x.transmissions).sum::<usize>(),
+            output.transmissions.len()
+        );
+        assert!(rows.iter().all(|x| x.mean_snr.is_some() == x.min_snr.is_some()));
+        assert!(rows.iter().any(|x| x.min_snr.is_some_and(|min| min <= x.mean_snr.unwrap())));
+
+        // Only the run decides the name, not how it went
+        let mut other = output.complete_identity.clone();
+        other.truncated = Some(TruncationReason::MaxEvents(1));
+        assert_eq!(per_node_file_name(&other), per_node_file_name(&output.complete_identity));
+        other.simulation_seed += 1;
+        assert_ne!(per_node_file_name(&other), per_node_file_name(&output.complete_identity));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
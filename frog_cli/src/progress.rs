@@ -0,0 +1,183 @@
+//! A progress line for long running commands.
+//!
+//! The line is redrawn in place on stderr, so messages printed while it is shown should go through
+//! [`report!`](crate::report) which clears the line first rather than writing after it.
+
+use std::{
+    fmt,
+    io::{self, IsTerminal, Write},
+    sync::{
+        Mutex,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+const REDRAW_INTERVAL: Duration = Duration::from_millis(100);
+const BAR_WIDTH: usize = 30;
+
+/// Set while a progress line is on screen.
+static LINE_SHOWN: AtomicBool = AtomicBool::new(false);
+
+/// Counts finished scenarios and shows them with the throughput and estimated time remaining.
+/// Can be shared between rayon workers.
+pub struct Progress {
+    label: String,
+    total: usize,
+    done: AtomicUsize,
+    skipped: AtomicUsize,
+    start: Instant,
+    visible: bool,
+    last_draw: Mutex<Instant>,
+}
+
+impl Progress {
+    /// Nothing is drawn if `quiet` is set or stderr is not a terminal.
+    pub fn new(label: impl Into<String>, total: usize, quiet: bool) -> Progress {
+        let progress = Progress {
+            label: label.into(),
+            total,
+            done: AtomicUsize::new(0),
+            skipped: AtomicUsize::new(0),
+            start: Instant::now(),
+            visible: !quiet && io::stderr().is_terminal(),
+            last_draw: Mutex::new(Instant::now()),
+        };
+
+        progress.draw();
+        progress
+    }
+
+    pub fn total(&self) -> usize {
+        self.total
+    }
+
+    pub fn completed(&self) -> usize {
+        self.done.load(Ordering::Relaxed)
+    }
+
+    pub fn inc(&self) {
+        self.inc_by(1);
+    }
+
+    pub fn inc_by(&self, n: usize) {
+        self.done.fetch_add(n, Ordering::Relaxed);
+
+        // Skip the redraw if another thread is drawing
+        let Ok(mut last_draw) = self.last_draw.try_lock() else {
+            return;
+        };
+
+        if last_draw.elapsed() >= REDRAW_INTERVAL {
+            *last_draw = Instant::now();
+            self.draw();
+        }
+    }
+
+    /// Counts work that was already done, such as runs restored from a checkpoint.
+    /// These are not included in the throughput.
+    pub fn skip(&self, n: usize) {
+        self.skipped.fetch_add(n, Ordering::Relaxed);
+        self.done.fetch_add(n, Ordering::Relaxed);
+        self.draw();
+    }
+
+    /// Draws the final state and moves to a new line.
+    pub fn finish(&self) {
+        if !self.visible {
+            return;
+        }
+
+        let _lock = self.last_draw.lock().unwrap();
+        let mut stderr = io::stderr().lock();
+        let _ = writeln!(stderr, "\r\x1b[2K{}", self.line());
+        LINE_SHOWN.store(false, Ordering::Relaxed);
+    }
+
+    fn draw(&self) {
+        if !self.visible {
+            return;
+        }
+
+        let mut stderr = io::stderr().lock();
+        let _ = write!(stderr, "\r\x1b[2K{}", self.line());
+        let _ = stderr.flush();
+        LINE_SHOWN.store(true, Ordering::Relaxed);
+    }
+
+    fn line(&self) -> String {
+        let done = self.completed().min(self.total);
+        let run = done.saturating_sub(self.skipped.load(Ordering::Relaxed));
+        let elapsed = self.start.elapsed().as_secs_f64();
+
+        let filled = (BAR_WIDTH * done)
+            .checked_div(self.total)
+            .unwrap_or(BAR_WIDTH);
+        let bar = format!("{}{}", "#".repeat(filled), "-".repeat(BAR_WIDTH - filled));
+
+        let rate = if elapsed > 0.0 {
+            run as f64 / elapsed
+        } else {
+            0.0
+        };
+
+        let eta = if done == self.total {
+            format!("done in {}", format_duration(elapsed))
+        } else if rate > 0.0 {
+            format!("ETA {}", format_duration((self.total - done) as f64 / rate))
+        } else {
+            "ETA --:--:--".to_owned()
+        };
+
+        format!(
+            "{} [{bar}] {done}/{} {rate:.2}/s {eta}",
+            self.label, self.total
+        )
+    }
+}
+
+fn format_duration(seconds: f64) -> String {
+    let seconds = seconds.round() as u64;
+    format!(
+        "{}:{:02}:{:02}",
+        seconds / 3600,
+        (seconds / 60) % 60,
+        seconds % 60
+    )
+}
+
+/// Writes a line to stderr, clearing any progress line first.
+/// The progress line is redrawn on its next update.
+pub fn report_line(args: fmt::Arguments) {
+    let mut stderr = io::stderr().lock();
+
+    if LINE_SHOWN.swap(false, Ordering::Relaxed) {
+        let _ = write!(stderr, "\r\x1b[2K");
+    }
+
+    let _ = writeln!(stderr, "{args}");
+}
+
+/// Like `eprintln!` but does not get mixed up with a progress line.
+#[macro_export]
+macro_rules! report {
+    ($($arg: tt)*) => {
+        $crate::progress::report_line(format_args!($($arg)*))
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use rayon::iter::{IntoParallelIterator, ParallelIterator};
+
+    use super::*;
+
+    #[test]
+    fn counter_reaches_total() {
+        let progress = Progress::new("Test", 1000, true);
+        (0..1000).into_par_iter().for_each(|_| progress.inc());
+
+        assert_eq!(progress.completed(), progress.total());
+        progress.finish();
+    }
+}
@@ -0,0 +1,678 @@
+//! Rows of the summary tables written by the command line tools.
+
+use std::{
+    fs::{File, read_dir},
+    io::{self, Write},
+    path::PathBuf,
+};
+
+use clap::ValueEnum;
+use frogcore::{
+    analysis::{CompleteAnalysis, EmergencyResult},
+    scenario::{Scenario, ScenarioIdentity},
+    sim_file::{SimOutput, TruncationReason, load_output},
+    units::{SECONDS, Time, Unit},
+    verification::{DutyCycleReport, failed_checks, max_duty_cycle},
+};
+use serde::{Deserialize, Deserializer, Serialize, de::Error as _};
+
+use crate::errors::{ErrorLog, RunError};
+
+/// File format for summary tables.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    #[default]
+    Csv,
+    /// A JSON array of rows
+    Json,
+    /// One JSON row per line
+    Ndjson,
+}
+
+impl OutputFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            OutputFormat::Csv => "csv",
+            OutputFormat::Json => "json",
+            OutputFormat::Ndjson => "ndjson",
+        }
+    }
+}
+
+/// A row of a summary table. JSON output uses the row as it is, CSV output uses [`TableRow::csv_row`].
+pub trait TableRow: Serialize {
+    type Csv: Serialize;
+
+    fn csv_row(&self) -> Self::Csv;
+}
+
+/// How the scenario identity is stored in CSV tables.
+pub fn identity_string(identity: &ScenarioIdentity) -> String {
+    serde_json::to_string(identity).unwrap()
+}
+
+fn output_writer(maybe_path: Option<PathBuf>) -> Box<dyn Write> {
+    if let Some(out_path) = maybe_path {
+        let file = File::create(out_path).unwrap();
+        Box::new(file) as Box<dyn Write>
+    } else {
+        Box::new(io::stdout())
+    }
+}
+
+/// Writes CSV to a file or stdout if there is no path.
+pub fn write_table<T: Serialize>(maybe_path: Option<PathBuf>, table: &[T]) {
+    let mut writer = csv::Writer::from_writer(output_writer(maybe_path));
+    table.iter().for_each(|x| {
+        writer.serialize(x).unwrap();
+    });
+    writer.flush().unwrap();
+}
+
+/// Writes a summary table to a file or stdout if there is no path.
+pub fn write_rows<T: TableRow>(maybe_path: Option<PathBuf>, rows: &[T], format: OutputFormat) {
+    write_rows_to(output_writer(maybe_path), rows, format).unwrap();
+}
+
+pub fn write_rows_to<T: TableRow>(
+    mut writer: impl Write,
+    rows: &[T],
+    format: OutputFormat,
+) -> io::Result<()> {
+    match format {
+        OutputFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(writer);
+            for row in rows {
+                writer.serialize(row.csv_row())?;
+            }
+            writer.flush()
+        }
+        OutputFormat::Json => {
+            serde_json::to_writer_pretty(&mut writer, rows)?;
+            writeln!(writer)
+        }
+        OutputFormat::Ndjson => {
+            for row in rows {
+                serde_json::to_writer(&mut writer, row)?;
+                writeln!(writer)?;
+            }
+            writer.flush()
+        }
+    }
+}
+
+/// Name duty cycle failures are reported under, as it isn't one of the harness checks
+#[derive(Debug)]
+struct DutyCycleWithin;
+
+/// With a `duty_cycle` limit the busiest one hour window is added to the entry,
+/// and going over the limit is a verification error.
+pub fn make_table_entry(
+    no_verify: bool,
+    verbose: bool,
+    duty_cycle: Option<f64>,
+    results: SimOutput,
+    errors: &ErrorLog,
+) -> TableEntry {
+    let scenario_file = results.complete_identity.scenario_identity.create();
+    make_table_entry_with_scenario(no_verify, verbose, duty_cycle, results, &scenario_file, errors)
+}
+
+/// Same as [`make_table_entry`] for when the scenario the results came from is already at hand,
+/// saving creating it again from its identity.
+pub fn make_table_entry_with_scenario(
+    no_verify: bool,
+    verbose: bool,
+    duty_cycle: Option<f64>,
+    results: SimOutput,
+    scenario_file: &Scenario,
+    errors: &ErrorLog,
+) -> TableEntry {
+    let frogcore::sim_file::OutputIdentity {
+        scenario_identity: scenario,
+        model_id,
+        simulation_seed: random_seed,
+        sim_version,
+        ..
+    } = &results.complete_identity;
+
+    let analysis = CompleteAnalysis::new(results.clone(), scenario_file.clone());
+
+    let first_message = scenario_file
+        .messages
+        .iter()
+        .map(|x| x.generate_time)
+        .min_by(Time::total_cmp);
+
+    let last_message = scenario_file
+        .messages
+        .iter()
+        .map(|x| x.generate_time)
+        .max_by(Time::total_cmp);
+
+    let messaging_time =
+        last_message.unwrap_or(0.0 * SECONDS) - first_message.unwrap_or(0.0 * SECONDS);
+    let messaging_time = messaging_time.seconds();
+
+    let pathloss_param = {
+        use frogcore::simulation::models::PathlossModel::*;
+
+        match scenario_file.model.path_loss() {
+            NoPathloss(_) => "None".to_owned(),
+            AdjustedFreeSpacePathLoss(free_space_path_loss, ..) => {
+                format!("Log {:.6}", free_space_path_loss.distance_exponent)
+            }
+            LinearPathLoss(linear_path_loss) => {
+                format!("Linear {:.6}", linear_path_loss.loss_rate.inner())
+            }
+        }
+    };
+
+    // Zero if there were no transmissions to check
+    let busiest_window = duty_cycle.map(|_| {
+        max_duty_cycle(&analysis).unwrap_or(DutyCycleReport {
+            node_id: 0,
+            window_start: 0.0 * SECONDS,
+            utilisation: 0.0,
+        })
+    });
+
+    let mut entry = TableEntry {
+        scenario_identity: scenario.clone(),
+        model_identity: model_id.clone(),
+        sim_version: sim_version.clone(),
+        seed: *random_seed,
+        avg_reception: analysis.reception_analysis.average_reception_rate,
+        min_reception: analysis.reception_analysis.min_reception_rate,
+        max_reception: analysis.reception_analysis.max_reception_rate,
+        total_transmissions: analysis.transmissions.len(),
+        total_airtime: analysis.total_airtime,
+        end_time: analysis.end_time,
+        avg_avg_latency: analysis.reception_analysis.avg_avg_latency.seconds(),
+        min_avg_latency: analysis.reception_analysis.min_avg_latency.seconds(),
+        max_avg_latency: analysis.reception_analysis.max_avg_latency.seconds(),
+        generated_messages: scenario_file.messages.len(),
+        messaging_time,
+        pathloss_param,
+        l120_score: analysis.reception_analysis.l120_score.seconds(),
+        l600_score: analysis.reception_analysis.l600_score.seconds(),
+        l6000_score: analysis.reception_analysis.l6000_score.seconds(),
+        all_packet_uniqueness: analysis.reception_analysis.all_packet_uniqueness,
+        message_packet_uniqueness: analysis.reception_analysis.message_packet_uniqueness,
+        phantom_uniqueness: analysis.reception_analysis.phantom_uniqueness,
+        message_reception_directness: analysis.reception_analysis.message_reception_directness,
+        reception_directness: analysis.reception_analysis.reception_directness,
+        message_reception_unique_directness: analysis
+            .reception_analysis
+            .message_reception_unique_directness,
+        reception_unique_directness: analysis.reception_analysis.reception_unique_directness,
+        message_transmission_directness: analysis
+            .reception_analysis
+            .message_transmission_directness,
+        transmission_directness: analysis.reception_analysis.transmission_directness,
+        message_transmission_unique_directness: analysis
+            .reception_analysis
+            .message_transmission_unique_directness,
+        transmission_unique_directness: analysis.reception_analysis.transmission_unique_directness,
+        emergency_result: analysis.reception_analysis.emergency_result,
+        transmission_sent_events: analysis.transmission_sent_events,
+        transmission_received_events: analysis.transmission_received_events,
+        transmission_blocked_events: analysis.transmission_blocked_events,
+        global_latency: analysis.reception_analysis.global_latency.seconds(),
+        global_reception_rate: analysis.reception_analysis.global_reception_rate,
+        t120_reception: analysis.reception_analysis.t120_reception,
+        t600_reception: analysis.reception_analysis.t600_reception,
+        t1800_reception: analysis.reception_analysis.t1800_reception,
+        t6000_reception: analysis.reception_analysis.t6000_reception,
+        gateway_latency: analysis.reception_analysis.gateway_latency.seconds(),
+        gateway_reception: analysis.reception_analysis.gateway_reception,
+        sim_wall_ms: results.stats.wall_time_ms,
+        events_processed: results.stats.events_processed,
+        peak_transmissions: results.stats.peak_em_field,
+        top_sf_airtime_share: analysis.top_sf_airtime_share(),
+        uplink_latency: analysis.reception_analysis.uplink_latency.seconds(),
+        uplink_reception: analysis.reception_analysis.uplink_reception_rate,
+        deadline_hit_rate: analysis.reception_analysis.deadline_hit_rate,
+        timed_out: false,
+        mean_degree: analysis.degree_stats.mean_degree,
+        isolated_nodes: analysis.degree_stats.isolated_nodes,
+        connected: analysis.degree_stats.connected,
+        mean_contact_ratio: analysis.degree_stats.mean_contact_ratio(),
+        busiest_gateway_share: analysis.reception_analysis.busiest_gateway_share(),
+        global_latency_from_first_generation: analysis
+            .reception_analysis
+            .global_latency_from_first_generation
+            .seconds(),
+        max_duty_cycle: busiest_window.map(|x| x.utilisation),
+        duty_cycle_node: busiest_window.map(|x| x.node_id),
+        duty_cycle_window_start: busiest_window.map(|x| x.window_start.seconds()),
+    };
+
+    if let Some(TruncationReason::MaxWallTime(_)) = results.complete_identity.truncated {
+        entry.set_timed_out();
+    }
+
+    if !no_verify {
+        for check in failed_checks(&analysis) {
+            errors.push(RunError::verification(
+                scenario.clone(),
+                check,
+                format!("{model_id} with seed {random_seed}"),
+            ));
+        }
+
+        if let (Some(limit), Some(window)) = (duty_cycle, busiest_window)
+            && window.utilisation > limit
+        {
+            errors.push(RunError::verification(
+                scenario.clone(),
+                DutyCycleWithin,
+                format!("{model_id} with seed {random_seed}, {window}"),
+            ));
+        }
+    }
+
+    if verbose {
+        printout(scenario_file.clone(), results);
+    }
+
+    entry
+}
+
+/// Loads a results file or every results file in a directory.
+/// Files that fail to load are recorded in `errors` and skipped.
+pub fn load_result_files(results_path: PathBuf, errors: &ErrorLog) -> Vec<SimOutput> {
+    let mut sim_results: Vec<SimOutput> = Vec::new();
+
+    if results_path.is_file() {
+        match load_output(results_path.clone()) {
+            Ok(loaded) => sim_results.push(loaded),
+            Err(e) => errors.push(RunError::load(results_path, e)),
+        }
+    } else {
+        let entries = match read_dir(&results_path) {
+            Ok(entries) => entries,
+            Err(e) => {
+                errors.push(RunError::load(results_path, e));
+                return sim_results;
+            }
+        };
+
+        for thing in entries {
+            let file = match thing {
+                Ok(file) => file,
+                Err(e) => {
+                    errors.push(RunError::load(&results_path, e));
+                    continue;
+                }
+            };
+
+            match load_output(file.path()) {
+                Ok(loaded) => {
+                    sim_results.push(loaded);
+                }
+                Err(e) => errors.push(RunError::load(file.path(), e)),
+            };
+        }
+    }
+    sim_results
+}
+
+/// One simulation run in a summary table.
+///
+/// CSV can't hold nested values so CSV tables use [`CsvTableEntry`] where the scenario identity
+/// is a JSON string.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TableEntry<S = ScenarioIdentity> {
+    pub scenario_identity: S,
+    pub model_identity: String,
+    pub sim_version: String,
+    pub seed: u64,
+    pub pathloss_param: String,
+    pub generated_messages: usize,
+    pub messaging_time: f64,
+    pub avg_reception: f64,
+    pub min_reception: f64,
+    pub max_reception: f64,
+    pub avg_avg_latency: f64,
+    pub min_avg_latency: f64,
+    pub max_avg_latency: f64,
+    pub total_transmissions: usize,
+    pub total_airtime: f64,
+    pub end_time: f64,
+    pub l120_score: f64,
+    pub l600_score: f64,
+    pub l6000_score: f64,
+    pub all_packet_uniqueness: f64,
+    pub message_packet_uniqueness: f64,
+    pub phantom_uniqueness: f64,
+
+    pub global_latency: f64,
+    pub global_reception_rate: f64,
+
+    pub t120_reception: f64,
+    pub t600_reception: f64,
+    pub t1800_reception: f64,
+    pub t6000_reception: f64,
+
+    pub message_reception_directness: f64,
+    pub reception_directness: f64,
+
+    pub message_reception_unique_directness: f64,
+    pub reception_unique_directness: f64,
+
+    pub message_transmission_directness: f64,
+    pub transmission_directness: f64,
+
+    pub message_transmission_unique_directness: f64,
+    pub transmission_unique_directness: f64,
+
+    #[serde(deserialize_with = "emergency_from_csv")]
+    pub emergency_result: EmergencyResult,
+
+    pub transmission_sent_events: usize,
+    pub transmission_received_events: usize,
+    pub transmission_blocked_events: usize,
+
+    pub gateway_latency: f64,
+    pub gateway_reception: f64,
+
+    pub sim_wall_ms: f64,
+    pub events_processed: u64,
+    /// Most transmissions the simulation held in its window of recent ones at once,
+    /// see [`ExecutionStats::peak_em_field`](frogcore::sim_file::ExecutionStats::peak_em_field).
+    #[serde(default)]
+    pub peak_transmissions: usize,
+
+    /// Fraction of the airtime used by the highest spreading factor transmitted with.
+    #[serde(default)]
+    pub top_sf_airtime_share: f64,
+
+    #[serde(default)]
+    pub uplink_latency: f64,
+    #[serde(default)]
+    pub uplink_reception: f64,
+
+    /// Empty if no messages had deadlines.
+    #[serde(default)]
+    pub deadline_hit_rate: Option<f64>,
+
+    /// The run was stopped for taking too long in wall-clock time,
+    /// so its metrics are NaN rather than describing part of a run.
+    #[serde(default)]
+    pub timed_out: bool,
+
+    /// Neighbours per node at the start of the run, from the scenario rather than the run.
+    #[serde(default)]
+    pub mean_degree: f64,
+    #[serde(default)]
+    pub isolated_nodes: usize,
+    #[serde(default)]
+    pub connected: bool,
+    /// Mean fraction of the run each node had a neighbour, low for sparse moving scenarios.
+    #[serde(default)]
+    pub mean_contact_ratio: f64,
+
+    /// Fraction of the messages that reached a gateway which the busiest gateway heard first.
+    #[serde(default)]
+    pub busiest_gateway_share: f64,
+
+    /// `global_latency` counted from each message's first generation rather than the repeat
+    /// that caused it arriving, which is what `global_latency` was before.
+    #[serde(default)]
+    pub global_latency_from_first_generation: f64,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_duty_cycle: Option<f64>,
+    /// Node and start in seconds of the one hour window `max_duty_cycle` comes from.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duty_cycle_node: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duty_cycle_window_start: Option<f64>,
+}
+
+pub type CsvTableEntry = TableEntry<String>;
+
+impl<S> TableEntry<S> {
+    pub fn map_identity<T>(self, f: impl FnOnce(S) -> T) -> TableEntry<T> {
+        TableEntry {
+            scenario_identity: f(self.scenario_identity),
+            model_identity: self.model_identity,
+            sim_version: self.sim_version,
+            seed: self.seed,
+            pathloss_param: self.pathloss_param,
+            generated_messages: self.generated_messages,
+            messaging_time: self.messaging_time,
+            avg_reception: self.avg_reception,
+            min_reception: self.min_reception,
+            max_reception: self.max_reception,
+            avg_avg_latency: self.avg_avg_latency,
+            min_avg_latency: self.min_avg_latency,
+            max_avg_latency: self.max_avg_latency,
+            total_transmissions: self.total_transmissions,
+            total_airtime: self.total_airtime,
+            end_time: self.end_time,
+            l120_score: self.l120_score,
+            l600_score: self.l600_score,
+            l6000_score: self.l6000_score,
+            all_packet_uniqueness: self.all_packet_uniqueness,
+            message_packet_uniqueness: self.message_packet_uniqueness,
+            phantom_uniqueness: self.phantom_uniqueness,
+            global_latency: self.global_latency,
+            global_reception_rate: self.global_reception_rate,
+            t120_reception: self.t120_reception,
+            t600_reception: self.t600_reception,
+            t1800_reception: self.t1800_reception,
+            t6000_reception: self.t6000_reception,
+            message_reception_directness: self.message_reception_directness,
+            reception_directness: self.reception_directness,
+            message_reception_unique_directness: self.message_reception_unique_directness,
+            reception_unique_directness: self.reception_unique_directness,
+            message_transmission_directness: self.message_transmission_directness,
+            transmission_directness: self.transmission_directness,
+            message_transmission_unique_directness: self.message_transmission_unique_directness,
+            transmission_unique_directness: self.transmission_unique_directness,
+            emergency_result: self.emergency_result,
+            transmission_sent_events: self.transmission_sent_events,
+            transmission_received_events: self.transmission_received_events,
+            transmission_blocked_events: self.transmission_blocked_events,
+            gateway_latency: self.gateway_latency,
+            gateway_reception: self.gateway_reception,
+            sim_wall_ms: self.sim_wall_ms,
+            events_processed: self.events_processed,
+            peak_transmissions: self.peak_transmissions,
+            top_sf_airtime_share: self.top_sf_airtime_share,
+            uplink_latency: self.uplink_latency,
+            uplink_reception: self.uplink_reception,
+            deadline_hit_rate: self.deadline_hit_rate,
+            timed_out: self.timed_out,
+            mean_degree: self.mean_degree,
+            isolated_nodes: self.isolated_nodes,
+            connected: self.connected,
+            mean_contact_ratio: self.mean_contact_ratio,
+            busiest_gateway_share: self.busiest_gateway_share,
+            global_latency_from_first_generation: self.global_latency_from_first_generation,
+            max_duty_cycle: self.max_duty_cycle,
+            duty_cycle_node: self.duty_cycle_node,
+            duty_cycle_window_start: self.duty_cycle_window_start,
+        }
+    }
+
+    /// Marks the run as timed out and sets its metrics to NaN.
+    /// Counts are left as they were when it stopped, and the run's own stats are kept.
+    fn set_timed_out(&mut self) {
+        self.timed_out = true;
+
+        for metric in [
+            &mut self.avg_reception,
+            &mut self.min_reception,
+            &mut self.max_reception,
+            &mut self.avg_avg_latency,
+            &mut self.min_avg_latency,
+            &mut self.max_avg_latency,
+            &mut self.total_airtime,
+            &mut self.l120_score,
+            &mut self.l600_score,
+            &mut self.l6000_score,
+            &mut self.all_packet_uniqueness,
+            &mut self.message_packet_uniqueness,
+            &mut self.phantom_uniqueness,
+            &mut self.global_latency,
+            &mut self.global_reception_rate,
+            &mut self.t120_reception,
+            &mut self.t600_reception,
+            &mut self.t1800_reception,
+            &mut self.t6000_reception,
+            &mut self.message_reception_directness,
+            &mut self.reception_directness,
+            &mut self.message_reception_unique_directness,
+            &mut self.reception_unique_directness,
+            &mut self.message_transmission_directness,
+            &mut self.transmission_directness,
+            &mut self.message_transmission_unique_directness,
+            &mut self.transmission_unique_directness,
+            &mut self.gateway_latency,
+            &mut self.gateway_reception,
+            &mut self.top_sf_airtime_share,
+            &mut self.uplink_latency,
+            &mut self.uplink_reception,
+            &mut self.busiest_gateway_share,
+            &mut self.global_latency_from_first_generation,
+        ] {
+            *metric = f64::NAN;
+        }
+
+        for metric in [
+            &mut self.deadline_hit_rate,
+            &mut self.max_duty_cycle,
+            &mut self.duty_cycle_window_start,
+        ] {
+            *metric = metric.map(|_| f64::NAN);
+        }
+        self.duty_cycle_node = None;
+    }
+}
+
+impl TableRow for TableEntry {
+    type Csv = CsvTableEntry;
+
+    fn csv_row(&self) -> CsvTableEntry {
+        self.clone().map_identity(|x| identity_string(&x))
+    }
+}
+
+impl CsvTableEntry {
+    pub fn parse_identity(self) -> serde_json::Result<TableEntry> {
+        let identity = serde_json::from_str(&self.scenario_identity)?;
+        Ok(self.map_identity(|_| identity))
+    }
+}
+
+/// CSV writes [`EmergencyResult::Latency`] as only the latency in seconds.
+fn emergency_from_csv<'de, D>(deserializer: D) -> Result<EmergencyResult, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let field = String::deserialize(deserializer)?;
+
+    match field.as_str() {
+        "NotEmergency" => Ok(EmergencyResult::NotEmergency),
+        "NotRecieved" => Ok(EmergencyResult::NotRecieved),
+        latency => latency
+            .parse::<f64>()
+            .map(|x| EmergencyResult::Latency(x * SECONDS))
+            .map_err(D::Error::custom),
+    }
+}
+
+fn printout(scenario: Scenario, results: SimOutput) {
+    let frogcore::sim_file::OutputIdentity {
+        scenario_identity: scenario_id,
+        model_id,
+        simulation_seed: random_seed,
+        sim_version,
+        truncated,
+        scenario_hash,
+    } = &results.complete_identity;
+
+    let analysis = CompleteAnalysis::new(results.clone(), scenario.clone());
+
+    println!();
+    println!("{scenario_id:?} with node model {model_id}");
+    println!("random seed: {random_seed}   simulation version: {sim_version}");
+    if let Some(reason) = truncated {
+        println!("Truncated: the run was stopped early as it {reason}");
+    }
+    if let Some(hash) = scenario_hash {
+        let current = scenario.content_hash();
+        if *hash == current {
+            println!("scenario hash: {hash:016x}");
+        } else {
+            println!("scenario hash: {hash:016x}, but it now generates as {current:016x}");
+        }
+    }
+    println!(
+        "Reception Rate: avg({:.4})  min({:.4})  max({:.4})",
+        analysis.reception_analysis.average_reception_rate,
+        analysis.reception_analysis.min_reception_rate,
+        analysis.reception_analysis.max_reception_rate
+    );
+    println!(
+        "Total Transmissions: {}  Total Airtime: {:.4} (Simulation End Time: {:.4})",
+        analysis.transmissions.len(),
+        analysis.total_airtime,
+        analysis.end_time,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::Path};
+
+    use frogcore::{
+        node::ModelSelection,
+        simulation::run_simulation,
+        units::MINS,
+    };
+
+    use super::*;
+
+    /// Set `BLESS_GOLDEN` to rewrite the golden files after an intended change.
+    fn check_golden(name: &str, actual: &[u8]) {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures/golden")
+            .join(name);
+
+        if std::env::var_os("BLESS_GOLDEN").is_some() {
+            fs::write(&path, actual).unwrap();
+        }
+
+        let expected = fs::read(&path).unwrap();
+        assert!(
+            expected == actual,
+            "{name} does not match golden file:\n{}",
+            String::from_utf8_lossy(actual)
+        );
+    }
+
+    #[test]
+    fn output_formats_golden() {
+        let identity = ScenarioIdentity::for_test(5, 5, 1.0 * MINS, 3);
+
+        let results = run_simulation(
+            10,
+            identity.create(),
+            ModelSelection::BasicFlood.into(),
+            false,
+        );
+        let mut rows = [make_table_entry(true, false, None, results, &ErrorLog::new())];
+        // Wall time differs between runs
+        rows[0].sim_wall_ms = 0.0;
+
+        for format in [OutputFormat::Csv, OutputFormat::Json, OutputFormat::Ndjson] {
+            let mut bytes = Vec::new();
+            write_rows_to(&mut bytes, &rows, format).unwrap();
+            check_golden(&format!("single_run.{}", format.extension()), &bytes);
+        }
+    }
+}
@@ -1,39 +1,566 @@
 use egui::{
-    Align, Color32, CornerRadius, DragValue, Frame, Label, Layout, Pos2, RichText, ScrollArea,
-    Stroke, Widget, style::WidgetVisuals,
+    Align, Color32, CornerRadius, DragValue, Frame, Label, Layout, RichText, ScrollArea, Stroke,
+    Widget, style::WidgetVisuals,
 };
 
-use std::collections::{HashMap, HashSet};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    fs::File,
+    sync::Arc,
+};
+
+use egui_extras::{Column, TableBuilder};
 
 use macroquad::prelude::*;
 
 use frogcore::{
-    analysis::{CompleteAnalysis, TransmissionGraph, WantedMessage, create_transmission_graphs},
-    node::NodeModel,
+    analysis::{
+        CompleteAnalysis, DegreeStats, DistanceLatency, FailureReport, GatewayStats, MessageHop,
+        SfBucketStats,
+        TransmissionGraph, WantedMessage, create_transmission_graphs, delivery_failure_report,
+        trace_message,
+    },
+    node::{BasicHeaderInfo, CustomContent, NodeModel},
     node_location::NodeLocation,
-    scenario::{Scenario, ScenarioNodeSettings},
-    sim_file::SimOutput,
+    scenario::{MessageMarker, Scenario},
+    sim_file::{SimOutput, write_trace_csv},
     simulation::{
-        LiveSimulation, MessageContent,
-        data_structs::{LogItem, Transmission},
-        run_simulation,
+        LiveSimulation, MessageContent, RunOptions,
+        data_structs::{
+            LogContent, LogItem, LogLevel, LogSource, NodeSettings, StepResult, TraceDirection,
+            TraceEntry, Transmission,
+        },
+        run_simulation_with,
     },
     units::{METRES, Time},
 };
 
-use crate::scene::{SceneData, point_to_vec};
-use crate::{Inspectable, convert_rect, get_event_window, short_content};
+use crate::components::StepChart;
+use crate::scene::{
+    CategoryColouring, ImageExport, NodeColours, SceneData, heat_colour, point_to_vec,
+};
+use crate::{Inspectable, convert_rect, get_event_window, scenario_name, short_content};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum InspectorTab {
     Overview,
     State,
+    Charts,
+    Trace,
+}
+
+/// What the node fill colours show in the scene.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ColourMode {
+    Off,
+    Category(CategoryColouring),
+    Reception,
+    Latency,
+}
+
+impl ColourMode {
+    const ALL: [ColourMode; 6] = [
+        ColourMode::Off,
+        ColourMode::Category(CategoryColouring::Gateway),
+        ColourMode::Category(CategoryColouring::Role),
+        ColourMode::Category(CategoryColouring::Sf),
+        ColourMode::Reception,
+        ColourMode::Latency,
+    ];
+
+    fn title(self) -> &'static str {
+        match self {
+            ColourMode::Off => "Off",
+            ColourMode::Category(category) => category.title(),
+            ColourMode::Reception => "Reception Rate",
+            ColourMode::Latency => "Average Latency",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum NodeColumn {
+    Id,
+    Gateway,
+    Reception,
+    Latency,
+    Wanted,
+    Received,
+    Sent,
+    Airtime,
+    Blocked,
+}
+
+impl NodeColumn {
+    const ALL: [NodeColumn; 9] = [
+        NodeColumn::Id,
+        NodeColumn::Gateway,
+        NodeColumn::Reception,
+        NodeColumn::Latency,
+        NodeColumn::Wanted,
+        NodeColumn::Received,
+        NodeColumn::Sent,
+        NodeColumn::Airtime,
+        NodeColumn::Blocked,
+    ];
+
+    fn title(self) -> &'static str {
+        match self {
+            NodeColumn::Id => "Node",
+            NodeColumn::Gateway => "Gateway",
+            NodeColumn::Reception => "Reception",
+            NodeColumn::Latency => "Avg Latency",
+            NodeColumn::Wanted => "Wanted",
+            NodeColumn::Received => "Received",
+            NodeColumn::Sent => "Sent",
+            NodeColumn::Airtime => "Airtime",
+            NodeColumn::Blocked => "Blocked",
+        }
+    }
+}
+
+/// One row of the node metrics table.
+#[derive(Debug, Clone)]
+struct NodeRow {
+    id: usize,
+    is_gateway: bool,
+    reception_rate: f64,
+    /// `None` if the node received none of the messages it wanted
+    avg_latency: Option<f64>,
+    wanted: usize,
+    received: usize,
+    sent: usize,
+    airtime: f64,
+    blocked: usize,
+}
+
+impl NodeRow {
+    fn sort_key(&self, column: NodeColumn) -> f64 {
+        match column {
+            NodeColumn::Id => self.id as f64,
+            NodeColumn::Gateway => self.is_gateway as u8 as f64,
+            NodeColumn::Reception => self.reception_rate,
+            NodeColumn::Latency => self.avg_latency.unwrap_or(f64::INFINITY),
+            NodeColumn::Wanted => self.wanted as f64,
+            NodeColumn::Received => self.received as f64,
+            NodeColumn::Sent => self.sent as f64,
+            NodeColumn::Airtime => self.airtime,
+            NodeColumn::Blocked => self.blocked as f64,
+        }
+    }
+}
+
+/// Node positions of a moving scenario sampled at a fixed interval so trails
+/// don't have to query [`NodeLocation::location`] every frame.
+struct MovementTrails {
+    /// Seconds between samples
+    step: f64,
+    /// Outer vec is sample index, inner vec is node id
+    samples: Vec<Vec<frogcore::node_location::Point>>,
+    /// Times of the scenario's timepoints in seconds
+    timepoint_times: Vec<f64>,
+}
+
+impl MovementTrails {
+    /// Upper bound on stored positions to keep memory reasonable for long runs.
+    const MAX_SAMPLES: f64 = 500_000.0;
+
+    /// None unless nodes actually move.
+    fn new(node_locations: &NodeLocation, end_time: f64) -> Option<MovementTrails> {
+        let NodeLocation::Points(points) = node_locations else {
+            return None;
+        };
+        if points.data.len() < 2 {
+            return None;
+        }
+
+        let node_count = node_locations.len();
+        let step = (end_time * node_count as f64 / Self::MAX_SAMPLES).max(1.0);
+        let sample_count = (end_time / step).ceil() as usize + 1;
+
+        let samples = (0..sample_count)
+            .map(|i| {
+                let time = Time::from_seconds(i as f64 * step);
+                (0..node_count)
+                    .map(|id| node_locations.location(time, id).unwrap())
+                    .collect()
+            })
+            .collect();
+
+        Some(MovementTrails {
+            step,
+            samples,
+            timepoint_times: points.data.iter().map(|x| x.time.seconds()).collect(),
+        })
+    }
+
+    /// Sampled positions of a node from `length` seconds before `time` up to `time`.
+    fn trail(&self, id: usize, time: f64, length: f64) -> impl Iterator<Item = Vec2> + '_ {
+        let first = ((time - length).max(0.0) / self.step).ceil() as usize;
+        let last = ((time / self.step).floor() as usize).min(self.samples.len() - 1);
+
+        self.samples[first.min(last + 1)..=last]
+            .iter()
+            .map(move |x| point_to_vec(x[id]))
+    }
+
+    /// Index of the first timepoint after `time`.
+    fn next_timepoint(&self, time: f64) -> Option<usize> {
+        self.timepoint_times.iter().position(|&x| x > time)
+    }
+}
+
+/// Listed by the help window opened with `?`.
+const SHORTCUTS: [(&str, &str); 8] = [
+    ("Space", "Play / pause"),
+    ("Left / Right", "Previous / next sim event"),
+    ("Shift + Left / Right", "Jump back / forward 10s"),
+    ("Up / Down", "Inspect previous / next node"),
+    ("T", "Next transmission involving the inspected node"),
+    ("Esc", "Clear selection"),
+    ("?", "Show / hide this help"),
+    ("Scroll on timeline", "Zoom the transmission timeline"),
+];
+
+/// What each row of the transmission timeline holds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TimelineRows {
+    Transmitter,
+    Sf,
+}
+
+impl TimelineRows {
+    fn title(self) -> &'static str {
+        match self {
+            TimelineRows::Transmitter => "Transmitter",
+            TimelineRows::Sf => "SF",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum MarkerKind {
+    Generation,
+    Delivery,
+    GatewayReception,
+    Emergency,
+}
+
+impl MarkerKind {
+    fn colour(self) -> Color32 {
+        match self {
+            MarkerKind::Generation => Color32::GRAY,
+            MarkerKind::Delivery => Color32::LIGHT_BLUE,
+            MarkerKind::GatewayReception => Color32::LIGHT_GREEN,
+            MarkerKind::Emergency => Color32::ORANGE,
+        }
+    }
+
+    fn title(self) -> &'static str {
+        match self {
+            MarkerKind::Generation => "Message generated",
+            MarkerKind::Delivery => "Message delivered",
+            MarkerKind::GatewayReception => "Emergency reached a gateway",
+            MarkerKind::Emergency => "Emergency generated",
+        }
+    }
+}
+
+/// Point of interest drawn above the time sliders.
+#[derive(Debug, Clone, Copy)]
+struct TimelineMarker {
+    time: f64,
+    kind: MarkerKind,
+}
+
+/// A node's state before its latest event and at the current time, as inspector values.
+struct StateDiff {
+    node: usize,
+    time: f64,
+    /// Time of the event before the latest one
+    previous: f64,
+    values: Result<(serde_inspector::Value, serde_inspector::Value), String>,
+}
+
+/// Ticks for every message generation, delivery to a target, emergency generation,
+/// and reception of an emergency by a gateway, in time order.
+fn timeline_markers(scenario: &Scenario, analysis: &CompleteAnalysis) -> Vec<TimelineMarker> {
+    let is_emergency = |id: usize| {
+        scenario.messages[id]
+            .markers
+            .contains(&MessageMarker::Emergency)
+    };
+
+    let generations = scenario.messages.iter().enumerate().flat_map(|(id, x)| {
+        let kind = match is_emergency(id) {
+            true => MarkerKind::Emergency,
+            false => MarkerKind::Generation,
+        };
+
+        (0..x.num_generations).map(move |generation| TimelineMarker {
+            time: (x.generate_time + x.generation_spacing * generation as f64).seconds(),
+            kind,
+        })
+    });
+
+    let transmissions: HashMap<u32, &Transmission> =
+        analysis.transmissions.iter().map(|x| (x.id, x)).collect();
+
+    let receptions = analysis.sim_events.iter().filter_map(|event| {
+        let LogContent::TransmissionReceived {
+            receiver_id,
+            transmission_id,
+            ..
+        } = event.content
+        else {
+            return None;
+        };
+
+        let transmission = transmissions.get(&transmission_id)?;
+        let MessageContent::GeneratedMessage(message_id) = transmission.message_content else {
+            return None;
+        };
+
+        (analysis.node_settings[receiver_id].is_gateway && is_emergency(message_id)).then_some(
+            TimelineMarker {
+                time: event.time.seconds(),
+                kind: MarkerKind::GatewayReception,
+            },
+        )
+    });
+
+    let deliveries = analysis.sim_events.iter().filter_map(|event| match event.content {
+        LogContent::MessageDelivered { .. } => Some(TimelineMarker {
+            time: event.time.seconds(),
+            kind: MarkerKind::Delivery,
+        }),
+        _ => None,
+    });
+
+    let mut markers: Vec<_> = generations.chain(receptions).chain(deliveries).collect();
+    markers.sort_by(|a, b| a.time.total_cmp(&b.time));
+    markers
+}
+
+/// Which entries an event list shows.
+/// Kept on the panel so it survives scrubbing the timeline.
+#[derive(Debug, Clone)]
+struct EventFilter {
+    /// Least important level that is still shown
+    level: LogLevel,
+    /// Case insensitive substring of the formatted content
+    text: String,
+    /// Node that must be the sender or receiver, only offered for the sim events
+    node: Option<usize>,
+}
+
+impl EventFilter {
+    fn new() -> EventFilter {
+        EventFilter {
+            level: LogLevel::Trace,
+            text: String::new(),
+            node: None,
+        }
+    }
+
+    fn matches(&self, item: &LogItem) -> bool {
+        if item.log_level > self.level {
+            return false;
+        }
+
+        if let Some(node) = self.node
+            && !item.content.involves_node(node)
+            && !matches!(item.source, LogSource::Node(source) if source == node)
+        {
+            return false;
+        }
+
+        self.text.is_empty()
+            || item
+                .content
+                .to_string()
+                .to_lowercase()
+                .contains(&self.text.to_lowercase())
+    }
+
+    fn controls(&mut self, id_salt: &str, with_node: bool, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Level");
+            egui::ComboBox::from_id_salt(id_salt)
+                .selected_text(format!("{:?}", self.level))
+                .width(70.0)
+                .show_ui(ui, |ui| {
+                    for level in LogLevel::ALL {
+                        ui.selectable_value(&mut self.level, level, format!("{level:?}"));
+                    }
+                });
+
+            ui.add(
+                egui::TextEdit::singleline(&mut self.text)
+                    .hint_text("Search")
+                    .desired_width(100.0),
+            );
+
+            if with_node {
+                let mut enabled = self.node.is_some();
+                let mut node = self.node.unwrap_or(0);
+
+                ui.checkbox(&mut enabled, "Node");
+                ui.add_enabled(enabled, DragValue::new(&mut node));
+
+                self.node = enabled.then_some(node);
+            }
+        });
+    }
+}
+
+/// Kinds of content the transmissions shown can be filtered by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ContentClass {
+    Generated,
+    RoutingControl,
+    Other,
+}
+
+impl ContentClass {
+    const ALL: [ContentClass; 3] = [
+        ContentClass::Generated,
+        ContentClass::RoutingControl,
+        ContentClass::Other,
+    ];
+
+    fn of(content: &MessageContent) -> ContentClass {
+        match content {
+            MessageContent::GeneratedMessage(_) => ContentClass::Generated,
+            MessageContent::NodeMessage(
+                CustomContent::RoutingMessage { .. }
+                | CustomContent::GlobalAck { .. }
+                | CustomContent::UplinkAck { .. },
+            ) => ContentClass::RoutingControl,
+            _ => ContentClass::Other,
+        }
+    }
+
+    fn title(self) -> &'static str {
+        match self {
+            ContentClass::Generated => "Generated messages",
+            ContentClass::RoutingControl => "Routing and acks",
+            ContentClass::Other => "Other",
+        }
+    }
+}
+
+/// What a transmission is filtered on, worked out once when the results are opened.
+#[derive(Debug, Clone, Copy)]
+struct TransmissionTags {
+    class: ContentClass,
+    sender: usize,
+    /// Generated message carried, if any
+    message: Option<usize>,
+}
+
+impl TransmissionTags {
+    fn new(transmission: &Transmission) -> TransmissionTags {
+        TransmissionTags {
+            class: ContentClass::of(&transmission.message_content),
+            sender: transmission.transmitter_id,
+            message: match transmission.message_content {
+                MessageContent::GeneratedMessage(id) => Some(id),
+                _ => None,
+            },
+        }
+    }
+}
+
+/// Which transmissions the scene and transmission timeline show.
+#[derive(Debug, Clone)]
+struct TransmissionFilter {
+    /// Shown classes, indexed like [`ContentClass::ALL`]
+    classes: [bool; 3],
+    sender: Option<usize>,
+    /// Only those carrying the inspected message, when one is inspected
+    inspected_message: bool,
+}
+
+impl TransmissionFilter {
+    fn new() -> TransmissionFilter {
+        TransmissionFilter {
+            classes: [true; 3],
+            sender: None,
+            inspected_message: false,
+        }
+    }
+
+    fn is_active(&self) -> bool {
+        self.classes.contains(&false) || self.sender.is_some() || self.inspected_message
+    }
+
+    fn matches(&self, tags: &TransmissionTags, inspected: Option<usize>) -> bool {
+        let class = ContentClass::ALL.iter().position(|x| *x == tags.class).unwrap();
+
+        self.classes[class]
+            && self.sender.is_none_or(|x| x == tags.sender)
+            && (!self.inspected_message || inspected.is_none() || tags.message == inspected)
+    }
+
+    fn controls(&mut self, node_count: usize, ui: &mut egui::Ui) {
+        for (class, shown) in ContentClass::ALL.iter().zip(&mut self.classes) {
+            ui.checkbox(shown, class.title());
+        }
+
+        ui.separator();
+        ui.horizontal(|ui| {
+            let mut enabled = self.sender.is_some();
+            let mut sender = self.sender.unwrap_or(0);
+
+            ui.checkbox(&mut enabled, "Sender");
+            ui.add_enabled(
+                enabled,
+                DragValue::new(&mut sender).range(0..=node_count.saturating_sub(1)),
+            );
+
+            self.sender = enabled.then_some(sender);
+        });
+
+        ui.checkbox(&mut self.inspected_message, "Only the inspected message")
+            .on_hover_text(
+                "Transmissions carrying the traced message, \
+                 or the message of the inspected transmission",
+            );
+    }
+}
+
+/// Titles of the whole run figures from [`headline_values`].
+pub(crate) const HEADLINE_TITLES: [&str; 8] = [
+    "Global Reception Rate",
+    "Average Reception Rate",
+    "Min Reception Rate",
+    "T600 Reception",
+    "Global Latency (s)",
+    "Average Latency (s)",
+    "Message Uniqueness",
+    "Transmissions",
+];
+
+/// Whole run figures used to compare runs, in the order of [`HEADLINE_TITLES`].
+pub(crate) fn headline_values(analysis: &CompleteAnalysis) -> [f64; 8] {
+    let reception = &analysis.reception_analysis;
+    [
+        reception.global_reception_rate,
+        reception.average_reception_rate,
+        reception.min_reception_rate,
+        reception.t600_reception,
+        reception.global_latency.seconds(),
+        reception.avg_avg_latency.seconds(),
+        reception.message_packet_uniqueness,
+        analysis.transmissions.len() as f64,
+    ]
 }
 
 pub struct AnalysisPanel {
     scene: SceneData,
     node_locations: NodeLocation,
-    node_settings: Vec<ScenarioNodeSettings>,
+    node_settings: Vec<NodeSettings>,
     node_events: Vec<Vec<LogItem>>,
     wanted_messages: Vec<Vec<WantedMessage>>,
     received_messages: Vec<Vec<usize>>,
@@ -41,6 +568,8 @@ pub struct AnalysisPanel {
     sim_events: Vec<LogItem>,
     transmission_graphs: HashMap<u32, TransmissionGraph>,
     transmissions: Vec<Transmission>,
+    transmission_tags: HashMap<u32, TransmissionTags>,
+    transmission_filter: TransmissionFilter,
     inspect_target: Inspectable,
     current_time: f64,
     prev_time: f64,
@@ -54,10 +583,85 @@ pub struct AnalysisPanel {
     inspector_tabs: InspectorTab,
     use_inspector_text_mode: bool,
     live_sim: Option<LiveSimulation>,
+    /// Stepping the live simulation event by event instead of replaying the results
+    live_mode: bool,
+    /// Time the live simulation was last moved to, so scrubbing away from it can be noticed
+    live_synced_time: f64,
+    /// Most recent steps first
+    live_steps: VecDeque<(f64, StepResult)>,
+    /// Scenario and model the results were made with, if they can be run again
+    source: Option<(Arc<Scenario>, NodeModel)>,
+    node_rows: Vec<NodeRow>,
+    /// Column the node table is sorted by and whether it is descending
+    node_sort: (NodeColumn, bool),
+    /// (latency in seconds, fraction of received wanted messages)
+    latency_cdf: Vec<(f64, f64)>,
+    /// Median, 90th and 99th percentile latency in seconds
+    latency_percentiles: [Option<f64>; 3],
+    /// (bucket start in seconds, fraction of the bucket on air)
+    channel_utilisation: Vec<(f64, f64)>,
+    sf_breakdown: Vec<SfBucketStats>,
+    degree_stats: DegreeStats,
+    distance_latency: DistanceLatency,
+    /// Messages each gateway heard before any other gateway, indexed the same as the nodes
+    captured_messages: Vec<Option<GatewayStats>>,
+    colour_mode: ColourMode,
+    /// Message id and hops of the last traced message
+    message_trace: Option<(usize, Vec<MessageHop>)>,
+    /// Scenario the results are for, kept to work out failure reports
+    scenario: Scenario,
+    /// Why the last clicked undelivered message didn't reach its node
+    failure_report: Option<FailureReport>,
+    trails: Option<MovementTrails>,
+    show_trails: bool,
+    /// Seconds of movement shown behind each node
+    trail_length: f64,
+    /// Whole run figures shown when comparing two runs
+    headline: Vec<(&'static str, f64)>,
+    sim_event_filter: EventFilter,
+    node_event_filter: EventFilter,
+    /// Seconds either side of the current time shown by the transmission timeline
+    timeline_span: f64,
+    timeline_rows: TimelineRows,
+    show_shortcuts: bool,
+    image_export: ImageExport,
+    scenario_name: String,
+    timeline_markers: Vec<TimelineMarker>,
+    /// Strip width the marker x positions were worked out for,
+    /// and the markers left once those landing on the same pixel are merged
+    marker_positions: Option<(f32, Vec<(f32, TimelineMarker)>)>,
+    compare_previous: bool,
+    state_diff: Option<StateDiff>,
+    /// Packets each node sent and received, empty unless the run was traced
+    node_traces: Vec<Vec<TraceEntry>>,
+    trace_csv_path: String,
+    /// Result of the last trace export
+    trace_status: Option<String>,
 }
 
 impl AnalysisPanel {
-    pub fn new(scenario: Scenario, results: SimOutput) -> AnalysisPanel {
+    pub fn new(scenario: Scenario, mut results: SimOutput) -> AnalysisPanel {
+        let node_traces = std::mem::take(&mut results.node_traces);
+        let analysis = CompleteAnalysis::new(results, scenario.clone());
+
+        let latency_cdf = analysis.reception_analysis.latency_cdf();
+        let latency_percentiles = [0.5, 0.9, 0.99].map(|p| {
+            analysis
+                .reception_analysis
+                .latency_percentile(p)
+                .map(|x| x.seconds())
+        });
+        // Around a hundred buckets over the run
+        let bucket = Time::from_seconds((analysis.end_time / 100.0).max(1.0));
+        let channel_utilisation = analysis.channel_utilisation(bucket);
+
+        let timeline_markers = timeline_markers(&scenario, &analysis);
+
+        let headline = HEADLINE_TITLES
+            .into_iter()
+            .zip(headline_values(&analysis))
+            .collect();
+
         let CompleteAnalysis {
             node_settings,
             node_events,
@@ -69,15 +673,56 @@ impl AnalysisPanel {
                     wanted_messages,
                     received_messages,
                     reception_rate,
+                    avg_latency_per_node,
+                    gateway_breakdown,
                     ..
                 },
             total_airtime: _,
+            transmissions_per_node,
+            airtime_per_node,
+            blocked_per_node,
+            sf_breakdown,
+            degree_stats,
+            distance_latency,
             complete_identity,
+            scenario: analysed_scenario,
             ..
-        } = CompleteAnalysis::new(results, scenario.clone());
+        } = analysis;
+
+        let node_rows = (0..node_settings.len())
+            .map(|id| {
+                let received = wanted_messages[id]
+                    .iter()
+                    .filter(|x| x.was_received)
+                    .count();
+
+                NodeRow {
+                    id,
+                    is_gateway: node_settings[id].is_gateway,
+                    reception_rate: reception_rate[id],
+                    avg_latency: (received > 0).then(|| avg_latency_per_node[id].seconds()),
+                    wanted: wanted_messages[id].len(),
+                    received,
+                    sent: transmissions_per_node[id],
+                    airtime: airtime_per_node[id],
+                    blocked: blocked_per_node[id],
+                }
+            })
+            .collect();
+
+        let mut captured_messages = vec![None; node_settings.len()];
+        for stats in gateway_breakdown {
+            let id = stats.gateway_id;
+            captured_messages[id] = Some(stats);
+        }
 
         let node_locations = scenario.map;
+        let trails = MovementTrails::new(&node_locations, end_time);
         let transmission_graphs = create_transmission_graphs(sim_events.clone());
+        let transmission_tags = transmissions
+            .iter()
+            .map(|x| (x.id, TransmissionTags::new(x)))
+            .collect();
 
         let mut scene = SceneData::new();
         scene.zoom_to_fit(&node_locations.display_locations(Time::from_seconds(0.0)));
@@ -89,6 +734,8 @@ impl AnalysisPanel {
             sim_events,
             transmission_graphs,
             transmissions,
+            transmission_tags,
+            transmission_filter: TransmissionFilter::new(),
             end_time,
             wanted_messages,
             received_messages,
@@ -106,24 +753,490 @@ impl AnalysisPanel {
             inspector_tabs: InspectorTab::Overview,
             use_inspector_text_mode: false,
             live_sim: None,
+            live_mode: false,
+            live_synced_time: 0.0,
+            live_steps: VecDeque::new(),
+            source: None,
+            node_rows,
+            node_sort: (NodeColumn::Id, false),
+            latency_cdf,
+            latency_percentiles,
+            channel_utilisation,
+            sf_breakdown,
+            degree_stats,
+            distance_latency,
+            captured_messages,
+            colour_mode: ColourMode::Off,
+            message_trace: None,
+            scenario: analysed_scenario,
+            failure_report: None,
+            trails,
+            show_trails: false,
+            trail_length: 60.0,
+            headline,
+            sim_event_filter: EventFilter::new(),
+            node_event_filter: EventFilter::new(),
+            timeline_span: 10.0,
+            timeline_rows: TimelineRows::Transmitter,
+            show_shortcuts: false,
+            image_export: ImageExport::new(),
+            scenario_name: scenario_name(&complete_identity.scenario_identity),
+            timeline_markers,
+            marker_positions: None,
+            compare_previous: false,
+            state_diff: None,
+            node_traces,
+            trace_csv_path: "trace.csv".to_owned(),
+            trace_status: None,
         }
     }
 
-    pub fn from_scenario(scenario: Scenario, model: NodeModel) -> AnalysisPanel {
-        let live = LiveSimulation::new(12345, scenario.clone(), model.clone(), true);
-        let sim_output = run_simulation(12345, scenario.clone(), model, true);
+    pub fn from_scenario(
+        scenario: impl Into<Arc<Scenario>>,
+        model: NodeModel,
+        seed: u64,
+    ) -> AnalysisPanel {
+        // One run gives both the events shown and the live simulation inspecting node states,
+        // so they always match
+        let scenario = scenario.into();
+        let (live, sim_output) =
+            LiveSimulation::run_collecting(seed, scenario.clone(), model.clone(), true);
 
-        let mut out = AnalysisPanel::new(scenario, sim_output);
+        let mut out = AnalysisPanel::new(Scenario::clone(&scenario), sim_output);
 
         out.live_sim = Some(live);
+        out.source = Some((scenario, model));
 
         out
     }
 
-    fn event_ui(events: &Vec<LogItem>, ui: &mut egui::Ui, time: Time) {
+    /// Runs the same scenario and model again with a different seed,
+    /// keeping the view, timeline position and inspect target where they still exist.
+    fn rerun(&mut self, seed: u64) {
+        let Some((scenario, model)) = self.source.clone() else {
+            return;
+        };
+
+        let mut next = AnalysisPanel::from_scenario(scenario, model, seed);
+
+        next.scene = std::mem::replace(&mut self.scene, SceneData::new());
+        next.current_time = self.current_time.min(next.end_time);
+        next.prev_time = self.prev_time.min(next.end_time);
+        next.play_timescale = self.play_timescale;
+        next.inspector_tabs = self.inspector_tabs;
+        next.sort_nodes(self.node_sort);
+        next.colour_mode = self.colour_mode;
+        next.show_trails = self.show_trails;
+        next.trail_length = self.trail_length;
+        next.sim_event_filter = self.sim_event_filter.clone();
+        next.node_event_filter = self.node_event_filter.clone();
+        next.timeline_span = self.timeline_span;
+        next.timeline_rows = self.timeline_rows;
+        next.transmission_filter = self.transmission_filter.clone();
+        next.use_inspector_text_mode = self.use_inspector_text_mode;
+        next.compare_previous = self.compare_previous;
+        next.trace_csv_path = self.trace_csv_path.clone();
+        if !self.node_traces.is_empty() {
+            next.record_traces();
+        }
+        next.inspect_target = match &self.inspect_target {
+            Inspectable::Transmission(id) if !next.transmissions.iter().any(|x| x.id == *id) => {
+                Inspectable::Nothing
+            }
+            other => other.clone(),
+        };
+
+        *self = next;
+    }
+
+    pub(crate) fn headline(&self) -> &[(&'static str, f64)] {
+        &self.headline
+    }
+
+    pub(crate) fn end_time(&self) -> f64 {
+        self.end_time
+    }
+
+    pub(crate) fn node_count(&self) -> usize {
+        self.node_settings.len()
+    }
+
+    pub(crate) fn run_label(&self) -> String {
+        format!("{} with seed {}", self.used_model, self.used_seed)
+    }
+
+    pub(crate) fn current_time(&self) -> f64 {
+        self.current_time
+    }
+
+    pub(crate) fn set_time(&mut self, time: f64) {
+        if time != self.current_time {
+            self.prev_time = self.current_time;
+            self.current_time = time;
+        }
+    }
+
+    /// Draws the scene into `scene_rect` only, so another run can be shown beside it.
+    pub(crate) fn comparison_scene(&mut self, ui: &mut egui::Ui, scene_rect: Rect, fit: bool) {
+        let node_locations = self
+            .node_locations
+            .display_locations(Time::from_seconds(self.current_time));
+
+        self.scene.set_viewport(scene_rect);
+        if fit {
+            self.scene.zoom_to_fit(&node_locations);
+        }
+
+        self.update_trace();
+        self.analysis_scene_panel(node_locations, ui, scene_rect);
+    }
+
+    /// Transmission timeline and inspector for one side of a comparison.
+    pub(crate) fn comparison_details(&mut self, ui: &mut egui::Ui) {
+        let node_locations = self
+            .node_locations
+            .display_locations(Time::from_seconds(self.current_time));
+        let item_background = Color32::from_hex("#212121").unwrap();
+        let main_red = Color32::from_hex("#9b0d0d").unwrap();
+
+        let (_, timeline_rect) = ui.allocate_space(egui::vec2(ui.available_width(), 150.0));
+        ui.scope_builder(egui::UiBuilder::new().max_rect(timeline_rect), |ui| {
+            self.analysis_transmission_timeline(main_red, ui);
+        });
+        ui.separator();
+        self.analysis_inspector_panel(&node_locations, item_background, ui);
+    }
+
+    /// Goes back to drawing over the whole window after being part of a comparison.
+    pub(crate) fn leave_comparison(&mut self) {
+        self.scene.clear_viewport();
+    }
+
+    /// Draws the scene as it is on screen into an image and saves it.
+    fn export_image(&mut self, ui: &mut egui::Ui) {
+        let ImageExport {
+            width,
+            height,
+            caption,
+            ..
+        } = self.image_export;
+
+        let scene_rect = self.scene.begin_export(width, height);
+        let node_locations = self
+            .node_locations
+            .display_locations(Time::from_seconds(self.current_time));
+        self.render_scene(node_locations, ui, scene_rect);
+
+        let caption =
+            caption.then(|| format!("{} at {:.3}s", self.scenario_name, self.current_time));
+        let path = self.image_export.path.clone();
+
+        let result = self.scene.finish_export(caption.as_deref(), &path);
+        self.image_export.status = Some(match result {
+            Ok(()) => format!("Saved {path}"),
+            Err(e) => e,
+        });
+    }
+
+    /// Steps the live simulation up to `count` events, stopping early after a step `until` accepts.
+    fn live_step(&mut self, count: usize, until: impl Fn(&StepResult) -> bool) {
+        const STEP_HISTORY: usize = 50;
+
+        let Some(live) = &mut self.live_sim else {
+            return;
+        };
+
+        for _ in 0..count {
+            if live.finished() {
+                break;
+            }
+
+            let result = live.step();
+            self.live_steps.push_front((live.sim_time().seconds(), result));
+
+            if until(&result) {
+                break;
+            }
+        }
+
+        self.live_steps.truncate(STEP_HISTORY);
+        self.playing = false;
+        self.prev_time = self.current_time;
+        self.current_time = live.sim_time().seconds();
+        self.live_synced_time = self.current_time;
+    }
+
+    /// Moves the live simulation to wherever the timeline was scrubbed or played to.
+    fn sync_live(&mut self) {
+        // Stepping leaves unprocessed events at the current time which seeking would run
+        if self.live_mode && (self.current_time - self.live_synced_time).abs() > 1e-9 {
+            self.seek_live();
+        }
+    }
+
+    fn seek_live(&mut self) {
+        if let Some(live) = &mut self.live_sim {
+            live.seek(self.current_time.into());
+            self.live_synced_time = self.current_time;
+            self.live_steps.clear();
+        }
+    }
+
+    fn live_controls(&mut self, ui: &mut egui::Ui) {
+        let Some(live) = &self.live_sim else {
+            return;
+        };
+
+        let finished = live.finished();
+        let events_processed = live.events_processed();
+
+        ui.horizontal(|ui| {
+            let toggled = ui
+                .checkbox(&mut self.live_mode, "Live")
+                .on_hover_text("Step through the simulation event by event")
+                .changed();
+
+            if toggled && self.live_mode {
+                self.seek_live();
+            }
+
+            if !self.live_mode {
+                return;
+            }
+
+            ui.add_enabled_ui(!finished, |ui| {
+                if ui.button("Step 1 Event").clicked() {
+                    self.live_step(1, |_| false);
+                }
+                if ui.button("Step 100 Events").clicked() {
+                    self.live_step(100, |_| false);
+                }
+                if ui.button("Run Until Next Transmission").clicked() {
+                    self.live_step(usize::MAX, |x| x.started_transmission().is_some());
+                }
+            });
+
+            ui.label(format!("{events_processed} events processed"));
+
+            match self.live_steps.front() {
+                Some((time, step)) => {
+                    ui.label(format!("Last step at {time:.3}s: {step}"))
+                        .on_hover_ui(|ui| {
+                            for (time, step) in self.live_steps.iter() {
+                                ui.label(format!("{time:.3}s: {step}"));
+                            }
+                        });
+                }
+                None if finished => {
+                    ui.label("Simulation finished");
+                }
+                None => (),
+            }
+        });
+    }
+
+    /// Thin strip of ticks marking when messages and emergencies happen.
+    /// Returns the time of the marker clicked, if any.
+    fn marker_strip(&mut self, ui: &mut egui::Ui) -> Option<f64> {
+        const HEIGHT: f32 = 10.0;
+        const CLICK_DISTANCE: f32 = 4.0;
+
+        let width = ui.spacing().slider_width;
+        let (rect, response) =
+            ui.allocate_exact_size(egui::vec2(width, HEIGHT), egui::Sense::click());
+
+        let end_time = self.end_time.max(f64::EPSILON);
+
+        // Only worked out again when the strip changes size
+        if self.marker_positions.as_ref().is_none_or(|(cached, _)| *cached != width) {
+            let mut positions: Vec<(f32, TimelineMarker)> = Vec::new();
+
+            for marker in self.timeline_markers.iter() {
+                let x = (marker.time / end_time) as f32 * width;
+                let x = x.round();
+
+                // Markers are in time order so only the last can share the pixel
+                match positions.last_mut().filter(|(other, _)| *other == x) {
+                    Some((_, existing)) if existing.kind < marker.kind => *existing = *marker,
+                    Some(_) => (),
+                    None => positions.push((x, *marker)),
+                }
+            }
+
+            self.marker_positions = Some((width, positions));
+        }
+
+        let positions = &self.marker_positions.as_ref()?.1;
+        let painter = ui.painter_at(rect);
+
+        for (x, marker) in positions.iter() {
+            let x = rect.left() + x;
+            let top = match marker.kind {
+                MarkerKind::Generation | MarkerKind::Delivery => rect.center().y,
+                _ => rect.top(),
+            };
+            painter.vline(x, top..=rect.bottom(), (1.5, marker.kind.colour()));
+        }
+
+        let now = rect.left() + (self.current_time / end_time) as f32 * width;
+        painter.vline(now, rect.y_range(), (1.0, Color32::WHITE));
+
+        let pointer = response.hover_pos()?;
+        let (_, nearest) = positions
+            .iter()
+            .min_by(|a, b| {
+                let a = (rect.left() + a.0 - pointer.x).abs();
+                let b = (rect.left() + b.0 - pointer.x).abs();
+                a.total_cmp(&b)
+            })
+            .filter(|(x, _)| (rect.left() + x - pointer.x).abs() <= CLICK_DISTANCE)?;
+
+        let response = response.on_hover_text(format!(
+            "{} at {:.3}s",
+            nearest.kind.title(),
+            nearest.time
+        ));
+
+        response.clicked().then_some(nearest.time)
+    }
+
+    fn toggle_play(&mut self, now: f64) {
+        self.playing = !self.playing;
+        self.play_offset = now;
+        self.play_time_offset = self.current_time;
+    }
+
+    fn prev_sim_event(&self) -> Option<Time> {
+        self.sim_events
+            .iter()
+            .rev()
+            .find(|x| x.time < self.current_time.into())
+            .map(|x| x.time)
+    }
+
+    fn next_sim_event(&self) -> Option<Time> {
+        self.sim_events
+            .iter()
+            .find(|x| x.time > self.current_time.into())
+            .map(|x| x.time)
+    }
+
+    /// Start of the first transmission after the current time that `node` sent or received.
+    fn next_transmission_involving(&self, node: usize) -> Option<f64> {
+        self.transmissions
+            .iter()
+            .filter(|x| x.start_time.seconds() > self.current_time)
+            .filter(|x| {
+                x.transmitter_id == node
+                    || self.transmission_graphs[&x.id].targets.contains(&node)
+            })
+            .map(|x| x.start_time.seconds())
+            .min_by(f64::total_cmp)
+    }
+
+    fn keyboard_shortcuts(&mut self, ui: &mut egui::Ui) {
+        use egui::{Key, Modifiers};
+
+        // Typing into a search box shouldn't move the timeline
+        if ui.ctx().wants_keyboard_input() {
+            return;
+        }
+
+        let node_count = self.node_settings.len();
+        let now = ui.input(|i| i.time);
+        let mut jump = None;
+
+        ui.input_mut(|i| {
+            // Shift variants first as unshifted shortcuts also match with shift held
+            if i.consume_key(Modifiers::SHIFT, Key::ArrowLeft) {
+                jump = Some((self.current_time - 10.0).max(0.0));
+            }
+            if i.consume_key(Modifiers::SHIFT, Key::ArrowRight) {
+                jump = Some((self.current_time + 10.0).min(self.end_time));
+            }
+            if i.consume_key(Modifiers::NONE, Key::ArrowLeft) {
+                jump = self.prev_sim_event().map(|x| x.seconds());
+            }
+            if i.consume_key(Modifiers::NONE, Key::ArrowRight) {
+                jump = self.next_sim_event().map(|x| x.seconds());
+            }
+
+            if i.consume_key(Modifiers::NONE, Key::Space) {
+                self.toggle_play(now);
+            }
+
+            if node_count > 0 {
+                let step = match (
+                    i.consume_key(Modifiers::NONE, Key::ArrowDown),
+                    i.consume_key(Modifiers::NONE, Key::ArrowUp),
+                ) {
+                    (true, false) => Some(1),
+                    (false, true) => Some(node_count - 1),
+                    _ => None,
+                };
+
+                if let Some(step) = step {
+                    self.inspect_target = match self.inspect_target {
+                        Inspectable::Node(id) => Inspectable::Node((id + step) % node_count),
+                        _ => Inspectable::Node(if step == 1 { 0 } else { node_count - 1 }),
+                    };
+                }
+            }
+
+            if i.consume_key(Modifiers::NONE, Key::T)
+                && let Inspectable::Node(id) = self.inspect_target
+            {
+                jump = self.next_transmission_involving(id).or(jump);
+            }
+
+            if i.consume_key(Modifiers::NONE, Key::Escape) {
+                self.inspect_target = Inspectable::Nothing;
+            }
+
+            if i.consume_key(Modifiers::NONE, Key::Questionmark) {
+                self.show_shortcuts = !self.show_shortcuts;
+            }
+        });
+
+        if let Some(time) = jump {
+            self.set_time(time);
+            // Playback carries on from wherever the jump landed
+            self.play_offset = now;
+            self.play_time_offset = self.current_time;
+        }
+
+        egui::Window::new("Keyboard Shortcuts")
+            .open(&mut self.show_shortcuts)
+            .collapsible(false)
+            .resizable(false)
+            .show(ui.ctx(), |ui| {
+                egui::Grid::new("Shortcut Grid").show(ui, |ui| {
+                    for (keys, action) in SHORTCUTS {
+                        ui.label(RichText::new(keys).monospace());
+                        ui.label(action);
+                        ui.end_row();
+                    }
+                });
+            });
+    }
+
+    fn event_ui(events: &Vec<LogItem>, filter: &EventFilter, ui: &mut egui::Ui, time: Time) {
+        let window: Vec<&LogItem> = get_event_window(events, time).collect();
+        let shown: Vec<&LogItem> = window.iter().copied().filter(|x| filter.matches(x)).collect();
+
+        if shown.len() < window.len() {
+            ui.label(
+                RichText::new(format!(
+                    "{} nearby events hidden by filters",
+                    window.len() - shown.len()
+                ))
+                .weak(),
+            );
+        }
+
         let mut in_future = false;
 
-        get_event_window(events, time).for_each(|x| {
+        shown.into_iter().for_each(|x| {
             if !in_future && x.time > time {
                 in_future = true;
                 ui.separator();
@@ -143,9 +1256,13 @@ impl Widget for &mut AnalysisPanel {
         let item_background = Color32::from_hex("#212121").unwrap();
         let main_red = Color32::from_hex("#9b0d0d").unwrap();
 
+        self.keyboard_shortcuts(ui);
+        self.update_trace();
+
         egui::TopBottomPanel::top("timeline").show_inside(ui, |ui| {
             self.analysis_timeline_panel(item_background, main_red, ui);
         });
+        self.sync_live();
 
         egui::SidePanel::left("inspector")
             .max_width(500.0)
@@ -164,6 +1281,10 @@ impl Widget for &mut AnalysisPanel {
                 self.analysis_transmission_timeline(main_red, ui);
             });
 
+        egui::TopBottomPanel::bottom("node_table").show_inside(ui, |ui| {
+            self.analysis_node_table(&node_locations, ui);
+        });
+
         let central_rect = egui::CentralPanel::default()
             .frame(Frame::NONE)
             .show_inside(ui, |ui| {
@@ -175,21 +1296,245 @@ impl Widget for &mut AnalysisPanel {
 
         self.analysis_scene_panel(node_locations, ui, convert_rect(central_rect));
 
+        if self.image_export.modal(ui.ctx()) {
+            self.export_image(ui);
+        }
+
         ui.response()
     }
 }
 
-impl AnalysisPanel {
-    fn analysis_scene_panel(
+impl AnalysisPanel {
+    fn analysis_scene_panel(
+        &mut self,
+        node_locations: Vec<frogcore::node_location::Point>,
+        ui: &mut egui::Ui,
+        scene_rect: Rect,
+    ) {
+        self.scene.camera_control(scene_rect);
+        self.scene
+            .select_interaction(&mut self.inspect_target, &node_locations, scene_rect);
+        self.hover_tooltip(&node_locations, ui, scene_rect);
+
+        self.render_scene(node_locations, ui, scene_rect);
+    }
+
+    /// Runs the simulation behind the results again with traces,
+    /// as the run the panel was made from doesn't keep them.
+    fn record_traces(&mut self) {
+        let Some((scenario, model)) = self.source.clone() else {
+            return;
+        };
+
+        let options = RunOptions {
+            traces: true,
+            ..RunOptions::default()
+        };
+        let output = run_simulation_with(self.used_seed, scenario, model, options);
+        self.node_traces = output.node_traces;
+    }
+
+    /// Every packet the node sent and received. Returns the time of a clicked packet.
+    fn trace_ui(&mut self, id: usize, ui: &mut egui::Ui) -> Option<Time> {
+        let Some(trace) = self.node_traces.get(id) else {
+            ui.label("Packets weren't traced for this run");
+            if self.source.is_some()
+                && ui
+                    .button("Record Traces")
+                    .on_hover_text("Runs again keeping every packet each node sent and received")
+                    .clicked()
+            {
+                self.record_traces();
+            }
+            return None;
+        };
+
+        ui.horizontal(|ui| {
+            ui.text_edit_singleline(&mut self.trace_csv_path);
+            if ui.button("Export CSV").clicked() {
+                let result = File::create(&self.trace_csv_path)
+                    .map_err(|e| e.to_string())
+                    .and_then(|file| write_trace_csv(trace, file).map_err(|e| e.to_string()));
+                self.trace_status = Some(match result {
+                    Ok(()) => format!("Saved {}", self.trace_csv_path),
+                    Err(e) => format!("Export failed: {e}"),
+                });
+            }
+        });
+        if let Some(status) = &self.trace_status {
+            ui.weak(status);
+        }
+
+        let mut clicked = None;
+        TableBuilder::new(ui)
+            .striped(true)
+            .sense(egui::Sense::click())
+            .max_scroll_height(400.0)
+            .columns(Column::auto().at_least(40.0), 7)
+            .header(20.0, |mut header| {
+                for title in ["Time", "", "Tx", "Header", "Content", "Size", "SNR"] {
+                    header.col(|ui| {
+                        ui.strong(title);
+                    });
+                }
+            })
+            .body(|body| {
+                body.rows(18.0, trace.len(), |mut row| {
+                    let entry = &trace[row.index()];
+
+                    row.col(|ui| {
+                        ui.label(format!("{:.3}", entry.time));
+                    });
+                    row.col(|ui| {
+                        ui.label(match entry.direction {
+                            TraceDirection::Sent => "Sent",
+                            TraceDirection::Received => "Recv",
+                        });
+                    });
+                    row.col(|ui| {
+                        ui.label(entry.transmission_id.to_string());
+                    });
+                    row.col(|ui| {
+                        let header = &entry.header;
+                        ui.label(format!(
+                            "{} -> {:?} #{}",
+                            header.sender(),
+                            header.dest(),
+                            header.packet_id()
+                        ))
+                        .on_hover_text(format!("{header:#?}"));
+                    });
+                    row.col(|ui| {
+                        ui.label(short_content(&entry.content));
+                    });
+                    row.col(|ui| {
+                        ui.label(format!("{} B", entry.size));
+                    });
+                    row.col(|ui| {
+                        if let Some(snr) = entry.snr {
+                            ui.label(format!("{:.1} dB", snr.as_db_float()));
+                        }
+                    });
+
+                    if row.response().clicked() {
+                        clicked = Some(entry.time);
+                    }
+                });
+            });
+
+        clicked
+    }
+
+    /// Quick look at the node or transmission line under the mouse without selecting it.
+    /// Node state at the current time with the changes since the event before its latest one,
+    /// worked out again only when the node or time changes.
+    fn state_diff_ui(&mut self, id: usize, ui: &mut egui::Ui) {
+        let Some(live) = self.live_sim.as_mut() else {
+            return;
+        };
+
+        let now = self.current_time;
+        let stale = self.state_diff.as_ref().is_none_or(|x| x.node != id || x.time != now);
+
+        if stale {
+            let mut event_times = self.node_events[id].iter().map(|x| x.time.seconds());
+            let latest = event_times.clone().rfind(|&x| x <= now);
+            let previous = latest
+                .and_then(|latest| event_times.rfind(|&x| x < latest))
+                .unwrap_or(0.0);
+
+            let old = serde_inspector::to_value(live.inspect_node(id, previous.into()));
+            let new = serde_inspector::to_value(live.inspect_node(id, now.into()));
+
+            self.state_diff = Some(StateDiff {
+                node: id,
+                time: now,
+                previous,
+                values: old.and_then(|old| Ok((old, new?))).map_err(|e| e.to_string()),
+            });
+        }
+
+        let Some(diff) = &self.state_diff else {
+            return;
+        };
+
+        ui.label(format!("Changes since {:.3}s", diff.previous));
+        match &diff.values {
+            Ok((old, new)) => serde_inspector::diff_inspector(id as u64, old, new, ui),
+            Err(e) => {
+                ui.label(e);
+            }
+        }
+    }
+
+    fn hover_tooltip(
+        &self,
+        node_locations: &[frogcore::node_location::Point],
+        ui: &egui::Ui,
+        scene_rect: Rect,
+    ) {
+        let tooltip = |id: &str, add_contents: &dyn Fn(&mut egui::Ui)| {
+            egui::show_tooltip_at_pointer(ui.ctx(), ui.layer_id(), egui::Id::new(id), |ui| {
+                add_contents(ui)
+            });
+        };
+
+        if let Some(id) = self.scene.hovered_node(node_locations, scene_rect) {
+            let point = node_locations[id];
+            tooltip("node_hover", &|ui| {
+                ui.label(RichText::new(format!("Node {id}")).strong());
+                ui.label(format!("Gateway: {}", self.node_settings[id].is_gateway));
+                ui.label(format!("Reception Rate: {:.3}", self.reception_rate[id]));
+                ui.label(format!(
+                    "Position: ({:.0}m, {:.0}m)",
+                    point.x.metres(),
+                    point.y.metres()
+                ));
+            });
+            return;
+        }
+
+        // Traces draw their own lines in place of the current transmissions
+        if !self.trace_hops().is_empty() {
+            return;
+        }
+
+        let hovered = self
+            .transmissions
+            .iter()
+            .filter(|x| {
+                x.start_time <= self.current_time.into()
+                    && x.end_time >= self.current_time.into()
+                    && self.shows_transmission(x.id)
+            })
+            .find(|x| {
+                let web = &self.transmission_graphs[&x.id];
+                let origin = point_to_vec(node_locations[web.origin]);
+                web.targets.iter().any(|target| {
+                    let target = point_to_vec(node_locations[*target]);
+                    self.scene.segment_hovered(origin, target, scene_rect)
+                })
+            });
+
+        if let Some(transmission) = hovered {
+            tooltip("transmission_hover", &|ui| {
+                ui.label(RichText::new(format!("Transmission {}", transmission.id)).strong());
+                ui.label(format!("Sender: {}", transmission.transmitter_id));
+                ui.label(format!("SF: {}", transmission.sf));
+                ui.label(format!(
+                    "Airtime: {:.3}s",
+                    (transmission.end_time - transmission.start_time).seconds()
+                ));
+            });
+        }
+    }
+
+    fn render_scene(
         &mut self,
         node_locations: Vec<frogcore::node_location::Point>,
         ui: &mut egui::Ui,
         scene_rect: Rect,
     ) {
-        self.scene.camera_control(scene_rect);
-        self.scene
-            .select_interaction(&mut self.inspect_target, &node_locations, scene_rect);
-
         set_camera(&self.scene.camera);
         self.scene.render_grid();
         self.scene.render_scale_indicator(ui, scene_rect);
@@ -219,93 +1564,510 @@ impl AnalysisPanel {
             _ => (),
         }
 
-        for (id, web) in {
-            self.transmissions.iter().filter(|x| {
-                x.start_time <= self.current_time.into() && x.end_time >= self.current_time.into()
-            })
+        if self.show_trails {
+            self.render_trails(&node_locations);
         }
-        .map(|x| x.id)
-        .map(|id| (id, &self.transmission_graphs[&id]))
-        {
-            let origin = point_to_vec(node_locations[web.origin]);
-            senders.insert(web.origin);
 
-            for target in web.targets.iter().copied() {
-                let target_pos = point_to_vec(node_locations[target]);
-
-                let line_colour = if Inspectable::Transmission(id) == self.inspect_target {
-                    GREEN
-                } else {
-                    ORANGE
-                };
+        let tracing = !self.trace_hops().is_empty();
+
+        // A trace replaces the current transmissions so the relay path stands out
+        if tracing {
+            self.render_trace(&node_locations);
+        } else {
+            // Live transmissions are only shown once the event sending them has been stepped
+            let active: Vec<u32> = match &self.live_sim {
+                Some(live) if self.live_mode => live
+                    .em_field()
+                    .filter(|x| x.end_time >= self.current_time.into())
+                    .map(|x| x.id)
+                    .collect(),
+                _ => self
+                    .transmissions
+                    .iter()
+                    .filter(|x| {
+                        x.start_time <= self.current_time.into()
+                            && x.end_time >= self.current_time.into()
+                    })
+                    .map(|x| x.id)
+                    .collect(),
+            };
 
-                draw_line(
-                    origin.x,
-                    origin.y,
-                    target_pos.x,
-                    target_pos.y,
-                    3.0 * line_base_size,
-                    line_colour,
-                );
+            for (id, web) in active
+                .into_iter()
+                .filter(|id| self.shows_transmission(*id))
+                .filter_map(|id| self.transmission_graphs.get(&id).map(|web| (id, web)))
+            {
+                let origin = point_to_vec(node_locations[web.origin]);
+                senders.insert(web.origin);
+
+                for target in web.targets.iter().copied() {
+                    let target_pos = point_to_vec(node_locations[target]);
+
+                    let line_colour = if Inspectable::Transmission(id) == self.inspect_target {
+                        GREEN
+                    } else {
+                        ORANGE
+                    };
+
+                    draw_line(
+                        origin.x,
+                        origin.y,
+                        target_pos.x,
+                        target_pos.y,
+                        3.0 * line_base_size,
+                        line_colour,
+                    );
+                }
             }
         }
 
+        let node_colours = if tracing {
+            Some(self.trace_colours())
+        } else {
+            self.mode_colours(ui, scene_rect)
+        };
+
         self.scene.render_nodes(
             &mut self.inspect_target,
             Some(&senders),
+            node_colours.as_ref(),
             &node_locations,
             ui,
             scene_rect,
         );
+
+        if tracing {
+            self.render_trace_numbers(&node_locations, ui, scene_rect);
+        }
+    }
+
+    /// Faded line behind each node over the trail length,
+    /// and a ring where it will be at the next timepoint.
+    fn render_trails(&self, node_locations: &[frogcore::node_location::Point]) {
+        let Some(trails) = &self.trails else {
+            return;
+        };
+
+        let line_base_size = 2. / self.scene.zoom_level;
+        let next_timepoint = trails.next_timepoint(self.current_time);
+
+        for (id, point) in node_locations.iter().enumerate() {
+            let current = point_to_vec(*point);
+            let trail: Vec<Vec2> = trails
+                .trail(id, self.current_time, self.trail_length)
+                .chain([current])
+                .collect();
+
+            for (i, pair) in trail.windows(2).enumerate() {
+                let alpha = 0.6 * (i + 1) as f32 / trail.len() as f32;
+                draw_line(
+                    pair[0].x,
+                    pair[0].y,
+                    pair[1].x,
+                    pair[1].y,
+                    2.0 * line_base_size,
+                    SKYBLUE.with_alpha(alpha),
+                );
+            }
+
+            if let Some(index) = next_timepoint
+                && let NodeLocation::Points(points) = &self.node_locations
+            {
+                let ghost = point_to_vec(points.data[index].node_points[id]);
+                draw_circle_lines(
+                    ghost.x,
+                    ghost.y,
+                    self.scene.node_size(),
+                    2.0 * line_base_size,
+                    WHITE.with_alpha(0.4),
+                );
+            }
+        }
+    }
+
+    /// Message being traced, or carried by the inspected transmission.
+    fn inspected_message(&self) -> Option<usize> {
+        match self.inspect_target {
+            Inspectable::MessageTrace(id) => Some(id),
+            Inspectable::Transmission(id) => self.transmission_tags.get(&id)?.message,
+            _ => None,
+        }
+    }
+
+    /// Whether the transmission filter lets the transmission through.
+    fn shows_transmission(&self, id: u32) -> bool {
+        let inspected = self.inspected_message();
+        self.transmission_tags
+            .get(&id)
+            .is_none_or(|tags| self.transmission_filter.matches(tags, inspected))
+    }
+
+    /// Recomputes the message trace when a different message starts being traced.
+    fn update_trace(&mut self) {
+        let Inspectable::MessageTrace(message_id) = self.inspect_target else {
+            self.message_trace = None;
+            return;
+        };
+
+        if self.message_trace.as_ref().is_some_and(|x| x.0 == message_id) {
+            return;
+        }
+
+        let hops = trace_message(&self.transmissions, &self.sim_events, message_id);
+        self.message_trace = Some((message_id, hops));
+    }
+
+    /// Hops of the traced message, empty when no message is being traced.
+    fn trace_hops(&self) -> &[MessageHop] {
+        match (&self.inspect_target, &self.message_trace) {
+            (Inspectable::MessageTrace(id), Some((trace_id, hops))) if id == trace_id => hops,
+            _ => &[],
+        }
+    }
+
+    /// Arrows from each relay to the nodes it delivered the message to.
+    /// Hops that have not happened yet at the current time are faded.
+    fn render_trace(&self, node_locations: &[frogcore::node_location::Point]) {
+        for hop in self.trace_hops() {
+            let colour = if hop.end_time.seconds() <= self.current_time {
+                GREEN
+            } else {
+                GREEN.with_alpha(0.3)
+            };
+
+            for receiver in hop.receivers.iter() {
+                self.scene.render_arrow(
+                    node_locations[hop.transmitter_id],
+                    node_locations[*receiver],
+                    colour,
+                );
+            }
+        }
+    }
+
+    fn render_trace_numbers(
+        &self,
+        node_locations: &[frogcore::node_location::Point],
+        ui: &mut egui::Ui,
+        scene_rect: Rect,
+    ) {
+        for (number, hop) in self.trace_hops().iter().enumerate() {
+            let from = point_to_vec(node_locations[hop.transmitter_id]);
+
+            for receiver in hop.receivers.iter() {
+                let middle = (from + point_to_vec(node_locations[*receiver])) / 2.0;
+                let screen_pos = self.scene.world_to_screen(middle);
+
+                if scene_rect.contains(screen_pos) {
+                    self.scene.overlay_text(
+                        ui,
+                        screen_pos,
+                        egui::Align2::CENTER_CENTER,
+                        &(number + 1).to_string(),
+                        18.0,
+                        Color32::WHITE,
+                    );
+                }
+            }
+        }
+    }
+
+    /// Nodes on the relay path keep their colour and every other node is dimmed.
+    fn trace_colours(&self) -> NodeColours {
+        let mut fill = vec![DARKGRAY.with_alpha(0.5); self.node_settings.len()];
+
+        for hop in self.trace_hops() {
+            fill[hop.transmitter_id] = Color::from_hex(0xff8080);
+            for receiver in hop.receivers.iter() {
+                fill[*receiver] = Color::from_hex(0xff8080);
+            }
+        }
+
+        NodeColours {
+            fill,
+            outlined: self.node_settings.iter().map(|x| x.is_gateway).collect(),
+        }
+    }
+
+    /// Node colours for the current colour mode, drawing its legend as well.
+    /// Nodes without a value for a heatmap metric are grey.
+    fn mode_colours(&self, ui: &mut egui::Ui, scene_rect: Rect) -> Option<NodeColours> {
+        let mut values = vec![None; self.node_settings.len()];
+
+        match self.colour_mode {
+            ColourMode::Off => return None,
+            ColourMode::Category(category) => {
+                let colours = category.colours(&self.node_settings);
+                self.scene.render_category_legend(ui, scene_rect, &colours);
+                return Some(colours.nodes);
+            }
+            ColourMode::Reception => {
+                for row in self.node_rows.iter().filter(|x| x.wanted > 0) {
+                    values[row.id] = Some(row.reception_rate);
+                }
+
+                self.scene
+                    .render_heatmap_legend(ui, scene_rect, "Reception Rate", "0%", "100%");
+            }
+            ColourMode::Latency => {
+                let max_latency = self
+                    .node_rows
+                    .iter()
+                    .filter_map(|x| x.avg_latency)
+                    .fold(0.0, f64::max);
+
+                // Low latency is good so it is green
+                for row in self.node_rows.iter() {
+                    values[row.id] = row
+                        .avg_latency
+                        .map(|x| 1.0 - x / max_latency.max(f64::EPSILON));
+                }
+
+                self.scene.render_heatmap_legend(
+                    ui,
+                    scene_rect,
+                    "Average Latency",
+                    &format!("{max_latency:.1}s"),
+                    "0s",
+                );
+            }
+        }
+
+        Some(NodeColours {
+            fill: values
+                .into_iter()
+                .map(|x| x.map_or(GRAY, |x| heat_colour(x as f32)))
+                .collect(),
+            outlined: self.node_settings.iter().map(|x| x.is_gateway).collect(),
+        })
     }
 
     fn analysis_transmission_timeline(&mut self, main_red: Color32, ui: &mut egui::Ui) {
-        let timespan = 10.0;
-        let timeline_trans = self.transmissions.iter().filter(|x| {
-            x.end_time.seconds() > self.current_time - timespan
-                && x.start_time.seconds() < self.current_time + timespan
+        const LABEL_WIDTH: f32 = 45.0;
+        const HEIGHT: f32 = 120.0;
+
+        ui.horizontal(|ui| {
+            ui.label("Rows by");
+            for grouping in [TimelineRows::Transmitter, TimelineRows::Sf] {
+                ui.selectable_value(&mut self.timeline_rows, grouping, grouping.title());
+            }
+            ui.add_space(10.0);
+            ui.label(format!("±{:.1}s", self.timeline_span))
+                .on_hover_text("Scroll to zoom, drag to scrub, double click to jump to a start");
         });
 
-        let size_adjust = ui.max_rect().width() / (timespan as f32 * 2.0);
-        let offset = ui.next_widget_position().to_vec2();
+        let (rect, response) = ui.allocate_exact_size(
+            egui::vec2(ui.available_width(), HEIGHT),
+            egui::Sense::click_and_drag(),
+        );
+        let bars = egui::Rect::from_min_max(rect.min + egui::vec2(LABEL_WIDTH, 0.0), rect.max);
+
+        if response.hovered() {
+            let scroll = ui.input(|i| i.smooth_scroll_delta.y) as f64;
+            self.timeline_span = (self.timeline_span * (-scroll * 0.002).exp()).clamp(0.05, 3600.0);
+        }
+
+        let span = self.timeline_span;
+
+        if response.dragged() {
+            let seconds = response.drag_delta().x as f64 / bars.width() as f64 * 2.0 * span;
+            self.set_time((self.current_time - seconds).clamp(0.0, self.end_time));
+        }
+
+        let seconds_to_x = |time: f64| {
+            bars.center().x + ((time - self.current_time) / span) as f32 * bars.width() / 2.0
+        };
+
+        let row_key = |x: &Transmission| match self.timeline_rows {
+            TimelineRows::Transmitter => x.transmitter_id as i64,
+            TimelineRows::Sf => x.sf as i64,
+        };
 
-        ui.painter().rect_filled(
-            egui::Rect {
-                min: Pos2::new(ui.max_rect().width() / 2.0 - 1.0, 0.0),
-                max: Pos2::new(ui.max_rect().width() / 2.0 + 1.0, 150.0),
+        // Cull before anything is laid out so long runs stay cheap
+        let visible: Vec<&Transmission> = self
+            .transmissions
+            .iter()
+            .filter(|x| {
+                x.end_time.seconds() > self.current_time - span
+                    && x.start_time.seconds() < self.current_time + span
+                    && self.shows_transmission(x.id)
+            })
+            .collect();
+
+        let mut rows: Vec<i64> = visible.iter().map(|x| row_key(x)).collect();
+        rows.sort_unstable();
+        rows.dedup();
+
+        let row_height = (HEIGHT / rows.len().max(1) as f32).clamp(3.0, 20.0);
+        let painter = ui.painter_at(rect);
+        painter.rect_filled(rect, 0.0, Color32::from_gray(30));
+
+        for (i, key) in rows.iter().enumerate() {
+            let y = rect.top() + i as f32 * row_height;
+            if row_height >= 10.0 {
+                let label = match self.timeline_rows {
+                    TimelineRows::Transmitter => format!("N{key}"),
+                    TimelineRows::Sf => format!("SF{key}"),
+                };
+                painter.text(
+                    egui::pos2(rect.left() + 2.0, y + row_height / 2.0),
+                    egui::Align2::LEFT_CENTER,
+                    label,
+                    egui::FontId::monospace(row_height.min(12.0)),
+                    Color32::GRAY,
+                );
             }
-            .translate(offset),
-            0.0,
-            Color32::BLACK,
-        );
+        }
 
-        for transmission in timeline_trans {
-            let row = (transmission.id % 5) as f32;
-            let pos_rect = egui::Rect {
-                min: Pos2::new(
-                    (transmission.start_time.seconds() + timespan - self.current_time) as f32
-                        * size_adjust,
-                    row * 30.0,
-                ),
-                max: Pos2::new(
-                    (transmission.end_time.seconds() + timespan - self.current_time) as f32
-                        * size_adjust,
-                    (row + 0.9) * 30.0,
+        let mut hovered = None;
+        let pointer = response.interact_pointer_pos().or(response.hover_pos());
+
+        for transmission in visible.iter() {
+            let row = rows.binary_search(&row_key(transmission)).unwrap_or(0);
+            let top = rect.top() + row as f32 * row_height;
+            let bar = egui::Rect::from_min_max(
+                egui::pos2(seconds_to_x(transmission.start_time.seconds()), top),
+                egui::pos2(
+                    seconds_to_x(transmission.end_time.seconds()),
+                    top + row_height * 0.9,
                 ),
+            )
+            .intersect(bars);
+
+            let colour = match transmission.message_content {
+                _ if self.inspect_target == Inspectable::Transmission(transmission.id) => {
+                    Color32::LIGHT_GREEN
+                }
+                MessageContent::GeneratedMessage(_) => main_red,
+                _ => Color32::from_rgb(70, 110, 160),
+            };
+
+            painter.rect_filled(bar, 0.0, colour);
+
+            if bar.width() > 30.0 && row_height >= 10.0 {
+                painter.text(
+                    bar.center(),
+                    egui::Align2::CENTER_CENTER,
+                    transmission.id.to_string(),
+                    egui::FontId::proportional(row_height.min(12.0)),
+                    Color32::WHITE,
+                );
             }
-            .translate(offset);
 
-            ui.painter().rect_filled(pos_rect, 0.0, main_red);
+            if pointer.is_some_and(|x| bar.contains(x)) {
+                hovered = Some((transmission.id, transmission.start_time.seconds()));
+            }
+        }
 
-            if ui
-                .put(pos_rect, Label::new(transmission.id.to_string()))
-                .clicked()
-            {
-                self.inspect_target = Inspectable::Transmission(transmission.id);
+        painter.vline(bars.center().x, rect.y_range(), (2.0, Color32::BLACK));
+
+        if let Some((id, start_time)) = hovered {
+            if response.double_clicked() {
+                self.set_time(start_time);
+            } else if response.clicked() {
+                self.inspect_target = Inspectable::Transmission(id);
             }
         }
     }
 
+    fn sort_nodes(&mut self, (column, descending): (NodeColumn, bool)) {
+        self.node_sort = (column, descending);
+        self.node_rows.sort_by(|a, b| {
+            let order = a
+                .sort_key(column)
+                .total_cmp(&b.sort_key(column))
+                .then(a.id.cmp(&b.id));
+
+            if descending { order.reverse() } else { order }
+        });
+    }
+
+    fn analysis_node_table(
+        &mut self,
+        node_locations: &[frogcore::node_location::Point],
+        ui: &mut egui::Ui,
+    ) {
+        egui::CollapsingHeader::new("Node Metrics").show(ui, |ui| {
+            let mut sort_by = None;
+            let mut clicked_node = None;
+
+            TableBuilder::new(ui)
+                .striped(true)
+                .sense(egui::Sense::click())
+                .max_scroll_height(200.0)
+                .columns(Column::auto().at_least(60.0), NodeColumn::ALL.len())
+                .header(20.0, |mut header| {
+                    for column in NodeColumn::ALL {
+                        header.col(|ui| {
+                            let (sorted, descending) = self.node_sort;
+                            let arrow = match (sorted == column, descending) {
+                                (false, _) => "",
+                                (true, false) => " ⬆",
+                                (true, true) => " ⬇",
+                            };
+
+                            let title = format!("{}{arrow}", column.title());
+                            if ui.button(RichText::new(title).strong()).clicked() {
+                                sort_by = Some((column, sorted == column && !descending));
+                            }
+                        });
+                    }
+                })
+                .body(|body| {
+                    body.rows(18.0, self.node_rows.len(), |mut row| {
+                        let node = &self.node_rows[row.index()];
+                        row.set_selected(self.inspect_target == Inspectable::Node(node.id));
+
+                        row.col(|ui| {
+                            ui.label(node.id.to_string());
+                        });
+                        row.col(|ui| {
+                            ui.label(if node.is_gateway { "Yes" } else { "" });
+                        });
+                        row.col(|ui| {
+                            ui.label(format!("{:.3}", node.reception_rate));
+                        });
+                        row.col(|ui| {
+                            ui.label(match node.avg_latency {
+                                Some(latency) => format!("{latency:.3}s"),
+                                None => "-".to_owned(),
+                            });
+                        });
+                        row.col(|ui| {
+                            ui.label(node.wanted.to_string());
+                        });
+                        row.col(|ui| {
+                            ui.label(node.received.to_string());
+                        });
+                        row.col(|ui| {
+                            ui.label(node.sent.to_string());
+                        });
+                        row.col(|ui| {
+                            ui.label(format!("{:.3}s", node.airtime));
+                        });
+                        row.col(|ui| {
+                            ui.label(node.blocked.to_string());
+                        });
+
+                        if row.response().clicked() {
+                            clicked_node = Some(node.id);
+                        }
+                    });
+                });
+
+            if let Some(sort) = sort_by {
+                self.sort_nodes(sort);
+            }
+
+            if let Some(id) = clicked_node {
+                self.inspect_target = Inspectable::Node(id);
+                if let Some(&point) = node_locations.get(id) {
+                    self.scene.centre_on(point);
+                }
+            }
+        });
+    }
+
     fn analysis_events_panel(
         &mut self,
         item_background: Color32,
@@ -326,7 +2088,13 @@ impl AnalysisPanel {
 
         ScrollArea::vertical().show(ui, |ui| {
             ui.heading("Sim Events");
-            AnalysisPanel::event_ui(&self.sim_events, ui, self.current_time.into());
+            self.sim_event_filter.controls("Sim Event Level", true, ui);
+            AnalysisPanel::event_ui(
+                &self.sim_events,
+                &self.sim_event_filter,
+                ui,
+                self.current_time.into(),
+            );
 
             ui.separator();
 
@@ -401,10 +2169,26 @@ impl AnalysisPanel {
             {
                 self.inspector_tabs = InspectorTab::State;
             }
+            if ui
+                .selectable_label(self.inspector_tabs == InspectorTab::Charts, "Charts")
+                .clicked()
+            {
+                self.inspector_tabs = InspectorTab::Charts;
+            }
+            if ui
+                .selectable_label(self.inspector_tabs == InspectorTab::Trace, "Trace")
+                .clicked()
+            {
+                self.inspector_tabs = InspectorTab::Trace;
+            }
             ui.add_space(10.0);
             ui.checkbox(&mut self.use_inspector_text_mode, "Text Mode");
         });
 
+        if self.inspector_tabs == InspectorTab::Charts {
+            return ScrollArea::vertical().show(ui, |ui| self.analysis_charts(ui));
+        }
+
         ScrollArea::vertical().show(ui, |ui| match self.inspect_target {
             Inspectable::Node(id) => match self.inspector_tabs {
                 InspectorTab::Overview => {
@@ -417,7 +2201,8 @@ impl AnalysisPanel {
                     ui.label(format!("x: {:.3}    y: {:.3}", loc.x, loc.y));
 
                     ui.separator();
-                    ui.heading("Node Settings");
+                    ui.heading("Node Settings")
+                        .on_hover_text("What the node started the run with");
                     ui.label(format!(
                         "Bandwidth: {:.3} kHz",
                         current_node.bandwidth.kHz()
@@ -428,6 +2213,13 @@ impl AnalysisPanel {
                         current_node.max_power.dbm()
                     ));
                     ui.label(format!("Default SF: {}", current_node.sf));
+                    ui.label(format!("Coding Rate: 4/{}", current_node.coding_rate));
+                    ui.label(format!(
+                        "Modem Preset: {}",
+                        current_node
+                            .preset()
+                            .map_or("Custom".to_owned(), |x| x.to_string())
+                    ));
 
                     ui.separator();
 
@@ -437,6 +2229,31 @@ impl AnalysisPanel {
 
                     ui.label(format!("Received: {:?}", self.received_messages[id]));
 
+                    if let Some(stats) = &self.captured_messages[id] {
+                        ui.label(format!(
+                            "Captured Messages: {} ({:.1}% of gateway captures)",
+                            stats.captured_messages,
+                            stats.message_share * 100.0
+                        ))
+                        .on_hover_text("Messages this gateway heard before any other gateway");
+                        ui.label(format!(
+                            "Capturing Airtime: {:.3}s ({:.1}%)",
+                            stats.airtime.seconds(),
+                            stats.airtime_share * 100.0
+                        ));
+                    }
+
+                    let contact = &self.degree_stats;
+                    ui.label(match contact.first_contact[id] {
+                        Some(time) => format!("First Contact: {time}"),
+                        None => "First Contact: never".to_owned(),
+                    })
+                    .on_hover_text("First time the node could read or be read by any other node");
+                    ui.label(format!("Contact Ratio: {:.3}", contact.contact_ratio[id]))
+                        .on_hover_text("Fraction of the run the node had a neighbour");
+
+                    let mut trace = None;
+                    let mut explain = None;
                     ui.horizontal_wrapped(|ui| {
                         ui.label("Wanted: ");
 
@@ -445,14 +2262,25 @@ impl AnalysisPanel {
                             .fill(item_background)
                             .show(ui, |ui| {
                                 for message in self.wanted_messages[id].iter() {
-                                    let colour = if message.was_received {
-                                        Color32::GREEN
-                                    } else {
-                                        Color32::RED
+                                    let received = message.was_received;
+                                    let colour = match (received, message.deadline_met) {
+                                        (false, _) => Color32::RED,
+                                        (true, Some(false)) => Color32::ORANGE,
+                                        (true, _) => Color32::GREEN,
                                     };
-                                    let response =
+                                    let mut response =
                                         ui.colored_label(colour, message.message_id.to_string());
+                                    if let Some(met) = message.deadline_met {
+                                        response = response.on_hover_text(if met {
+                                            "Received within its deadline"
+                                        } else {
+                                            "Missed its deadline"
+                                        });
+                                    }
                                     if response.clicked() {
+                                        if !message.was_received {
+                                            explain = Some(message.message_id);
+                                        }
                                         self.transmissions
                                             .iter()
                                             .find(|x| match x.message_content {
@@ -465,18 +2293,65 @@ impl AnalysisPanel {
                                                 set_time!(x.start_time);
                                             });
                                     }
+                                    if ui.small_button("Trace").clicked() {
+                                        trace = Some(message.message_id);
+                                    }
                                 }
                             });
                     });
 
+                    if let Some(message_id) = trace {
+                        self.inspect_target = Inspectable::MessageTrace(message_id);
+                    }
+
+                    if let Some(message_id) = explain {
+                        self.failure_report = Some(delivery_failure_report(
+                            &self.scenario,
+                            &self.transmissions,
+                            &self.sim_events,
+                            message_id,
+                            id,
+                        ));
+                    }
+
+                    if let Some(report) = &self.failure_report
+                        && report.target_node == id
+                    {
+                        Self::failure_report_ui(report, ui);
+                    }
+
                     ui.separator();
                     ui.heading("Node Events");
-
-                    AnalysisPanel::event_ui(&self.node_events[id], ui, self.current_time.into());
+                    self.node_event_filter.controls("Node Event Level", false, ui);
+
+                    AnalysisPanel::event_ui(
+                        &self.node_events[id],
+                        &self.node_event_filter,
+                        ui,
+                        self.current_time.into(),
+                    );
+                }
+                InspectorTab::Charts => (),
+                InspectorTab::Trace => {
+                    ui.label(format!("Packets of Node ID {}", id));
+                    if let Some(time) = self.trace_ui(id, ui) {
+                        set_time!(time);
+                    }
                 }
                 InspectorTab::State => {
-                    if let Some(ref mut live) = self.live_sim {
-                        let this_node = live.inspect_node(id, self.current_time.into());
+                    let label = "Compare with previous event";
+                    let compare = egui::Checkbox::new(&mut self.compare_previous, label);
+                    ui.add_enabled(!self.live_mode && self.live_sim.is_some(), compare)
+                        .on_disabled_hover_text("Not available while stepping live");
+
+                    if self.compare_previous && !self.live_mode {
+                        self.state_diff_ui(id, ui);
+                    } else if let Some(ref mut live) = self.live_sim {
+                        let this_node = if self.live_mode {
+                            live.node(id)
+                        } else {
+                            live.inspect_node(id, self.current_time.into())
+                        };
 
                         if self.use_inspector_text_mode {
                             ui.label(format!("{this_node:#?}"));
@@ -495,6 +2370,14 @@ impl AnalysisPanel {
                                 }
                             }
                         }
+
+                        let store = live.node_store(id);
+                        ui.separator();
+                        ui.collapsing(format!("Persisted ({} keys)", store.len()), |ui| {
+                            if let Ok(value) = serde_inspector::to_value(store) {
+                                serde_inspector::any_inspector(1, value, ui);
+                            }
+                        });
                     }
                 }
             },
@@ -534,6 +2417,33 @@ impl AnalysisPanel {
 
                 ui.label(format!("SF: {}", current_transmission.sf));
 
+                ui.separator();
+                ui.add(Label::new(RichText::new("Receptions").underline().weak()));
+                let receptions = self.sim_events.iter().filter_map(|x| match x.content {
+                    LogContent::TransmissionReceived {
+                        receiver_id,
+                        transmission_id,
+                        snr,
+                        rssi,
+                    } if transmission_id == id => Some((receiver_id, snr, rssi)),
+                    _ => None,
+                });
+                let mut any = false;
+                for (receiver_id, snr, rssi) in receptions {
+                    any = true;
+                    match (snr, rssi) {
+                        (Some(snr), Some(rssi)) => ui.label(format!(
+                            "Node {receiver_id}: SNR {:.1} dB, RSSI {:.1} dBm",
+                            snr.as_db_float(),
+                            rssi.dbm()
+                        )),
+                        _ => ui.label(format!("Node {receiver_id}: not logged")),
+                    };
+                }
+                if !any {
+                    ui.label("Not received by any node");
+                }
+
                 ui.separator();
                 ui.add(Label::new(RichText::new("Header").underline().weak()));
                 ui.label(format!("{:#?}", current_transmission.header));
@@ -542,10 +2452,226 @@ impl AnalysisPanel {
                 ui.add(Label::new(RichText::new("Content").underline().weak()));
                 ui.label(format!("{:#?}", current_transmission.message_content));
             }
+            Inspectable::MessageTrace(id) => {
+                ui.label(format!("Tracing Message ID {}", id));
+
+                let hops = self.trace_hops();
+                let Some(first) = hops.first() else {
+                    ui.label("Message was never received");
+                    return;
+                };
+
+                ui.label(format!("Source: Node {}", first.transmitter_id));
+                ui.label(format!(
+                    "Reached {} nodes in {} hops",
+                    hops.iter().map(|x| x.receivers.len()).sum::<usize>(),
+                    hops.len()
+                ));
+                ui.weak("Escape or click empty space to clear");
+
+                ui.separator();
+                ui.heading("Hops");
+
+                let mut selected = None;
+                for (number, hop) in hops.iter().enumerate() {
+                    let text = format!(
+                        "{}. <{:.3}> Node {} -> {:?}",
+                        number + 1,
+                        hop.end_time,
+                        hop.transmitter_id,
+                        hop.receivers
+                    );
+                    if ui.selectable_label(false, text).clicked() {
+                        selected = Some(hop.end_time);
+                    }
+                }
+
+                if let Some(time) = selected {
+                    set_time!(time);
+                }
+            }
+            Inspectable::Nothing if self.inspector_tabs == InspectorTab::Overview => {
+                self.network_overview(ui);
+                ui.separator();
+                self.sf_breakdown_table(ui);
+            }
             _ => (),
         })
     }
 
+    fn failure_report_ui(report: &FailureReport, ui: &mut egui::Ui) {
+        let title = format!("Message {}: {}", report.message_id, report.cause);
+        ui.label(RichText::new(title).strong());
+        ui.label(format!("Reached: {:?}", report.reached));
+        ui.label(format!("Relayed by: {:?}", report.relayed_by));
+        ui.label(format!("Transmissions in range: {:?}", report.in_range));
+        ui.label(format!("Blocked here: {:?}", report.blocked_at_target));
+        ui.label(format!("Too weak here: {:?}", report.too_weak_at_target));
+    }
+
+    fn network_overview(&self, ui: &mut egui::Ui) {
+        let stats = &self.degree_stats;
+
+        ui.heading("Network");
+        ui.label("Neighbours each node can reach directly at the start of the run");
+        ui.label(format!(
+            "Degree: mean {:.2}, min {}, max {}",
+            stats.mean_degree, stats.min_degree, stats.max_degree
+        ));
+        ui.label(format!("Isolated nodes: {}", stats.isolated_nodes));
+        ui.label(if stats.connected {
+            "Every node can reach every other"
+        } else {
+            "Not connected, some nodes can't reach others"
+        });
+
+        let over_time = stats.mean_degree_over_time.iter().map(|x| x.1);
+        if let (Some(low), Some(high)) = (
+            over_time.clone().min_by(f64::total_cmp),
+            over_time.max_by(f64::total_cmp),
+        ) {
+            ui.label(format!("Mean degree during the run: {low:.2} to {high:.2}"));
+        }
+        ui.label(format!("Mean contact ratio: {:.3}", stats.mean_contact_ratio()))
+            .on_hover_text("Fraction of the run each node had a neighbour, on average");
+    }
+
+    fn sf_breakdown_table(&self, ui: &mut egui::Ui) {
+        ui.heading("Spreading Factors");
+        ui.label("Transmissions by spreading factor and bandwidth");
+
+        let total_airtime: f64 = self.sf_breakdown.iter().map(|x| x.airtime).sum();
+
+        egui::Grid::new("sf_breakdown").striped(true).show(ui, |ui| {
+            for title in ["SF", "Bandwidth", "Sent", "Airtime", "Share", "Blocked", "Mean Size"] {
+                ui.label(RichText::new(title).strong());
+            }
+            ui.end_row();
+
+            for bucket in &self.sf_breakdown {
+                let share = if total_airtime > 0.0 { bucket.airtime / total_airtime } else { 0.0 };
+
+                ui.label(format!("SF{}", bucket.sf));
+                ui.label(format!("{} kHz", bucket.bandwidth.kHz()));
+                ui.label(bucket.transmissions.to_string());
+                ui.label(format!("{:.3}s", bucket.airtime));
+                ui.label(format!("{:.1}%", share * 100.0));
+                ui.label(bucket.blocked.to_string());
+                ui.label(format!("{:.1} bytes", bucket.mean_size));
+                ui.end_row();
+            }
+        });
+    }
+
+    fn analysis_charts(&mut self, ui: &mut egui::Ui) {
+        ui.heading("Channel Utilisation");
+        ui.label("Fraction of time on air, overlapping transmissions add");
+
+        let (_, selected) = StepChart::new(&self.channel_utilisation, "s")
+            .x_range(0.0, self.end_time)
+            .cursor(self.current_time)
+            .show(ui);
+
+        if let Some(time) = selected {
+            self.prev_time = self.current_time;
+            self.current_time = time;
+        }
+
+        ui.separator();
+        ui.heading("Latency CDF");
+        ui.label("Received wanted messages at or below each latency");
+
+        StepChart::new(&self.latency_cdf, "s").show(ui);
+
+        let [p50, p90, p99] = self
+            .latency_percentiles
+            .map(|x| x.map_or("-".to_owned(), |x| format!("{x:.3}s")));
+        ui.label(format!("p50: {p50}    p90: {p90}    p99: {p99}"));
+
+        ui.separator();
+        ui.heading("Latency by Distance");
+        let distance_latency = &self.distance_latency;
+        let units = if distance_latency.hops { " hops" } else { "m" };
+        ui.label(format!(
+            "Mean latency in seconds of received wanted messages by how far the target was \
+            from the sender, over {} receptions",
+            distance_latency.pairs.len()
+        ));
+
+        let bins: Vec<(f64, f64)> = distance_latency
+            .bins
+            .iter()
+            .map(|x| (x.start, x.mean_latency))
+            .collect();
+        let end = distance_latency.bins.last().map_or(0.0, |x| x.end);
+        StepChart::new(&bins, units).x_range(0.0, end).show(ui);
+    }
+
+    /// Hop stepping buttons and a strip marking when each hop of the traced message arrived.
+    /// Returns a time to jump to.
+    fn trace_timeline(
+        &self,
+        item_background: Color32,
+        main_red: Color32,
+        ui: &mut egui::Ui,
+    ) -> Option<f64> {
+        let hops = self.trace_hops();
+        let Inspectable::MessageTrace(message_id) = self.inspect_target else {
+            return None;
+        };
+
+        let hop_times: Vec<f64> = hops.iter().map(|x| x.end_time.seconds()).collect();
+        // Stepping lands exactly on hop times so compare with a little slack
+        let done = hop_times
+            .iter()
+            .filter(|&&x| x <= self.current_time + 1e-9)
+            .count();
+
+        let mut jump = None;
+
+        ui.horizontal(|ui| {
+            ui.label(format!("Tracing message {message_id}"));
+
+            if ui.button("Prev Hop").clicked() {
+                jump = hop_times
+                    .iter()
+                    .rev()
+                    .find(|&&x| x < self.current_time - 1e-9)
+                    .copied();
+            }
+            if ui.button("Next Hop").clicked() {
+                jump = hop_times
+                    .iter()
+                    .find(|&&x| x > self.current_time + 1e-9)
+                    .copied();
+            }
+
+            ui.label(format!("Hop {done} of {}", hops.len()));
+        });
+
+        let (rect, response) = ui.allocate_exact_size(
+            egui::vec2(ui.available_width(), 12.0),
+            egui::Sense::click_and_drag(),
+        );
+        let painter = ui.painter_at(rect);
+        let x_at = |time: f64| rect.left() + (time / self.end_time) as f32 * rect.width();
+
+        painter.rect_filled(rect, 0.0, item_background);
+        for time in hop_times.iter() {
+            painter.vline(x_at(*time), rect.y_range(), Stroke::new(2.0, Color32::GREEN));
+        }
+        painter.vline(x_at(self.current_time), rect.y_range(), Stroke::new(2.0, main_red));
+
+        if let Some(pos) = response.interact_pointer_pos()
+            && (response.clicked() || response.dragged())
+        {
+            let fraction = ((pos.x - rect.left()) / rect.width()).clamp(0.0, 1.0);
+            jump = Some(fraction as f64 * self.end_time);
+        }
+
+        jump
+    }
+
     fn analysis_timeline_panel(
         &mut self,
         item_background: Color32,
@@ -569,6 +2695,41 @@ impl AnalysisPanel {
 
         ui.horizontal(|ui| {
             ui.label("Timeline");
+
+            if self.source.is_some() && ui.button("Re-run with next seed").clicked() {
+                self.rerun(self.used_seed.wrapping_add(1));
+            }
+
+            if ui.button("Export Image").clicked() {
+                self.image_export.open = true;
+            }
+
+            let title = if self.transmission_filter.is_active() {
+                "Transmissions (filtered)"
+            } else {
+                "Transmissions"
+            };
+            let node_count = self.node_settings.len();
+            ui.menu_button(title, |ui| self.transmission_filter.controls(node_count, ui));
+
+            egui::ComboBox::from_label("Node Colours")
+                .selected_text(self.colour_mode.title())
+                .show_ui(ui, |ui| {
+                    for mode in ColourMode::ALL {
+                        ui.selectable_value(&mut self.colour_mode, mode, mode.title());
+                    }
+                });
+
+            if self.trails.is_some() {
+                ui.checkbox(&mut self.show_trails, "Trails");
+                ui.add_enabled(
+                    self.show_trails,
+                    egui::Slider::new(&mut self.trail_length, 5.0..=600.0)
+                        .suffix("s")
+                        .logarithmic(true),
+                );
+            }
+
             ui.centered_and_justified(|ui| {
                 ui.label(format!(
                     "Results for {} with seed {}",
@@ -579,24 +2740,13 @@ impl AnalysisPanel {
 
         ui.horizontal(|ui| {
             if ui.button("Prev Sim Event").clicked() {
-                if let Some(e) = self
-                    .sim_events
-                    .iter()
-                    .rev()
-                    .skip_while(|x| x.time >= self.current_time.into())
-                    .next()
-                {
-                    set_time!(e.time);
+                if let Some(time) = self.prev_sim_event() {
+                    set_time!(time);
                 }
             }
             if ui.button("Next Sim Event").clicked() {
-                if let Some(e) = self
-                    .sim_events
-                    .iter()
-                    .skip_while(|x| x.time <= self.current_time.into())
-                    .next()
-                {
-                    set_time!(e.time);
+                if let Some(time) = self.next_sim_event() {
+                    set_time!(time);
                 }
             }
 
@@ -611,9 +2761,7 @@ impl AnalysisPanel {
             ui.add_space(20.0);
 
             if ui.button("Play / Pause").clicked() {
-                self.playing = !self.playing;
-                self.play_offset = ui.input(|i| i.time);
-                self.play_time_offset = self.current_time;
+                self.toggle_play(ui.input(|i| i.time));
             }
 
             ui.label("at");
@@ -628,6 +2776,8 @@ impl AnalysisPanel {
             });
         });
 
+        self.live_controls(ui);
+
         let mut minutes = (self.current_time / 60.0).floor();
         let mut seconds = self.current_time % 60.0;
         ui.spacing_mut().slider_width = 900.0;
@@ -650,6 +2800,10 @@ impl AnalysisPanel {
             .handle_shape(egui::style::HandleShape::Rect { aspect_ratio: 0.4 })
             .text("Seconds");
 
+        if let Some(time) = self.marker_strip(ui) {
+            set_time!(time);
+        }
+
         ui.add(mins_slider);
         ui.add(secs_slider);
 
@@ -659,6 +2813,10 @@ impl AnalysisPanel {
             set_time!(slider_time);
         }
 
+        if let Some(time) = self.trace_timeline(item_background, main_red, ui) {
+            set_time!(time);
+        }
+
         ui.add_space(5.0);
     }
 }
@@ -1,126 +1,587 @@
-use std::{cell::RefCell, fs::read_dir, path::PathBuf, sync::Arc};
+use std::{
+    cell::RefCell,
+    fs::{metadata, read_dir},
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::SystemTime,
+};
 
-use egui::{CentralPanel, ScrollArea, SidePanel, Widget};
+use egui::{CentralPanel, Color32, Modal, RichText, ScrollArea, SidePanel, Widget};
+use egui_extras::{Column, TableBuilder};
 
 use frogcore::{
     scenario::{Scenario, ScenarioIdentity},
-    sim_file::{load_file, SimOutput},
+    sim_file::{FileSummary, SimOutput, load_file, load_output, summarise_file},
 };
 
-use crate::{GlobalAction, GuiStore};
-use serde_inspector::AnyInspector;
+use crate::{GlobalAction, GuiStore, recent::RecentKind};
+use serde_inspector::{AnyInspector, Value, variant_name};
 
 pub struct BrowserPanel {
     store: Arc<RefCell<GuiStore>>,
-    sim_files: Vec<PathBuf>,
+    current_dir: PathBuf,
+    entries: Vec<BrowserEntry>,
     active_file: Option<usize>,
     inspect_file: InspectableFile,
+    /// Entry of the open pack shown in full under its list
+    pack_entry: Option<usize>,
+    file_action: Option<FileAction>,
+    error_message: Option<String>,
+}
+
+struct BrowserEntry {
+    path: PathBuf,
+    is_dir: bool,
+    /// `None` for directories and files that couldn't be read
+    summary: Option<FileSummary>,
+    size: u64,
+    modified: Option<SystemTime>,
+}
+
+/// File operation waiting on a confirmation modal.
+enum FileAction {
+    Delete(PathBuf),
+    Rename { path: PathBuf, new_name: String },
 }
 
 impl BrowserPanel {
     pub fn new(store: Arc<RefCell<GuiStore>>) -> BrowserPanel {
-        let sim_files = read_sim_files();
+        let current_dir = std::fs::canonicalize(".").unwrap_or_else(|_| PathBuf::from("."));
+        let entries = read_entries(&current_dir);
 
         BrowserPanel {
             store,
-            sim_files,
+            current_dir,
+            entries,
             active_file: None,
             inspect_file: InspectableFile::Nothing,
+            pack_entry: None,
+            file_action: None,
+            error_message: None,
         }
     }
 
     pub fn refresh(&mut self) {
-        let sim_files = read_sim_files();
-        self.sim_files = sim_files;
+        self.entries = read_entries(&self.current_dir);
         self.active_file = None;
         self.inspect_file = InspectableFile::Nothing;
+        self.pack_entry = None;
+    }
+
+    fn navigate(&mut self, dir: PathBuf) {
+        self.current_dir = dir;
+        self.error_message = None;
+        self.refresh();
+    }
+
+    /// Selects a file and loads it for the inspector.
+    fn select(&mut self, index: usize) {
+        let entry = &self.entries[index];
+        let path = entry.path.clone();
+
+        self.active_file = Some(index);
+        self.pack_entry = None;
+        self.inspect_file = match entry.summary {
+            Some(FileSummary::Pack { .. }) => load_file::<Vec<ScenarioIdentity>>(path)
+                .map_or(InspectableFile::Nothing, |pack| {
+                    InspectableFile::Simpack(pack.into_iter().map(PackEntry::new).collect())
+                }),
+            Some(FileSummary::ScenarioIdentity) => {
+                load_file(path).map_or(InspectableFile::Nothing, InspectableFile::ScenarioIdentity)
+            }
+            Some(FileSummary::Scenario { .. }) => {
+                load_file(path).map_or(InspectableFile::Nothing, InspectableFile::Scenario)
+            }
+            Some(FileSummary::Results { .. }) => {
+                load_output(path).map_or(InspectableFile::Nothing, InspectableFile::Results)
+            }
+            None => InspectableFile::Nothing,
+        };
+    }
+
+    /// Sends results to the analysis panel and scenarios to the editor.
+    /// Packs only list their entries as there is no single scenario to open.
+    fn open(&mut self, index: usize) {
+        self.select(index);
+
+        let (action, kind) = match &self.inspect_file {
+            InspectableFile::ScenarioIdentity(identity) => (
+                GlobalAction::SetScenario(identity.create()),
+                RecentKind::ScenarioIdentity,
+            ),
+            InspectableFile::Scenario(scenario) => (
+                GlobalAction::SetScenario(scenario.clone()),
+                RecentKind::Scenario,
+            ),
+            InspectableFile::Results(output) => (
+                GlobalAction::OpenResults(Box::new(output.clone())),
+                RecentKind::Results,
+            ),
+            InspectableFile::Simpack(_) | InspectableFile::Nothing => return,
+        };
+
+        self.store.borrow_mut().global_action = action;
+        self.remember(kind);
+    }
+
+    /// Adds the selected file to the recent files.
+    fn remember(&self, kind: RecentKind) {
+        if let Some(index) = self.active_file {
+            let path = &self.entries[index].path;
+            self.store.borrow_mut().recent_files.touch(path, kind);
+        }
+    }
+
+    fn breadcrumbs(&mut self, ui: &mut egui::Ui) {
+        let mut ancestors: Vec<PathBuf> = self
+            .current_dir
+            .ancestors()
+            .map(Path::to_path_buf)
+            .collect();
+        ancestors.reverse();
+
+        let mut target = None;
+
+        ui.horizontal_wrapped(|ui| {
+            for (i, dir) in ancestors.iter().enumerate() {
+                if i > 1 {
+                    ui.label("/");
+                }
+
+                let name = match dir.file_name() {
+                    Some(name) => name.to_string_lossy().into_owned(),
+                    None => dir.display().to_string(),
+                };
+
+                if ui.small_button(name).clicked() {
+                    target = Some(dir.clone());
+                }
+            }
+        });
+
+        if let Some(target) = target {
+            self.navigate(target);
+        }
+    }
+
+    fn file_table(&mut self, ui: &mut egui::Ui) {
+        let mut clicked = None;
+        let mut opened = None;
+
+        TableBuilder::new(ui)
+            .striped(true)
+            .sense(egui::Sense::click())
+            .column(Column::remainder().at_least(150.0).clip(true))
+            .columns(Column::auto().at_least(70.0), 4)
+            .header(20.0, |mut header| {
+                for title in ["Name", "Type", "Contents", "Modified", "Size"] {
+                    header.col(|ui| {
+                        ui.label(RichText::new(title).strong());
+                    });
+                }
+            })
+            .body(|body| {
+                body.rows(18.0, self.entries.len(), |mut row| {
+                    let index = row.index();
+                    let entry = &self.entries[index];
+                    row.set_selected(self.active_file == Some(index));
+
+                    let name = entry.path.file_name().unwrap_or_default().to_string_lossy();
+
+                    row.col(|ui| {
+                        match entry.is_dir {
+                            true => ui.label(format!("📁 {name}")),
+                            false => ui.label(name.as_ref()),
+                        };
+                    });
+                    row.col(|ui| {
+                        ui.label(type_label(entry));
+                    });
+                    row.col(|ui| {
+                        ui.label(contents_label(entry.summary));
+                    });
+                    row.col(|ui| {
+                        ui.label(entry.modified.map_or("-".to_owned(), format_age));
+                    });
+                    row.col(|ui| {
+                        if !entry.is_dir {
+                            ui.label(format_size(entry.size));
+                        }
+                    });
+
+                    let response = row.response();
+                    if response.double_clicked() {
+                        opened = Some(index);
+                    } else if response.clicked() {
+                        clicked = Some(index);
+                    }
+
+                    if !entry.is_dir {
+                        response.context_menu(|ui| {
+                            if ui.button("Rename").clicked() {
+                                self.file_action = Some(FileAction::Rename {
+                                    path: entry.path.clone(),
+                                    new_name: name.clone().into_owned(),
+                                });
+                                ui.close_menu();
+                            }
+                            if ui.button("Delete").clicked() {
+                                self.file_action = Some(FileAction::Delete(entry.path.clone()));
+                                ui.close_menu();
+                            }
+                        });
+                    }
+                });
+            });
+
+        // Directories are entered on a single click, files need a double click to open
+        if let Some(index) = clicked.or(opened) {
+            match (self.entries[index].is_dir, opened.is_some()) {
+                (true, _) => self.navigate(self.entries[index].path.clone()),
+                (false, true) => self.open(index),
+                (false, false) => self.select(index),
+            }
+        }
+    }
+
+    /// Lists the entries of a pack, creating an entry's scenario only when it is opened or run.
+    fn pack_view(&mut self, ui: &mut egui::Ui) {
+        let InspectableFile::Simpack(pack) = &self.inspect_file else {
+            return;
+        };
+
+        let mut action = None;
+        let mut inspected = self.pack_entry;
+
+        ui.label(format!("{} entries", pack.len()));
+        let list_height = ui.available_height() * 0.5;
+        TableBuilder::new(ui)
+            .striped(true)
+            .sense(egui::Sense::click())
+            .max_scroll_height(list_height)
+            .column(Column::auto().at_least(40.0))
+            .column(Column::auto().at_least(150.0))
+            .column(Column::auto().at_least(60.0))
+            .column(Column::remainder().at_least(150.0).clip(true))
+            .column(Column::auto().at_least(140.0))
+            .header(20.0, |mut header| {
+                for title in ["#", "Generator", "Seed", "Parameters", ""] {
+                    header.col(|ui| {
+                        ui.label(RichText::new(title).strong());
+                    });
+                }
+            })
+            .body(|body| {
+                body.rows(20.0, pack.len(), |mut row| {
+                    let index = row.index();
+                    let entry = &pack[index];
+                    row.set_selected(inspected == Some(index));
+
+                    row.col(|ui| {
+                        ui.label(index.to_string());
+                    });
+                    row.col(|ui| {
+                        ui.label(&entry.generator);
+                    });
+                    row.col(|ui| {
+                        ui.label(entry.seed.map_or("-".to_owned(), |x| x.to_string()));
+                    });
+                    row.col(|ui| {
+                        ui.label(&entry.parameters).on_hover_text(&entry.parameters);
+                    });
+                    row.col(|ui| {
+                        // Custom scenarios can't be recreated from their identity
+                        ui.add_enabled_ui(entry.seed.is_some(), |ui| {
+                            if ui.small_button("Open in editor").clicked() {
+                                action = Some(GlobalAction::SetScenario(entry.identity.create()));
+                            }
+                            if ui.small_button("Run").clicked() {
+                                action = Some(GlobalAction::RunScenario(entry.identity.create()));
+                            }
+                        });
+                    });
+
+                    if row.response().clicked() {
+                        inspected = Some(index);
+                    }
+                });
+            });
+
+        if let Some(index) = inspected {
+            ui.separator();
+            ui.label(format!("Entry {index}"));
+            ScrollArea::vertical().show(ui, |ui| {
+                let val = serde_inspector::to_value(&pack[index].identity).unwrap();
+                ui.add(&mut AnyInspector::new(val, index as u64));
+            });
+        }
+
+        self.pack_entry = inspected;
+        if let Some(action) = action {
+            self.store.borrow_mut().global_action = action;
+        }
+    }
+
+    fn file_action_modal(&mut self, ctx: &egui::Context) {
+        let Some(action) = &mut self.file_action else {
+            return;
+        };
+
+        let mut confirmed = false;
+        let mut cancelled = false;
+
+        let modal = Modal::new("File Action Modal".into()).show(ctx, |ui| {
+            match action {
+                FileAction::Delete(path) => {
+                    ui.heading(format!("Delete {}?", path.display()));
+                    ui.label("This can't be undone.");
+                }
+                FileAction::Rename { path, new_name } => {
+                    ui.heading(format!("Rename {}", path.display()));
+                    ui.text_edit_singleline(new_name);
+                }
+            }
+
+            ui.horizontal(|ui| {
+                confirmed = ui.button("Confirm").clicked();
+                cancelled = ui.button("Cancel").clicked();
+            });
+        });
+
+        if cancelled || modal.should_close() {
+            self.file_action = None;
+            return;
+        }
+
+        if !confirmed {
+            return;
+        }
+
+        let result = match self.file_action.take().unwrap() {
+            FileAction::Delete(path) => std::fs::remove_file(&path)
+                .map_err(|e| format!("Could not delete {}: {e}", path.display())),
+            FileAction::Rename { path, new_name } => rename_file(&path, &new_name),
+        };
+
+        self.error_message = result.err();
+        self.refresh();
     }
 }
 
 const EXTENSIONS: [&str; 4] = ["json", "sim", "simpack", "rmp"];
 
-fn read_sim_files() -> Vec<PathBuf> {
-    let Ok(dir) = read_dir(".") else {
+/// Subdirectories followed by simulation files, each sorted by name.
+fn read_entries(dir: &Path) -> Vec<BrowserEntry> {
+    let Ok(dir) = read_dir(dir) else {
         return Vec::new();
     };
 
-    dir.filter_map(|x| x.ok().map(|inner| inner.path()))
-        .filter(|x| x.extension().map(|ext| in_extensions(ext)).unwrap_or(false))
-        .collect()
+    let mut entries: Vec<BrowserEntry> = dir
+        .filter_map(|x| x.ok().map(|inner| inner.path()))
+        .filter(|x| x.is_dir() || x.extension().map(in_extensions).unwrap_or(false))
+        .map(|path| {
+            let is_dir = path.is_dir();
+            let metadata = metadata(&path).ok();
+
+            BrowserEntry {
+                summary: match is_dir {
+                    true => None,
+                    false => summarise_file(path.clone()).ok(),
+                },
+                size: metadata.as_ref().map_or(0, |x| x.len()),
+                modified: metadata.and_then(|x| x.modified().ok()),
+                is_dir,
+                path,
+            }
+        })
+        .collect();
+
+    entries.sort_by(|a, b| b.is_dir.cmp(&a.is_dir).then_with(|| a.path.cmp(&b.path)));
+    entries
 }
 
 fn in_extensions(s: &std::ffi::OsStr) -> bool {
     EXTENSIONS.iter().any(|x| s.eq_ignore_ascii_case(x))
 }
 
+/// Generator type and key parameters of a pack entry, worked out once when the pack is opened.
+#[derive(Debug, Clone)]
+struct PackEntry {
+    identity: ScenarioIdentity,
+    generator: String,
+    /// `None` for custom scenarios, which can't be recreated
+    seed: Option<u64>,
+    /// The generator's fields that fit on one line, such as the node count
+    parameters: String,
+}
+
+impl PackEntry {
+    fn new(identity: ScenarioIdentity) -> PackEntry {
+        let ScenarioIdentity::Generated { generator, seed } = &identity else {
+            return PackEntry {
+                identity,
+                generator: "Custom".to_owned(),
+                seed: None,
+                parameters: String::new(),
+            };
+        };
+
+        // Generators are struct variants, a map from the variant's name to one of its fields
+        let value = serde_inspector::to_value(generator).unwrap();
+        let name = variant_name(&value).unwrap_or("Unknown").to_owned();
+        let parameters = match &value {
+            Value::Map(variant) => match variant.values().next() {
+                Some(Value::Map(fields)) => fields
+                    .iter()
+                    .filter_map(|(field, value)| {
+                        Some(format!("{}: {}", leaf_text(field)?, leaf_text(value)?))
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                _ => String::new(),
+            },
+            _ => String::new(),
+        };
+
+        PackEntry {
+            seed: Some(*seed),
+            identity,
+            generator: name,
+            parameters,
+        }
+    }
+}
+
+/// A value written out on its own, or `None` if it has fields of its own.
+fn leaf_text(value: &Value) -> Option<String> {
+    let text = match value {
+        Value::Bool(x) => x.to_string(),
+        Value::U8(x) => x.to_string(),
+        Value::U16(x) => x.to_string(),
+        Value::U32(x) => x.to_string(),
+        Value::U64(x) => x.to_string(),
+        Value::I8(x) => x.to_string(),
+        Value::I16(x) => x.to_string(),
+        Value::I32(x) => x.to_string(),
+        Value::I64(x) => x.to_string(),
+        Value::F32(x) => x.to_string(),
+        Value::F64(x) => x.to_string(),
+        Value::Char(x) => x.to_string(),
+        Value::String(x) => x.clone(),
+        Value::Newtype(inner) => return leaf_text(inner),
+        _ => return None,
+    };
+    Some(text)
+}
+
+/// Renames a file without moving it out of its directory or replacing another file.
+fn rename_file(path: &Path, new_name: &str) -> Result<(), String> {
+    if new_name.is_empty() || new_name.contains(std::path::is_separator) {
+        return Err(format!("{new_name:?} is not a valid file name"));
+    }
+
+    let new_path = path.with_file_name(new_name);
+    if new_path.exists() {
+        return Err(format!("{} already exists", new_path.display()));
+    }
+
+    std::fs::rename(path, &new_path)
+        .map_err(|e| format!("Could not rename {}: {e}", path.display()))
+}
+
+fn type_label(entry: &BrowserEntry) -> &'static str {
+    match (entry.is_dir, entry.summary) {
+        (true, _) => "Folder",
+        (false, Some(FileSummary::Scenario { .. })) => "Scenario",
+        (false, Some(FileSummary::ScenarioIdentity)) => "Scenario Id",
+        (false, Some(FileSummary::Pack { .. })) => "Pack",
+        (false, Some(FileSummary::Results { .. })) => "Results",
+        (false, None) => "Unknown",
+    }
+}
+
+fn contents_label(summary: Option<FileSummary>) -> String {
+    match summary {
+        Some(FileSummary::Scenario { nodes, messages }) => {
+            format!("{nodes} nodes, {messages} messages")
+        }
+        Some(FileSummary::Pack { entries }) => format!("{entries} entries"),
+        Some(FileSummary::Results { transmissions }) => format!("{transmissions} transmissions"),
+        Some(FileSummary::ScenarioIdentity) | None => String::new(),
+    }
+}
+
+fn format_age(time: SystemTime) -> String {
+    let secs = SystemTime::now()
+        .duration_since(time)
+        .map_or(0, |x| x.as_secs());
+
+    match secs {
+        ..60 => "just now".to_owned(),
+        60..3600 => format!("{} min ago", secs / 60),
+        3600..86400 => format!("{} h ago", secs / 3600),
+        _ => format!("{} days ago", secs / 86400),
+    }
+}
+
+fn format_size(bytes: u64) -> String {
+    match bytes {
+        ..1024 => format!("{bytes} B"),
+        1024..1048576 => format!("{:.1} KB", bytes as f64 / 1024.),
+        _ => format!("{:.1} MB", bytes as f64 / 1048576.),
+    }
+}
+
 impl Widget for &mut BrowserPanel {
     fn ui(self, ui: &mut egui::Ui) -> egui::Response {
-        SidePanel::left("file_browser").show_inside(ui, |ui| {
-            if ui.button("Refresh").clicked() {
-                self.refresh();
-            }
+        self.file_action_modal(ui.ctx());
 
-            self.sim_files.iter().enumerate().for_each(|(index, path)| {
-                if ui
-                    .selectable_label(
-                        self.active_file.map(|x| x == index).unwrap_or(false),
-                        path.file_name().unwrap().to_str().unwrap(),
-                    )
-                    .clicked()
-                {
-                    self.active_file = Some(index);
-
-                    let inspectable = if let Ok(inner) = load_file(path.clone()) {
-                        InspectableFile::ScenarioIdentity(inner)
-                    } else if let Ok(inner) = load_file(path.clone()) {
-                        InspectableFile::Simpack(inner)
-                    } else if let Ok(inner) = load_file(path.clone()) {
-                        InspectableFile::Scenario(inner)
-                    } else if let Ok(inner) = load_file(path.clone()) {
-                        InspectableFile::Results(inner)
-                    } else {
-                        InspectableFile::Nothing
-                    };
-
-                    self.inspect_file = inspectable;
+        SidePanel::left("file_browser")
+            .resizable(true)
+            .default_width(650.0)
+            .show_inside(ui, |ui| {
+                ui.horizontal(|ui| {
+                    if ui.button("Refresh").clicked() {
+                        self.refresh();
+                    }
+                    self.breadcrumbs(ui);
+                });
+
+                if let Some(message) = &self.error_message {
+                    ui.label(RichText::new(message).color(Color32::RED));
                 }
+
+                ui.separator();
+                self.file_table(ui);
             });
-        });
 
         CentralPanel::default().show_inside(ui, |ui| {
-            ScrollArea::vertical().show(ui, |ui| {
-                let Some(active_file) = self.active_file else {
-                    return;
-                };
+            let Some(active_file) = self.active_file else {
+                return;
+            };
 
-                ui.heading(
-                    self.sim_files[active_file]
-                        .file_name()
-                        .unwrap()
-                        .to_str()
-                        .unwrap(),
-                );
+            ui.heading(
+                self.entries[active_file]
+                    .path
+                    .file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy(),
+            );
 
-                match &self.inspect_file {
-                    InspectableFile::Nothing => (),
-                    InspectableFile::Simpack(identities) => {
-                        for (index, scen_id) in identities.iter().enumerate() {
-                            ui.separator();
-
-                            if ui.button("Load").clicked() {
-                                self.store.borrow_mut().global_action =
-                                    GlobalAction::SetScenario(scen_id.create())
-                            }
+            // Packs scroll their own list, as they can have thousands of entries
+            if let InspectableFile::Simpack(_) = self.inspect_file {
+                self.pack_view(ui);
+                return;
+            }
 
-                            let val = serde_inspector::to_value(scen_id).unwrap();
-                            ui.add(&mut AnyInspector::new(val, index as u64));
-                            ui.separator();
-                        }
-                    }
+            ScrollArea::vertical().show(ui, |ui| {
+                match &self.inspect_file {
+                    InspectableFile::Nothing | InspectableFile::Simpack(_) => (),
                     InspectableFile::ScenarioIdentity(identity) => {
                         if ui.button("Load").clicked() {
                             self.store.borrow_mut().global_action =
-                                GlobalAction::SetScenario(identity.create())
+                                GlobalAction::SetScenario(identity.create());
+                            self.remember(RecentKind::ScenarioIdentity);
                         }
 
                         let val = serde_inspector::to_value(identity).unwrap();
@@ -131,25 +592,43 @@ impl Widget for &mut BrowserPanel {
 
                         if ui.button("Load").clicked() {
                             self.store.borrow_mut().global_action =
-                                GlobalAction::SetScenario(scenario.clone())
+                                GlobalAction::SetScenario(scenario.clone());
+                            self.remember(RecentKind::Scenario);
                         }
 
                         let val = serde_inspector::to_value(identity).unwrap();
                         ui.add(&mut AnyInspector::new(val, 0));
-                    },
+                    }
                     InspectableFile::Results(sim_output) => {
                         let scenario_id = &sim_output.complete_identity.scenario_identity;
 
-                        if ui.button("Load").clicked() {
-                            self.store.borrow_mut().global_action =
-                                GlobalAction::SetScenario(scenario_id.create())
-                        }
+                        ui.label(format!(
+                            "Results from {} with seed {}",
+                            sim_output.complete_identity.model_id,
+                            sim_output.complete_identity.simulation_seed
+                        ));
+
+                        ui.horizontal(|ui| {
+                            if ui.button("Open Results").clicked() {
+                                self.store.borrow_mut().global_action =
+                                    GlobalAction::OpenResults(Box::new(sim_output.clone()));
+                                self.remember(RecentKind::Results);
+                            }
+
+                            // Custom scenarios can't be recreated from their identity
+                            if *scenario_id != ScenarioIdentity::Custom
+                                && ui.button("Load Scenario").clicked()
+                            {
+                                self.store.borrow_mut().global_action =
+                                    GlobalAction::SetScenario(scenario_id.create())
+                            }
+                        });
 
                         let val = serde_inspector::to_value(scenario_id).unwrap();
                         ui.add(&mut AnyInspector::new(val, 0));
-                    },
+                    }
                 }
-            })
+            });
         });
 
         ui.response()
@@ -159,7 +638,7 @@ impl Widget for &mut BrowserPanel {
 #[derive(Debug, Clone)]
 enum InspectableFile {
     Nothing,
-    Simpack(Vec<ScenarioIdentity>),
+    Simpack(Vec<PackEntry>),
     ScenarioIdentity(ScenarioIdentity),
     Scenario(Scenario),
     Results(SimOutput),
@@ -0,0 +1,149 @@
+use egui::{CentralPanel, Color32, Frame, RichText, Slider, TopBottomPanel, Widget};
+
+use crate::{analysis_panel::AnalysisPanel, convert_rect};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    A,
+    B,
+}
+
+/// Two runs of the same scenario shown side by side on a shared timeline.
+pub struct ComparisonPanel {
+    a: AnalysisPanel,
+    b: AnalysisPanel,
+    current_time: f64,
+    /// Scenes are fitted to their half of the window on the next frame
+    needs_fit: bool,
+    /// Run the user chose to keep when leaving the comparison
+    pub keep: Option<Side>,
+}
+
+impl ComparisonPanel {
+    pub fn new(a: AnalysisPanel, b: AnalysisPanel) -> ComparisonPanel {
+        ComparisonPanel {
+            a,
+            b,
+            current_time: 0.0,
+            needs_fit: true,
+            keep: None,
+        }
+    }
+
+    pub fn replace(&mut self, side: Side, panel: AnalysisPanel) {
+        match side {
+            Side::A => self.a = panel,
+            Side::B => self.b = panel,
+        }
+        self.needs_fit = true;
+    }
+
+    /// Ends the comparison, giving back the run the user chose to keep.
+    pub fn into_kept(self) -> AnalysisPanel {
+        let mut kept = match self.keep {
+            Some(Side::B) => self.b,
+            _ => self.a,
+        };
+
+        kept.leave_comparison();
+        kept
+    }
+
+    fn metrics_table(&self, ui: &mut egui::Ui) {
+        egui::Grid::new("comparison_metrics")
+            .striped(true)
+            .min_col_width(120.0)
+            .show(ui, |ui| {
+                for title in ["Metric", "A", "B", "Delta (B - A)"] {
+                    ui.label(RichText::new(title).strong());
+                }
+                ui.end_row();
+
+                for (&(title, a), &(_, b)) in self.a.headline().iter().zip(self.b.headline()) {
+                    ui.label(title);
+                    ui.label(format!("{a:.3}"));
+                    ui.label(format!("{b:.3}"));
+                    ui.label(format!("{:+.3}", b - a));
+                    ui.end_row();
+                }
+            });
+    }
+}
+
+impl Widget for &mut ComparisonPanel {
+    fn ui(self, ui: &mut egui::Ui) -> egui::Response {
+        TopBottomPanel::top("comparison_timeline").show_inside(ui, |ui| {
+            ui.horizontal(|ui| {
+                ui.label(RichText::new("Comparing").strong());
+                ui.label(format!("A: {}", self.a.run_label()));
+                ui.label(format!("B: {}", self.b.run_label()));
+
+                ui.add_space(20.0);
+
+                if ui.button("Keep A").clicked() {
+                    self.keep = Some(Side::A);
+                }
+                if ui.button("Keep B").clicked() {
+                    self.keep = Some(Side::B);
+                }
+            });
+
+            let end_time = self.a.end_time().max(self.b.end_time());
+            ui.spacing_mut().slider_width = ui.available_width() - 150.0;
+            ui.add(
+                Slider::new(&mut self.current_time, 0.0..=end_time)
+                    .text("Time")
+                    .suffix("s"),
+            );
+        });
+
+        self.a.set_time(self.current_time);
+        self.b.set_time(self.current_time);
+
+        TopBottomPanel::top("comparison_metrics").show_inside(ui, |ui| self.metrics_table(ui));
+
+        TopBottomPanel::bottom("comparison_details")
+            .resizable(true)
+            .default_height(350.0)
+            .show_inside(ui, |ui| {
+                ui.columns_const(|[col_a, col_b]| {
+                    col_a.push_id("A", |ui| self.a.comparison_details(ui));
+                    col_b.push_id("B", |ui| self.b.comparison_details(ui));
+                });
+            });
+
+        // Either inspector can jump to a time, which then moves both runs
+        for time in [self.a.current_time(), self.b.current_time()] {
+            if time != self.current_time {
+                self.current_time = time;
+            }
+        }
+
+        let central_rect = CentralPanel::default()
+            .frame(Frame::NONE)
+            .show_inside(ui, |ui| ui.response())
+            .inner
+            .rect;
+
+        let (left, right) = central_rect.split_left_right_at_fraction(0.5);
+        self.a
+            .comparison_scene(ui, convert_rect(left), self.needs_fit);
+        self.b
+            .comparison_scene(ui, convert_rect(right), self.needs_fit);
+        self.needs_fit = false;
+
+        let painter = ui.painter();
+        painter.vline(right.left(), right.y_range(), (2.0, Color32::BLACK));
+        for (rect, name) in [(left, "A"), (right, "B")] {
+            painter.text(
+                rect.left_top() + egui::vec2(10.0, 10.0),
+                egui::Align2::LEFT_TOP,
+                name,
+                egui::FontId::proportional(28.0),
+                Color32::WHITE,
+            );
+        }
+
+        ui.response()
+    }
+}
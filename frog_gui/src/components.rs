@@ -1,4 +1,4 @@
-use egui::{DragValue, WidgetText};
+use egui::{Align2, DragValue, FontId, Pos2, Rect, Sense, Stroke, WidgetText, pos2, vec2};
 
 pub trait UiExt {
     fn unit_edit(
@@ -38,3 +38,144 @@ impl UiExt for &mut egui::Ui {
         })
     }
 }
+
+/// Step chart of a series drawn straight onto the painter.
+/// Each point holds its value until the next point.
+pub struct StepChart<'a> {
+    points: &'a [(f64, f64)],
+    x_range: (f64, f64),
+    x_units: &'a str,
+    cursor: Option<f64>,
+    height: f32,
+}
+
+impl<'a> StepChart<'a> {
+    pub fn new(points: &'a [(f64, f64)], x_units: &'a str) -> Self {
+        let x_max = points.last().map(|x| x.0).unwrap_or(0.0);
+
+        StepChart {
+            points,
+            x_range: (points.first().map(|x| x.0).unwrap_or(0.0).min(0.0), x_max),
+            x_units,
+            cursor: None,
+            height: 120.0,
+        }
+    }
+
+    pub fn x_range(mut self, min: f64, max: f64) -> Self {
+        self.x_range = (min, max);
+        self
+    }
+
+    /// Draws a vertical line at this x value.
+    pub fn cursor(mut self, x: f64) -> Self {
+        self.cursor = Some(x);
+        self
+    }
+
+    /// Returns the x value under the pointer while the chart is clicked or dragged.
+    pub fn show(self, ui: &mut egui::Ui) -> (egui::Response, Option<f64>) {
+        let (rect, response) = ui.allocate_exact_size(
+            vec2(ui.available_width(), self.height),
+            Sense::click_and_drag(),
+        );
+
+        let visuals = ui.visuals().widgets.noninteractive;
+        let text_color = ui.visuals().text_color();
+        let font = FontId::monospace(10.0);
+        let painter = ui.painter_at(rect);
+
+        painter.rect_filled(rect, 0.0, visuals.bg_fill);
+
+        let (x_min, x_max) = self.x_range;
+        let x_span = (x_max - x_min).max(f64::EPSILON);
+        let y_max = self
+            .points
+            .iter()
+            .map(|x| x.1)
+            .fold(0.0, f64::max)
+            .max(f64::EPSILON);
+
+        let plot = Rect::from_min_max(rect.min + vec2(36.0, 6.0), rect.max - vec2(6.0, 16.0));
+        let to_screen = |x: f64, y: f64| {
+            pos2(
+                plot.left() + ((x - x_min) / x_span) as f32 * plot.width(),
+                plot.bottom() - (y / y_max) as f32 * plot.height(),
+            )
+        };
+
+        painter.line_segment([plot.left_bottom(), plot.right_bottom()], visuals.bg_stroke);
+        painter.line_segment([plot.left_bottom(), plot.left_top()], visuals.bg_stroke);
+
+        let label = |pos, align, text: String| {
+            painter.text(pos, align, text, font.clone(), text_color);
+        };
+        label(
+            plot.left_top() - vec2(4.0, 0.0),
+            Align2::RIGHT_TOP,
+            format!("{y_max:.2}"),
+        );
+        label(
+            plot.left_bottom() - vec2(4.0, 0.0),
+            Align2::RIGHT_BOTTOM,
+            "0".into(),
+        );
+        label(
+            plot.left_bottom() + vec2(0.0, 2.0),
+            Align2::LEFT_TOP,
+            format!("{x_min:.1}{}", self.x_units),
+        );
+        label(
+            plot.right_bottom() + vec2(0.0, 2.0),
+            Align2::RIGHT_TOP,
+            format!("{x_max:.1}{}", self.x_units),
+        );
+
+        let mut line = Vec::with_capacity(self.points.len() * 2 + 1);
+        let mut prev_y = 0.0;
+        for &(x, y) in self.points {
+            line.push(to_screen(x, prev_y));
+            line.push(to_screen(x, y));
+            prev_y = y;
+        }
+        if !line.is_empty() {
+            line.push(to_screen(x_max, prev_y));
+        }
+        painter.line(line, Stroke::new(1.5, visuals.fg_stroke.color));
+
+        if let Some(x) = self.cursor {
+            let top = to_screen(x, y_max);
+            painter.line_segment(
+                [top, pos2(top.x, plot.bottom())],
+                Stroke::new(1.0, text_color),
+            );
+        }
+
+        let pointer_x = |pos: Pos2| {
+            x_min + ((pos.x - plot.left()) / plot.width()).clamp(0.0, 1.0) as f64 * x_span
+        };
+
+        if let Some(pos) = response.hover_pos() {
+            let x = pointer_x(pos);
+            let y = self
+                .points
+                .iter()
+                .take_while(|p| p.0 <= x)
+                .last()
+                .map(|p| p.1)
+                .unwrap_or(0.0);
+            label(
+                plot.right_top(),
+                Align2::RIGHT_TOP,
+                format!("{x:.2}{}: {y:.3}", self.x_units),
+            );
+        }
+
+        let selected = (response.clicked() || response.dragged())
+            .then(|| response.interact_pointer_pos())
+            .flatten()
+            .map(pointer_x);
+
+        (response, selected)
+    }
+}
@@ -1,12 +1,12 @@
 use std::cell::RefCell;
 use std::sync::Arc;
 
-use egui::{CentralPanel, ComboBox, Frame, Modal, RichText, TopBottomPanel, vec2};
+use egui::{CentralPanel, ComboBox, DragValue, Frame, Modal, RichText, TopBottomPanel, vec2};
 
 use frogcore::{
     node::{MODEL_LIST, ModelSelection},
-    scenario::Scenario,
-    sim_file::write_file,
+    scenario::{Scenario, ScenarioIdentity},
+    sim_file::{SimOutput, load_file, load_output, write_file},
     simulation::{MessageContent, data_structs::LogItem},
     units::Time,
 };
@@ -14,14 +14,21 @@ use frogcore::{
 use macroquad::prelude::*;
 
 use crate::{
-    analysis_panel::AnalysisPanel, browser_panel::BrowserPanel,
+    analysis_panel::AnalysisPanel,
+    browser_panel::BrowserPanel,
+    comparison_panel::{ComparisonPanel, Side},
+    model_sweep::ModelSweep,
+    recent::{RecentFile, RecentFiles, RecentKind},
     scenario_editor_panel::ScenarioEditorPanel, scenario_generator_panel::ScenarioGeneratorPanel,
     style::dark_visuals,
 };
 
 pub mod analysis_panel;
 pub mod browser_panel;
+mod comparison_panel;
 mod components;
+mod model_sweep;
+mod recent;
 pub mod scenario_editor_panel;
 mod scenario_generator_panel;
 mod scene;
@@ -42,8 +49,8 @@ fn window_conf() -> macroquad::conf::Conf {
 #[macroquad::main(window_conf)]
 async fn main() {
     let store = Arc::new(RefCell::new(GuiStore {
-        node_spacing: 1.0,
         global_action: GlobalAction::None,
+        recent_files: RecentFiles::load(),
     }));
 
     let (main_panel, editor_panel) = (None, Some(scenario_editor_panel::new_scenario_and_panel()));
@@ -59,11 +66,20 @@ async fn main() {
 
     let app = MyApp {
         main_panel,
+        comparison: None,
+        pending_run: None,
+        model_sweep: None,
         active_tab,
         editor_panel,
         save_path: "output.json".to_owned(),
+        results_path: "sim_output.json".to_owned(),
+        pending_results: None,
+        custom_scenario_path: "scenario.json".to_owned(),
+        error_message: None,
         model_selection: ModelSelection::Meshtastic,
-        new_modal_open: false,
+        run_seed: 12345,
+        pending_replacement: None,
+        save_error: None,
         store,
         browser_panel,
         generator_panel,
@@ -82,16 +98,39 @@ enum Tabs {
 
 struct MyApp {
     main_panel: Option<AnalysisPanel>,
+    /// Replaces `main_panel` while two runs are being compared
+    comparison: Option<ComparisonPanel>,
+    /// Finished run waiting for the user to choose where it goes
+    pending_run: Option<AnalysisPanel>,
+    /// Runs of the scenario with every model, shown in a window until it is closed
+    model_sweep: Option<ModelSweep>,
     editor_panel: Option<ScenarioEditorPanel>,
     generator_panel: ScenarioGeneratorPanel,
     browser_panel: BrowserPanel,
     model_selection: ModelSelection,
-    new_modal_open: bool,
+    run_seed: u64,
+    /// Waiting for the user to decide what happens to unsaved changes in the editor
+    pending_replacement: Option<EditorReplacement>,
+    /// Why saving from the unsaved changes modal failed
+    save_error: Option<String>,
     active_tab: Tabs,
     save_path: String,
+    results_path: String,
+    /// Results from a custom scenario, waiting for the scenario file to be picked
+    pending_results: Option<SimOutput>,
+    custom_scenario_path: String,
+    error_message: Option<String>,
     store: Arc<RefCell<GuiStore>>,
 }
 
+/// Scenario to put in the editor in place of the current one.
+enum EditorReplacement {
+    New,
+    Open(Scenario),
+    /// Opened and run straight away
+    Run(Scenario),
+}
+
 impl MyApp {
     async fn run(mut self) {
         loop {
@@ -109,11 +148,316 @@ impl MyApp {
         egui_macroquad::ui(|ctx| self.update_egui(ctx));
     }
 
+    /// Shows results in the analysis tab.
+    /// Results from a custom scenario can't be regenerated so the user is asked for the scenario file.
+    fn open_results(&mut self, results: SimOutput) {
+        match &results.complete_identity.scenario_identity {
+            ScenarioIdentity::Custom => self.pending_results = Some(results),
+            generated => {
+                let scenario = generated.create();
+                self.show_results(scenario, results);
+            }
+        }
+    }
+
+    fn show_results(&mut self, scenario: Scenario, results: SimOutput) {
+        if let Err(e) = scenario.validate() {
+            self.error_message = Some(format!("Invalid scenario: {e}"));
+            return;
+        }
+
+        let node_count = scenario.map.len();
+        if let Some(trans) = results
+            .transmissions
+            .iter()
+            .find(|x| x.transmitter_id >= node_count)
+        {
+            self.error_message = Some(format!(
+                "Results have a transmission from node {} but the scenario only has {node_count} nodes",
+                trans.transmitter_id
+            ));
+            return;
+        }
+
+        self.main_panel = Some(AnalysisPanel::new(scenario, results));
+        self.comparison = None;
+        self.active_tab = Tabs::Analysis;
+    }
+
+    /// Shows a new run, asking where it should go if results are already open.
+    fn finish_run(&mut self, panel: AnalysisPanel) {
+        if self.main_panel.is_some() || self.comparison.is_some() {
+            self.pending_run = Some(panel);
+        } else {
+            self.main_panel = Some(panel);
+        }
+        self.active_tab = Tabs::Analysis;
+    }
+
+    fn remember(&self, path: &str, kind: RecentKind) {
+        self.store.borrow_mut().recent_files.touch(path, kind);
+    }
+
+    /// Recently opened files, greying out any that have gone with an option to forget them.
+    fn recent_menu(&mut self, ui: &mut egui::Ui) {
+        let files = self.store.borrow().recent_files.files().to_vec();
+        if files.is_empty() {
+            ui.label("Nothing opened yet");
+        }
+
+        let mut reopen = None;
+        let mut forget = None;
+
+        for file in files {
+            ui.horizontal(|ui| {
+                let exists = file.path.exists();
+                let name = file.path.file_name().unwrap_or_default().to_string_lossy();
+                let hover = format!("{}\n{}", file.path.display(), opened_ago(file.timestamp));
+
+                let button = egui::Button::new(format!("{name} ({:?})", file.kind));
+                let response = ui.add_enabled(exists, button);
+                if response.on_hover_text(&hover).on_disabled_hover_text(&hover).clicked() {
+                    reopen = Some(file.clone());
+                    ui.close_menu();
+                }
+
+                if !exists && ui.small_button("Remove").clicked() {
+                    forget = Some(file.path.clone());
+                }
+            });
+        }
+
+        if let Some(path) = forget {
+            self.store.borrow_mut().recent_files.remove(&path);
+        }
+        if let Some(file) = reopen {
+            self.open_recent(file);
+        }
+    }
+
+    fn open_recent(&mut self, file: RecentFile) {
+        let path = file.path.clone();
+        let opened = match file.kind {
+            RecentKind::Scenario => load_file::<Scenario>(path.clone())
+                .map(|x| self.replace_editor(EditorReplacement::Open(x))),
+            RecentKind::ScenarioIdentity => load_file::<ScenarioIdentity>(path.clone())
+                .map(|x| self.replace_editor(EditorReplacement::Open(x.create()))),
+            RecentKind::Results => load_output(path.clone()).map(|x| self.open_results(x)),
+        };
+
+        match opened {
+            Ok(()) => self.store.borrow_mut().recent_files.touch(&path, file.kind),
+            Err(e) => self.error_message = Some(format!("Could not load {}: {e}", path.display())),
+        }
+    }
+
+    /// Replaces the scenario in the editor, asking what to do with it first if it has
+    /// unsaved changes.
+    fn replace_editor(&mut self, replacement: EditorReplacement) {
+        if let EditorReplacement::Open(scenario) | EditorReplacement::Run(scenario) = &replacement
+            && let Err(e) = scenario.map.check()
+        {
+            self.error_message = Some(format!("Can't open the scenario: {e}"));
+            return;
+        }
+
+        if self.editor_panel.as_ref().is_some_and(|x| x.is_dirty()) {
+            self.pending_replacement = Some(replacement);
+            self.save_error = None;
+        } else {
+            self.apply_replacement(replacement);
+        }
+    }
+
+    fn apply_replacement(&mut self, replacement: EditorReplacement) {
+        match replacement {
+            EditorReplacement::New => {
+                self.editor_panel = Some(scenario_editor_panel::new_scenario_and_panel());
+                self.active_tab = Tabs::ScenarioEditor;
+            }
+            EditorReplacement::Open(scenario) => {
+                self.editor_panel = Some(ScenarioEditorPanel::new(scenario));
+                self.active_tab = Tabs::ScenarioEditor;
+            }
+            EditorReplacement::Run(scenario) => {
+                let panel = ScenarioEditorPanel::new(scenario);
+                let run = AnalysisPanel::from_scenario(
+                    panel.scenario.clone(),
+                    self.model_selection.into(),
+                    self.run_seed,
+                );
+                self.editor_panel = Some(panel);
+                self.finish_run(run);
+            }
+        }
+    }
+
+    fn unsaved_changes_modal(&mut self, ctx: &egui::Context) {
+        if self.pending_replacement.is_none() {
+            return;
+        }
+
+        let mut discard = false;
+        let mut cancel = false;
+
+        let modal = Modal::new("Unsaved Changes Modal".into()).show(ctx, |ui| {
+            ui.heading("The current scenario has unsaved changes");
+            ui.label("They will be lost unless it is saved first.");
+
+            ui.horizontal(|ui| {
+                if ui.button("Save As:").clicked()
+                    && let Some(panel) = &mut self.editor_panel
+                {
+                    let path = self.save_path.clone();
+                    match write_file(path.clone().into(), panel.scenario.clone(), false) {
+                        Ok(()) => {
+                            panel.mark_saved();
+                            self.remember(&path, RecentKind::Scenario);
+                            discard = true;
+                        }
+                        Err(e) => self.save_error = Some(format!("Could not save to {path}: {e}")),
+                    }
+                }
+                ui.text_edit_singleline(&mut self.save_path);
+            });
+
+            if let Some(error) = &self.save_error {
+                ui.colored_label(egui::Color32::RED, error);
+            }
+
+            ui.horizontal(|ui| {
+                if ui.button("Discard").clicked() {
+                    discard = true;
+                }
+                if ui.button("Cancel").clicked() {
+                    cancel = true;
+                }
+            });
+        });
+
+        if cancel || modal.should_close() {
+            self.pending_replacement = None;
+        } else if discard {
+            let replacement = self.pending_replacement.take().unwrap();
+            self.apply_replacement(replacement);
+        }
+    }
+
+    fn pending_run_modal(&mut self, ctx: &egui::Context) {
+        let Some(pending) = &self.pending_run else {
+            return;
+        };
+
+        let node_count = match (&self.comparison, &self.main_panel) {
+            (Some(_), _) => None,
+            (None, Some(panel)) => Some(panel.node_count()),
+            (None, None) => None,
+        };
+        let can_compare = node_count.is_none_or(|x| x == pending.node_count());
+
+        let mut choice = None;
+
+        let modal = Modal::new("Pending Run Modal".into()).show(ctx, |ui| {
+            ui.heading("Results are already open");
+            ui.label(format!("New run: {}", pending.run_label()));
+
+            ui.horizontal(|ui| {
+                if ui.button("Replace A").clicked() {
+                    choice = Some(Some(Side::A));
+                }
+                if ui
+                    .add_enabled(can_compare, egui::Button::new("Set as B"))
+                    .on_disabled_hover_text("Both runs must use the same scenario")
+                    .clicked()
+                {
+                    choice = Some(Some(Side::B));
+                }
+                if ui.button("Discard").clicked() {
+                    choice = Some(None);
+                }
+            });
+        });
+
+        if modal.should_close() {
+            choice = Some(None);
+        }
+
+        let Some(choice) = choice else {
+            return;
+        };
+
+        let pending = self.pending_run.take().unwrap();
+
+        match (choice, &mut self.comparison) {
+            (None, _) => (),
+            (Some(side), Some(comparison)) => comparison.replace(side, pending),
+            (Some(Side::A), None) => self.main_panel = Some(pending),
+            (Some(Side::B), None) => {
+                let a = self.main_panel.take().unwrap();
+                self.comparison = Some(ComparisonPanel::new(a, pending));
+            }
+        }
+    }
+
+    fn results_modals(&mut self, ctx: &egui::Context) {
+        if let Some(message) = &self.error_message {
+            let modal = Modal::new("Error Modal".into()).show(ctx, |ui| {
+                ui.heading("Error");
+                ui.label(message);
+
+                ui.button("Close").clicked()
+            });
+
+            if modal.inner || modal.should_close() {
+                self.error_message = None;
+            }
+
+            return;
+        }
+
+        if self.pending_results.is_none() {
+            return;
+        }
+
+        let modal = Modal::new("Custom Scenario Modal".into()).show(ctx, |ui| {
+            ui.heading("These results are from a custom scenario");
+            ui.label(
+                "Custom scenarios can't be regenerated. Open the scenario file they were made with:",
+            );
+            ui.text_edit_singleline(&mut self.custom_scenario_path);
+
+            ui.horizontal(|ui| {
+                if ui.button("Open").clicked() {
+                    let path = self.custom_scenario_path.clone();
+                    match load_file::<Scenario>(path.clone().into()) {
+                        Ok(scenario) => {
+                            self.remember(&path, RecentKind::Scenario);
+                            let results = self.pending_results.take().unwrap();
+                            self.show_results(scenario, results);
+                        }
+                        Err(e) => {
+                            self.error_message = Some(format!("Could not load {path}: {e}"));
+                        }
+                    }
+                }
+                if ui.button("Cancel").clicked() {
+                    self.pending_results = None;
+                }
+            });
+        });
+
+        if modal.should_close() {
+            self.pending_results = None;
+        }
+    }
+
     fn update_egui(&mut self, ctx: &egui::Context) {
         ctx.style_mut(|style| {
             style.visuals = dark_visuals();
         });
 
+        let mut finished_run = None;
+
         TopBottomPanel::top("main_top")
             .default_height(36.0)
             .show(ctx, |ui| {
@@ -166,41 +510,51 @@ impl MyApp {
 
                     ui.add_space(5.0);
                     if ui.button("New Scenario").clicked() {
-                        self.new_modal_open = true;
+                        self.replace_editor(EditorReplacement::New);
                     }
                     ui.add_space(5.0);
+                    ui.menu_button("Recent", |ui| self.recent_menu(ui));
+                    ui.add_space(5.0);
 
-                    if self.new_modal_open {
-                        let modal = Modal::new("New Modal".into()).show(ui.ctx(), |ui| {
-                            ui.heading("Create new scenario? Current scenario will be discarded");
-
-                            ui.horizontal_centered(|ui| {
-                                if ui.button("Confirm").clicked() {
-                                    self.editor_panel =
-                                        Some(scenario_editor_panel::new_scenario_and_panel());
-                                    self.active_tab = Tabs::ScenarioEditor;
-                                    self.new_modal_open = false;
-                                };
-                                if ui.button("Cancel").clicked() {
-                                    self.new_modal_open = false;
+                    ui.vertical(|ui| {
+                        if ui.button("Open Results:").clicked() {
+                            let path = self.results_path.clone();
+                            match load_output(path.clone().into()) {
+                                Ok(results) => {
+                                    self.remember(&path, RecentKind::Results);
+                                    self.open_results(results);
                                 }
-                            });
-                        });
-
-                        if modal.should_close() {
-                            self.new_modal_open = false;
+                                Err(e) => {
+                                    self.error_message = Some(format!(
+                                        "Could not load {}: {e}",
+                                        self.results_path
+                                    ));
+                                }
+                            }
                         }
-                    }
+                        ui.text_edit_singleline(&mut self.results_path);
+                    });
 
-                    if let Some(ref panel) = self.editor_panel {
+                    ui.separator();
+
+                    if let Some(panel) = &mut self.editor_panel {
                         ui.vertical(|ui| {
                             if ui.button("Save Scenario As:").clicked() {
-                                write_file(
-                                    self.save_path.clone().into(),
-                                    panel.scenario.clone(),
-                                    false,
-                                )
-                                .unwrap();
+                                let path = self.save_path.clone();
+                                match write_file(path.clone().into(), panel.scenario.clone(), false)
+                                {
+                                    Ok(()) => {
+                                        panel.mark_saved();
+                                        self.store
+                                            .borrow_mut()
+                                            .recent_files
+                                            .touch(&path, RecentKind::Scenario);
+                                    }
+                                    Err(e) => {
+                                        self.error_message =
+                                            Some(format!("Could not save to {path}: {e}"));
+                                    }
+                                }
                             }
                             ui.text_edit_singleline(&mut self.save_path);
                         });
@@ -208,11 +562,19 @@ impl MyApp {
                         ui.separator();
 
                         if ui.button("Run Scenario").clicked() {
-                            self.main_panel = Some(AnalysisPanel::from_scenario(
+                            finished_run = Some(AnalysisPanel::from_scenario(
                                 panel.scenario.clone(),
                                 self.model_selection.into(),
+                                self.run_seed,
                             ));
-                            self.active_tab = Tabs::Analysis;
+                        }
+
+                        ui.label("seed");
+                        ui.add(DragValue::new(&mut self.run_seed));
+                        if ui.button("🎲").on_hover_text("Random seed").clicked() {
+                            let timestamp = get_time().to_ne_bytes();
+                            macroquad::rand::srand(u64::from_ne_bytes(timestamp));
+                            self.run_seed = macroquad::rand::rand() as u64;
                         }
 
                         ui.label("with");
@@ -228,32 +590,64 @@ impl MyApp {
                                     );
                                 }
                             });
+
+                        if ui
+                            .button("Run All Models")
+                            .on_hover_text("Run the scenario with every model using this seed")
+                            .clicked()
+                        {
+                            self.model_sweep =
+                                Some(ModelSweep::start(panel.scenario.clone(), self.run_seed));
+                        }
                     }
                 });
             });
 
+        if let Some(sweep) = &mut self.model_sweep {
+            let mut open = true;
+            finished_run = finished_run.or(sweep.show(ctx, &mut open));
+            if !open {
+                self.model_sweep = None;
+            }
+        }
+
+        if let Some(run) = finished_run {
+            self.finish_run(run);
+        }
+
+        self.results_modals(ctx);
+        self.pending_run_modal(ctx);
+        self.unsaved_changes_modal(ctx);
+
         CentralPanel::default()
             .frame(Frame::NONE)
             .show(ctx, |ui| match self.active_tab {
                 Tabs::Analysis => {
-                    if let Some(ref mut panel) = self.main_panel {
+                    if let Some(ref mut comparison) = self.comparison {
+                        ui.add(comparison);
+                    } else if let Some(ref mut panel) = self.main_panel {
                         ui.add(panel);
                     } else {
                         ui.centered_and_justified(|ui| {
                             ui.heading(
-                                "No active analysis.\nRun the current scenario from the top panel.",
+                                "No active analysis.\nRun the current scenario or open results from the top panel.",
                             );
                         });
                     }
                 }
                 Tabs::ScenarioEditor => {
                     if let Some(ref mut panel) = self.editor_panel {
-                        ui.add(panel);
+                        ui.add(&mut *panel);
                     } else {
                         ui.centered_and_justified(|ui| {
                             ui.heading("No Active Scenario");
                         });
                     }
+
+                    let regenerated = self.editor_panel.as_mut().and_then(|x| x.regenerated.take());
+                    if let Some(scenario) = regenerated {
+                        self.replace_editor(EditorReplacement::Open(scenario));
+                    }
                 }
                 Tabs::Browser => {
                     ui.add(&mut self.browser_panel);
@@ -263,31 +657,32 @@ impl MyApp {
                 }
             });
 
-        match &self.store.borrow().global_action {
+        if self.comparison.as_ref().is_some_and(|x| x.keep.is_some()) {
+            self.main_panel = self.comparison.take().map(ComparisonPanel::into_kept);
+        }
+
+        let action = std::mem::replace(
+            &mut self.store.borrow_mut().global_action,
+            GlobalAction::None,
+        );
+
+        match action {
             GlobalAction::None => (),
             GlobalAction::SetScenario(scenario) => {
-                self.editor_panel = Some(ScenarioEditorPanel::new(scenario.clone()));
-                self.active_tab = Tabs::ScenarioEditor;
+                self.replace_editor(EditorReplacement::Open(scenario));
             }
             GlobalAction::RunScenario(scenario) => {
-                self.editor_panel = Some(ScenarioEditorPanel::new(scenario.clone()));
-                self.main_panel = Some(AnalysisPanel::from_scenario(
-                    self.editor_panel.as_ref().unwrap().scenario.clone(),
-                    self.model_selection.into(),
-                ));
-                self.active_tab = Tabs::Analysis;
+                self.replace_editor(EditorReplacement::Run(scenario));
             }
+            GlobalAction::OpenResults(results) => self.open_results(*results),
         }
-
-        self.store.borrow_mut().global_action = GlobalAction::None;
     }
 }
 
 #[derive(Debug, Clone)]
 pub struct GuiStore {
-    pub node_spacing: f32,
-
     pub global_action: GlobalAction,
+    recent_files: RecentFiles,
 }
 
 #[derive(Debug, Clone)]
@@ -295,6 +690,7 @@ pub enum GlobalAction {
     None,
     SetScenario(Scenario),
     RunScenario(Scenario),
+    OpenResults(Box<SimOutput>),
 }
 
 const BACK_TIME: Time = Time::from_seconds(1.0);
@@ -319,6 +715,33 @@ where
         .skip_while(move |x| x.time() < time - BACK_TIME)
         .take_while(move |x| x.time() < time + FORWARD_TIME)
 }
+/// How long ago a [`RecentFile`] was last opened or saved, to the largest whole unit.
+fn opened_ago(timestamp: u64) -> String {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |x| x.as_secs());
+    let seconds = now.saturating_sub(timestamp);
+
+    match seconds {
+        0..60 => "Used just now".to_owned(),
+        60..3600 => format!("Used {} min ago", seconds / 60),
+        3600..86400 => format!("Used {} hours ago", seconds / 3600),
+        _ => format!("Used {} days ago", seconds / 86400),
+    }
+}
+
+/// Short name for captions, generated scenarios are named after their generator.
+fn scenario_name(identity: &ScenarioIdentity) -> String {
+    match identity {
+        ScenarioIdentity::Custom => "Custom Scenario".to_owned(),
+        ScenarioIdentity::Generated { generator, seed } => {
+            let debug = format!("{generator:?}");
+            let name = debug.split([' ', '(', '{']).next().unwrap_or_default();
+            format!("{name} (seed {seed})")
+        }
+    }
+}
+
 fn short_content(content: &MessageContent) -> String {
     match content {
         MessageContent::GeneratedMessage(id) => format!("Message({id})"),
@@ -331,7 +754,11 @@ fn short_content(content: &MessageContent) -> String {
 enum Inspectable {
     Nothing,
     Node(usize),
+    /// Several nodes selected together in the scenario editor, in ascending id order
+    Nodes(Vec<usize>),
     Transmission(u32),
+    /// Relay path of the generated message with this id
+    MessageTrace(usize),
 }
 
 fn convert_rect(rect_in: egui::Rect) -> Rect {
@@ -0,0 +1,177 @@
+//! Running the scenario in the editor with every model, on background threads
+//! so the interface keeps responding.
+
+use std::{
+    sync::{
+        Arc,
+        mpsc::{Receiver, TryRecvError, channel},
+    },
+    thread,
+};
+
+use egui::{ProgressBar, RichText, ScrollArea, Spinner, Window};
+
+use frogcore::{
+    analysis::CompleteAnalysis,
+    node::{MODEL_LIST, ModelSelection},
+    scenario::Scenario,
+    sim_file::SimOutput,
+    simulation::{RunOptions, SimJob, run_batch_map},
+};
+
+use crate::analysis_panel::{AnalysisPanel, HEADLINE_TITLES, headline_values};
+
+struct FinishedRun {
+    headline: [f64; 8],
+    output: SimOutput,
+}
+
+/// Every model in [`MODEL_LIST`] run on one scenario with the same seed.
+pub struct ModelSweep {
+    scenario: Arc<Scenario>,
+    seed: u64,
+    /// Indexed the same as [`MODEL_LIST`], `None` until the run finishes
+    runs: Vec<Option<FinishedRun>>,
+    /// Finished runs from the worker thread, with their index in [`MODEL_LIST`]
+    receiver: Receiver<(usize, FinishedRun)>,
+    /// The worker thread stopped before every run finished
+    failed: bool,
+}
+
+impl ModelSweep {
+    /// Starts the runs in the background.
+    /// They keep going if the sweep is dropped, but their results are thrown away.
+    pub fn start(scenario: Scenario, seed: u64) -> ModelSweep {
+        let scenario = Arc::new(scenario);
+        let jobs = MODEL_LIST
+            .iter()
+            .map(|&model| SimJob {
+                scenario: scenario.clone(),
+                model: model.into(),
+                seed,
+                options: RunOptions {
+                    node_logs: true,
+                    ..RunOptions::default()
+                },
+            })
+            .collect();
+
+        let (sender, receiver) = channel();
+        thread::spawn(move || {
+            run_batch_map(
+                jobs,
+                None,
+                |_| (),
+                |index, scenario, output| {
+                    let analysis = CompleteAnalysis::new(output.clone(), scenario.clone());
+                    let run = FinishedRun {
+                        headline: headline_values(&analysis),
+                        output,
+                    };
+                    // Only fails if the sweep has been dropped
+                    let _ = sender.send((index, run));
+                },
+            );
+        });
+
+        ModelSweep {
+            scenario,
+            seed,
+            runs: MODEL_LIST.iter().map(|_| None).collect(),
+            receiver,
+            failed: false,
+        }
+    }
+
+    fn completed(&self) -> usize {
+        self.runs.iter().filter(|x| x.is_some()).count()
+    }
+
+    fn poll(&mut self) {
+        loop {
+            match self.receiver.try_recv() {
+                Ok((index, run)) => self.runs[index] = Some(run),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    self.failed = self.completed() < self.runs.len();
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Shows the results window, returning the run to open if one was picked.
+    /// `open` is set to false when the window is closed.
+    pub fn show(&mut self, ctx: &egui::Context, open: &mut bool) -> Option<AnalysisPanel> {
+        self.poll();
+
+        let completed = self.completed();
+        let total = self.runs.len();
+        let running = completed < total && !self.failed;
+        if running {
+            ctx.request_repaint();
+        }
+
+        let mut picked = None;
+
+        Window::new(format!("All Models (seed {})", self.seed))
+            .open(open)
+            .show(ctx, |ui| {
+                if running {
+                    ui.add(
+                        ProgressBar::new(completed as f32 / total as f32)
+                            .text(format!("{completed} of {total} runs finished")),
+                    );
+                } else if self.failed {
+                    ui.colored_label(
+                        ui.visuals().error_fg_color,
+                        "A run panicked, so the models without results never finished",
+                    );
+                }
+
+                ScrollArea::horizontal().show(ui, |ui| {
+                    egui::Grid::new("model_sweep").striped(true).show(ui, |ui| {
+                        ui.label(RichText::new("Model").strong());
+                        for title in HEADLINE_TITLES {
+                            ui.label(RichText::new(title).strong());
+                        }
+                        ui.end_row();
+
+                        for (model, run) in MODEL_LIST.iter().zip(&self.runs) {
+                            if let Some(panel) = self.row(ui, *model, run.as_ref()) {
+                                picked = Some(panel);
+                            }
+                            ui.end_row();
+                        }
+                    });
+                });
+            });
+
+        picked
+    }
+
+    fn row(
+        &self,
+        ui: &mut egui::Ui,
+        model: ModelSelection,
+        run: Option<&FinishedRun>,
+    ) -> Option<AnalysisPanel> {
+        ui.label(format!("{model:?}"));
+
+        let Some(run) = run else {
+            if !self.failed {
+                ui.add(Spinner::new());
+            }
+            return None;
+        };
+
+        for value in run.headline {
+            ui.label(format!("{value:.3}"));
+        }
+
+        ui.button("Open")
+            .on_hover_text("Show this run in the analysis tab")
+            .clicked()
+            .then(|| AnalysisPanel::new(Scenario::clone(&self.scenario), run.output.clone()))
+    }
+}
@@ -0,0 +1,144 @@
+//! Files recently opened or saved, kept between sessions in a config file next to the
+//! executable.
+
+use std::{
+    env::current_exe,
+    fs::File,
+    io::BufReader,
+    path::{Path, PathBuf, absolute},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+const MAX_RECENT: usize = 10;
+const CONFIG_FILE_NAME: &str = "frog_gui_recent.json";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RecentKind {
+    Scenario,
+    ScenarioIdentity,
+    Results,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecentFile {
+    pub path: PathBuf,
+    pub kind: RecentKind,
+    /// Seconds since the unix epoch when it was last opened or saved
+    pub timestamp: u64,
+}
+
+/// Newest first, with each path at most once.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RecentFiles {
+    files: Vec<RecentFile>,
+}
+
+impl RecentFiles {
+    /// Loads the list from the config file, or starts an empty one if it can't be read.
+    pub fn load() -> RecentFiles {
+        config_path()
+            .and_then(|path| File::open(path).ok())
+            .and_then(|file| serde_json::from_reader(BufReader::new(file)).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn files(&self) -> &[RecentFile] {
+        &self.files
+    }
+
+    /// Records that a file was opened or saved now and writes the list out.
+    pub fn touch(&mut self, path: impl AsRef<Path>, kind: RecentKind) {
+        let path = path.as_ref();
+        let path = absolute(path).unwrap_or_else(|_| path.to_path_buf());
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |x| x.as_secs());
+
+        self.push(RecentFile {
+            path,
+            kind,
+            timestamp,
+        });
+        self.save();
+    }
+
+    pub fn remove(&mut self, path: &Path) {
+        self.files.retain(|x| x.path != path);
+        self.save();
+    }
+
+    /// Puts `file` first, dropping any older entry for the same path and the oldest entries
+    /// past [`MAX_RECENT`].
+    fn push(&mut self, file: RecentFile) {
+        self.files.retain(|x| x.path != file.path);
+        self.files.insert(0, file);
+        self.files.truncate(MAX_RECENT);
+    }
+
+    /// Not being able to remember files isn't worth interrupting anything over,
+    /// so failures are ignored.
+    fn save(&self) {
+        if let Some(path) = config_path()
+            && let Ok(file) = File::create(path)
+        {
+            let _ = serde_json::to_writer_pretty(file, self);
+        }
+    }
+}
+
+fn config_path() -> Option<PathBuf> {
+    let exe = current_exe().ok()?;
+    Some(exe.parent()?.join(CONFIG_FILE_NAME))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(name: &str, timestamp: u64) -> RecentFile {
+        RecentFile {
+            path: name.into(),
+            kind: RecentKind::Scenario,
+            timestamp,
+        }
+    }
+
+    fn names(recent: &RecentFiles) -> Vec<String> {
+        recent
+            .files()
+            .iter()
+            .map(|x| x.path.display().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn newest_first_without_repeats() {
+        let mut recent = RecentFiles::default();
+        recent.push(file("a", 1));
+        recent.push(file("b", 2));
+        recent.push(file("a", 3));
+
+        assert_eq!(names(&recent), ["a", "b"]);
+        assert_eq!(recent.files()[0].timestamp, 3);
+    }
+
+    #[test]
+    fn oldest_are_evicted() {
+        let mut recent = RecentFiles::default();
+        for n in 0..MAX_RECENT as u64 + 3 {
+            recent.push(file(&n.to_string(), n));
+        }
+
+        assert_eq!(recent.files().len(), MAX_RECENT);
+        assert_eq!(recent.files()[0].timestamp, MAX_RECENT as u64 + 2);
+        assert_eq!(recent.files().last().unwrap().timestamp, 3);
+
+        // Opening one again doesn't evict anything
+        recent.push(file("5", 100));
+        assert_eq!(recent.files().len(), MAX_RECENT);
+        assert_eq!(names(&recent)[0], "5");
+        assert_eq!(recent.files().last().unwrap().timestamp, 3);
+    }
+}
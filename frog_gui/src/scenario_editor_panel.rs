@@ -1,40 +1,359 @@
+use std::{collections::BTreeMap, fs::File};
+
 use egui::{Color32, ComboBox, DragValue, Frame, Modal, RichText, Widget};
 
 use macroquad::prelude::*;
 use frogcore::{
-    node_location::{NodeLocation, Point, Points, Timepoint},
+    node_location::{Edge, NodeLocation, Point, Points, Timepoint},
     scenario::{
         MovementIndicator, Scenario, ScenarioIdentity, ScenarioMessage, ScenarioNodeSettings,
+        default_frequency_plan, group_members,
+        message_csv::{read_messages, write_messages},
+        modem_preset::ModemPreset,
     },
     simulation::models::PairWiseCaptureEffect,
-    units::{DbPerLength, METRES, SECONDS, Temperature, Unit},
+    simulation::data_structs::CarrierBand,
+    units::{DbPerLength, Dbm, Frequency, Length, METRES, SECONDS, Temperature, Time, Unit},
 };
 
 use super::Inspectable;
-use crate::{convert_rect, scene::SceneData};
+use crate::{
+    convert_rect, scenario_name,
+    scene::{CategoryColouring, CategoryColours, ImageExport, SceneData, point_to_vec},
+};
 
 pub struct ScenarioEditorPanel {
     scene: SceneData,
     pub scenario: Scenario,
     inspect_target: Inspectable,
-    delete_node_pending: Option<usize>,
+    /// Ids of the nodes waiting for deletion to be confirmed
+    delete_node_pending: Option<Vec<usize>>,
     message_sender_filter: Option<usize>,
     message_target_filter: Option<usize>,
+    graph_edit: GraphEditState,
+    bulk: BulkPlacement,
+    message_tools: MessageTools,
+    image_export: ImageExport,
+    /// Node property shown by the fill colours, if any
+    colour_mode: Option<CategoryColouring>,
+    /// Scenario as it was when opened or last saved, serialised.
+    /// `None` once it has been changed
+    saved: Option<Vec<u8>>,
+    /// [`Scenario::content_hash`] when opened, while the scenario still has its generated identity
+    generated_hash: Option<u64>,
+    /// How the scenario was generated, kept once edits have made it custom
+    generated_from: Option<ScenarioIdentity>,
+    /// Made by "Regenerate with new seed", for the app to open in place of this scenario
+    pub regenerated: Option<Scenario>,
+    /// Factor the "Rescale" button multiplies distances by
+    rescale_factor: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PlacementPattern {
+    Grid,
+    Circle,
+    Random,
+}
+
+impl PlacementPattern {
+    const VALUES: [PlacementPattern; 3] = [
+        PlacementPattern::Grid,
+        PlacementPattern::Circle,
+        PlacementPattern::Random,
+    ];
+}
+
+/// Spawns many nodes at once in a pattern inside an area drawn in the scene.
+struct BulkPlacement {
+    count: usize,
+    pattern: PlacementPattern,
+    /// Dragging in the scene draws the area instead of selecting and moving nodes
+    drawing: bool,
+    drag_start: Option<Vec2>,
+    /// In world coordinates
+    area: Option<Rect>,
+}
+
+impl BulkPlacement {
+    fn positions(&self, area: Rect) -> Vec<Point> {
+        let count = self.count;
+        let to_point = |v: Vec2| Point {
+            x: (v.x as f64) * METRES,
+            y: (v.y as f64) * METRES,
+        };
+
+        match self.pattern {
+            PlacementPattern::Grid => {
+                // Roughly square cells whatever the shape of the area
+                let cols = (count as f32 * area.w / area.h).sqrt().ceil() as usize;
+                let cols = cols.clamp(1, count);
+                let rows = count.div_ceil(cols);
+                let cell = vec2(area.w / cols as f32, area.h / rows as f32);
+
+                (0..count)
+                    .map(|i| {
+                        let (col, row) = ((i % cols) as f32, (i / cols) as f32);
+                        to_point(area.point() + cell * (vec2(col, row) + 0.5))
+                    })
+                    .collect()
+            }
+            PlacementPattern::Circle => {
+                let radius = area.w.min(area.h) / 2.0;
+
+                (0..count)
+                    .map(|i| {
+                        let angle = i as f32 * std::f32::consts::TAU / count as f32;
+                        to_point(area.center() + Vec2::from_angle(angle) * radius)
+                    })
+                    .collect()
+            }
+            PlacementPattern::Random => (0..count)
+                .map(|_| {
+                    to_point(vec2(
+                        rand::gen_range(area.left(), area.right()),
+                        rand::gen_range(area.top(), area.bottom()),
+                    ))
+                })
+                .collect(),
+        }
+    }
+
+    /// Draws the area by dragging in the scene.
+    fn area_interaction(&mut self, scene: &SceneData, scene_rect: Rect) {
+        let mouse = scene.world_mouse_pos();
+
+        if scene_rect.contains(mouse_position().into())
+            && is_mouse_button_pressed(MouseButton::Left)
+        {
+            self.drag_start = Some(mouse);
+        }
+
+        let Some(start) = self.drag_start else {
+            return;
+        };
+
+        let min = start.min(mouse);
+        let size = (start - mouse).abs();
+        self.area = Some(Rect::new(min.x, min.y, size.x, size.y));
+
+        if !is_mouse_button_down(MouseButton::Left) {
+            self.drag_start = None;
+            self.drawing = false;
+
+            // Treat a click without a drag as cancelling
+            if size.x <= f32::EPSILON || size.y <= f32::EPSILON {
+                self.area = None;
+            }
+        }
+    }
+}
+
+struct GraphEditState {
+    /// Clicking two nodes adds or removes the edge between them instead of selecting and moving
+    edge_mode: bool,
+    directed: bool,
+    /// First node clicked when adding or removing an edge
+    edge_start: Option<usize>,
+    /// Weight of new edges in metres
+    new_weight: f64,
+}
+
+impl GraphEditState {
+    /// Removes the edge if it exists, otherwise adds it.
+    /// Undirected edits apply to both directions.
+    fn toggle_edge(&self, edges: &[Vec<Edge>], from: usize, to: usize, edits: &mut Vec<MapEdit>) {
+        let exists = edges[from].iter().any(|x| x.to == to);
+
+        let mut pairs = vec![(from, to)];
+        if !self.directed {
+            pairs.push((to, from));
+        }
+
+        for (from, to) in pairs {
+            edits.push(if exists {
+                MapEdit::RemoveEdge { from, to }
+            } else {
+                MapEdit::SetEdge {
+                    from,
+                    to,
+                    weight: self.new_weight * METRES,
+                }
+            });
+        }
+    }
+}
+
+/// Changes to the node locations made while the display locations are borrowed.
+/// They are applied at the end of the frame.
+enum MapEdit {
+    AddNode(Point),
+    SetEdge {
+        from: usize,
+        to: usize,
+        weight: Length,
+    },
+    RemoveEdge {
+        from: usize,
+        to: usize,
+    },
+    Recentre,
+    Rescale(f64),
+}
+
+fn apply_edit(scenario: &mut Scenario, edit: MapEdit) {
+    match (&mut scenario.map, edit) {
+        (_, MapEdit::Recentre) => scenario.recentre(),
+        (_, MapEdit::Rescale(factor)) => scenario.rescale(factor),
+        // A new node stays still at every timepoint
+        (NodeLocation::Points(points), MapEdit::AddNode(point)) => {
+            points.data.iter_mut().for_each(|x| x.node_points.push(point));
+        }
+        (NodeLocation::Graph(graph), MapEdit::AddNode(point)) => {
+            graph.add_node(point);
+        }
+        (NodeLocation::Graph(graph), MapEdit::SetEdge { from, to, weight }) => {
+            graph.set_edge(from, to, weight);
+        }
+        (NodeLocation::Graph(graph), MapEdit::RemoveEdge { from, to }) => {
+            graph.remove_edge(from, to);
+        }
+        (NodeLocation::Points(_), _) => (),
+    }
+}
+
+/// Removes the node, the messages it sends or is the only target of, its setting changes,
+/// and for graphs every edge to it. Nodes with higher ids have their id decremented.
+fn delete_node(scenario: &mut Scenario, delete_id: usize) {
+    let Scenario {
+        map,
+        messages,
+        settings,
+        setting_changes,
+        ..
+    } = scenario;
+
+    match map {
+        NodeLocation::Points(points) => points.data.iter_mut().for_each(|x| {
+            x.node_points.remove(delete_id);
+        }),
+        NodeLocation::Graph(graph) => graph.remove_node(delete_id),
+    }
+
+    // Delete
+    settings.remove(delete_id);
+    messages.retain(|x| x.sender != delete_id);
+    messages.iter_mut().for_each(|x| x.targets.retain(|&target| target != delete_id));
+    messages.retain(|x| !x.targets.is_empty());
+
+    // Decrement
+    messages.iter_mut().for_each(|x| {
+        if x.sender > delete_id {
+            x.sender -= 1;
+        }
+
+        for target in x.targets.iter_mut().filter(|x| **x > delete_id) {
+            *target -= 1;
+        }
+    });
+
+    setting_changes.retain(|x| x.node_id != delete_id);
+    for change in setting_changes.iter_mut().filter(|x| x.node_id > delete_id) {
+        change.node_id -= 1;
+    }
+
+    sync_group_targets(messages, settings);
+}
+
+/// Keeps the targets of group messages in line with who is in each group
+/// as group membership changes.
+fn sync_group_targets(messages: &mut [ScenarioMessage], settings: &[ScenarioNodeSettings]) {
+    for message in messages.iter_mut() {
+        if let Some(group) = message.group {
+            message.targets = group_members(settings, group)
+                .filter(|&x| x != message.sender)
+                .collect();
+        }
+    }
 }
 
 impl ScenarioEditorPanel {
-    pub fn new(mut scenario: Scenario) -> ScenarioEditorPanel {
+    pub fn new(scenario: Scenario) -> ScenarioEditorPanel {
         let mut scene = SceneData::new();
-        scenario.identity = ScenarioIdentity::Custom;
         scene.zoom_to_fit(&scenario.map.display_locations(0.0 * SECONDS));
 
-        ScenarioEditorPanel {
+        // Such as from generators that pick spreading factors by distance to a gateway
+        let mixed_sfs = scenario.settings.windows(2).any(|x| x[0].sf != x[1].sf);
+
+        let generated_from = match scenario.identity {
+            ScenarioIdentity::Generated { .. } => Some(scenario.identity.clone()),
+            ScenarioIdentity::Custom => None,
+        };
+        let generated_hash = generated_from.as_ref().map(|_| scenario.content_hash());
+
+        let mut panel = ScenarioEditorPanel {
             scene,
             scenario,
             inspect_target: Inspectable::Nothing,
             delete_node_pending: None,
             message_sender_filter: None,
             message_target_filter: None,
+            graph_edit: GraphEditState {
+                edge_mode: false,
+                directed: false,
+                edge_start: None,
+                new_weight: 1000.0,
+            },
+            message_tools: MessageTools {
+                page: 0,
+                time_shift: 0.0,
+                csv_path: "messages.csv".to_owned(),
+                csv_report: None,
+            },
+            image_export: ImageExport::new(),
+            colour_mode: mixed_sfs.then_some(CategoryColouring::Sf),
+            bulk: BulkPlacement {
+                count: 10,
+                pattern: PlacementPattern::Grid,
+                drawing: false,
+                drag_start: None,
+                area: None,
+            },
+            saved: None,
+            generated_hash,
+            generated_from,
+            regenerated: None,
+            rescale_factor: 2.0,
+        };
+        panel.mark_saved();
+        panel
+    }
+
+    /// Whether the scenario has changed since it was opened or last saved.
+    pub fn is_dirty(&self) -> bool {
+        self.saved.is_none()
+    }
+
+    /// Call once the scenario has been written to a file.
+    pub fn mark_saved(&mut self) {
+        self.saved = Some(serde_json::to_vec(&self.scenario).unwrap());
+    }
+
+    /// Edits are made all over the panel so rather than flagging each one
+    /// the scenario is compared with the saved one, until it first differs.
+    /// Changes that alter what runs also make a generated scenario custom.
+    fn check_dirty(&mut self) {
+        if let Some(saved) = &self.saved
+            && serde_json::to_vec(&self.scenario).unwrap() != *saved
+        {
+            self.saved = None;
+        }
+
+        if self.is_dirty()
+            && let Some(hash) = self.generated_hash
+            && self.scenario.drop_identity_if_edited(hash)
+        {
+            self.generated_hash = None;
         }
     }
 }
@@ -52,6 +371,11 @@ pub fn new_scenario_and_panel() -> ScenarioEditorPanel {
         model: PairWiseCaptureEffect::default().into(),
         messages: vec![],
         settings: vec![ScenarioNodeSettings::default()],
+        frequency_plan: default_frequency_plan(),
+        gateway_uplink: false,
+        setting_changes: Vec::new(),
+        reception_feedback: false,
+        tags: BTreeMap::new(),
     })
 }
 
@@ -59,56 +383,38 @@ impl Widget for &mut ScenarioEditorPanel {
     fn ui(self, ui: &mut egui::Ui) -> egui::Response {
         let item_background = Color32::from_hex("#212121").unwrap();
 
-        let Scenario {
-            identity: _,
-            map,
-            model,
-            messages,
-            settings,
-        } = &mut self.scenario;
-
-        let map = match map {
-            NodeLocation::Points(points) if points.data.len() == 1 => {
-                &mut points.data[0].node_points
-            }
-            _ => {
-                ui.label("Graphs and Points with movement are not yet supported");
-                ui.label("Run the scenario from the top bar.");
-                return ui.response();
-            }
+        let supported = match &self.scenario.map {
+            NodeLocation::Points(points) => points.data.len() == 1,
+            NodeLocation::Graph(_) => true,
         };
 
-        if let Some(delete_id) = self.delete_node_pending {
+        if !supported {
+            ui.label("Points with movement are not yet supported");
+            ui.label("Run the scenario from the top bar.");
+            return ui.response();
+        }
+
+        if let Some(delete_ids) = self.delete_node_pending.clone() {
             let modal = Modal::new("Delete Node Modal".into()).show(ui.ctx(), |ui| {
-                ui.heading(format!("Delete Node {delete_id}?"));
+                match delete_ids.as_slice() {
+                    [delete_id] => ui.heading(format!("Delete Node {delete_id}?")),
+                    _ => ui.heading(format!("Delete {} Nodes?", delete_ids.len())),
+                };
                 ui.label("Assossiated messages will be deleted too.");
+                if let NodeLocation::Graph(_) = self.scenario.map {
+                    ui.label("Edges to and from it will be deleted too.");
+                }
                 ui.label("Nodes with higher ids will have their id decremented.");
 
                 ui.horizontal_centered(|ui| {
                     if ui.button("Confirm").clicked() {
                         self.inspect_target = Inspectable::Nothing;
+                        self.graph_edit.edge_start = None;
 
-                        // Delete
-                        map.remove(delete_id);
-                        settings.remove(delete_id);
-                        messages.retain(|x| x.sender != delete_id);
-                        messages.retain(|x| {
-                            x.targets.len() > 1 || *x.targets.first().unwrap() != delete_id
-                        });
-
-                        // Decrement
-                        messages.iter_mut().for_each(|x| {
-                            if x.sender > delete_id {
-                                x.sender -= 1;
-                            }
-
-                            if x.targets.len() == 1 {
-                                let target = x.targets.first_mut().unwrap();
-                                if *target > delete_id {
-                                    *target -= 1;
-                                }
-                            }
-                        });
+                        // Highest first so the remaining ids are still correct
+                        for &delete_id in delete_ids.iter().rev() {
+                            delete_node(&mut self.scenario, delete_id);
+                        }
 
                         self.delete_node_pending = None;
                     };
@@ -123,17 +429,106 @@ impl Widget for &mut ScenarioEditorPanel {
             }
         }
 
+        if let Some(report) = &self.message_tools.csv_report {
+            let modal = Modal::new("Message CSV Modal".into()).show(ui.ctx(), |ui| {
+                ui.heading("Message CSV");
+
+                egui::ScrollArea::vertical().max_height(400.0).show(ui, |ui| {
+                    for line in report {
+                        ui.label(line);
+                    }
+                });
+
+                ui.button("Ok").clicked()
+            });
+
+            if modal.inner || modal.should_close() {
+                self.message_tools.csv_report = None;
+            }
+        }
+
+        // Copied so they can be shown while the display locations are borrowed
+        let edges: Option<Vec<Vec<Edge>>> = match &self.scenario.map {
+            NodeLocation::Graph(graph) => {
+                let node_count = self.scenario.map.len();
+                Some((0..node_count).map(|x| graph.edges(x).to_vec()).collect())
+            }
+            NodeLocation::Points(_) => None,
+        };
+        let mut edits = Vec::new();
+
+        let Scenario {
+            identity,
+            map: location,
+            model,
+            messages,
+            settings,
+            frequency_plan,
+            gateway_uplink,
+            reception_feedback,
+            ..
+        } = &mut self.scenario;
+
+        let map = match location {
+            NodeLocation::Points(points) => &mut points.data[0].node_points,
+            NodeLocation::Graph(graph) => graph.display_locations_mut(),
+        };
+
         egui::SidePanel::left("Scenario Editor Inspector").show_inside(ui, |ui| {
+            provenance_panel(identity, self.generated_from.as_ref(), &mut self.regenerated, ui);
+
+            if ui.button("Export Image").clicked() {
+                self.image_export.open = true;
+            }
+
+            ComboBox::from_label("Node Colours")
+                .selected_text(self.colour_mode.map_or("Off", |x| x.title()))
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut self.colour_mode, None, "Off");
+                    for mode in CategoryColouring::ALL {
+                        ui.selectable_value(&mut self.colour_mode, Some(mode), mode.title());
+                    }
+                });
+
+            ui.checkbox(gateway_uplink, "Gateway Uplink")
+                .on_hover_text("Messages count as delivered once any gateway hears them");
+
+            ui.checkbox(reception_feedback, "Reception Feedback").on_hover_text(
+                "Transmitting nodes are told whenever a node fails to receive their transmission",
+            );
+
+            if let Some(edges) = &edges {
+                graph_edit_panel(
+                    &self.inspect_target,
+                    &mut self.graph_edit,
+                    edges,
+                    &mut edits,
+                    ui,
+                );
+            }
+
+            node_placement_panel(
+                settings,
+                &mut edits,
+                &mut self.bulk,
+                &mut self.rescale_factor,
+                ui,
+            );
+
             node_setting_edit_panel(
                 &mut self.inspect_target,
                 settings,
                 model,
+                frequency_plan,
                 map,
+                &mut edits,
                 &mut self.delete_node_pending,
                 ui,
             );
         });
 
+        sync_group_targets(messages, settings);
+
         egui::SidePanel::right("Scenario Editor Message Panel").show_inside(ui, |ui| {
             egui::ScrollArea::vertical().show(ui, |ui| {
                 message_editor_panel(
@@ -141,7 +536,8 @@ impl Widget for &mut ScenarioEditorPanel {
                     messages,
                     &mut self.message_sender_filter,
                     &mut self.message_target_filter,
-                    map,
+                    &mut self.message_tools,
+                    settings,
                     ui,
                 );
             });
@@ -156,32 +552,227 @@ impl Widget for &mut ScenarioEditorPanel {
             .inner
             .rect;
 
+        let colours = self.colour_mode.map(|x| x.colours(settings));
+
         editor_scene(
             &mut self.inspect_target,
             &mut self.scene,
             convert_rect(central_rect),
             map,
+            edges.as_deref(),
+            colours.as_ref(),
+            &mut self.graph_edit,
+            &mut self.bulk,
+            &mut edits,
             ui,
         );
 
+        if self.image_export.modal(ui.ctx()) {
+            let ImageExport {
+                width,
+                height,
+                caption,
+                ref path,
+                ..
+            } = self.image_export;
+
+            let scene_rect = self.scene.begin_export(width, height);
+            render_editor_scene(
+                &mut self.inspect_target,
+                &self.scene,
+                map,
+                edges.as_deref(),
+                colours.as_ref(),
+                ui,
+                scene_rect,
+            );
+
+            let caption = caption.then(|| scenario_name(identity));
+            let result = self.scene.finish_export(caption.as_deref(), path);
+            self.image_export.status = Some(match result {
+                Ok(()) => format!("Saved {path}"),
+                Err(e) => e,
+            });
+        }
+
+        let transformed = edits
+            .iter()
+            .any(|x| matches!(x, MapEdit::Recentre | MapEdit::Rescale(_)));
+        for edit in edits {
+            apply_edit(&mut self.scenario, edit);
+        }
+        if transformed {
+            let locations = self.scenario.map.display_locations(0.0 * SECONDS);
+            self.scene.zoom_to_fit(&locations);
+        }
+
+        self.check_dirty();
+
         ui.response()
     }
 }
 
+/// Where the scenario came from, with the generator it was made by if it was generated.
+fn provenance_panel(
+    identity: &ScenarioIdentity,
+    generated_from: Option<&ScenarioIdentity>,
+    regenerated: &mut Option<Scenario>,
+    ui: &mut egui::Ui,
+) {
+    match (identity, generated_from) {
+        (ScenarioIdentity::Generated { .. }, _) => {
+            ui.label(format!("Generated by {}", scenario_name(identity)));
+        }
+        (ScenarioIdentity::Custom, Some(original)) => {
+            ui.label(format!("Edited from {}", scenario_name(original)))
+                .on_hover_text("Custom since the edits, so results can't regenerate it");
+        }
+        (ScenarioIdentity::Custom, None) => {
+            ui.label("Custom Scenario");
+        }
+    }
+
+    if let Some(ScenarioIdentity::Generated { generator, .. }) = generated_from
+        && ui.button("Regenerate with new seed").clicked()
+    {
+        let identity = ScenarioIdentity::Generated {
+            generator: generator.clone(),
+            seed: rand::rand() as u64,
+        };
+        *regenerated = Some(identity.create());
+    }
+
+    ui.separator();
+}
+
+#[allow(clippy::too_many_arguments)]
 fn editor_scene(
     inspect_target: &mut Inspectable,
     scene: &mut SceneData,
     scene_rect: Rect,
     map: &mut Vec<Point>,
+    edges: Option<&[Vec<Edge>]>,
+    colours: Option<&CategoryColours>,
+    graph_edit: &mut GraphEditState,
+    bulk: &mut BulkPlacement,
+    edits: &mut Vec<MapEdit>,
     ui: &mut egui::Ui,
 ) {
     scene.camera_control(scene_rect);
-    scene.select_and_reposition_interaction(inspect_target, map, scene_rect);
 
+    match edges {
+        _ if bulk.drawing => bulk.area_interaction(scene, scene_rect),
+        Some(edges) if graph_edit.edge_mode => {
+            if let Some(clicked) = scene.clicked_node(map, scene_rect) {
+                match graph_edit.edge_start.take() {
+                    Some(start) if start != clicked => {
+                        graph_edit.toggle_edge(edges, start, clicked, edits);
+                    }
+                    // Clicking the first node again cancels
+                    Some(_) => (),
+                    None => graph_edit.edge_start = Some(clicked),
+                }
+
+                *inspect_target = Inspectable::Node(clicked);
+            }
+        }
+        _ => {
+            if !scene.box_select_interaction(inspect_target, map, scene_rect) {
+                scene.select_and_reposition_interaction(inspect_target, map, scene_rect);
+            }
+        }
+    }
+
+    render_editor_scene(inspect_target, scene, map, edges, colours, ui, scene_rect);
+
+    let line_base_size = 2. / scene.zoom_level;
+
+    if let Some(start) = graph_edit.edge_start {
+        let at = point_to_vec(map[start]);
+        let ring = scene.node_size() + 4.0 * line_base_size;
+        draw_circle_lines(at.x, at.y, ring, 3.0 * line_base_size, YELLOW);
+    }
+
+    scene.render_select_box();
+
+    if let Some(area) = bulk.area {
+        draw_rectangle_lines(area.x, area.y, area.w, area.h, 3.0 * line_base_size, SKYBLUE);
+    }
+}
+
+/// Everything that makes up the scenario itself, without the editing aids.
+fn render_editor_scene(
+    inspect_target: &mut Inspectable,
+    scene: &SceneData,
+    map: &Vec<Point>,
+    edges: Option<&[Vec<Edge>]>,
+    colours: Option<&CategoryColours>,
+    ui: &mut egui::Ui,
+    scene_rect: Rect,
+) {
     set_camera(&scene.camera);
     scene.render_grid();
-    scene.render_nodes(inspect_target, None, map, ui, scene_rect);
+
+    if let Some(edges) = edges {
+        let line_base_size = 2. / scene.zoom_level;
+
+        for (from, list) in edges.iter().enumerate() {
+            for edge in list.iter() {
+                let is_undirected = edges[edge.to].iter().any(|x| x.to == from);
+
+                if !is_undirected {
+                    scene.render_arrow(map[from], map[edge.to], BLACK);
+                } else if from < edge.to {
+                    let (start, end) = (point_to_vec(map[from]), point_to_vec(map[edge.to]));
+                    draw_line(start.x, start.y, end.x, end.y, 3.0 * line_base_size, BLACK);
+                }
+            }
+        }
+    }
+
+    let node_colours = colours.map(|x| &x.nodes);
+    scene.render_nodes(inspect_target, None, node_colours, map, ui, scene_rect);
     scene.render_scale_indicator(ui, scene_rect);
+
+    if let Some(colours) = colours {
+        scene.render_category_legend(ui, scene_rect, colours);
+    }
+}
+
+const MESSAGES_PER_PAGE: usize = 50;
+
+/// State for the message panel's paging, bulk edits and CSV files.
+struct MessageTools {
+    page: usize,
+    /// Seconds added to the time of every matching message
+    time_shift: f64,
+    csv_path: String,
+    /// Import or export problems waiting to be shown
+    csv_report: Option<Vec<String>>,
+}
+
+/// Appends the valid messages from the CSV file and describes every rejected row.
+fn import_messages(
+    path: &str,
+    messages: &mut Vec<ScenarioMessage>,
+    node_count: usize,
+) -> Vec<String> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) => return vec![format!("Import failed: {e}")],
+    };
+
+    let (imported, rejected) = read_messages(file, node_count);
+
+    let mut report = vec![format!(
+        "Imported {} messages, rejected {} rows",
+        imported.len(),
+        rejected.len()
+    )];
+    report.extend(rejected.iter().map(|x| x.to_string()));
+
+    messages.extend(imported);
+    report
 }
 
 fn message_editor_panel(
@@ -189,7 +780,8 @@ fn message_editor_panel(
     messages: &mut Vec<ScenarioMessage>,
     sender_filter: &mut Option<usize>,
     target_filter: &mut Option<usize>,
-    map: &mut Vec<Point>,
+    tools: &mut MessageTools,
+    settings: &[ScenarioNodeSettings],
     ui: &mut egui::Ui,
 ) {
     ui.heading("Messages Editor");
@@ -248,31 +840,85 @@ fn message_editor_panel(
         *target_filter = None;
     }
 
+    let matches = |message: &ScenarioMessage| {
+        sender_filter.is_none_or(|x| message.sender == x)
+            && target_filter.is_none_or(|x| message.targets.contains(&x))
+    };
+    let matching: Vec<usize> = (0..messages.len())
+        .filter(|&x| matches(&messages[x]))
+        .collect();
+
+    ui.separator();
+
+    ui.label(format!("Bulk ({} matching)", matching.len()));
+
+    ui.horizontal(|ui| {
+        ui.add(DragValue::new(&mut tools.time_shift).prefix("+/- ").suffix(" s"));
+        if ui.button("Shift Times").clicked() {
+            for &index in matching.iter() {
+                let time = &mut messages[index].generate_time;
+                *time = (time.seconds() + tools.time_shift).max(0.0) * SECONDS;
+            }
+        }
+    });
+
+    if ui.button("Delete All Matching").clicked() {
+        messages.retain(|x| !matches(x));
+        tools.page = 0;
+        return;
+    }
+
     ui.separator();
 
+    ui.label("CSV (sender,targets,time,size)");
+    ui.text_edit_singleline(&mut tools.csv_path);
+    ui.horizontal(|ui| {
+        if ui.button("Import").clicked() {
+            tools.csv_report = Some(import_messages(&tools.csv_path, messages, settings.len()));
+        }
+        if ui.button("Export").clicked() {
+            let result = File::create(&tools.csv_path)
+                .map_err(|e| e.to_string())
+                .and_then(|file| write_messages(messages, file).map_err(|e| e.to_string()));
+
+            if let Err(e) = result {
+                tools.csv_report = Some(vec![format!("Export failed: {e}")]);
+            }
+        }
+    });
+
+    ui.separator();
+
+    let page_count = matching.len().div_ceil(MESSAGES_PER_PAGE).max(1);
+    tools.page = tools.page.min(page_count - 1);
+
+    ui.horizontal(|ui| {
+        if ui.button("<").clicked() {
+            tools.page = tools.page.saturating_sub(1);
+        }
+        ui.label(format!("Page {} of {page_count}", tools.page + 1));
+        if ui.button(">").clicked() {
+            tools.page = (tools.page + 1).min(page_count - 1);
+        }
+    });
+
     let mut should_delete = None;
 
-    for (
-        index,
-        ScenarioMessage {
+    for &index in matching
+        .iter()
+        .skip(tools.page * MESSAGES_PER_PAGE)
+        .take(MESSAGES_PER_PAGE)
+    {
+        let ScenarioMessage {
             sender,
             targets,
+            group,
             generate_time: send_time,
             size,
+            deadline,
             ..
-        },
-    ) in messages.iter_mut().enumerate()
-    {
-        if let Some(ni) = sender_filter {
-            if sender != ni {
-                continue;
-            }
-        }
-        if let Some(ni) = target_filter {
-            if targets.contains(&ni) == false {
-                continue;
-            }
-        }
+        } = &mut messages[index];
+
         Frame::new()
             .inner_margin(3.0)
             .fill(item_background)
@@ -280,7 +926,7 @@ fn message_editor_panel(
                 let mut time_float = send_time.seconds();
                 ui.horizontal(|ui| {
                     ui.label("Sender: ");
-                    ui.add(DragValue::new(sender).range(0..=map.len() - 1));
+                    ui.add(DragValue::new(sender).range(0..=settings.len() - 1));
                 });
                 ui.horizontal(|ui| {
                     ui.label("Time:  ");
@@ -296,25 +942,32 @@ fn message_editor_panel(
                 });
                 *send_time = time_float * SECONDS;
 
-                let mut probably_broadcast = targets.len() > 1;
+                ui.horizontal(|ui| {
+                    let mut has_deadline = deadline.is_some();
+                    ui.checkbox(&mut has_deadline, "Deadline: ");
+                    match (has_deadline, deadline.as_mut()) {
+                        (true, Some(deadline)) => {
+                            let mut deadline_float = deadline.seconds();
+                            ui.add(
+                                DragValue::new(&mut deadline_float)
+                                    .suffix(" s")
+                                    .range(0..=99999),
+                            );
+                            *deadline = deadline_float * SECONDS;
+                        }
+                        (true, None) => *deadline = Some(60.0 * SECONDS),
+                        (false, _) => *deadline = None,
+                    }
+                });
 
-                if map.len() > 1 {
+                if let Some(group) = group {
                     ui.horizontal(|ui| {
-                        ui.label("Broadcast: ");
-                        ui.checkbox(&mut probably_broadcast, "");
+                        ui.label("Group: ");
+                        ui.add(DragValue::new(group));
+                        ui.label(format!("{} members", targets.len()));
                     });
-
-                    if probably_broadcast {
-                        *targets = (0..map.len()).collect();
-                        ui.label("");
-                    } else {
-                        let mut val = targets[0];
-                        ui.horizontal(|ui| {
-                            ui.label("Target: ");
-                            ui.add(DragValue::new(&mut val));
-                        });
-                        *targets = vec![val];
-                    }
+                } else if settings.len() > 1 {
+                    target_selector(index, targets, settings, ui);
                 }
 
                 ui.add_space(2.0);
@@ -335,24 +988,212 @@ fn message_editor_panel(
     }
 }
 
-fn node_setting_edit_panel(
-    inspect_target: &mut Inspectable,
+/// Checkbox for each node choosing exactly which nodes want a message.
+/// The targets are only rewritten when the choice changes, so they keep their order otherwise.
+fn target_selector(
+    index: usize,
+    targets: &mut Vec<usize>,
+    settings: &[ScenarioNodeSettings],
+    ui: &mut egui::Ui,
+) {
+    let node_count = settings.len();
+    let title = match targets.len() {
+        n if n == node_count => "Targets: everyone".to_owned(),
+        1 => format!("Target: {}", targets[0]),
+        n => format!("Targets: {n} nodes"),
+    };
+
+    egui::CollapsingHeader::new(title)
+        .id_salt(("message_targets", index))
+        .show(ui, |ui| {
+            ui.horizontal(|ui| {
+                if ui.small_button("All").clicked() {
+                    *targets = (0..node_count).collect();
+                }
+                if ui.small_button("Gateways").clicked() {
+                    *targets = (0..node_count).filter(|&x| settings[x].is_gateway).collect();
+                }
+            });
+
+            let mut chosen: Vec<bool> = (0..node_count).map(|x| targets.contains(&x)).collect();
+            let mut changed = false;
+
+            ui.horizontal_wrapped(|ui| {
+                for (id, chosen) in chosen.iter_mut().enumerate() {
+                    changed |= ui.checkbox(chosen, id.to_string()).changed();
+                }
+            });
+
+            if changed {
+                *targets = (0..node_count).filter(|&x| chosen[x]).collect();
+            }
+
+            if targets.is_empty() {
+                ui.colored_label(ui.visuals().error_fg_color, "Needs at least one target");
+            }
+        });
+}
+
+fn graph_edit_panel(
+    inspect_target: &Inspectable,
+    graph_edit: &mut GraphEditState,
+    edges: &[Vec<Edge>],
+    edits: &mut Vec<MapEdit>,
+    ui: &mut egui::Ui,
+) {
+    ui.heading("Graph Editor");
+
+    if ui.checkbox(&mut graph_edit.edge_mode, "Edit Edges").changed() {
+        graph_edit.edge_start = None;
+    }
+    ui.checkbox(&mut graph_edit.directed, "Directed");
+    ui.horizontal(|ui| {
+        ui.label("New Edge Weight");
+        ui.add(
+            DragValue::new(&mut graph_edit.new_weight)
+                .range(0.0..=f64::MAX)
+                .suffix(" m"),
+        );
+    });
+
+    if graph_edit.edge_mode {
+        ui.weak(match graph_edit.edge_start {
+            Some(start) => format!("Click another node to add or remove its edge with {start}"),
+            None => "Click two nodes to add or remove the edge between them".to_owned(),
+        });
+    }
+
+    if let Inspectable::Node(id) = *inspect_target
+        && id < edges.len()
+    {
+        ui.add_space(5.0);
+        ui.label(RichText::new(format!("Edges From Node {id}")).underline());
+
+        if edges[id].is_empty() {
+            ui.label("None");
+        }
+
+        for edge in edges[id].iter() {
+            ui.horizontal(|ui| {
+                ui.label(format!("-> {}", edge.to));
+
+                let mut weight = edge.weight.metres();
+                let response = ui.add(
+                    DragValue::new(&mut weight)
+                        .range(0.0..=f64::MAX)
+                        .suffix(" m"),
+                );
+
+                if response.changed() {
+                    let reverse = edges[edge.to].iter().any(|x| x.to == id);
+
+                    edits.push(MapEdit::SetEdge {
+                        from: id,
+                        to: edge.to,
+                        weight: weight * METRES,
+                    });
+                    if !graph_edit.directed && reverse {
+                        edits.push(MapEdit::SetEdge {
+                            from: edge.to,
+                            to: id,
+                            weight: weight * METRES,
+                        });
+                    }
+                }
+
+                if ui.button("Remove").clicked() {
+                    graph_edit.toggle_edge(edges, id, edge.to, edits);
+                }
+            });
+        }
+    }
+
+    ui.separator();
+}
+
+fn node_placement_panel(
     settings: &mut Vec<ScenarioNodeSettings>,
-    model: &mut frogcore::simulation::models::TransmissionModel,
-    map: &mut Vec<Point>,
-    modal_open: &mut Option<usize>,
+    edits: &mut Vec<MapEdit>,
+    bulk: &mut BulkPlacement,
+    rescale_factor: &mut f64,
     ui: &mut egui::Ui,
 ) {
     ui.heading("Node Editor");
 
     if ui.button("Add Node").clicked() {
-        map.push(Point {
+        edits.push(MapEdit::AddNode(Point {
             x: 25.0 * METRES,
             y: 25.0 * METRES,
-        });
+        }));
         settings.push(ScenarioNodeSettings::default());
     }
 
+    ui.horizontal(|ui| {
+        if ui
+            .button("Recentre")
+            .on_hover_text("Moves the nodes so where they start is centred on the origin")
+            .clicked()
+        {
+            edits.push(MapEdit::Recentre);
+        }
+
+        if ui
+            .button("Rescale")
+            .on_hover_text("Multiplies every distance between nodes by the factor")
+            .clicked()
+        {
+            edits.push(MapEdit::Rescale(*rescale_factor));
+        }
+        ui.add(DragValue::new(rescale_factor).range(0.01..=100.0).speed(0.01).prefix("x"));
+    });
+
+    egui::CollapsingHeader::new("Bulk Placement").show(ui, |ui| {
+        ui.horizontal(|ui| {
+            ui.label("Count");
+            ui.add(DragValue::new(&mut bulk.count).range(1..=1000));
+
+            ComboBox::from_id_salt("Placement Pattern")
+                .selected_text(format!("{:?}", bulk.pattern))
+                .show_ui(ui, |ui| {
+                    for value in PlacementPattern::VALUES {
+                        ui.selectable_value(&mut bulk.pattern, value, format!("{:?}", value));
+                    }
+                });
+        });
+
+        ui.horizontal(|ui| {
+            if ui.selectable_label(bulk.drawing, "Draw Area").clicked() {
+                bulk.drawing = !bulk.drawing;
+            }
+
+            match bulk.area {
+                Some(area) => ui.label(format!("{:.0} m x {:.0} m", area.w, area.h)),
+                None => ui.weak("Drag in the scene to draw"),
+            };
+        });
+
+        if let Some(area) = bulk.area
+            && ui.button(format!("Spawn {} Nodes", bulk.count)).clicked()
+        {
+            for point in bulk.positions(area) {
+                edits.push(MapEdit::AddNode(point));
+                settings.push(ScenarioNodeSettings::default());
+            }
+        }
+    });
+}
+
+#[allow(clippy::too_many_arguments)]
+fn node_setting_edit_panel(
+    inspect_target: &mut Inspectable,
+    settings: &mut Vec<ScenarioNodeSettings>,
+    model: &mut frogcore::simulation::models::TransmissionModel,
+    frequency_plan: &mut Vec<CarrierBand>,
+    map: &mut [Point],
+    edits: &mut Vec<MapEdit>,
+    modal_open: &mut Option<Vec<usize>>,
+    ui: &mut egui::Ui,
+) {
     ui.separator();
 
     match *inspect_target {
@@ -366,8 +1207,44 @@ fn node_setting_edit_panel(
             });
             inspect_node(&mut settings[id], &mut map[id], ui);
             ui.add_space(5.0);
-            if ui.button("Delete Node").clicked() {
-                *modal_open = Some(id);
+            ui.horizontal(|ui| {
+                if ui.button("Duplicate Node").clicked() {
+                    edits.push(MapEdit::AddNode(Point {
+                        x: map[id].x + 20.0 * METRES,
+                        y: map[id].y + 20.0 * METRES,
+                    }));
+                    settings.push(settings[id].clone());
+                    *inspect_target = Inspectable::Node(settings.len() - 1);
+                }
+                if ui.button("Delete Node").clicked() {
+                    *modal_open = Some(vec![id]);
+                }
+            });
+        }
+        Inspectable::Nodes(ref ids) => {
+            let ids = ids.clone();
+
+            ui.horizontal(|ui| {
+                ui.label(format!("{} nodes selected", ids.len()));
+
+                if ui.button("Deselect").clicked() {
+                    *inspect_target = Inspectable::Nothing;
+                }
+            });
+
+            ui.add_space(5.0);
+            ui.horizontal(|ui| {
+                let mut all_gateways = ids.iter().all(|&x| settings[x].is_gateway);
+
+                ui.label("Is Gateway: ");
+                if ui.checkbox(&mut all_gateways, "").changed() {
+                    ids.iter().for_each(|&x| settings[x].is_gateway = all_gateways);
+                }
+            });
+
+            ui.add_space(5.0);
+            if ui.button("Delete Nodes").clicked() {
+                *modal_open = Some(ids);
             }
         }
         _ => {
@@ -388,23 +1265,24 @@ fn node_setting_edit_panel(
     ui.add_space(5.0);
 
     use frogcore::simulation::models::*;
-    let (path_loss, noise_temp) = match model {
-        TransmissionModel::PairWiseNone(PairWiseCaptureEffect {
-            path_loss,
-            noise_temp,
-            ..
-        })
-        | TransmissionModel::PairWiseNormal(PairWiseCaptureEffect {
-            path_loss,
-            noise_temp,
-            ..
-        })
-        | TransmissionModel::PairWiseUniform(PairWiseCaptureEffect {
-            path_loss,
-            noise_temp,
-            ..
-        }) => (path_loss, noise_temp),
-    };
+
+    ui.horizontal(|ui| {
+        let mut lossy = matches!(model, TransmissionModel::BernoulliLoss(_));
+        if ui.checkbox(&mut lossy, "Random Packet Loss").changed() {
+            *model = match std::mem::replace(model, PairWiseCaptureEffect::default().into()) {
+                TransmissionModel::BernoulliLoss(loss) => *loss.inner,
+                physical => BernoulliLoss::new(physical, 0.1).into(),
+            };
+        }
+
+        if let TransmissionModel::BernoulliLoss(loss) = model {
+            let mut percent = loss.loss_probability * 100.0;
+            ui.add(DragValue::new(&mut percent).range(0.0..=100.0).speed(0.5).suffix(" %"));
+            loss.loss_probability = percent / 100.0;
+        }
+    });
+
+    let (path_loss, noise_temp) = physical_settings(model);
 
     let pathloss_label = match path_loss {
         PathlossModel::NoPathloss(_) => "No Pathloss",
@@ -477,6 +1355,37 @@ fn node_setting_edit_panel(
             });
         });
 
+        ui.add_space(10.0);
+        fading_edit(model, ui);
+
+        ui.add_space(10.0);
+        ui.label("Frequency Plan");
+
+        // Nodes using a band left out of the plan fail validation, see `Scenario::validate`
+        ui.indent("Frequency Plan", |ui| {
+            for band in [CarrierBand::B433, CarrierBand::B868] {
+                let mut allowed = frequency_plan.contains(&band);
+                let last = allowed && frequency_plan.len() == 1;
+
+                let checkbox = egui::Checkbox::new(&mut allowed, band_label(band));
+                let response = ui
+                    .add_enabled(!last, checkbox)
+                    .on_disabled_hover_text("The plan needs at least one band");
+                if response.changed() {
+                    frequency_plan.retain(|x| *x != band);
+                    if allowed {
+                        frequency_plan.push(band);
+                    }
+                }
+
+                let users = settings.iter().filter(|x| x.carrier_band == band).count();
+                if !allowed && users > 0 {
+                    let warning = format!("{users} nodes use {}", band_label(band));
+                    ui.colored_label(Color32::RED, warning);
+                }
+            }
+        });
+
         ui.separator();
     }
 }
@@ -502,6 +1411,26 @@ fn inspect_node(current_node: &mut ScenarioNodeSettings, point: &mut Point, ui:
         ui.checkbox(&mut current_node.is_gateway, "");
     });
 
+    ui.horizontal_wrapped(|ui| {
+        ui.label("Groups: ");
+
+        let mut removed = None;
+        for (index, group) in current_node.groups.iter_mut().enumerate() {
+            ui.add(DragValue::new(group));
+            if ui.small_button("x").clicked() {
+                removed = Some(index);
+            }
+        }
+
+        if let Some(index) = removed {
+            current_node.groups.remove(index);
+        }
+        if ui.small_button("+").clicked() {
+            let next = current_node.groups.iter().max().map_or(0, |x| x.saturating_add(1));
+            current_node.groups.push(next);
+        }
+    });
+
     ui.horizontal(|ui| {
         ui.label("Movement Indicator: ");
         ComboBox::from_id_salt("Movement Indicator")
@@ -517,15 +1446,217 @@ fn inspect_node(current_node: &mut ScenarioNodeSettings, point: &mut Point, ui:
             });
     });
 
+    ui.horizontal(|ui| {
+        ui.label("Reaction Time: ");
+        let mut millis = current_node.reaction_time.milis();
+        ui.add(DragValue::new(&mut millis).range(0.0..=60_000.0).speed(0.1).suffix(" ms"));
+        current_node.reaction_time = Time::from_milis(millis);
+    });
+
+    ui.horizontal(|ui| {
+        ui.label("Processing Time: ");
+        let mut millis = current_node.processing_time.milis();
+        ui.add(DragValue::new(&mut millis).range(0.0..=60_000.0).speed(10.0).suffix(" ms"));
+        current_node.processing_time = Time::from_milis(millis);
+    });
+
+    ui.horizontal(|ui| {
+        ui.label("Persistent Store: ");
+        ui.add(DragValue::new(&mut current_node.store_bytes).speed(16.0).suffix(" bytes"))
+            .on_hover_text("Space the node model has for state that survives restarts");
+    });
+
     ui.add_space(5.0);
-    ui.label(format!(
-        "Bandwidth: {:.3} kHz",
-        current_node.bandwidth.kHz()
-    ));
-    ui.label(format!("Carrier Band: {:?}", current_node.carrier_band));
-    ui.label(format!(
-        "Max Power: {:.3} dBm",
-        current_node.max_power.dbm()
-    ));
-    ui.label(format!("Default SF: {}", current_node.sf));
+    ui.label(RichText::new("Radio").underline());
+
+    ui.horizontal(|ui| {
+        ui.label("Modem Preset: ");
+        let current = current_node.preset();
+        ComboBox::from_id_salt("Modem Preset")
+            .selected_text(current.map_or("Custom".to_owned(), |x| x.to_string()))
+            .show_ui(ui, |ui| {
+                for preset in ModemPreset::ALL {
+                    if ui
+                        .selectable_label(current == Some(preset), preset.to_string())
+                        .clicked()
+                    {
+                        current_node.apply_preset(preset);
+                    }
+                }
+            });
+    });
+
+    // Widgets only offer values a LoRa radio can use, see `ScenarioNodeSettings::radio_error`
+    ui.horizontal(|ui| {
+        ui.label("Bandwidth: ");
+        ComboBox::from_id_salt("Bandwidth")
+            .selected_text(format!("{} kHz", current_node.bandwidth.kHz()))
+            .show_ui(ui, |ui| {
+                for khz in ScenarioNodeSettings::BANDWIDTHS_KHZ {
+                    ui.selectable_value(
+                        &mut current_node.bandwidth,
+                        Frequency::from_kHz(khz),
+                        format!("{khz} kHz"),
+                    );
+                }
+            });
+    });
+
+    ui.horizontal(|ui| {
+        ui.label("Carrier Band: ");
+        ComboBox::from_id_salt("Carrier Band")
+            .selected_text(band_label(current_node.carrier_band))
+            .show_ui(ui, |ui| {
+                for band in [CarrierBand::B433, CarrierBand::B868] {
+                    ui.selectable_value(&mut current_node.carrier_band, band, band_label(band));
+                }
+            });
+    });
+
+    ui.horizontal(|ui| {
+        ui.label("Max Power: ");
+        let mut dbm = current_node.max_power.dbm();
+        ui.add(
+            DragValue::new(&mut dbm)
+                .range(ScenarioNodeSettings::MAX_POWER_RANGE_DBM)
+                .speed(0.1)
+                .suffix(" dBm"),
+        );
+        current_node.max_power = Dbm::from_dbm(dbm);
+    });
+
+    ui.horizontal(|ui| {
+        ui.label("Default SF: ");
+        ui.add(egui::Slider::new(
+            &mut current_node.sf,
+            ScenarioNodeSettings::SF_RANGE,
+        ));
+    });
+
+    ui.horizontal(|ui| {
+        ui.label("Coding Rate: 4/");
+        ui.add(egui::Slider::new(
+            &mut current_node.coding_rate,
+            ScenarioNodeSettings::CODING_RATE_RANGE,
+        ));
+    });
+
+    ui.horizontal(|ui| {
+        ui.label("Preamble: ");
+        ui.add(
+            DragValue::new(&mut current_node.preamble_len)
+                .range(ScenarioNodeSettings::PREAMBLE_LEN_RANGE)
+                .suffix(" symbols"),
+        );
+    });
+
+    ui.checkbox(&mut current_node.explicit_header, "Explicit Header")
+        .on_hover_text("Send the LoRa physical header, turn off for implicit header mode");
+}
+
+/// Path loss and noise temperature of the capture effect model underneath any random loss.
+fn physical_settings(
+    model: &mut frogcore::simulation::models::TransmissionModel,
+) -> (
+    &mut frogcore::simulation::models::PathlossModel,
+    &mut Temperature,
+) {
+    use frogcore::simulation::models::*;
+
+    match model {
+        TransmissionModel::PairWiseNone(PairWiseCaptureEffect {
+            path_loss,
+            noise_temp,
+            ..
+        })
+        | TransmissionModel::PairWiseNormal(PairWiseCaptureEffect {
+            path_loss,
+            noise_temp,
+            ..
+        })
+        | TransmissionModel::PairWiseUniform(PairWiseCaptureEffect {
+            path_loss,
+            noise_temp,
+            ..
+        }) => (path_loss, noise_temp),
+        TransmissionModel::BernoulliLoss(loss) => physical_settings(&mut loss.inner),
+    }
+}
+
+/// Fading of the capture effect model. Switching distribution keeps the path loss and noise
+/// temperature, and the model is only replaced when the settings change.
+fn fading_edit(model: &mut frogcore::simulation::models::TransmissionModel, ui: &mut egui::Ui) {
+    use frogcore::simulation::models::FadingSettings;
+
+    let title = |fading: FadingSettings| match fading {
+        FadingSettings::None => "No Fading",
+        FadingSettings::Normal { .. } => "Normal",
+        FadingSettings::Uniform { .. } => "Uniform",
+    };
+
+    let current = model.fading();
+    let mut fading = current;
+
+    ui.horizontal(|ui| {
+        ui.label("Fading");
+        ComboBox::from_id_salt("Fading")
+            .selected_text(title(fading))
+            .show_ui(ui, |ui| {
+                let options = [
+                    FadingSettings::None,
+                    FadingSettings::Normal {
+                        mean: 0.0,
+                        std_dev: 4.0,
+                    },
+                    FadingSettings::Uniform {
+                        min: -4.0,
+                        max: 4.0,
+                    },
+                ];
+
+                for option in options {
+                    let selected = title(option) == title(fading);
+                    if ui.selectable_label(selected, title(option)).clicked() && !selected {
+                        fading = option;
+                    }
+                }
+            });
+    });
+
+    ui.indent("Fading Parameters", |ui| match &mut fading {
+        FadingSettings::None => (),
+        FadingSettings::Normal { std_dev, .. } => {
+            ui.horizontal(|ui| {
+                ui.label("Standard Deviation");
+                ui.add(
+                    DragValue::new(std_dev)
+                        .range(0.0..=f64::MAX)
+                        .speed(0.1)
+                        .suffix(" dB"),
+                );
+            });
+        }
+        FadingSettings::Uniform { min, max } => {
+            let (low, high) = (*min, *max);
+            ui.horizontal(|ui| {
+                ui.label("Range");
+                ui.add(DragValue::new(min).range(f64::MIN..=high).speed(0.1).suffix(" dB"));
+                ui.label("to");
+                ui.add(DragValue::new(max).range(low..=f64::MAX).speed(0.1).suffix(" dB"));
+            });
+        }
+    });
+
+    // Settings that aren't a valid distribution, like an empty range, are dropped
+    // so the values go back to the last valid ones
+    if fading != current {
+        let _ = model.set_fading(fading);
+    }
+}
+
+fn band_label(band: CarrierBand) -> &'static str {
+    match band {
+        CarrierBand::B433 => "433 MHz",
+        CarrierBand::B868 => "868 MHz",
+    }
 }
@@ -1,23 +1,87 @@
 use std::{cell::RefCell, sync::Arc};
 
 use egui::{ComboBox, DragValue, Frame, RichText, Widget};
-use macroquad::prelude::rand;
+use macroquad::prelude::*;
 use frogcore::{
     scenario::{
-        ScenarioIdentity,
+        Scenario, ScenarioIdentity,
         generation::{
-            ScenarioGenerator,
+            ScenarioGenerator, SfAssignment,
             messaging::IndependentRandomMessaging,
             positioning::{IndependentPositionFrames, PathwayMovement, WonderingNodes},
+            setting_limits,
         },
     },
     simulation::models::{
-        AdjustedFreeSpacePathLoss, Normal, PairWiseCaptureEffect, adjusted_free_space_path_loss,
+        AdjustedFreeSpacePathLoss, Normal, PairWiseCaptureEffect, PathlossModel, TransmissionModel,
+        adjusted_free_space_path_loss,
     },
-    units::{KM, METRES, MINS, MPS},
+    units::{KM, METRES, MINS, MPS, SECONDS},
 };
+use serde_inspector::{EditorHints, EnumSchema, FieldMeta};
 
-use crate::{GlobalAction, GuiStore, components::UiExt};
+use crate::{
+    GlobalAction, GuiStore, Inspectable,
+    components::UiExt,
+    convert_rect,
+    scene::{CategoryColouring, NodeColours, SceneData},
+};
+
+const PREVIEW_SIZE: egui::Vec2 = egui::vec2(500., 350.);
+
+/// A scenario from the custom generator shown before it's sent to the editor.
+struct GeneratorPreview {
+    scenario: Scenario,
+    scene: SceneData,
+    /// The camera can only be fitted once the size of the preview is known
+    needs_fit: bool,
+}
+
+impl GeneratorPreview {
+    fn new(identity: ScenarioIdentity) -> GeneratorPreview {
+        GeneratorPreview {
+            scenario: identity.create(),
+            scene: SceneData::new(),
+            needs_fit: true,
+        }
+    }
+
+    fn show(&mut self, ui: &mut egui::Ui) {
+        let (rect, _) = ui.allocate_exact_size(PREVIEW_SIZE, egui::Sense::hover());
+        let scene_rect = convert_rect(rect);
+        let map = self.scenario.map.display_locations(0.0 * SECONDS);
+
+        self.scene.set_viewport(scene_rect);
+        if self.needs_fit {
+            self.scene.zoom_to_fit(&map);
+            self.needs_fit = false;
+        }
+        self.scene.camera_control(scene_rect);
+
+        set_camera(&self.scene.camera);
+        draw_rectangle(-1e7, -1e7, 2e7, 2e7, Color::from_hex(0x303030));
+        self.scene.render_grid();
+
+        let settings = &self.scenario.settings;
+        let colours = if settings.windows(2).any(|x| x[0].sf != x[1].sf) {
+            CategoryColouring::Sf.colours(settings).nodes
+        } else {
+            NodeColours {
+                fill: vec![Color::from_hex(0xff8080); map.len()],
+                outlined: settings.iter().map(|x| x.is_gateway).collect(),
+            }
+        };
+        self.scene.render_nodes(
+            &mut Inspectable::Nothing,
+            None,
+            Some(&colours),
+            &map,
+            ui,
+            scene_rect,
+        );
+        set_default_camera();
+    }
+}
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum GeneratorSelection {
@@ -43,6 +107,8 @@ pub struct ScenarioGeneratorPanel {
     generator: ScenarioGenerator,
     generator_selection: GeneratorSelection,
     store: Arc<RefCell<GuiStore>>,
+    preview: Option<GeneratorPreview>,
+    editor_hints: EditorHints,
 
     // Random Placement
     rp_node_count: usize,
@@ -81,9 +147,13 @@ impl ScenarioGeneratorPanel {
                     .into(),
                 gateway_count: 2,
                 gateways_move: false,
+                reaction_times: None,
+                sf_assignment: SfAssignment::default(),
             },
             seed: 1,
             store,
+            preview: None,
+            editor_hints: editor_hints(),
             generator_selection: GeneratorSelection::RandomSquare,
             rp_node_count: 10,
             rp_side_len: 5000.,
@@ -124,6 +194,8 @@ impl Widget for &mut ScenarioGeneratorPanel {
                                 model: PairWiseCaptureEffect::default()
                                     .with_pathloss(adjusted_free_space_path_loss(3.7).into())
                                     .into(),
+                                reaction_times: None,
+                                sf_assignment: SfAssignment::default(),
                             },
                             seed: rand::rand() as u64,
                         }
@@ -160,6 +232,7 @@ impl Widget for &mut ScenarioGeneratorPanel {
                                 model: PairWiseCaptureEffect::default()
                                     .with_pathloss(adjusted_free_space_path_loss(3.5).into())
                                     .into(),
+                                reaction_times: None,
                             },
                             seed: rand::rand() as u64,
                         }
@@ -182,6 +255,7 @@ impl Widget for &mut ScenarioGeneratorPanel {
                                 n_connections: self.graph_min_degree,
                                 messaging: default_messaging(),
                                 directed: false,
+                                reaction_times: None,
                             },
                             seed: rand::rand() as u64,
                         }
@@ -209,7 +283,17 @@ impl Widget for &mut ScenarioGeneratorPanel {
                 ui.label("with seed: ");
 
                 ui.add(DragValue::new(&mut self.seed));
+
+                if ui.button("Preview").clicked() {
+                    self.preview = Some(GeneratorPreview::new(ScenarioIdentity::Generated {
+                        generator: self.generator.clone(),
+                        seed: self.seed,
+                    }));
+                }
             });
+
+            self.preview_panel(ui);
+
             ui.heading("Generator Type");
 
             let prev = self.generator_selection;
@@ -231,7 +315,7 @@ impl Widget for &mut ScenarioGeneratorPanel {
 
             let mut value = serde_inspector::to_value(&self.generator).unwrap();
             ui.heading("Settings");
-            serde_inspector::any_editor(12345, &mut value, ui);
+            serde_inspector::any_editor_with_hints(12345, &mut value, &self.editor_hints, ui);
 
             self.generator = value.deserialize_into().unwrap();
         });
@@ -240,6 +324,54 @@ impl Widget for &mut ScenarioGeneratorPanel {
     }
 }
 
+impl ScenarioGeneratorPanel {
+    fn preview_panel(&mut self, ui: &mut egui::Ui) {
+        let Some(preview) = &mut self.preview else {
+            return;
+        };
+
+        let mut accept = false;
+        let mut discard = false;
+
+        ui.horizontal_top(|ui| {
+            preview.show(ui);
+
+            ui.vertical(|ui| {
+                let settings = &preview.scenario.settings;
+                let gateways = settings.iter().filter(|x| x.is_gateway).count();
+
+                ui.label(format!("Nodes: {}", settings.len()));
+                ui.label(format!("Gateways: {gateways}"));
+                ui.label(format!("Messages: {}", preview.scenario.messages.len()));
+
+                let current = ScenarioIdentity::Generated {
+                    generator: self.generator.clone(),
+                    seed: self.seed,
+                };
+                if preview.scenario.identity != current {
+                    ui.label(
+                        RichText::new("Settings have changed since this preview")
+                            .color(egui::Color32::YELLOW),
+                    );
+                }
+
+                ui.add_space(5.);
+                ui.horizontal(|ui| {
+                    accept = ui.button("Accept").clicked();
+                    discard = ui.button("Discard").clicked();
+                });
+            });
+        });
+
+        if accept {
+            let preview = self.preview.take().unwrap();
+            self.store.borrow_mut().global_action = GlobalAction::SetScenario(preview.scenario);
+        } else if discard {
+            self.preview = None;
+        }
+    }
+}
+
 impl From<GeneratorSelection> for ScenarioGenerator {
     fn from(value: GeneratorSelection) -> Self {
         match value {
@@ -248,6 +380,7 @@ impl From<GeneratorSelection> for ScenarioGenerator {
                 n_connections: 3,
                 messaging: default_messaging(),
                 directed: false,
+                reaction_times: None,
             },
             GeneratorSelection::RandomSquare => ScenarioGenerator::RandomSquare {
                 node_count: 10,
@@ -260,6 +393,8 @@ impl From<GeneratorSelection> for ScenarioGenerator {
                 },
                 messaging: default_messaging(),
                 model: PairWiseCaptureEffect::default().into(),
+                reaction_times: None,
+                sf_assignment: SfAssignment::default(),
             },
             GeneratorSelection::WonderingRandomSquare => ScenarioGenerator::WonderingRandomSquare {
                 node_count: 10,
@@ -273,6 +408,8 @@ impl From<GeneratorSelection> for ScenarioGenerator {
                 },
                 messaging: default_messaging(),
                 model: PairWiseCaptureEffect::default().into(),
+                reaction_times: None,
+                sf_assignment: SfAssignment::default(),
             },
             GeneratorSelection::PathwaysOne => ScenarioGenerator::PathwaysOne {
                 passive_key_points: 5,
@@ -290,23 +427,55 @@ impl From<GeneratorSelection> for ScenarioGenerator {
                     nth_pathway_chance: vec![1.0, 0.5, 0.1],
                 },
                 model: PairWiseCaptureEffect::default().into(),
+                reaction_times: None,
             },
             GeneratorSelection::SimpleTreeGraph => ScenarioGenerator::SimpleTreeGraph {
                 nodes: 10,
                 min_degree: 1,
                 max_degree: 5,
                 messaging: default_messaging(),
+                reaction_times: None,
             },
             GeneratorSelection::RandomTilConnectedGraph => {
                 ScenarioGenerator::RandomTilConnectedGraph {
                     nodes: 10,
                     messaging: default_messaging(),
+                    reaction_times: None,
                 }
             }
         }
     }
 }
 
+/// Values for the settings editor to fall back on when switching options and models.
+fn editor_hints() -> EditorHints {
+    let mut hints = EditorHints::new();
+
+    hints.insert_default("emergency_time", serde_inspector::to_value(1.0 * MINS).unwrap());
+    hints.insert_enum(
+        "model",
+        EnumSchema::from_examples(TransmissionModel::examples()).unwrap(),
+    );
+    hints.insert_enum(
+        "path_loss",
+        EnumSchema::from_examples(PathlossModel::examples()).unwrap(),
+    );
+    hints.insert_enum(
+        "sf_assignment",
+        EnumSchema::from_examples(SfAssignment::examples()).unwrap(),
+    );
+
+    for (path, limits) in setting_limits() {
+        let meta = FieldMeta::new()
+            .range(limits.min, limits.max)
+            .speed(limits.speed)
+            .suffix(limits.suffix);
+        hints.insert_field(path, meta);
+    }
+
+    hints
+}
+
 fn default_messaging() -> IndependentRandomMessaging {
     IndependentRandomMessaging {
         message_count: 30,
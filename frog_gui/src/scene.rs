@@ -1,16 +1,222 @@
-use std::collections::HashSet;
+use std::{cell::RefCell, collections::HashSet};
 
 use macroquad::prelude::*;
-use frogcore::{node_location::Point, units::Length};
+use frogcore::{
+    node_location::Point,
+    scenario::{MovementIndicator, ScenarioNodeSettings},
+    simulation::data_structs::NodeSettings,
+    units::Length,
+};
 
 use crate::Inspectable;
 
+/// Fill colours that replace the default node colours, indexed by node id.
+pub struct NodeColours {
+    pub fill: Vec<Color>,
+    /// Nodes drawn with a white outline so they stand out whatever their fill, such as gateways
+    pub outlined: Vec<bool>,
+}
+
+/// Settings a [`CategoryColouring`] reads, from a scenario or from what a run used.
+pub trait CategorySettings {
+    /// Whether the node is a gateway, how it moves and its spreading factor
+    fn category_settings(&self) -> (bool, MovementIndicator, i32);
+}
+
+impl CategorySettings for ScenarioNodeSettings {
+    fn category_settings(&self) -> (bool, MovementIndicator, i32) {
+        (self.is_gateway, self.movement_indicator, self.sf)
+    }
+}
+
+impl CategorySettings for NodeSettings {
+    fn category_settings(&self) -> (bool, MovementIndicator, i32) {
+        (self.is_gateway, self.movement_indicator, self.sf)
+    }
+}
+
+/// Node properties that can be shown as fill colours without running the scenario.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CategoryColouring {
+    Gateway,
+    Role,
+    Sf,
+}
+
+impl CategoryColouring {
+    pub const ALL: [CategoryColouring; 3] = [
+        CategoryColouring::Gateway,
+        CategoryColouring::Role,
+        CategoryColouring::Sf,
+    ];
+
+    pub fn title(self) -> &'static str {
+        match self {
+            CategoryColouring::Gateway => "Gateway",
+            CategoryColouring::Role => "Role",
+            CategoryColouring::Sf => "Spreading Factor",
+        }
+    }
+
+    fn category(self, settings: &impl CategorySettings) -> (Color, String) {
+        const SF_COLOURS: [Color; 6] = [SKYBLUE, GREEN, YELLOW, ORANGE, PINK, VIOLET];
+
+        let (is_gateway, movement_indicator, sf) = settings.category_settings();
+        match (self, is_gateway) {
+            (CategoryColouring::Gateway | CategoryColouring::Role, true) => {
+                (GOLD, "Gateway".to_owned())
+            }
+            (CategoryColouring::Gateway, false) => (Color::from_hex(0xff8080), "Node".to_owned()),
+            (CategoryColouring::Role, false) => match movement_indicator {
+                MovementIndicator::Unset => (Color::from_hex(0xff8080), "Unset".to_owned()),
+                MovementIndicator::Mobile => (SKYBLUE, "Mobile".to_owned()),
+                MovementIndicator::Stationary => (VIOLET, "Stationary".to_owned()),
+            },
+            (CategoryColouring::Sf, _) => {
+                let index = (sf - 7).clamp(0, SF_COLOURS.len() as i32 - 1);
+                (SF_COLOURS[index as usize], format!("SF{sf}"))
+            }
+        }
+    }
+
+    /// Colours each node by its category, listing the categories present for the legend.
+    pub fn colours(self, settings: &[impl CategorySettings]) -> CategoryColours {
+        let mut legend: Vec<(Color, String)> = Vec::new();
+
+        let fill = settings
+            .iter()
+            .map(|x| {
+                let (colour, name) = self.category(x);
+                if !legend.iter().any(|(_, existing)| *existing == name) {
+                    legend.push((colour, name));
+                }
+                colour
+            })
+            .collect();
+
+        legend.sort_by(|a, b| a.1.cmp(&b.1));
+
+        CategoryColours {
+            title: self.title(),
+            nodes: NodeColours {
+                fill,
+                outlined: settings.iter().map(|x| x.category_settings().0).collect(),
+            },
+            legend,
+        }
+    }
+}
+
+/// Node colours for a [`CategoryColouring`] along with what each colour means.
+pub struct CategoryColours {
+    pub title: &'static str,
+    pub nodes: NodeColours,
+    pub legend: Vec<(Color, String)>,
+}
+
 pub struct SceneData {
     pub camera: Camera2D,
     pub zoom_level: f32,
     pub drag_token: Option<(usize, Vec2)>,
     pub show_help_text: bool,
     pub panning: Option<Vec2>,
+    /// World position a box selection started from
+    pub select_box_start: Option<Vec2>,
+    /// Set while the scene is being drawn into an image rather than the window
+    capture: Option<Capture>,
+}
+
+/// An image export in progress.
+struct Capture {
+    target: RenderTarget,
+    size: Vec2,
+    /// Text and legends are drawn by egui on screen so they are collected and drawn last
+    overlay: RefCell<Vec<Overlay>>,
+    /// How much larger the image is than the view on screen, used to scale text
+    scale: f32,
+    window_camera: Camera2D,
+}
+
+enum Overlay {
+    Text {
+        pos: Vec2,
+        align: egui::Align2,
+        text: String,
+        size: f32,
+        colour: Color,
+    },
+    Rect {
+        rect: Rect,
+        colour: Color,
+    },
+}
+
+/// Options for saving the scene as an image, edited in a modal.
+pub struct ImageExport {
+    pub open: bool,
+    pub path: String,
+    pub width: u32,
+    pub height: u32,
+    /// Burn the scenario name and time into the top left corner
+    pub caption: bool,
+    /// Outcome of the last export
+    pub status: Option<String>,
+}
+
+impl ImageExport {
+    pub fn new() -> ImageExport {
+        ImageExport {
+            open: false,
+            path: "scene.png".to_owned(),
+            width: 1920,
+            height: 1080,
+            caption: true,
+            status: None,
+        }
+    }
+
+    /// Shows the export modal if it is open.
+    /// Returns true on the frame the image should be written.
+    pub fn modal(&mut self, ctx: &egui::Context) -> bool {
+        if !self.open {
+            return false;
+        }
+
+        let mut export = false;
+
+        let modal = egui::Modal::new("Image Export Modal".into()).show(ctx, |ui| {
+            ui.heading("Export Image");
+
+            ui.horizontal(|ui| {
+                ui.label("Path: ");
+                ui.text_edit_singleline(&mut self.path);
+            });
+            ui.horizontal(|ui| {
+                ui.label("Size: ");
+                ui.add(egui::DragValue::new(&mut self.width).range(64..=8192));
+                ui.label("x");
+                ui.add(egui::DragValue::new(&mut self.height).range(64..=8192));
+            });
+            ui.checkbox(&mut self.caption, "Caption");
+
+            if let Some(status) = &self.status {
+                ui.label(status);
+            }
+
+            ui.horizontal(|ui| {
+                export = ui.button("Export").clicked();
+                if ui.button("Close").clicked() {
+                    self.open = false;
+                }
+            });
+        });
+
+        if modal.should_close() {
+            self.open = false;
+        }
+
+        export
+    }
 }
 
 impl SceneData {
@@ -26,6 +232,8 @@ impl SceneData {
             camera,
             drag_token: None,
             panning: None,
+            select_box_start: None,
+            capture: None,
             show_help_text: true,
         }
     }
@@ -43,16 +251,208 @@ impl SceneData {
             max_y = max_y.max(y);
         }
 
-        let x_factor = (screen_width()) / (max_x - min_x).max(10.);
-        let y_factor = (screen_height()) / (max_y - min_y).max(10.);
+        let view = self.view_size();
+        let x_factor = view.x / (max_x - min_x).max(10.);
+        let y_factor = view.y / (max_y - min_y).max(10.);
 
         self.camera.target = vec2((max_x + min_x) / 2., (max_y + min_y) / 2.);
         self.zoom_level = x_factor.min(y_factor);
         self.zoom_level = self.zoom_level.clamp(0.1, 10.0);
-        self.camera.zoom = vec2(
-            self.zoom_level / screen_width(),
-            self.zoom_level / screen_height(),
+        self.camera.zoom = self.zoom_level / self.view_size();
+    }
+
+    /// Draws the scene into `rect` instead of the whole window, keeping the current zoom level.
+    pub fn set_viewport(&mut self, rect: Rect) {
+        let bottom = screen_height() - rect.y - rect.h;
+        self.camera.viewport = Some((rect.x as i32, bottom as i32, rect.w as i32, rect.h as i32));
+        self.camera.zoom = self.zoom_level / self.view_size();
+    }
+
+    pub fn clear_viewport(&mut self) {
+        self.camera.viewport = None;
+        self.camera.zoom = self.zoom_level / self.view_size();
+    }
+
+    /// Size in pixels of the area the scene is drawn into.
+    fn view_size(&self) -> Vec2 {
+        self.view_rect().size()
+    }
+
+    /// Area the scene is drawn into, in window pixels or image pixels while exporting.
+    fn view_rect(&self) -> Rect {
+        match (&self.capture, self.camera.viewport) {
+            (Some(capture), _) => Rect::new(0., 0., capture.size.x, capture.size.y),
+            (None, Some((x, y, w, h))) => {
+                let top = screen_height() - (y + h) as f32;
+                Rect::new(x as f32, top, w as f32, h as f32)
+            }
+            (None, None) => Rect::new(0., 0., screen_width(), screen_height()),
+        }
+    }
+
+    /// Screen position of a world position, taking the viewport into account.
+    pub fn world_to_screen(&self, at: Vec2) -> Vec2 {
+        let view = self.view_rect();
+        let ndc = self.camera.matrix().transform_point3(vec3(at.x, at.y, 0.));
+
+        view.point() + vec2((ndc.x / 2. + 0.5) * view.w, (0.5 - ndc.y / 2.) * view.h)
+    }
+
+    fn screen_to_world(&self, at: Vec2) -> Vec2 {
+        let view = self.view_rect();
+        let ndc = vec2(
+            (at.x - view.x) / view.w * 2. - 1.,
+            1. - (at.y - view.y) / view.h * 2.,
         );
+        let world = self.camera.matrix().inverse().transform_point3(ndc.extend(0.));
+
+        vec2(world.x, world.y)
+    }
+
+    /// Text drawn over the scene, captured into the image while exporting.
+    pub fn overlay_text(
+        &self,
+        ui: &egui::Ui,
+        pos: Vec2,
+        align: egui::Align2,
+        text: &str,
+        size: f32,
+        colour: egui::Color32,
+    ) {
+        match &self.capture {
+            Some(capture) => capture.overlay.borrow_mut().push(Overlay::Text {
+                pos,
+                align,
+                text: text.to_owned(),
+                size: size * capture.scale,
+                colour: egui_to_colour(colour),
+            }),
+            None => {
+                ui.painter().text(
+                    egui::pos2(pos.x, pos.y),
+                    align,
+                    text,
+                    egui::FontId::monospace(size),
+                    colour,
+                );
+            }
+        }
+    }
+
+    /// Filled rectangle in screen space, captured into the image while exporting.
+    pub fn overlay_rect(&self, ui: &egui::Ui, rect: Rect, colour: egui::Color32) {
+        match &self.capture {
+            Some(capture) => capture.overlay.borrow_mut().push(Overlay::Rect {
+                rect,
+                colour: egui_to_colour(colour),
+            }),
+            None => {
+                let rect = egui::Rect::from_min_size(
+                    egui::pos2(rect.x, rect.y),
+                    egui::vec2(rect.w, rect.h),
+                );
+                ui.painter().rect_filled(rect, 0.0, colour);
+            }
+        }
+    }
+
+    /// Points the camera at an offscreen image of `width` by `height` showing what's on screen.
+    /// Draw the scene as normal using the returned rect as the scene rect,
+    /// then call [`SceneData::finish_export`].
+    pub fn begin_export(&mut self, width: u32, height: u32) -> Rect {
+        let target = render_target(width, height);
+        target.texture.set_filter(FilterMode::Linear);
+
+        let size = vec2(width as f32, height as f32);
+        let window_view = self.view_size();
+
+        // Same horizontal extent as the window with square pixels.
+        // Flipped vertically as render targets are stored bottom up.
+        let zoom = self.camera.zoom.x;
+        let camera = Camera2D {
+            target: self.camera.target,
+            zoom: vec2(zoom, -zoom * size.x / size.y),
+            render_target: Some(target.clone()),
+            ..Default::default()
+        };
+
+        let window_camera = std::mem::replace(&mut self.camera, camera);
+        self.capture = Some(Capture {
+            target,
+            size,
+            overlay: RefCell::new(Vec::new()),
+            scale: size.x / window_view.x,
+            window_camera,
+        });
+
+        set_camera(&self.camera);
+        clear_background(Color::from_hex(0x404040));
+
+        Rect::new(0., 0., size.x, size.y)
+    }
+
+    /// Draws the collected overlay and an optional caption, then writes the image as a PNG.
+    pub fn finish_export(&mut self, caption: Option<&str>, path: &str) -> Result<(), String> {
+        let Some(capture) = self.capture.take() else {
+            return Err("No export in progress".to_owned());
+        };
+        self.camera = capture.window_camera;
+
+        let mut overlay_camera = Camera2D::from_display_rect(Rect::new(
+            0.,
+            0.,
+            capture.size.x,
+            capture.size.y,
+        ));
+        overlay_camera.render_target = Some(capture.target.clone());
+        set_camera(&overlay_camera);
+
+        let mut overlay = capture.overlay.into_inner();
+        if let Some(caption) = caption {
+            let size = 24. * capture.scale;
+            let width = measure_text(caption, None, size as u16, 1.).width;
+            overlay.push(Overlay::Rect {
+                rect: Rect::new(0., 0., width + size, size * 1.6),
+                colour: BLACK.with_alpha(0.5),
+            });
+            overlay.push(Overlay::Text {
+                pos: vec2(size / 2., size * 0.3),
+                align: egui::Align2::LEFT_TOP,
+                text: caption.to_owned(),
+                size,
+                colour: WHITE,
+            });
+        }
+
+        for item in overlay {
+            match item {
+                Overlay::Text {
+                    pos,
+                    align,
+                    text,
+                    size,
+                    colour,
+                } => draw_aligned_text(&text, pos, align, size, colour),
+                Overlay::Rect { rect, colour } => {
+                    draw_rectangle(rect.x, rect.y, rect.w, rect.h, colour)
+                }
+            }
+        }
+
+        set_default_camera();
+
+        // Encoded directly rather than through `Image::export_png`, which guesses the format from
+        // the extension and panics on anything it can't write
+        let image = capture.target.texture.get_texture_data();
+        image::save_buffer_with_format(
+            path,
+            &image.bytes,
+            image.width as u32,
+            image.height as u32,
+            image::ColorType::Rgba8,
+            image::ImageFormat::Png,
+        )
+        .map_err(|e| format!("Could not write {path}: {e}"))
     }
 
     pub fn camera_control(&mut self, scene_rect: Rect) {
@@ -69,10 +469,7 @@ impl SceneData {
         self.zoom_level *= scroll.tanh() * 0.25 + 1.0;
 
         self.zoom_level = self.zoom_level.clamp(0.1, 10.0);
-        self.camera.zoom = vec2(
-            self.zoom_level / screen_width(),
-            self.zoom_level / screen_height(),
-        );
+        self.camera.zoom = self.zoom_level / self.view_size();
 
         let delta = mouse_pos - self.world_mouse_pos();
         self.camera.target += delta;
@@ -99,27 +496,74 @@ impl SceneData {
         map: &Vec<Point>,
         scene_rect: Rect,
     ) {
-        if !scene_rect.contains(mouse_position().into()) {
+        if !scene_rect.contains(mouse_position().into())
+            || !is_mouse_button_pressed(MouseButton::Left)
+        {
             return;
         }
 
-        let mouse_pos = self.world_mouse_pos();
-        let left_click = is_mouse_button_pressed(MouseButton::Left);
-        let node_size = self.node_size();
+        match self.node_under_mouse(map) {
+            Some(node_id) => *inspect_target = Inspectable::Node(node_id),
+            None if matches!(inspect_target, Inspectable::MessageTrace(_)) => {
+                *inspect_target = Inspectable::Nothing;
+            }
+            None => (),
+        }
+    }
 
-        if left_click {
-            let clicked = map
-                .iter()
-                .enumerate()
-                .find(|(_, x)| {
-                    (mouse_pos - point_to_vec(**x)).length_squared() < node_size * node_size
-                })
-                .map(|(i, _)| i);
+    /// Id of the node clicked this frame, if any.
+    pub fn clicked_node(&self, map: &[Point], scene_rect: Rect) -> Option<usize> {
+        if !scene_rect.contains(mouse_position().into())
+            || !is_mouse_button_pressed(MouseButton::Left)
+        {
+            return None;
+        }
 
-            if let Some(node_id) = clicked {
-                *inspect_target = Inspectable::Node(node_id);
-            }
+        self.node_under_mouse(map)
+    }
+
+    /// Id of the node under the mouse, for tooltips.
+    pub fn hovered_node(&self, map: &[Point], scene_rect: Rect) -> Option<usize> {
+        if !self.can_hover(scene_rect) {
+            return None;
+        }
+
+        self.node_under_mouse(map)
+    }
+
+    /// Whether the mouse is within a few pixels of the line between two world positions.
+    pub fn segment_hovered(&self, start: Vec2, end: Vec2, scene_rect: Rect) -> bool {
+        const TOLERANCE: f32 = 6.0;
+
+        if !self.can_hover(scene_rect) {
+            return false;
         }
+
+        // Compared on screen so the tolerance doesn't change with zoom
+        let mouse = Vec2::from(mouse_position());
+        let start = self.world_to_screen(start);
+        let along = self.world_to_screen(end) - start;
+        let t = (mouse - start).dot(along) / along.length_squared().max(f32::EPSILON);
+        let t = t.clamp(0., 1.);
+
+        (start + along * t).distance(mouse) < TOLERANCE
+    }
+
+    /// Tooltips are held back while a button is down so they don't cover drags and pans.
+    fn can_hover(&self, scene_rect: Rect) -> bool {
+        scene_rect.contains(mouse_position().into())
+            && self.panning.is_none()
+            && !is_mouse_button_down(MouseButton::Left)
+    }
+
+    fn node_under_mouse(&self, map: &[Point]) -> Option<usize> {
+        let mouse_pos = self.world_mouse_pos();
+        let node_size = self.node_size();
+
+        map.iter()
+            .enumerate()
+            .find(|(_, x)| (mouse_pos - point_to_vec(**x)).length_squared() < node_size * node_size)
+            .map(|(i, _)| i)
     }
 
     pub fn select_and_reposition_interaction(
@@ -139,13 +583,21 @@ impl SceneData {
         // Handle Dragging
         if let Some((key, offset)) = self.drag_token {
             let drag_point = mouse_pos + offset;
+            let drag_point = Point {
+                x: Length::from_metres(drag_point.x as f64),
+                y: Length::from_metres(drag_point.y as f64),
+            };
 
             // Continue Dragging
             if left_click {
-                map[key] = Point {
-                    x: Length::from_metres(drag_point.x as f64),
-                    y: Length::from_metres(drag_point.y as f64),
-                };
+                match inspect_target {
+                    // Every selected node moves by the same amount as the dragged one
+                    Inspectable::Nodes(ids) if ids.contains(&key) => {
+                        let delta = drag_point - map[key];
+                        ids.iter().for_each(|&id| map[id] = map[id] + delta);
+                    }
+                    _ => map[key] = drag_point,
+                }
             }
             // End dragging
             else {
@@ -162,7 +614,13 @@ impl SceneData {
                 })
                 .map(|(i, _)| i);
 
-            if let Some(node_id) = clicked {
+            let in_group = clicked.is_some_and(|node_id| {
+                matches!(inspect_target, Inspectable::Nodes(ids) if ids.contains(&node_id))
+            });
+
+            if let Some(node_id) = clicked
+                && !in_group
+            {
                 *inspect_target = Inspectable::Node(node_id);
             }
 
@@ -171,6 +629,68 @@ impl SceneData {
         }
     }
 
+    /// Shift and drag to select every node inside a box.
+    /// Returns true while a box is being drawn.
+    pub fn box_select_interaction(
+        &mut self,
+        inspect_target: &mut Inspectable,
+        map: &[Point],
+        scene_rect: Rect,
+    ) -> bool {
+        let mouse_pos = self.world_mouse_pos();
+
+        if self.select_box_start.is_none()
+            && self.drag_token.is_none()
+            && scene_rect.contains(mouse_position().into())
+            && is_key_down(KeyCode::LeftShift)
+            && is_mouse_button_pressed(MouseButton::Left)
+        {
+            self.select_box_start = Some(mouse_pos);
+        }
+
+        let Some(start) = self.select_box_start else {
+            return false;
+        };
+
+        if is_mouse_button_down(MouseButton::Left) {
+            return true;
+        }
+
+        self.select_box_start = None;
+
+        let (min, max) = (start.min(mouse_pos), start.max(mouse_pos));
+        let mut ids: Vec<usize> = map
+            .iter()
+            .enumerate()
+            .filter(|(_, x)| {
+                let point = point_to_vec(**x);
+                point.cmpge(min).all() && point.cmple(max).all()
+            })
+            .map(|(i, _)| i)
+            .collect();
+
+        *inspect_target = match ids.len() {
+            0 => Inspectable::Nothing,
+            1 => Inspectable::Node(ids.pop().unwrap()),
+            _ => Inspectable::Nodes(ids),
+        };
+
+        true
+    }
+
+    pub fn render_select_box(&self) {
+        let Some(start) = self.select_box_start else {
+            return;
+        };
+
+        let end = self.world_mouse_pos();
+        let min = start.min(end);
+        let size = (start - end).abs();
+        let line_base_size = 2. / self.zoom_level;
+
+        draw_rectangle_lines(min.x, min.y, size.x, size.y, 2.0 * line_base_size, YELLOW);
+    }
+
     pub fn scene_egui(&mut self, ui: &mut egui::Ui, can_drag: bool) {
         if self.show_help_text {
             egui::Frame::new()
@@ -180,6 +700,7 @@ impl SceneData {
                 .show(ui, |ui| {
                     if can_drag {
                         ui.label("Left click to select and drag to move nodes");
+                        ui.label("Shift and drag to select several nodes");
                     } else {
                         ui.label("Left click to select nodes");
                     }
@@ -234,34 +755,121 @@ impl SceneData {
         let line_base_size = 2. / self.zoom_level;
 
         let ni = scene_rect.point() + scene_rect.size() - vec2(10., 20.);
-        let Vec2 { x, y } = self.camera.screen_to_world(ni);
+        let Vec2 { x, y } = self.screen_to_world(ni);
 
         draw_line(x - 1000., y, x, y, 10. * line_base_size, BLUE);
         draw_line(x - 100., y, x, y, 10. * line_base_size, RED);
 
-        ui.painter().text(
-            egui::Pos2::new(ni.x, ni.y - 10.),
+        self.overlay_text(
+            ui,
+            vec2(ni.x, ni.y - 10.),
             egui::Align2::RIGHT_BOTTOM,
             "  Red:  100m\nTotal: 1000m",
-            egui::FontId::monospace(18.0),
+            18.0,
             egui::Color32::WHITE,
         );
     }
 
+    /// Draws a red to green gradient in the bottom left corner of the scene explaining a heatmap.
+    pub fn render_heatmap_legend(
+        &self,
+        ui: &mut egui::Ui,
+        scene_rect: Rect,
+        title: &str,
+        low: &str,
+        high: &str,
+    ) {
+        let size = vec2(200., 12.);
+        let min = vec2(scene_rect.x + 10., scene_rect.y + scene_rect.h - 30.);
+        let steps = 20;
+
+        for step in 0..steps {
+            let colour = heat_colour(step as f32 / (steps - 1) as f32);
+            let width = size.x / steps as f32;
+
+            self.overlay_rect(
+                ui,
+                Rect::new(min.x + width * step as f32, min.y, width, size.y),
+                colour_to_egui(colour),
+            );
+        }
+
+        let white = egui::Color32::WHITE;
+        self.overlay_text(ui, min, egui::Align2::LEFT_BOTTOM, title, 14.0, white);
+        self.overlay_text(
+            ui,
+            min + vec2(0., size.y),
+            egui::Align2::LEFT_TOP,
+            low,
+            14.0,
+            white,
+        );
+        self.overlay_text(
+            ui,
+            min + size,
+            egui::Align2::RIGHT_TOP,
+            high,
+            14.0,
+            white,
+        );
+    }
+
+    /// Draws a box in the bottom left corner of the scene naming each node colour.
+    pub fn render_category_legend(
+        &self,
+        ui: &mut egui::Ui,
+        scene_rect: Rect,
+        colours: &CategoryColours,
+    ) {
+        let row = 18.;
+        let height = row * (colours.legend.len() + 1) as f32 + 8.;
+        let min = vec2(scene_rect.x + 10., scene_rect.y + scene_rect.h - 10. - height);
+
+        self.overlay_rect(
+            ui,
+            Rect::new(min.x, min.y, 160., height),
+            egui::Color32::from_black_alpha(160),
+        );
+
+        let white = egui::Color32::WHITE;
+        let title_pos = min + vec2(6., 4.);
+        self.overlay_text(ui, title_pos, egui::Align2::LEFT_TOP, colours.title, 14.0, white);
+
+        for (i, (colour, name)) in colours.legend.iter().enumerate() {
+            let top = min + vec2(6., 4. + row * (i + 1) as f32);
+
+            self.overlay_rect(
+                ui,
+                Rect::new(top.x, top.y + 2., 12., 12.),
+                colour_to_egui(*colour),
+            );
+            self.overlay_text(
+                ui,
+                top + vec2(18., 0.),
+                egui::Align2::LEFT_TOP,
+                name,
+                14.0,
+                white,
+            );
+        }
+    }
+
     pub fn render_nodes(
         &self,
         inspect_target: &mut Inspectable,
         senders: Option<&HashSet<usize>>,
+        node_colours: Option<&NodeColours>,
         map: &Vec<Point>,
         ui: &mut egui::Ui,
         scene_rect: Rect,
     ) {
         let node_size = self.node_size();
+        let line_base_size = 2. / self.zoom_level;
         for (i, point) in map.iter().enumerate() {
-            let is_inspected = if let Inspectable::Node(id) = inspect_target {
-                *id == i
-            } else {
-                false
+            let is_inspected = match inspect_target {
+                Inspectable::Node(id) => *id == i,
+                Inspectable::Nodes(ids) => ids.contains(&i),
+                _ => false,
             };
 
             let is_sending = senders.is_some_and(|x| x.contains(&i));
@@ -275,23 +883,59 @@ impl SceneData {
 
             let at_pos = vec2(point.x.metres() as f32, point.y.metres() as f32);
 
-            draw_circle(at_pos.x, at_pos.y, node_size, colour);
+            match node_colours {
+                Some(node_colours) => {
+                    let thickness = 3.0 * line_base_size;
+                    draw_circle(at_pos.x, at_pos.y, node_size, node_colours.fill[i]);
+
+                    // The fill shows the metric so inspection and sending become rings
+                    if is_inspected || is_sending {
+                        let ring = node_size + 4.0 * line_base_size;
+                        draw_circle_lines(at_pos.x, at_pos.y, ring, thickness, colour);
+                    }
+                    if node_colours.outlined[i] {
+                        draw_circle_lines(at_pos.x, at_pos.y, node_size, thickness, WHITE);
+                    }
+                }
+                None => draw_circle(at_pos.x, at_pos.y, node_size, colour),
+            }
 
-            let screen_pos = self.camera.world_to_screen(at_pos);
+            let screen_pos = self.world_to_screen(at_pos);
 
             if scene_rect.contains(screen_pos) {
-                ui.painter().text(
-                    egui::Pos2::new(screen_pos.x, screen_pos.y),
+                self.overlay_text(
+                    ui,
+                    screen_pos,
                     egui::Align2::CENTER_CENTER,
-                    i.to_string(),
-                    egui::FontId::monospace(24.0),
+                    &i.to_string(),
+                    24.0,
                     egui::Color32::BLACK,
                 );
             }
         }
     }
 
-    fn world_mouse_pos(&self) -> Vec2 {
+    /// Draws an arrow between two nodes that stops at the edge of the target node.
+    pub fn render_arrow(&self, from: Point, to: Point, colour: Color) {
+        let line_base_size = 2. / self.zoom_level;
+        let start = point_to_vec(from);
+        let end = point_to_vec(to);
+
+        let direction = (end - start).normalize_or_zero();
+        let tip = end - direction * self.node_size();
+        let side = direction.perp() * 6.0 * line_base_size;
+        let back = tip - direction * 12.0 * line_base_size;
+
+        draw_line(start.x, start.y, back.x, back.y, 3.0 * line_base_size, colour);
+        draw_triangle(tip, back + side, back - side, colour);
+    }
+
+    /// Moves the camera so `point` is in the middle of the scene without changing the zoom.
+    pub fn centre_on(&mut self, point: Point) {
+        self.camera.target = point_to_vec(point);
+    }
+
+    pub fn world_mouse_pos(&self) -> Vec2 {
         self.camera.screen_to_world(mouse_position().into())
     }
 
@@ -300,6 +944,46 @@ impl SceneData {
     }
 }
 
+/// Red at 0 through yellow to green at 1.
+pub fn heat_colour(t: f32) -> Color {
+    let t = t.clamp(0., 1.);
+    Color::new((2. - 2. * t).min(1.), (2. * t).min(1.), 0., 1.)
+}
+
+fn egui_to_colour(colour: egui::Color32) -> Color {
+    let [r, g, b, a] = colour.to_srgba_unmultiplied();
+    Color::from_rgba(r, g, b, a)
+}
+
+fn colour_to_egui(colour: Color) -> egui::Color32 {
+    let [r, g, b, a]: [u8; 4] = colour.into();
+    egui::Color32::from_rgba_unmultiplied(r, g, b, a)
+}
+
+/// Draws text, which may span several lines, positioned like egui's painter would.
+fn draw_aligned_text(text: &str, pos: Vec2, align: egui::Align2, size: f32, colour: Color) {
+    let font_size = size as u16;
+    let line_height = size * 1.2;
+    let lines: Vec<&str> = text.lines().collect();
+
+    let width = lines
+        .iter()
+        .map(|x| measure_text(x, None, font_size, 1.).width)
+        .fold(0., f32::max);
+    let height = line_height * lines.len() as f32;
+
+    let left = pos.x - width * align.x().to_factor();
+    let top = pos.y - height * align.y().to_factor();
+
+    for (i, line) in lines.iter().enumerate() {
+        let line_width = measure_text(line, None, font_size, 1.).width;
+        // Lines line up on the side they are anchored to
+        let x = left + (width - line_width) * align.x().to_factor();
+        let baseline = top + line_height * (i as f32 + 0.8);
+        draw_text(line, x, baseline, size, colour);
+    }
+}
+
 pub fn point_to_vec(point: Point) -> Vec2 {
     vec2(point.x.metres() as f32, point.y.metres() as f32)
 }
@@ -1,19 +1,24 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
 
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    scenario::{MessageMarker, Scenario, ScenarioNodeSettings},
-    sim_file::{OutputIdentity, SimOutput},
+    node::{BasicHeader, Destination, Header, HeaderSize},
+    node_location::NodeLocation,
+    scenario::{MessageMarker, Scenario, ScenarioMessage},
+    sim_file::{NodeStats, OutputIdentity, SimOutput},
     simulation::{
         MessageContent,
-        data_structs::{LogContent, LogItem, LogSource, Transmission},
+        data_structs::{LogContent, LogItem, LogSource, NodeSettings, Transmission},
     },
-    units::{SECONDS, Time},
+    units::{Frequency, SECONDS, Time},
 };
 
 pub struct CompleteAnalysis {
-    pub node_settings: Vec<ScenarioNodeSettings>,
+    /// Settings each node ran with. Index is node id.
+    /// Taken from the output when it has them, otherwise converted from the scenario's,
+    /// which may have been edited since the run.
+    pub node_settings: Vec<NodeSettings>,
 
     /// Lists is log items for each node.
     /// Outer vec is nodes (index is node id).
@@ -33,18 +38,54 @@ pub struct CompleteAnalysis {
     /// Sum of the airtime of all transmissions in seconds.
     pub total_airtime: f64,
 
+    /// Number of transmissions sent by each node. Index is node id.
+    pub transmissions_per_node: Vec<usize>,
+
+    /// Sum of the airtime of each node's transmissions in seconds. Index is node id.
+    pub airtime_per_node: Vec<f64>,
+
+    /// Number of receptions blocked by interference at each node. Index is node id.
+    pub blocked_per_node: Vec<usize>,
+
+    /// Counters for each node. Index is node id.
+    /// Taken from the output when it has them, otherwise recomputed from the logs
+    /// which leaves [`NodeStats::too_weak`] and [`NodeStats::wrong_sf`] at zero as those are
+    /// not logged,
+    /// and [`NodeStats::relay_drops`] at zero unless node logs were on.
+    /// The rng draw counts are always zero when recomputed.
+    pub node_stats: Vec<NodeStats>,
+
+    /// Transmissions grouped by spreading factor and bandwidth, ordered by both.
+    pub sf_breakdown: Vec<SfBucketStats>,
+
+    /// Signal quality of the receptions over each link that carried any,
+    /// ordered by transmitter then receiver. Empty for outputs from before SNRs were logged.
+    pub link_snr: Vec<LinkSnr>,
+
     /// Time of the last [`LogItem`] in [`Self::sim_events`] in seconds.
     pub end_time: f64,
 
     pub reception_analysis: ReceptionAnalysis,
 
+    /// How many nodes each node can reach directly.
+    pub degree_stats: DegreeStats,
+
+    pub distance_latency: DistanceLatency,
+
     pub complete_identity: OutputIdentity,
+
+    /// Scenario the results are for.
+    pub scenario: Scenario,
 }
 
 impl CompleteAnalysis {
     pub fn new(results: SimOutput, scenario: Scenario) -> CompleteAnalysis {
-        let node_settings = scenario.get_settings();
-        let node_count = node_settings.len();
+        let node_count = scenario.settings.len();
+        let node_settings = if results.node_settings.len() == node_count {
+            results.node_settings
+        } else {
+            scenario.get_settings().into_iter().map(|x| x.into()).collect()
+        };
 
         let mut node_events = vec![Vec::new(); node_count];
         let mut sim_events = Vec::new();
@@ -56,7 +97,7 @@ impl CompleteAnalysis {
             }
         }
 
-        sim_events.sort_by(|x, y| f64::total_cmp(&x.time.seconds(), &y.time.seconds()));
+        sim_events.sort_by(|x, y| x.time.total_cmp(&y.time));
 
         let end_time = sim_events.last().map(|x| x.time.seconds()).unwrap_or(1.0);
 
@@ -71,7 +112,16 @@ impl CompleteAnalysis {
                 LogContent::TransmissionSent { .. } => (a + 1, b, c, d),
                 LogContent::TransmissionReceived { .. } => (a, b + 1, c, d),
                 LogContent::TransmissionBlocked { .. } => (a, b, c + 1, d),
-                LogContent::Text(_) => (a, b, c, d + 1),
+                LogContent::Text(_)
+                | LogContent::MessageDelivered { .. }
+                | LogContent::ContractViolation { .. }
+                | LogContent::TransmitDelay { .. }
+                | LogContent::SpreadingFactorChosen { .. }
+                | LogContent::RelayDropped { .. }
+                | LogContent::UtilisationGated { .. }
+                | LogContent::SettingChanged { .. }
+                | LogContent::NotificationSuppressed { .. }
+                | LogContent::NotificationStale { .. } => (a, b, c, d + 1),
             });
 
         assert_eq!(
@@ -83,21 +133,43 @@ impl CompleteAnalysis {
         );
 
         node_events.iter_mut().for_each(|list| {
-            list.sort_by(|x, y| f64::total_cmp(&x.time.seconds(), &y.time.seconds()))
+            list.sort_by(|x, y| x.time.total_cmp(&y.time))
         });
 
         let mut transmissions = results.transmissions;
         transmissions
-            .sort_by(|x, y| f64::total_cmp(&x.start_time.seconds(), &y.start_time.seconds()));
+            .sort_by(|x, y| x.start_time.total_cmp(&y.start_time));
 
         let total_airtime = transmissions
             .iter()
             .map(|x| x.airtime().seconds())
             .sum::<f64>();
 
+        let node_stats = if results.node_stats.len() == node_count {
+            results.node_stats
+        } else {
+            node_stats_from_logs(
+                &scenario,
+                &transmissions,
+                &sim_events,
+                &node_events,
+                node_count,
+            )
+        };
+
+        let transmissions_per_node = node_stats.iter().map(|x| x.transmissions as usize).collect();
+        let airtime_per_node = node_stats.iter().map(|x| x.tx_airtime).collect();
+        let blocked_per_node = node_stats.iter().map(|x| x.blocked as usize).collect();
+
+        let sf_breakdown = sf_breakdown(&scenario, &transmissions, &sim_events);
+        let link_snr = link_snr(&transmissions, &sim_events);
+
         let reception_analysis =
             ReceptionAnalysis::new(&scenario, &transmissions, &sim_events, node_count);
 
+        let degree_stats = DegreeStats::new(&scenario, end_time * SECONDS);
+        let distance_latency = DistanceLatency::new(&scenario, &reception_analysis);
+
         let complete_identity = results.complete_identity;
 
         CompleteAnalysis {
@@ -107,16 +179,592 @@ impl CompleteAnalysis {
             transmissions,
             end_time,
             reception_analysis,
+            degree_stats,
+            distance_latency,
             total_airtime,
+            transmissions_per_node,
+            airtime_per_node,
+            blocked_per_node,
+            node_stats,
+            sf_breakdown,
+            link_snr,
             complete_identity,
             transmission_sent_events,
             transmission_received_events,
             transmission_blocked_events,
+            scenario,
+        }
+    }
+
+    /// Fraction of each `bucket` long window, starting from time zero, that transmissions were
+    /// on air as (window start in seconds, fraction) pairs covering the whole run.
+    /// Overlapping transmissions are summed so the fraction can exceed one.
+    pub fn channel_utilisation(&self, bucket: Time) -> Vec<(f64, f64)> {
+        let bucket = bucket.seconds();
+        let bucket_count = (self.end_time / bucket).ceil().max(1.0) as usize;
+        let mut airtime = vec![0.0; bucket_count];
+
+        for transmission in self.transmissions.iter() {
+            let start = transmission.start_time.seconds();
+            let end = transmission.end_time.seconds();
+
+            let first = (start / bucket) as usize;
+            let last = ((end / bucket) as usize).min(bucket_count - 1);
+
+            for (i, x) in airtime.iter_mut().enumerate().take(last + 1).skip(first) {
+                let window_start = i as f64 * bucket;
+                let window_end = window_start + bucket;
+                *x += (end.min(window_end) - start.max(window_start)).max(0.0);
+            }
+        }
+
+        airtime
+            .into_iter()
+            .enumerate()
+            .map(|(i, x)| (i as f64 * bucket, x / bucket))
+            .collect()
+    }
+
+    /// Why `message_id` did or didn't reach `target_node`, see [`delivery_failure_report`].
+    pub fn delivery_failure_report(&self, message_id: usize, target_node: usize) -> FailureReport {
+        delivery_failure_report(
+            &self.scenario,
+            &self.transmissions,
+            &self.sim_events,
+            message_id,
+            target_node,
+        )
+    }
+
+    /// Fraction of the total airtime used by the highest spreading factor transmitted with,
+    /// across every bandwidth. Zero if nothing was transmitted.
+    pub fn top_sf_airtime_share(&self) -> f64 {
+        let Some(top) = self.sf_breakdown.iter().map(|x| x.sf).max() else {
+            return 0.0;
+        };
+
+        let airtime: f64 = self
+            .sf_breakdown
+            .iter()
+            .filter(|x| x.sf == top)
+            .map(|x| x.airtime)
+            .sum();
+
+        if self.total_airtime > 0.0 {
+            airtime / self.total_airtime
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Transmissions sent with one spreading factor and bandwidth.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SfBucketStats {
+    pub sf: i32,
+    pub bandwidth: Frequency,
+    pub transmissions: usize,
+    /// Total airtime in seconds.
+    pub airtime: f64,
+    /// Receptions blocked by interference where the transmission being received was in this bucket.
+    pub blocked: usize,
+    /// Mean packet size in bytes, including headers.
+    pub mean_size: f64,
+}
+
+fn sf_breakdown(
+    scenario: &Scenario,
+    transmissions: &[Transmission],
+    sim_events: &[LogItem],
+) -> Vec<SfBucketStats> {
+    let mut buckets: Vec<SfBucketStats> = Vec::new();
+    let mut bucket_of = HashMap::new();
+
+    for transmission in transmissions {
+        let index = buckets
+            .iter()
+            .position(|x| x.sf == transmission.sf && x.bandwidth == transmission.bandwidth)
+            .unwrap_or_else(|| {
+                buckets.push(SfBucketStats {
+                    sf: transmission.sf,
+                    bandwidth: transmission.bandwidth,
+                    transmissions: 0,
+                    airtime: 0.0,
+                    blocked: 0,
+                    mean_size: 0.0,
+                });
+                buckets.len() - 1
+            });
+
+        let bucket = &mut buckets[index];
+        bucket.transmissions += 1;
+        bucket.airtime += transmission.airtime().seconds();
+        // Summed here and divided once everything is counted
+        bucket.mean_size += packet_size(scenario, transmission) as f64;
+
+        bucket_of.insert(transmission.id, index);
+    }
+
+    for event in sim_events {
+        if let LogContent::TransmissionBlocked {
+            target_transmission_id,
+            ..
+        } = event.content
+            && let Some(&index) = bucket_of.get(&target_transmission_id)
+        {
+            buckets[index].blocked += 1;
+        }
+    }
+
+    for bucket in buckets.iter_mut() {
+        bucket.mean_size /= bucket.transmissions as f64;
+    }
+
+    buckets.sort_by(|a, b| a.sf.cmp(&b.sf).then(a.bandwidth.total_cmp(&b.bandwidth)));
+
+    buckets
+}
+
+/// Receptions from one node at another.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LinkSnr {
+    pub transmitter_id: usize,
+    pub receiver_id: usize,
+    /// Successful receptions with a logged SNR.
+    pub receptions: usize,
+    /// In dB.
+    pub mean_snr: f64,
+    /// In dB.
+    pub min_snr: f64,
+    /// Mean estimated RSSI in dBm.
+    pub mean_rssi: f64,
+}
+
+fn link_snr(transmissions: &[Transmission], sim_events: &[LogItem]) -> Vec<LinkSnr> {
+    let transmitter_of: HashMap<u32, usize> = transmissions
+        .iter()
+        .map(|x| (x.id, x.transmitter_id))
+        .collect();
+
+    let mut links: BTreeMap<(usize, usize), LinkSnr> = BTreeMap::new();
+
+    for event in sim_events {
+        let LogContent::TransmissionReceived {
+            receiver_id,
+            transmission_id,
+            snr: Some(snr),
+            rssi,
+        } = event.content
+        else {
+            continue;
+        };
+        let transmitter_id = transmitter_of[&transmission_id];
+
+        let link = links
+            .entry((transmitter_id, receiver_id))
+            .or_insert(LinkSnr {
+                transmitter_id,
+                receiver_id,
+                receptions: 0,
+                mean_snr: 0.0,
+                min_snr: f64::INFINITY,
+                mean_rssi: 0.0,
+            });
+        let snr = snr.as_db_float();
+        link.receptions += 1;
+        // Summed here and divided once everything is counted
+        link.mean_snr += snr;
+        link.min_snr = link.min_snr.min(snr);
+        link.mean_rssi += rssi.map_or(f64::NAN, |x| x.dbm());
+    }
+
+    links
+        .into_values()
+        .map(|mut link| {
+            link.mean_snr /= link.receptions as f64;
+            link.mean_rssi /= link.receptions as f64;
+            link
+        })
+        .collect()
+}
+
+/// Size of the packet sent, including the header.
+fn packet_size(scenario: &Scenario, transmission: &Transmission) -> u64 {
+    let content_size = match &transmission.message_content {
+        MessageContent::GeneratedMessage(id) => scenario.messages[*id].size,
+        MessageContent::NodeMessage(content) => content.size(),
+        MessageContent::Empty => 0,
+    };
+
+    (transmission.header.size() + content_size) as u64
+}
+
+/// Neighbour counts over the links that can be read with no fading or interference,
+/// see [`TransmissionModel::in_range`](crate::simulation::models::TransmissionModel::in_range).
+/// A node's neighbours are the nodes that can read its transmissions,
+/// sent with its scenario settings at full power.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DegreeStats {
+    /// Neighbours of each node at the start of the run. Index is node id.
+    pub degree_per_node: Vec<usize>,
+    pub mean_degree: f64,
+    pub min_degree: usize,
+    pub max_degree: usize,
+    /// Nodes that can't read any other node and that no other node can read.
+    pub isolated_nodes: usize,
+    /// Every node can reach every other node over readable links, hop by hop.
+    pub connected: bool,
+    /// Mean degree sampled over the run as (time in seconds, mean degree) pairs.
+    /// Empty unless the nodes move.
+    pub mean_degree_over_time: Vec<(f64, f64)>,
+    /// First sampled time each node could read, or be read by, any other node.
+    /// `None` if it never could. Index is node id.
+    pub first_contact: Vec<Option<Time>>,
+    /// Fraction of the sampled times each node had at least one neighbour either way,
+    /// which is the fraction of the run when the nodes move. Index is node id.
+    pub contact_ratio: Vec<f64>,
+}
+
+impl DegreeStats {
+    /// Number of evenly spaced times the mean degree is sampled at when nodes move.
+    const SAMPLES: usize = 20;
+
+    /// Number of evenly spaced times contact is sampled at when nodes move,
+    /// more than [`Self::SAMPLES`] as it's looking for when things change.
+    const CONTACT_SAMPLES: usize = 100;
+
+    pub fn new(scenario: &Scenario, end_time: Time) -> DegreeStats {
+        let links = readable_links(scenario, Time::from_seconds(0.0));
+        let node_count = links.len();
+
+        let degree_per_node: Vec<usize> = links.iter().map(|x| x.len()).collect();
+        let mean_degree = average_degree(&links);
+        let min_degree = degree_per_node.iter().copied().min().unwrap_or(0);
+        let max_degree = degree_per_node.iter().copied().max().unwrap_or(0);
+
+        let isolated_nodes = in_contact(&links).iter().filter(|&&x| !x).count();
+
+        let reversed = {
+            let mut reversed = vec![Vec::new(); node_count];
+            for (from, neighbours) in links.iter().enumerate() {
+                neighbours.iter().for_each(|&to| reversed[to].push(from));
+            }
+            reversed
+        };
+        let connected = node_count == 0
+            || (reaches_everyone(&links) && reaches_everyone(&reversed));
+
+        let moves = matches!(&scenario.map, NodeLocation::Points(points) if points.data.len() > 1);
+        let sample_times = |samples: usize| {
+            (0..samples).map(move |i| end_time * (i as f64 / (samples - 1) as f64))
+        };
+        let mean_degree_over_time = if moves {
+            sample_times(Self::SAMPLES)
+                .map(|at| (at.seconds(), average_degree(&readable_links(scenario, at))))
+                .collect()
+        } else {
+            Vec::new()
+        };
+
+        let contact_samples: Vec<_> = if moves {
+            sample_times(Self::CONTACT_SAMPLES)
+                .map(|at| (at, in_contact(&readable_links(scenario, at))))
+                .collect()
+        } else {
+            vec![(Time::from_seconds(0.0), in_contact(&links))]
+        };
+        let first_contact = (0..node_count)
+            .map(|id| contact_samples.iter().find(|x| x.1[id]).map(|x| x.0))
+            .collect();
+        let contact_ratio = (0..node_count)
+            .map(|id| {
+                let in_contact = contact_samples.iter().filter(|x| x.1[id]).count();
+                in_contact as f64 / contact_samples.len() as f64
+            })
+            .collect();
+
+        DegreeStats {
+            degree_per_node,
+            mean_degree,
+            min_degree,
+            max_degree,
+            isolated_nodes,
+            connected,
+            mean_degree_over_time,
+            first_contact,
+            contact_ratio,
+        }
+    }
+
+    /// Mean of [`Self::contact_ratio`] across the nodes, zero if there are none.
+    pub fn mean_contact_ratio(&self) -> f64 {
+        if self.contact_ratio.is_empty() {
+            return 0.0;
+        }
+
+        self.contact_ratio.iter().sum::<f64>() / self.contact_ratio.len() as f64
+    }
+}
+
+/// The nodes that can read each node's transmissions at `at`. Index is node id.
+fn readable_links(scenario: &Scenario, at: Time) -> Vec<Vec<usize>> {
+    (0..scenario.map.len())
+        .map(|from| {
+            let settings = &scenario.settings[from];
+            let probe = Transmission {
+                id: 0,
+                transmitter_id: from,
+                start_time: at,
+                end_time: at,
+                sf: settings.sf,
+                power: settings.max_power,
+                carrier_band: settings.carrier_band,
+                bandwidth: settings.bandwidth,
+                header: Header::Basic(BasicHeader::new(from, Destination::Broadcast, 0)),
+                message_content: MessageContent::Empty,
+            };
+
+            scenario
+                .map
+                .get_adj(from)
+                .filter(|&to| scenario.model.in_range(&scenario.map, to, &probe))
+                .collect()
+        })
+        .collect()
+}
+
+/// Whether each node can read or be read by any other node over `links`. Index is node id.
+fn in_contact(links: &[Vec<usize>]) -> Vec<bool> {
+    let mut in_contact: Vec<bool> = links.iter().map(|x| !x.is_empty()).collect();
+    links.iter().flatten().for_each(|&x| in_contact[x] = true);
+    in_contact
+}
+
+fn average_degree(links: &[Vec<usize>]) -> f64 {
+    if links.is_empty() {
+        return 0.0;
+    }
+
+    links.iter().map(|x| x.len()).sum::<usize>() as f64 / links.len() as f64
+}
+
+/// Whether every node can be reached from node 0 by following `links`.
+fn reaches_everyone(links: &[Vec<usize>]) -> bool {
+    let mut reached = vec![false; links.len()];
+    reached[0] = true;
+    let mut frontier = vec![0];
+
+    while let Some(node) = frontier.pop() {
+        for &next in &links[node] {
+            if !reached[next] {
+                reached[next] = true;
+                frontier.push(next);
+            }
         }
     }
+
+    reached.into_iter().all(|x| x)
+}
+
+/// How the latency of received wanted messages varies with how far the target was from the
+/// sender when the message was generated. Distances are in metres, or in hops over the graph's
+/// edges for graph scenarios, which have no positions.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DistanceLatency {
+    /// Distances are hop counts rather than metres
+    pub hops: bool,
+    /// (distance, latency in seconds) for each received wanted message,
+    /// ordered by message then target
+    pub pairs: Vec<(f64, f64)>,
+    /// Mean latency of the pairs in each distance bin, ordered by distance. Empty bins are left out
+    pub bins: Vec<DistanceBin>,
+}
+
+/// Received wanted messages whose targets were a similar distance from the sender.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DistanceBin {
+    /// Shortest distance in the bin
+    pub start: f64,
+    /// Longest distance in the bin, only included for the last bin
+    pub end: f64,
+    pub messages: usize,
+    /// Seconds
+    pub mean_latency: f64,
+}
+
+impl DistanceLatency {
+    /// Number of equal width bins positions are split into. Hops get a bin for each count.
+    const BINS: usize = 10;
+
+    pub fn new(scenario: &Scenario, reception: &ReceptionAnalysis) -> DistanceLatency {
+        let hops = matches!(scenario.map, NodeLocation::Graph(_));
+        let hop_counts: Vec<Vec<Option<usize>>> = if hops {
+            (0..scenario.map.len())
+                .map(|from| hop_counts(&scenario.map, from))
+                .collect()
+        } else {
+            Vec::new()
+        };
+
+        let mut received: Vec<(usize, usize, Time)> = reception
+            .wanted_messages
+            .iter()
+            .enumerate()
+            .flat_map(|(target, messages)| {
+                messages
+                    .iter()
+                    .filter_map(move |x| Some((x.message_id, target, x.latency?)))
+            })
+            .collect();
+        received.sort_by_key(|&(message_id, target, _)| (message_id, target));
+
+        let pairs: Vec<(f64, f64)> = received
+            .into_iter()
+            .filter_map(|(message_id, target, latency)| {
+                let message = &scenario.messages[message_id];
+                let distance = if hops {
+                    hop_counts[message.sender][target]? as f64
+                } else {
+                    let at = message.generate_time;
+                    let sender = scenario.map.location(at, message.sender)?;
+                    let target = scenario.map.location(at, target)?;
+                    (target - sender).mag().metres()
+                };
+                Some((distance, latency.seconds()))
+            })
+            .collect();
+
+        let bins = if hops {
+            let max = pairs.iter().map(|x| x.0 as usize).max().unwrap_or(0);
+            bin_pairs(&pairs, max + 1, |distance| distance as usize, |i| (i as f64, i as f64 + 1.0))
+        } else {
+            let max = pairs.iter().map(|x| x.0).fold(0.0, f64::max);
+            let width = max / Self::BINS as f64;
+            let index = |distance: f64| {
+                if width > 0.0 {
+                    ((distance / width) as usize).min(Self::BINS - 1)
+                } else {
+                    0
+                }
+            };
+            let range = |i: usize| (i as f64 * width, (i + 1) as f64 * width);
+            bin_pairs(&pairs, Self::BINS, index, range)
+        };
+
+        DistanceLatency { hops, pairs, bins }
+    }
+}
+
+/// Mean latency of the pairs put in each of `count` bins by `index`, leaving out empty bins.
+fn bin_pairs(
+    pairs: &[(f64, f64)],
+    count: usize,
+    index: impl Fn(f64) -> usize,
+    range: impl Fn(usize) -> (f64, f64),
+) -> Vec<DistanceBin> {
+    let mut sums = vec![(0, 0.0); count];
+    for &(distance, latency) in pairs {
+        let bin = &mut sums[index(distance)];
+        bin.0 += 1;
+        bin.1 += latency;
+    }
+
+    sums.into_iter()
+        .enumerate()
+        .filter(|(_, (messages, _))| *messages > 0)
+        .map(|(i, (messages, total))| {
+            let (start, end) = range(i);
+            DistanceBin {
+                start,
+                end,
+                messages,
+                mean_latency: total / messages as f64,
+            }
+        })
+        .collect()
+}
+
+/// Fewest edges from `from` to each node, `None` for nodes it can't reach.
+fn hop_counts(map: &NodeLocation, from: usize) -> Vec<Option<usize>> {
+    let mut hops = vec![None; map.len()];
+    hops[from] = Some(0);
+    let mut frontier = VecDeque::from([from]);
+
+    while let Some(node) = frontier.pop_front() {
+        let next_hops = hops[node].map(|x| x + 1);
+        for next in map.get_adj(node) {
+            if hops[next].is_none() {
+                hops[next] = next_hops;
+                frontier.push_back(next);
+            }
+        }
+    }
+
+    hops
 }
 
 /// Collection of graphs by transmission id.
+/// Works out what it can of each node's [`NodeStats`] for output files that don't have them.
+/// Too weak receptions aren't logged so are always zero,
+/// and relay drops are only there if node logs were on.
+pub fn node_stats_from_logs(
+    scenario: &Scenario,
+    transmissions: &[Transmission],
+    sim_events: &[LogItem],
+    node_events: &[Vec<LogItem>],
+    node_count: usize,
+) -> Vec<NodeStats> {
+    let packet_size = |transmission: &Transmission| packet_size(scenario, transmission);
+
+    let mut stats = vec![NodeStats::default(); node_count];
+    let mut by_id = HashMap::new();
+
+    for transmission in transmissions {
+        let sender = &mut stats[transmission.transmitter_id];
+        sender.transmissions += 1;
+        sender.tx_airtime += transmission.airtime().seconds();
+        sender.bytes_sent += packet_size(transmission);
+
+        by_id.insert(transmission.id, transmission);
+    }
+
+    for event in sim_events {
+        match event.content {
+            LogContent::TransmissionReceived {
+                receiver_id,
+                transmission_id,
+                ..
+            } => {
+                let receiver = &mut stats[receiver_id];
+                receiver.receptions += 1;
+                receiver.bytes_received += packet_size(by_id[&transmission_id]);
+            }
+            LogContent::TransmissionBlocked { receiver_id, .. } => {
+                stats[receiver_id].blocked += 1;
+            }
+            LogContent::TransmissionSent { .. }
+            | LogContent::Text(_)
+            | LogContent::MessageDelivered { .. }
+            | LogContent::ContractViolation { .. }
+            | LogContent::TransmitDelay { .. }
+            | LogContent::SpreadingFactorChosen { .. }
+            | LogContent::RelayDropped { .. }
+            | LogContent::UtilisationGated { .. }
+            | LogContent::SettingChanged { .. }
+            | LogContent::NotificationSuppressed { .. }
+            | LogContent::NotificationStale { .. } => {}
+        }
+    }
+
+    for event in node_events.iter().flatten() {
+        if let LogContent::RelayDropped { node_id, .. } = event.content {
+            stats[node_id].relay_drops += 1;
+        }
+    }
+
+    stats
+}
+
 /// Each graph represents the sending node connected to each node that successfully recieved the transmission.
 pub fn create_transmission_graphs(sim_events: Vec<LogItem>) -> HashMap<u32, TransmissionGraph> {
     let mut transmission_graphs = HashMap::new();
@@ -142,6 +790,7 @@ pub fn create_transmission_graphs(sim_events: Vec<LogItem>) -> HashMap<u32, Tran
         if let LogContent::TransmissionReceived {
             receiver_id,
             transmission_id,
+            ..
         } = event_prime.content
         {
             transmission_graphs
@@ -155,11 +804,303 @@ pub fn create_transmission_graphs(sim_events: Vec<LogItem>) -> HashMap<u32, Tran
     transmission_graphs
 }
 
+/// A transmission that carried a message to at least one node that did not have it yet.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MessageHop {
+    pub transmission_id: u32,
+    pub transmitter_id: usize,
+    pub start_time: Time,
+    pub end_time: Time,
+    /// Nodes that received the message for the first time from this transmission
+    pub receivers: Vec<usize>,
+}
+
+/// Transmissions that spread a generated message through the network, in the order they first
+/// delivered it. Transmissions only received by nodes that already had the message are left out.
+pub fn trace_message(
+    transmissions: &[Transmission],
+    sim_events: &[LogItem],
+    message_id: usize,
+) -> Vec<MessageHop> {
+    let by_id: HashMap<u32, &Transmission> = transmissions.iter().map(|x| (x.id, x)).collect();
+
+    let mut has_message = HashSet::new();
+    let mut hops: Vec<MessageHop> = Vec::new();
+
+    for event in sim_events.iter() {
+        let LogContent::TransmissionReceived {
+            receiver_id,
+            transmission_id,
+            ..
+        } = event.content
+        else {
+            continue;
+        };
+
+        let transmission = by_id[&transmission_id];
+
+        let MessageContent::GeneratedMessage(id) = transmission.message_content else {
+            continue;
+        };
+        if id != message_id {
+            continue;
+        }
+
+        // Anything that sent the message must have had it, including the source
+        has_message.insert(transmission.transmitter_id);
+
+        if !has_message.insert(receiver_id) {
+            continue;
+        }
+
+        match hops
+            .iter_mut()
+            .find(|x| x.transmission_id == transmission_id)
+        {
+            Some(hop) => hop.receivers.push(receiver_id),
+            None => hops.push(MessageHop {
+                transmission_id,
+                transmitter_id: transmission.transmitter_id,
+                start_time: transmission.start_time,
+                end_time: transmission.end_time,
+                receivers: vec![receiver_id],
+            }),
+        }
+    }
+
+    hops
+}
+
+/// What happened to a message on its way to a node, from [`delivery_failure_report`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct FailureReport {
+    pub message_id: usize,
+    pub target_node: usize,
+    /// Nodes that had the message, the sender first then in the order they received it
+    pub reached: Vec<usize>,
+    /// Nodes that transmitted the message, in the order they first did
+    pub relayed_by: Vec<usize>,
+    /// Transmissions of the message strong enough to be read at the target
+    /// without random fading or interference
+    pub in_range: Vec<u32>,
+    /// Transmissions of the message that were blocked at the target
+    pub blocked_at_target: Vec<u32>,
+    /// Transmissions of the message in range of the target that it neither received
+    /// nor was blocked from receiving, so were too weak once faded
+    pub too_weak_at_target: Vec<u32>,
+    pub cause: FailureCause,
+}
+
+/// Most likely reason a message didn't reach a node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FailureCause {
+    /// It did reach the node
+    Delivered,
+    /// Transmissions of the message were blocked at the node, this many times
+    BlockedAtTarget(usize),
+    /// This many transmissions of the message were in range of the node but too weak to read
+    TooWeakAtTarget(usize),
+    /// The message stopped spreading at this node, which had it but never transmitted it
+    NeverRelayedPast(usize),
+    /// Every node that had the message transmitted it but none were in range of the node
+    OutOfCoverage,
+}
+
+impl std::fmt::Display for FailureCause {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FailureCause::Delivered => write!(f, "Delivered"),
+            FailureCause::BlockedAtTarget(n) => write!(f, "Blocked {n} times at target"),
+            FailureCause::TooWeakAtTarget(n) => write!(f, "Too weak {n} times at target"),
+            FailureCause::NeverRelayedPast(node) => write!(f, "Never relayed past node {node}"),
+            FailureCause::OutOfCoverage => write!(f, "Target out of coverage"),
+        }
+    }
+}
+
+/// Walks the transmissions of a generated message to find why it did or didn't reach
+/// `target_node`. Whether a transmission was in range of the target is decided by the
+/// scenario's map and transmission model, ignoring random fading.
+///
+/// Causes at the target are preferred over ones further back, so a message that was blocked
+/// at the target is reported as blocked even if it was also too weak there.
+/// When nothing was in range the node the message stopped at is the first that had it,
+/// didn't pass it on and could have reached the target, or failing that the last to get it.
+pub fn delivery_failure_report(
+    scenario: &Scenario,
+    transmissions: &[Transmission],
+    sim_events: &[LogItem],
+    message_id: usize,
+    target_node: usize,
+) -> FailureReport {
+    let sender = scenario.messages[message_id].sender;
+    let carried: Vec<&Transmission> = transmissions
+        .iter()
+        .filter(|x| match x.message_content {
+            MessageContent::GeneratedMessage(id) => id == message_id,
+            _ => false,
+        })
+        .collect();
+    let carried_ids: HashSet<u32> = carried.iter().map(|x| x.id).collect();
+
+    let mut reached = vec![sender];
+    let mut has_message = HashSet::from([sender]);
+    let mut received_at_target = HashSet::new();
+    let mut blocked_at_target = Vec::new();
+
+    for event in sim_events.iter() {
+        match event.content {
+            LogContent::TransmissionReceived {
+                receiver_id,
+                transmission_id,
+                ..
+            } if carried_ids.contains(&transmission_id) => {
+                if has_message.insert(receiver_id) {
+                    reached.push(receiver_id);
+                }
+                if receiver_id == target_node {
+                    received_at_target.insert(transmission_id);
+                }
+            }
+            LogContent::TransmissionBlocked {
+                receiver_id,
+                target_transmission_id,
+                ..
+            } if receiver_id == target_node && carried_ids.contains(&target_transmission_id) => {
+                blocked_at_target.push(target_transmission_id)
+            }
+            _ => (),
+        }
+    }
+
+    let mut relayed_by = Vec::new();
+    for transmission in carried.iter() {
+        if !relayed_by.contains(&transmission.transmitter_id) {
+            relayed_by.push(transmission.transmitter_id);
+        }
+    }
+
+    let in_range: Vec<u32> = carried
+        .iter()
+        .filter(|x| x.transmitter_id != target_node)
+        .filter(|x| scenario.model.in_range(&scenario.map, target_node, x))
+        .map(|x| x.id)
+        .collect();
+
+    let too_weak_at_target: Vec<u32> = in_range
+        .iter()
+        .copied()
+        .filter(|x| !received_at_target.contains(x) && !blocked_at_target.contains(x))
+        .collect();
+
+    // Whether the node could have reached the target had it sent the message like the others did
+    let could_reach = |node: usize| {
+        carried.first().is_some_and(|x| {
+            let mut sent_by_node = Transmission::clone(x);
+            sent_by_node.transmitter_id = node;
+            scenario
+                .model
+                .in_range(&scenario.map, target_node, &sent_by_node)
+        })
+    };
+    let mut stopped = reached.iter().copied().filter(|x| !relayed_by.contains(x));
+    let stopped_at = stopped
+        .clone()
+        .find(|x| could_reach(*x))
+        .or_else(|| stopped.next_back());
+
+    let cause = if target_node == sender || !received_at_target.is_empty() {
+        FailureCause::Delivered
+    } else if !blocked_at_target.is_empty() {
+        FailureCause::BlockedAtTarget(blocked_at_target.len())
+    } else if !too_weak_at_target.is_empty() {
+        FailureCause::TooWeakAtTarget(too_weak_at_target.len())
+    } else if let Some(node) = stopped_at {
+        FailureCause::NeverRelayedPast(node)
+    } else {
+        FailureCause::OutOfCoverage
+    };
+
+    FailureReport {
+        message_id,
+        target_node,
+        reached,
+        relayed_by,
+        in_range,
+        blocked_at_target,
+        too_weak_at_target,
+        cause,
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct WantedMessage {
     pub message_id: usize,
     pub was_received: bool,
+    /// From the generation that caused the message to arrive, which for repeated messages is
+    /// taken to be the latest generation before the transmission that brought it started.
     pub latency: Option<Time>,
+    /// From the message's first generation, which was the only latency before repeats were
+    /// told apart. Kept for comparing with older results.
+    pub latency_from_first_generation: Option<Time>,
+    /// If the message had a [`ScenarioMessage::deadline`](crate::scenario::ScenarioMessage),
+    /// whether it was received within it. Deadlines count from the first generation.
+    pub deadline_met: Option<bool>,
+}
+
+/// How long a message took to arrive somewhere, see [`WantedMessage::latency`].
+#[derive(Debug, Clone, Copy)]
+struct ArrivalLatency {
+    from_first_generation: Time,
+    from_causing_generation: Time,
+}
+
+impl ArrivalLatency {
+    fn new(message: &ScenarioMessage, transmission: &Transmission, arrival: Time) -> Self {
+        ArrivalLatency {
+            from_first_generation: arrival - message.generate_time,
+            from_causing_generation: arrival - message.generation_before(transmission.start_time),
+        }
+    }
+
+    /// For messages out of the mesh as soon as they were generated
+    fn zero() -> Self {
+        ArrivalLatency {
+            from_first_generation: 0.0 * SECONDS,
+            from_causing_generation: 0.0 * SECONDS,
+        }
+    }
+
+    /// Whichever arrived first
+    fn earliest(self, other: Self) -> Self {
+        match other.from_first_generation < self.from_first_generation {
+            true => other,
+            false => self,
+        }
+    }
+}
+
+/// The gateway that heard a message before any other, which is where it left the mesh.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GatewayCapture {
+    pub gateway_id: usize,
+    /// End of the transmission the gateway heard it in.
+    pub time: Time,
+    pub transmission_id: u32,
+}
+
+/// How much of getting messages out of the mesh fell to one gateway.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GatewayStats {
+    pub gateway_id: usize,
+    /// Messages this gateway heard before any other gateway did.
+    pub captured_messages: usize,
+    /// Fraction of all the messages captured by a gateway that this one captured.
+    pub message_share: f64,
+    /// Airtime of the transmissions this gateway captured its messages from.
+    pub airtime: Time,
+    /// Fraction of the capturing airtime over every gateway that was this one's.
+    pub airtime_share: f64,
 }
 
 pub struct ReceptionAnalysis {
@@ -175,13 +1116,19 @@ pub struct ReceptionAnalysis {
 
     /// Average time between generation and reception over all received wanted message
     /// at each node. Messages that are never received do not effect this value.
+    /// Like every latency here it counts from the generation that caused the reception,
+    /// see [`WantedMessage::latency`].
     pub avg_latency_per_node: Vec<Time>,
 
+    /// NaN like the other aggregates over nodes when there are no nodes.
     pub avg_avg_latency: Time,
     pub min_avg_latency: Time,
     pub max_avg_latency: Time,
 
     pub global_latency: Time,
+    /// [`Self::global_latency`] counted from each message's first generation,
+    /// which it was before repeats were told apart.
+    pub global_latency_from_first_generation: Time,
 
     pub l120_score: Time,
     pub l600_score: Time,
@@ -234,6 +1181,25 @@ pub struct ReceptionAnalysis {
 
     pub gateway_reception: f64,
     pub gateway_latency: Time,
+
+    /// Proportion of messages generated away from a gateway that any gateway heard,
+    /// which is when they would leave the mesh with [`Scenario::gateway_uplink`].
+    pub uplink_reception_rate: f64,
+    /// Mean time from generation until a gateway first heard the message,
+    /// of the messages counted in `uplink_reception_rate` that were heard.
+    pub uplink_latency: Time,
+
+    /// Proportion of wanted messages with a deadline that were received within it.
+    /// `None` if no messages had deadlines.
+    pub deadline_hit_rate: Option<f64>,
+
+    /// First gateway to receive each message, `None` if no gateway received it.
+    /// Messages generated at a gateway only have one if another gateway heard them.
+    /// Index is message id.
+    pub first_gateway: Vec<Option<GatewayCapture>>,
+
+    /// Messages captured by each gateway in the scenario, in id order.
+    pub gateway_breakdown: Vec<GatewayStats>,
 }
 
 impl ReceptionAnalysis {
@@ -246,9 +1212,13 @@ impl ReceptionAnalysis {
         let mut wanted_messages = vec![Vec::new(); node_count];
         let mut received_messages = vec![HashSet::new(); node_count];
 
-        let mut latency_per_node: Vec<HashMap<usize, Time>> = vec![HashMap::new(); node_count];
+        let mut latency_per_node: Vec<HashMap<usize, ArrivalLatency>> =
+            vec![HashMap::new(); node_count];
         let mut foobar_per_node: Vec<HashMap<usize, u32>> = vec![HashMap::new(); node_count];
 
+        let is_gateway = |id: usize| scenario.settings[id].is_gateway;
+        let mut first_gateway: Vec<Option<GatewayCapture>> = vec![None; scenario.messages.len()];
+
         let maybe_max_id = transmissions.iter().map(|x| x.id).max();
 
         let id_to_index = if let Some(max_id) = maybe_max_id {
@@ -266,6 +1236,7 @@ impl ReceptionAnalysis {
             let LogContent::TransmissionReceived {
                 receiver_id,
                 transmission_id,
+                ..
             } = event.content
             else {
                 continue;
@@ -276,10 +1247,12 @@ impl ReceptionAnalysis {
             if let MessageContent::GeneratedMessage(id) = transmission.message_content {
                 received_messages[receiver_id].insert(id);
                 let prev = latency_per_node[receiver_id].get(&id);
-                let this_latency = transmission.end_time - scenario.messages[id].generate_time;
+                let message = &scenario.messages[id];
+                let this_latency =
+                    ArrivalLatency::new(message, transmission, transmission.end_time);
 
                 if let Some(&latency) = prev {
-                    if this_latency < latency {
+                    if this_latency.from_first_generation < latency.from_first_generation {
                         latency_per_node[receiver_id].insert(id, this_latency);
                         foobar_per_node[receiver_id].insert(id, transmission_id);
                     }
@@ -287,15 +1260,88 @@ impl ReceptionAnalysis {
                     latency_per_node[receiver_id].insert(id, this_latency);
                     foobar_per_node[receiver_id].insert(id, transmission_id);
                 }
+
+                if is_gateway(receiver_id) {
+                    let capture = GatewayCapture {
+                        gateway_id: receiver_id,
+                        time: transmission.end_time,
+                        transmission_id,
+                    };
+                    // Ties go to the lowest gateway id so the result doesn't depend on log order
+                    let first = &mut first_gateway[id];
+                    if first.is_none_or(|x| (capture.time, receiver_id) < (x.time, x.gateway_id)) {
+                        *first = Some(capture);
+                    }
+                }
             }
         }
 
+        // Outputs from before deliveries were logged have them worked out from the receptions,
+        // which gives the same first arrivals at the targets
+        let has_deliveries = sim_events
+            .iter()
+            .any(|x| matches!(x.content, LogContent::MessageDelivered { .. }));
+        let delivery_latency = if has_deliveries {
+            let mut delivery_latency: Vec<HashMap<usize, ArrivalLatency>> =
+                vec![HashMap::new(); node_count];
+            for event in sim_events.iter() {
+                if let LogContent::MessageDelivered {
+                    message_id,
+                    receiver_id,
+                    transmission_id,
+                } = event.content
+                {
+                    let transmission = &transmissions[id_to_index[transmission_id as usize]];
+                    let message = &scenario.messages[message_id];
+                    let latency = ArrivalLatency::new(message, transmission, event.time);
+                    delivery_latency[receiver_id].insert(message_id, latency);
+                }
+            }
+            delivery_latency
+        } else {
+            latency_per_node.clone()
+        };
+
+        // Gateway uplink, when each message was first heard by any gateway.
+        // Ones generated at a gateway are out of the mesh straight away
+        let uplinked: Vec<Option<ArrivalLatency>> = scenario
+            .messages
+            .iter()
+            .enumerate()
+            .map(|(id, message)| {
+                if is_gateway(message.sender) {
+                    return Some(ArrivalLatency::zero());
+                }
+
+                latency_per_node
+                    .iter()
+                    .enumerate()
+                    .filter(|(node, _)| is_gateway(*node))
+                    .filter_map(|(_, latencies)| latencies.get(&id).copied())
+                    .reduce(ArrivalLatency::earliest)
+            })
+            .collect();
+
         for (i, message) in scenario.messages.iter().enumerate() {
             message.targets.iter().for_each(|&x| {
+                let mut latency = delivery_latency[x].get(&i).copied();
+                if scenario.gateway_uplink
+                    && let Some(uplink) = uplinked[i]
+                {
+                    latency = Some(latency.map_or(uplink, |x| x.earliest(uplink)));
+                }
+                let from_first = latency.map(|x| x.from_first_generation);
+
+                let deadline_met = message
+                    .deadline
+                    .map(|deadline| from_first.is_some_and(|x| x <= deadline));
+
                 wanted_messages[x].push(WantedMessage {
                     message_id: i,
-                    was_received: received_messages[x].contains(&i),
-                    latency: latency_per_node[x].get(&i).copied(),
+                    was_received: latency.is_some(),
+                    latency: latency.map(|x| x.from_causing_generation),
+                    latency_from_first_generation: from_first,
+                    deadline_met,
                 });
             });
         }
@@ -348,6 +1394,7 @@ impl ReceptionAnalysis {
                 LogContent::TransmissionReceived {
                     receiver_id,
                     transmission_id,
+                    ..
                 } => {
                     let transmission = &transmissions[id_to_index[transmission_id as usize]];
                     match transmission.message_content {
@@ -384,6 +1431,7 @@ impl ReceptionAnalysis {
             let LogContent::TransmissionReceived {
                 receiver_id,
                 transmission_id,
+                ..
             } = event.content
             else {
                 continue;
@@ -430,13 +1478,14 @@ impl ReceptionAnalysis {
             .iter()
             .filter(|x| x.markers.contains(&MessageMarker::Emergency))
             .map(|x| x.generate_time)
-            .min_by(|x, y| x.partial_cmp(&y).unwrap());
+            .min_by(Time::total_cmp);
 
         let emergency_result = if let Some(emergency_start) = maybe_emergency_start {
             let gateway_emer_recptions = sim_events.iter().filter_map(|event| {
                 let LogContent::TransmissionReceived {
                     receiver_id,
                     transmission_id,
+                    ..
                 } = event.content
                 else {
                     return None;
@@ -463,8 +1512,7 @@ impl ReceptionAnalysis {
                 return Some(transmission.end_time);
             });
 
-            let maybe_arrival_time =
-                gateway_emer_recptions.min_by(|x, y| x.partial_cmp(&y).unwrap());
+            let maybe_arrival_time = gateway_emer_recptions.min_by(Time::total_cmp);
 
             match maybe_arrival_time {
                 Some(arrival_time) => EmergencyResult::Latency(arrival_time - emergency_start),
@@ -476,16 +1524,19 @@ impl ReceptionAnalysis {
 
         // global reception and latency
 
-        let global_latency = {
+        let mean_latency = |latency: fn(&WantedMessage) -> Option<Time>| {
             let (agg, total) = wanted_messages
                 .iter()
-                .flat_map(|messages| messages.iter().filter_map(|x| x.latency))
+                .flat_map(|messages| messages.iter().filter_map(latency))
                 .fold((0.0 * SECONDS, 0), |(agg, total), val| {
                     (agg + val, total + 1)
                 });
 
             agg / (total as f64).max(1.0)
         };
+        let global_latency = mean_latency(|x| x.latency);
+        let global_latency_from_first_generation =
+            mean_latency(|x| x.latency_from_first_generation);
 
         let global_reception_rate = {
             let total: usize = wanted_messages.iter().map(|x| x.len()).sum();
@@ -497,6 +1548,73 @@ impl ReceptionAnalysis {
             (agg as f64) / (total as f64).max(1.0)
         };
 
+        // Uplink reception and latency
+
+        let (uplink_reception_rate, uplink_latency) = {
+            let counted: Vec<Option<Time>> = scenario
+                .messages
+                .iter()
+                .zip(&uplinked)
+                .filter(|(message, _)| !is_gateway(message.sender))
+                .map(|(_, uplink)| uplink.map(|x| x.from_causing_generation))
+                .collect();
+            let heard: Vec<Time> = counted.iter().flatten().copied().collect();
+
+            (
+                heard.len() as f64 / (counted.len() as f64).max(1.0),
+                heard.iter().copied().sum::<Time>() / (heard.len() as f64).max(1.0),
+            )
+        };
+
+        // Deadline hits
+
+        let deadline_hit_rate = {
+            let (hits, total) = wanted_messages
+                .iter()
+                .flatten()
+                .filter_map(|x| x.deadline_met)
+                .fold((0, 0), |(hits, total), met| (hits + met as u32, total + 1));
+
+            (total > 0).then(|| hits as f64 / total as f64)
+        };
+
+        // Gateway breakdown
+
+        let gateway_breakdown = {
+            let mut breakdown: Vec<GatewayStats> = (0..node_count)
+                .filter(|&id| is_gateway(id))
+                .map(|gateway_id| GatewayStats {
+                    gateway_id,
+                    captured_messages: 0,
+                    message_share: 0.0,
+                    airtime: 0.0 * SECONDS,
+                    airtime_share: 0.0,
+                })
+                .collect();
+
+            for capture in first_gateway.iter().flatten() {
+                let transmission = &transmissions[id_to_index[capture.transmission_id as usize]];
+                let stats = breakdown
+                    .iter_mut()
+                    .find(|x| x.gateway_id == capture.gateway_id)
+                    .unwrap();
+                stats.captured_messages += 1;
+                stats.airtime = stats.airtime + transmission.airtime();
+            }
+
+            let total_messages = breakdown.iter().map(|x| x.captured_messages).sum::<usize>();
+            let total_airtime = breakdown.iter().map(|x| x.airtime.seconds()).sum::<f64>();
+            for stats in breakdown.iter_mut() {
+                stats.message_share =
+                    stats.captured_messages as f64 / (total_messages as f64).max(1.0);
+                if total_airtime > 0.0 {
+                    stats.airtime_share = stats.airtime.seconds() / total_airtime;
+                }
+            }
+
+            breakdown
+        };
+
         // Gateway reception and latency
 
         let gateway_latency = {
@@ -551,17 +1669,19 @@ impl ReceptionAnalysis {
         let avg_avg_latency =
             avg_latency_per_node.iter().copied().sum::<Time>() / (node_count as f64);
 
+        // NaN with no nodes, like the averages
+        let no_nodes = Time::from_seconds(f64::NAN);
         let min_avg_latency = avg_latency_per_node
             .iter()
             .copied()
-            .min_by(|a, b| a.partial_cmp(b).unwrap())
-            .unwrap();
+            .min_by(Time::total_cmp)
+            .unwrap_or(no_nodes);
 
         let max_avg_latency = avg_latency_per_node
             .iter()
             .copied()
-            .max_by(|a, b| a.partial_cmp(b).unwrap())
-            .unwrap();
+            .max_by(Time::total_cmp)
+            .unwrap_or(no_nodes);
 
         let mut reception_rate = vec![0.0; node_count];
 
@@ -594,13 +1714,13 @@ impl ReceptionAnalysis {
         let min_reception_rate = reception_rate
             .iter()
             .copied()
-            .min_by(|a, b| a.partial_cmp(b).unwrap())
-            .unwrap();
+            .min_by(f64::total_cmp)
+            .unwrap_or(f64::NAN);
         let max_reception_rate = reception_rate
             .iter()
             .copied()
-            .max_by(|a, b| a.partial_cmp(b).unwrap())
-            .unwrap();
+            .max_by(f64::total_cmp)
+            .unwrap_or(f64::NAN);
 
         ReceptionAnalysis {
             wanted_messages,
@@ -629,6 +1749,7 @@ impl ReceptionAnalysis {
             transmission_unique_directness,
             emergency_result,
             global_latency,
+            global_latency_from_first_generation,
             t120_reception,
             t600_reception,
             t1800_reception,
@@ -636,8 +1757,55 @@ impl ReceptionAnalysis {
             global_reception_rate,
             gateway_reception,
             gateway_latency,
+            uplink_reception_rate,
+            uplink_latency,
+            deadline_hit_rate,
+            first_gateway,
+            gateway_breakdown,
         }
     }
+
+    /// Largest fraction of the gateway-captured messages that one gateway captured,
+    /// 0 if no gateway captured any.
+    pub fn busiest_gateway_share(&self) -> f64 {
+        self.gateway_breakdown
+            .iter()
+            .map(|x| x.message_share)
+            .fold(0.0, f64::max)
+    }
+
+    /// Latencies of every received wanted message in ascending order.
+    pub fn sorted_latencies(&self) -> Vec<Time> {
+        let mut latencies: Vec<Time> = self
+            .wanted_messages
+            .iter()
+            .flatten()
+            .filter_map(|x| x.latency)
+            .collect();
+        latencies.sort_by(Time::total_cmp);
+        latencies
+    }
+
+    /// Empirical CDF of the latency of received wanted messages as
+    /// (latency in seconds, fraction of received messages at or below it) pairs.
+    pub fn latency_cdf(&self) -> Vec<(f64, f64)> {
+        let latencies = self.sorted_latencies();
+        let count = latencies.len() as f64;
+
+        latencies
+            .into_iter()
+            .enumerate()
+            .map(|(i, x)| (x.seconds(), (i + 1) as f64 / count))
+            .collect()
+    }
+
+    /// Nearest rank percentile of the latency of received wanted messages, `p` is from 0 to 1.
+    /// None if no wanted messages were received.
+    pub fn latency_percentile(&self, p: f64) -> Option<Time> {
+        let latencies = self.sorted_latencies();
+        let rank = (p.clamp(0.0, 1.0) * latencies.len() as f64).ceil() as usize;
+        latencies.get(rank.max(1) - 1).copied()
+    }
 }
 
 fn latency_score(wanted_messages: &Vec<Vec<WantedMessage>>, penalty_time: Time) -> Time {
@@ -688,3 +1856,698 @@ pub struct TransmissionGraph {
     pub origin: usize,
     pub targets: Vec<usize>,
 }
+
+/// Mean and sample standard deviation of a metric over repeated runs.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct RunStatistic {
+    pub mean: f64,
+    pub std: f64,
+    pub runs: usize,
+}
+
+impl RunStatistic {
+    /// Standard deviation is zero for fewer than two values.
+    pub fn new(values: &[f64]) -> RunStatistic {
+        let runs = values.len();
+        let mean = values.iter().sum::<f64>() / runs as f64;
+
+        let std = if runs < 2 {
+            0.0
+        } else {
+            let square_diff: f64 = values.iter().map(|x| (x - mean).powi(2)).sum();
+            (square_diff / (runs - 1) as f64).sqrt()
+        };
+
+        RunStatistic { mean, std, runs }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{assert_close, scenario::ScenarioNodeSettings};
+
+    #[test]
+    fn run_statistic() {
+        let stat = RunStatistic::new(&[2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0]);
+        assert_close(stat.mean, 5.0);
+        assert_close(stat.std, 2.138089935);
+        assert_eq!(stat.runs, 8);
+
+        let single = RunStatistic::new(&[3.0]);
+        assert_close(single.mean, 3.0);
+        assert_close(single.std, 0.0);
+    }
+
+    fn flood_output() -> (SimOutput, Scenario) {
+        use crate::{
+            node::ModelSelection,
+            scenario::ScenarioIdentity,
+            simulation::run_simulation,
+            units::MINS,
+        };
+
+        let scenario = ScenarioIdentity::for_test(12, 20, 1.0 * MINS, 5).create();
+
+        let output = run_simulation(
+            2,
+            scenario.clone(),
+            ModelSelection::BasicFlood.into(),
+            false,
+        );
+        (output, scenario)
+    }
+
+    /// Node 0 sending node 1 a message at each (sf, bandwidth in kHz, airtime in seconds, size),
+    /// with the first transmission blocked once and the second twice.
+    fn mixed_sf_output() -> (SimOutput, Scenario) {
+        use crate::{
+            node::{BasicHeader, Destination, Header},
+            scenario::{ScenarioIdentity, ScenarioMessage},
+            simulation::data_structs::{CarrierBand, LogLevel},
+            units::Dbm,
+        };
+
+        let sent = [
+            (7, 125.0, 0.1, 20),
+            (12, 125.0, 2.0, 40),
+            (7, 125.0, 0.3, 40),
+            (12, 250.0, 1.0, 10),
+            (9, 125.0, 0.5, 30),
+        ];
+
+        let scenario = Scenario {
+            messages: sent
+                .iter()
+                .enumerate()
+                .map(|(i, x)| ScenarioMessage::new(0, vec![1], i as f64 * 10.0 * SECONDS, x.3))
+                .collect(),
+            ..Scenario::test_pair()
+        };
+
+        let transmissions = sent
+            .iter()
+            .enumerate()
+            .map(|(i, &(sf, bandwidth, airtime, _))| {
+                let start_time = i as f64 * 10.0 * SECONDS;
+                Transmission {
+                    id: i as u32,
+                    transmitter_id: 0,
+                    start_time,
+                    end_time: start_time + airtime * SECONDS,
+                    sf,
+                    power: Dbm::from_dbm(22.0),
+                    carrier_band: CarrierBand::B868,
+                    bandwidth: Frequency::from_kHz(bandwidth),
+                    header: Header::Basic(BasicHeader::new(0, Destination::Node(1), i as u32)),
+                    message_content: MessageContent::GeneratedMessage(i),
+                }
+            })
+            .collect();
+
+        let logs = [0, 1, 1]
+            .into_iter()
+            .map(|target| LogItem {
+                time: target as f64 * 10.0 * SECONDS,
+                log_level: LogLevel::Info,
+                source: LogSource::Simulation,
+                content: LogContent::TransmissionBlocked {
+                    receiver_id: 1,
+                    target_transmission_id: target,
+                    blocking_transmission_id: 4,
+                },
+            })
+            .collect();
+
+        let output = SimOutput {
+            logs,
+            transmissions,
+            complete_identity: OutputIdentity {
+                scenario_identity: ScenarioIdentity::Custom,
+                model_id: "Constructed".into(),
+                simulation_seed: 0,
+                sim_version: "".into(),
+                truncated: None,
+                scenario_hash: None,
+            },
+            stats: Default::default(),
+            node_stats: Vec::new(),
+            node_stores: Vec::new(),
+            node_settings: Vec::new(),
+            node_traces: Vec::new(),
+        };
+
+        (output, scenario)
+    }
+
+    #[test]
+    fn sf_breakdown_groups_by_sf_and_bandwidth() {
+        let (output, scenario) = mixed_sf_output();
+        let analysis = CompleteAnalysis::new(output, scenario);
+
+        let buckets: Vec<_> = analysis
+            .sf_breakdown
+            .iter()
+            .map(|x| (x.sf, x.bandwidth.kHz(), x.transmissions, x.blocked))
+            .collect();
+        assert_eq!(
+            buckets,
+            [
+                (7, 125.0, 2, 1),
+                (9, 125.0, 1, 0),
+                (12, 125.0, 1, 2),
+                (12, 250.0, 1, 0)
+            ]
+        );
+
+        let airtimes = analysis.sf_breakdown.iter().map(|x| x.airtime);
+        for (airtime, expected) in airtimes.zip([0.4, 0.5, 2.0, 1.0]) {
+            assert_close(airtime, expected);
+        }
+
+        // Sizes include the 12 byte basic header
+        let sizes = analysis.sf_breakdown.iter().map(|x| x.mean_size);
+        for (size, expected) in sizes.zip([42.0, 42.0, 52.0, 22.0]) {
+            assert_close(size, expected);
+        }
+
+        // Both SF12 buckets count
+        assert_close(analysis.top_sf_airtime_share(), 3.0 / 3.9);
+    }
+
+    fn flood_analysis() -> CompleteAnalysis {
+        let (output, scenario) = flood_output();
+        CompleteAnalysis::new(output, scenario)
+    }
+
+    #[test]
+    fn accumulated_node_stats_match_logs() {
+        let (mut output, scenario) = flood_output();
+        let node_stats = std::mem::take(&mut output.node_stats);
+        assert!(node_stats.iter().any(|x| x.too_weak > 0 || x.blocked > 0));
+
+        // Output files from before node stats were kept get them from the logs
+        let from_logs = CompleteAnalysis::new(output, scenario).node_stats;
+        assert_eq!(from_logs.len(), node_stats.len());
+
+        for (accumulated, logged) in node_stats.iter().zip(from_logs) {
+            assert_close(accumulated.tx_airtime, logged.tx_airtime);
+            assert_eq!(
+                NodeStats {
+                    tx_airtime: logged.tx_airtime,
+                    too_weak: 0,
+                    wrong_sf: 0,
+                    rng_draws: 0,
+                    model_draws: 0,
+                    ..*accumulated
+                },
+                logged
+            );
+        }
+    }
+
+    #[test]
+    fn node_settings_are_the_ones_that_ran() {
+        let (output, mut scenario) = flood_output();
+        let ran_with = scenario.settings[0].sf;
+        scenario.settings[0].sf = ran_with + 1;
+
+        let output: SimOutput =
+            serde_json::from_str(&serde_json::to_string(&output).unwrap()).unwrap();
+        assert_eq!(output.node_settings.len(), scenario.settings.len());
+        let analysis = CompleteAnalysis::new(output.clone(), scenario.clone());
+        assert_eq!(analysis.node_settings[0].sf, ran_with);
+
+        // Older output files only have the scenario to go on
+        let older = SimOutput {
+            node_settings: Vec::new(),
+            ..output
+        };
+        let analysis = CompleteAnalysis::new(older, scenario);
+        assert_eq!(analysis.node_settings[0].sf, ran_with + 1);
+    }
+
+    #[test]
+    fn per_node_counts_sum_to_totals() {
+        let analysis = flood_analysis();
+
+        assert_eq!(
+            analysis.transmissions_per_node.iter().sum::<usize>(),
+            analysis.transmissions.len()
+        );
+        assert_close(
+            analysis.airtime_per_node.iter().sum::<f64>(),
+            analysis.total_airtime,
+        );
+        assert_eq!(
+            analysis.blocked_per_node.iter().sum::<usize>(),
+            analysis.transmission_blocked_events
+        );
+    }
+
+    #[test]
+    fn latency_cdf_and_utilisation() {
+        let analysis = flood_analysis();
+        let reception = &analysis.reception_analysis;
+
+        let cdf = reception.latency_cdf();
+        assert!(!cdf.is_empty());
+        assert!(cdf.windows(2).all(|x| x[0].0 <= x[1].0 && x[0].1 < x[1].1));
+        assert_close(cdf.last().unwrap().1, 1.0);
+
+        let max = reception.latency_percentile(1.0).unwrap();
+        assert_close(max.seconds(), cdf.last().unwrap().0);
+        assert!(reception.latency_percentile(0.5).unwrap() <= max);
+
+        let bucket = 5.0 * SECONDS;
+        let utilisation = analysis.channel_utilisation(bucket);
+        assert!(utilisation.iter().all(|x| x.1 >= 0.0));
+        assert_close(
+            utilisation.iter().map(|x| x.1 * bucket.seconds()).sum(),
+            analysis.total_airtime,
+        );
+    }
+
+    #[test]
+    fn logged_deliveries_match_receptions() {
+        let (output, scenario) = flood_output();
+
+        let mut deliveries = HashSet::new();
+        for log in output.logs.iter() {
+            if let LogContent::MessageDelivered {
+                message_id,
+                receiver_id,
+                ..
+            } = log.content
+            {
+                assert!(scenario.messages[message_id].targets.contains(&receiver_id));
+                assert!(deliveries.insert((message_id, receiver_id)));
+            }
+        }
+        assert!(!deliveries.is_empty());
+
+        // Outputs from before deliveries were logged
+        let mut without = output.clone();
+        without
+            .logs
+            .retain(|x| !matches!(x.content, LogContent::MessageDelivered { .. }));
+
+        let wanted = |output: SimOutput| {
+            let analysis = CompleteAnalysis::new(output, scenario.clone());
+            analysis
+                .reception_analysis
+                .wanted_messages
+                .iter()
+                .flatten()
+                .map(|x| (x.message_id, x.was_received, x.latency))
+                .collect::<Vec<_>>()
+        };
+        let logged = wanted(output);
+        assert_eq!(logged.iter().filter(|x| x.1).count(), deliveries.len());
+        assert_eq!(logged, wanted(without));
+    }
+
+    #[test]
+    fn only_messages_with_deadlines_count_towards_hit_rate() {
+        let (output, mut scenario) = flood_output();
+        let analysis = CompleteAnalysis::new(output.clone(), scenario.clone());
+        assert_eq!(analysis.reception_analysis.deadline_hit_rate, None);
+
+        // One deadline anything received meets and one nothing can
+        scenario.messages[0].deadline = Some(Time::from_seconds(f64::MAX));
+        scenario.messages[1].deadline = Some(0.0 * SECONDS);
+        let analysis = CompleteAnalysis::new(output, scenario.clone());
+        let wanted: Vec<_> = analysis.reception_analysis.wanted_messages.iter().flatten().collect();
+
+        for message in wanted.iter() {
+            let expected = match message.message_id {
+                0 => Some(message.was_received),
+                1 => Some(false),
+                _ => None,
+            };
+            assert_eq!(message.deadline_met, expected);
+        }
+
+        let hits = wanted.iter().filter(|x| x.deadline_met == Some(true)).count();
+        let total = scenario.messages[0].targets.len() + scenario.messages[1].targets.len();
+        assert!(hits > 0);
+        assert_close(
+            analysis.reception_analysis.deadline_hit_rate.unwrap(),
+            hits as f64 / total as f64,
+        );
+    }
+
+    #[test]
+    fn message_trace_reaches_every_receiver() {
+        let analysis = flood_analysis();
+        let received = &analysis.reception_analysis.received_messages;
+
+        for message_id in 0..5 {
+            let hops = trace_message(&analysis.transmissions, &analysis.sim_events, message_id);
+            let Some(first) = hops.first() else {
+                continue;
+            };
+
+            let mut reached = HashSet::from([first.transmitter_id]);
+            for hop in hops.iter() {
+                // Every relay already had the message
+                assert!(reached.contains(&hop.transmitter_id));
+                for receiver in hop.receivers.iter() {
+                    assert!(reached.insert(*receiver));
+                }
+            }
+
+            let expected: HashSet<usize> = (0..received.len())
+                .filter(|&x| received[x].contains(&message_id))
+                .chain([first.transmitter_id])
+                .collect();
+            assert_eq!(reached, expected);
+        }
+    }
+
+    #[test]
+    fn failure_report_finds_where_hops_ran_out() {
+        use crate::{
+            node::ModelSelection,
+            node_location::{Graph, NodeLocation},
+            scenario::ScenarioMessage,
+            simulation::run_simulation,
+            units::METRES,
+        };
+
+        // A corridor longer than the three hops meshtastic allows
+        const NODES: usize = 8;
+        let scenario = Scenario {
+            messages: vec![ScenarioMessage::new(0, vec![NODES - 1], 1.0 * SECONDS, 60)],
+            ..Scenario::for_test(NodeLocation::Graph(Graph::line(NODES, 100.0 * METRES)))
+        };
+
+        let model = ModelSelection::Meshtastic.into();
+        let output = run_simulation(1, scenario.clone(), model, true);
+        let analysis = CompleteAnalysis::new(output, scenario);
+
+        let report = analysis.delivery_failure_report(0, NODES - 1);
+        assert_eq!(report.reached, vec![0, 1, 2, 3, 4]);
+        assert_eq!(report.relayed_by, vec![0, 1, 2, 3]);
+        assert!(report.in_range.is_empty());
+        assert!(report.blocked_at_target.is_empty());
+        assert_eq!(report.cause, FailureCause::NeverRelayedPast(4));
+
+        // Only node 2 sent it within range, as node 4 never passed it back
+        let report = analysis.delivery_failure_report(0, 3);
+        assert_eq!(report.cause, FailureCause::Delivered);
+        assert_eq!(report.in_range.len(), 1);
+    }
+
+    #[test]
+    fn corridor_nodes_have_two_neighbours() {
+        use crate::{
+            node_location::{Graph, Point},
+            units::METRES,
+        };
+
+        const NODES: usize = 20;
+        let mut corridor = Graph::line(NODES, 100.0 * METRES);
+        let scenario = |corridor: &Graph| Scenario::for_test(NodeLocation::Graph(corridor.clone()));
+
+        let stats = DegreeStats::new(&scenario(&corridor), 60.0 * SECONDS);
+        // Only the two ends miss out on a neighbour
+        assert_close(stats.mean_degree, 2.0 - 2.0 / NODES as f64);
+        assert_eq!((stats.min_degree, stats.max_degree), (1, 2));
+        assert_eq!(stats.isolated_nodes, 0);
+        assert!(stats.connected);
+        assert!(stats.mean_degree_over_time.is_empty());
+
+        // A node on its own splits the network
+        corridor.add_node(Point::ZERO);
+        let stats = DegreeStats::new(&scenario(&corridor), 60.0 * SECONDS);
+        assert_eq!(stats.min_degree, 0);
+        assert_eq!(stats.isolated_nodes, 1);
+        assert!(!stats.connected);
+        assert_eq!(stats.first_contact[NODES], None);
+        assert_close(stats.mean_contact_ratio(), NODES as f64 / (NODES + 1) as f64);
+    }
+
+    #[test]
+    fn empty_scenarios_are_reported_as_nan() {
+        use crate::{
+            node::ModelSelection,
+            node_location::{Graph, NodeLocation},
+            simulation::run_simulation,
+        };
+
+        let scenario = Scenario {
+            settings: Vec::new(),
+            ..Scenario::for_test(NodeLocation::Graph(Graph::new(Vec::new())))
+        };
+        let model = ModelSelection::Meshtastic.into();
+        let output = run_simulation(1, scenario.clone(), model, false);
+        let analysis = CompleteAnalysis::new(output, scenario);
+
+        let reception = &analysis.reception_analysis;
+        for rate in [
+            reception.average_reception_rate,
+            reception.min_reception_rate,
+            reception.max_reception_rate,
+        ] {
+            assert!(rate.is_nan(), "{rate}");
+        }
+        for latency in [
+            reception.avg_avg_latency,
+            reception.min_avg_latency,
+            reception.max_avg_latency,
+        ] {
+            assert!(latency.seconds().is_nan(), "{latency}");
+        }
+        assert!(reception.sorted_latencies().is_empty());
+        assert_eq!(analysis.degree_stats.mean_contact_ratio(), 0.0);
+    }
+
+    #[test]
+    fn contact_follows_nodes_coming_into_range() {
+        use crate::{
+            node_location::{Point, Points, Timepoint},
+            units::METRES,
+        };
+
+        // Nodes 0 and 1 stay together, node 2 jumps over to them halfway through
+        // and node 3 is always far away
+        let point = |x: f64| Point {
+            x: x * METRES,
+            y: 0.0 * METRES,
+        };
+        let far = 10_000_000.0;
+        let frame = |time: f64, node_2: f64| Timepoint {
+            time: time * SECONDS,
+            node_points: vec![point(0.0), point(100.0), point(node_2), point(-far)],
+        };
+
+        let scenario = Scenario::for_test(NodeLocation::Points(Points::new(vec![
+            frame(0.0, far),
+            frame(59.9, far),
+            frame(60.0, 200.0),
+            frame(120.0, 200.0),
+        ])));
+
+        let stats = DegreeStats::new(&scenario, 120.0 * SECONDS);
+        assert_eq!(stats.first_contact[0], Some(0.0 * SECONDS));
+        let joined = stats.first_contact[2].unwrap().seconds();
+        // The first sample after the jump, samples being 120/99 seconds apart
+        assert!((60.0..62.0).contains(&joined), "{joined}");
+        assert_eq!(stats.first_contact[3], None);
+
+        assert_close(stats.contact_ratio[0], 1.0);
+        assert_close(stats.contact_ratio[2], 0.5);
+        assert_close(stats.contact_ratio[3], 0.0);
+        assert_close(stats.mean_contact_ratio(), 2.5 / 4.0);
+    }
+
+    #[test]
+    fn gateways_capture_the_senders_near_them() {
+        use crate::{
+            node::NoRouting,
+            node_location::{Point, Points, Timepoint},
+            scenario::ScenarioMessage,
+            simulation::run_simulation,
+            units::METRES,
+        };
+
+        // Three senders around gateway 0 and one next to gateway 4, far out of range
+        let point = |x: f64, y: f64| Point {
+            x: x * METRES,
+            y: y * METRES,
+        };
+        let far = 10_000_000.0;
+        let node_points = vec![
+            point(0.0, 0.0),
+            point(100.0, 0.0),
+            point(0.0, 100.0),
+            point(-100.0, 0.0),
+            point(far, 0.0),
+            point(far + 100.0, 0.0),
+        ];
+
+        let settings = ScenarioNodeSettings::default();
+        let mut node_settings = vec![settings; 6];
+        node_settings[0] = node_settings[0].clone().as_gateway();
+        node_settings[4] = node_settings[4].clone().as_gateway();
+
+        let scenario = Scenario {
+            messages: [(1, 0), (2, 0), (3, 0), (5, 4)]
+                .into_iter()
+                .enumerate()
+                .map(|(i, (sender, gateway))| {
+                    ScenarioMessage::new(sender, vec![gateway], i as f64 * 10.0 * SECONDS, 40)
+                })
+                .collect(),
+            settings: node_settings,
+            ..Scenario::for_test(NodeLocation::Points(Points::new(vec![Timepoint {
+                time: 0.0 * SECONDS,
+                node_points,
+            }])))
+        };
+
+        let output = run_simulation(1, scenario.clone(), NoRouting::new().into(), false);
+        let analysis = CompleteAnalysis::new(output, scenario).reception_analysis;
+
+        let captured_by: Vec<_> = analysis
+            .first_gateway
+            .iter()
+            .map(|x| x.map(|x| x.gateway_id))
+            .collect();
+        assert_eq!(captured_by, [Some(0), Some(0), Some(0), Some(4)]);
+
+        let breakdown = &analysis.gateway_breakdown;
+        assert_eq!(breakdown.len(), 2);
+        assert_eq!(
+            (breakdown[0].gateway_id, breakdown[0].captured_messages),
+            (0, 3)
+        );
+        assert_eq!(
+            (breakdown[1].gateway_id, breakdown[1].captured_messages),
+            (4, 1)
+        );
+        assert_close(breakdown[0].message_share, 0.75);
+        // Every message was the same size and spreading factor
+        assert_close(breakdown[1].airtime_share, 0.25);
+        assert_close(analysis.busiest_gateway_share(), 0.75);
+    }
+
+    #[test]
+    fn repeats_count_latency_from_their_own_generation() {
+        use crate::{
+            node::ModelSelection,
+            node_location::{Point, Points, Timepoint},
+            scenario::ScenarioMessage,
+            simulation::{
+                models::{AdjustedFreeSpacePathLoss, PairWiseCaptureEffect},
+                run_simulation,
+            },
+            units::KM,
+        };
+
+        // The nodes are out of range until after the second generation
+        let apart = |time, distance| Timepoint {
+            time,
+            node_points: vec![
+                Point {
+                    x: 0.0 * KM,
+                    y: 0.0 * KM,
+                },
+                Point {
+                    x: distance,
+                    y: 0.0 * KM,
+                },
+            ],
+        };
+        let scenario = Scenario {
+            model: PairWiseCaptureEffect::default()
+                .with_pathloss(AdjustedFreeSpacePathLoss::new(3.5, 0.0.into()).into())
+                .into(),
+            messages: vec![
+                ScenarioMessage::new(0, vec![1], 0.0 * SECONDS, 30)
+                    .with_repeats(3, 60.0 * SECONDS),
+            ],
+            ..Scenario::for_test(NodeLocation::Points(Points::new(vec![
+                apart(0.0 * SECONDS, 1000.0 * KM),
+                apart(100.0 * SECONDS, 1000.0 * KM),
+                apart(101.0 * SECONDS, 0.1 * KM),
+            ])))
+        };
+
+        let model = ModelSelection::BasicFlood.into();
+        let output = run_simulation(1, scenario.clone(), model, false);
+        let reception = CompleteAnalysis::new(output, scenario).reception_analysis;
+
+        let wanted = &reception.wanted_messages[1][0];
+        let from_first = wanted.latency_from_first_generation.unwrap();
+        let from_causing = wanted.latency.unwrap();
+        assert!(from_first > 120.0 * SECONDS, "{from_first:?}");
+        assert!(from_causing < 60.0 * SECONDS, "{from_causing:?}");
+        assert_close(from_first - from_causing, 120.0 * SECONDS);
+
+        assert_close(reception.global_latency, from_causing);
+        assert_close(reception.global_latency_from_first_generation, from_first);
+    }
+
+    #[test]
+    fn latency_grows_along_corridor() {
+        use crate::{
+            node::ModelSelection,
+            node_location::{Graph, Point, Points, Timepoint},
+            scenario::ScenarioMessage,
+            simulation::{
+                models::{AdjustedFreeSpacePathLoss, PairWiseCaptureEffect},
+                run_simulation,
+            },
+            units::{KM, METRES},
+        };
+
+        // Nodes 3.5km apart only hear their neighbours with this path loss
+        const NODES: usize = 6;
+        let spacing = 3.5 * KM;
+        let corridor = |map| Scenario {
+            model: PairWiseCaptureEffect::default()
+                .with_pathloss(AdjustedFreeSpacePathLoss::new(3.5, 0.0.into()).into())
+                .into(),
+            messages: vec![ScenarioMessage::new(0, (1..NODES).collect(), 1.0 * SECONDS, 60)],
+            ..Scenario::for_test(map)
+        };
+        let analyse = |scenario: Scenario| {
+            let model = ModelSelection::BasicFlood.into();
+            let output = run_simulation(1, scenario.clone(), model, false);
+            CompleteAnalysis::new(output, scenario).distance_latency
+        };
+
+        let node_points = (0..NODES)
+            .map(|id| Point {
+                x: id as f64 * spacing,
+                y: 0.0 * METRES,
+            })
+            .collect();
+        let positioned = analyse(corridor(NodeLocation::Points(Points::new(vec![
+            Timepoint {
+                time: 0.0 * SECONDS,
+                node_points,
+            },
+        ]))));
+
+        assert!(!positioned.hops);
+        assert_eq!(positioned.pairs.len(), NODES - 1);
+        for (id, pair) in positioned.pairs.iter().enumerate() {
+            assert_close(pair.0, (id + 1) as f64 * spacing.metres());
+        }
+        assert!(positioned.pairs.windows(2).all(|x| x[0].1 < x[1].1));
+        assert_eq!(positioned.bins.len(), NODES - 1);
+        assert!(positioned.bins.windows(2).all(|x| x[0].mean_latency < x[1].mean_latency));
+        assert_close(positioned.bins.last().unwrap().end, 5.0 * spacing.metres());
+
+        // The same corridor as a graph measures distance in hops
+        let graph = analyse(corridor(NodeLocation::Graph(Graph::line(NODES, 100.0 * METRES))));
+
+        assert!(graph.hops);
+        let bins: Vec<_> = graph.bins.iter().map(|x| (x.start, x.messages)).collect();
+        assert_eq!(bins, [(1.0, 1), (2.0, 1), (3.0, 1), (4.0, 1), (5.0, 1)]);
+        assert!(graph.bins.windows(2).all(|x| x[0].mean_latency < x[1].mean_latency));
+    }
+}
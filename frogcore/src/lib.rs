@@ -12,7 +12,7 @@ pub mod simulation;
 pub mod units;
 pub mod verification;
 pub mod scenario;
-mod utility;
+pub mod utility;
 
 use std::fmt::Debug;
 
@@ -29,11 +29,18 @@ const SNR_MAX: f64 = 20.0;
 /// I can't find the original source for this formula but the same thing is used [here](https://www.rfwireless-world.com/calculators/LoRaWAN-Airtime-calculator.html)
 ///
 /// * `payload_size` - size of the header and packet body in bytes.
+///   The simulation passes the message or [`CustomContent`](node::CustomContent) size
+///   plus the [`HeaderSize`](node::HeaderSize) of the packet's header,
+///   so 12 bytes on top for [`BasicHeader`](node::BasicHeader) models
+///   and 16 for [`MeshtasticHeader`](node::MeshtasticHeader) ones.
+///   The LoRa physical header, preamble and CRC are accounted for here, not in `payload_size`.
+/// * `radio_setting` - the spreading factor, bandwidth, coding rate, preamble length
+///   and whether the physical header is sent are used.
 pub fn calculate_air_time(payload_size: i32, radio_setting: &NodeSettings) -> Time {
-    /// Is the header disabled. Refers to the LoRA Phys header not meshtastic header.
-    /// This is a number not a bool for convenience.
-    const HEAD_DISABLE: i32 = 0;
-    const PREAMBLE_LEN: f64 = 16.0;
+    // Is the header disabled. Refers to the LoRA Phys header not meshtastic header.
+    // This is a number not a bool for convenience.
+    let head_disable = !radio_setting.explicit_header as i32;
+    let preamble_len = radio_setting.preamble_len as f64;
 
     let coding_rate: f64 = radio_setting.coding_rate as f64;
 
@@ -42,11 +49,11 @@ pub fn calculate_air_time(payload_size: i32, radio_setting: &NodeSettings) -> Ti
 
     let low_data_mode = symbol_time > Time::from_milis(16.0);
 
-    let preamble_time = (PREAMBLE_LEN + 4.25) * symbol_time;
+    let preamble_time = (preamble_len + 4.25) * symbol_time;
 
     // What all the magic numbers in this mean is a mystery to me. Looking through quite a number of papers has not helped
     let probably_number_of_bits_before_coding =
-        (8 * payload_size - 4 * sf + 28 + 16 - 20 * HEAD_DISABLE) as f64;
+        (8 * payload_size - 4 * sf + 28 + 16 - 20 * head_disable) as f64;
     let adjusted_sf = if low_data_mode { sf - 2 } else { sf } as f64;
 
     let payload_symbols = 8.0
@@ -80,7 +87,119 @@ where
 
 #[cfg(test)]
 mod tests {
-    use crate::{assert_close, units::Length};
+    use crate::{
+        assert_close, calculate_air_time,
+        node::{BasicHeader, HeaderSize, MeshtasticHeader, ModelSelection},
+        node_location::{Graph, NodeLocation},
+        scenario::{Scenario, ScenarioMessage, ScenarioNodeSettings},
+        simulation::{
+            data_structs::{NodeSettings, Transmission},
+            run_simulation,
+        },
+        units::{Length, SECONDS, Time},
+    };
+
+    /// Two nodes, in range of each other if `connected`,
+    /// with node 0 sending one message to node 1 at 1 second.
+    fn latency_transmissions(
+        settings: ScenarioNodeSettings,
+        connected: bool,
+        model: ModelSelection,
+    ) -> Vec<Transmission> {
+        let base = if connected {
+            Scenario::test_pair()
+        } else {
+            Scenario::for_test(NodeLocation::Graph(Graph::new(vec![vec![], vec![]])))
+        };
+
+        let scenario = Scenario {
+            messages: vec![ScenarioMessage::new(0, vec![1], 1.0 * SECONDS, 60)],
+            settings: vec![settings; 2],
+            ..base
+        };
+
+        run_simulation(1, scenario, model.into(), false).transmissions
+    }
+
+    #[test]
+    fn longer_preambles_add_whole_symbols() {
+        let base = ScenarioNodeSettings::default();
+        let long = ScenarioNodeSettings {
+            preamble_len: 32,
+            ..base.clone()
+        };
+
+        // Each preamble symbol takes 2^SF / bandwidth
+        let symbol_time = 2f64.powi(base.sf) / base.bandwidth;
+        let delta =
+            calculate_air_time(60, &long.into()) - calculate_air_time(60, &base.clone().into());
+        assert_close(delta, 16.0 * symbol_time);
+
+        // Implicit header mode leaves the 20 header bits out of the payload symbols
+        let implicit = ScenarioNodeSettings {
+            explicit_header: false,
+            ..base.clone()
+        };
+        assert!(calculate_air_time(60, &implicit.into()) < calculate_air_time(60, &base.into()));
+    }
+
+    #[test]
+    fn airtime_includes_the_models_header() {
+        let settings = ScenarioNodeSettings::default();
+        let radio: NodeSettings = settings.clone().into();
+
+        let basic = latency_transmissions(settings.clone(), true, ModelSelection::BasicFlood);
+        let meshtastic = latency_transmissions(settings, true, ModelSelection::Meshtastic);
+
+        assert_close(
+            basic[0].airtime(),
+            calculate_air_time(60 + BasicHeader::SIZE, &radio),
+        );
+        assert_close(
+            meshtastic[0].airtime(),
+            calculate_air_time(60 + MeshtasticHeader::SIZE, &radio),
+        );
+        // The same message takes longer to send with the bigger header
+        assert!(meshtastic[0].airtime() > basic[0].airtime());
+    }
+
+    #[test]
+    fn latency_shifts_with_reaction_time() {
+        let delta = Time::from_milis(250.0);
+        let base = ScenarioNodeSettings::default();
+        let slow = ScenarioNodeSettings {
+            reaction_time: base.reaction_time + delta,
+            ..base.clone()
+        };
+
+        let before = latency_transmissions(base.clone(), true, ModelSelection::BasicFlood);
+        let after = latency_transmissions(slow, true, ModelSelection::BasicFlood);
+
+        let airtime = calculate_air_time(60 + before[0].header.size(), &base.into());
+        assert_close(before[0].airtime(), airtime);
+
+        // Delivered once the transmission from node 0 ends
+        assert_close(after[0].end_time - before[0].end_time, delta);
+        assert_close(after[0].airtime(), airtime);
+    }
+
+    #[test]
+    fn retransmissions_shift_with_processing_time() {
+        let delta = Time::from_milis(1500.0);
+        let base = ScenarioNodeSettings::default();
+        let slow = ScenarioNodeSettings {
+            processing_time: base.processing_time + delta,
+            ..base.clone()
+        };
+
+        // Node 1 can't be reached so node 0 never gets an acknowledgement and retransmits
+        let gap = |settings| {
+            let sent = latency_transmissions(settings, false, ModelSelection::Meshtastic);
+            sent[1].start_time - sent[0].start_time
+        };
+
+        assert_close(gap(slow) - gap(base), delta);
+    }
 
     #[test]
     fn test_assert_close_pos() {
@@ -1,35 +1,51 @@
 pub mod ack_repeat_flood;
+pub mod adaptive_sf;
 pub mod basic_flood;
+pub mod beacon;
 pub mod meshtastic;
 pub mod no_routing;
 pub mod probabilistic_flood;
+pub mod relay_queue;
 pub mod simple_managed_flooding;
 pub mod stack_flood;
+pub mod trickle;
 
 use thiserror::Error;
 
 use crate::{
-    simulation::{data_structs::MessageInfo, Context, MessageContent, NodeError},
+    simulation::{
+        data_structs::{MessageInfo, ReceptionFailure},
+        Context, MessageContent, NodeError,
+    },
     units::{Db, Time},
 };
 
 pub use ack_repeat_flood::AcknowledgedOrRepeatFlood;
+pub use adaptive_sf::AdaptiveSf;
 pub use basic_flood::BasicFlood;
+pub use beacon::{BeaconKind, PeriodicBeacon};
 pub use meshtastic::Meshtastic;
 pub use no_routing::NoRouting;
 pub use probabilistic_flood::ProbabilisticFlood;
+pub use relay_queue::RelayQueue;
 pub use serde::{Deserialize, Serialize};
 pub use simple_managed_flooding::SimpleManagedFlooding;
 pub use stack_flood::StackFlood;
+pub use trickle::Trickle;
 
+/// `$test` models are only in [`NodeModel`] for tests, not [`ModelSelection`].
 macro_rules! node_model {
-    ($count:literal, $($variant:ident),+) => {
+    ($count:literal, $($variant:ident),+; test: $($test:ident),+) => {
 
         #[derive(Debug, Clone, Serialize, Deserialize)]
         pub enum NodeModel {
             $(
                 $variant($variant),
             )*
+            $(
+                #[cfg(test)]
+                $test($test),
+            )*
         }
 
         impl ImplNodeModel for NodeModel {
@@ -41,6 +57,10 @@ macro_rules! node_model {
                     $(
                         NodeModel::$variant(inner) => inner.identity_str(),
                     )*
+                    $(
+                        #[cfg(test)]
+                        NodeModel::$test(inner) => inner.identity_str(),
+                    )*
                 }
             }
 
@@ -49,6 +69,10 @@ macro_rules! node_model {
                     $(
                         NodeModel::$variant(inner) => inner.initalisation(context),
                     )*
+                    $(
+                        #[cfg(test)]
+                        NodeModel::$test(inner) => inner.initalisation(context),
+                    )*
                 }
             }
 
@@ -69,12 +93,52 @@ macro_rules! node_model {
                                 return;
                             };
 
+                            inner.receive_message(context, inner_header, message_content, payload_size, snr);
+                        },
+                    )*
+                    $(
+                        #[cfg(test)]
+                        NodeModel::$test(inner) => {
+                            let Ok(inner_header) = header.try_into() else {
+                                return;
+                            };
+
                             inner.receive_message(context, inner_header, message_content, payload_size, snr);
                         },
                     )*
                 }
             }
 
+            fn on_reception_failed(
+                &mut self,
+                context: Context,
+                header: &Self::InnerHeader,
+                receiver_id: usize,
+                reason: ReceptionFailure,
+            ) {
+                match self {
+                    $(
+                        NodeModel::$variant(inner) => {
+                            let Ok(inner_header) = header.try_into() else {
+                                return;
+                            };
+
+                            inner.on_reception_failed(context, inner_header, receiver_id, reason);
+                        },
+                    )*
+                    $(
+                        #[cfg(test)]
+                        NodeModel::$test(inner) => {
+                            let Ok(inner_header) = header.try_into() else {
+                                return;
+                            };
+
+                            inner.on_reception_failed(context, inner_header, receiver_id, reason);
+                        },
+                    )*
+                }
+            }
+
             fn generate_message(
                 &mut self,
                 context: Context,
@@ -85,6 +149,10 @@ macro_rules! node_model {
                     $(
                         NodeModel::$variant(inner) => inner.generate_message(context, message_id, message_info),
                     )*
+                    $(
+                        #[cfg(test)]
+                        NodeModel::$test(inner) => inner.generate_message(context, message_id, message_info),
+                    )*
                 }
             }
 
@@ -93,13 +161,21 @@ macro_rules! node_model {
                     $(
                         NodeModel::$variant(inner) => inner.handle_error(context, error),
                     )*
+                    $(
+                        #[cfg(test)]
+                        NodeModel::$test(inner) => inner.handle_error(context, error),
+                    )*
                 }
             }
 
-            fn get_notified(&mut self, context: Context, notification: Notification, thread: NodeThread) {
+            fn get_notified(&mut self, context: Context, notification: Notification, tag: u64, thread: NodeThread) {
                 match self {
                     $(
-                        NodeModel::$variant(inner) => inner.get_notified(context, notification, thread),
+                        NodeModel::$variant(inner) => inner.get_notified(context, notification, tag, thread),
+                    )*
+                    $(
+                        #[cfg(test)]
+                        NodeModel::$test(inner) => inner.get_notified(context, notification, tag, thread),
                     )*
                 }
             }
@@ -115,6 +191,17 @@ macro_rules! node_model {
 
         )*
 
+        $(
+
+        #[cfg(test)]
+        impl From<$test> for NodeModel {
+            fn from(value: $test) -> Self {
+                NodeModel::$test(value)
+            }
+        }
+
+        )*
+
         #[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
         pub enum ModelSelection {
             $(
@@ -142,16 +229,22 @@ macro_rules! node_model {
 }
 
 node_model!(
-    7,
+    9,
     Meshtastic,
     AcknowledgedOrRepeatFlood,
     BasicFlood,
     StackFlood,
     NoRouting,
     ProbabilisticFlood,
-    SimpleManagedFlooding
+    SimpleManagedFlooding,
+    Trickle,
+    AdaptiveSf;
+    test: FeedbackRecorder
 );
 
+#[cfg(test)]
+use tests::FeedbackRecorder;
+
 #[derive(Debug, Error)]
 #[error("Failed to parse string to node model")]
 pub struct ParseModelError;
@@ -166,15 +259,22 @@ pub fn parse_model(s: &str) -> Result<ModelSelection, ParseModelError> {
         "stackflood" | "stack flood" | "stack_flood" => StackFlood,
         "probabilisticflood" | "probabilistic_flood" => ProbabilisticFlood,
         "norouting" | "no_routing" => NoRouting,
+        "simplemanagedflooding" | "simple_managed_flooding" | "managed_flood" => {
+            SimpleManagedFlooding
+        }
+        "trickle" => Trickle,
+        "adaptivesf" | "adaptive_sf" => AdaptiveSf,
         _ => return Err(ParseModelError),
     })
 }
 
-#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, Serialize, Deserialize)]
 pub enum NodeThread {
     RadioThread,
     RoutingThread,
     CacheThread,
+    TrickleThread,
+    BeaconThread,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -186,6 +286,15 @@ pub enum CustomContent {
     GlobalAck {
         id: GlobalPacketId,
     },
+    /// Flooded back by a gateway that heard the packet, as it has left the mesh from there
+    UplinkAck {
+        id: GlobalPacketId,
+    },
+    /// Sent by a [`PeriodicBeacon`], `sequence` counting up from 0 for each node
+    Beacon {
+        kind: BeaconKind,
+        sequence: u32,
+    },
 }
 
 impl CustomContent {
@@ -194,6 +303,8 @@ impl CustomContent {
         match self {
             CustomContent::RoutingMessage { .. } => 8,
             CustomContent::GlobalAck { .. } => 8,
+            CustomContent::UplinkAck { .. } => 8,
+            CustomContent::Beacon { kind, .. } => kind.size(),
         }
     }
 }
@@ -240,6 +351,21 @@ pub trait ImplNodeModel {
         snr: Db<f64>,
     );
 
+    /// Called on the transmitting node when `receiver_id` fails to receive one of its
+    /// transmissions, but only if [`Scenario::reception_feedback`] is set.
+    /// Only receivers the packet was meant for are reported: the destination node,
+    /// members of the destination group, or anyone for a broadcast.
+    ///
+    /// [`Scenario::reception_feedback`]: crate::scenario::Scenario::reception_feedback
+    fn on_reception_failed(
+        &mut self,
+        _context: Context,
+        _header: &Self::InnerHeader,
+        _receiver_id: usize,
+        _reason: ReceptionFailure,
+    ) {
+    }
+
     /// This function is called from the simulation when a simulated user generates a message to be sent by the node.
     ///
     /// - `message_id` - An id for the simulator to identify the generated message. Make sure it is transmitted (see [`Context::enqueue_send`]).
@@ -263,7 +389,15 @@ pub trait ImplNodeModel {
     /// Then for each simulated thread notifications can be registered using [`Context::notify_later`].
     /// There can only be one notification per time per thread meaning trying to register a new notification for the same thread will
     /// either override the existing one or be ignored. See [`Context::notify_later`]
-    fn get_notified(&mut self, context: Context, notification: Notification, thread: NodeThread);
+    ///
+    /// `tag` is whatever was passed to [`Context::notify_later_tagged`], or 0 for [`Context::notify_later`].
+    fn get_notified(
+        &mut self,
+        context: Context,
+        notification: Notification,
+        tag: u64,
+        thread: NodeThread,
+    );
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -286,6 +420,13 @@ pub trait BasicHeaderInfo {
     fn packet_id(&self) -> u32;
 }
 
+/// Bytes a header adds to the packet body on air.
+/// Models with their own header type implement this for it so their airtime is worked out
+/// with their overhead, see [`calculate_air_time`](crate::calculate_air_time).
+pub trait HeaderSize {
+    fn size(&self) -> i32;
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BasicHeader {
     dest: Destination,
@@ -293,6 +434,19 @@ pub struct BasicHeader {
     packet_id: u32,
 }
 
+impl BasicHeader {
+    /// Destination, sender and packet id, sent as 4 bytes each.
+    pub const SIZE: i32 = 3 * size_of::<u32>() as i32;
+
+    pub fn new(sender: usize, dest: Destination, packet_id: u32) -> BasicHeader {
+        BasicHeader {
+            dest,
+            sender,
+            packet_id,
+        }
+    }
+}
+
 impl BasicHeaderInfo for BasicHeader {
     fn dest(&self) -> Destination {
         self.dest
@@ -307,6 +461,12 @@ impl BasicHeaderInfo for BasicHeader {
     }
 }
 
+impl HeaderSize for BasicHeader {
+    fn size(&self) -> i32 {
+        BasicHeader::SIZE
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MeshtasticHeader {
     dest: Destination,
@@ -331,11 +491,47 @@ impl BasicHeaderInfo for MeshtasticHeader {
     }
 }
 
-impl Header {
-    /// Implement a function to calculate the header size in bytes.
-    /// Will be constant in many cases.
-    pub fn size(&self) -> i32 {
-        16 // default for Meshtastic
+impl MeshtasticHeader {
+    /// The firmware's `PacketHeader`. Destination, sender and packet id are 4 bytes each,
+    /// then a byte each for the flags, channel hash, next hop and relay node.
+    pub const SIZE: i32 = 16;
+}
+
+impl HeaderSize for MeshtasticHeader {
+    fn size(&self) -> i32 {
+        MeshtasticHeader::SIZE
+    }
+}
+
+impl BasicHeaderInfo for Header {
+    fn dest(&self) -> Destination {
+        match self {
+            Header::Basic(header) => header.dest(),
+            Header::Meshtastic(header) => header.dest(),
+        }
+    }
+
+    fn sender(&self) -> usize {
+        match self {
+            Header::Basic(header) => header.sender(),
+            Header::Meshtastic(header) => header.sender(),
+        }
+    }
+
+    fn packet_id(&self) -> u32 {
+        match self {
+            Header::Basic(header) => header.packet_id(),
+            Header::Meshtastic(header) => header.packet_id(),
+        }
+    }
+}
+
+impl HeaderSize for Header {
+    fn size(&self) -> i32 {
+        match self {
+            Header::Basic(header) => header.size(),
+            Header::Meshtastic(header) => header.size(),
+        }
     }
 }
 
@@ -399,20 +595,42 @@ impl From<MeshtasticHeader> for Header {
 pub enum Destination {
     Broadcast,
     Node(usize),
+    /// Every node in the group, see [`crate::scenario::ScenarioNodeSettings::groups`].
+    /// Forwarded like a broadcast but only delivered to members.
+    Group(u16),
 }
 
 impl Destination {
-    /// Returns false if broadcast. Otherwise returns true if the destination is the provided node id
-    fn is_to_node(self, node_id: usize) -> bool {
+    /// Returns false if broadcast. Otherwise returns true if the current node is the destination
+    /// or a member of the destination group.
+    fn is_to_node(self, context: &Context) -> bool {
         match self {
             Destination::Broadcast => false,
+            Destination::Node(id) => id == context.node_id(),
+            Destination::Group(group) => context.node_setting().groups.contains(&group),
+        }
+    }
+
+    /// Returns true if the provided node id is the only destination,
+    /// meaning there's no need to pass it on once it arrives there.
+    fn is_only_to_node(self, node_id: usize) -> bool {
+        match self {
             Destination::Node(id) => id == node_id,
+            Destination::Broadcast | Destination::Group(_) => false,
         }
     }
 
     fn is_broadcast(self) -> bool {
         match self {
             Destination::Broadcast => true,
+            Destination::Node(_) | Destination::Group(_) => false,
+        }
+    }
+
+    /// Broadcasts and groups, which can have more than one destination.
+    fn is_multicast(self) -> bool {
+        match self {
+            Destination::Broadcast | Destination::Group(_) => true,
             Destination::Node(_) => false,
         }
     }
@@ -460,11 +678,7 @@ struct PendingPacket<H> {
 
 /// Function for creating a standard header for a user generated message.
 fn basic_header(sender_id: usize, packet_id: u32, message_info: &MessageInfo) -> BasicHeader {
-    let dest = if message_info.targets.len() == 1 {
-        Destination::Node(*message_info.targets.first().expect("checked length"))
-    } else {
-        Destination::Broadcast
-    };
+    let dest = message_info.std_destination();
 
     let header = BasicHeader {
         dest,
@@ -481,11 +695,7 @@ fn meshtastic_header(
     packet_id: u32,
     message_info: &MessageInfo,
 ) -> MeshtasticHeader {
-    let dest = if message_info.targets.len() == 1 {
-        Destination::Node(*message_info.targets.first().expect("checked length"))
-    } else {
-        Destination::Broadcast
-    };
+    let dest = message_info.std_destination();
 
     let header = MeshtasticHeader {
         hop_limit: 3,
@@ -498,3 +708,370 @@ fn meshtastic_header(
 
     header
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        analysis::CompleteAnalysis,
+        node_location::{Edge, Graph, NodeLocation},
+        scenario::{Scenario, ScenarioError, ScenarioMessage, ScenarioNodeSettings},
+        sim_file::SimOutput,
+        simulation::{
+            RunOptions, data_structs::LogContent, run_simulation, run_simulation_with_state,
+        },
+        units::{METRES, MINS, SECONDS},
+    };
+
+    /// Sends generated messages straight away, like [`NoRouting`], and keeps every reception
+    /// failure it is told about.
+    #[derive(Debug, Clone, Default, Serialize, Deserialize)]
+    pub struct FeedbackRecorder {
+        next_packet_id: u32,
+        /// Receiver of each failed reception
+        #[serde(skip)]
+        pub failed: Vec<usize>,
+    }
+
+    impl ImplNodeModel for FeedbackRecorder {
+        type InnerHeader = BasicHeader;
+
+        fn identity_str(&self) -> &str {
+            "Feedback Recorder"
+        }
+
+        fn initalisation(&mut self, _context: Context) {}
+
+        fn receive_message(
+            &mut self,
+            _context: Context,
+            _header: &Self::InnerHeader,
+            _message_content: MessageContent,
+            _payload_size: i32,
+            _snr: Db<f64>,
+        ) {
+        }
+
+        fn on_reception_failed(
+            &mut self,
+            _context: Context,
+            _header: &Self::InnerHeader,
+            receiver_id: usize,
+            _reason: ReceptionFailure,
+        ) {
+            self.failed.push(receiver_id);
+        }
+
+        fn generate_message(
+            &mut self,
+            mut context: Context,
+            message_id: MessageContent,
+            message_info: &MessageInfo,
+        ) {
+            let header = basic_header(context.node_id(), self.next_packet_id, message_info);
+            self.next_packet_id += 1;
+            context.enqueue_send(header, message_id);
+        }
+
+        fn handle_error(&mut self, _context: Context, _error: NodeError) {}
+
+        fn get_notified(
+            &mut self,
+            _context: Context,
+            _notification: Notification,
+            _tag: u64,
+            _thread: NodeThread,
+        ) {
+        }
+    }
+
+    /// Five nodes in a line split between three groups, so group messages have to be passed on
+    /// by nodes outside the group as well as by members to reach everyone in it.
+    fn three_group_scenario() -> Scenario {
+        // Node 4 is in two groups
+        let settings: Vec<_> = [vec![0], vec![1], vec![2], vec![0], vec![1, 2]]
+            .into_iter()
+            .map(|groups| ScenarioNodeSettings::default().in_groups(groups))
+            .collect();
+
+        let messages = vec![
+            ScenarioMessage::to_group(0, 1, &settings, 1.0 * SECONDS, 60),
+            ScenarioMessage::to_group(4, 0, &settings, 60.0 * SECONDS, 60),
+            ScenarioMessage::to_group(1, 2, &settings, 120.0 * SECONDS, 60),
+        ];
+
+        Scenario {
+            messages,
+            settings,
+            ..Scenario::for_test(NodeLocation::Graph(Graph::line(5, 100.0 * METRES)))
+        }
+    }
+
+    #[test]
+    fn group_messages_reach_every_member() {
+        use ModelSelection::*;
+
+        let scenario = three_group_scenario();
+        scenario.validate().unwrap();
+
+        let targets: Vec<_> = scenario.messages.iter().map(|x| x.targets.clone()).collect();
+        assert_eq!(targets, [vec![1, 4], vec![0, 3], vec![2, 4]]);
+
+        // The same messages sent to everyone, which group messages should be passed on like
+        let mut broadcast = scenario.clone();
+        broadcast.messages.iter_mut().for_each(|x| x.group = None);
+
+        let run = |scenario: &Scenario, model: ModelSelection| {
+            let output = run_simulation(1, scenario.clone(), model.into(), false);
+            let analysis = CompleteAnalysis::new(output.clone(), scenario.clone());
+            let received: Vec<Vec<(usize, bool)>> = analysis
+                .reception_analysis
+                .wanted_messages
+                .iter()
+                .map(|x| x.iter().map(|x| (x.message_id, x.was_received)).collect())
+                .collect();
+
+            (output, received)
+        };
+
+        for model in [
+            Meshtastic,
+            AcknowledgedOrRepeatFlood,
+            BasicFlood,
+            StackFlood,
+            SimpleManagedFlooding,
+        ] {
+            let (output, received) = run(&scenario, model);
+
+            for transmission in output.transmissions.iter() {
+                if let MessageContent::GeneratedMessage(id) = transmission.message_content {
+                    let group = scenario.messages[id].group;
+                    let dest = transmission.header.dest();
+                    assert!(matches!(dest, Destination::Group(x) if Some(x) == group));
+                }
+            }
+
+            // Only members want each message
+            let wanted: Vec<Vec<usize>> = received
+                .iter()
+                .map(|x| x.iter().map(|x| x.0).collect())
+                .collect();
+            assert_eq!(wanted, [vec![1], vec![0], vec![2], vec![1], vec![0, 2]]);
+
+            assert_eq!(received, run(&broadcast, model).1, "{model:?}");
+
+            // Passive acknowledgements stop this one short along a line
+            if !matches!(model, AcknowledgedOrRepeatFlood) {
+                assert!(received.iter().flatten().all(|x| x.1), "{model:?}");
+            }
+        }
+
+        let mut stale = scenario;
+        stale.settings[3].groups.clear();
+        assert!(matches!(
+            stale.validate(),
+            Err(ScenarioError::GroupTargets { message: 1, group: 0 })
+        ));
+    }
+
+    #[test]
+    fn far_nodes_rebroadcast_first() {
+        // Node 1 is close to node 0 and node 2 is near the edge of its range
+        const FAR: f64 = 1600000.0;
+        let edge = |to, metres| Edge {
+            to,
+            weight: metres * METRES,
+        };
+        let line = vec![
+            vec![edge(1, 100.0), edge(2, FAR)],
+            vec![edge(0, 100.0), edge(2, FAR - 100.0)],
+            vec![edge(0, FAR), edge(1, FAR - 100.0)],
+        ];
+
+        let scenario = Scenario {
+            messages: vec![ScenarioMessage::new(0, vec![1, 2], 1.0 * SECONDS, 60)],
+            ..Scenario::for_test(NodeLocation::Graph(Graph::new(line)))
+        };
+
+        let output = run_simulation(1, scenario, ModelSelection::Meshtastic.into(), true);
+
+        let rebroadcast_delay = |node| {
+            output
+                .logs
+                .iter()
+                .find_map(|x| match x.content {
+                    LogContent::TransmitDelay {
+                        node_id,
+                        snr: Some(snr),
+                        delay,
+                    } if node_id == node => Some((snr, delay)),
+                    _ => None,
+                })
+                .expect("rebroadcast delay is weighted by snr")
+        };
+
+        let (near_snr, near_delay) = rebroadcast_delay(1);
+        let (far_snr, far_delay) = rebroadcast_delay(2);
+
+        assert!(far_snr < near_snr);
+        assert!(far_delay < near_delay);
+    }
+
+    const LEAVES: usize = 5;
+
+    /// A gateway that every other node can reach, but which the others can only reach
+    /// through it, with each of them sending a message to the next.
+    fn gateway_star() -> Scenario {
+        let mut star = vec![(1..=LEAVES).collect()];
+        star.extend((1..=LEAVES).map(|_| vec![0]));
+
+        let mut settings = vec![ScenarioNodeSettings::default(); LEAVES + 1];
+        settings[0] = ScenarioNodeSettings::default().as_gateway();
+
+        let messages = (1..=LEAVES)
+            .map(|id| {
+                let time = (1.0 + 60.0 * id as f64) * SECONDS;
+                ScenarioMessage::new(id, vec![id % LEAVES + 1], time, 60)
+            })
+            .collect();
+
+        Scenario {
+            messages,
+            settings,
+            ..Scenario::for_test(NodeLocation::Graph(Graph::uniform(star, 100.0 * METRES)))
+        }
+    }
+
+    #[test]
+    fn gateways_ack_uplinked_messages() {
+        let scenario = gateway_star();
+        let mut uplinked = scenario.clone();
+        uplinked.gateway_uplink = true;
+
+        let models = |uplink_acks| -> [NodeModel; 2] {
+            [
+                Meshtastic::new().with_uplink_acks(uplink_acks).into(),
+                AcknowledgedOrRepeatFlood::new()
+                    .with_uplink_acks(uplink_acks)
+                    .into(),
+            ]
+        };
+
+        let uplink_acks = |output: &SimOutput| -> Vec<(usize, GlobalPacketId)> {
+            output
+                .transmissions
+                .iter()
+                .filter_map(|x| match x.message_content {
+                    MessageContent::NodeMessage(CustomContent::UplinkAck { id }) => {
+                        Some((x.transmitter_id, id))
+                    }
+                    _ => None,
+                })
+                .collect()
+        };
+        let sent_by_senders = |output: &SimOutput| {
+            output
+                .transmissions
+                .iter()
+                .filter(|x| match x.message_content {
+                    MessageContent::GeneratedMessage(id) => {
+                        scenario.messages[id].sender == x.transmitter_id
+                    }
+                    _ => false,
+                })
+                .count()
+        };
+
+        for (without, with) in models(false).into_iter().zip(models(true)) {
+            let output = run_simulation(1, scenario.clone(), without, false);
+            let acked = run_simulation(1, scenario.clone(), with, false);
+            let id = &acked.complete_identity.model_id;
+
+            assert!(uplink_acks(&output).is_empty());
+
+            // The gateway acks each message once and the ack floods back to its sender
+            let acks = uplink_acks(&acked);
+            let mut from_gateway: Vec<_> = acks
+                .iter()
+                .filter(|x| x.0 == 0)
+                .map(|x| x.1.node_id)
+                .collect();
+            from_gateway.sort();
+            assert_eq!(from_gateway, (1..=LEAVES).collect::<Vec<_>>(), "{id}");
+            for sender in 1..=LEAVES {
+                assert!(acks.iter().any(|x| x.0 == sender && x.1.node_id == sender));
+            }
+
+            assert!(sent_by_senders(&acked) <= sent_by_senders(&output), "{id}");
+
+            for output in [&output, &acked] {
+                let analysis = CompleteAnalysis::new(output.clone(), uplinked.clone());
+                assert_eq!(analysis.reception_analysis.uplink_reception_rate, 1.0);
+                assert_eq!(analysis.reception_analysis.average_reception_rate, 1.0);
+            }
+        }
+
+        // Repeats stop once acked, and the gateway doesn't pass on what it has uplinked,
+        // so without counting uplinks the other nodes miss out
+        let output = run_simulation(1, scenario.clone(), models(false)[1].clone(), false);
+        let acked = run_simulation(1, scenario.clone(), models(true)[1].clone(), false);
+        assert!(sent_by_senders(&acked) < sent_by_senders(&output));
+
+        let reception = |output: &SimOutput| {
+            CompleteAnalysis::new(output.clone(), scenario.clone())
+                .reception_analysis
+                .average_reception_rate
+        };
+        assert!(reception(&acked) < reception(&output));
+    }
+
+    #[test]
+    fn failures_reported_to_intended_receivers_with_feedback() {
+        // Node 1 is close to node 0 but nodes 2 and 3 are far too far away to hear it
+        let edge = |to, weight| Edge { to, weight };
+        let far = 10_000_000.0 * METRES;
+        let graph = Graph::new(vec![
+            vec![edge(1, 100.0 * METRES), edge(2, far), edge(3, far)],
+            vec![edge(0, 100.0 * METRES)],
+            vec![edge(0, far)],
+            vec![edge(0, far)],
+        ]);
+        let settings = [vec![], vec![], vec![], vec![4]]
+            .map(|groups| ScenarioNodeSettings::default().in_groups(groups))
+            .to_vec();
+        let mut scenario = Scenario {
+            messages: vec![
+                ScenarioMessage::new(0, vec![1, 2, 3], 10.0 * MINS, 60),
+                ScenarioMessage::new(0, vec![1], 20.0 * MINS, 60),
+                ScenarioMessage::new(0, vec![2], 30.0 * MINS, 60),
+                ScenarioMessage::to_group(0, 4, &settings, 40.0 * MINS, 60),
+            ],
+            settings,
+            ..Scenario::for_test(NodeLocation::Graph(graph))
+        };
+        scenario.validate().unwrap();
+
+        let failures = |scenario: &Scenario| {
+            let model = FeedbackRecorder::default().into();
+            let options = RunOptions::default();
+            let (_, nodes) = run_simulation_with_state(1, scenario.clone(), model, options);
+            nodes
+                .into_iter()
+                .map(|node| match node {
+                    NodeModel::FeedbackRecorder(inner) => inner.failed,
+                    _ => panic!("every node is a FeedbackRecorder"),
+                })
+                .collect::<Vec<_>>()
+        };
+
+        assert!(failures(&scenario).iter().all(Vec::is_empty));
+
+        // Everyone for the broadcast, nobody for the message node 1 got, then the target
+        // and the group member
+        scenario.reception_feedback = true;
+        let mut failures = failures(&scenario);
+        failures[0][..2].sort();
+        assert_eq!(failures[0], [2, 3, 2, 3]);
+        assert!(failures[1..].iter().all(Vec::is_empty));
+    }
+}
@@ -20,13 +20,21 @@ pub struct AcknowledgedOrRepeatFlood {
     acknowledgements: HashSet<GlobalPacketId>,
     radio_interface: MeshtasticRadioInterface<BasicHeader>,
     next_packet_id: u32,
+    /// Gateways acknowledge messages they hear with an [`CustomContent::UplinkAck`]
+    /// instead of passing them on
+    #[serde(default)]
+    uplink_acks: bool,
 }
 
 impl ImplNodeModel for AcknowledgedOrRepeatFlood {
     type InnerHeader = BasicHeader;
 
     fn identity_str(&self) -> &str {
-        "Acknowledged Or Repeat Flood 1.1"
+        if self.uplink_acks {
+            "Acknowledged Or Repeat Flood 1.1 (uplink acks)"
+        } else {
+            "Acknowledged Or Repeat Flood 1.1"
+        }
     }
 
     fn initalisation(&mut self, mut context: Context) {
@@ -53,7 +61,7 @@ impl ImplNodeModel for AcknowledgedOrRepeatFlood {
 
         let key = packet.global_id();
 
-        if header.dest.is_to_node(context.node_id()) {
+        if header.dest.is_only_to_node(context.node_id()) {
             let ack_packet = StoredPacket {
                 header: BasicHeader {
                     dest: Destination::Broadcast,
@@ -75,9 +83,30 @@ impl ImplNodeModel for AcknowledgedOrRepeatFlood {
             return;
         }
 
+        if self.uplink_acks
+            && context.node_setting().is_gateway
+            && header.sender != context.node_id()
+            && let MessageContent::GeneratedMessage(_) = packet.message_content
+        {
+            let ack_packet = StoredPacket {
+                header: BasicHeader {
+                    dest: Destination::Broadcast,
+                    sender: context.node_id(),
+                    packet_id: self.next_packet_id(),
+                },
+                message_content: MessageContent::NodeMessage(CustomContent::UplinkAck { id: key }),
+                size: 0,
+                snr: None,
+            };
+
+            self.acknowledge(&mut context, key);
+            self.add_to_rebroadcasts(&mut context, ack_packet);
+            return;
+        }
+
         match &packet.message_content {
             simulation::MessageContent::NodeMessage(custom_content) => match custom_content {
-                super::CustomContent::GlobalAck { id } => {
+                super::CustomContent::GlobalAck { id } | super::CustomContent::UplinkAck { id } => {
                     self.remove_from_rebroadcasts(&mut context, *id);
                     self.acknowledge(&mut context, *id);
                 }
@@ -125,10 +154,11 @@ impl ImplNodeModel for AcknowledgedOrRepeatFlood {
         &mut self,
         mut context: Context,
         notification: super::Notification,
+        tag: u64,
         thread: super::NodeThread,
     ) {
         self.radio_interface
-            .on_get_notified(&mut context, notification, thread);
+            .on_get_notified(&mut context, notification, tag, thread);
 
         match notification {
             Notification::Routing => {
@@ -152,9 +182,17 @@ impl AcknowledgedOrRepeatFlood {
             acknowledgements: HashSet::new(),
             radio_interface: MeshtasticRadioInterface::new(),
             next_packet_id: 0,
+            uplink_acks: false,
         }
     }
 
+    /// Has gateways flood back an [`CustomContent::UplinkAck`] for each message they hear,
+    /// so nodes stop repeating it once it has left the mesh.
+    pub fn with_uplink_acks(mut self, uplink_acks: bool) -> Self {
+        self.uplink_acks = uplink_acks;
+        self
+    }
+
     fn next_packet_id(&mut self) -> u32 {
         let out = self.next_packet_id;
         self.next_packet_id += 1;
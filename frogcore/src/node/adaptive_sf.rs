@@ -0,0 +1,335 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    node::{MeshtasticHeader, meshtastic_header},
+    simulation::{
+        Context, NodeError,
+        data_structs::{LogContent, LogLevel},
+    },
+    units::{Db, Time},
+};
+
+use super::{
+    Destination, GlobalPacketId, ImplNodeModel, NodeThread, Notification, PeriodicBeacon,
+    StoredPacket, meshtastic::MeshtasticRadioInterface,
+};
+
+use serde::{Deserialize, Serialize};
+
+/// Snr kept above the read threshold when picking a spreading factor.
+/// Reported snr is clamped to no less than -15dB so this needs to be more than the 2.5dB
+/// between that and the SF11 threshold, otherwise weak links would never get SF12.
+const SNR_MARGIN: f64 = 3.0;
+
+/// How long the snr heard from a neighbour is relied on.
+const NEIGHBOUR_MEMORY: Time = Time::from_seconds(30.0 * 60.0);
+
+const FALLBACK_SF: i32 = 12;
+
+/// Flooding, like [`super::ProbabilisticFlood`] without dropping any rebroadcasts, where each
+/// transmission uses the lowest spreading factor the weakest link it is for can still read.
+///
+/// Links are only known for neighbours heard from directly, meaning packets that haven't been
+/// passed on yet, as the header doesn't say which node passed a packet on. The snr heard from
+/// a neighbour is assumed to be the same as the snr it hears this node with.
+///
+/// Packets addressed to a known neighbour use the link to that neighbour,
+/// everything else uses the weakest link heard from in the last [`NEIGHBOUR_MEMORY`].
+/// With no links known SF12 is used.
+///
+/// The radio scans every spreading factor, see [`Context::set_sf_scanning`], so neighbours
+/// don't need to agree on one to hear each other.
+///
+/// With a [`PeriodicBeacon`] nodes also learn of neighbours that have nothing to send.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AdaptiveSf {
+    seen: HashSet<GlobalPacketId>,
+    neighbours: HashMap<usize, HeardLink>,
+    radio_interface: MeshtasticRadioInterface<MeshtasticHeader>,
+    next_packet_id: u32,
+    #[serde(default)]
+    beacon: Option<PeriodicBeacon>,
+    identity: String,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+struct HeardLink {
+    snr: Db<f64>,
+    heard_at: Time,
+}
+
+impl ImplNodeModel for AdaptiveSf {
+    type InnerHeader = MeshtasticHeader;
+
+    fn identity_str(&self) -> &str {
+        &self.identity
+    }
+
+    fn initalisation(&mut self, mut context: Context) {
+        context.set_sf_scanning(true);
+        self.radio_interface.on_initalisation(&mut context);
+        if let Some(beacon) = &self.beacon {
+            beacon.on_initalisation(&mut context);
+        }
+    }
+
+    fn receive_message(
+        &mut self,
+        mut context: Context,
+        header: &Self::InnerHeader,
+        message_content: crate::simulation::MessageContent,
+        payload_size: i32,
+        snr: Db<f64>,
+    ) {
+        if header.hop_start == header.hop_limit {
+            let heard_at = context.clock_time();
+            self.neighbours
+                .insert(header.sender, HeardLink { snr, heard_at });
+        }
+
+        let packet = StoredPacket {
+            header: header.clone(),
+            message_content,
+            size: payload_size,
+            snr: Some(snr),
+        };
+
+        let key = packet.global_id();
+        if !self.seen.insert(key) {
+            return;
+        }
+
+        if !packet.header.dest.is_only_to_node(context.node_id()) && packet.header.hop_limit > 0 {
+            context.log(
+                || format!("Enqueuing rebroadcast for {key:?}"),
+                LogLevel::Info,
+            );
+
+            let mut rebroadcast_packet = packet;
+            rebroadcast_packet.header.hop_limit -= 1;
+            self.radio_interface.send(&mut context, rebroadcast_packet);
+        }
+    }
+
+    fn generate_message(
+        &mut self,
+        mut context: Context,
+        message_id: crate::simulation::MessageContent,
+        message_info: &crate::simulation::data_structs::MessageInfo,
+    ) {
+        let header = meshtastic_header(context.node_id(), self.next_packet_id(), message_info);
+
+        let packet = StoredPacket {
+            header,
+            // Acceptable to clone here because we know its not a custom content
+            message_content: message_id.clone(),
+            size: message_info.size,
+            snr: None,
+        };
+
+        context.log(
+            || {
+                format!(
+                    "Message {message_id:?} generated and enqueued as packet {:?}",
+                    packet.global_id()
+                )
+            },
+            LogLevel::Info,
+        );
+
+        self.seen.insert(packet.global_id());
+        self.radio_interface.send(&mut context, packet);
+    }
+
+    fn handle_error(&mut self, mut context: Context, error: NodeError) {
+        match error {
+            NodeError::RadioBusyError(_header, _content) => {
+                context.log(|| "Radio busy error".into(), LogLevel::Error)
+            }
+        }
+    }
+
+    fn get_notified(
+        &mut self,
+        mut context: Context,
+        notification: Notification,
+        tag: u64,
+        thread: NodeThread,
+    ) {
+        if let Some(beacon) = &mut self.beacon {
+            // Only heard by neighbours, which is all it is for
+            let sender = context.node_id();
+            let next_packet_id = &mut self.next_packet_id;
+            let header = || {
+                *next_packet_id += 1;
+                MeshtasticHeader {
+                    dest: Destination::Broadcast,
+                    sender,
+                    packet_id: *next_packet_id - 1,
+                    hop_limit: 0,
+                    hop_start: 0,
+                    want_ack: false,
+                }
+            };
+
+            let radio_interface = &mut self.radio_interface;
+            if beacon.on_get_notified(&mut context, radio_interface, thread, header) {
+                return;
+            }
+        }
+
+        // The radio interface may send the packet at the front of its queue now,
+        // so that's the one to pick the spreading factor for
+        if let Notification::TransmitDelayCompleted = notification
+            && let Some(packet) = self.radio_interface.next_to_send()
+        {
+            let dest = packet.header.dest;
+            self.choose_sf(&mut context, dest);
+        }
+
+        self.radio_interface
+            .on_get_notified(&mut context, notification, tag, thread);
+    }
+}
+
+impl Default for AdaptiveSf {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AdaptiveSf {
+    pub fn new() -> Self {
+        AdaptiveSf {
+            seen: HashSet::new(),
+            neighbours: HashMap::new(),
+            radio_interface: MeshtasticRadioInterface::new(),
+            next_packet_id: 0,
+            beacon: None,
+            identity: "Adaptive SF 1.0".into(),
+        }
+    }
+
+    pub fn with_beacon(mut self, beacon: PeriodicBeacon) -> Self {
+        self.identity = format!("Adaptive SF 1.0 ({})", beacon.describe());
+        self.beacon = Some(beacon);
+        self
+    }
+
+    fn next_packet_id(&mut self) -> u32 {
+        let out = self.next_packet_id;
+        self.next_packet_id += 1;
+        out
+    }
+
+    fn choose_sf(&self, context: &mut Context, dest: Destination) {
+        let link_snr = self.link_snr(context.clock_time(), dest);
+        let sf = link_snr.map_or(FALLBACK_SF, |snr| {
+            (7..FALLBACK_SF)
+                .find(|sf| snr.as_db_float() - SNR_MARGIN >= read_threshold(*sf))
+                .unwrap_or(FALLBACK_SF)
+        });
+
+        context
+            .change_sf(sf)
+            .expect("only picks spreading factors from 7 to 12");
+
+        let node_id = context.node_id();
+        context.log_content(
+            || LogContent::SpreadingFactorChosen {
+                node_id,
+                sf,
+                link_snr,
+            },
+            LogLevel::Debug,
+        );
+    }
+
+    /// Snr of the weakest link a packet to `dest` needs, if known.
+    fn link_snr(&self, now: Time, dest: Destination) -> Option<Db<f64>> {
+        let recent = |link: &&HeardLink| now - link.heard_at <= NEIGHBOUR_MEMORY;
+
+        if let Destination::Node(id) = dest
+            && let Some(link) = self.neighbours.get(&id).filter(recent)
+        {
+            return Some(link.snr);
+        }
+
+        self.neighbours
+            .values()
+            .filter(recent)
+            .map(|x| x.snr)
+            .min_by(Db::total_cmp)
+    }
+}
+
+/// Mirrors the read threshold the transmission models use.
+fn read_threshold(sf: i32) -> f64 {
+    -2.5 * (sf as f64) + 10.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        node::ModelSelection,
+        node_location::{Graph, NodeLocation},
+        scenario::{Scenario, ScenarioMessage},
+        simulation::{
+            models::{PairWiseCaptureEffect, adjusted_free_space_path_loss},
+            run_simulation,
+        },
+        units::{Length, METRES, SECONDS},
+    };
+
+    /// Spreading factors each node chose, in order, for two nodes `distance` apart
+    /// taking turns sending each other messages.
+    fn chosen_sfs(distance: Length) -> [Vec<(i32, Option<Db<f64>>)>; 2] {
+        let graph = Graph::uniform(vec![vec![1], vec![0]], distance);
+        let scenario = Scenario {
+            // Lossy enough that 10km can only be read at SF12
+            model: PairWiseCaptureEffect::default()
+                .with_pathloss(adjusted_free_space_path_loss(3.25).into())
+                .into(),
+            messages: (0..4)
+                .map(|n| {
+                    let time = (1.0 + 60.0 * n as f64) * SECONDS;
+                    ScenarioMessage::new(n % 2, vec![1 - n % 2], time, 60)
+                })
+                .collect(),
+            ..Scenario::for_test(NodeLocation::Graph(graph))
+        };
+
+        let output = run_simulation(1, scenario, ModelSelection::AdaptiveSf.into(), true);
+        let mut chosen = [Vec::new(), Vec::new()];
+        for log in output.logs {
+            if let LogContent::SpreadingFactorChosen {
+                node_id,
+                sf,
+                link_snr,
+            } = log.content
+            {
+                chosen[node_id].push((sf, link_snr));
+            }
+        }
+
+        chosen
+    }
+
+    #[test]
+    fn sf_follows_link_strength() {
+        let sfs =
+            |chosen: &Vec<(i32, Option<Db<f64>>)>| chosen.iter().map(|x| x.0).collect::<Vec<_>>();
+
+        // Node 0 goes first, knowing nothing about the link
+        let [first, second] = chosen_sfs(100.0 * METRES);
+        assert_eq!(first[0], (12, None));
+        assert_eq!(sfs(&first), vec![12, 7]);
+        assert_eq!(sfs(&second), vec![7, 7]);
+
+        let [first, second] = chosen_sfs(10_000.0 * METRES);
+        assert_eq!(sfs(&first), vec![12, 12]);
+        assert_eq!(sfs(&second), vec![12, 12]);
+        assert!(second.iter().all(|x| x.1.is_some()));
+    }
+}
@@ -6,7 +6,7 @@ use crate::{
 };
 
 use super::{
-    meshtastic::MeshtasticRadioInterface, GlobalPacketId, ImplNodeModel,
+    meshtastic::MeshtasticRadioInterface, GlobalPacketId, ImplNodeModel, RelayQueue,
     StoredPacket,
 };
 use serde::{Deserialize, Serialize};
@@ -15,6 +15,9 @@ use serde::{Deserialize, Serialize};
 pub struct BasicFlood {
     seen: HashSet<GlobalPacketId>,
     radio_interface: MeshtasticRadioInterface<BasicHeader>,
+    #[serde(default)]
+    relay_queue: RelayQueue,
+    identity: String,
     next_packet_id: u32,
 }
 
@@ -22,7 +25,7 @@ impl ImplNodeModel for BasicFlood {
     type InnerHeader = BasicHeader;
 
     fn identity_str(&self) -> &str {
-        "Basic Flood 2.0"
+        &self.identity
     }
 
     fn initalisation(&mut self, mut context: crate::simulation::Context) {
@@ -50,12 +53,13 @@ impl ImplNodeModel for BasicFlood {
             return;
         }
 
-        if !packet.header.dest.is_to_node(context.node_id()) {
+        if !packet.header.dest.is_only_to_node(context.node_id()) {
             context.log(
                 || format!("Enqueuing rebroadcast for {key:?}"),
                 LogLevel::Info,
             );
-            self.radio_interface.send(&mut context, packet);
+            self.relay_queue
+                .enqueue(&mut context, &mut self.radio_interface, packet);
         }
 
         self.seen.insert(key);
@@ -105,10 +109,13 @@ impl ImplNodeModel for BasicFlood {
         &mut self,
         mut context: crate::simulation::Context,
         notification: super::Notification,
+        tag: u64,
         thread: super::NodeThread,
     ) {
+        self.relay_queue
+            .on_get_notified(&mut context, &mut self.radio_interface, notification);
         self.radio_interface
-            .on_get_notified(&mut context, notification, thread);
+            .on_get_notified(&mut context, notification, tag, thread);
     }
 }
 
@@ -123,10 +130,22 @@ impl BasicFlood {
         BasicFlood {
             seen: HashSet::new(),
             radio_interface: MeshtasticRadioInterface::new(),
+            relay_queue: RelayQueue::default(),
+            identity: "Basic Flood 2.0".into(),
             next_packet_id: 0,
         }
     }
 
+    /// Bounds the rebroadcasts waiting to be sent, which are unbounded by default.
+    pub fn with_relay_queue(mut self, relay_queue: RelayQueue) -> Self {
+        self.identity = match relay_queue.describe() {
+            Some(bounds) => format!("Basic Flood 2.0 ({bounds})"),
+            None => "Basic Flood 2.0".into(),
+        };
+        self.relay_queue = relay_queue;
+        self
+    }
+
     fn next_packet_id(&mut self) -> u32 {
         let out = self.next_packet_id;
         self.next_packet_id += 1;
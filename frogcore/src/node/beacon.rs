@@ -0,0 +1,179 @@
+//! Components are pieces of node model behaviour that more than one model needs, such as
+//! [`MeshtasticRadioInterface`], [`RelayQueue`](super::RelayQueue) and [`PeriodicBeacon`].
+//!
+//! A component is a field of the node model that keeps its own state. Its hooks are named after
+//! the [`ImplNodeModel`](super::ImplNodeModel) method they are called from, so `on_initalisation`
+//! goes in `initalisation` and `on_get_notified` in `get_notified`, and the model uses it through
+//! its other methods. Each component's docs list the hooks it needs and any [`NodeThread`] it
+//! uses, which the rest of the model has to leave alone.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    simulation::{Context, MessageContent, data_structs::LogLevel},
+    units::{SECONDS, Time},
+};
+
+use super::{
+    BasicHeaderInfo, CustomContent, Header, NodeThread, Notification, StoredPacket,
+    meshtastic::MeshtasticRadioInterface,
+};
+
+/// What a [`PeriodicBeacon`] sends.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BeaconKind {
+    /// Lets neighbours know the node is there, carrying nothing else
+    Hello,
+}
+
+impl BeaconKind {
+    /// Returns the size in bytes
+    pub fn size(&self) -> i32 {
+        match self {
+            BeaconKind::Hello => 4,
+        }
+    }
+}
+
+/// Component transmitting a [`CustomContent::Beacon`] every `interval`, give or take up to
+/// `jitter`. The first goes a random point into the first interval so nodes started together
+/// don't all beacon at once.
+///
+/// To use this component:
+/// - Add the `PeriodicBeacon::on_initalisation` method to `initalisation`
+/// - Add the `PeriodicBeacon::on_get_notified` method in `get_notified`.
+///   It returns true for notifications that were for the beacon, which need nothing else doing
+///
+/// Beacons are sent through a [`MeshtasticRadioInterface`], with a header the node model makes.
+///
+/// This component uses the [`NodeThread::BeaconThread`]. Do not use this elsewhere in your
+/// node model.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PeriodicBeacon {
+    interval: Time,
+    jitter: Time,
+    kind: BeaconKind,
+    /// Beacons sent so far
+    #[serde(default)]
+    sent: u32,
+}
+
+impl PeriodicBeacon {
+    /// `jitter` is capped at `interval`.
+    pub fn new(interval: Time, jitter: Time, kind: BeaconKind) -> Self {
+        Self {
+            interval,
+            jitter: jitter.min(interval),
+            kind,
+            sent: 0,
+        }
+    }
+
+    /// The beacon's settings, for node models to put in their identity.
+    pub fn describe(&self) -> String {
+        format!("{:?} beacon every {} ± {}", self.kind, self.interval, self.jitter)
+    }
+
+    // Hooks
+
+    pub(super) fn on_initalisation(&self, context: &mut Context) {
+        context.register_thread(NodeThread::BeaconThread);
+
+        let first = context.rng(0.0, 1.0) * self.interval;
+        context.notify_later(first, Notification::InfoTimer, NodeThread::BeaconThread, true);
+    }
+
+    /// Sends a beacon with the header from `header` if the notification is for one,
+    /// and schedules the next.
+    pub(super) fn on_get_notified<T>(
+        &mut self,
+        context: &mut Context,
+        radio_interface: &mut MeshtasticRadioInterface<T>,
+        thread: NodeThread,
+        header: impl FnOnce() -> T,
+    ) -> bool
+    where
+        T: BasicHeaderInfo + Into<Header>,
+    {
+        if thread != NodeThread::BeaconThread {
+            return false;
+        }
+
+        let packet = StoredPacket {
+            header: header(),
+            message_content: MessageContent::NodeMessage(CustomContent::Beacon {
+                kind: self.kind,
+                sequence: self.sent,
+            }),
+            size: 0,
+            snr: None,
+        };
+
+        context.log(
+            || format!("Sending beacon {} as packet {:?}", self.sent, packet.global_id()),
+            LogLevel::Debug,
+        );
+
+        self.sent += 1;
+        radio_interface.send(context, packet);
+
+        let jitter = self.jitter.seconds();
+        let offset = if jitter > 0.0 {
+            context.rng(-jitter, jitter) * SECONDS
+        } else {
+            0.0 * SECONDS
+        };
+        context.notify_later(
+            self.interval + offset,
+            Notification::InfoTimer,
+            NodeThread::BeaconThread,
+            true,
+        );
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        node::AdaptiveSf,
+        scenario::Scenario,
+        simulation::{SIM_END, run_simulation},
+    };
+
+    const INTERVAL: Time = Time::from_seconds(60.0);
+    const JITTER: Time = Time::from_seconds(10.0);
+
+    #[test]
+    fn beacons_keep_to_interval() {
+        let scenario = Scenario::test_pair();
+
+        let beacon = PeriodicBeacon::new(INTERVAL, JITTER, BeaconKind::Hello);
+        let model = AdaptiveSf::new().with_beacon(beacon);
+        let output = run_simulation(1, scenario, model.into(), false);
+
+        // Allowing for the radio interface's random delay before each goes out
+        let slack = 1.0 * SECONDS;
+        for node in 0..2 {
+            let starts: Vec<Time> = output
+                .transmissions
+                .iter()
+                .filter(|x| x.transmitter_id == node)
+                .map(|x| x.start_time)
+                .collect();
+
+            let expected = SIM_END.seconds() / INTERVAL.seconds();
+            assert!((starts.len() as f64 - expected).abs() <= 2.0, "{}", starts.len());
+            assert!(starts[0] < INTERVAL + slack);
+            for gap in starts.windows(2).map(|x| x[1] - x[0]) {
+                assert!(gap > INTERVAL - JITTER - slack, "{gap}");
+                assert!(gap < INTERVAL + JITTER + slack, "{gap}");
+            }
+
+            // Each beacon is heard, so the neighbour is always known
+            assert_eq!(output.node_stats[1 - node].receptions, starts.len() as u64);
+        }
+    }
+}
@@ -6,7 +6,7 @@ use crate::{
         BasicHeaderInfo, MeshPendingPacket, MeshStoredPacket, MeshtasticHeader,
     },
     simulation::{
-        data_structs::{LogLevel, MessageInfo},
+        data_structs::{LogContent, LogLevel, MessageInfo},
         Context, MessageContent, NodeError,
     },
     units::*,
@@ -28,10 +28,12 @@ pub(super) const SNR_DIFF: f64 = SNR_MAX - SNR_MIN;
 // Consts from ReliableRouter.cpp
 pub(super) const NUM_RETRANSMISSIONS: i32 = 3;
 
-// TODO: We do not consider this apparent huge processing time and I guess we should do
-// NOTE: I think the time is exagerated here but more real life testing is needed.
-// Consts from RadioInterface
-pub(super) const PROCESSING_TIME: Time = Time::from_milis(4500.0);
+// Consts from airtime.h, as proportions rather than percentages
+const POLITE_CHANNEL_UTIL: f64 = 0.25;
+const MAX_CHANNEL_UTIL: f64 = 0.40;
+
+/// How often packets held back by utilisation gating are tried again.
+const DEFERRED_RETRY: Time = Time::from_seconds(5.0);
 
 fn slot_time(bandwidth: Frequency, sf: i32) -> Time {
     let adjustment = Time::from_milis(0.2 + 0.4 + 7.0);
@@ -50,6 +52,15 @@ pub struct Meshtastic {
     pending: HashMap<GlobalPacketId, MeshPendingPacket>,
     seen_recently: HashSet<GlobalPacketId>,
     next_packet_id: u32,
+    /// Gateways flood back a [`CustomContent::UplinkAck`] for each message they hear
+    #[serde(default)]
+    uplink_acks: bool,
+    /// Holds back packets while the channel is busy, see [`Meshtastic::with_utilisation_gating`]
+    #[serde(default)]
+    utilisation_gating: bool,
+    /// The node's own packets held back by utilisation gating, oldest first
+    #[serde(default)]
+    deferred: VecDeque<MeshStoredPacket>,
 }
 
 use serde::{Deserialize, Serialize};
@@ -60,7 +71,12 @@ impl ImplNodeModel for Meshtastic {
     type InnerHeader = MeshtasticHeader;
 
     fn identity_str(&self) -> &str {
-        "Meshtastic 1.2"
+        match (self.uplink_acks, self.utilisation_gating) {
+            (false, false) => "Meshtastic 1.2",
+            (true, false) => "Meshtastic 1.2 (uplink acks)",
+            (false, true) => "Meshtastic 1.2 (utilisation gating)",
+            (true, true) => "Meshtastic 1.2 (uplink acks, utilisation gating)",
+        }
     }
 
     fn initalisation(&mut self, mut context: Context) {
@@ -137,10 +153,11 @@ impl ImplNodeModel for Meshtastic {
         &mut self,
         mut context: Context,
         notification: Notification,
+        tag: u64,
         _thread: NodeThread,
     ) {
         self.radio_interface
-            .on_get_notified(&mut context, notification, _thread);
+            .on_get_notified(&mut context, notification, tag, _thread);
 
         match notification {
             Routing => {
@@ -165,9 +182,30 @@ impl Meshtastic {
             pending: HashMap::new(),
             seen_recently: HashSet::new(),
             next_packet_id: 0,
+            uplink_acks: false,
+            utilisation_gating: false,
+            deferred: VecDeque::new(),
         }
     }
 
+    /// Has gateways flood back a [`CustomContent::UplinkAck`] for each message they hear,
+    /// which stops the sender retransmitting it once it has left the mesh.
+    pub fn with_uplink_acks(mut self, uplink_acks: bool) -> Self {
+        self.uplink_acks = uplink_acks;
+        self
+    }
+
+    /// Has the node check the channel utilisation before each send, as recent firmware does with
+    /// `AirTime::isTxAllowedChannelUtil`. Acks and naks always go.
+    ///
+    /// Rebroadcasts are held to the polite limit of 25% and dropped above it, as the sender will
+    /// retry. The node's own messages stand in for the position and telemetry the firmware limits
+    /// to 40%, and are deferred until the channel is quieter rather than dropped.
+    pub fn with_utilisation_gating(mut self, utilisation_gating: bool) -> Self {
+        self.utilisation_gating = utilisation_gating;
+        self
+    }
+
     // Private Methods
 
     fn run_routing_thread(&mut self, context: &mut Context) {
@@ -176,6 +214,7 @@ impl Meshtastic {
 
         // STAGE ONE STUFF
         let delay = self.do_retransmissions(context);
+        let delay = delay.min(self.send_deferred(context));
 
         // STAGE TWO
         while let Some(packet) = self.from_radio_queue.pop_front() {
@@ -235,7 +274,7 @@ impl Meshtastic {
 
         if is_repeated {
             if !self.perhaps_rebroadcast(context, packet)
-                && packet.header.dest.is_to_node(context.node_id())
+                && packet.header.dest.is_to_node(context)
                 && packet.header.want_ack
             {
                 self.send_ack_nak(
@@ -274,13 +313,15 @@ impl Meshtastic {
     }
 
     fn perhaps_rebroadcast(&mut self, context: &mut Context, packet: &MeshStoredPacket) -> bool {
-        let to_us = packet.header.dest.is_to_node(context.node_id());
+        let to_us = packet.header.dest.is_only_to_node(context.node_id());
         let from_us = packet.header.sender == context.node_id();
 
         if !to_us && !from_us && packet.header.hop_limit > 0 {
             // We ignore the other conditions
             // They are not implemented in the sim
 
+            // Keeps the snr the packet was received with, so the radio interface weights the
+            // delay by it and far away nodes rebroadcast first, as RadioLibInterface does
             let mut send_packet = packet.clone();
 
             send_packet.header.hop_limit -= 1;
@@ -414,7 +455,7 @@ impl Meshtastic {
     }
 
     fn send_local(&mut self, context: &mut Context, packet: MeshStoredPacket) {
-        if packet.header.dest.is_to_node(context.node_id()) {
+        if packet.header.dest.is_only_to_node(context.node_id()) {
             // should be equiv to enqueueReceivedMessage call
             self.from_radio_queue.push_back(packet.clone());
             context.notify_later(
@@ -424,7 +465,8 @@ impl Meshtastic {
                 true,
             );
         } else {
-            if packet.header.dest.is_broadcast() {
+            // Groups we're in are for us as well as everyone else in them
+            if packet.header.dest.is_broadcast() || packet.header.dest.is_to_node(context) {
                 self.handle_received(context, &packet);
             }
 
@@ -457,16 +499,20 @@ impl Meshtastic {
     }
 
     fn base_send(&mut self, context: &mut Context, mut packet: MeshStoredPacket) {
-        if packet.header.dest.is_to_node(context.node_id()) {
+        if packet.header.dest.is_only_to_node(context.node_id()) {
             panic!("This shouldn't happen. Though maybe should panic either");
         }
 
+        if self.utilisation_gating && self.held_back(context, &packet) {
+            return;
+        }
+
         if self.violating_duty_cycle(context) {
             // TODO: Implement correct behaviour
             context.log(|| ("Violating Duty Cycle").to_string(), LogLevel::Error);
         }
 
-        if packet.header.dest.is_broadcast() {
+        if packet.header.dest.is_multicast() {
             packet.header.want_ack = false;
         }
 
@@ -479,6 +525,80 @@ impl Meshtastic {
         self.radio_interface.send(context, packet);
     }
 
+    /// Whether utilisation gating stops the packet being sent now,
+    /// deferring it if it's the node's own or dropping it otherwise.
+    fn held_back(&mut self, context: &mut Context, packet: &MeshStoredPacket) -> bool {
+        let own = packet.header.sender == context.node_id();
+        let limit = match &packet.message_content {
+            MessageContent::NodeMessage(
+                CustomContent::RoutingMessage { .. }
+                | CustomContent::GlobalAck { .. }
+                | CustomContent::UplinkAck { .. },
+            ) => return false,
+            _ if own => MAX_CHANNEL_UTIL,
+            _ => POLITE_CHANNEL_UTIL,
+        };
+
+        let utilisation = context.channel_utilisation();
+        if utilisation <= limit {
+            return false;
+        }
+
+        let node_id = context.node_id();
+        let id = packet.global_id();
+        context.log_content(
+            || LogContent::UtilisationGated {
+                node_id,
+                packet: id,
+                utilisation,
+                deferred: own,
+            },
+            LogLevel::Info,
+        );
+
+        if own {
+            // A retransmission replaces the copy already waiting
+            self.deferred.retain(|x| x.global_id() != id);
+            self.deferred.push_back(packet.clone());
+            context.notify_later(
+                Time::from_milis(1.0),
+                Routing,
+                NodeThread::RoutingThread,
+                false,
+            );
+        }
+
+        true
+    }
+
+    /// Tries the packets held back by utilisation gating again,
+    /// returning how long to wait before the next try.
+    fn send_deferred(&mut self, context: &mut Context) -> Time {
+        if self.deferred.is_empty() {
+            return Time::from_imilis(i32::MAX);
+        }
+
+        // Saves logging each of them being deferred again
+        if context.channel_utilisation() > MAX_CHANNEL_UTIL {
+            return DEFERRED_RETRY;
+        }
+
+        for packet in std::mem::take(&mut self.deferred) {
+            // Acked or given up on while it waited
+            if packet.header.want_ack && !self.pending.contains_key(&packet.global_id()) {
+                continue;
+            }
+
+            self.base_send(context, packet);
+        }
+
+        if self.deferred.is_empty() {
+            Time::from_imilis(i32::MAX)
+        } else {
+            DEFERRED_RETRY
+        }
+    }
+
     fn violating_duty_cycle(&mut self, _context: &mut Context) -> bool {
         // TODO
         false
@@ -495,6 +615,10 @@ impl Meshtastic {
     fn handle_received(&mut self, context: &mut Context, packet: &MeshStoredPacket) {
         context.log(|| format!("Received {:?}", packet), LogLevel::Info);
 
+        if self.uplink_acks {
+            self.handle_uplink(context, packet);
+        }
+
         //I'm pretty sure the only module that needs taking into account is the
         // routing module but I could be wrong
 
@@ -502,6 +626,40 @@ impl Meshtastic {
         self.routing_module_handle_received(context, packet);
     }
 
+    /// Gateways ack messages from other nodes, and senders stop retransmitting once acked.
+    fn handle_uplink(&mut self, context: &mut Context, packet: &MeshStoredPacket) {
+        match &packet.message_content {
+            MessageContent::GeneratedMessage(_)
+                if context.node_setting().is_gateway
+                    && packet.header.sender != context.node_id() =>
+            {
+                let ack = StoredPacket {
+                    header: MeshtasticHeader {
+                        dest: Broadcast,
+                        sender: context.node_id(),
+                        packet_id: self.next_packet_id(),
+                        hop_limit: DEFAULT_HOP_LIMIT,
+                        hop_start: DEFAULT_HOP_LIMIT,
+                        want_ack: false,
+                    },
+                    message_content: MessageContent::NodeMessage(CustomContent::UplinkAck {
+                        id: packet.global_id(),
+                    }),
+                    size: 0,
+                    snr: None,
+                };
+
+                self.send_local(context, ack);
+            }
+            MessageContent::NodeMessage(CustomContent::UplinkAck { id })
+                if id.node_id == context.node_id() =>
+            {
+                self.stop_retransmission(context, *id);
+            }
+            _ => (),
+        }
+    }
+
     fn routing_module_handle_received(&mut self, context: &mut Context, packet: &MeshStoredPacket) {
         // Ignoring foreign mesh stuff. Assume everything is from a single mesh
         // maybe eventually account for forign meshes but idk
@@ -531,7 +689,7 @@ impl Meshtastic {
     }
 
     fn reliable_sniff_received(&mut self, context: &mut Context, packet: &MeshStoredPacket) {
-        let is_to_us = packet.header.dest.is_to_node(context.node_id());
+        let is_to_us = packet.header.dest.is_to_node(context);
 
         let routing_content = match &packet.message_content {
             MessageContent::NodeMessage(custom_content) => match custom_content {
@@ -615,9 +773,9 @@ impl Meshtastic {
         packet: &MeshStoredPacket,
         was_ack_or_reply: bool,
     ) {
-        let is_to_us = packet.header.dest.is_to_node(context.node_id());
+        let is_to_us = packet.header.dest.is_only_to_node(context.node_id());
 
-        if was_ack_or_reply && !is_to_us && !packet.header.dest.is_broadcast() {
+        if was_ack_or_reply && !is_to_us && !packet.header.dest.is_multicast() {
             self.radio_interface
                 .cancel_sending(context, packet.global_id());
         }
@@ -645,7 +803,7 @@ impl Meshtastic {
         2.0 * airtime
             + Time::from_milis(2f64.powi(window_size) + 2.0 * CW_MAX as f64)
             + 2f64.powi(CW_MAX + CW_MIN / 2) * slot_time(settings.bandwidth, settings.sf)
-            + PROCESSING_TIME
+            + settings.processing_time
     }
 }
 
@@ -685,14 +843,25 @@ where
         &mut self,
         context: &mut Context,
         notification: Notification,
+        tag: u64,
         _thread: NodeThread,
     ) {
         match notification {
             TransmitDelayCompleted => {
                 if self.tx_queue.is_empty() == false {
+                    // Not in the firmware. If the packet this delay was for has been cancelled
+                    // the new head of the queue gets its own delay rather than going straight away
+                    let cancelled = !self.tx_queue.iter().any(|x| Self::packet_tag(x) == tag);
+                    if cancelled {
+                        context.log(
+                            || format!("Ignoring transmit delay for cancelled packet {tag}"),
+                            LogLevel::Debug,
+                        );
+                    }
+
                     // Some chance channel_in_use is not correct here
                     // Possibly needs to be receiving a packet not just detecting use
-                    if context.is_transmitting() || context.channel_in_use() {
+                    if cancelled || context.is_transmitting() || context.channel_in_use() {
                         self.set_transmit_delay(context);
                     } else {
                         let packet = self
@@ -721,31 +890,52 @@ where
         }
     }
 
+    /// The packet that will be sent next, if any.
+    pub(super) fn next_to_send(&self) -> Option<&StoredPacket<T>> {
+        self.tx_queue.front()
+    }
+
+    /// Whether the packet is still waiting to be sent.
+    pub(super) fn is_queued(&self, key: GlobalPacketId) -> bool {
+        self.tx_queue
+            .iter()
+            .any(|x| x.header.sender() == key.node_id && x.header.packet_id() == key.packet_id)
+    }
+
+    /// Identifies a queued packet in the tag of the transmit delay notification.
+    fn packet_tag(packet: &StoredPacket<T>) -> u64 {
+        ((packet.header.sender() as u64) << 32) | packet.header.packet_id() as u64
+    }
+
     fn set_transmit_delay(&mut self, context: &mut Context) {
         let Some(packet) = self.tx_queue.front() else {
             return;
         };
 
-        match packet.snr {
-            Some(inner) => {
-                let delay = Self::get_weighted_tx_delay(inner, context);
-                context.notify_later(
-                    delay,
-                    TransmitDelayCompleted,
-                    NodeThread::RadioThread,
-                    false,
-                );
-            }
-            None => {
-                let delay = Self::get_tx_delay(context);
-                context.notify_later(
-                    delay,
-                    TransmitDelayCompleted,
-                    NodeThread::RadioThread,
-                    false,
-                );
-            }
-        }
+        let tag = Self::packet_tag(packet);
+        let snr = packet.snr;
+        let delay = match snr {
+            Some(inner) => Self::get_weighted_tx_delay(inner, context),
+            None => Self::get_tx_delay(context),
+        };
+
+        let node_id = context.node_id();
+        context.log_content(
+            || LogContent::TransmitDelay {
+                node_id,
+                snr,
+                delay,
+            },
+            LogLevel::Debug,
+        );
+
+        context.notify_later_tagged(
+            delay,
+            TransmitDelayCompleted,
+            tag,
+            NodeThread::RadioThread,
+            false,
+        );
     }
 
     fn get_weighted_tx_delay(snr: Db<f64>, context: &mut Context) -> Time {
@@ -799,3 +989,138 @@ where
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        node::NodeModel,
+        scenario::{
+            Scenario, ScenarioIdentity,
+            generation::{
+                ScenarioGenerator, SfAssignment, messaging::IndependentRandomMessaging,
+                positioning::IndependentPositionFrames,
+            },
+        },
+        sim_file::SimOutput,
+        simulation::{models::PairWiseCaptureEffect, run_simulation},
+    };
+
+    /// Proportion of the messages sent to a single node that their sender heard acked
+    /// within `deadline` of the message being generated.
+    fn ack_success(output: &SimOutput, scenario: &Scenario, deadline: Time) -> f64 {
+        let heard: HashMap<_, _> = output
+            .logs
+            .iter()
+            .filter_map(|x| match x.content {
+                LogContent::TransmissionReceived {
+                    receiver_id,
+                    transmission_id,
+                    ..
+                } => Some(((receiver_id, transmission_id), x.time)),
+                _ => None,
+            })
+            .collect();
+
+        let mut generated = HashMap::new();
+        let mut acked = HashMap::new();
+        for transmission in &output.transmissions {
+            let Header::Meshtastic(header) = &transmission.header else {
+                continue;
+            };
+            match transmission.message_content {
+                MessageContent::GeneratedMessage(id)
+                    if header.sender == transmission.transmitter_id
+                        && matches!(header.dest, Node(_)) =>
+                {
+                    let generate_time = scenario.messages[id].generate_time;
+                    generated.insert((header.sender, header.packet_id), generate_time);
+                }
+                MessageContent::NodeMessage(CustomContent::RoutingMessage {
+                    status: RoutingStatus::NotError,
+                    about_id,
+                }) => {
+                    let Node(sender) = header.dest else {
+                        continue;
+                    };
+                    if let Some(&time) = heard.get(&(sender, transmission.id)) {
+                        acked.entry((sender, about_id)).or_insert(time);
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        let in_time = generated
+            .iter()
+            .filter(|(key, generate_time)| {
+                acked
+                    .get(key)
+                    .is_some_and(|&time| time - **generate_time <= deadline)
+            })
+            .count();
+        in_time as f64 / generated.len() as f64
+    }
+
+    #[test]
+    fn utilisation_gating_eases_congestion() {
+        // Everyone in range of everyone else, sending more than the channel can carry
+        let scenario = ScenarioIdentity::Generated {
+            generator: ScenarioGenerator::RandomSquare {
+                node_count: 20,
+                gateway_count: 0,
+                gateways_move: false,
+                positioning: IndependentPositionFrames {
+                    side_len: 2.0 * KM,
+                    position_count: 1,
+                    movement_timespan: 5.0 * MINS,
+                },
+                messaging: IndependentRandomMessaging {
+                    message_count: 200,
+                    messaging_timespan: 3.0 * MINS,
+                    mean_message_size: 150.0,
+                    std_message_size: 30.0,
+                    broadcast_chance: 0.5,
+                    gateway_priority: 0.0,
+                },
+                model: PairWiseCaptureEffect::default().into(),
+                reaction_times: None,
+                sf_assignment: SfAssignment::default(),
+            },
+            seed: 1,
+        }
+        .create();
+
+        let run = |gating| {
+            let model: NodeModel = Meshtastic::new().with_utilisation_gating(gating).into();
+            run_simulation(1, scenario.clone(), model, true)
+        };
+        let (ungated, gated) = (run(false), run(true));
+
+        let gated_as = |output: &SimOutput, deferred: bool| {
+            output
+                .logs
+                .iter()
+                .filter(|x| {
+                    matches!(
+                        x.content,
+                        LogContent::UtilisationGated { deferred: d, .. } if d == deferred
+                    )
+                })
+                .count()
+        };
+        assert!(gated_as(&gated, true) > 0 && gated_as(&gated, false) > 0);
+        assert_eq!(gated_as(&ungated, true) + gated_as(&ungated, false), 0);
+
+        let (sent, gated_sent) = (ungated.transmissions.len(), gated.transmissions.len());
+        assert!(gated_sent * 2 < sent, "{gated_sent} sent with gating, {sent} without");
+
+        // Without gating the queues grow so long that hardly anything is acked in time
+        let in_time = |output| ack_success(output, &scenario, 60.0 * SECONDS);
+        let (acked, gated_acked) = (in_time(&ungated), in_time(&gated));
+        assert!(
+            gated_acked > acked + 0.1,
+            "{gated_acked} acked in time with gating, {acked} without"
+        );
+    }
+}
@@ -59,6 +59,7 @@ impl ImplNodeModel for NoRouting {
         &mut self,
         _context: crate::simulation::Context,
         _notification: super::Notification,
+        _tag: u64,
         _thread: super::NodeThread,
     ) {
     }
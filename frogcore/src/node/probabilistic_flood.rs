@@ -3,7 +3,7 @@ use std::collections::HashSet;
 use crate::{node::{meshtastic_header, MeshtasticHeader}, simulation::{data_structs::LogLevel, NodeError}};
 
 use super::{
-    meshtastic::MeshtasticRadioInterface, GlobalPacketId, ImplNodeModel,
+    meshtastic::MeshtasticRadioInterface, GlobalPacketId, ImplNodeModel, RelayQueue,
     StoredPacket,
 };
 use serde::{Deserialize, Serialize};
@@ -12,6 +12,9 @@ use serde::{Deserialize, Serialize};
 pub struct ProbabilisticFlood {
     seen: HashSet<GlobalPacketId>,
     radio_interface: MeshtasticRadioInterface<MeshtasticHeader>,
+    #[serde(default)]
+    relay_queue: RelayQueue,
+    identity: String,
     next_packet_id: u32,
 }
 
@@ -23,7 +26,7 @@ const REBROADCAST_PROB: f64 = 0.65;
 impl ImplNodeModel for ProbabilisticFlood {
     type InnerHeader = MeshtasticHeader;
     fn identity_str(&self) -> &str {
-        "Probabilistic Flood"
+        &self.identity
     }
 
     fn initalisation(&mut self, mut context: crate::simulation::Context) {
@@ -52,7 +55,7 @@ impl ImplNodeModel for ProbabilisticFlood {
             return;
         }
 
-        if !packet.header.dest.is_to_node(context.node_id()) {
+        if !packet.header.dest.is_only_to_node(context.node_id()) {
             let drop_packet: f64 =
                 if (packet.header.hop_start - packet.header.hop_limit) >= MIN_HOPS {
                     context.rng(0.0, 1.0)
@@ -67,7 +70,8 @@ impl ImplNodeModel for ProbabilisticFlood {
                 );
                 let mut rebroadcast_packet = packet.clone();
                 rebroadcast_packet.header.hop_limit -= 1;
-                self.radio_interface.send(&mut context, rebroadcast_packet);
+                self.relay_queue
+                    .enqueue(&mut context, &mut self.radio_interface, rebroadcast_packet);
             } else {
                 context.log(
                     || format!("Probabilistically dropping rebroadcast for {key:?} at {node_id}"),
@@ -123,10 +127,13 @@ impl ImplNodeModel for ProbabilisticFlood {
         &mut self,
         mut context: crate::simulation::Context,
         notification: super::Notification,
+        tag: u64,
         thread: super::NodeThread,
     ) {
+        self.relay_queue
+            .on_get_notified(&mut context, &mut self.radio_interface, notification);
         self.radio_interface
-            .on_get_notified(&mut context, notification, thread);
+            .on_get_notified(&mut context, notification, tag, thread);
     }
 }
 impl Default for ProbabilisticFlood {
@@ -139,10 +146,22 @@ impl ProbabilisticFlood {
         ProbabilisticFlood {
             seen: HashSet::new(),
             radio_interface: MeshtasticRadioInterface::new(),
+            relay_queue: RelayQueue::default(),
+            identity: "Probabilistic Flood".into(),
             next_packet_id: 0,
         }
     }
 
+    /// Bounds the rebroadcasts waiting to be sent, which are unbounded by default.
+    pub fn with_relay_queue(mut self, relay_queue: RelayQueue) -> Self {
+        self.identity = match relay_queue.describe() {
+            Some(bounds) => format!("Probabilistic Flood ({bounds})"),
+            None => "Probabilistic Flood".into(),
+        };
+        self.relay_queue = relay_queue;
+        self
+    }
+
     fn next_packet_id(&mut self) -> u32 {
         let out = self.next_packet_id;
         self.next_packet_id += 1;
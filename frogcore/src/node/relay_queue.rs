@@ -0,0 +1,209 @@
+use std::collections::VecDeque;
+
+use crate::{
+    simulation::{Context, data_structs::RelayDropReason},
+    units::Time,
+};
+
+use super::{
+    BasicHeaderInfo, GlobalPacketId, Header, Notification, StoredPacket,
+    meshtastic::MeshtasticRadioInterface,
+};
+
+use serde::{Deserialize, Serialize};
+
+/// Component bounding the rebroadcasts waiting in a [`MeshtasticRadioInterface`] queue.
+/// Once more than `max_len` are waiting the oldest is dropped,
+/// and any that have waited longer than `max_age` are dropped before the next one is sent.
+/// Packets the node generates itself go straight to the radio interface so are never dropped.
+///
+/// To use this component:
+/// - Use `RelayQueue::enqueue` rather than `MeshtasticRadioInterface::send` for rebroadcasts
+/// - Add the `RelayQueue::on_get_notified` method in `get_notified`,
+///   before `MeshtasticRadioInterface::on_get_notified`
+///
+/// Drops are recorded with [`Context::relay_dropped`].
+/// Without either bound, the default, it does nothing but pass rebroadcasts on.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RelayQueue {
+    #[serde(default)]
+    max_len: Option<usize>,
+    #[serde(default)]
+    max_age: Option<Time>,
+    /// Rebroadcasts that may still be in the radio interface queue and when they were added,
+    /// oldest first
+    #[serde(default)]
+    waiting: VecDeque<(GlobalPacketId, Time)>,
+}
+
+impl RelayQueue {
+    /// `None` means no limit on that bound.
+    pub fn new(max_len: Option<usize>, max_age: Option<Time>) -> Self {
+        Self {
+            max_len,
+            max_age,
+            waiting: VecDeque::new(),
+        }
+    }
+
+    /// The bounds, for node models to put in their identity. `None` when there aren't any.
+    pub fn describe(&self) -> Option<String> {
+        match (self.max_len, self.max_age) {
+            (None, None) => None,
+            (Some(len), None) => Some(format!("relay queue of {len}")),
+            (None, Some(age)) => Some(format!("relays kept {age}")),
+            (Some(len), Some(age)) => Some(format!("relay queue of {len} kept {age}")),
+        }
+    }
+
+    fn is_bounded(&self) -> bool {
+        self.max_len.is_some() || self.max_age.is_some()
+    }
+
+    // Hooks
+
+    pub(super) fn on_get_notified<T>(
+        &mut self,
+        context: &mut Context,
+        radio_interface: &mut MeshtasticRadioInterface<T>,
+        notification: Notification,
+    ) where
+        T: BasicHeaderInfo + Into<Header>,
+    {
+        // The radio interface may send the packet at the front of its queue now
+        if let Notification::TransmitDelayCompleted = notification {
+            self.drop_stale(context, radio_interface);
+        }
+    }
+
+    // Other Functions
+
+    pub(super) fn enqueue<T>(
+        &mut self,
+        context: &mut Context,
+        radio_interface: &mut MeshtasticRadioInterface<T>,
+        packet: StoredPacket<T>,
+    ) where
+        T: BasicHeaderInfo + Into<Header>,
+    {
+        if !self.is_bounded() {
+            radio_interface.send(context, packet);
+            return;
+        }
+
+        self.drop_stale(context, radio_interface);
+
+        let now = context.clock_time();
+        self.waiting.push_back((packet.global_id(), now));
+        radio_interface.send(context, packet);
+
+        let max_len = self.max_len.unwrap_or(usize::MAX);
+        while self.waiting.len() > max_len {
+            let (key, queued_at) = self.waiting.pop_front().expect("longer than max_len");
+            radio_interface.cancel_sending(context, key);
+            context.relay_dropped(key, RelayDropReason::QueueFull, now - queued_at);
+        }
+    }
+
+    /// Forgets rebroadcasts that have been sent and drops any that have waited too long.
+    fn drop_stale<T>(
+        &mut self,
+        context: &mut Context,
+        radio_interface: &mut MeshtasticRadioInterface<T>,
+    ) where
+        T: BasicHeaderInfo + Into<Header>,
+    {
+        self.waiting
+            .retain(|(key, _)| radio_interface.is_queued(*key));
+
+        let Some(max_age) = self.max_age else {
+            return;
+        };
+
+        let now = context.clock_time();
+        while let Some(&(key, queued_at)) = self.waiting.front()
+            && now - queued_at > max_age
+        {
+            self.waiting.pop_front();
+            radio_interface.cancel_sending(context, key);
+            context.relay_dropped(key, RelayDropReason::TooOld, now - queued_at);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        analysis::CompleteAnalysis,
+        node::BasicFlood,
+        node_location::{Graph, NodeLocation},
+        scenario::{Scenario, ScenarioMessage},
+        sim_file::{NodeStats, SimOutput},
+        simulation::{data_structs::LogContent, run_simulation},
+        units::{METRES, SECONDS},
+    };
+
+    const MAX_AGE: Time = Time::from_seconds(20.0);
+
+    /// Node 0 sends a burst of messages to node 2 which node 1 has to pass on,
+    /// while competing with node 0 for the channel.
+    fn burst_through_relay(relay_queue: RelayQueue) -> (SimOutput, Scenario) {
+        let scenario = Scenario {
+            messages: (0..30)
+                .map(|_| ScenarioMessage::new(0, vec![2], 1.0 * SECONDS, 200))
+                .collect(),
+            ..Scenario::for_test(NodeLocation::Graph(Graph::line(3, 100.0 * METRES)))
+        };
+
+        let model = BasicFlood::new().with_relay_queue(relay_queue);
+        let output = run_simulation(1, scenario.clone(), model.into(), true);
+        (output, scenario)
+    }
+
+    #[test]
+    fn old_relays_are_dropped() {
+        let (unbounded, _) = burst_through_relay(RelayQueue::default());
+        assert!(unbounded.node_stats.iter().all(|x| x.relay_drops == 0));
+
+        let (output, scenario) = burst_through_relay(RelayQueue::new(None, Some(MAX_AGE)));
+        let drops: Vec<_> = output
+            .logs
+            .iter()
+            .filter_map(|x| match x.content {
+                LogContent::RelayDropped {
+                    node_id,
+                    reason,
+                    waited,
+                    ..
+                } => Some((node_id, reason, waited)),
+                _ => None,
+            })
+            .collect();
+
+        assert!(!drops.is_empty());
+        assert_eq!(output.node_stats[1].relay_drops, drops.len() as u64);
+        for (node_id, reason, waited) in drops {
+            assert_eq!(node_id, 1);
+            assert_eq!(reason, RelayDropReason::TooOld);
+            assert!(waited > MAX_AGE);
+        }
+
+        let relayed = |output: &SimOutput| {
+            output
+                .transmissions
+                .iter()
+                .filter(|x| x.transmitter_id == 1)
+                .count()
+        };
+        assert!(relayed(&output) < relayed(&unbounded));
+
+        // Also picked up from the logs of outputs without node stats
+        let mut from_logs = output.clone();
+        from_logs.node_stats.clear();
+        let analysis = CompleteAnalysis::new(from_logs, scenario);
+        let drops_per_node = |stats: &[NodeStats]| stats.iter().map(|x| x.relay_drops).collect();
+        let expected: Vec<_> = drops_per_node(&output.node_stats);
+        assert_eq!(drops_per_node(&analysis.node_stats), expected);
+    }
+}
@@ -53,7 +53,7 @@ impl ImplNodeModel for SimpleManagedFlooding {
             return;
         }
 
-        if !packet.header.dest.is_to_node(context.node_id()) && !self.sent.contains(&key) {
+        if !packet.header.dest.is_only_to_node(context.node_id()) && !self.sent.contains(&key) {
             context.log(
                 || format!("Enqueuing rebroadcast for {key:?}"),
                 LogLevel::Info,
@@ -113,10 +113,11 @@ impl ImplNodeModel for SimpleManagedFlooding {
         &mut self,
         mut context: crate::simulation::Context,
         notification: super::Notification,
+        tag: u64,
         thread: super::NodeThread,
     ) {
         self.radio_interface
-            .on_get_notified(&mut context, notification, thread);
+            .on_get_notified(&mut context, notification, tag, thread);
     }
 }
 impl Default for SimpleManagedFlooding {
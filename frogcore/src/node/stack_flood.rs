@@ -47,7 +47,7 @@ impl ImplNodeModel for StackFlood {
             return;
         }
 
-        if !packet.header.dest.is_to_node(context.node_id()) {
+        if !packet.header.dest.is_only_to_node(context.node_id()) {
             context.log(
                 || format!("Enqueuing rebroadcast for {key:?}"),
                 LogLevel::Info,
@@ -102,10 +102,11 @@ impl ImplNodeModel for StackFlood {
         &mut self,
         mut context: crate::simulation::Context,
         notification: super::Notification,
+        tag: u64,
         thread: super::NodeThread,
     ) {
         self.radio_interface
-            .on_get_notified(&mut context, notification, thread);
+            .on_get_notified(&mut context, notification, tag, thread);
     }
 }
 impl Default for StackFlood {
@@ -0,0 +1,320 @@
+use std::cmp::Ordering;
+
+use crate::{
+    node::{BasicHeader, basic_header},
+    simulation::{Context, NodeError, data_structs::LogLevel},
+    units::{SECONDS, Time},
+};
+
+use super::{
+    BasicStoredPacket, GlobalPacketId, ImplNodeModel, NodeThread, Notification, StoredPacket,
+    meshtastic::MeshtasticRadioInterface,
+};
+
+use serde::{Deserialize, Serialize};
+
+/// Tag of the notification for the point in the interval to transmit at
+const TRANSMIT_POINT: u64 = 0;
+/// Tag of the notification for the end of the interval
+const INTERVAL_END: u64 = 1;
+
+/// Disseminates messages with the Trickle algorithm from RFC 6206.
+///
+/// Each node keeps the newest message it knows about and transmits it once per interval,
+/// at a random point in the second half, unless it has already heard `k` neighbours
+/// transmit the same message. The interval doubles each time it ends, up to
+/// `i_min * 2^i_max_doublings`, and goes back to `i_min` when the node hears something
+/// inconsistent, either a newer message or a neighbour that is behind.
+///
+/// Messages are versioned by their packet id, which a node picks to be one more than the
+/// newest it knows, ties being broken by sender. So like any Trickle dissemination a newer
+/// message supersedes older ones, and a message generated at the same time as a newer one
+/// elsewhere may not reach everyone.
+///
+/// Messages are disseminated to every node whatever their destination,
+/// except that a node doesn't pass on a message addressed only to itself.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Trickle {
+    /// Shortest interval
+    i_min: Time,
+    /// Number of times the interval can double
+    i_max_doublings: u32,
+    /// Redundancy constant
+    k: u32,
+
+    identity: String,
+    radio_interface: MeshtasticRadioInterface<BasicHeader>,
+    newest: Option<BasicStoredPacket>,
+    /// Packet waiting in the radio interface queue, if any
+    queued: Option<GlobalPacketId>,
+    /// `None` until the node has something to disseminate
+    interval: Option<Time>,
+    interval_end: Time,
+    /// Consistent transmissions heard this interval
+    counter: u32,
+}
+
+impl ImplNodeModel for Trickle {
+    type InnerHeader = BasicHeader;
+
+    fn identity_str(&self) -> &str {
+        &self.identity
+    }
+
+    fn initalisation(&mut self, mut context: Context) {
+        self.radio_interface.on_initalisation(&mut context);
+        context.register_thread(NodeThread::TrickleThread);
+    }
+
+    fn receive_message(
+        &mut self,
+        mut context: Context,
+        header: &Self::InnerHeader,
+        message_content: crate::simulation::MessageContent,
+        payload_size: i32,
+        _snr: crate::units::Db<f64>,
+    ) {
+        // Sent with the unweighted delay when passed on, as Trickle does its own timing
+        let packet = StoredPacket {
+            header: header.clone(),
+            message_content,
+            size: payload_size,
+            snr: None,
+        };
+
+        let heard = Self::version(&packet);
+        match self.newest.as_ref().map(|x| Self::version(x).cmp(&heard)) {
+            Some(Ordering::Equal) => self.counter += 1,
+            Some(Ordering::Greater) => {
+                context.log(
+                    || format!("Heard older {:?}", packet.global_id()),
+                    LogLevel::Debug,
+                );
+                self.reset(&mut context);
+            }
+            None | Some(Ordering::Less) => {
+                context.log(
+                    || format!("Heard newer {:?}", packet.global_id()),
+                    LogLevel::Info,
+                );
+                self.newest = Some(packet);
+                self.reset(&mut context);
+            }
+        }
+    }
+
+    fn generate_message(
+        &mut self,
+        mut context: Context,
+        message_id: crate::simulation::MessageContent,
+        message_info: &crate::simulation::data_structs::MessageInfo,
+    ) {
+        let version = self.newest.as_ref().map_or(0, |x| x.header.packet_id + 1);
+        let packet = StoredPacket {
+            header: basic_header(context.node_id(), version, message_info),
+            // Acceptable to clone here because we know its not a custom content
+            message_content: message_id.clone(),
+            size: message_info.size,
+            snr: None,
+        };
+
+        context.log(
+            || {
+                format!(
+                    "Message {message_id:?} generated as packet {:?}",
+                    packet.global_id()
+                )
+            },
+            LogLevel::Info,
+        );
+
+        self.newest = Some(packet);
+        self.reset(&mut context);
+    }
+
+    fn handle_error(&mut self, mut context: Context, error: NodeError) {
+        match error {
+            NodeError::RadioBusyError(_header, _content) => {
+                context.log(|| "Radio busy error".into(), LogLevel::Error)
+            }
+        }
+    }
+
+    fn get_notified(
+        &mut self,
+        mut context: Context,
+        notification: Notification,
+        tag: u64,
+        thread: NodeThread,
+    ) {
+        if thread != NodeThread::TrickleThread {
+            self.radio_interface
+                .on_get_notified(&mut context, notification, tag, thread);
+            return;
+        }
+
+        match tag {
+            TRANSMIT_POINT => self.transmit_point(&mut context),
+            _ => self.interval_ended(&mut context),
+        }
+    }
+}
+
+impl Default for Trickle {
+    fn default() -> Self {
+        Self::new(10.0 * SECONDS, 8, 1)
+    }
+}
+
+impl Trickle {
+    pub fn new(i_min: Time, i_max_doublings: u32, k: u32) -> Self {
+        Trickle {
+            i_min,
+            i_max_doublings,
+            k,
+            identity: format!("Trickle 1.0 (i_min {i_min}, doublings {i_max_doublings}, k {k})"),
+            radio_interface: MeshtasticRadioInterface::new(),
+            newest: None,
+            queued: None,
+            interval: None,
+            interval_end: Time::from_seconds(0.0),
+            counter: 0,
+        }
+    }
+
+    /// Newer messages compare greater.
+    fn version(packet: &BasicStoredPacket) -> (u32, usize) {
+        (packet.header.packet_id, packet.header.sender)
+    }
+
+    fn i_max(&self) -> Time {
+        2f64.powi(self.i_max_doublings as i32) * self.i_min
+    }
+
+    /// Goes back to the shortest interval, or starts the first one.
+    /// Does nothing if already on the shortest interval.
+    fn reset(&mut self, context: &mut Context) {
+        if self.interval.is_some_and(|x| x <= self.i_min) {
+            return;
+        }
+
+        self.interval = Some(self.i_min);
+        self.start_interval(context);
+    }
+
+    fn start_interval(&mut self, context: &mut Context) {
+        let interval = self
+            .interval
+            .expect("only started once there is an interval");
+
+        self.counter = 0;
+        self.interval_end = context.clock_time() + interval;
+
+        let transmit_at = context.rng(0.5, 1.0) * interval;
+        context.notify_later_tagged(
+            transmit_at,
+            Notification::Routing,
+            TRANSMIT_POINT,
+            NodeThread::TrickleThread,
+            true,
+        );
+    }
+
+    fn transmit_point(&mut self, context: &mut Context) {
+        if let Some(packet) = &self.newest
+            && !packet.header.dest.is_only_to_node(context.node_id())
+        {
+            if self.counter < self.k {
+                if let Some(key) = self.queued.take() {
+                    self.radio_interface.cancel_sending(context, key);
+                }
+
+                self.queued = Some(packet.global_id());
+                self.radio_interface.send(context, packet.clone());
+            } else {
+                context.log(
+                    || format!("Suppressed after hearing {} consistent", self.counter),
+                    LogLevel::Debug,
+                );
+            }
+        }
+
+        let until_end = self.interval_end - context.clock_time();
+        context.notify_later_tagged(
+            until_end,
+            Notification::Routing,
+            INTERVAL_END,
+            NodeThread::TrickleThread,
+            true,
+        );
+    }
+
+    fn interval_ended(&mut self, context: &mut Context) {
+        let doubled = 2.0 * self.interval.expect("interval ends after it starts");
+        self.interval = Some(doubled.min(self.i_max()));
+        self.start_interval(context);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        analysis::CompleteAnalysis,
+        node::NodeModel,
+        node_location::{Graph, NodeLocation},
+        scenario::{Scenario, ScenarioMessage},
+        simulation::{RunOptions, run_simulation_with_state},
+        units::{METRES, MINS},
+    };
+
+    const SIDE: usize = 5;
+
+    /// Nodes on a grid that can each only reach the nodes beside them.
+    fn grid_scenario(messages: Vec<ScenarioMessage>) -> Scenario {
+        Scenario {
+            messages,
+            ..Scenario::for_test(NodeLocation::Graph(Graph::grid(SIDE, 100.0 * METRES)))
+        }
+    }
+
+    #[test]
+    fn transmissions_collapse_once_consistent() {
+        let everyone = |from| (0..SIDE * SIDE).filter(|x| *x != from).collect();
+        let scenario = grid_scenario(vec![
+            ScenarioMessage::new(0, everyone(0), 1.0 * SECONDS, 60),
+            ScenarioMessage::new(12, everyone(12), 10.0 * MINS, 60),
+        ]);
+
+        let model = Trickle::default().into();
+        let (output, nodes) =
+            run_simulation_with_state(1, scenario.clone(), model, RunOptions::default());
+
+        // Everyone ends up with the message from the middle of the grid as their newest
+        for node in nodes {
+            let NodeModel::Trickle(trickle) = node else {
+                panic!("every node is a Trickle node");
+            };
+            let newest = trickle.newest.expect("node heard something");
+            assert_eq!(newest.header.sender, 12);
+        }
+
+        let analysis = CompleteAnalysis::new(output.clone(), scenario);
+        assert_eq!(analysis.reception_analysis.average_reception_rate, 1.0);
+
+        let per_minute = |from: Time, to: Time| {
+            let sent = output
+                .transmissions
+                .iter()
+                .filter(|x| x.start_time >= from && x.start_time < to)
+                .count();
+            sent as f64 / (to - from).seconds() * 60.0
+        };
+
+        // Still keeping each other up to date, just far less often
+        let spreading = per_minute(0.0 * MINS, 20.0 * MINS);
+        let consistent = per_minute(120.0 * MINS, 240.0 * MINS);
+        assert!(consistent > 0.0);
+        assert!(consistent < spreading / 10.0, "{consistent} vs {spreading}");
+    }
+}
@@ -1,10 +1,16 @@
-use std::cell::{Cell, RefCell};
+use std::cmp::Ordering;
 use std::f64::consts::TAU;
+use std::sync::OnceLock;
+use std::sync::atomic::{self, AtomicUsize};
 
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use thiserror::Error;
 
 use std::ops::{Add, Mul, Sub};
 use crate::units::{Length, Time, METRES};
+use crate::utility::fnv1a;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha12Rng;
 
 macro_rules! node_location {
     ($($variant:ident),+) => {
@@ -121,6 +127,83 @@ impl Point {
     }
 }
 
+/// Axis aligned box given by its lowest and highest corners.
+#[derive(Debug, Clone, Copy)]
+pub struct BoundingBox {
+    pub min: Point,
+    pub max: Point,
+}
+
+impl BoundingBox {
+    /// Smallest box containing every point, `None` if there are no points.
+    pub fn from_points(points: impl IntoIterator<Item = Point>) -> Option<BoundingBox> {
+        let mut points = points.into_iter();
+        let first = points.next()?;
+
+        Some(points.fold(
+            BoundingBox {
+                min: first,
+                max: first,
+            },
+            |mut bounds, point| {
+                if point.x < bounds.min.x {
+                    bounds.min.x = point.x;
+                }
+                if point.y < bounds.min.y {
+                    bounds.min.y = point.y;
+                }
+                if point.x > bounds.max.x {
+                    bounds.max.x = point.x;
+                }
+                if point.y > bounds.max.y {
+                    bounds.max.y = point.y;
+                }
+                bounds
+            },
+        ))
+    }
+
+    pub fn width(&self) -> Length {
+        self.max.x - self.min.x
+    }
+
+    pub fn height(&self) -> Length {
+        self.max.y - self.min.y
+    }
+}
+
+/// Flat projection of latitude and longitude onto points around an origin, x being east and y
+/// north. Equirectangular, so only good over the few kilometres a deployment covers.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct GeoProjection {
+    /// Degrees
+    pub origin_lat: f64,
+    /// Degrees
+    pub origin_lon: f64,
+}
+
+impl GeoProjection {
+    /// Mean radius of the Earth
+    const EARTH_RADIUS: Length = Length::from_metres(6_371_008.8);
+
+    pub fn to_point(&self, lat: f64, lon: f64) -> Point {
+        Point {
+            x: (lon - self.origin_lon).to_radians()
+                * self.origin_lat.to_radians().cos()
+                * Self::EARTH_RADIUS,
+            y: (lat - self.origin_lat).to_radians() * Self::EARTH_RADIUS,
+        }
+    }
+
+    /// Inverse of [`GeoProjection::to_point`], giving (latitude, longitude) in degrees.
+    pub fn to_lat_lon(&self, point: Point) -> (f64, f64) {
+        let lat = self.origin_lat + (point.y / Self::EARTH_RADIUS).to_degrees();
+        let lon = self.origin_lon
+            + (point.x / Self::EARTH_RADIUS / self.origin_lat.to_radians().cos()).to_degrees();
+        (lat, lon)
+    }
+}
+
 impl Sub for Point {
     type Output = Point;
 
@@ -156,6 +239,17 @@ impl Mul<f64> for Point {
 
 node_location!(Graph, Points);
 
+impl NodeLocation {
+    /// Checks the node positions can be interpolated between, see [`Points::check`].
+    /// Graphs are always fine.
+    pub fn check(&self) -> Result<(), PointsError> {
+        match self {
+            NodeLocation::Graph(_) => Ok(()),
+            NodeLocation::Points(points) => points.check(),
+        }
+    }
+}
+
 trait ImplNodeLocation {
     fn display_locations(&self, at_time: Time) -> Vec<Point>;
     fn distance_to(&self, at_time: Time, from_id: usize, to_id: usize) -> Option<Length>;
@@ -173,16 +267,183 @@ trait ImplNodeLocation {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Graph {
     data: Vec<Vec<Edge>>,
-    display: RefCell<Option<Vec<Point>>>,
+
+    /// Where each node is shown, worked out the first time it's needed.
+    /// Kept in the file so it survives being saved and any nodes the user moved stay put
+    #[serde(
+        default,
+        alias = "display",
+        skip_serializing_if = "layout_is_unset",
+        serialize_with = "serialize_layout",
+        deserialize_with = "deserialize_layout"
+    )]
+    layout: OnceLock<Vec<Point>>,
+}
+
+fn layout_is_unset(layout: &OnceLock<Vec<Point>>) -> bool {
+    layout.get().is_none()
+}
+
+/// Saved the same as an `Option`, as serde can't save a `OnceLock` itself
+fn serialize_layout<S: Serializer>(
+    layout: &OnceLock<Vec<Point>>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    layout.get().serialize(serializer)
+}
+
+fn deserialize_layout<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<OnceLock<Vec<Point>>, D::Error> {
+    let layout = Option::<Vec<Point>>::deserialize(deserializer)?;
+    Ok(layout.map_or_else(OnceLock::new, OnceLock::from))
 }
 
 impl Graph {
     pub fn new(edges: Vec<Vec<Edge>>) -> Graph {
         Graph {
             data: edges,
-            display: None.into(),
+            layout: OnceLock::new(),
         }
     }
+
+    /// Same edges without the display layout
+    pub(crate) fn without_layout(&self) -> Graph {
+        Graph::new(self.data.clone())
+    }
+
+    /// Edges leaving the node with the given id
+    pub fn edges(&self, node_id: usize) -> &[Edge] {
+        &self.data[node_id]
+    }
+
+    /// Display locations that can be moved around.
+    /// Lays the graph out first if that hasn't happened yet.
+    pub fn display_locations_mut(&mut self) -> &mut Vec<Point> {
+        self.layout.get_or_init(|| force_layout(&self.data));
+        self.layout.get_mut().unwrap()
+    }
+
+    /// Adds a node with no edges and returns its id
+    pub fn add_node(&mut self, display_location: Point) -> usize {
+        self.display_locations_mut().push(display_location);
+        self.data.push(Vec::new());
+        self.data.len() - 1
+    }
+
+    /// Removes the node and every edge to it.
+    /// Nodes with higher ids have their id decremented.
+    pub fn remove_node(&mut self, node_id: usize) {
+        self.display_locations_mut().remove(node_id);
+        self.data.remove(node_id);
+
+        for edges in self.data.iter_mut() {
+            edges.retain(|x| x.to != node_id);
+            edges
+                .iter_mut()
+                .filter(|x| x.to > node_id)
+                .for_each(|x| x.to -= 1);
+        }
+    }
+
+    /// Adds an edge or updates the weight of an existing one
+    pub fn set_edge(&mut self, from: usize, to: usize, weight: Length) {
+        match self.data[from].iter_mut().find(|x| x.to == to) {
+            Some(edge) => edge.weight = weight,
+            None => self.data[from].push(Edge { to, weight }),
+        }
+    }
+
+    pub fn remove_edge(&mut self, from: usize, to: usize) {
+        self.data[from].retain(|x| x.to != to);
+    }
+
+    /// Multiplies every edge weight and the display layout by `factor`.
+    pub fn scale(&mut self, factor: f64) {
+        for edge in self.data.iter_mut().flatten() {
+            edge.weight = edge.weight * factor;
+        }
+        self.display_locations_mut()
+            .iter_mut()
+            .for_each(|x| *x = *x * factor);
+    }
+
+    /// Puts the node with id `order[new]` at `new`, keeping its edges and display location.
+    /// `order` has to be a permutation of the node ids.
+    pub fn reorder(&mut self, order: &[usize]) {
+        let mut new_id = vec![0; order.len()];
+        for (new, &old) in order.iter().enumerate() {
+            new_id[old] = new;
+        }
+
+        let layout = self.display_locations_mut();
+        *layout = order.iter().map(|&old| layout[old]).collect();
+
+        self.data = order
+            .iter()
+            .map(|&old| {
+                self.data[old]
+                    .iter()
+                    .map(|edge| Edge {
+                        to: new_id[edge.to],
+                        weight: edge.weight,
+                    })
+                    .collect()
+            })
+            .collect();
+    }
+}
+
+#[cfg(test)]
+impl Graph {
+    /// Each node `spacing` away from the nodes listed for it in `neighbours`.
+    pub(crate) fn uniform(neighbours: Vec<Vec<usize>>, spacing: Length) -> Graph {
+        Graph::new(
+            neighbours
+                .into_iter()
+                .map(|adj| {
+                    adj.into_iter()
+                        .map(|to| Edge {
+                            to,
+                            weight: spacing,
+                        })
+                        .collect()
+                })
+                .collect(),
+        )
+    }
+
+    /// `nodes` in a row, each only reaching the ones either side of it.
+    pub(crate) fn line(nodes: usize, spacing: Length) -> Graph {
+        let neighbours = (0..nodes)
+            .map(|id| {
+                [id.checked_sub(1), (id + 1 < nodes).then_some(id + 1)]
+                    .into_iter()
+                    .flatten()
+                    .collect()
+            })
+            .collect();
+        Graph::uniform(neighbours, spacing)
+    }
+
+    /// A `side` by `side` grid, each node only reaching the ones beside it.
+    pub(crate) fn grid(side: usize, spacing: Length) -> Graph {
+        let neighbours = (0..side * side)
+            .map(|id| {
+                let (x, y) = (id % side, id / side);
+                [
+                    (x > 0).then(|| id - 1),
+                    (x + 1 < side).then(|| id + 1),
+                    (y > 0).then(|| id - side),
+                    (y + 1 < side).then(|| id + side),
+                ]
+                .into_iter()
+                .flatten()
+                .collect()
+            })
+            .collect();
+        Graph::uniform(neighbours, spacing)
+    }
 }
 
 impl ImplNodeLocation for Graph {
@@ -204,137 +465,189 @@ impl ImplNodeLocation for Graph {
     }
 
     fn display_locations(&self, _at_time: Time) -> Vec<Point> {
-        let mut display = self.display.borrow_mut();
-        let count = self.data.len() as f64;
-
-        display
-            .get_or_insert_with(|| {
-                let mut positions: Vec<Point> = self
-                    .data
-                    .iter()
-                    .enumerate()
-                    .map(|(n, _)| Point {
-                        x: (n as f64 * (TAU / count)).cos() * 10000.0 * METRES,
-                        y: (n as f64 * (TAU / count)).sin() * 10000.0 * METRES,
-                    })
-                    .collect();
-
-                for step in 0..1000u32 {
-                    let mut updates = vec![(Point::ZERO, false); positions.len()];
-
-                    for (n, pos) in positions.iter().copied().enumerate() {
-                        let (point, is_repelling) = &mut updates[n];
-
-                        for (m, other) in positions.iter().copied().enumerate() {
-                            if n == m {
-                                continue;
-                            }
-
-                            let diff = other - pos;
-                            if diff.mag() < 1000.0 * METRES {
-                                if *is_repelling == false {
-                                    *is_repelling = true;
-                                    *point = Point::ZERO;
-                                }
-
-                                *point = *point - diff * (1000.0 * METRES / diff.mag());
-                            }
+        self.layout
+            .get_or_init(|| force_layout(&self.data))
+            .clone()
+    }
 
-                            if *is_repelling {
-                                continue;
-                            }
+    fn location(&self, _at_time: Time, _id: usize) -> Option<Point> {
+        None
+    }
+}
 
-                            assert!(*is_repelling == false);
+/// Spreads the nodes out with connected nodes pulled together, for showing graphs.
+/// The jiggle that stops it getting stuck is seeded from the edges,
+/// so the same graph is always laid out the same way.
+fn force_layout(edges: &[Vec<Edge>]) -> Vec<Point> {
+    let count = edges.len() as f64;
+    let mut rng = ChaCha12Rng::seed_from_u64(fnv1a(&serde_json::to_vec(edges).unwrap()));
+
+    let mut positions: Vec<Point> = (0..edges.len())
+        .map(|n| Point {
+            x: (n as f64 * (TAU / count)).cos() * 10000.0 * METRES,
+            y: (n as f64 * (TAU / count)).sin() * 10000.0 * METRES,
+        })
+        .collect();
+
+    for step in 0..1000u32 {
+        let mut updates = vec![(Point::ZERO, false); positions.len()];
+
+        for (n, pos) in positions.iter().copied().enumerate() {
+            let (point, is_repelling) = &mut updates[n];
+
+            for (m, other) in positions.iter().copied().enumerate() {
+                if n == m {
+                    continue;
+                }
 
-                            if self.data[n].iter().find(|x| x.to == m).is_some() {
-                                *point = *point + diff;
-                            } else {
-                                *point = *point - diff * (100_000.0 * METRES / diff.mag().powi(2));
-                            }
-                        }
+                let diff = other - pos;
+                if diff.mag() < 1000.0 * METRES {
+                    if !*is_repelling {
+                        *is_repelling = true;
+                        *point = Point::ZERO;
                     }
-                    for (n, pos) in positions.iter_mut().enumerate() {
-                        let (point, is_repelling) = updates[n];
-
-                        *pos = *pos + point * 0.1;
-
-                        if is_repelling {
-                            continue;
-                        }
-
-                        let x = point.x.metres();
-                        let random = (x - x.floor()) * 30.0;
 
-                        assert!(!random.is_nan());
+                    *point = *point - diff * (1000.0 * METRES / diff.mag());
+                }
 
-                        *pos = *pos
-                            + Point {
-                                x: random.cos() * METRES,
-                                y: random.sin() * METRES,
-                            } * (500.0 - 1.2 * step as f64).max(0.0)
-                    }
+                if *is_repelling {
+                    continue;
                 }
 
-                for pos in positions.iter_mut() {
-                    *pos = *pos * 0.1;
+                if edges[n].iter().any(|x| x.to == m) {
+                    *point = *point + diff;
+                } else {
+                    *point = *point - diff * (100_000.0 * METRES / diff.mag().powi(2));
                 }
+            }
+        }
+        for (n, pos) in positions.iter_mut().enumerate() {
+            let (point, is_repelling) = updates[n];
 
-                positions
-            })
-            .clone()
+            *pos = *pos + point * 0.1;
+
+            if is_repelling {
+                continue;
+            }
+
+            let angle = rng.random_range(0.0..TAU);
+            *pos = *pos
+                + Point::from_angle_mag(angle, METRES) * (500.0 - 1.2 * step as f64).max(0.0);
+        }
     }
 
-    fn location(&self, _at_time: Time, _id: usize) -> Option<Point> {
-        None
+    for pos in positions.iter_mut() {
+        *pos = *pos * 0.1;
     }
+
+    positions
 }
 
 /// Points
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct Points {
     pub data: Vec<Timepoint>,
 
+    /// Timepoint the last lookup was at, where the next one starts looking from.
+    /// Atomic so runs on other threads can share the map, which only costs them a longer search
     #[serde(skip)]
-    counter: Cell<usize>,
+    counter: AtomicUsize,
+}
+
+impl Clone for Points {
+    fn clone(&self) -> Self {
+        Points::new(self.data.clone())
+    }
 }
 
 impl Points {
     pub fn new(data: Vec<Timepoint>) -> Self {
         Self {
             data,
-            counter: 0.into(),
+            counter: AtomicUsize::new(0),
+        }
+    }
+
+    /// Checks there is at least one timepoint, every timepoint has the same number of nodes,
+    /// and the times strictly increase.
+    /// The other methods assume this and panic or give nonsense otherwise.
+    pub fn check(&self) -> Result<(), PointsError> {
+        let Some(first) = self.data.first() else {
+            return Err(PointsError::NoTimepoints);
+        };
+
+        for (timepoint, pair) in self.data.windows(2).enumerate() {
+            let (prev, next) = (&pair[0], &pair[1]);
+
+            if next.node_points.len() != first.node_points.len() {
+                return Err(PointsError::NodeCount {
+                    timepoint: timepoint + 1,
+                    found: next.node_points.len(),
+                    expected: first.node_points.len(),
+                });
+            }
+
+            // Written this way so NaN times fail too
+            if next.time.partial_cmp(&prev.time) != Some(Ordering::Greater) {
+                return Err(PointsError::TimeOrder {
+                    timepoint: timepoint + 1,
+                    time: next.time,
+                });
+            }
         }
+
+        Ok(())
     }
 
-    fn move_counter(&self, at_time: Time) {
-        while (self.counter.get() != 0 && at_time < self.data[self.counter.get()].time)
+    /// Index of the timepoint `at_time` is at or after, or 0 if it's before all of them.
+    fn move_counter(&self, at_time: Time) -> usize {
+        let mut counter = self.counter.load(atomic::Ordering::Relaxed);
+        while (counter != 0 && at_time < self.data[counter].time)
             || self
                 .data
-                .get(self.counter.get() + 1)
+                .get(counter + 1)
                 .map(|x| at_time > x.time)
                 .unwrap_or(false)
         {
-            self.counter.set((self.counter.get() + 1) % self.data.len());
+            counter = (counter + 1) % self.data.len();
         }
+
+        self.counter.store(counter, atomic::Ordering::Relaxed);
+        counter
     }
 }
 
+/// Why a [`Points`] map can't be used, see [`Points::check`].
+#[derive(Debug, Error, PartialEq)]
+pub enum PointsError {
+    #[error("Points map has no timepoints")]
+    NoTimepoints,
+    #[error("Timepoint {timepoint} has {found} node points but timepoint 0 has {expected}")]
+    NodeCount {
+        timepoint: usize,
+        found: usize,
+        expected: usize,
+    },
+    #[error("Timepoint {timepoint} is at {time} which is not after the timepoint before it")]
+    TimeOrder { timepoint: usize, time: Time },
+}
+
 /// Two things cannot be in exactly the same place.
 /// It breaks at least pathloss calculation.
 const MIN_DISTANCE: Length = Length::from_metres(0.05);
 
 impl ImplNodeLocation for Points {
     fn distance_to(&self, at_time: Time, from_id: usize, to_id: usize) -> Option<Length> {
-        self.move_counter(at_time);
+        let counter = self.move_counter(at_time);
 
-        let prev = &self.data[self.counter.get()];
+        let prev = &self.data[counter];
 
-        let (a, b) = if self.counter.get() == self.data.len() - 1 {
+        let (a, b) = if counter == self.data.len() - 1 {
             (prev.node_points[from_id], prev.node_points[to_id])
-        } else if self.counter.get() == 0 && at_time < self.data[0].time {
+        } else if counter == 0 && at_time < self.data[0].time {
             (prev.node_points[from_id], prev.node_points[to_id])
         } else {
-            let next = &self.data[self.counter.get() + 1];
+            let next = &self.data[counter + 1];
             let lerp = (at_time - prev.time) / (next.time - prev.time);
 
             let a = Point::point_lerp(prev.node_points[from_id], lerp, next.node_points[from_id]);
@@ -362,16 +675,16 @@ impl ImplNodeLocation for Points {
     }
 
     fn display_locations(&self, at_time: Time) -> Vec<Point> {
-        self.move_counter(at_time);
+        let counter = self.move_counter(at_time);
 
-        if self.counter.get() == self.data.len() - 1 {
-            return self.data[self.counter.get()].node_points.clone();
-        } else if self.counter.get() == 0 && at_time < self.data[0].time {
-            return self.data[self.counter.get()].node_points.clone();
+        if counter == self.data.len() - 1 {
+            return self.data[counter].node_points.clone();
+        } else if counter == 0 && at_time < self.data[0].time {
+            return self.data[counter].node_points.clone();
         }
 
-        let prev = &self.data[self.counter.get()];
-        let next = &self.data[self.counter.get() + 1];
+        let prev = &self.data[counter];
+        let next = &self.data[counter + 1];
         let lerp = (at_time - prev.time) / (next.time - prev.time);
 
         prev.node_points
@@ -382,16 +695,16 @@ impl ImplNodeLocation for Points {
     }
 
     fn location(&self, at_time: Time, id: usize) -> Option<Point> {
-        self.move_counter(at_time);
+        let counter = self.move_counter(at_time);
 
-        let prev = &self.data[self.counter.get()];
+        let prev = &self.data[counter];
 
-        let point = if self.counter.get() == self.data.len() - 1 {
+        let point = if counter == self.data.len() - 1 {
             prev.node_points[id]
-        } else if self.counter.get() == 0 && at_time < self.data[0].time {
+        } else if counter == 0 && at_time < self.data[0].time {
             prev.node_points[id]
         } else {
-            let next = &self.data[self.counter.get() + 1];
+            let next = &self.data[counter + 1];
             let lerp = (at_time - prev.time) / (next.time - prev.time);
 
             let p = Point::point_lerp(prev.node_points[id], lerp, next.node_points[id]);
@@ -435,6 +748,34 @@ mod tests {
         Points::new(data)
     }
 
+    #[test]
+    fn check_finds_bad_timepoints() {
+        let points = get_points(3, 4);
+        assert_eq!(points.check(), Ok(()));
+        assert_eq!(Points::new(Vec::new()).check(), Err(PointsError::NoTimepoints));
+
+        let mut extra_node = points.clone();
+        extra_node.data[2].node_points.push(Point::ZERO);
+        assert_eq!(
+            extra_node.check(),
+            Err(PointsError::NodeCount {
+                timepoint: 2,
+                found: 5,
+                expected: 4
+            })
+        );
+
+        let mut repeated_time = points.clone();
+        repeated_time.data[2].time = repeated_time.data[1].time;
+        assert_eq!(
+            repeated_time.check(),
+            Err(PointsError::TimeOrder {
+                timepoint: 2,
+                time: Time::from_seconds(60.0)
+            })
+        );
+    }
+
     #[test]
     fn points_len() {
         let points = Points::new(vec![Timepoint {
@@ -489,4 +830,78 @@ mod tests {
             }
         }
     }
+    #[test]
+    fn graph_remove_node_reindexes() {
+        let mut graph = Graph::uniform(vec![vec![1, 2], vec![2], vec![0]], 100.0 * METRES);
+
+        graph.remove_node(1);
+
+        assert_eq!(graph.len(), 2);
+        assert_eq!(graph.display_locations(Time::from_seconds(0.0)).len(), 2);
+        assert_eq!(graph.get_adj(0).collect::<Vec<_>>(), vec![1]);
+        assert_eq!(graph.get_adj(1).collect::<Vec<_>>(), vec![0]);
+
+        let id = graph.add_node(Point::ZERO);
+        graph.set_edge(id, 0, 50.0 * METRES);
+        graph.set_edge(id, 0, 70.0 * METRES);
+        assert_eq!(graph.edges(id).len(), 1);
+        assert_close(graph.distance_to(Time::from_seconds(0.0), id, 0).unwrap(), 70.0 * METRES);
+
+        graph.remove_edge(id, 0);
+        assert!(graph.edges(id).is_empty());
+    }
+
+    /// A 4 by 4 grid where each node is connected to the ones beside it
+    fn grid_graph() -> Graph {
+        Graph::grid(4, 100.0 * METRES)
+    }
+
+    #[test]
+    fn graph_layout_spreads_nodes_and_keeps_neighbours_close() {
+        let graph = grid_graph();
+        let layout = graph.display_locations(Time::from_seconds(0.0));
+
+        let mut connected = Vec::new();
+        let mut unconnected = Vec::new();
+        for n in 0..layout.len() {
+            for m in n + 1..layout.len() {
+                let distance = (layout[n] - layout[m]).mag();
+                assert!(distance > 50.0 * METRES, "nodes {n} and {m} overlap");
+
+                if graph.edges(n).iter().any(|x| x.to == m) {
+                    connected.push(distance.metres());
+                } else {
+                    unconnected.push(distance.metres());
+                }
+            }
+        }
+
+        let mean = |x: &[f64]| x.iter().sum::<f64>() / x.len() as f64;
+        assert!(mean(&connected) < mean(&unconnected));
+    }
+
+    #[test]
+    fn graph_layout_is_deterministic_and_kept() {
+        let at = Time::from_seconds(0.0);
+        let layout = grid_graph().display_locations(at);
+
+        let assert_same = |a: &[Point], b: &[Point]| {
+            assert_eq!(a.len(), b.len());
+            for (a, b) in a.iter().zip(b) {
+                assert_close(a.x, b.x);
+                assert_close(a.y, b.y);
+            }
+        };
+        assert_same(&grid_graph().display_locations(at), &layout);
+
+        // Moved by the user then saved and loaded again
+        let mut graph = grid_graph();
+        graph.display_locations_mut()[0] = Point::ZERO;
+        let json = serde_json::to_string(&graph.clone()).unwrap();
+        let loaded: Graph = serde_json::from_str(&json).unwrap();
+
+        let mut expected = layout.clone();
+        expected[0] = Point::ZERO;
+        assert_same(&loaded.display_locations(at), &expected);
+    }
 }
@@ -1,11 +1,23 @@
 pub mod generation;
+pub mod message_csv;
+pub mod modem_preset;
+
+use std::{collections::BTreeMap, fmt::Display, ops::RangeInclusive};
 
 use serde::{Deserialize, Serialize};
+use thiserror::Error;
 
 use crate::{
-    node_location::NodeLocation,
-    scenario::generation::ScenarioGenerator,
-    simulation::{data_structs::CarrierBand, models::TransmissionModel}, units::{Db, Dbm, Frequency, Power, SECONDS, Time},
+    node_location::{BoundingBox, NodeLocation, Point, PointsError},
+    scenario::{
+        generation::ScenarioGenerator,
+        modem_preset::{ModemPreset, PresetError, from_preset},
+    },
+    simulation::{
+        data_structs::{CarrierBand, UtilisationMode},
+        models::TransmissionModel,
+    }, units::{Db, Dbm, Frequency, Power, SECONDS, Time},
+    utility::fnv1a,
 };
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -41,6 +53,42 @@ pub struct Scenario {
     pub model: TransmissionModel,
     pub messages: Vec<ScenarioMessage>,
     pub settings: Vec<ScenarioNodeSettings>,
+
+    /// Carrier bands nodes are allowed to use
+    #[serde(
+        default = "default_frequency_plan",
+        skip_serializing_if = "is_default_frequency_plan"
+    )]
+    pub frequency_plan: Vec<CarrierBand>,
+
+    /// Messages count as delivered to their targets once any gateway hears them,
+    /// as they leave the mesh from there
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub gateway_uplink: bool,
+
+    /// Changes to node settings, and node restarts, partway through the run
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub setting_changes: Vec<ScheduledSettingChange>,
+
+    /// Transmitting nodes are told each time one of their transmissions fails to be received,
+    /// see [`ImplNodeModel::on_reception_failed`](crate::node::ImplNodeModel::on_reception_failed).
+    /// Real radios can't know this, so it is only for experimenting with idealised feedback
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub reception_feedback: bool,
+
+    /// Free form metadata, such as fields from an imported deployment that frog has no use for
+    /// but should give back on export. Doesn't affect a run
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub tags: BTreeMap<String, String>,
+}
+
+/// Just the band [`ScenarioNodeSettings::default`] uses.
+pub fn default_frequency_plan() -> Vec<CarrierBand> {
+    vec![CarrierBand::B868]
+}
+
+fn is_default_frequency_plan(plan: &Vec<CarrierBand>) -> bool {
+    *plan == default_frequency_plan()
 }
 
 impl Scenario {
@@ -48,6 +96,358 @@ impl Scenario {
     pub fn get_settings(&self) -> Vec<ScenarioNodeSettings> {
         self.settings.clone()
     }
+
+    /// Makes the scenario [`ScenarioIdentity::Custom`] if it has been changed since it was
+    /// generated, `generated_hash` being its [`Scenario::content_hash`] then. Like the hash this
+    /// ignores moving the nodes of a graph around. Returns whether the identity was dropped.
+    pub fn drop_identity_if_edited(&mut self, generated_hash: u64) -> bool {
+        if self.identity == ScenarioIdentity::Custom || self.content_hash() == generated_hash {
+            return false;
+        }
+
+        self.identity = ScenarioIdentity::Custom;
+        true
+    }
+
+    /// FNV-1a hash of everything that affects a run, serialised as JSON.
+    /// Leaves out the identity, tags and any graph layout, so the same scenario hashes the same
+    /// however it was made or displayed. Stable across builds, so hashes from generating the
+    /// same identity under different versions show whether the generator changed.
+    pub fn content_hash(&self) -> u64 {
+        let Scenario {
+            identity: _,
+            map,
+            model,
+            messages,
+            settings,
+            frequency_plan,
+            gateway_uplink,
+            setting_changes,
+            reception_feedback,
+            tags: _,
+        } = self;
+
+        let unlaid;
+        let map = match map {
+            NodeLocation::Graph(graph) => {
+                unlaid = NodeLocation::Graph(graph.without_layout());
+                &unlaid
+            }
+            points => points,
+        };
+
+        let content = (
+            map,
+            model,
+            messages,
+            settings,
+            frequency_plan,
+            gateway_uplink,
+            setting_changes,
+            reception_feedback,
+        );
+        fnv1a(&serde_json::to_vec(&content).unwrap())
+    }
+
+    /// Checks the scenario is consistent enough to be simulated.
+    pub fn validate(&self) -> Result<(), ScenarioError> {
+        self.map.check()?;
+        let node_count = self.map.len();
+
+        if self.settings.len() != node_count {
+            return Err(ScenarioError::SettingsCount {
+                settings: self.settings.len(),
+                nodes: node_count,
+            });
+        }
+
+        if self.frequency_plan.is_empty() {
+            return Err(ScenarioError::EmptyFrequencyPlan);
+        }
+
+        for (node, settings) in self.settings.iter().enumerate() {
+            if let Some(reason) = settings.radio_error() {
+                return Err(ScenarioError::InvalidRadio { node, reason });
+            }
+
+            if !self.frequency_plan.contains(&settings.carrier_band) {
+                return Err(ScenarioError::BandNotInPlan {
+                    node,
+                    band: settings.carrier_band,
+                });
+            }
+        }
+
+        for (index, message) in self.messages.iter().enumerate() {
+            if message.sender >= node_count {
+                return Err(ScenarioError::UnknownSender {
+                    message: index,
+                    sender: message.sender,
+                });
+            }
+
+            if message.targets.is_empty() {
+                return Err(ScenarioError::NoTargets { message: index });
+            }
+
+            if let Some(&target) = message.targets.iter().find(|&&x| x >= node_count) {
+                return Err(ScenarioError::UnknownTarget {
+                    message: index,
+                    target,
+                });
+            }
+
+            if let Some(group) = message.group {
+                let members: Vec<usize> = group_members(&self.settings, group)
+                    .filter(|&x| x != message.sender)
+                    .collect();
+
+                if message.targets != members {
+                    return Err(ScenarioError::GroupTargets {
+                        message: index,
+                        group,
+                    });
+                }
+            }
+        }
+
+        for (index, change) in self.setting_changes.iter().enumerate() {
+            if change.node_id >= node_count {
+                return Err(ScenarioError::UnknownChangedNode {
+                    change: index,
+                    node: change.node_id,
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Smallest box containing every node at every point in time.
+    /// Graphs have no real positions so the box is of their display layout instead.
+    pub fn bounding_box(&self) -> Option<BoundingBox> {
+        if self.map.len() == 0 {
+            return None;
+        }
+
+        match &self.map {
+            // Nodes move in straight lines between timepoints so the extremes are at a timepoint
+            NodeLocation::Points(points) => BoundingBox::from_points(
+                points
+                    .data
+                    .iter()
+                    .flat_map(|x| x.node_points.iter().copied()),
+            ),
+            map => BoundingBox::from_points(map.display_locations(0.0 * SECONDS)),
+        }
+    }
+
+    pub fn gateway_count(&self) -> usize {
+        self.settings.iter().filter(|x| x.is_gateway).count()
+    }
+
+    /// Time of the first and last message generation, including repeats.
+    /// `None` if there are no messages.
+    pub fn message_window(&self) -> Option<(Time, Time)> {
+        self.messages
+            .iter()
+            .map(|x| {
+                let last_repeat = x.num_generations.saturating_sub(1) as f64;
+                (
+                    x.generate_time,
+                    x.generate_time + x.generation_spacing * last_repeat,
+                )
+            })
+            .reduce(|(start, end), (first, last)| {
+                (
+                    if first < start { first } else { start },
+                    if last > end { last } else { end },
+                )
+            })
+    }
+
+    /// Moves every node by the same amount so the centroid of where they start is at the origin.
+    /// Graphs only have their display layout moved.
+    pub fn recentre(&mut self) {
+        fn centroid(points: &[Point]) -> Option<Point> {
+            let sum = points.iter().fold(Point::ZERO, |sum, &x| sum + x);
+            (!points.is_empty()).then(|| sum * (1.0 / points.len() as f64))
+        }
+
+        match &mut self.map {
+            NodeLocation::Points(points) => {
+                let Some(centre) = points.data.first().and_then(|x| centroid(&x.node_points))
+                else {
+                    return;
+                };
+                for point in points.data.iter_mut().flat_map(|x| x.node_points.iter_mut()) {
+                    *point = *point - centre;
+                }
+            }
+            NodeLocation::Graph(graph) => {
+                let layout = graph.display_locations_mut();
+                let Some(centre) = centroid(layout) else {
+                    return;
+                };
+                layout.iter_mut().for_each(|x| *x = *x - centre);
+            }
+        }
+    }
+
+    /// Scales every position about the origin by `factor`, or every edge weight of a graph,
+    /// so the distance between any two nodes is multiplied by it. Message sizes stay the same.
+    pub fn rescale(&mut self, factor: f64) {
+        match &mut self.map {
+            NodeLocation::Points(points) => {
+                for point in points.data.iter_mut().flat_map(|x| x.node_points.iter_mut()) {
+                    *point = *point * factor;
+                }
+            }
+            NodeLocation::Graph(graph) => graph.scale(factor),
+        }
+    }
+
+    /// Renumbers the nodes so the one with id `order[new]` gets id `new`. Its positions, settings
+    /// and `node.{id}.` tags move with it, and messages and setting changes are rewritten to
+    /// refer to the new ids.
+    ///
+    /// # Panics
+    /// If `order` isn't a permutation of the node ids.
+    pub fn reorder_nodes(&mut self, order: &[usize]) {
+        let count = self.map.len();
+        assert_eq!(order.len(), count, "order should have every node once");
+        let mut new_id = vec![None; count];
+        for (new, &old) in order.iter().enumerate() {
+            assert!(
+                old < count && new_id[old].replace(new).is_none(),
+                "order should have every node once"
+            );
+        }
+        let new_id: Vec<usize> = new_id.into_iter().flatten().collect();
+
+        match &mut self.map {
+            NodeLocation::Points(points) => {
+                for timepoint in points.data.iter_mut() {
+                    let node_points = &timepoint.node_points;
+                    timepoint.node_points = order.iter().map(|&old| node_points[old]).collect();
+                }
+            }
+            NodeLocation::Graph(graph) => graph.reorder(order),
+        }
+
+        self.settings = order.iter().map(|&old| self.settings[old].clone()).collect();
+
+        for message in self.messages.iter_mut() {
+            message.sender = new_id[message.sender];
+            message.targets.iter_mut().for_each(|x| *x = new_id[*x]);
+            // Group targets are kept in id order, like `group_members` gives them
+            if message.group.is_some() {
+                message.targets.sort_unstable();
+            }
+        }
+
+        for change in self.setting_changes.iter_mut() {
+            change.node_id = new_id[change.node_id];
+        }
+
+        self.tags = std::mem::take(&mut self.tags)
+            .into_iter()
+            .map(|(key, value)| {
+                let renumbered = key
+                    .strip_prefix("node.")
+                    .and_then(|x| x.split_once('.'))
+                    .and_then(|(id, field)| {
+                        let id = *new_id.get(id.parse::<usize>().ok()?)?;
+                        Some(format!("node.{id}.{field}"))
+                    });
+                (renumbered.unwrap_or(key), value)
+            })
+            .collect();
+    }
+}
+
+#[cfg(test)]
+impl Scenario {
+    /// A custom scenario on `map` with default settings for every node and no messages,
+    /// for tests to fill in just the fields they care about with struct update syntax.
+    pub(crate) fn for_test(map: NodeLocation) -> Scenario {
+        Scenario {
+            identity: ScenarioIdentity::Custom,
+            settings: vec![ScenarioNodeSettings::default(); map.len()],
+            map,
+            model: crate::simulation::models::PairWiseCaptureEffect::default().into(),
+            messages: Vec::new(),
+            frequency_plan: default_frequency_plan(),
+            gateway_uplink: false,
+            setting_changes: Vec::new(),
+            reception_feedback: false,
+            tags: BTreeMap::new(),
+        }
+    }
+
+    /// Two nodes 100 metres apart, close enough to always hear each other.
+    pub(crate) fn test_pair() -> Scenario {
+        use crate::{node_location::Graph, units::METRES};
+
+        let graph = Graph::uniform(vec![vec![1], vec![0]], 100.0 * METRES);
+        Scenario::for_test(NodeLocation::Graph(graph))
+    }
+}
+
+impl ScenarioIdentity {
+    /// A small generated graph with about three neighbours per node, for tests that just need
+    /// some traffic to run. Not behind `cfg(test)` so the command line tools' tests can use it.
+    #[doc(hidden)]
+    pub fn for_test(
+        nodes: usize,
+        message_count: usize,
+        messaging_timespan: Time,
+        seed: u64,
+    ) -> ScenarioIdentity {
+        use crate::scenario::generation::messaging::IndependentRandomMessaging;
+
+        ScenarioIdentity::Generated {
+            generator: ScenarioGenerator::PsudoSpatialGraph {
+                nodes,
+                n_connections: 3,
+                messaging: IndependentRandomMessaging {
+                    message_count,
+                    messaging_timespan,
+                    mean_message_size: 60.0,
+                    std_message_size: 20.0,
+                    broadcast_chance: 0.3,
+                    gateway_priority: 0.0,
+                },
+                directed: false,
+                reaction_times: None,
+            },
+            seed,
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum ScenarioError {
+    #[error("Scenario has {settings} node settings but {nodes} nodes")]
+    SettingsCount { settings: usize, nodes: usize },
+    #[error("Message {message} is sent by node {sender} which does not exist")]
+    UnknownSender { message: usize, sender: usize },
+    #[error("Message {message} targets node {target} which does not exist")]
+    UnknownTarget { message: usize, target: usize },
+    #[error("Message {message} has no targets")]
+    NoTargets { message: usize },
+    #[error("Message {message} targets don't match the members of group {group}")]
+    GroupTargets { message: usize, group: u16 },
+    #[error("Node {node} has an invalid radio: {reason}")]
+    InvalidRadio { node: usize, reason: String },
+    #[error("Scenario has no carrier bands in its frequency plan")]
+    EmptyFrequencyPlan,
+    #[error("Node {node} uses carrier band {band:?} which is not in the frequency plan")]
+    BandNotInPlan { node: usize, band: CarrierBand },
+    #[error("Setting change {change} is for node {node} which does not exist")]
+    UnknownChangedNode { change: usize, node: usize },
+    #[error("Invalid node positions: {0}")]
+    Positions(#[from] PointsError),
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -58,6 +458,10 @@ pub struct ScenarioMessage {
     /// who needs to receive the message
     pub targets: Vec<usize>,
 
+    /// group the message is addressed to, the targets are then its members other than the sender
+    #[serde(default)]
+    pub group: Option<u16>,
+
     /// at what sim time will the message be sent in seconds
     pub generate_time: Time,
 
@@ -71,6 +475,10 @@ pub struct ScenarioMessage {
     pub num_generations: u32,
     /// message is generated at `send_time + generation_spacing * gen` where `gen = 0..num_generations`
     pub generation_spacing: Time,
+
+    /// how long after `generate_time` the message has to reach its targets by, if it has to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deadline: Option<Time>,
 }
 
 impl ScenarioMessage {
@@ -78,11 +486,31 @@ impl ScenarioMessage {
         Self {
             sender,
             targets,
+            group: None,
             generate_time,
             size,
             markers: Vec::new(),
             num_generations: 1,
             generation_spacing: 1.0 * SECONDS,
+            deadline: None,
+        }
+    }
+
+    /// A message for every member of `group` other than the sender.
+    pub fn to_group(
+        sender: usize,
+        group: u16,
+        settings: &[ScenarioNodeSettings],
+        generate_time: Time,
+        size: i32,
+    ) -> Self {
+        let targets = group_members(settings, group)
+            .filter(|&x| x != sender)
+            .collect();
+
+        Self {
+            group: Some(group),
+            ..Self::new(sender, targets, generate_time, size)
         }
     }
 
@@ -96,6 +524,76 @@ impl ScenarioMessage {
         self.generation_spacing = spacing;
         self
     }
+
+    pub fn with_deadline(mut self, deadline: Time) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Time of the latest generation at or before `time`, or the first if `time` is before them.
+    pub fn generation_before(&self, time: Time) -> Time {
+        let last = self.num_generations.saturating_sub(1) as f64;
+        let generation = if self.generation_spacing > 0.0 * SECONDS {
+            ((time - self.generate_time) / self.generation_spacing)
+                .floor()
+                .clamp(0.0, last)
+        } else {
+            0.0
+        };
+
+        self.generate_time + self.generation_spacing * generation
+    }
+}
+
+/// A change to one node's settings at a set time, whatever its node model is doing.
+/// Changes are checked like a node model's own, so one the node couldn't make is logged
+/// as an error and skipped.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScheduledSettingChange {
+    pub node_id: usize,
+    pub at: Time,
+    pub change: SettingChange,
+}
+
+/// New value for one of a node's settings,
+/// applied with the matching `change_` method of [`Context`](crate::simulation::Context).
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum SettingChange {
+    Sf(i32),
+    CodingRate(i32),
+    CarrierBand(CarrierBand),
+    Bandwidth(Frequency),
+    Power(Db<Power>),
+    /// Power cycles the node. Its model starts again from scratch and whatever it was waiting
+    /// to send or be notified about is lost, only its settings and persistent store are kept.
+    /// Node models can't restart themselves with
+    /// [`Context::change_setting`](crate::simulation::Context::change_setting)
+    Restart,
+}
+
+impl Display for SettingChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SettingChange::Sf(sf) => write!(f, "SF{sf}"),
+            SettingChange::CodingRate(rate) => write!(f, "coding rate 4/{rate}"),
+            SettingChange::CarrierBand(band) => write!(f, "carrier band {band:?}"),
+            SettingChange::Bandwidth(bandwidth) => write!(f, "bandwidth {} kHz", bandwidth.kHz()),
+            SettingChange::Power(power) => write!(f, "power {} dBm", power.dbm()),
+            SettingChange::Restart => write!(f, "restart"),
+        }
+    }
+}
+
+/// Ids of the nodes in `group`.
+pub fn group_members(
+    settings: &[ScenarioNodeSettings],
+    group: u16,
+) -> impl Iterator<Item = usize> + '_ {
+    settings
+        .iter()
+        .enumerate()
+        .filter(move |(_, x)| x.groups.contains(&group))
+        .map(|(id, _)| id)
 }
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
@@ -120,7 +618,10 @@ impl MovementIndicator {
     ];
 }
 
+/// Can be read with a [`ModemPreset`] name under `preset` in place of the spreading factor,
+/// bandwidth, coding rate and preamble length, but is always written with those.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "ScenarioNodeSettingsFile")]
 pub struct ScenarioNodeSettings {
     /// Number of bits per transmission symbol. Known as spreading factor.
     pub sf: i32,
@@ -140,8 +641,38 @@ pub struct ScenarioNodeSettings {
     pub max_power: Db<Power>,
     pub carrier_band: CarrierBand,
 
-    /// Time in milleseconds
+    /// Delay between a node deciding to send and the send happening
+    #[serde(default = "ScenarioNodeSettings::default_reaction_time")]
     pub reaction_time: Time,
+
+    /// Time the meshtastic models allow for a packet to be processed
+    /// when waiting to see if it gets rebroadcast
+    #[serde(default = "ScenarioNodeSettings::default_processing_time")]
+    pub processing_time: Time,
+
+    /// Groups the node is a member of, messages can be sent to every member of a group.
+    #[serde(default)]
+    pub groups: Vec<u16>,
+
+    /// Preamble length in symbols, not counting the 4.25 symbols of sync word that follow it.
+    /// Meshtastic uses 16 for every modem preset it currently has.
+    #[serde(default = "ScenarioNodeSettings::default_preamble_len")]
+    pub preamble_len: u16,
+
+    /// Whether the LoRa physical header is sent. Without it the radio is in implicit header
+    /// mode and the receiver has to already know the packet length and coding rate.
+    #[serde(default = "ScenarioNodeSettings::default_explicit_header")]
+    pub explicit_header: bool,
+
+    /// Bytes the node's persistent store can hold, see
+    /// [`Context::store_set`](crate::simulation::Context::store_set).
+    #[serde(default = "ScenarioNodeSettings::default_store_bytes")]
+    pub store_bytes: usize,
+
+    /// Which transmissions count towards the channel activity the node sees,
+    /// see [`UtilisationMode`]. Only written when it isn't the default
+    #[serde(default, skip_serializing_if = "UtilisationMode::is_default")]
+    pub utilisation_mode: UtilisationMode,
 }
 
 impl Default for ScenarioNodeSettings {
@@ -158,9 +689,15 @@ impl Default for ScenarioNodeSettings {
     ///     carrier_band: CarrierBand::B868,
     ///     bandwidth: Frequency::from_kHz(250.0),
     ///     reaction_time: Time::from_milis(0.1),
+    ///     processing_time: Time::from_milis(4500.0),
     ///     coding_rate: 5,
     ///     is_gateway: false,
     ///     movement_indicator: MovementIndicator::Unset,
+    ///     groups: Vec::new(),
+    ///     preamble_len: 16,
+    ///     explicit_header: true,
+    ///     store_bytes: 4096,
+    ///     utilisation_mode: UtilisationMode::CountAllDetected,
     /// };
     /// ```
     ///
@@ -170,15 +707,100 @@ impl Default for ScenarioNodeSettings {
             max_power: Dbm::from_dbm(22.0),
             carrier_band: CarrierBand::B868,
             bandwidth: Frequency::from_kHz(250.0),
-            reaction_time: Time::from_milis(0.1),
+            reaction_time: Self::default_reaction_time(),
+            processing_time: Self::default_processing_time(),
             coding_rate: 5,
             is_gateway: false,
             movement_indicator: MovementIndicator::Unset,
+            groups: Vec::new(),
+            preamble_len: Self::default_preamble_len(),
+            explicit_header: Self::default_explicit_header(),
+            store_bytes: Self::default_store_bytes(),
+            utilisation_mode: UtilisationMode::default(),
         }
     }
 }
 
+/// What [`ScenarioNodeSettings`] are read from, in the same field order so positional (rmp)
+/// files still work, with the radio settings optional when there is a preset.
+#[derive(Deserialize)]
+struct ScenarioNodeSettingsFile {
+    #[serde(default)]
+    sf: Option<i32>,
+    #[serde(default)]
+    bandwidth: Option<Frequency>,
+    #[serde(default)]
+    coding_rate: Option<i32>,
+    is_gateway: bool,
+    movement_indicator: MovementIndicator,
+    max_power: Db<Power>,
+    carrier_band: CarrierBand,
+    #[serde(default = "ScenarioNodeSettings::default_reaction_time")]
+    reaction_time: Time,
+    #[serde(default = "ScenarioNodeSettings::default_processing_time")]
+    processing_time: Time,
+    #[serde(default)]
+    groups: Vec<u16>,
+    #[serde(default)]
+    preamble_len: Option<u16>,
+    #[serde(default = "ScenarioNodeSettings::default_explicit_header")]
+    explicit_header: bool,
+    #[serde(default = "ScenarioNodeSettings::default_store_bytes")]
+    store_bytes: usize,
+    #[serde(default)]
+    utilisation_mode: UtilisationMode,
+    #[serde(default)]
+    preset: Option<ModemPreset>,
+}
+
+impl TryFrom<ScenarioNodeSettingsFile> for ScenarioNodeSettings {
+    type Error = PresetError;
+
+    fn try_from(value: ScenarioNodeSettingsFile) -> Result<Self, Self::Error> {
+        let preset = value.preset;
+
+        // Files from before the preamble was a setting all used the default
+        let preamble_len = if value.preamble_len.is_none() && preset.is_none() {
+            Self::default_preamble_len()
+        } else {
+            from_preset("preamble_len", value.preamble_len, preset, ModemPreset::preamble_len)?
+        };
+
+        Ok(ScenarioNodeSettings {
+            sf: from_preset("sf", value.sf, preset, ModemPreset::sf)?,
+            bandwidth: from_preset("bandwidth", value.bandwidth, preset, ModemPreset::bandwidth)?,
+            coding_rate: from_preset("coding_rate", value.coding_rate, preset, |x| {
+                x.coding_rate()
+            })?,
+            is_gateway: value.is_gateway,
+            movement_indicator: value.movement_indicator,
+            max_power: value.max_power,
+            carrier_band: value.carrier_band,
+            reaction_time: value.reaction_time,
+            processing_time: value.processing_time,
+            groups: value.groups,
+            preamble_len,
+            explicit_header: value.explicit_header,
+            store_bytes: value.store_bytes,
+            utilisation_mode: value.utilisation_mode,
+        })
+    }
+}
+
 impl ScenarioNodeSettings {
+    /// Sets the spreading factor, bandwidth, coding rate and preamble length to the preset's.
+    pub fn apply_preset(&mut self, preset: ModemPreset) {
+        self.sf = preset.sf();
+        self.bandwidth = preset.bandwidth();
+        self.coding_rate = preset.coding_rate();
+        self.preamble_len = preset.preamble_len();
+    }
+
+    /// The preset these radio settings are, if they exactly match one.
+    pub fn preset(&self) -> Option<ModemPreset> {
+        ModemPreset::matching(self.sf, self.bandwidth, self.coding_rate, self.preamble_len)
+    }
+
     pub fn with_movement_indicator(mut self, indicator: MovementIndicator) -> ScenarioNodeSettings {
         self.movement_indicator = indicator;
         self
@@ -188,4 +810,264 @@ impl ScenarioNodeSettings {
         self.is_gateway = true;
         self
     }
+
+    pub fn in_groups(mut self, groups: impl IntoIterator<Item = u16>) -> ScenarioNodeSettings {
+        self.groups.extend(groups);
+        self
+    }
+
+    fn default_reaction_time() -> Time {
+        Time::from_milis(0.1)
+    }
+
+    // NOTE: I think the time is exagerated here but more real life testing is needed.
+    // Taken from RadioInterface
+    fn default_processing_time() -> Time {
+        Time::from_milis(4500.0)
+    }
+
+    fn default_preamble_len() -> u16 {
+        16
+    }
+
+    fn default_explicit_header() -> bool {
+        true
+    }
+
+    fn default_store_bytes() -> usize {
+        4096
+    }
+
+    pub const SF_RANGE: RangeInclusive<i32> = 7..=12;
+    /// As the denominator of 4/x
+    pub const CODING_RATE_RANGE: RangeInclusive<i32> = 5..=8;
+    /// Bandwidths LoRa radios support in kHz
+    pub const BANDWIDTHS_KHZ: [f64; 4] = [62.5, 125.0, 250.0, 500.0];
+    /// Output power range of common LoRa radios in dBm
+    pub const MAX_POWER_RANGE_DBM: RangeInclusive<f64> = -9.0..=30.0;
+    /// Preamble lengths in symbols a LoRa radio can be set to
+    pub const PREAMBLE_LEN_RANGE: RangeInclusive<u16> = 6..=u16::MAX;
+
+    /// Describes the first radio setting a LoRa radio could not use.
+    pub fn radio_error(&self) -> Option<String> {
+        if !Self::SF_RANGE.contains(&self.sf) {
+            return Some(format!("spreading factor {} is not 7 to 12", self.sf));
+        }
+        if !Self::CODING_RATE_RANGE.contains(&self.coding_rate) {
+            return Some(format!("coding rate 4/{} is not 4/5 to 4/8", self.coding_rate));
+        }
+        if !Self::BANDWIDTHS_KHZ.contains(&self.bandwidth.kHz()) {
+            return Some(format!("bandwidth {} kHz is not a LoRa bandwidth", self.bandwidth.kHz()));
+        }
+        if !Self::MAX_POWER_RANGE_DBM.contains(&self.max_power.dbm()) {
+            return Some(format!("max power {} dBm is not -9 to 30 dBm", self.max_power.dbm()));
+        }
+        if !Self::PREAMBLE_LEN_RANGE.contains(&self.preamble_len) {
+            return Some(format!("preamble of {} symbols is under 6", self.preamble_len));
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        node_location::{Points, Timepoint},
+        units::METRES,
+    };
+
+    fn point(x: f64, y: f64) -> Point {
+        Point {
+            x: x * METRES,
+            y: y * METRES,
+        }
+    }
+
+    fn moving_scenario() -> Scenario {
+        let settings = ScenarioNodeSettings::default();
+
+        Scenario {
+            messages: vec![
+                ScenarioMessage::new(0, vec![1], 30.0 * SECONDS, 20),
+                ScenarioMessage::new(1, vec![2], 10.0 * SECONDS, 20)
+                    .with_repeats(3, 25.0 * SECONDS),
+            ],
+            settings: vec![settings.clone(), settings.clone().as_gateway(), settings],
+            ..Scenario::for_test(NodeLocation::Points(Points::new(vec![
+                Timepoint {
+                    time: 0.0 * SECONDS,
+                    node_points: vec![point(0.0, 0.0), point(100.0, 50.0), point(20.0, 10.0)],
+                },
+                Timepoint {
+                    time: 60.0 * SECONDS,
+                    node_points: vec![point(-40.0, 0.0), point(100.0, 80.0), point(20.0, 10.0)],
+                },
+            ])))
+        }
+    }
+
+    #[test]
+    fn scenario_summary_helpers() {
+        let scenario = moving_scenario();
+
+        let bounds = scenario.bounding_box().unwrap();
+        assert_eq!(bounds.min.x, -40.0 * METRES);
+        assert_eq!(bounds.min.y, 0.0 * METRES);
+        assert_eq!(bounds.width(), 140.0 * METRES);
+        assert_eq!(bounds.height(), 80.0 * METRES);
+
+        assert_eq!(scenario.gateway_count(), 1);
+        assert_eq!(
+            scenario.message_window(),
+            Some((10.0 * SECONDS, 60.0 * SECONDS))
+        );
+
+        let silent = Scenario {
+            messages: Vec::new(),
+            ..scenario
+        };
+        assert_eq!(silent.message_window(), None);
+    }
+    #[test]
+    fn invalid_radio_settings_fail_validation() {
+        let mut scenario = moving_scenario();
+        assert!(scenario.validate().is_ok());
+
+        scenario.settings[1].sf = 13;
+        assert!(matches!(
+            scenario.validate(),
+            Err(ScenarioError::InvalidRadio { node: 1, .. })
+        ));
+
+        scenario.settings[1].sf = 12;
+        scenario.settings[2].bandwidth = Frequency::from_kHz(200.0);
+        assert!(matches!(
+            scenario.validate(),
+            Err(ScenarioError::InvalidRadio { node: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn bands_outside_the_plan_fail_validation() {
+        let mut scenario = moving_scenario();
+        scenario.settings[1].carrier_band = CarrierBand::B433;
+        assert!(matches!(
+            scenario.validate(),
+            Err(ScenarioError::BandNotInPlan {
+                node: 1,
+                band: CarrierBand::B433
+            })
+        ));
+
+        scenario.frequency_plan.push(CarrierBand::B433);
+        assert!(scenario.validate().is_ok());
+
+        scenario.frequency_plan.clear();
+        assert!(matches!(
+            scenario.validate(),
+            Err(ScenarioError::EmptyFrequencyPlan)
+        ));
+
+        // Only written out when it isn't the default
+        let json = serde_json::to_string(&moving_scenario()).unwrap();
+        assert!(!json.contains("frequency_plan"));
+        let read: Scenario = serde_json::from_str(&json).unwrap();
+        assert_eq!(read.frequency_plan, default_frequency_plan());
+    }
+
+    #[test]
+    fn uneven_timepoints_fail_validation() {
+        let NodeLocation::Points(points) = moving_scenario().map else {
+            unreachable!()
+        };
+
+        // As if the file had been edited by hand
+        let mut data = points.data;
+        data[1].node_points.pop();
+        let scenario = Scenario {
+            map: NodeLocation::Points(Points::new(data)),
+            ..moving_scenario()
+        };
+        let json = serde_json::to_string(&scenario).unwrap();
+        let read: Scenario = serde_json::from_str(&json).unwrap();
+
+        let error = read.validate().unwrap_err();
+        assert!(matches!(error, ScenarioError::Positions(PointsError::NodeCount { .. })));
+        assert_eq!(
+            error.to_string(),
+            "Invalid node positions: Timepoint 1 has 2 node points but timepoint 0 has 3"
+        );
+    }
+
+    #[test]
+    fn recentring_and_rescaling_keep_the_shape() {
+        let original = moving_scenario();
+        let mut scenario = original.clone();
+        scenario.recentre();
+        scenario.rescale(2.5);
+
+        let NodeLocation::Points(points) = &scenario.map else {
+            unreachable!()
+        };
+        let start = &points.data[0].node_points;
+        let centre = start.iter().fold(Point::ZERO, |sum, &x| sum + x);
+        assert!(centre.mag() < 1e-9 * METRES, "{centre:?}");
+
+        for at in [0.0 * SECONDS, 30.0 * SECONDS, 60.0 * SECONDS] {
+            for (from, to) in [(0, 1), (0, 2), (1, 2)] {
+                let before = original.map.distance_to(at, from, to).unwrap();
+                let after = scenario.map.distance_to(at, from, to).unwrap();
+                assert!((after / before - 2.5).abs() < 1e-12, "{before:?} {after:?}");
+            }
+        }
+        let sizes = |x: &Scenario| x.messages.iter().map(|x| x.size).collect::<Vec<_>>();
+        assert_eq!(sizes(&scenario), sizes(&original));
+    }
+
+    #[test]
+    fn reordering_nodes_keeps_messages_consistent() {
+        let mut original = moving_scenario();
+        original.settings[0].groups = vec![4];
+        original.settings[2].groups = vec![4];
+        original
+            .messages
+            .push(ScenarioMessage::to_group(1, 4, &original.settings, 5.0 * SECONDS, 10));
+        original.setting_changes.push(ScheduledSettingChange {
+            node_id: 2,
+            at: 20.0 * SECONDS,
+            change: SettingChange::Restart,
+        });
+        original.tags.insert("node.0.name".into(), "Hut".into());
+        original.tags.insert("site".into(), "North Valley".into());
+
+        let order = [2, 0, 1];
+        let mut scenario = original.clone();
+        scenario.reorder_nodes(&order);
+        scenario.validate().unwrap();
+
+        let new_id = |old| order.iter().position(|&x| x == old).unwrap();
+        for (message, before) in scenario.messages.iter().zip(&original.messages) {
+            assert_eq!(message.sender, new_id(before.sender));
+            let mut targets: Vec<_> = before.targets.iter().map(|&x| new_id(x)).collect();
+            targets.sort();
+            let mut after = message.targets.clone();
+            after.sort();
+            assert_eq!(after, targets);
+        }
+        assert_eq!(scenario.setting_changes[0].node_id, 0);
+        assert!(scenario.settings[2].is_gateway);
+        assert_eq!(scenario.tags["node.1.name"], "Hut");
+        assert_eq!(scenario.tags["site"], "North Valley");
+
+        for (new, &old) in order.iter().enumerate() {
+            for (other_new, &other_old) in order.iter().enumerate() {
+                assert_eq!(
+                    scenario.map.distance_to(45.0 * SECONDS, new, other_new),
+                    original.map.distance_to(45.0 * SECONDS, old, other_old)
+                );
+            }
+        }
+    }
 }
@@ -1,23 +1,31 @@
 pub mod messaging;
 pub mod positioning;
+pub mod sweep;
 
-use std::collections::{HashSet, VecDeque};
+#[cfg(test)]
+mod golden;
+
+use std::collections::{BTreeMap, HashSet, VecDeque};
 
 use messaging::IndependentRandomMessaging;
 use positioning::{IndependentPositionFrames, PathwayMovement, WonderingNodes, pos_random_square};
 use rand::{Rng, SeedableRng};
 use rand_chacha::ChaCha12Rng;
+use rand_distr::{Distribution, Normal};
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    node_location::{Edge, Graph, NodeLocation, Points},
+    node_location::{Edge, Graph, NodeLocation, Point, Points},
     scenario::{MessageMarker, MovementIndicator, ScenarioMessage, ScenarioNodeSettings},
-    scenario::{Scenario, ScenarioIdentity},
+    scenario::{Scenario, ScenarioIdentity, default_frequency_plan},
     simulation::models::{PairWiseCaptureEffect, TransmissionModel},
     units::*,
     utility::n_min,
 };
 
+/// How long after being generated emergency messages have to arrive by.
+const EMERGENCY_DEADLINE: Time = Time::from_seconds(120.0);
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum ScenarioGenerator {
     PsudoSpatialGraph {
@@ -25,6 +33,9 @@ pub enum ScenarioGenerator {
         n_connections: usize,
         messaging: IndependentRandomMessaging,
         directed: bool,
+        /// If set, each node's reaction time is drawn from this
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        reaction_times: Option<ReactionTimes>,
     },
     RandomSquare {
         /// Number of nodes that are not gateways.
@@ -43,6 +54,14 @@ pub enum ScenarioGenerator {
         messaging: IndependentRandomMessaging,
 
         model: TransmissionModel,
+
+        /// If set, each node's reaction time is drawn from this
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        reaction_times: Option<ReactionTimes>,
+
+        /// Spreading factor each node is given
+        #[serde(default, skip_serializing_if = "SfAssignment::is_default")]
+        sf_assignment: SfAssignment,
     },
     WonderingRandomSquare {
         /// Number of nodes that are not gateways.
@@ -64,6 +83,14 @@ pub enum ScenarioGenerator {
         messaging: IndependentRandomMessaging,
 
         model: TransmissionModel,
+
+        /// If set, each node's reaction time is drawn from this
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        reaction_times: Option<ReactionTimes>,
+
+        /// Spreading factor each node is given
+        #[serde(default, skip_serializing_if = "SfAssignment::is_default")]
+        sf_assignment: SfAssignment,
     },
     PathwaysOne {
         /// A key point people will move between
@@ -97,19 +124,197 @@ pub enum ScenarioGenerator {
         positioning: PathwayMovement,
 
         model: TransmissionModel,
+
+        /// If set, each node's reaction time is drawn from this
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        reaction_times: Option<ReactionTimes>,
     },
     SimpleTreeGraph {
         nodes: usize,
         min_degree: usize,
         max_degree: usize,
         messaging: IndependentRandomMessaging,
+        /// If set, each node's reaction time is drawn from this
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        reaction_times: Option<ReactionTimes>,
     },
     RandomTilConnectedGraph {
         nodes: usize,
         messaging: IndependentRandomMessaging,
+        /// If set, each node's reaction time is drawn from this
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        reaction_times: Option<ReactionTimes>,
     },
 }
 
+/// Normally distributed reaction times, for modelling nodes on different hardware.
+/// Samples below zero are clamped to zero.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReactionTimes {
+    pub mean_reaction_time: Time,
+    pub std_reaction_time: Time,
+}
+
+/// How the square generators pick each node's spreading factor.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum SfAssignment {
+    /// Every node uses this spreading factor
+    Fixed(i32),
+    /// Nodes further from their nearest gateway at the start use higher spreading factors,
+    /// as deployments do to keep distant nodes within the link budget.
+    ///
+    /// Each `(distance, sf)` gives the spreading factor of nodes at least `distance` from a
+    /// gateway, the furthest threshold a node reaches being the one used.
+    /// Nodes closer than every threshold, or with no gateway, keep the default.
+    ByGatewayDistance { thresholds: Vec<(Length, i32)> },
+}
+
+impl Default for SfAssignment {
+    fn default() -> Self {
+        SfAssignment::Fixed(ScenarioNodeSettings::default().sf)
+    }
+}
+
+impl SfAssignment {
+    pub fn is_default(&self) -> bool {
+        *self == SfAssignment::default()
+    }
+
+    pub fn examples() -> Vec<SfAssignment> {
+        vec![
+            SfAssignment::default(),
+            SfAssignment::ByGatewayDistance {
+                thresholds: vec![(0.0 * KM, 7), (1.0 * KM, 9), (3.0 * KM, 11), (5.0 * KM, 12)],
+            },
+        ]
+    }
+
+    /// Sets the spreading factor of every node in `settings` from where they are at the start,
+    /// `gateways` being the ids of the gateways.
+    pub fn apply(
+        &self,
+        settings: &mut [ScenarioNodeSettings],
+        map: &NodeLocation,
+        gateways: impl IntoIterator<Item = usize>,
+    ) {
+        let thresholds = match self {
+            SfAssignment::Fixed(sf) => {
+                settings.iter_mut().for_each(|x| x.sf = *sf);
+                return;
+            }
+            SfAssignment::ByGatewayDistance { thresholds } => thresholds,
+        };
+
+        let start = 0.0 * SECONDS;
+        let gateways: Vec<Point> = gateways
+            .into_iter()
+            .filter_map(|id| map.location(start, id))
+            .collect();
+
+        for (id, node) in settings.iter_mut().enumerate() {
+            let Some(location) = map.location(start, id) else {
+                continue;
+            };
+
+            let distance = gateways
+                .iter()
+                .map(|x| (*x - location).mag())
+                .min_by(Length::total_cmp);
+
+            let threshold = distance.and_then(|distance| {
+                thresholds
+                    .iter()
+                    .filter(|x| distance >= x.0)
+                    .max_by(|a, b| a.0.total_cmp(&b.0))
+            });
+
+            if let Some((_, sf)) = threshold {
+                node.sf = *sf;
+            }
+        }
+    }
+}
+
+impl ReactionTimes {
+    /// Gives every node in `settings` its own reaction time.
+    pub fn apply(&self, settings: &mut [ScenarioNodeSettings], rng: &mut impl Rng) {
+        let dist = Normal::new(self.mean_reaction_time.seconds(), self.std_reaction_time.seconds())
+            .unwrap();
+
+        for node in settings {
+            node.reaction_time = Time::from_seconds(dist.sample(rng).max(0.0));
+        }
+    }
+}
+
+/// Range, drag speed and unit an editor should use for a numeric setting,
+/// in the unit the setting is stored and serialized in.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SettingLimits {
+    pub min: f64,
+    pub max: f64,
+    pub speed: f64,
+    pub suffix: &'static str,
+}
+
+impl SettingLimits {
+    const fn new(min: f64, max: f64, speed: f64, suffix: &'static str) -> SettingLimits {
+        SettingLimits { min, max, speed, suffix }
+    }
+}
+
+/// Limits for the numeric fields of [`ScenarioGenerator`], the positioning, messaging and
+/// transmission models it holds and [`ScenarioNodeSettings`], keyed by field name.
+/// `"nth_pathway_chance[]"` applies to each element of `nth_pathway_chance`.
+pub fn setting_limits() -> Vec<(&'static str, SettingLimits)> {
+    let count = |min| SettingLimits::new(min, 100_000.0, 1.0, "");
+    let small_count = |min| SettingLimits::new(min, 1000.0, 0.2, "");
+    let time = SettingLimits::new(0.0, 7.0 * 24.0 * 60.0 * 60.0, 10.0, " s");
+    let speed = SettingLimits::new(0.0, 100.0, 0.1, " m/s");
+    let chance = SettingLimits::new(0.0, 1.0, 0.005, "");
+    let exponent = SettingLimits::new(1.0, 6.0, 0.01, "");
+    let int_range = |range: std::ops::RangeInclusive<i32>| {
+        SettingLimits::new(*range.start() as f64, *range.end() as f64, 0.05, "")
+    };
+
+    vec![
+        ("nodes", count(1.0)),
+        ("node_count", count(0.0)),
+        ("people_count", count(0.0)),
+        ("message_count", count(0.0)),
+        ("gateway_count", small_count(0.0)),
+        ("n_connections", SettingLimits::new(1.0, 100.0, 0.1, "")),
+        ("min_degree", SettingLimits::new(1.0, 100.0, 0.1, "")),
+        ("max_degree", SettingLimits::new(1.0, 100.0, 0.1, "")),
+        ("passive_key_points", small_count(0.0)),
+        ("radio_key_points", small_count(0.0)),
+        ("gateway_key_points", small_count(0.0)),
+        ("isolated_points_count", small_count(0.0)),
+        ("isolated_gateway_count", small_count(0.0)),
+        ("position_count", small_count(1.0)),
+        ("side_len", SettingLimits::new(1.0, 100_000.0, 10.0, " m")),
+        ("reference_distance", SettingLimits::new(0.01, 10_000.0, 0.1, " m")),
+        ("emergency_time", time),
+        ("movement_timespan", time),
+        ("messaging_timespan", time),
+        ("wonder_speed", speed),
+        ("mean_movement_speed", speed),
+        ("std_movement_speed", speed),
+        // Generated sizes are clamped to a LoRa payload
+        ("mean_message_size", SettingLimits::new(1.0, 237.0, 0.5, " bytes")),
+        ("std_message_size", SettingLimits::new(0.0, 237.0, 0.5, " bytes")),
+        ("broadcast_chance", chance),
+        ("gateway_priority", chance),
+        ("nth_pathway_chance[]", chance),
+        ("distance_exponent", exponent),
+        ("exponent", exponent),
+        ("loss_rate", SettingLimits::new(0.0, 1.0, 0.001, " dB/m")),
+        ("noise_temp", SettingLimits::new(0.0, 1000.0, 1.0, " K")),
+        ("sf", int_range(ScenarioNodeSettings::SF_RANGE)),
+        ("coding_rate", int_range(ScenarioNodeSettings::CODING_RATE_RANGE)),
+    ]
+}
+
 impl ScenarioGenerator {
     pub fn generate_from_seed(&self, seed: u64) -> Scenario {
         let rng = ChaCha12Rng::seed_from_u64(seed);
@@ -127,6 +332,8 @@ impl ScenarioGenerator {
                 messaging,
                 model,
                 emergency_time,
+                reaction_times,
+                sf_assignment,
             } => {
                 let map = if gateways_move {
                     positioning.generate(node_count + gateway_count, 0, &mut rng)
@@ -136,7 +343,7 @@ impl ScenarioGenerator {
 
                 let map = NodeLocation::Points(Points::new(map));
 
-                let settings: Vec<_> = (0..node_count + gateway_count)
+                let mut settings: Vec<_> = (0..node_count + gateway_count)
                     .map(|index| {
                         let mut val = ScenarioNodeSettings::default();
                         if index < node_count {
@@ -169,16 +376,29 @@ impl ScenarioGenerator {
                             32,
                         )
                         .with_marker(MessageMarker::Emergency)
-                        .with_repeats(30, 10.0 * SECONDS),
+                        .with_repeats(30, 10.0 * SECONDS)
+                        .with_deadline(EMERGENCY_DEADLINE),
                     );
                 }
 
+                if let Some(reaction_times) = reaction_times {
+                    reaction_times.apply(&mut settings, &mut rng);
+                }
+
+                // Gateways come after the other nodes
+                sf_assignment.apply(&mut settings, &map, node_count..node_count + gateway_count);
+
                 Scenario {
                     identity: ScenarioIdentity::Custom,
                     map,
                     model,
                     messages,
                     settings,
+                    frequency_plan: default_frequency_plan(),
+                    gateway_uplink: false,
+                    setting_changes: Vec::new(),
+                    reception_feedback: false,
+                    tags: BTreeMap::new(),
                 }
             }
             ScenarioGenerator::RandomSquare {
@@ -188,6 +408,8 @@ impl ScenarioGenerator {
                 gateways_move,
                 positioning,
                 model,
+                reaction_times,
+                sf_assignment,
             } => {
                 let map = if gateways_move {
                     positioning.generate(node_count + gateway_count, 0, &mut rng)
@@ -197,7 +419,7 @@ impl ScenarioGenerator {
 
                 let map = NodeLocation::Points(Points::new(map));
 
-                let settings: Vec<_> = (0..node_count + gateway_count)
+                let mut settings: Vec<_> = (0..node_count + gateway_count)
                     .map(|index| {
                         let mut val = ScenarioNodeSettings::default();
                         if index < node_count {
@@ -218,12 +440,24 @@ impl ScenarioGenerator {
 
                 let messages = messaging.generate(&settings, &mut rng);
 
+                if let Some(reaction_times) = reaction_times {
+                    reaction_times.apply(&mut settings, &mut rng);
+                }
+
+                // Gateways come after the other nodes
+                sf_assignment.apply(&mut settings, &map, node_count..node_count + gateway_count);
+
                 Scenario {
                     identity: ScenarioIdentity::Custom,
                     map,
                     model,
                     messages,
                     settings,
+                    frequency_plan: default_frequency_plan(),
+                    gateway_uplink: false,
+                    setting_changes: Vec::new(),
+                    reception_feedback: false,
+                    tags: BTreeMap::new(),
                 }
             }
             ScenarioGenerator::PathwaysOne {
@@ -237,6 +471,7 @@ impl ScenarioGenerator {
                 positioning,
                 model,
                 emergency_time,
+                reaction_times,
             } => {
                 // [isolated_points, active_key_points, people]
                 let map = positioning.generate(
@@ -250,7 +485,7 @@ impl ScenarioGenerator {
 
                 let map = NodeLocation::Points(Points::new(map));
 
-                let settings: Vec<_> = (0..isolated_points_count)
+                let mut settings: Vec<_> = (0..isolated_points_count)
                     .map(|_| ScenarioNodeSettings::default())
                     .chain(
                         (0..isolated_gateway_count)
@@ -281,16 +516,26 @@ impl ScenarioGenerator {
                             32,
                         )
                         .with_marker(MessageMarker::Emergency)
-                        .with_repeats(30, 10.0 * SECONDS),
+                        .with_repeats(30, 10.0 * SECONDS)
+                        .with_deadline(EMERGENCY_DEADLINE),
                     );
                 }
 
+                if let Some(reaction_times) = reaction_times {
+                    reaction_times.apply(&mut settings, &mut rng);
+                }
+
                 Scenario {
                     identity: ScenarioIdentity::Custom,
                     map,
                     model,
                     messages,
                     settings,
+                    frequency_plan: default_frequency_plan(),
+                    gateway_uplink: false,
+                    setting_changes: Vec::new(),
+                    reception_feedback: false,
+                    tags: BTreeMap::new(),
                 }
             }
             ScenarioGenerator::SimpleTreeGraph {
@@ -298,6 +543,7 @@ impl ScenarioGenerator {
                 min_degree,
                 max_degree,
                 messaging,
+                reaction_times,
             } => {
                 let mut expand = VecDeque::new();
                 let mut graph = Vec::new();
@@ -330,20 +576,33 @@ impl ScenarioGenerator {
 
                 let model = PairWiseCaptureEffect::default().into();
 
-                let settings = vec![ScenarioNodeSettings::default(); nodes];
+                let mut settings = vec![ScenarioNodeSettings::default(); nodes];
                 let messages = messaging.generate(&settings, &mut rng);
 
                 assert_eq!(settings.len(), graph.len());
 
+                if let Some(reaction_times) = reaction_times {
+                    reaction_times.apply(&mut settings, &mut rng);
+                }
+
                 Scenario {
                     identity: ScenarioIdentity::Custom,
                     map: NodeLocation::Graph(Graph::new(graph)),
                     model,
                     messages,
                     settings,
+                    frequency_plan: default_frequency_plan(),
+                    gateway_uplink: false,
+                    setting_changes: Vec::new(),
+                    reception_feedback: false,
+                    tags: BTreeMap::new(),
                 }
             }
-            ScenarioGenerator::RandomTilConnectedGraph { nodes, messaging } => {
+            ScenarioGenerator::RandomTilConnectedGraph {
+                nodes,
+                messaging,
+                reaction_times,
+            } => {
                 let mut graph = vec![Vec::new(); nodes];
 
                 while !graph_is_connected(&graph) {
@@ -369,15 +628,24 @@ impl ScenarioGenerator {
 
                 let model = PairWiseCaptureEffect::default().into();
 
-                let settings = vec![ScenarioNodeSettings::default(); nodes];
+                let mut settings = vec![ScenarioNodeSettings::default(); nodes];
                 let messages = messaging.generate(&settings, &mut rng);
 
+                if let Some(reaction_times) = reaction_times {
+                    reaction_times.apply(&mut settings, &mut rng);
+                }
+
                 Scenario {
                     identity: ScenarioIdentity::Custom,
                     map: NodeLocation::Graph(Graph::new(graph)),
                     model,
                     messages,
                     settings,
+                    frequency_plan: default_frequency_plan(),
+                    gateway_uplink: false,
+                    setting_changes: Vec::new(),
+                    reception_feedback: false,
+                    tags: BTreeMap::new(),
                 }
             }
             ScenarioGenerator::PsudoSpatialGraph {
@@ -385,6 +653,7 @@ impl ScenarioGenerator {
                 n_connections,
                 messaging,
                 directed,
+                reaction_times,
             } => {
                 let points = pos_random_square(nodes, 1000.0 * METRES, &mut rng);
 
@@ -412,17 +681,26 @@ impl ScenarioGenerator {
                     });
                 }
 
-                let settings = vec![ScenarioNodeSettings::default(); nodes];
+                let mut settings = vec![ScenarioNodeSettings::default(); nodes];
                 let messages = messaging.generate(&settings, &mut rng);
 
                 let model = PairWiseCaptureEffect::default().into();
 
+                if let Some(reaction_times) = reaction_times {
+                    reaction_times.apply(&mut settings, &mut rng);
+                }
+
                 Scenario {
                     identity: ScenarioIdentity::Custom,
                     map: NodeLocation::Graph(Graph::new(graph)),
                     model,
                     messages,
                     settings,
+                    frequency_plan: default_frequency_plan(),
+                    gateway_uplink: false,
+                    setting_changes: Vec::new(),
+                    reception_feedback: false,
+                    tags: BTreeMap::new(),
                 }
             }
         }
@@ -448,3 +726,83 @@ fn graph_is_connected(graph: &Vec<Vec<Edge>>) -> bool {
 
     visited.len() == graph.len()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::node_location::Timepoint;
+
+    fn thresholds() -> Vec<(Length, i32)> {
+        vec![(0.0 * KM, 7), (2.0 * KM, 9), (4.0 * KM, 12)]
+    }
+
+    fn expected_sf(distance: Length) -> i32 {
+        match distance.metres() {
+            x if x >= 4000.0 => 12,
+            x if x >= 2000.0 => 9,
+            _ => 7,
+        }
+    }
+
+    #[test]
+    fn sf_gradient_follows_thresholds() {
+        let assignment = SfAssignment::ByGatewayDistance {
+            thresholds: thresholds(),
+        };
+
+        // A corridor of nodes every 500m leading away from a gateway at one end
+        let node_points = (0..12)
+            .map(|n| Point {
+                x: n as f64 * 500.0 * METRES,
+                y: 0.0 * METRES,
+            })
+            .collect();
+        let corridor = NodeLocation::Points(Points::new(vec![Timepoint {
+            time: 0.0 * SECONDS,
+            node_points,
+        }]));
+
+        let mut settings = vec![ScenarioNodeSettings::default(); 12];
+        assignment.apply(&mut settings, &corridor, [0]);
+        let sfs: Vec<_> = settings.iter().map(|x| x.sf).collect();
+        assert_eq!(sfs, [7, 7, 7, 7, 9, 9, 9, 9, 12, 12, 12, 12]);
+
+        // Generated scenarios go by the nearest of the stationary gateways
+        let generator = ScenarioGenerator::RandomSquare {
+            node_count: 40,
+            gateway_count: 2,
+            gateways_move: false,
+            positioning: IndependentPositionFrames {
+                side_len: 8.0 * KM,
+                position_count: 2,
+                movement_timespan: 5.0 * MINS,
+            },
+            messaging: IndependentRandomMessaging {
+                message_count: 5,
+                messaging_timespan: 5.0 * MINS,
+                mean_message_size: 60.0,
+                std_message_size: 10.0,
+                broadcast_chance: 0.5,
+                gateway_priority: 0.0,
+            },
+            model: PairWiseCaptureEffect::default().into(),
+            reaction_times: None,
+            sf_assignment: assignment,
+        };
+        let scenario = generator.generate_from_seed(4);
+
+        let start = 0.0 * SECONDS;
+        let mut seen = HashSet::new();
+        for (id, node) in scenario.settings.iter().enumerate() {
+            let distance = [40, 41]
+                .map(|gateway| scenario.map.distance_to(start, id, gateway).unwrap())
+                .into_iter()
+                .min_by(Length::total_cmp)
+                .unwrap();
+
+            assert_eq!(node.sf, expected_sf(distance), "node {id} {distance}");
+            seen.insert(node.sf);
+        }
+        assert_eq!(seen.len(), 3);
+    }
+}
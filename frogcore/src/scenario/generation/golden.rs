@@ -0,0 +1,202 @@
+//! Golden content hashes for every generator, so changes to what a seed generates are noticed.
+//!
+//! Results archived from an old pack are only comparable with new runs if the pack's identities
+//! still generate the same scenarios. When a generator change is meant to alter its output, rerun
+//! the tests with `BLESS_GOLDEN` set to rewrite `tests/fixtures/golden/generator_hashes.txt`
+//! and mention the change in the commit, since older packs no longer reproduce.
+
+use std::{fs, path::Path};
+
+use super::*;
+use crate::{
+    simulation::models::PairWiseCaptureEffect,
+    units::{KM, METRES, MINS, MPS},
+};
+
+const SEED: u64 = 42;
+
+/// Only generators that make gateways can have a nonzero `gateway_priority`.
+fn messaging(gateway_priority: f64) -> IndependentRandomMessaging {
+    IndependentRandomMessaging {
+        message_count: 10,
+        messaging_timespan: 10.0 * MINS,
+        mean_message_size: 50.0,
+        std_message_size: 10.0,
+        broadcast_chance: 0.5,
+        gateway_priority,
+    }
+}
+
+fn reaction_times() -> Option<ReactionTimes> {
+    Some(ReactionTimes {
+        mean_reaction_time: 0.5 * SECONDS,
+        std_reaction_time: 0.1 * SECONDS,
+    })
+}
+
+/// Name of the generator variant, the key in the golden file.
+/// Adding a variant here means adding it to [`generators`] too.
+fn name(generator: &ScenarioGenerator) -> &'static str {
+    match generator {
+        ScenarioGenerator::PsudoSpatialGraph { .. } => "PsudoSpatialGraph",
+        ScenarioGenerator::RandomSquare { .. } => "RandomSquare",
+        ScenarioGenerator::WonderingRandomSquare { .. } => "WonderingRandomSquare",
+        ScenarioGenerator::PathwaysOne { .. } => "PathwaysOne",
+        ScenarioGenerator::SimpleTreeGraph { .. } => "SimpleTreeGraph",
+        ScenarioGenerator::RandomTilConnectedGraph { .. } => "RandomTilConnectedGraph",
+    }
+}
+
+/// One of every generator with fixed parameters, using the optional settings where they have them.
+fn generators() -> Vec<ScenarioGenerator> {
+    vec![
+        ScenarioGenerator::PsudoSpatialGraph {
+            nodes: 12,
+            n_connections: 3,
+            messaging: messaging(0.0),
+            directed: false,
+            reaction_times: reaction_times(),
+        },
+        ScenarioGenerator::RandomSquare {
+            node_count: 10,
+            gateway_count: 2,
+            gateways_move: false,
+            positioning: IndependentPositionFrames {
+                side_len: 3.0 * KM,
+                position_count: 3,
+                movement_timespan: 20.0 * MINS,
+            },
+            messaging: messaging(0.0),
+            model: PairWiseCaptureEffect::default().into(),
+            reaction_times: reaction_times(),
+            sf_assignment: SfAssignment::examples().pop().unwrap(),
+        },
+        ScenarioGenerator::WonderingRandomSquare {
+            node_count: 10,
+            gateway_count: 2,
+            gateways_move: true,
+            emergency_time: Some(5.0 * MINS),
+            positioning: WonderingNodes {
+                side_len: 3.0 * KM,
+                movement_timespan: 20.0 * MINS,
+                wonder_speed: 1.5 * MPS,
+            },
+            messaging: messaging(0.0),
+            model: PairWiseCaptureEffect::default().into(),
+            reaction_times: reaction_times(),
+            sf_assignment: SfAssignment::default(),
+        },
+        ScenarioGenerator::PathwaysOne {
+            passive_key_points: 3,
+            radio_key_points: 2,
+            gateway_key_points: 1,
+            isolated_points_count: 2,
+            isolated_gateway_count: 1,
+            people_count: 6,
+            emergency_time: Some(5.0 * MINS),
+            messaging: messaging(0.2),
+            positioning: PathwayMovement {
+                side_len: 3.0 * KM,
+                mean_movement_speed: 1.4 * MPS,
+                std_movement_speed: 0.3 * MPS,
+                // Every key point needs a path out for people to leave it by
+                nth_pathway_chance: vec![1.0, 0.3],
+            },
+            model: PairWiseCaptureEffect::default().into(),
+            reaction_times: reaction_times(),
+        },
+        ScenarioGenerator::SimpleTreeGraph {
+            nodes: 12,
+            min_degree: 1,
+            max_degree: 3,
+            messaging: messaging(0.0),
+            reaction_times: reaction_times(),
+        },
+        ScenarioGenerator::RandomTilConnectedGraph {
+            nodes: 12,
+            messaging: messaging(0.0),
+            reaction_times: reaction_times(),
+        },
+    ]
+}
+
+#[test]
+fn generators_match_golden_hashes() {
+    let generators = generators();
+
+    let mut names: Vec<_> = generators.iter().map(name).collect();
+    names.sort();
+    names.dedup();
+    assert_eq!(
+        names.len(),
+        generators.len(),
+        "each generator should only appear once"
+    );
+
+    let actual: String = generators
+        .into_iter()
+        .map(|generator| {
+            let name = name(&generator);
+            let scenario = ScenarioIdentity::Generated {
+                generator,
+                seed: SEED,
+            }
+            .create();
+            format!("{name} {:016x}\n", scenario.content_hash())
+        })
+        .collect();
+
+    let path =
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/golden/generator_hashes.txt");
+    if std::env::var_os("BLESS_GOLDEN").is_some() {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, &actual).unwrap();
+    }
+
+    let expected = fs::read_to_string(&path).unwrap();
+    assert!(
+        expected == actual,
+        "generated scenarios changed, set BLESS_GOLDEN if that was intended:\n{actual}"
+    );
+}
+
+#[test]
+fn content_hash_ignores_identity_and_layout() {
+    let identity = ScenarioIdentity::Generated {
+        generator: generators().pop().unwrap(),
+        seed: SEED,
+    };
+    let scenario = identity.create();
+    let hash = scenario.content_hash();
+
+    let mut custom = scenario.clone();
+    custom.identity = ScenarioIdentity::Custom;
+    custom.map.display_locations(0.0 * SECONDS);
+    assert_eq!(custom.content_hash(), hash);
+
+    custom.messages.pop();
+    assert_ne!(custom.content_hash(), hash);
+}
+
+#[test]
+fn only_edits_drop_the_generated_identity() {
+    let identity = ScenarioIdentity::Generated {
+        generator: generators().pop().unwrap(),
+        seed: SEED,
+    };
+    let mut scenario = identity.create();
+    let hash = scenario.content_hash();
+
+    let NodeLocation::Graph(graph) = &mut scenario.map else {
+        panic!("expected a graph");
+    };
+    let moved = &mut graph.display_locations_mut()[0];
+    moved.x = moved.x + 10.0 * METRES;
+    assert!(!scenario.drop_identity_if_edited(hash));
+    assert_eq!(scenario.identity, identity);
+
+    scenario.messages[0].size += 1;
+    assert!(scenario.drop_identity_if_edited(hash));
+    assert_eq!(scenario.identity, ScenarioIdentity::Custom);
+    assert!(!scenario.drop_identity_if_edited(hash));
+}
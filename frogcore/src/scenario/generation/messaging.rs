@@ -48,7 +48,7 @@ impl IndependentRandomMessaging {
             .map(|_| messaging_timespan.map(|x| rng.random_range(0.0..x)))
             .collect();
 
-        message_times.sort_by(|a, b| a.partial_cmp(b).expect("Shoud not be NaN"));
+        message_times.sort_by(Time::total_cmp);
 
         let message_size_dist = Normal::new(mean_message_size, std_message_size).unwrap();
 
@@ -105,7 +105,7 @@ impl IndependentPositionFrames {
             .map(|_| movement_timespan.map(|x| rng.random_range(0.0..x)))
             .collect();
 
-        pos_times.sort_by(|a, b| a.partial_cmp(b).expect("Should not be NaN"));
+        pos_times.sort_by(Time::total_cmp);
 
         let stationary_points = pos_random_square(stationary_count, side_len, rng);
 
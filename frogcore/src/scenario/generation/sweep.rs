@@ -0,0 +1,751 @@
+//! Parameter sweeps over scenario generators.
+//!
+//! A [`SweepSpec`] lists values for each parameter of a generator and expands to every
+//! combination of them as a pack of [`ScenarioIdentity`].
+//! Specs can be written in TOML or JSON, for example:
+//!
+//! ```toml
+//! seed = { random = 42 }
+//! skip = 0.5
+//!
+//! [[sweeps]]
+//! generator = "PsudoSpatialGraph"
+//! nodes = { range = [15, 30, 1] }
+//! n_connections = 3
+//! message_count = [50, 200]
+//! messaging_timespan = { linspace = ["1 min", "5 min", 5] }
+//! mean_message_size = 160.0
+//! std_message_size = [10.0, 50.0]
+//! broadcast_chance = 0.3
+//! directed = [false, true]
+//! ```
+//!
+//! Each parameter is a single value, a list of values, `{ linspace = [start, end, count] }`
+//! or `{ range = [start, end, step] }` where `end` is excluded.
+//! Lengths, times and speeds can be numbers in base units or strings with units such as `"5 km"`.
+
+use std::{fs::read_to_string, path::PathBuf};
+
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha12Rng;
+use rand_distr::Normal;
+use serde::{Deserialize, Deserializer, Serialize, de::Error as _};
+use serde_value::Value;
+use thiserror::Error;
+
+use crate::{
+    scenario::{
+        ScenarioIdentity,
+        generation::{
+            ScenarioGenerator, SfAssignment,
+            messaging::IndependentRandomMessaging,
+            positioning::{IndependentPositionFrames, PathwayMovement, WonderingNodes},
+        },
+    },
+    simulation::models::{AdjustedFreeSpacePathLoss, PairWiseCaptureEffect, TransmissionModel},
+    units::{Length, Speed, Time},
+};
+
+#[derive(Debug, Error)]
+pub enum SweepError {
+    #[error("IO Error: {0}")]
+    IO(#[from] std::io::Error),
+    #[error("TOML Error: {0}")]
+    Toml(#[from] toml::de::Error),
+    #[error("JSON Error: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+/// Loads a sweep spec. Files with a `toml` extension are read as TOML, everything else as JSON.
+pub fn load_sweep(path: PathBuf) -> Result<SweepSpec, SweepError> {
+    let text = read_to_string(&path)?;
+
+    if path.extension().is_some_and(|x| x == "toml") {
+        Ok(SweepSpec::from_toml(&text)?)
+    } else {
+        Ok(serde_json::from_str(&text)?)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SweepSpec {
+    #[serde(default)]
+    pub seed: SeedPolicy,
+
+    /// Skip each generated scenario with this chance
+    #[serde(default)]
+    pub skip: Option<f64>,
+
+    pub sweeps: Vec<Sweep>,
+}
+
+impl SweepSpec {
+    pub fn from_toml(text: &str) -> Result<SweepSpec, toml::de::Error> {
+        toml::from_str(text)
+    }
+
+    /// Number of scenarios before any are skipped.
+    pub fn len(&self) -> usize {
+        self.sweeps.iter().map(|x| x.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Creates the identity of every scenario in the sweep, in the order the sweeps are listed.
+    pub fn expand(&self) -> Vec<ScenarioIdentity> {
+        self.expand_with(|_| {})
+    }
+
+    /// Like [`SweepSpec::expand`] but calls `on_sweep` with the number of scenarios
+    /// after each sweep is expanded.
+    pub fn expand_with(&self, mut on_sweep: impl FnMut(usize)) -> Vec<ScenarioIdentity> {
+        let (SeedPolicy::Random(base) | SeedPolicy::Fixed(base) | SeedPolicy::Sequential(base)) =
+            self.seed;
+        let mut rng = ChaCha12Rng::seed_from_u64(base);
+
+        let mut output = Vec::with_capacity(self.len());
+
+        for sweep in self.sweeps.iter() {
+            for generator in sweep.generators() {
+                let seed = match self.seed {
+                    SeedPolicy::Random(_) => rng.random(),
+                    SeedPolicy::Fixed(seed) => seed,
+                    SeedPolicy::Sequential(start) => start.wrapping_add(output.len() as u64),
+                };
+
+                output.push(ScenarioIdentity::Generated { generator, seed });
+            }
+
+            on_sweep(sweep.len());
+        }
+
+        if let Some(skip_prob) = self.skip {
+            output.retain(|_| rng.random_bool(1.0 - skip_prob));
+        }
+
+        output
+    }
+}
+
+/// How the seed of each generated scenario is chosen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SeedPolicy {
+    /// Seeds are drawn from an rng seeded with this value
+    Random(u64),
+    /// Every scenario uses this seed
+    Fixed(u64),
+    /// Scenarios use consecutive seeds starting from this value
+    Sequential(u64),
+}
+
+impl Default for SeedPolicy {
+    fn default() -> Self {
+        SeedPolicy::Random(0)
+    }
+}
+
+/// The list of values a single parameter takes in a sweep.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Param<T> {
+    pub values: Vec<T>,
+}
+
+impl<T> From<Vec<T>> for Param<T> {
+    fn from(values: Vec<T>) -> Self {
+        Param { values }
+    }
+}
+
+impl<'de, T> Deserialize<'de> for Param<T>
+where
+    T: SweepValue,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let value = Value::deserialize(deserializer)?;
+        Param::from_value(value).map_err(D::Error::custom)
+    }
+}
+
+impl<T> Param<T>
+where
+    T: SweepValue,
+{
+    fn from_value(value: Value) -> Result<Param<T>, String> {
+        let values = match value {
+            Value::Map(map) if map.len() == 1 => {
+                let (key, args) = map.into_iter().next().expect("checked length");
+                let args = match args {
+                    Value::Seq(args) if args.len() == 3 => args,
+                    _ => return Err("Expected a list of three values".into()),
+                };
+
+                match key {
+                    Value::String(key) if key == "linspace" => {
+                        let count = usize::from_value(&args[2]).ok_or("Invalid linspace count")?;
+                        linspace(
+                            number_arg::<T>(&args[0])?,
+                            number_arg::<T>(&args[1])?,
+                            count,
+                        )?
+                    }
+                    Value::String(key) if key == "range" => range(
+                        number_arg::<T>(&args[0])?,
+                        number_arg::<T>(&args[1])?,
+                        number_arg::<T>(&args[2])?,
+                    )?,
+                    _ => return Err(format!("Unknown parameter generator {key:?}")),
+                }
+            }
+            Value::Seq(items) => {
+                let as_list: Option<Vec<T>> = items.iter().map(T::from_value).collect();
+                match as_list {
+                    Some(list) => list,
+                    None => vec![T::from_value(&Value::Seq(items)).ok_or("Invalid list value")?],
+                }
+            }
+            other => vec![T::from_value(&other).ok_or_else(|| format!("Invalid value {other:?}"))?],
+        };
+
+        if values.is_empty() {
+            return Err("Parameter has no values".into());
+        }
+
+        Ok(Param { values })
+    }
+}
+
+fn number_arg<T: SweepValue>(value: &Value) -> Result<f64, String> {
+    T::from_value(value)
+        .and_then(|x| x.as_f64())
+        .ok_or_else(|| format!("Expected a number but found {value:?}"))
+}
+
+fn linspace<T: SweepValue>(start: f64, end: f64, count: usize) -> Result<Vec<T>, String> {
+    if count < 2 {
+        return T::from_f64(start)
+            .map(|x| vec![x])
+            .ok_or_else(|| "Invalid linspace".into());
+    }
+
+    let delta = (end - start) / (count - 1) as f64;
+    (0..count)
+        .map(|n| T::from_f64(start + delta * n as f64))
+        .collect::<Option<_>>()
+        .ok_or_else(|| "Linspace value not valid for parameter".into())
+}
+
+fn range<T: SweepValue>(start: f64, end: f64, step: f64) -> Result<Vec<T>, String> {
+    if step <= 0.0 {
+        return Err("Range step must be positive".into());
+    }
+
+    let mut output = Vec::new();
+    let mut current = start;
+    while current < end {
+        output.push(T::from_f64(current).ok_or("Range value not valid for parameter")?);
+        current += step;
+    }
+
+    Ok(output)
+}
+
+/// A type that can be used as a [`Param`].
+pub trait SweepValue: Sized + Clone {
+    fn from_value(value: &Value) -> Option<Self>;
+
+    /// Numeric types can be used with `linspace` and `range`.
+    fn as_f64(&self) -> Option<f64> {
+        None
+    }
+
+    fn from_f64(_value: f64) -> Option<Self> {
+        None
+    }
+}
+
+fn value_number(value: &Value) -> Option<f64> {
+    Some(match *value {
+        Value::U8(x) => x as f64,
+        Value::U16(x) => x as f64,
+        Value::U32(x) => x as f64,
+        Value::U64(x) => x as f64,
+        Value::I8(x) => x as f64,
+        Value::I16(x) => x as f64,
+        Value::I32(x) => x as f64,
+        Value::I64(x) => x as f64,
+        Value::F32(x) => x as f64,
+        Value::F64(x) => x,
+        _ => return None,
+    })
+}
+
+impl SweepValue for f64 {
+    fn from_value(value: &Value) -> Option<Self> {
+        value_number(value)
+    }
+
+    fn as_f64(&self) -> Option<f64> {
+        Some(*self)
+    }
+
+    fn from_f64(value: f64) -> Option<Self> {
+        Some(value)
+    }
+}
+
+impl SweepValue for usize {
+    fn from_value(value: &Value) -> Option<Self> {
+        value_number(value).and_then(Self::from_f64)
+    }
+
+    fn as_f64(&self) -> Option<f64> {
+        Some(*self as f64)
+    }
+
+    fn from_f64(value: f64) -> Option<Self> {
+        (value >= 0.0 && value.fract() == 0.0).then_some(value as usize)
+    }
+}
+
+impl SweepValue for bool {
+    fn from_value(value: &Value) -> Option<Self> {
+        match value {
+            Value::Bool(inner) => Some(*inner),
+            _ => None,
+        }
+    }
+}
+
+impl SweepValue for Vec<f64> {
+    fn from_value(value: &Value) -> Option<Self> {
+        match value {
+            Value::Seq(items) => items.iter().map(value_number).collect(),
+            _ => None,
+        }
+    }
+}
+
+/// `"none"` or an empty value is `None`
+impl SweepValue for Option<f64> {
+    fn from_value(value: &Value) -> Option<Self> {
+        match value {
+            Value::Unit | Value::Option(None) => Some(None),
+            Value::String(inner) if inner.eq_ignore_ascii_case("none") => Some(None),
+            Value::Option(Some(inner)) => value_number(inner).map(Some),
+            other => value_number(other).map(Some),
+        }
+    }
+}
+
+macro_rules! unit_sweep_value {
+    ($($name: ident),+) => {
+        $(
+            impl SweepValue for $name {
+                fn from_value(value: &Value) -> Option<Self> {
+                    match value {
+                        Value::String(inner) => inner.parse().ok(),
+                        other => value_number(other).map($name::from),
+                    }
+                }
+
+                fn as_f64(&self) -> Option<f64> {
+                    Some((*self).into())
+                }
+
+                fn from_f64(value: f64) -> Option<Self> {
+                    Some(value.into())
+                }
+            }
+        )+
+    };
+}
+
+unit_sweep_value!(Length, Time, Speed);
+
+macro_rules! sweep_params {
+    {
+        $name: ident { $( $var_name: ident : $var_type: ty  ),+ $(,)? } $data_name: ident
+    } => {
+        /// One combination of parameter values.
+        #[derive(Debug, Clone)]
+        pub struct $data_name {
+            $(
+                pub $var_name : $var_type,
+            )+
+        }
+
+        #[derive(Debug, Clone, Deserialize)]
+        #[serde(deny_unknown_fields)]
+        pub struct $name {
+            $(
+                pub $var_name : Param<$var_type>,
+            )+
+        }
+
+        impl $name {
+            /// Number of combinations.
+            pub fn len(&self) -> usize {
+                $(self.$var_name.values.len() * )+ 1
+            }
+
+            pub fn is_empty(&self) -> bool {
+                self.len() == 0
+            }
+
+            /// Every combination of parameter values. The first parameter changes fastest.
+            pub fn combinations(&self) -> impl Iterator<Item = $data_name> + '_ {
+                (0..self.len()).map(|mut index| {
+                    $(
+                        let count = self.$var_name.values.len();
+                        let $var_name = self.$var_name.values[index % count].clone();
+                        index /= count;
+                    )+
+                    let _ = index;
+
+                    $data_name {
+                        $( $var_name, )+
+                    }
+                })
+            }
+        }
+    };
+}
+
+sweep_params! {
+    PsudoSpatialGraphParams {
+        nodes: usize,
+        n_connections: usize,
+        message_count: usize,
+        messaging_timespan: Time,
+        mean_message_size: f64,
+        std_message_size: f64,
+        broadcast_chance: f64,
+        directed: bool,
+    }
+    PsudoSpatialGraphData
+}
+
+sweep_params! {
+    WonderingRandomSquareParams {
+        side_len: Length,
+        node_count: usize,
+        movement_speed: Speed,
+        gateway_count: usize,
+        gateways_move: bool,
+        message_count: usize,
+        timespan: Time,
+        mean_message_size: f64,
+        std_message_size: f64,
+        broadcast_chance: f64,
+        path_loss_exp: f64,
+        emergency_time_coef: Option<f64>,
+        with_fading: bool,
+    }
+    WonderingRandomSquareData
+}
+
+sweep_params! {
+    RandomSquareParams {
+        side_len: Length,
+        node_count: usize,
+        position_count: usize,
+        gateway_count: usize,
+        gateways_move: bool,
+        message_count: usize,
+        timespan: Time,
+        mean_message_size: f64,
+        std_message_size: f64,
+        broadcast_chance: f64,
+        path_loss_exp: f64,
+        with_fading: bool,
+    }
+    RandomSquareData
+}
+
+sweep_params! {
+    PathwaysOneParams {
+        passive_key_points: usize,
+        radio_key_points: usize,
+        gateway_key_points: usize,
+        isolated_points_count: usize,
+        isolated_gateway_count: usize,
+        people_count: usize,
+
+        nth_pathway_chance: Vec<f64>,
+        mean_movement_speed: Speed,
+        std_movement_speed: Speed,
+        side_len: Length,
+
+        message_count: usize,
+        messaging_timespan: Time,
+        mean_message_size: f64,
+        std_message_size: f64,
+        broadcast_chance: f64,
+
+        emergency_time_coef: Option<f64>,
+
+        path_loss_exp: f64,
+    }
+    PathwaysOneData
+}
+
+/// The parameters to sweep over for one generator.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "generator")]
+pub enum Sweep {
+    PsudoSpatialGraph(PsudoSpatialGraphParams),
+    RandomSquare(RandomSquareParams),
+    WonderingRandomSquare(WonderingRandomSquareParams),
+    PathwaysOne(PathwaysOneParams),
+}
+
+impl Sweep {
+    pub fn len(&self) -> usize {
+        match self {
+            Sweep::PsudoSpatialGraph(params) => params.len(),
+            Sweep::RandomSquare(params) => params.len(),
+            Sweep::WonderingRandomSquare(params) => params.len(),
+            Sweep::PathwaysOne(params) => params.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn generators(&self) -> Box<dyn Iterator<Item = ScenarioGenerator> + '_> {
+        match self {
+            Sweep::PsudoSpatialGraph(params) => {
+                Box::new(params.combinations().map(psudo_spatial_graph))
+            }
+            Sweep::RandomSquare(params) => Box::new(params.combinations().map(random_square)),
+            Sweep::WonderingRandomSquare(params) => {
+                Box::new(params.combinations().map(wondering_random_square))
+            }
+            Sweep::PathwaysOne(params) => Box::new(params.combinations().map(pathways_one)),
+        }
+    }
+}
+
+fn messaging(
+    message_count: usize,
+    messaging_timespan: Time,
+    mean_message_size: f64,
+    std_message_size: f64,
+    broadcast_chance: f64,
+) -> IndependentRandomMessaging {
+    IndependentRandomMessaging {
+        message_count,
+        messaging_timespan,
+        mean_message_size,
+        std_message_size,
+        broadcast_chance,
+        gateway_priority: 0.0,
+    }
+}
+
+fn transmission_model(path_loss_exp: f64, with_fading: bool) -> TransmissionModel {
+    let model = PairWiseCaptureEffect::default()
+        .with_pathloss(AdjustedFreeSpacePathLoss::new(path_loss_exp, 0.0.into()).into());
+
+    if with_fading {
+        model.with_fading(Normal::new(0.0, 4.0).unwrap()).into()
+    } else {
+        model.into()
+    }
+}
+
+fn psudo_spatial_graph(data: PsudoSpatialGraphData) -> ScenarioGenerator {
+    let PsudoSpatialGraphData {
+        nodes,
+        n_connections,
+        message_count,
+        messaging_timespan,
+        mean_message_size,
+        std_message_size,
+        broadcast_chance,
+        directed,
+    } = data;
+
+    ScenarioGenerator::PsudoSpatialGraph {
+        nodes,
+        n_connections,
+        messaging: messaging(
+            message_count,
+            messaging_timespan,
+            mean_message_size,
+            std_message_size,
+            broadcast_chance,
+        ),
+        directed,
+        reaction_times: None,
+    }
+}
+
+fn random_square(data: RandomSquareData) -> ScenarioGenerator {
+    let RandomSquareData {
+        side_len,
+        node_count,
+        position_count,
+        gateway_count,
+        gateways_move,
+        message_count,
+        timespan,
+        mean_message_size,
+        std_message_size,
+        broadcast_chance,
+        path_loss_exp,
+        with_fading,
+    } = data;
+
+    ScenarioGenerator::RandomSquare {
+        node_count,
+        messaging: messaging(
+            message_count,
+            timespan,
+            mean_message_size,
+            std_message_size,
+            broadcast_chance,
+        ),
+        model: transmission_model(path_loss_exp, with_fading),
+        gateway_count,
+        gateways_move,
+        positioning: IndependentPositionFrames {
+            side_len,
+            position_count,
+            movement_timespan: timespan * 2.0,
+        },
+        reaction_times: None,
+        sf_assignment: SfAssignment::default(),
+    }
+}
+
+fn wondering_random_square(data: WonderingRandomSquareData) -> ScenarioGenerator {
+    let WonderingRandomSquareData {
+        side_len,
+        node_count,
+        movement_speed,
+        gateway_count,
+        gateways_move,
+        message_count,
+        timespan,
+        mean_message_size,
+        std_message_size,
+        broadcast_chance,
+        path_loss_exp,
+        emergency_time_coef,
+        with_fading,
+    } = data;
+
+    ScenarioGenerator::WonderingRandomSquare {
+        node_count,
+        messaging: messaging(
+            message_count,
+            timespan,
+            mean_message_size,
+            std_message_size,
+            broadcast_chance,
+        ),
+        model: transmission_model(path_loss_exp, with_fading),
+        gateway_count,
+        gateways_move,
+        positioning: WonderingNodes {
+            side_len,
+            wonder_speed: movement_speed,
+            movement_timespan: timespan * 2.0,
+        },
+        emergency_time: emergency_time_coef.map(|n| timespan * n),
+        reaction_times: None,
+        sf_assignment: SfAssignment::default(),
+    }
+}
+
+fn pathways_one(data: PathwaysOneData) -> ScenarioGenerator {
+    let PathwaysOneData {
+        passive_key_points,
+        radio_key_points,
+        gateway_key_points,
+        isolated_points_count,
+        isolated_gateway_count,
+        people_count,
+        nth_pathway_chance,
+        mean_movement_speed,
+        std_movement_speed,
+        side_len,
+        message_count,
+        messaging_timespan,
+        mean_message_size,
+        std_message_size,
+        broadcast_chance,
+        emergency_time_coef,
+        path_loss_exp,
+    } = data;
+
+    ScenarioGenerator::PathwaysOne {
+        messaging: messaging(
+            message_count,
+            messaging_timespan,
+            mean_message_size,
+            std_message_size,
+            broadcast_chance,
+        ),
+        model: transmission_model(path_loss_exp, true),
+        passive_key_points,
+        radio_key_points,
+        gateway_key_points,
+        isolated_points_count,
+        isolated_gateway_count,
+        people_count,
+        positioning: PathwayMovement {
+            side_len,
+            mean_movement_speed,
+            std_movement_speed,
+            nth_pathway_chance,
+        },
+        emergency_time: emergency_time_coef.map(|n| messaging_timespan * n),
+        reaction_times: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::units::{KM, MINS};
+
+    #[test]
+    fn small_spec_product() {
+        let spec = SweepSpec::from_toml(include_str!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/small_sweep.toml"
+        )))
+        .unwrap();
+
+        // 2 * 3 * 2 for the graph sweep and 3 * 2 for the random square sweep
+        assert_eq!(spec.len(), 12 + 6);
+
+        let Sweep::RandomSquare(params) = &spec.sweeps[1] else {
+            panic!("Expected random square sweep");
+        };
+        assert_eq!(params.side_len.values, vec![1.0 * KM, 2.0 * KM, 3.0 * KM]);
+        assert_eq!(params.timespan.values, vec![5.0 * MINS]);
+
+        let pack = spec.expand();
+        assert_eq!(pack.len(), 18);
+
+        let seeds: Vec<_> = pack
+            .iter()
+            .map(|x| match x {
+                ScenarioIdentity::Generated { seed, .. } => *seed,
+                ScenarioIdentity::Custom => panic!(),
+            })
+            .collect();
+        assert_eq!(seeds, (100..118).collect::<Vec<u64>>());
+    }
+}
@@ -0,0 +1,159 @@
+//! Reading and writing scenario messages as CSV so traffic can be prepared in a spreadsheet.
+//!
+//! Each row is `sender,targets,time,size` with targets separated by `;` and time in seconds.
+//! Markers and repeats are not part of the format, exported messages lose them
+//! and imported messages have none.
+
+use std::io::{Read, Write};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{scenario::ScenarioMessage, units::Time};
+
+#[derive(Debug, Serialize, Deserialize)]
+struct MessageRow {
+    sender: usize,
+    targets: String,
+    time: f64,
+    size: i32,
+}
+
+/// A CSV row that could not be turned into a message.
+#[derive(Debug, Clone, Error)]
+#[error("Line {line}: {reason}")]
+pub struct RejectedRow {
+    pub line: u64,
+    pub reason: String,
+}
+
+pub fn write_messages(messages: &[ScenarioMessage], writer: impl Write) -> Result<(), csv::Error> {
+    let mut writer = csv::Writer::from_writer(writer);
+
+    for message in messages {
+        let targets: Vec<String> = message.targets.iter().map(|x| x.to_string()).collect();
+
+        writer.serialize(MessageRow {
+            sender: message.sender,
+            targets: targets.join(";"),
+            time: message.generate_time.seconds(),
+            size: message.size,
+        })?;
+    }
+
+    writer.flush()?;
+    Ok(())
+}
+
+/// Reads every valid row as a message, node ids must be less than `node_count`.
+/// Invalid rows are returned rather than stopping the import.
+pub fn read_messages(
+    reader: impl Read,
+    node_count: usize,
+) -> (Vec<ScenarioMessage>, Vec<RejectedRow>) {
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_reader(reader);
+
+    let mut messages = Vec::new();
+    let mut rejected = Vec::new();
+
+    let headers = match reader.headers() {
+        Ok(headers) => headers.clone(),
+        Err(e) => {
+            let reason = e.to_string();
+            return (messages, vec![RejectedRow { line: 1, reason }]);
+        }
+    };
+
+    for result in reader.records() {
+        let record = match result {
+            Ok(record) => record,
+            Err(e) => {
+                let line = e.position().map_or(0, |x| x.line());
+                let reason = e.to_string();
+                rejected.push(RejectedRow { line, reason });
+                continue;
+            }
+        };
+
+        let line = record.position().map_or(0, |x| x.line());
+        let message = record
+            .deserialize::<MessageRow>(Some(&headers))
+            .map_err(|e| e.to_string())
+            .and_then(|row| to_message(row, node_count));
+
+        match message {
+            Ok(message) => messages.push(message),
+            Err(reason) => rejected.push(RejectedRow { line, reason }),
+        }
+    }
+
+    (messages, rejected)
+}
+
+fn to_message(row: MessageRow, node_count: usize) -> Result<ScenarioMessage, String> {
+    let targets = row
+        .targets
+        .split(';')
+        .map(|x| {
+            x.trim()
+                .parse::<usize>()
+                .map_err(|e| format!("Bad target {x:?}: {e}"))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if row.sender >= node_count {
+        return Err(format!("Sender {} is not a node", row.sender));
+    }
+    if let Some(target) = targets.iter().find(|&&x| x >= node_count) {
+        return Err(format!("Target {target} is not a node"));
+    }
+    if !row.time.is_finite() || row.time < 0.0 {
+        return Err(format!("Time {} must be zero or more seconds", row.time));
+    }
+    if !(0..=255).contains(&row.size) {
+        return Err(format!("Size {} must be 0 to 255 bytes", row.size));
+    }
+
+    Ok(ScenarioMessage::new(
+        row.sender,
+        targets,
+        Time::from_seconds(row.time),
+        row.size,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::units::SECONDS;
+
+    #[test]
+    fn round_trip_and_rejections() {
+        let messages = vec![
+            ScenarioMessage::new(0, vec![1], 2.5 * SECONDS, 40),
+            ScenarioMessage::new(2, vec![0, 1], 10.0 * SECONDS, 200),
+        ];
+
+        let mut buffer = Vec::new();
+        write_messages(&messages, &mut buffer).unwrap();
+        let text = String::from_utf8(buffer).unwrap();
+        assert!(text.starts_with("sender,targets,time,size\n0,1,2.5,40\n2,0;1,10.0,200\n"));
+
+        let (read, rejected) = read_messages(text.as_bytes(), 3);
+        assert!(rejected.is_empty());
+        assert_eq!(read.len(), 2);
+        assert_eq!(read[1].targets, vec![0, 1]);
+        assert_eq!(read[1].generate_time, 10.0 * SECONDS);
+
+        let text =
+            "sender,targets,time,size\n0,1,1,10\n5,1,1,10\n0,1;9,1,10\n0,x,1,10\n0,1,-1,10\n";
+        let (read, rejected) = read_messages(text.as_bytes(), 3);
+        assert_eq!(read.len(), 1);
+        assert_eq!(
+            rejected.iter().map(|x| x.line).collect::<Vec<_>>(),
+            vec![3, 4, 5, 6]
+        );
+    }
+}
@@ -0,0 +1,204 @@
+//! The modem presets Meshtastic offers instead of setting the radio directly.
+//! Values are from the firmware's `RadioInterface::applyModemConfig`,
+//! see <https://meshtastic.org/docs/overview/radio-settings/>.
+
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::units::Frequency;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ModemPreset {
+    ShortTurbo,
+    ShortFast,
+    ShortSlow,
+    MediumFast,
+    MediumSlow,
+    LongFast,
+    LongModerate,
+    LongSlow,
+    /// Deprecated in the firmware but still in older networks
+    VeryLongSlow,
+}
+
+impl ModemPreset {
+    pub const ALL: [ModemPreset; 9] = [
+        ModemPreset::ShortTurbo,
+        ModemPreset::ShortFast,
+        ModemPreset::ShortSlow,
+        ModemPreset::MediumFast,
+        ModemPreset::MediumSlow,
+        ModemPreset::LongFast,
+        ModemPreset::LongModerate,
+        ModemPreset::LongSlow,
+        ModemPreset::VeryLongSlow,
+    ];
+
+    /// (spreading factor, bandwidth in kHz, coding rate as the denominator of 4/x)
+    fn radio(self) -> (i32, f64, i32) {
+        match self {
+            ModemPreset::ShortTurbo => (7, 500.0, 5),
+            ModemPreset::ShortFast => (7, 250.0, 5),
+            ModemPreset::ShortSlow => (8, 250.0, 5),
+            ModemPreset::MediumFast => (9, 250.0, 5),
+            ModemPreset::MediumSlow => (10, 250.0, 5),
+            ModemPreset::LongFast => (11, 250.0, 5),
+            ModemPreset::LongModerate => (11, 125.0, 8),
+            ModemPreset::LongSlow => (12, 125.0, 8),
+            ModemPreset::VeryLongSlow => (12, 62.5, 8),
+        }
+    }
+
+    pub fn sf(self) -> i32 {
+        self.radio().0
+    }
+
+    pub fn bandwidth(self) -> Frequency {
+        Frequency::from_kHz(self.radio().1)
+    }
+
+    pub fn coding_rate(self) -> i32 {
+        self.radio().2
+    }
+
+    /// Every preset uses a 16 symbol preamble.
+    pub fn preamble_len(self) -> u16 {
+        16
+    }
+
+    /// Name of the preset in the firmware's config, such as `LONG_FAST`.
+    pub fn firmware_name(self) -> &'static str {
+        match self {
+            ModemPreset::ShortTurbo => "SHORT_TURBO",
+            ModemPreset::ShortFast => "SHORT_FAST",
+            ModemPreset::ShortSlow => "SHORT_SLOW",
+            ModemPreset::MediumFast => "MEDIUM_FAST",
+            ModemPreset::MediumSlow => "MEDIUM_SLOW",
+            ModemPreset::LongFast => "LONG_FAST",
+            ModemPreset::LongModerate => "LONG_MODERATE",
+            ModemPreset::LongSlow => "LONG_SLOW",
+            ModemPreset::VeryLongSlow => "VERY_LONG_SLOW",
+        }
+    }
+
+    pub fn from_firmware_name(name: &str) -> Option<ModemPreset> {
+        ModemPreset::ALL
+            .into_iter()
+            .find(|x| x.firmware_name() == name)
+    }
+
+    /// The preset with exactly these radio settings, if there is one.
+    pub fn matching(
+        sf: i32,
+        bandwidth: Frequency,
+        coding_rate: i32,
+        preamble_len: u16,
+    ) -> Option<ModemPreset> {
+        ModemPreset::ALL.into_iter().find(|x| {
+            x.sf() == sf
+                && x.bandwidth() == bandwidth
+                && x.coding_rate() == coding_rate
+                && x.preamble_len() == preamble_len
+        })
+    }
+}
+
+impl Display for ModemPreset {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+/// Node settings in a file that don't say what radio settings to use.
+#[derive(Debug, Error)]
+pub enum PresetError {
+    #[error("node settings need either a preset or {0}")]
+    Missing(&'static str),
+    #[error("{field} does not match the {preset} preset")]
+    Mismatch {
+        preset: ModemPreset,
+        field: &'static str,
+    },
+}
+
+/// Fills in a radio setting from the preset if the file left it out,
+/// otherwise checks the two agree.
+pub(crate) fn from_preset<T: PartialEq>(
+    field: &'static str,
+    explicit: Option<T>,
+    preset: Option<ModemPreset>,
+    value: impl Fn(ModemPreset) -> T,
+) -> Result<T, PresetError> {
+    match (explicit, preset) {
+        (Some(explicit), Some(preset)) if explicit != value(preset) => {
+            Err(PresetError::Mismatch { preset, field })
+        }
+        (Some(explicit), _) => Ok(explicit),
+        (None, Some(preset)) => Ok(value(preset)),
+        (None, None) => Err(PresetError::Missing(field)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{assert_close, calculate_air_time, scenario::ScenarioNodeSettings, units::Time};
+
+    #[test]
+    fn preset_airtimes() {
+        // For 32 bytes with the firmware's `RadioInterface::getPacketTime`,
+        // which rounds up after multiplying by the coding rate
+        let expected = [
+            (ModemPreset::ShortTurbo, 19.776),
+            (ModemPreset::ShortFast, 39.552),
+            (ModemPreset::ShortSlow, 71.936),
+            (ModemPreset::MediumFast, 133.632),
+            (ModemPreset::MediumSlow, 250.88),
+            (ModemPreset::LongFast, 477.184),
+            (ModemPreset::LongModerate, 1396.736),
+            (ModemPreset::LongSlow, 2596.864),
+            (ModemPreset::VeryLongSlow, 5193.728),
+        ];
+        assert_eq!(expected.len(), ModemPreset::ALL.len());
+
+        for (preset, millis) in expected {
+            let mut settings = ScenarioNodeSettings::default();
+            settings.apply_preset(preset);
+            assert_eq!(settings.preset(), Some(preset));
+            assert_close(
+                calculate_air_time(32, &settings.into()),
+                Time::from_milis(millis),
+            );
+        }
+    }
+
+    #[test]
+    fn settings_read_from_preset_name() {
+        let read = |json: &str| {
+            let json = format!(
+                r#"{{{json}"is_gateway":false,"movement_indicator":"Unset",
+                "max_power":[-8.0],"carrier_band":"B868"}}"#
+            );
+            serde_json::from_str::<ScenarioNodeSettings>(&json)
+        };
+
+        let settings = read(r#""preset":"LongSlow","#).unwrap();
+        assert_eq!((settings.sf, settings.coding_rate), (12, 8));
+        assert_eq!(settings.preset(), Some(ModemPreset::LongSlow));
+
+        // Explicit fields still work and have to agree with a preset if there is one
+        let settings = read(r#""sf":9,"bandwidth":250000.0,"coding_rate":5,"#).unwrap();
+        assert_eq!(settings.preset(), Some(ModemPreset::MediumFast));
+        assert!(read(r#""preset":"LongSlow","sf":12,"#).is_ok());
+        assert!(read(r#""preset":"LongSlow","sf":7,"#).is_err());
+        assert!(read(r#""sf":9,"bandwidth":250000.0,"#).is_err());
+
+        // Written out with the explicit fields, without the preset
+        let json = serde_json::to_string(&settings).unwrap();
+        assert!(!json.contains("preset"));
+        let again: ScenarioNodeSettings = serde_json::from_str(&json).unwrap();
+        assert_eq!(again.preset(), Some(ModemPreset::MediumFast));
+    }
+}
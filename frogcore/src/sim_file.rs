@@ -1,16 +1,29 @@
+mod deployment;
+
 use rmp_serde::{decode, encode};
 use std::{
+    collections::BTreeMap,
+    fmt,
     fs::File,
-    io::{BufReader, BufWriter},
+    io::{BufReader, BufWriter, Read, Write},
     path::PathBuf,
+    time::Duration,
 };
 use thiserror::Error;
 
-use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use serde::{
+    Deserialize, Deserializer, Serialize,
+    de::{DeserializeOwned, IgnoredAny, SeqAccess, Visitor},
+};
+use serde_value::Value;
+
+pub use deployment::{DeploymentError, export_deployment, import_deployment};
 
 use crate::{
+    node::BasicHeaderInfo,
     scenario::ScenarioIdentity,
-    simulation::data_structs::{LogItem, Transmission},
+    simulation::data_structs::{LogItem, NodeSettings, TraceDirection, TraceEntry, Transmission},
+    units::Time,
 };
 
 #[derive(Debug, Error)]
@@ -23,6 +36,8 @@ pub enum SimFileError {
     RMPWriteError(#[from] encode::Error),
     #[error(transparent)]
     RMPReadError(#[from] decode::Error),
+    #[error(transparent)]
+    CsvError(#[from] csv::Error),
 }
 
 pub fn load_output(path: PathBuf) -> Result<SimOutput, SimFileError> {
@@ -79,6 +94,106 @@ where
     })
 }
 
+/// What kind of file a path holds and roughly how big its contents are.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileSummary {
+    Scenario { nodes: usize, messages: usize },
+    ScenarioIdentity,
+    Pack { entries: usize },
+    Results { transmissions: usize },
+}
+
+/// Works out what a file holds without keeping the bulk of its contents in memory.
+/// Logs, transmissions, maps and messages are skipped over and only counted.
+pub fn summarise_file(path: PathBuf) -> Result<FileSummary, SimFileError> {
+    if let Ok(pack) = load_file::<Vec<ScenarioIdentity>>(path.clone()) {
+        return Ok(FileSummary::Pack {
+            entries: pack.len(),
+        });
+    }
+
+    if let Ok(header) = load_file::<ResultsHeader>(path.clone()) {
+        return Ok(FileSummary::Results {
+            transmissions: header.transmissions.0,
+        });
+    }
+
+    if let Ok(header) = load_file::<ScenarioHeader>(path.clone()) {
+        return Ok(FileSummary::Scenario {
+            nodes: header.settings.0,
+            messages: header.messages.0,
+        });
+    }
+
+    load_file::<ScenarioIdentity>(path).map(|_| FileSummary::ScenarioIdentity)
+}
+
+/// Fields in the same order as [`SimOutput`] so positional (rmp) files can be read too.
+#[derive(Deserialize)]
+struct ResultsHeader {
+    #[serde(rename = "logs")]
+    _logs: IgnoredAny,
+    transmissions: SeqLen,
+    #[serde(rename = "complete_identity")]
+    _complete_identity: OutputIdentity,
+    #[serde(rename = "stats", default)]
+    _stats: IgnoredAny,
+    #[serde(rename = "node_stats", default)]
+    _node_stats: IgnoredAny,
+    #[serde(rename = "node_stores", default)]
+    _node_stores: IgnoredAny,
+    #[serde(rename = "node_settings", default)]
+    _node_settings: IgnoredAny,
+    #[serde(rename = "node_traces", default)]
+    _node_traces: IgnoredAny,
+}
+
+/// Fields in the same order as [`crate::scenario::Scenario`].
+#[derive(Deserialize)]
+struct ScenarioHeader {
+    #[serde(rename = "identity")]
+    _identity: ScenarioIdentity,
+    #[serde(rename = "map")]
+    _map: IgnoredAny,
+    #[serde(rename = "model")]
+    _model: IgnoredAny,
+    messages: SeqLen,
+    settings: SeqLen,
+}
+
+/// Length of a sequence, found without keeping any of its elements.
+struct SeqLen(usize);
+
+impl<'de> Deserialize<'de> for SeqLen {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct SeqLenVisitor;
+
+        impl<'de> Visitor<'de> for SeqLenVisitor {
+            type Value = SeqLen;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a sequence")
+            }
+
+            fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+            where
+                A: SeqAccess<'de>,
+            {
+                let mut len = 0;
+                while seq.next_element::<IgnoredAny>()?.is_some() {
+                    len += 1;
+                }
+                Ok(SeqLen(len))
+            }
+        }
+
+        deserializer.deserialize_seq(SeqLenVisitor)
+    }
+}
+
 pub fn write_file<T>(path: PathBuf, object: T, use_rmp: bool) -> Result<(), SimFileError>
 where
     T: Serialize,
@@ -103,6 +218,32 @@ pub struct OutputIdentity {
     pub model_id: String,
     pub simulation_seed: u64,
     pub sim_version: String,
+    /// Set if the run was stopped before the simulation finished,
+    /// see [`RunLimits`](crate::simulation::RunLimits).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub truncated: Option<TruncationReason>,
+    /// [`Scenario::content_hash`](crate::scenario::Scenario::content_hash) of the scenario run,
+    /// if it was asked for.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scenario_hash: Option<u64>,
+}
+
+/// Which of the [`RunLimits`](crate::simulation::RunLimits) stopped a run.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum TruncationReason {
+    /// Processed this many events
+    MaxEvents(u64),
+    /// Ran for this long in wall-clock time
+    MaxWallTime(Duration),
+}
+
+impl fmt::Display for TruncationReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TruncationReason::MaxEvents(limit) => write!(f, "reached {limit} events"),
+            TruncationReason::MaxWallTime(limit) => write!(f, "ran for {limit:?}"),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -113,4 +254,291 @@ pub struct SimOutput {
 
     // Regeneration
     pub complete_identity: OutputIdentity,
+
+    // Performance
+    #[serde(default)]
+    pub stats: ExecutionStats,
+
+    /// Counts for each node, index is node id.
+    /// Empty for output files written before these were recorded.
+    #[serde(default)]
+    pub node_stats: Vec<NodeStats>,
+
+    /// What each node had in its persistent store at the end, index is node id.
+    /// See [`Context::store_set`](crate::simulation::Context::store_set)
+    #[serde(default)]
+    pub node_stores: Vec<BTreeMap<String, Value>>,
+
+    /// Settings each node started the run with, index is node id.
+    /// Unlike a custom scenario's identity these are enough to rerun it.
+    /// Empty for output files written before these were recorded.
+    #[serde(default)]
+    pub node_settings: Vec<NodeSettings>,
+
+    /// Every packet each node sent or received in the order it happened, index is node id.
+    /// Empty unless the run was made with
+    /// [`RunOptions::traces`](crate::simulation::RunOptions::traces) set
+    #[serde(default)]
+    pub node_traces: Vec<Vec<TraceEntry>>,
+}
+
+/// One transmission to replay with [`run_replay`](crate::simulation::run_replay).
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ScheduledTransmission {
+    pub node_id: usize,
+    pub time: Time,
+    pub sf: i32,
+    /// Payload size in bytes, the header is added on top as for any other transmission
+    pub size: i32,
+}
+
+#[derive(Deserialize)]
+struct ScheduleRow {
+    node_id: usize,
+    time: f64,
+    sf: i32,
+    size: i32,
+}
+
+/// Loads a transmission schedule from a CSV file with a `node_id,time,sf,size` header,
+/// time being in seconds. See [`read_schedule`].
+pub fn load_schedule(path: PathBuf) -> Result<Vec<ScheduledTransmission>, SimFileError> {
+    let file = File::open(path)?;
+    Ok(read_schedule(BufReader::new(file))?)
+}
+
+/// Reads a transmission schedule in the format [`load_schedule`] takes,
+/// stopping at the first row that can't be read.
+pub fn read_schedule(reader: impl Read) -> Result<Vec<ScheduledTransmission>, csv::Error> {
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_reader(reader);
+
+    reader
+        .deserialize()
+        .map(|row| {
+            let row: ScheduleRow = row?;
+            Ok(ScheduledTransmission {
+                node_id: row.node_id,
+                time: Time::from_seconds(row.time),
+                sf: row.sf,
+                size: row.size,
+            })
+        })
+        .collect()
+}
+
+#[derive(Serialize)]
+struct TraceRow {
+    time: f64,
+    direction: TraceDirection,
+    transmission_id: u32,
+    sender: usize,
+    dest: String,
+    packet_id: u32,
+    content: String,
+    size: i32,
+    snr: Option<f64>,
+}
+
+/// Writes the node's packet trace from [`SimOutput::node_traces`] as CSV, see
+/// [`write_trace_csv`]. Nothing is written if the output has no trace for the node.
+pub fn export_trace_csv(
+    output: &SimOutput,
+    node_id: usize,
+    writer: impl Write,
+) -> Result<(), csv::Error> {
+    let trace = output.node_traces.get(node_id).map_or(&[][..], Vec::as_slice);
+    write_trace_csv(trace, writer)
+}
+
+/// Writes one packet per row with the time in seconds
+/// and the header split into `sender`, `dest` and `packet_id`.
+pub fn write_trace_csv(trace: &[TraceEntry], writer: impl Write) -> Result<(), csv::Error> {
+    let mut writer = csv::Writer::from_writer(writer);
+
+    for entry in trace {
+        writer.serialize(TraceRow {
+            time: entry.time.seconds(),
+            direction: entry.direction,
+            transmission_id: entry.transmission_id,
+            sender: entry.header.sender(),
+            dest: format!("{:?}", entry.header.dest()),
+            packet_id: entry.header.packet_id(),
+            content: format!("{:?}", entry.content),
+            size: entry.size,
+            snr: entry.snr.map(|x| x.as_db_float()),
+        })?;
+    }
+
+    writer.flush()?;
+    Ok(())
+}
+
+/// Counters describing how much work a simulation run took.
+/// Output files written before these were recorded load with every counter at zero.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct ExecutionStats {
+    /// Wall-clock time spent running the event loop, in milliseconds.
+    pub wall_time_ms: f64,
+    pub events_processed: u64,
+    /// Largest number of transmissions held at once in the window of recent ones that
+    /// reception and utilisation checks look through.
+    pub peak_em_field: usize,
+}
+
+/// Counters for one node, kept up to date as the simulation runs
+/// so they are there even when node logs are turned off.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct NodeStats {
+    /// Transmissions the node started.
+    pub transmissions: u64,
+    /// Total air time of the node's transmissions in seconds.
+    pub tx_airtime: f64,
+    /// Transmissions the node successfully received.
+    pub receptions: u64,
+    /// Receptions blocked by interference.
+    pub blocked: u64,
+    /// Receptions that failed because the signal was too weak.
+    pub too_weak: u64,
+    /// Receptions missed because the node was listening on a different spreading factor.
+    #[serde(default)]
+    pub wrong_sf: u64,
+    /// Bytes transmitted, including headers.
+    pub bytes_sent: u64,
+    /// Bytes successfully received, including headers.
+    pub bytes_received: u64,
+    /// Rebroadcasts dropped from the node's relay queue before being sent.
+    #[serde(default)]
+    pub relay_drops: u64,
+    /// Values the node model took from the simulation rng with [`Context::rng`].
+    ///
+    /// [`Context::rng`]: crate::simulation::Context::rng
+    #[serde(default)]
+    pub rng_draws: u64,
+    /// Values the transmission model took from the simulation rng while handling the node's
+    /// events, such as fading.
+    #[serde(default)]
+    pub model_draws: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::units::MINS;
+
+    #[test]
+    fn summaries_detect_each_file_type() {
+        let identity = ScenarioIdentity::for_test(6, 4, 1.0 * MINS, 3);
+        let output = SimOutput {
+            logs: vec![],
+            transmissions: vec![],
+            complete_identity: OutputIdentity {
+                scenario_identity: identity.clone(),
+                model_id: "BasicFlood".to_owned(),
+                simulation_seed: 1,
+                sim_version: "test".to_owned(),
+                truncated: None,
+                scenario_hash: None,
+            },
+            stats: ExecutionStats::default(),
+            node_stats: vec![],
+            node_stores: vec![],
+            node_settings: vec![],
+            node_traces: vec![],
+        };
+
+        let dir = std::env::temp_dir().join(format!("frog_summaries_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        for use_rmp in [false, true] {
+            let path = dir.join("file");
+
+            write_file(path.clone(), identity.create(), use_rmp).unwrap();
+            assert_eq!(
+                summarise_file(path.clone()).unwrap(),
+                FileSummary::Scenario {
+                    nodes: 6,
+                    messages: 4
+                }
+            );
+
+            write_file(path.clone(), vec![identity.clone(); 3], use_rmp).unwrap();
+            assert_eq!(
+                summarise_file(path.clone()).unwrap(),
+                FileSummary::Pack { entries: 3 }
+            );
+
+            write_file(path.clone(), identity.clone(), use_rmp).unwrap();
+            assert_eq!(
+                summarise_file(path.clone()).unwrap(),
+                FileSummary::ScenarioIdentity
+            );
+
+            write_output(path.clone(), output.clone(), use_rmp).unwrap();
+            assert_eq!(
+                summarise_file(path.clone()).unwrap(),
+                FileSummary::Results { transmissions: 0 }
+            );
+        }
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn reception_snr_survives_round_trip() {
+        use crate::{
+            node::ModelSelection,
+            simulation::{data_structs::LogContent, run_simulation},
+        };
+        use std::collections::HashMap;
+
+        let scenario = ScenarioIdentity::for_test(6, 4, 1.0 * MINS, 3)
+        .create();
+        let output = run_simulation(1, scenario, ModelSelection::BasicFlood.into(), false);
+
+        let receptions = |output: &SimOutput| -> Vec<_> {
+            output
+                .logs
+                .iter()
+                .filter_map(|x| match x.content {
+                    LogContent::TransmissionReceived {
+                        transmission_id,
+                        snr,
+                        rssi,
+                        ..
+                    } => Some((transmission_id, snr?.as_db_float(), rssi?.dbm())),
+                    _ => None,
+                })
+                .collect()
+        };
+        let expected = receptions(&output);
+        assert!(!expected.is_empty());
+
+        let path = std::env::temp_dir().join(format!("frog_snr_{}", std::process::id()));
+        for use_rmp in [false, true] {
+            write_output(path.clone(), output.clone(), use_rmp).unwrap();
+            assert_eq!(receptions(&load_output(path.clone()).unwrap()), expected);
+        }
+        std::fs::remove_file(path).unwrap();
+
+        // Receptions logged before the SNR was have none
+        let json = r#"{"TransmissionReceived":{"receiver_id":1,"transmission_id":2}}"#;
+        let rmp =
+            encode::to_vec(&HashMap::from([("TransmissionReceived", (1usize, 2u32))])).unwrap();
+        for old in [
+            serde_json::from_str(json).unwrap(),
+            decode::from_slice(&rmp).unwrap(),
+        ] {
+            assert!(matches!(
+                old,
+                LogContent::TransmissionReceived {
+                    receiver_id: 1,
+                    transmission_id: 2,
+                    snr: None,
+                    rssi: None,
+                }
+            ));
+        }
+    }
 }
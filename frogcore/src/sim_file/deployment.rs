@@ -0,0 +1,278 @@
+//! Reading and writing the node lists field teams keep for real Meshtastic deployments.
+//!
+//! ```json
+//! {
+//!     "site": "North Valley",
+//!     "nodes": [
+//!         { "name": "Hut", "role": "GATEWAY", "lat": 54.45, "lon": -3.21 },
+//!         { "name": "Ridge", "role": "ROUTER", "lat": 54.46, "lon": -3.19,
+//!           "modem_preset": "LONG_SLOW", "channel": 2 }
+//!     ]
+//! }
+//! ```
+//!
+//! Roles are the firmware's, plus `GATEWAY` for nodes with an uplink. Nodes without a modem
+//! preset get the default settings, which are `LONG_FAST`. Anything frog doesn't use,
+//! like `site` and `channel` above, goes into the scenario's tags so exporting gives it back.
+
+use std::{
+    collections::BTreeMap,
+    io::{Read, Write},
+};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use thiserror::Error;
+
+use crate::{
+    node_location::{GeoProjection, NodeLocation, Points, Timepoint},
+    scenario::{
+        MovementIndicator, Scenario, ScenarioIdentity, ScenarioNodeSettings,
+        default_frequency_plan, modem_preset::ModemPreset,
+    },
+    simulation::models::PairWiseCaptureEffect,
+    units::SECONDS,
+};
+
+const ORIGIN_LAT: &str = "deployment.origin_lat";
+const ORIGIN_LON: &str = "deployment.origin_lon";
+
+#[derive(Debug, Error)]
+pub enum DeploymentError {
+    #[error(transparent)]
+    JsonError(#[from] serde_json::Error),
+    #[error("node {node} has unknown modem preset {preset}")]
+    UnknownPreset { node: usize, preset: String },
+    #[error("only scenarios with node positions can be exported as a deployment")]
+    NoPositions,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Deployment {
+    nodes: Vec<DeploymentNode>,
+    #[serde(flatten)]
+    other: BTreeMap<String, Value>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct DeploymentNode {
+    name: String,
+    role: String,
+    lat: f64,
+    lon: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    modem_preset: Option<String>,
+    #[serde(flatten)]
+    other: BTreeMap<String, Value>,
+}
+
+/// Whether a node with the role is a gateway, and how it moves.
+fn role_settings(role: &str) -> (bool, MovementIndicator) {
+    match role {
+        "GATEWAY" => (true, MovementIndicator::Stationary),
+        "ROUTER" | "ROUTER_LATE" | "ROUTER_CLIENT" | "REPEATER" => {
+            (false, MovementIndicator::Stationary)
+        }
+        _ => (false, MovementIndicator::Unset),
+    }
+}
+
+fn node_tag(node: usize, field: &str) -> String {
+    format!("node.{node}.{field}")
+}
+
+/// Reads a deployment into a custom scenario with no messages, the nodes placed by projecting
+/// their positions around the middle of the deployment.
+pub fn import_deployment(reader: impl Read) -> Result<Scenario, DeploymentError> {
+    let deployment: Deployment = serde_json::from_reader(reader)?;
+    let count = deployment.nodes.len().max(1) as f64;
+    let projection = GeoProjection {
+        origin_lat: deployment.nodes.iter().map(|x| x.lat).sum::<f64>() / count,
+        origin_lon: deployment.nodes.iter().map(|x| x.lon).sum::<f64>() / count,
+    };
+
+    let mut tags = BTreeMap::from([
+        (ORIGIN_LAT.to_owned(), projection.origin_lat.to_string()),
+        (ORIGIN_LON.to_owned(), projection.origin_lon.to_string()),
+    ]);
+    for (key, value) in deployment.other {
+        tags.insert(format!("deployment.{key}"), value.to_string());
+    }
+
+    let mut node_points = Vec::new();
+    let mut settings = Vec::new();
+    for (id, node) in deployment.nodes.into_iter().enumerate() {
+        node_points.push(projection.to_point(node.lat, node.lon));
+
+        let mut node_settings = ScenarioNodeSettings::default();
+        (node_settings.is_gateway, node_settings.movement_indicator) = role_settings(&node.role);
+        if let Some(preset) = node.modem_preset {
+            let Some(preset) = ModemPreset::from_firmware_name(&preset) else {
+                return Err(DeploymentError::UnknownPreset { node: id, preset });
+            };
+            node_settings.apply_preset(preset);
+        }
+        settings.push(node_settings);
+
+        tags.insert(node_tag(id, "name"), node.name);
+        tags.insert(node_tag(id, "role"), node.role);
+        for (key, value) in node.other {
+            tags.insert(node_tag(id, &key), value.to_string());
+        }
+    }
+
+    Ok(Scenario {
+        identity: ScenarioIdentity::Custom,
+        map: NodeLocation::Points(Points::new(vec![Timepoint {
+            time: 0.0 * SECONDS,
+            node_points,
+        }])),
+        model: PairWiseCaptureEffect::default().into(),
+        messages: Vec::new(),
+        settings,
+        frequency_plan: default_frequency_plan(),
+        gateway_uplink: false,
+        setting_changes: Vec::new(),
+        reception_feedback: false,
+        tags,
+    })
+}
+
+/// Writes the scenario's nodes where they start as a deployment, giving back anything kept in
+/// the tags by [`import_deployment`].
+/// Scenarios that weren't imported are placed around latitude and longitude 0, their nodes
+/// named by id, and nodes whose radio doesn't match a preset are written without one.
+pub fn export_deployment(scenario: &Scenario, writer: impl Write) -> Result<(), DeploymentError> {
+    let NodeLocation::Points(points) = &scenario.map else {
+        return Err(DeploymentError::NoPositions);
+    };
+    let Some(start) = points.data.first() else {
+        return Err(DeploymentError::NoPositions);
+    };
+
+    let tag = |key: &str| scenario.tags.get(key);
+    let coordinate = |key| tag(key).and_then(|x| x.parse().ok()).unwrap_or(0.0);
+    let projection = GeoProjection {
+        origin_lat: coordinate(ORIGIN_LAT),
+        origin_lon: coordinate(ORIGIN_LON),
+    };
+
+    // Tags hold JSON, but ones edited by hand might not be
+    let value =
+        |text: &String| serde_json::from_str(text).unwrap_or_else(|_| Value::String(text.clone()));
+
+    let other = |prefix: &str, known: &[&str]| {
+        scenario
+            .tags
+            .iter()
+            .filter_map(|(key, text)| {
+                let field = key.strip_prefix(prefix)?;
+                (!known.contains(&field)).then(|| (field.to_owned(), value(text)))
+            })
+            .collect::<BTreeMap<_, _>>()
+    };
+
+    let nodes = start
+        .node_points
+        .iter()
+        .zip(&scenario.settings)
+        .enumerate()
+        .map(|(id, (&point, settings))| {
+            let (lat, lon) = projection.to_lat_lon(point);
+            let current = (settings.is_gateway, settings.movement_indicator);
+            let role = match tag(&node_tag(id, "role")) {
+                Some(role) if role_settings(role) == current => role.clone(),
+                _ if settings.is_gateway => "GATEWAY".to_owned(),
+                _ if settings.movement_indicator == MovementIndicator::Stationary => {
+                    "ROUTER".to_owned()
+                }
+                _ => "CLIENT".to_owned(),
+            };
+
+            DeploymentNode {
+                name: tag(&node_tag(id, "name"))
+                    .cloned()
+                    .unwrap_or_else(|| format!("Node {id}")),
+                role,
+                lat,
+                lon,
+                modem_preset: settings.preset().map(|x| x.firmware_name().to_owned()),
+                other: other(&format!("node.{id}."), &["name", "role"]),
+            }
+        })
+        .collect();
+
+    let deployment = Deployment {
+        nodes,
+        other: other("deployment.", &["origin_lat", "origin_lon"]),
+    };
+    serde_json::to_writer_pretty(writer, &deployment)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs::File, path::Path};
+
+    use super::*;
+    use crate::assert_close;
+
+    fn fixture() -> File {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/deployment.json");
+        File::open(path).unwrap()
+    }
+
+    #[test]
+    fn deployment_round_trip() {
+        let original: Value = serde_json::from_reader(fixture()).unwrap();
+        let scenario = import_deployment(fixture()).unwrap();
+        scenario.validate().unwrap();
+
+        assert_eq!(scenario.map.len(), 4);
+        let gateways: Vec<_> = scenario.settings.iter().map(|x| x.is_gateway).collect();
+        assert_eq!(gateways, [true, false, false, false]);
+        assert_eq!(scenario.settings[0].preset(), Some(ModemPreset::LongFast));
+        assert_eq!(scenario.settings[2].preset(), Some(ModemPreset::MediumSlow));
+        assert_eq!(
+            scenario.settings[1].movement_indicator,
+            MovementIndicator::Stationary
+        );
+
+        // Nodes 0 and 1 are 0.01 degrees of latitude apart
+        let distance = scenario.map.distance_to(0.0 * SECONDS, 0, 1).unwrap();
+        assert!((distance.metres() - 1111.95).abs() < 0.5, "{distance:?}");
+
+        let mut exported = Vec::new();
+        export_deployment(&scenario, &mut exported).unwrap();
+        let exported: Value = serde_json::from_slice(&exported).unwrap();
+
+        assert_eq!(exported["site"], original["site"]);
+        let (nodes, original_nodes) = (
+            exported["nodes"].as_array().unwrap(),
+            original["nodes"].as_array().unwrap(),
+        );
+        assert_eq!(nodes.len(), original_nodes.len());
+        for (node, original) in nodes.iter().zip(original_nodes) {
+            let mut node = node.as_object().unwrap().clone();
+            let mut original = original.as_object().unwrap().clone();
+            for field in ["lat", "lon"] {
+                assert_close(
+                    node.remove(field).unwrap().as_f64().unwrap(),
+                    original.remove(field).unwrap().as_f64().unwrap(),
+                );
+            }
+            assert_eq!(node, original);
+        }
+    }
+
+    #[test]
+    fn unknown_presets_are_rejected() {
+        let json = r#"{"nodes":[
+            {"name":"A","role":"CLIENT","lat":0,"lon":0,"modem_preset":"FAST"}
+        ]}"#;
+        assert!(matches!(
+            import_deployment(json.as_bytes()),
+            Err(DeploymentError::UnknownPreset { node: 0, .. })
+        ));
+    }
+}
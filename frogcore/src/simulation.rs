@@ -1,87 +1,273 @@
 use std::{
-    cell::RefCell,
-    collections::{BinaryHeap, HashMap},
+    cell::{Cell, RefCell, RefMut},
+    collections::{BTreeMap, BinaryHeap, HashMap, HashSet, VecDeque},
     i32,
+    rc::Rc,
+    sync::Arc,
+    time::{Duration, Instant},
 };
 
 use crate::{
     node::NodeModel,
     node_location::{NodeLocation, Point},
-    scenario::{Scenario, ScenarioMessage},
-    sim_file::{OutputIdentity, SimOutput},
+    scenario::{Scenario, ScenarioMessage, ScheduledSettingChange, SettingChange},
+    sim_file::{
+        ExecutionStats, NodeStats, OutputIdentity, ScheduledTransmission, SimOutput,
+        TruncationReason,
+    },
     units::{Db, Frequency, Power},
 };
 
 use data_structs::{
-    LogContent, LogItem, LogLevel, LogSource, MessageInfo, NodeSettings, NotifyStatus, SimAction,
-    SimEvent, Transmission,
+    CarrierBand, LogContent, LogItem, LogLevel, LogSource, MessageInfo, NodeSettings, NotifyStatus,
+    ReceptionFailure, ReceptionOutcome, RelayDropReason, SimAction, SimEvent, StepResult,
+    SuppressionReason, TraceDirection, TraceEntry, Transmission, UtilisationMode,
 };
 use models::{TransmissionModel, TransmissionResult};
+use draws::DrawWatch;
+use utilisation::ChannelUsage;
 use rand::{Rng, SeedableRng};
 use rand_chacha::ChaCha12Rng;
 use serde::{Deserialize, Serialize};
+use serde_value::Value;
 use thiserror::Error;
 
 use crate::{
     Time,
-    node::{CustomContent, Header, ImplNodeModel, NodeThread, Notification},
+    node::{
+        BasicHeaderInfo, CustomContent, Destination, GlobalPacketId, Header, HeaderSize,
+        ImplNodeModel, NodeThread, Notification,
+    },
 };
 
+pub mod batch;
 pub mod data_structs;
+pub mod draws;
 mod em;
 pub mod models;
+mod replay;
+mod utilisation;
+
+pub use batch::{BatchProgress, SimJob, run_batch, run_batch_map};
+pub use draws::{
+    DrawCount, DrawDivergence, run_simulation_checking_draws, run_simulation_recording_draws,
+};
+pub use replay::run_replay;
 
 type EventQueue = BinaryHeap<SimEvent>;
 
-const SIM_END: Time = Time::from_seconds(60.0 * 60.0 * 4.0); //Time::from_imilis(i32::MAX / 2);
+pub(crate) const SIM_END: Time = Time::from_seconds(60.0 * 60.0 * 4.0); //Time::from_imilis(i32::MAX / 2);
 
+/// Takes the scenario as anything that converts into an `Arc`
+/// so callers running it more than once can share one copy between runs, on any thread.
 pub fn run_simulation(
     random_seed: u64,
-    scenario: Scenario,
+    scenario: impl Into<Arc<Scenario>>,
     model: NodeModel,
     do_node_logs: bool,
 ) -> SimOutput {
-    let scenario_identity = scenario.identity.clone();
+    let options = RunOptions {
+        node_logs: do_node_logs,
+        ..RunOptions::default()
+    };
+    run_simulation_with(random_seed, scenario, model, options)
+}
 
-    let mut sim = init_simulation(random_seed, scenario, model, do_node_logs);
+/// Same as [`run_simulation`] but with all the [`RunOptions`].
+pub fn run_simulation_with(
+    random_seed: u64,
+    scenario: impl Into<Arc<Scenario>>,
+    model: NodeModel,
+    options: RunOptions,
+) -> SimOutput {
+    run_simulation_with_state(random_seed, scenario, model, options).0
+}
+
+/// Same as [`run_simulation_with`] but also returns the final state of every node.
+pub(crate) fn run_simulation_with_state(
+    random_seed: u64,
+    scenario: impl Into<Arc<Scenario>>,
+    model: NodeModel,
+    options: RunOptions,
+) -> (SimOutput, Vec<NodeModel>) {
+    let sim = init_simulation(random_seed, scenario.into(), model, options);
+    run_initialised(sim, random_seed, options.limits, None)
+}
+
+/// How to carry out a run, see [`run_simulation_with`].
+/// The default is a plain run without node logs.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct RunOptions {
+    /// Keep what node models log, see [`Context::log`]
+    pub node_logs: bool,
+    /// Check the calls node models make to [`Context`] for mistakes, like sending a packet
+    /// addressed to the node itself, and log any found as [`LogContent::ContractViolation`].
+    /// Off by default as the checks cost a little time.
+    pub strict: bool,
+    /// Log the notifications that are dropped without a node model hearing of it,
+    /// as [`LogContent::NotificationSuppressed`] and [`LogContent::NotificationStale`].
+    /// Dropping them is allowed, so these are only for finding out why a model went quiet.
+    pub audit_notifications: bool,
+    /// Stop early if the run goes past any of these
+    pub limits: RunLimits,
+    /// Record every packet each node sends and receives in [`SimOutput::node_traces`].
+    /// The traces hold a copy of each packet for every node that heard it,
+    /// so are only worth the memory when debugging a model.
+    pub traces: bool,
+}
 
+/// Runs a simulation from [`init_simulation`] until it finishes or goes past one of `limits`.
+///
+/// A truncated run gets a log saying which nodes processed the most events,
+/// as a model stuck in a loop is usually the reason.
+/// Rng draws are counted as it goes if there is a `draws` watch, see [`draws`].
+fn run_initialised(
+    mut sim: Simulation,
+    random_seed: u64,
+    limits: RunLimits,
+    mut draws: Option<&mut DrawWatch>,
+) -> (SimOutput, Vec<NodeModel>) {
+    let mut producers = EventProducers::new(sim.nodes.len());
+    let mut truncated = None;
+
+    let start = Instant::now();
     while !sim.finished() {
-        sim.step();
+        let step = sim.step();
+        if let Some(draws) = draws.as_deref_mut()
+            && let Some(node_id) = step.node_id()
+        {
+            draws.after_step(&mut sim, node_id);
+        }
+        producers.count(step);
+
+        if let Some(reason) = limits.exceeded(sim.events_processed, start) {
+            sim.log(
+                format!(
+                    "Run stopped early as it {reason}, most events came from {}",
+                    producers.top(5)
+                ),
+                LogLevel::Error,
+            );
+            truncated = Some(reason);
+            break;
+        }
+    }
+    let wall_time = start.elapsed();
+
+    if let Some(draws) = draws {
+        draws.finish(&mut sim);
+    }
+
+    let logs = std::mem::take(&mut sim.logs);
+    let transmissions = sim.take_transmissions();
+    let mut output = sim.output(random_seed, wall_time, logs, transmissions);
+    output.complete_identity.truncated = truncated;
+    output.node_traces = sim.node_traces.take().unwrap_or_default();
+
+    (output, sim.nodes)
+}
+
+/// Limits on how much work a run can take, so a node model that never stops
+/// producing events can't keep a run going until [`SIM_END`]. No limits by default.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct RunLimits {
+    pub max_events: Option<u64>,
+    pub max_wall_time: Option<Duration>,
+}
+
+impl RunLimits {
+    /// Returns which limit the run has gone past, if any.
+    fn exceeded(&self, events_processed: u64, start: Instant) -> Option<TruncationReason> {
+        if let Some(max) = self.max_events
+            && events_processed >= max
+        {
+            return Some(TruncationReason::MaxEvents(max));
+        }
+
+        // Only looking at the clock now and then as it's slow next to most events
+        if let Some(max) = self.max_wall_time
+            && events_processed.is_multiple_of(1024)
+            && start.elapsed() >= max
+        {
+            return Some(TruncationReason::MaxWallTime(max));
+        }
+
+        None
     }
+}
+
+/// Events processed for each node, split by the kind of action.
+struct EventProducers(Vec<[u64; 5]>);
 
-    let version = "0.1.0";
-    SimOutput {
-        complete_identity: OutputIdentity {
-            scenario_identity,
-            model_id: model_identity_string(&sim.node_identities()),
-            simulation_seed: random_seed,
-            sim_version: version.to_string(),
-        },
-        logs: sim.logs,
-        transmissions: sim.em_field,
+impl EventProducers {
+    const ACTIONS: [&str; 5] = [
+        "GenerateMessage",
+        "SendMessage",
+        "RecieveMessage",
+        "MaybeNotify",
+        "ChangeSetting",
+    ];
+
+    fn new(nodes: usize) -> Self {
+        Self(vec![[0; 5]; nodes])
+    }
+
+    fn count(&mut self, step: StepResult) {
+        let (node_id, action) = match step {
+            StepResult::MessageGenerated { node_id, .. } => (node_id, 0),
+            StepResult::SendAttempted { node_id, .. } => (node_id, 1),
+            StepResult::ReceptionAttempted { node_id, .. } => (node_id, 2),
+            StepResult::NotifyChecked { node_id, .. } => (node_id, 3),
+            StepResult::SettingChanged { node_id, .. } => (node_id, 4),
+            StepResult::Finished | StepResult::ReachedEnd => return,
+        };
+
+        self.0[node_id][action] += 1;
+    }
+
+    /// The `n` largest counts, largest first, e.g. `node 3 MaybeNotify x9000`.
+    fn top(&self, n: usize) -> String {
+        let mut counts: Vec<_> = self
+            .0
+            .iter()
+            .enumerate()
+            .flat_map(|(node_id, x)| x.iter().enumerate().map(move |(a, c)| (*c, node_id, a)))
+            .filter(|x| x.0 > 0)
+            .collect();
+        counts.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+
+        counts
+            .iter()
+            .take(n)
+            .map(|(count, node_id, action)| {
+                format!("node {node_id} {} x{count}", Self::ACTIONS[*action])
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
     }
 }
 
+/// Limits aren't used here, they are for [`run_initialised`].
 fn init_simulation(
     random_seed: u64,
-    scenario: Scenario,
+    scenario: Arc<Scenario>,
     model: NodeModel,
-    do_node_logs: bool,
+    options: RunOptions,
 ) -> Simulation {
     let node_settings = scenario.get_settings();
 
     // Set up Simulation and create node structs
     let mut sim = Simulation::new(
-        scenario.map,
+        scenario.clone(),
         node_settings.into_iter().map(|x| x.into()),
-        scenario.model,
         random_seed,
         model,
-        do_node_logs,
+        options,
     );
 
     // Add message generation to event queue
     sim.enqueue_message_generation(scenario.messages.iter().cloned());
+    sim.enqueue_setting_changes(&scenario.setting_changes);
 
     // Call node init
     sim.initalise_nodes();
@@ -90,7 +276,10 @@ fn init_simulation(
 }
 
 fn model_identity_string(models: &Vec<String>) -> String {
-    let first = models.first().unwrap();
+    // A scenario without nodes runs no models
+    let Some(first) = models.first() else {
+        return String::new();
+    };
     let all_same = models.iter().all(|x| x == first);
 
     if all_same {
@@ -112,10 +301,23 @@ pub struct Context<'a> {
     logs: &'a mut Vec<LogItem>,
     settings: &'a mut NodeSettings,
     rng: &'a RefCell<ChaCha12Rng>,
+    /// Draws the transmission model has taken for this node, see [`NodeStats::model_draws`]
+    model_draws: &'a Cell<u64>,
     transmission: &'a TransmissionModel,
-    em_field: &'a Vec<Transmission>,
+    recent: &'a VecDeque<Transmission>,
+    channel_usage: &'a RefCell<ChannelUsage>,
+    /// Power each transmission arrived at each node with, see [`Simulation::received_power`]
+    received_power: &'a RefCell<Vec<Vec<Option<Db<Power>>>>>,
     graph: &'a NodeLocation,
+    frequency_plan: &'a [CarrierBand],
+    stats: &'a mut NodeStats,
+    store: &'a mut BTreeMap<String, Value>,
+    /// Packets the node has received, see [`Simulation::heard`]
+    heard: &'a HashSet<(usize, u32)>,
+    test_messages: &'a [MessageInfo],
     do_node_logs: bool,
+    strict: bool,
+    audit_notifications: bool,
 }
 
 pub enum NodeError {
@@ -133,6 +335,19 @@ pub enum MessageContent {
 #[error("Provided value was out of range")]
 pub struct NodeUpdateError;
 
+/// A value that didn't fit in a node's persistent store, see [`Context::store_set`].
+#[derive(Debug, Error)]
+#[error("Store would take {needed} bytes but can only hold {limit}")]
+pub struct StoreFullError {
+    pub needed: usize,
+    pub limit: usize,
+}
+
+/// Bytes a persistent store takes up, as MessagePack.
+fn store_size(store: &BTreeMap<String, Value>) -> usize {
+    rmp_serde::to_vec(store).map_or(usize::MAX, |x| x.len())
+}
+
 impl<'a> Context<'a> {
     /// Returns the clock time of the current node
     pub fn clock_time(&self) -> Time {
@@ -164,6 +379,14 @@ impl<'a> Context<'a> {
         Ok(())
     }
 
+    /// Has the radio hop channel activity detection across every spreading factor and receive
+    /// whichever transmission it detects first, instead of only listening on its own.
+    /// It still demodulates one at a time, so one on another spreading factor starting
+    /// while it is receiving is lost. Its own spreading factor is still used to send.
+    pub fn set_sf_scanning(&mut self, scan: bool) {
+        self.settings.scan_sfs = scan;
+    }
+
     pub fn change_coding_rate(&mut self, coding_rate: i32) -> Result<(), NodeUpdateError> {
         if coding_rate < 4 {
             return Err(NodeUpdateError);
@@ -173,6 +396,21 @@ impl<'a> Context<'a> {
         Ok(())
     }
 
+    /// Carrier bands the scenario allows nodes to use.
+    pub fn frequency_plan(&self) -> &[CarrierBand] {
+        self.frequency_plan
+    }
+
+    /// Fails if the band is not in the [`Context::frequency_plan`].
+    pub fn change_carrier_band(&mut self, band: CarrierBand) -> Result<(), NodeUpdateError> {
+        if !self.frequency_plan.contains(&band) {
+            return Err(NodeUpdateError);
+        }
+
+        self.settings.carrier_band = band;
+        Ok(())
+    }
+
     pub fn change_bandwidth(&mut self, bandwidth: Frequency) {
         self.settings.bandwidth = bandwidth;
     }
@@ -186,6 +424,52 @@ impl<'a> Context<'a> {
         Ok(())
     }
 
+    /// Makes the change with the matching `change_` method, failing when it would.
+    /// Always fails for [`SettingChange::Restart`].
+    pub fn change_setting(&mut self, change: SettingChange) -> Result<(), NodeUpdateError> {
+        match change {
+            SettingChange::Sf(sf) => self.change_sf(sf),
+            SettingChange::CodingRate(coding_rate) => self.change_coding_rate(coding_rate),
+            SettingChange::CarrierBand(band) => self.change_carrier_band(band),
+            SettingChange::Bandwidth(bandwidth) => {
+                self.change_bandwidth(bandwidth);
+                Ok(())
+            }
+            SettingChange::Power(power) => self.change_power(power),
+            SettingChange::Restart => Err(NodeUpdateError),
+        }
+    }
+
+    /// Value saved under `key` in the node's persistent store.
+    /// Like flash on a real device, the store keeps its contents when the node restarts.
+    pub fn store_get(&self, key: &str) -> Option<&Value> {
+        self.store.get(key)
+    }
+
+    /// Saves `value` under `key` in the node's persistent store, replacing any value already
+    /// there. Fails without changing the store if it would take more than
+    /// [`NodeSettings::store_bytes`], counting keys and values as MessagePack.
+    pub fn store_set(
+        &mut self,
+        key: impl Into<String>,
+        value: Value,
+    ) -> Result<(), StoreFullError> {
+        let key = key.into();
+        let old = self.store.insert(key.clone(), value);
+
+        let needed = store_size(self.store);
+        let limit = self.settings.store_bytes;
+        if needed > limit {
+            match old {
+                Some(old) => self.store.insert(key, old),
+                None => self.store.remove(&key),
+            };
+            return Err(StoreFullError { needed, limit });
+        }
+
+        Ok(())
+    }
+
     /// Used for transmitting messages in the simulation.
     ///
     /// Enqueues a send event that will be processed with some delay depending on the nodes [`NodeSettings::reaction_time`].
@@ -202,12 +486,20 @@ impl<'a> Context<'a> {
     /// [`MessageContent::GeneratedMessage`] otherwise it will be a custom message. Custom messages are used for the node models
     /// own purposes, likely as part of a routing algorithm.
     pub fn enqueue_send(&mut self, header: impl Into<Header>, message_content: MessageContent) {
+        let header = header.into();
+        if self.strict {
+            self.check_send(&header, &message_content);
+        }
+
+        let delay = self.settings.reaction_time;
+        self.log(|| format!("Send enqueued with {delay} reaction delay"), LogLevel::Debug);
+
         self.events.push(SimEvent {
-            time: self.sim_time + self.settings.reaction_time,
+            time: self.sim_time + delay,
             action: SimAction::SendMessage {
                 node_id: self.node_id,
                 message_content,
-                header: header.into(),
+                header,
             },
         });
     }
@@ -224,14 +516,100 @@ impl<'a> Context<'a> {
         }
     }
 
+    /// Same as [`Context::log`] but for structured log content.
+    pub fn log_content(&mut self, content: impl FnOnce() -> LogContent, level: LogLevel) {
+        if self.do_node_logs {
+            self.logs.push(LogItem {
+                time: self.sim_time,
+                log_level: level,
+                source: LogSource::Node(self.node_id),
+                content: content(),
+            });
+        }
+    }
+
+    /// Records a rebroadcast being dropped from the node's relay queue.
+    /// Always counted in the node's [`NodeStats`], only logged when node logs are on.
+    pub fn relay_dropped(&mut self, packet: GlobalPacketId, reason: RelayDropReason, waited: Time) {
+        self.stats.relay_drops += 1;
+
+        let node_id = self.node_id;
+        self.log_content(
+            || LogContent::RelayDropped {
+                node_id,
+                packet,
+                reason,
+                waited,
+            },
+            LogLevel::Info,
+        );
+    }
+
+    /// Records the node model breaking the contract of a [`Context`] method.
+    /// Always logged, as it's only called in strict mode.
+    fn contract_violation(&mut self, description: String) {
+        self.logs.push(LogItem {
+            time: self.sim_time,
+            log_level: LogLevel::Error,
+            source: LogSource::Node(self.node_id),
+            content: LogContent::ContractViolation {
+                node_id: self.node_id,
+                description,
+            },
+        });
+    }
+
+    /// Always logged, as it's only called when auditing notifications.
+    fn notification_suppressed(&mut self, thread: NodeThread, reason: SuppressionReason) {
+        self.logs.push(LogItem {
+            time: self.sim_time,
+            log_level: LogLevel::Debug,
+            source: LogSource::Node(self.node_id),
+            content: LogContent::NotificationSuppressed {
+                node_id: self.node_id,
+                thread,
+                reason,
+            },
+        });
+    }
+
+    fn check_send(&mut self, header: &Header, message_content: &MessageContent) {
+        if matches!(header.dest(), Destination::Node(id) if id == self.node_id) {
+            self.contract_violation("Sent a packet addressed to itself".into());
+        }
+
+        // The sender is where the packet came from, so only packets being passed on can have
+        // another sender
+        let sender = header.sender();
+        if sender != self.node_id && !self.heard.contains(&(sender, header.packet_id())) {
+            self.contract_violation(format!("Sent a packet from node {sender} it never received"));
+        }
+
+        let size = match message_content {
+            MessageContent::GeneratedMessage(id) => {
+                self.test_messages.get(*id).map_or(0, |x| x.size)
+            }
+            MessageContent::NodeMessage(content) => content.size(),
+            MessageContent::Empty => 0,
+        };
+        if size < 0 {
+            self.contract_violation(format!("Sent a packet with a negative size of {size}"));
+        }
+    }
+
     /// Register a thread for use with [Self::notify_later].
     /// This should be called exactly once for each thread the node model uses.
     /// Usually this should only be called in [NodeModel::initalisation].
     pub fn register_thread(&mut self, thread: NodeThread) {
+        if self.strict && self.notify_status.contains_key(&thread) {
+            self.contract_violation(format!("Registered {thread:?} more than once"));
+        }
+
         self.notify_status.insert(
             thread,
             NotifyStatus {
                 notification: None,
+                tag: 0,
                 at_time: Time::from_seconds(0.0),
             },
         );
@@ -249,14 +627,42 @@ impl<'a> Context<'a> {
         thread: NodeThread,
         should_override: bool,
     ) {
-        let notify_status = self.notify_status.get_mut(&thread).unwrap();
+        self.notify_later_tagged(delay, notif, 0, thread, should_override);
+    }
 
-        if should_override
-            || notify_status.at_time < self.sim_time
-            || notify_status.notification.is_none()
-        {
+    /// Same as [`Self::notify_later`] but `tag` is passed to `NodeModel::get_notified` along with
+    /// the notification, so the node model can tell what it was for.
+    /// The tag is only replaced when the notification is.
+    pub fn notify_later_tagged(
+        &mut self,
+        delay: Time,
+        notif: Notification,
+        tag: u64,
+        thread: NodeThread,
+        should_override: bool,
+    ) {
+        if self.strict && delay < Time::from_seconds(0.0) {
+            self.contract_violation(format!("Asked to be notified {delay} in the past"));
+        }
+
+        let notify_status = self
+            .notify_status
+            .get_mut(&thread)
+            .expect("Threads must be registered before being notified");
+
+        let pending = !should_override
+            && notify_status.at_time >= self.sim_time
+            && notify_status.notification.is_some();
+
+        if pending {
+            if self.audit_notifications {
+                let due = notify_status.at_time;
+                self.notification_suppressed(thread, SuppressionReason::Pending { due });
+            }
+        } else {
             let notify_time = self.sim_time + delay;
             notify_status.notification = Some(notif);
+            notify_status.tag = tag;
             notify_status.at_time = notify_time;
 
             self.events.push(SimEvent {
@@ -276,102 +682,125 @@ impl<'a> Context<'a> {
             .is_some()
     }
 
-    /// Is the channel in use based on what the current node can observe
+    /// Is the channel in use based on what the current node can observe,
+    /// which depends on the node's [`UtilisationMode`]
     pub fn channel_in_use(&self) -> bool {
-        self.transmission.detecting_any_at(self, self.node_id)
+        match self.settings.utilisation_mode {
+            UtilisationMode::CountAllDetected => {
+                self.transmission.detecting_any_at(self, self.node_id)
+            }
+            UtilisationMode::CountDecodedOnly => self.active_transmissions().any(|x| {
+                x.transmitter_id != self.node_id
+                    && x.carrier_band == self.settings.carrier_band
+                    && self.settings.listens_on(x.sf)
+                    && self.transmission.in_range(self.graph, self.node_id, x)
+            }),
+            UtilisationMode::CountDetectedPlusOwnTx => {
+                self.is_transmitting() || self.transmission.detecting_any_at(self, self.node_id)
+            }
+        }
     }
 
-    /// Returns the current nodes calculation of the channel utilisation
-    /// based on what the node has observed and the algorithm from airtime.cpp
+    /// Returns proportion channel utalisation (between 0.0 and 1.0)
+    /// based on what proportion of the time the node saw activity on the channel.
+    /// Full window that is considered is 60 seconds but rolling 10 second
+    /// buckets are used meaning on average the utalisation over the last 55 seconds is being returned.
+    /// Based on airtime.cpp in meshtastic firmware.
+    ///
+    /// What counts as activity is set by the node's [`UtilisationMode`]. By default detections
+    /// are counted even if the decoding was blocked by interference, which the mode's docs
+    /// compare with the firmware.
     pub fn channel_utilisation(&self) -> f64 {
-        self.observed_utalisation(self.node_id)
+        self.channel_usage.borrow_mut().utilisation(self)
     }
 
     /// Generate a random float between the min and max (inclusive..exclusive)
     /// This method should always be used for creating random values in node models
     pub fn rng(&mut self, min: f64, max: f64) -> f64 {
+        self.stats.rng_draws += 1;
         self.rng.borrow_mut().random_range(min..max)
     }
 
-    /// Returns proportion channel utalisation (between 0.0 and 1.0)
-    /// based on what proportion of the time the node could detect activity on the channel
-    /// or was transmitting. Full window that is considered is 60 seconds but rolling 10 second
-    /// buckets are used meaning on average the utalisation over the last 55 seconds is being returned.
-    /// Based on airtime.cpp in meshtastic firmware.
-    ///
-    /// Currently detections are counted even if the decoding was blocked by interference
-    /// but there is some chance this not how the real firmware works. See RadioLibInterface.cpp:handleReceiveInterupt
-    fn observed_utalisation(&self, at_node: usize) -> f64 {
-        // Constants taken from airtime.cpp
-        const CHANNEL_UTILIZATION_PERIODS: i32 = 6;
-        const UTIL_PERIOD_LENGTH: Time = Time::from_seconds(10.0);
-
-        // utalisation is calculated with rolling discrete periods that reset to 0 on rollover
-        // so the most recent period will only be partly filled (time wise)
-        let full_periods = (CHANNEL_UTILIZATION_PERIODS - 1) as f64;
-        let look_back_time = full_periods * UTIL_PERIOD_LENGTH + self.sim_time % UTIL_PERIOD_LENGTH;
-
-        let limit_time = self.sim_time - look_back_time;
-
-        let mut end_clamp = self.sim_time;
-        let start_clamp = limit_time;
-        let mut total = Time::from_seconds(0.0);
-
-        let observation_range = self
-            .em_field
-            .iter()
-            .rev()
-            .take_while(|x| x.end_time >= limit_time)
-            .filter(|x| self.transmission.detected_at(self, at_node, x));
-
-        for x in observation_range {
-            if x.start_time < end_clamp {
-                total = total + x.end_time.min(end_clamp) - x.start_time.max(start_clamp);
-                end_clamp = x.start_time;
-
-                if end_clamp < start_clamp {
-                    break;
-                }
-            }
-        }
-
-        let out = total / look_back_time;
-
-        // TEST
-        assert!(out <= 1.00001 && out >= 0.0, "value was {}", out);
-
-        out
+    /// The simulation rng for transmission models, counting a draw for this node.
+    /// Should be borrowed once for each value taken.
+    pub(super) fn model_rng(&self) -> RefMut<'_, ChaCha12Rng> {
+        self.model_draws.set(self.model_draws.get() + 1);
+        self.rng.borrow_mut()
     }
 
     pub(super) fn active_transmissions(&self) -> impl Iterator<Item = &Transmission> {
-        self.em_field
+        self.recent
             .iter()
             .rev()
             .take_while(|x| x.end_time >= self.sim_time)
     }
 }
 
+/// How far back channel utilisation looks, see [`Context::channel_utilisation`].
+/// Transmissions that ended longer ago than this, and longer ago than the longest air time
+/// reception checks look back over, are moved out of [`Simulation`]'s recent window.
+const RECENT_WINDOW: Time = Time::from_seconds(60.0);
+
 #[derive(Debug, Clone)]
 pub struct Simulation {
     pub sim_time: Time,
     event_queue: EventQueue,
-    graph: NodeLocation,
+    /// Shared with every clone, as it never changes while running
+    scenario: Arc<Scenario>,
     nodes: Vec<NodeModel>,
+    /// What every node starts as, for restarting them
+    fresh_node: NodeModel,
     node_settings: Vec<NodeSettings>,
+    /// What each node's settings were before the run changed any, for the output
+    start_settings: Rc<Vec<NodeSettings>>,
+    /// Persistent store of each node, kept through restarts, see [`Context::store_set`]
+    stores: Vec<BTreeMap<String, Value>>,
     notify_status: Vec<HashMap<NodeThread, NotifyStatus>>,
-    pub em_field: Vec<Transmission>,
+    /// Transmissions moved out of `recent`, ordered by end time.
+    /// Only appended to, so physics queries never have to look through it.
+    /// Use [`Simulation::transmissions`] for every transmission sent so far.
+    em_field: Vec<Transmission>,
+    /// Transmissions that ended recently enough to affect utilisation or reception,
+    /// ordered by end time
+    recent: VecDeque<Transmission>,
+    /// Most transmissions `recent` has held at once
+    peak_recent: usize,
+    /// Longest air time of any transmission so far, which reception checks look back over
+    longest_air_time: Time,
+    /// What each node has observed of the channel, see [`Context::channel_utilisation`]
+    channel_usage: Vec<RefCell<ChannelUsage>>,
     next_trans_id: u32,
+    pub events_processed: u64,
+    node_stats: Vec<NodeStats>,
 
     test_messages: Vec<MessageInfo>,
+    /// (sender, packet id) of every packet each node has received. Only kept in strict mode,
+    /// to tell packets being passed on from ones sent as another node
+    heard: Vec<HashSet<(usize, u32)>>,
+    /// (message id, receiver) pairs already logged as [`LogContent::MessageDelivered`]
+    delivered: HashSet<(usize, usize)>,
 
     pub logs: Vec<LogItem>,
 
     // Output Detail
     do_node_logs: bool,
+    /// Check node models keep to the contract of [`Context`], see [`RunOptions::strict`]
+    strict: bool,
+    /// Log dropped notifications, see [`RunOptions::audit_notifications`]
+    audit_notifications: bool,
+    /// Packets each node sent and received, only kept with [`RunOptions::traces`]
+    node_traces: Option<Vec<Vec<TraceEntry>>>,
 
     // Models
     transmission: TransmissionModel,
+    /// Power each transmission arrived at each node with, indexed by transmission id then node.
+    /// Kept by transmission models that draw random fading so each pair is only drawn once
+    received_power: RefCell<Vec<Vec<Option<Db<Power>>>>>,
     rng: RefCell<ChaCha12Rng>,
+    /// Kept apart from `node_stats` as transmission models only get a shared [`Context`]
+    model_draws: Vec<Cell<u64>>,
+    /// Set when replaying with [`run_replay`], node models are never called then
+    schedule: Option<Vec<ScheduledTransmission>>,
 }
 
 /// Used to create a Context object.
@@ -387,40 +816,67 @@ macro_rules! context {
             notify_status: &mut $sim.notify_status[$node_id],
             settings: &mut $sim.node_settings[$node_id],
             logs: &mut $sim.logs,
-            em_field: &$sim.em_field,
-            graph: &$sim.graph,
+            recent: &$sim.recent,
+            channel_usage: &$sim.channel_usage[$node_id],
+            received_power: &$sim.received_power,
+            graph: &$sim.scenario.map,
+            frequency_plan: &$sim.scenario.frequency_plan,
+            stats: &mut $sim.node_stats[$node_id],
+            store: &mut $sim.stores[$node_id],
+            heard: &$sim.heard[$node_id],
+            test_messages: &$sim.test_messages,
             transmission: &$sim.transmission,
             rng: &$sim.rng,
+            model_draws: &$sim.model_draws[$node_id],
             do_node_logs: $sim.do_node_logs,
+            strict: $sim.strict,
+            audit_notifications: $sim.audit_notifications,
         }
     }};
 }
 
 impl Simulation {
     pub fn new(
-        graph: NodeLocation,
+        scenario: Arc<Scenario>,
         node_settings: impl Iterator<Item = NodeSettings>,
-        transmission: TransmissionModel,
         random_seed: u64,
         node_model: NodeModel,
-        do_node_logs: bool,
+        options: RunOptions,
     ) -> Self {
-        let graph_len = graph.len();
+        let graph_len = scenario.map.len();
+        let node_settings: Vec<_> = node_settings.take(graph_len).collect();
 
         let sim = Simulation {
             sim_time: 0.0.into(),
             event_queue: BinaryHeap::new(),
-            graph,
+            transmission: scenario.model.clone(),
+            scenario,
             em_field: Vec::new(),
+            recent: VecDeque::new(),
+            peak_recent: 0,
+            longest_air_time: Time::from_seconds(0.0),
+            channel_usage: (0..graph_len).map(|_| Default::default()).collect(),
             nodes: (0..graph_len).map(|_| node_model.clone()).collect(),
-            node_settings: node_settings.take(graph_len).collect(),
+            fresh_node: node_model,
+            start_settings: Rc::new(node_settings.clone()),
+            node_settings,
+            stores: vec![BTreeMap::new(); graph_len],
             notify_status: (0..graph_len).map(|_| HashMap::new()).collect(),
             test_messages: Vec::new(),
+            heard: vec![HashSet::new(); graph_len],
+            delivered: HashSet::new(),
             next_trans_id: 0,
-            transmission,
+            events_processed: 0,
+            node_stats: vec![NodeStats::default(); graph_len],
             logs: Vec::new(),
+            received_power: RefCell::new(Vec::new()),
             rng: ChaCha12Rng::seed_from_u64(random_seed).into(),
-            do_node_logs,
+            model_draws: vec![Cell::new(0); graph_len],
+            schedule: None,
+            do_node_logs: options.node_logs,
+            strict: options.strict,
+            audit_notifications: options.audit_notifications,
+            node_traces: options.traces.then(|| vec![Vec::new(); graph_len]),
         };
 
         sim
@@ -439,12 +895,16 @@ impl Simulation {
         });
     }
 
-    pub fn step(&mut self) {
+    /// Processes the next event in the queue and reports what it was.
+    pub fn step(&mut self) -> StepResult {
         let Some(event) = self.event_queue.pop() else {
-            return;
+            return StepResult::Finished;
         };
 
+        self.events_processed += 1;
+
         self.sim_time = event.time;
+        self.prune_recent();
 
         if self.sim_time >= SIM_END {
             self.event_queue.drain().for_each(|x| match x.action {
@@ -452,7 +912,7 @@ impl Simulation {
                 _ => (), //eprintln!("!! Non-notify was in event queue at end !!"),
             });
 
-            return;
+            return StepResult::ReachedEnd;
         }
 
         let action = event.action;
@@ -471,13 +931,18 @@ impl Simulation {
                     MessageContent::GeneratedMessage(message_id),
                     message_info,
                 );
+
+                StepResult::MessageGenerated {
+                    node_id,
+                    message_id,
+                }
             }
             SimAction::RecieveMessage {
                 node_id,
                 transmission_id,
             } => {
                 let this_trans = self
-                    .em_field
+                    .recent
                     .iter()
                     .rev()
                     .find(|x| x.id == transmission_id)
@@ -488,8 +953,16 @@ impl Simulation {
                     .transmission
                     .reception_at(&context, node_id, this_trans);
 
-                let snr = match trans_res {
+                let attempted = |outcome| StepResult::ReceptionAttempted {
+                    node_id,
+                    transmission_id,
+                    outcome,
+                };
+
+                let stats = &mut self.node_stats[node_id];
+                let (snr, rssi) = match trans_res {
                     TransmissionResult::Blocked { blocker_id } => {
+                        stats.blocked += 1;
                         self.log_content(
                             LogContent::TransmissionBlocked {
                                 receiver_id: node_id,
@@ -498,54 +971,174 @@ impl Simulation {
                             },
                             LogLevel::Debug,
                         );
-                        return;
+                        let reason = ReceptionFailure::Blocked {
+                            blocking_transmission_id: blocker_id,
+                        };
+                        self.report_reception_failure(node_id, transmission_id, reason);
+                        return attempted(reason.into());
+                    }
+                    TransmissionResult::TooWeak => {
+                        stats.too_weak += 1;
+                        let reason = ReceptionFailure::TooWeak;
+                        self.report_reception_failure(node_id, transmission_id, reason);
+                        return attempted(reason.into());
                     }
-                    TransmissionResult::TooWeak => return,
-                    TransmissionResult::Success { snr } => snr,
+                    TransmissionResult::WrongSf => {
+                        stats.wrong_sf += 1;
+                        let reason = ReceptionFailure::WrongSf;
+                        self.report_reception_failure(node_id, transmission_id, reason);
+                        return attempted(reason.into());
+                    }
+                    TransmissionResult::Success { snr, rssi } => (snr, rssi),
                 };
 
-                let message_size = self.message_size(&this_trans.message_content);
+                let message_size =
+                    self.message_size(&this_trans.header, &this_trans.message_content);
+                let message_id = match this_trans.message_content {
+                    MessageContent::GeneratedMessage(id) => Some(id),
+                    _ => None,
+                };
 
-                let context = context!(self, node_id);
+                let stats = &mut self.node_stats[node_id];
+                stats.receptions += 1;
+                stats.bytes_received += (message_size + this_trans.header.size()) as u64;
 
-                self.nodes[node_id].receive_message(
-                    context,
-                    &this_trans.header,
-                    this_trans.message_content.clone(),
-                    message_size,
-                    snr,
-                );
+                if self.strict {
+                    let header = &this_trans.header;
+                    self.heard[node_id].insert((header.sender(), header.packet_id()));
+                }
+
+                if self.schedule.is_none() {
+                    let context = context!(self, node_id);
+
+                    self.nodes[node_id].receive_message(
+                        context,
+                        &this_trans.header,
+                        this_trans.message_content.clone(),
+                        message_size,
+                        snr,
+                    );
+                }
+
+                let mode = self.node_settings[node_id].utilisation_mode;
+                if mode == UtilisationMode::CountDecodedOnly {
+                    self.channel_usage[node_id]
+                        .borrow_mut()
+                        .add_decoded(this_trans.start_time, this_trans.end_time);
+                }
+
+                if let Some(traces) = &mut self.node_traces {
+                    traces[node_id].push(TraceEntry {
+                        time: self.sim_time,
+                        direction: TraceDirection::Received,
+                        transmission_id,
+                        header: this_trans.header.clone(),
+                        content: this_trans.message_content.clone(),
+                        size: message_size,
+                        snr: Some(snr),
+                    });
+                }
 
                 self.log_content(
                     LogContent::TransmissionReceived {
                         receiver_id: node_id,
                         transmission_id: this_trans.id,
+                        snr: Some(snr),
+                        rssi: Some(rssi),
                     },
                     LogLevel::Info,
                 );
+
+                if let Some(message_id) = message_id
+                    && self.test_messages[message_id].targets.contains(&node_id)
+                    && self.delivered.insert((message_id, node_id))
+                {
+                    self.log_content(
+                        LogContent::MessageDelivered {
+                            message_id,
+                            receiver_id: node_id,
+                            transmission_id,
+                        },
+                        LogLevel::Info,
+                    );
+                }
+
+                attempted(ReceptionOutcome::Received)
             }
             SimAction::SendMessage {
                 node_id,
                 header,
                 message_content,
             } => {
-                self.try_broadcast(node_id, header, message_content);
+                if let Some(scheduled) = self.scheduled(&header) {
+                    self.node_settings[node_id].sf = scheduled.sf;
+                }
+
+                StepResult::SendAttempted {
+                    node_id,
+                    transmission_id: self.try_broadcast(node_id, header, message_content),
+                }
             }
             SimAction::MaybeNotify { node_id, on_thread } => {
-                let status = self.notify_status[node_id]
-                    .get_mut(&on_thread)
-                    .expect("existed when this action was created");
+                // Only missing if the node restarted since this action was created
+                if let Some(status) = self.notify_status[node_id].get_mut(&on_thread)
+                    && status.at_time == self.sim_time
+                    && let Some(notif) = status.notification
+                {
+                    // Remove notification
+                    status.notification = None;
+
+                    let tag = status.tag;
+                    let context = context!(self, node_id);
+                    self.nodes[node_id].get_notified(context, notif, tag, on_thread);
+
+                    return StepResult::NotifyChecked {
+                        node_id,
+                        notified: true,
+                    };
+                }
 
-                if status.at_time == self.sim_time {
-                    if let Some(notif) = status.notification {
-                        // Remove notification
-                        status.notification = None;
+                if self.audit_notifications {
+                    self.logs.push(LogItem {
+                        time: self.sim_time,
+                        log_level: LogLevel::Debug,
+                        source: LogSource::Node(node_id),
+                        content: LogContent::NotificationStale {
+                            node_id,
+                            thread: on_thread,
+                        },
+                    });
+                }
 
-                        let context = context!(self, node_id);
-                        self.nodes[node_id].get_notified(context, notif, on_thread);
-                    }
+                StepResult::NotifyChecked {
+                    node_id,
+                    notified: false,
                 }
             }
+            SimAction::ChangeSetting { node_id, change } => {
+                let applied = if change == SettingChange::Restart {
+                    self.restart(node_id);
+                    true
+                } else {
+                    let mut context = context!(self, node_id);
+                    context.change_setting(change).is_ok()
+                };
+
+                self.log_content(
+                    LogContent::SettingChanged {
+                        node_id,
+                        change,
+                        applied,
+                    },
+                    if applied {
+                        LogLevel::Info
+                    } else {
+                        LogLevel::Error
+                    },
+                );
+
+                StepResult::SettingChanged { node_id, applied }
+            }
         }
     }
 
@@ -555,6 +1148,7 @@ impl Simulation {
             self.test_messages.push(MessageInfo {
                 size: x.size,
                 targets: x.targets,
+                group: x.group,
             });
 
             for generation in 0..x.num_generations {
@@ -569,6 +1163,33 @@ impl Simulation {
         });
     }
 
+    pub fn enqueue_setting_changes(&mut self, changes: &[ScheduledSettingChange]) {
+        for x in changes {
+            self.event_queue.push(SimEvent {
+                time: x.at,
+                action: SimAction::ChangeSetting {
+                    node_id: x.node_id,
+                    change: x.change,
+                },
+            });
+        }
+    }
+
+    /// Starts the node's model again from scratch, see [`SettingChange::Restart`].
+    fn restart(&mut self, node_id: usize) {
+        self.nodes[node_id] = self.fresh_node.clone();
+        self.notify_status[node_id].clear();
+        self.event_queue.retain(|x| match x.action {
+            SimAction::SendMessage { node_id: sender, .. } => sender != node_id,
+            _ => true,
+        });
+
+        if self.schedule.is_none() {
+            let context = context!(self, node_id);
+            self.nodes[node_id].initalisation(context);
+        }
+    }
+
     pub fn node_identities(&self) -> Vec<String> {
         self.nodes
             .iter()
@@ -586,6 +1207,39 @@ impl Simulation {
         });
     }
 
+    /// Tells the transmitter that `receiver_id` missed the transmission, if the scenario asks
+    /// for it, the node models are running and the packet was meant for `receiver_id`.
+    fn report_reception_failure(
+        &mut self,
+        receiver_id: usize,
+        transmission_id: u32,
+        reason: ReceptionFailure,
+    ) {
+        if !self.scenario.reception_feedback || self.schedule.is_some() {
+            return;
+        }
+
+        let transmission = self
+            .recent
+            .iter()
+            .rev()
+            .find(|x| x.id == transmission_id)
+            .expect("receptions are checked as the transmission ends so it is still recent");
+        let sender = transmission.transmitter_id;
+
+        let intended = match transmission.header.dest() {
+            Destination::Broadcast => true,
+            Destination::Node(id) => id == receiver_id,
+            Destination::Group(group) => self.node_settings[receiver_id].groups.contains(&group),
+        };
+        if !intended {
+            return;
+        }
+
+        let context = context!(self, sender);
+        self.nodes[sender].on_reception_failed(context, &transmission.header, receiver_id, reason);
+    }
+
     fn log_content(&mut self, content: LogContent, level: LogLevel) {
         self.logs.push(LogItem {
             time: self.sim_time,
@@ -594,32 +1248,165 @@ impl Simulation {
             content: content,
         });
     }
+
+    /// Every transmission sent so far, ordered by end time.
+    pub fn transmissions(&self) -> impl Iterator<Item = &Transmission> {
+        self.em_field.iter().chain(&self.recent)
+    }
+
+    /// Moves every transmission out, leaving the simulation with none.
+    fn take_transmissions(&mut self) -> Vec<Transmission> {
+        let mut transmissions = std::mem::take(&mut self.em_field);
+        transmissions.extend(self.recent.drain(..));
+        transmissions
+    }
+
+    /// Output for a run that has finished, made from its `logs` and `transmissions`.
+    fn output(
+        &self,
+        random_seed: u64,
+        wall_time: Duration,
+        logs: Vec<LogItem>,
+        transmissions: Vec<Transmission>,
+    ) -> SimOutput {
+        let stats = ExecutionStats {
+            wall_time_ms: wall_time.as_secs_f64() * 1000.0,
+            events_processed: self.events_processed,
+            peak_em_field: self.peak_recent,
+        };
+
+        let version = "0.2.0";
+        SimOutput {
+            complete_identity: OutputIdentity {
+                scenario_identity: self.scenario.identity.clone(),
+                model_id: model_identity_string(&self.node_identities()),
+                simulation_seed: random_seed,
+                sim_version: version.to_string(),
+                truncated: None,
+                scenario_hash: None,
+            },
+            logs,
+            transmissions,
+            stats,
+            node_stats: (0..self.nodes.len()).map(|x| self.node_stats(x)).collect(),
+            node_stores: self.stores.clone(),
+            node_settings: self.start_settings.to_vec(),
+            node_traces: Vec::new(),
+        }
+    }
+
+    /// Counters for a node so far.
+    fn node_stats(&self, node_id: usize) -> NodeStats {
+        NodeStats {
+            model_draws: self.model_draws[node_id].get(),
+            ..self.node_stats[node_id]
+        }
+    }
+
+    /// Rough number of bytes a clone of the simulation takes up.
+    /// Only counts the collections that grow as it runs, not what the nodes allocate themselves.
+    fn approx_size(&self) -> usize {
+        size_of::<Simulation>()
+            + self.nodes.len() * (size_of::<NodeModel>() + size_of::<NodeStats>())
+            + self.event_queue.len() * size_of::<SimEvent>()
+            + (self.em_field.len() + self.recent.len()) * size_of::<Transmission>()
+            + self.logs.len() * size_of::<LogItem>()
+            + self.test_messages.len() * size_of::<MessageInfo>()
+            + self.delivered.len() * size_of::<(usize, usize)>()
+    }
 }
 
+/// Memory [`LiveSimulation`] spends on snapshots unless told otherwise.
+pub const DEFAULT_SNAPSHOT_BUDGET: usize = 256 * 1024 * 1024;
+
+/// Simulated time between snapshots before any have been thinned out.
+const INITIAL_SNAPSHOT_INTERVAL: Time = Time::from_seconds(5.0);
+
 #[derive(Debug, Clone)]
 pub struct LiveSimulation {
     active: Simulation,
     base: Simulation,
+    /// Earlier states to restart from when going back in time, ordered by time
+    snapshots: Vec<Simulation>,
+    snapshot_interval: Time,
+    /// Bytes the snapshots may take up before every other one is dropped
+    snapshot_budget: usize,
+    snapshot_bytes: usize,
 }
 
 impl LiveSimulation {
     pub fn new(
         random_seed: u64,
-        scenario: Scenario,
+        scenario: impl Into<Arc<Scenario>>,
         model: NodeModel,
         do_node_logs: bool,
     ) -> LiveSimulation {
-        let sim = init_simulation(random_seed, scenario, model, do_node_logs);
+        let options = RunOptions {
+            node_logs: do_node_logs,
+            ..RunOptions::default()
+        };
+        let sim = init_simulation(random_seed, scenario.into(), model, options);
 
         LiveSimulation {
             active: sim.clone(),
             base: sim,
+            snapshots: Vec::new(),
+            snapshot_interval: INITIAL_SNAPSHOT_INTERVAL,
+            snapshot_budget: DEFAULT_SNAPSHOT_BUDGET,
+            snapshot_bytes: 0,
         }
     }
 
-    pub fn inspect_node(&mut self, node_id: usize, at_time: Time) -> &NodeModel {
+    /// Runs the whole simulation once, returning a live simulation left at the end of the run
+    /// along with the same output [`run_simulation`] gives.
+    /// The snapshots taken on the way make going back to any point quick.
+    pub fn run_collecting(
+        random_seed: u64,
+        scenario: impl Into<Arc<Scenario>>,
+        model: NodeModel,
+        do_node_logs: bool,
+    ) -> (LiveSimulation, SimOutput) {
+        let mut live = LiveSimulation::new(random_seed, scenario, model, do_node_logs);
+
+        let start = Instant::now();
+        while !live.finished() {
+            live.step();
+        }
+        let wall_time = start.elapsed();
+
+        let sim = &live.active;
+        let transmissions = sim.transmissions().cloned().collect();
+        let output = sim.output(random_seed, wall_time, sim.logs.clone(), transmissions);
+
+        (live, output)
+    }
+
+    /// Limits the memory used by snapshots to roughly `bytes`.
+    /// Larger scenarios end up with snapshots further apart.
+    pub fn with_snapshot_budget(mut self, bytes: usize) -> Self {
+        self.snapshot_budget = bytes;
+        self
+    }
+
+    /// Processes the next event, carrying on from wherever the simulation is up to.
+    pub fn step(&mut self) -> StepResult {
+        let result = self.active.step();
+        self.maybe_snapshot();
+        result
+    }
+
+    /// Processes every event up to and including `at_time`.
+    /// Going forward continues from the current state while going back
+    /// replays from the latest snapshot before `at_time`.
+    pub fn seek(&mut self, at_time: Time) {
         if at_time < self.active.sim_time {
-            self.active = self.base.clone();
+            self.active = self
+                .snapshots
+                .iter()
+                .rev()
+                .find(|x| x.sim_time <= at_time)
+                .unwrap_or(&self.base)
+                .clone();
         }
 
         while self
@@ -629,9 +1416,640 @@ impl LiveSimulation {
             .map(|x| x.time <= at_time)
             .unwrap_or(false)
         {
-            self.active.step();
+            self.step();
         }
+    }
+
+    fn maybe_snapshot(&mut self) {
+        let last_time = self.snapshots.last().map_or(self.base.sim_time, |x| x.sim_time);
 
+        // Replaying from a snapshot passes times that already have one
+        if self.active.sim_time < last_time + self.snapshot_interval {
+            return;
+        }
+
+        self.snapshot_bytes += self.active.approx_size();
+        self.snapshots.push(self.active.clone());
+
+        if self.snapshot_bytes > self.snapshot_budget && self.snapshots.len() > 1 {
+            let mut index = 0;
+            self.snapshots.retain(|_| {
+                index += 1;
+                index % 2 == 0
+            });
+            self.snapshot_interval = 2.0 * self.snapshot_interval;
+            self.snapshot_bytes = self.snapshots.iter().map(Simulation::approx_size).sum();
+        }
+    }
+
+    pub fn inspect_node(&mut self, node_id: usize, at_time: Time) -> &NodeModel {
+        self.seek(at_time);
         &self.active.nodes[node_id]
     }
+
+    /// State of a node as of the last processed event.
+    pub fn node(&self, node_id: usize) -> &NodeModel {
+        &self.active.nodes[node_id]
+    }
+
+    /// Persistent store of a node as of the last processed event.
+    pub fn node_store(&self, node_id: usize) -> &BTreeMap<String, Value> {
+        &self.active.stores[node_id]
+    }
+
+    pub fn sim_time(&self) -> Time {
+        self.active.sim_time
+    }
+
+    pub fn events_processed(&self) -> u64 {
+        self.active.events_processed
+    }
+
+    pub fn finished(&self) -> bool {
+        self.active.finished()
+    }
+
+    /// Every transmission sent so far, ordered by end time.
+    pub fn em_field(&self) -> impl Iterator<Item = &Transmission> {
+        self.active.transmissions()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        node::{BasicHeader, ModelSelection},
+        scenario::ScenarioIdentity,
+        units::{MINS, SECONDS},
+    };
+
+    fn flood_scenario(nodes: usize) -> Scenario {
+        ScenarioIdentity::for_test(nodes, nodes, 2.0 * MINS, 42).create()
+    }
+
+    fn flood_stats(nodes: usize) -> ExecutionStats {
+        let model = ModelSelection::BasicFlood.into();
+        run_simulation(1, flood_scenario(nodes), model, false).stats
+    }
+
+    #[test]
+    fn execution_stats_grow_with_scenario() {
+        let stats: Vec<_> = [5, 10, 20, 40].map(flood_stats).into();
+
+        for stat in &stats {
+            assert!(stat.events_processed > 0);
+            assert!(stat.peak_em_field > 0);
+            assert!(stat.wall_time_ms >= 0.0);
+        }
+
+        for pair in stats.windows(2) {
+            assert!(pair[0].events_processed < pair[1].events_processed);
+            assert!(pair[0].peak_em_field < pair[1].peak_em_field);
+        }
+    }
+
+    #[test]
+    fn recent_window_stays_small() {
+        let scenario = ScenarioIdentity::for_test(10, 200, 60.0 * MINS, 42).create();
+
+        let model = ModelSelection::BasicFlood.into();
+        let mut sim = init_simulation(1, scenario.into(), model, RunOptions::default());
+
+        let mut largest_window = 0;
+        while !sim.finished() {
+            sim.step();
+            largest_window = largest_window.max(sim.recent.len());
+        }
+
+        assert_eq!(sim.peak_recent, largest_window);
+        let transmissions = sim.take_transmissions();
+        assert!(largest_window * 4 < transmissions.len());
+        assert!(transmissions.windows(2).all(|x| x[0].end_time <= x[1].end_time));
+    }
+
+    #[test]
+    fn live_stepping_matches_full_run() {
+        let model: NodeModel = ModelSelection::BasicFlood.into();
+        let output = run_simulation(1, flood_scenario(10), model.clone(), false);
+        let mut live = LiveSimulation::new(1, flood_scenario(10), model, false);
+
+        let mut started = 0;
+        while !live.finished() {
+            if live.step().started_transmission().is_some() {
+                started += 1;
+            }
+        }
+
+        assert_eq!(live.step(), StepResult::Finished);
+        assert_eq!(started, output.transmissions.len());
+        assert_eq!(live.events_processed(), output.stats.events_processed);
+
+        // Going back replays from the start to the same point
+        let time = live.em_field().nth(started / 2).unwrap().start_time;
+        live.seek(time);
+        assert!(live.sim_time() <= time);
+        assert!(live.em_field().all(|x| x.start_time <= time));
+    }
+
+    #[test]
+    fn snapshots_match_forward_replay() {
+        let model: NodeModel = ModelSelection::BasicFlood.into();
+        let mut live = LiveSimulation::new(1, flood_scenario(10), model.clone(), true)
+            // Small enough that snapshots get thinned out along the way
+            .with_snapshot_budget(64 * 1024);
+
+        let mut rng = ChaCha12Rng::seed_from_u64(7);
+        for _ in 0..20 {
+            let time = Time::from_seconds(rng.random_range(0.0..180.0));
+            let node = rng.random_range(0..10);
+
+            let mut fresh = LiveSimulation::new(1, flood_scenario(10), model.clone(), true);
+            assert_eq!(
+                format!("{:?}", live.inspect_node(node, time)),
+                format!("{:?}", fresh.inspect_node(node, time)),
+            );
+        }
+
+        assert!(!live.snapshots.is_empty());
+        assert!(live.snapshot_interval > INITIAL_SNAPSHOT_INTERVAL);
+    }
+
+    #[test]
+    fn run_collecting_matches_separate_runs() {
+        let model: NodeModel = ModelSelection::BasicFlood.into();
+        let scenario = Arc::new(flood_scenario(10));
+
+        let expected = run_simulation(1, scenario.clone(), model.clone(), true);
+        let (mut live, output) = LiveSimulation::run_collecting(1, scenario, model.clone(), true);
+
+        assert!(live.finished());
+        assert_eq!(output.stats.events_processed, expected.stats.events_processed);
+        assert_eq!(
+            serde_json::to_string(&(&output.logs, &output.transmissions)).unwrap(),
+            serde_json::to_string(&(&expected.logs, &expected.transmissions)).unwrap(),
+        );
+
+        // Going back from the end gives the same node states as stepping forward
+        let time = output.transmissions[output.transmissions.len() / 2].start_time;
+        let mut fresh = LiveSimulation::new(1, flood_scenario(10), model, true);
+        assert_eq!(
+            format!("{:?}", live.inspect_node(3, time)),
+            format!("{:?}", fresh.inspect_node(3, time)),
+        );
+    }
+
+    #[test]
+    fn tagged_notifications_follow_override_rules() {
+        let model = ModelSelection::NoRouting.into();
+        let mut sim = init_simulation(1, flood_scenario(3).into(), model, RunOptions::default());
+        let thread = NodeThread::CacheThread;
+
+        let mut context = context!(sim, 0);
+        context.register_thread(thread);
+
+        let status = |sim: &Simulation| {
+            let status = &sim.notify_status[0][&thread];
+            (status.tag, status.at_time)
+        };
+
+        let mut context = context!(sim, 0);
+        context.notify_later_tagged(1.0 * SECONDS, Notification::Routing, 7, thread, false);
+        assert_eq!(status(&sim), (7, 1.0 * SECONDS));
+
+        // Already pending so left alone
+        let mut context = context!(sim, 0);
+        context.notify_later_tagged(2.0 * SECONDS, Notification::Routing, 8, thread, false);
+        assert_eq!(status(&sim), (7, 1.0 * SECONDS));
+
+        let mut context = context!(sim, 0);
+        context.notify_later_tagged(3.0 * SECONDS, Notification::Routing, 9, thread, true);
+        assert_eq!(status(&sim), (9, 3.0 * SECONDS));
+
+        let mut context = context!(sim, 0);
+        context.notify_later(4.0 * SECONDS, Notification::Routing, thread, true);
+        assert_eq!(status(&sim), (0, 4.0 * SECONDS));
+    }
+
+    #[test]
+    fn carrier_band_changes_keep_to_the_plan() {
+        let model = ModelSelection::NoRouting.into();
+        let mut sim = init_simulation(1, flood_scenario(3).into(), model, RunOptions::default());
+
+        let mut context = context!(sim, 0);
+        assert_eq!(context.frequency_plan(), &[CarrierBand::B868]);
+        assert!(context.change_carrier_band(CarrierBand::B433).is_err());
+        assert_eq!(sim.node_settings[0].carrier_band, CarrierBand::B868);
+
+        let mut scenario = flood_scenario(3);
+        scenario.frequency_plan = vec![CarrierBand::B868, CarrierBand::B433];
+        let model = ModelSelection::NoRouting.into();
+        let mut sim = init_simulation(1, scenario.into(), model, RunOptions::default());
+
+        let mut context = context!(sim, 0);
+        assert_eq!(context.frequency_plan().len(), 2);
+        assert!(context.change_carrier_band(CarrierBand::B433).is_ok());
+        assert_eq!(sim.node_settings[0].carrier_band, CarrierBand::B433);
+    }
+
+    #[test]
+    fn transmit_delays_for_cancelled_packets_are_ignored() {
+        let model = ModelSelection::Meshtastic.into();
+        let output = run_simulation(1, flood_scenario(20), model, true);
+
+        let ignored = output
+            .logs
+            .iter()
+            .filter(|x| {
+                matches!(&x.content, LogContent::Text(text) if text.starts_with("Ignoring"))
+            })
+            .count();
+
+        // Nothing would ever be sent if the tags didn't make it back to the radio interface
+        assert!(ignored > 0);
+        assert!(output.transmissions.len() > ignored);
+    }
+
+    /// The calls a badly written node model might make, in and out of strict mode.
+    fn broken_model_logs(strict: bool) -> Vec<LogItem> {
+        let model = ModelSelection::NoRouting.into();
+        let options = RunOptions {
+            strict,
+            ..RunOptions::default()
+        };
+        let mut sim = init_simulation(1, flood_scenario(3).into(), model, options);
+        // Node 1 has received node 2's first packet, and a message is somehow negatively sized
+        sim.heard[1].insert((2, 0));
+        let negative = MessageContent::GeneratedMessage(sim.test_messages.len());
+        sim.test_messages.push(MessageInfo {
+            size: -5,
+            targets: vec![0],
+            group: None,
+        });
+        let mut context = context!(sim, 1);
+
+        context.register_thread(NodeThread::CacheThread);
+        context.register_thread(NodeThread::CacheThread);
+        context.notify_later(-1.0 * SECONDS, Notification::Routing, NodeThread::CacheThread, true);
+
+        let header = |sender, dest| BasicHeader::new(sender, dest, 0);
+        context.enqueue_send(header(1, Destination::Node(1)), MessageContent::Empty);
+        // Nothing wrong with this one, as the packet is being passed on
+        context.enqueue_send(header(2, Destination::Node(0)), MessageContent::Empty);
+        context.enqueue_send(header(0, Destination::Broadcast), MessageContent::Empty);
+        context.enqueue_send(header(1, Destination::Broadcast), negative);
+
+        sim.logs
+    }
+
+    #[test]
+    fn strict_mode_reports_contract_violations() {
+        let violations: Vec<(usize, String)> = broken_model_logs(true)
+            .into_iter()
+            .filter_map(|x| match x.content {
+                LogContent::ContractViolation {
+                    node_id,
+                    description,
+                } => Some((node_id, description)),
+                _ => None,
+            })
+            .collect();
+
+        let expected = [
+            "Registered CacheThread more than once",
+            "Asked to be notified -1 in the past",
+            "Sent a packet addressed to itself",
+            "Sent a packet from node 0 it never received",
+            "Sent a packet with a negative size of -5",
+        ];
+        assert_eq!(violations, expected.map(|x| (1, x.to_owned())));
+
+        assert!(broken_model_logs(false).is_empty());
+    }
+
+    #[test]
+    fn audit_logs_dropped_notifications() {
+        let dropped = |options| {
+            let model = ModelSelection::NoRouting.into();
+            let mut sim = init_simulation(1, flood_scenario(3).into(), model, options);
+            let mut context = context!(sim, 1);
+
+            let thread = NodeThread::CacheThread;
+            context.register_thread(thread);
+            context.notify_later(1.0 * SECONDS, Notification::Routing, thread, false);
+            // Does nothing as the first is still pending
+            context.notify_later(2.0 * SECONDS, Notification::Routing, thread, false);
+            // Leaves the first one's check with nothing to do
+            context.notify_later(3.0 * SECONDS, Notification::Routing, thread, true);
+
+            while !sim.finished() {
+                sim.step();
+            }
+
+            sim.logs
+                .into_iter()
+                .filter(|x| {
+                    matches!(
+                        x.content,
+                        LogContent::NotificationSuppressed { .. }
+                            | LogContent::NotificationStale { .. }
+                    )
+                })
+                .map(|x| (x.time, x.log_level, x.content))
+                .collect::<Vec<_>>()
+        };
+
+        let logs = dropped(RunOptions {
+            audit_notifications: true,
+            ..RunOptions::default()
+        });
+        assert_eq!(logs.len(), 2, "{logs:?}");
+        assert!(matches!(
+            logs[0],
+            (
+                time,
+                LogLevel::Debug,
+                LogContent::NotificationSuppressed {
+                    node_id: 1,
+                    thread: NodeThread::CacheThread,
+                    reason: SuppressionReason::Pending { due },
+                },
+            ) if time == 0.0 * SECONDS && due == 1.0 * SECONDS
+        ));
+        assert!(matches!(
+            logs[1],
+            (
+                time,
+                LogLevel::Debug,
+                LogContent::NotificationStale {
+                    node_id: 1,
+                    thread: NodeThread::CacheThread,
+                },
+            ) if time == 1.0 * SECONDS
+        ));
+
+        // Independent of the contract checks
+        assert!(dropped(RunOptions::default()).is_empty());
+        assert!(
+            dropped(RunOptions {
+                strict: true,
+                ..RunOptions::default()
+            })
+            .is_empty()
+        );
+    }
+
+    /// A simulation where node 2 behaves like a model that asks to be notified
+    /// every millisecond, with far more events queued than any sane model makes.
+    fn runaway_simulation() -> Simulation {
+        let model = ModelSelection::NoRouting.into();
+        let mut sim = init_simulation(1, flood_scenario(3).into(), model, RunOptions::default());
+        let mut context = context!(sim, 2);
+
+        context.register_thread(NodeThread::CacheThread);
+        for ms in 1..100_000 {
+            let delay = Time::from_milis(ms as f64);
+            context.notify_later(delay, Notification::Routing, NodeThread::CacheThread, true);
+        }
+
+        sim
+    }
+
+    #[test]
+    fn runaway_runs_are_truncated() {
+        let limits = RunLimits {
+            max_events: Some(5000),
+            max_wall_time: None,
+        };
+        let (output, _) = run_initialised(runaway_simulation(), 1, limits, None);
+
+        assert_eq!(output.stats.events_processed, 5000);
+        assert_eq!(
+            output.complete_identity.truncated,
+            Some(TruncationReason::MaxEvents(5000))
+        );
+
+        let diagnostic = output.logs.last().unwrap();
+        assert_eq!(diagnostic.log_level, LogLevel::Error);
+        assert!(
+            diagnostic
+                .content
+                .to_string()
+                .contains("most events came from node 2 MaybeNotify x")
+        );
+
+        let limits = RunLimits {
+            max_events: None,
+            max_wall_time: Some(Duration::ZERO),
+        };
+        let (output, _) = run_initialised(runaway_simulation(), 1, limits, None);
+        assert_eq!(
+            output.complete_identity.truncated,
+            Some(TruncationReason::MaxWallTime(Duration::ZERO))
+        );
+
+        // Well behaved runs are left alone
+        let options = RunOptions {
+            limits: RunLimits {
+                max_events: Some(1_000_000),
+                max_wall_time: Some(Duration::from_secs(600)),
+            },
+            ..RunOptions::default()
+        };
+        let output =
+            run_simulation_with(1, flood_scenario(3), ModelSelection::BasicFlood.into(), options);
+        assert_eq!(output.complete_identity.truncated, None);
+    }
+
+    #[test]
+    fn scheduled_sf_change_shortens_air_time() {
+        let at = 30.0 * MINS;
+        let scenario = Scenario {
+            messages: vec![
+                ScenarioMessage::new(0, vec![1], 10.0 * MINS, 60),
+                ScenarioMessage::new(0, vec![1], 50.0 * MINS, 60),
+            ],
+            setting_changes: vec![
+                ScheduledSettingChange {
+                    node_id: 0,
+                    at,
+                    change: SettingChange::Sf(7),
+                },
+                ScheduledSettingChange {
+                    node_id: 1,
+                    at,
+                    change: SettingChange::Sf(13),
+                },
+            ],
+            ..Scenario::test_pair()
+        };
+        scenario.validate().unwrap();
+
+        let model = ModelSelection::BasicFlood.into();
+        let output = run_simulation(1, scenario, model, false);
+
+        let sent: Vec<&Transmission> = output
+            .transmissions
+            .iter()
+            .filter(|x| x.transmitter_id == 0)
+            .collect();
+        assert_eq!(sent.len(), 2);
+        assert!(sent[0].start_time < at && sent[1].start_time > at);
+        assert_eq!((sent[0].sf, sent[1].sf), (11, 7));
+        assert!(sent[1].airtime() < sent[0].airtime() / 4.0);
+
+        // Node 1 is still listening on SF11 so misses the second one
+        assert_eq!(output.node_stats[1].receptions, 1);
+        assert_eq!(output.node_stats[1].wrong_sf, 1);
+
+        // Node 1 can't use SF13 so keeps relaying on its own spreading factor
+        assert!(output.transmissions.iter().filter(|x| x.transmitter_id == 1).all(|x| x.sf == 11));
+        let changes: Vec<(usize, bool)> = output
+            .logs
+            .iter()
+            .filter_map(|x| match x.content {
+                LogContent::SettingChanged {
+                    node_id, applied, ..
+                } => Some((node_id, applied)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(changes.len(), 2);
+        assert!(changes.contains(&(0, true)) && changes.contains(&(1, false)));
+    }
+
+    #[test]
+    fn traces_follow_a_two_node_exchange() {
+        let scenario = Scenario {
+            messages: vec![
+                ScenarioMessage::new(0, vec![1], 1.0 * MINS, 40),
+                ScenarioMessage::new(1, vec![0], 2.0 * MINS, 20),
+            ],
+            ..Scenario::test_pair()
+        };
+        let model: NodeModel = ModelSelection::BasicFlood.into();
+
+        let untraced = run_simulation(1, scenario.clone(), model.clone(), false);
+        assert!(untraced.node_traces.is_empty());
+
+        let traces = RunOptions {
+            traces: true,
+            ..RunOptions::default()
+        };
+        let output = run_simulation_with(1, scenario, model, traces);
+        assert_eq!(output.node_traces.len(), 2);
+
+        for (node_id, trace) in output.node_traces.iter().enumerate() {
+            assert!(trace.windows(2).all(|x| x[0].time <= x[1].time));
+
+            for entry in trace {
+                let transmission = output
+                    .transmissions
+                    .iter()
+                    .find(|x| x.id == entry.transmission_id)
+                    .unwrap();
+                match entry.direction {
+                    TraceDirection::Sent => {
+                        assert_eq!(transmission.transmitter_id, node_id);
+                        assert_eq!(entry.time, transmission.start_time);
+                        assert!(entry.snr.is_none());
+                    }
+                    TraceDirection::Received => {
+                        assert_ne!(transmission.transmitter_id, node_id);
+                        assert_eq!(entry.time, transmission.end_time);
+                        assert!(entry.snr.is_some());
+                    }
+                }
+            }
+
+            let sent = trace
+                .iter()
+                .filter(|x| x.direction == TraceDirection::Sent)
+                .count();
+            assert_eq!(sent as u64, output.node_stats[node_id].transmissions);
+        }
+
+        // Node 0 sends its message then hears node 1's
+        let first = |node_id: usize, direction| {
+            output.node_traces[node_id]
+                .iter()
+                .find(|x| x.direction == direction)
+                .unwrap()
+        };
+        let sent = first(0, TraceDirection::Sent);
+        assert!(matches!(sent.content, MessageContent::GeneratedMessage(0)));
+        assert_eq!(sent.size, 40);
+        assert_eq!(
+            first(1, TraceDirection::Received).transmission_id,
+            sent.transmission_id
+        );
+        let received = first(0, TraceDirection::Received);
+        assert!(matches!(received.content, MessageContent::GeneratedMessage(1)));
+        assert!(received.time > sent.time);
+
+        let mut csv = Vec::new();
+        crate::sim_file::export_trace_csv(&output, 1, &mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let mut lines = csv.lines();
+        assert_eq!(
+            lines.next(),
+            Some("time,direction,transmission_id,sender,dest,packet_id,content,size,snr")
+        );
+        assert_eq!(lines.count(), output.node_traces[1].len());
+    }
+
+    #[test]
+    fn store_survives_restart() {
+        let scenario = Scenario {
+            messages: vec![
+                ScenarioMessage::new(0, vec![1], 10.0 * MINS, 60),
+                ScenarioMessage::new(0, vec![1], 50.0 * MINS, 60),
+            ],
+            setting_changes: vec![ScheduledSettingChange {
+                node_id: 1,
+                at: 30.0 * MINS,
+                change: SettingChange::Restart,
+            }],
+            ..Scenario::test_pair()
+        };
+        scenario.validate().unwrap();
+
+        let model = ModelSelection::BasicFlood.into();
+        let mut sim = init_simulation(1, scenario.into(), model, RunOptions::default());
+        let mut context = context!(sim, 1);
+        context.store_set("boots", Value::U32(1)).unwrap();
+
+        // Too big for the store, which is left as it was
+        let big = Value::String("x".repeat(5000));
+        assert!(context.store_set("boots", big).is_err());
+        assert_eq!(context.store_get("boots"), Some(&Value::U32(1)));
+
+        // Lost when the node restarts, so never checked
+        context.register_thread(NodeThread::CacheThread);
+        context.notify_later(40.0 * MINS, Notification::Routing, NodeThread::CacheThread, true);
+
+        let (output, _) = run_initialised(sim, 1, RunLimits::default(), None);
+
+        assert!(output.logs.iter().any(|x| matches!(
+            x.content,
+            LogContent::SettingChanged {
+                node_id: 1,
+                change: SettingChange::Restart,
+                applied: true,
+            }
+        )));
+        assert_eq!(output.node_stores[1].get("boots"), Some(&Value::U32(1)));
+        assert!(output.node_stores[0].is_empty());
+
+        // Still working after the restart
+        let delivered: Vec<usize> = output
+            .logs
+            .iter()
+            .filter_map(|x| match x.content {
+                LogContent::MessageDelivered { message_id, .. } => Some(message_id),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(delivered, [0, 1]);
+    }
 }
@@ -0,0 +1,167 @@
+//! Running many simulations at once across a pool of threads.
+//!
+//! Each job is run by [`run_simulation_with`] with its own seed, so a job's output is
+//! the same whichever thread runs it and whatever else is running alongside it.
+
+use std::{
+    num::NonZeroUsize,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicUsize, Ordering},
+    },
+    thread,
+};
+
+use crate::{node::NodeModel, scenario::Scenario, sim_file::SimOutput};
+
+use super::{RunOptions, run_simulation_with};
+
+/// One simulation to run as part of a batch.
+#[derive(Debug, Clone)]
+pub struct SimJob {
+    /// Shared so jobs running the same scenario with different seeds need only one copy
+    pub scenario: Arc<Scenario>,
+    pub model: NodeModel,
+    pub seed: u64,
+    pub options: RunOptions,
+}
+
+/// Passed to the progress callback each time a job finishes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BatchProgress {
+    /// Index of the job that just finished
+    pub job: usize,
+    /// Jobs finished so far, including this one
+    pub completed: usize,
+    pub total: usize,
+}
+
+/// Runs every job on up to `parallelism` threads, defaulting to the number of cores.
+/// Outputs are in the same order as `jobs`.
+///
+/// `progress` is called from the worker threads as each job finishes.
+pub fn run_batch(
+    jobs: Vec<SimJob>,
+    parallelism: Option<usize>,
+    progress: impl Fn(BatchProgress) + Sync,
+) -> Vec<SimOutput> {
+    run_batch_map(jobs, parallelism, progress, |_, _, output| output)
+}
+
+/// Same as [`run_batch`] but each output is passed through `finish` on the worker thread,
+/// along with its job index and scenario, so large batches don't have to keep every
+/// [`SimOutput`].
+pub fn run_batch_map<T, F>(
+    jobs: Vec<SimJob>,
+    parallelism: Option<usize>,
+    progress: impl Fn(BatchProgress) + Sync,
+    finish: F,
+) -> Vec<T>
+where
+    T: Send,
+    F: Fn(usize, &Scenario, SimOutput) -> T + Sync,
+{
+    let total = jobs.len();
+    let threads = parallelism
+        .unwrap_or_else(|| thread::available_parallelism().map_or(1, NonZeroUsize::get))
+        .clamp(1, total.max(1));
+
+    let jobs: Vec<Mutex<Option<SimJob>>> = jobs.into_iter().map(|x| Mutex::new(Some(x))).collect();
+    let outputs: Vec<Mutex<Option<T>>> = (0..total).map(|_| Mutex::new(None)).collect();
+    let next_job = AtomicUsize::new(0);
+    let completed = AtomicUsize::new(0);
+
+    let worker = || {
+        loop {
+            let index = next_job.fetch_add(1, Ordering::Relaxed);
+            let Some(slot) = jobs.get(index) else {
+                break;
+            };
+
+            // Each job is taken exactly once, so the lock is never contended
+            let job = slot.lock().unwrap().take().unwrap();
+            let output =
+                run_simulation_with(job.seed, job.scenario.clone(), job.model, job.options);
+            let output = finish(index, &job.scenario, output);
+            *outputs[index].lock().unwrap() = Some(output);
+
+            progress(BatchProgress {
+                job: index,
+                completed: completed.fetch_add(1, Ordering::Relaxed) + 1,
+                total,
+            });
+        }
+    };
+
+    thread::scope(|scope| {
+        for _ in 0..threads {
+            scope.spawn(worker);
+        }
+    });
+
+    outputs
+        .into_iter()
+        .map(|x| x.into_inner().unwrap().expect("every job is run"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        node::ModelSelection,
+        scenario::ScenarioIdentity,
+        simulation::run_simulation,
+        units::MINS,
+    };
+
+    fn jobs() -> Vec<SimJob> {
+        let scenario = |seed| ScenarioIdentity::for_test(12, 10, 2.0 * MINS, seed).create();
+        let scenarios: Vec<_> = (0..3).map(|seed| Arc::new(scenario(seed))).collect();
+
+        (0..6)
+            .map(|n| SimJob {
+                scenario: scenarios[n as usize / 2].clone(),
+                model: ModelSelection::BasicFlood.into(),
+                seed: 100 + n,
+                options: RunOptions {
+                    node_logs: n % 2 == 0,
+                    ..RunOptions::default()
+                },
+            })
+            .collect()
+    }
+
+    /// Everything but the timing stats, which differ between runs
+    fn fingerprint(output: &SimOutput) -> String {
+        serde_json::to_string(&(
+            &output.logs,
+            &output.transmissions,
+            &output.complete_identity,
+            output.stats.events_processed,
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn batch_results_ignore_thread_count() {
+        let expected: Vec<String> = jobs()
+            .into_iter()
+            .map(|x| run_simulation(x.seed, x.scenario, x.model, x.options.node_logs))
+            .map(|x| fingerprint(&x))
+            .collect();
+
+        for parallelism in [Some(1), Some(3), Some(16), None] {
+            let calls = AtomicUsize::new(0);
+            let outputs = run_batch(jobs(), parallelism, |progress| {
+                assert_eq!(progress.total, 6);
+                calls.fetch_add(1, Ordering::Relaxed);
+            });
+
+            assert_eq!(calls.into_inner(), 6);
+            assert_eq!(outputs.iter().map(fingerprint).collect::<Vec<_>>(), expected);
+        }
+
+        assert!(run_batch(Vec::new(), Some(4), |_| ()).is_empty());
+    }
+}
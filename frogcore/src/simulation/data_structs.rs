@@ -3,13 +3,15 @@ use std::{fmt::Display, ops::Neg};
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    node::{Destination, Header, NodeThread, Notification},
-    scenario::{ScenarioNodeSettings, MovementIndicator},
+    node::{Destination, GlobalPacketId, Header, NodeThread, Notification},
+    scenario::{
+        modem_preset::ModemPreset, MovementIndicator, ScenarioNodeSettings, SettingChange,
+    },
     simulation::MessageContent,
     units::*,
 };
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NodeSettings {
     pub sf: i32,
     pub bandwidth: Frequency,
@@ -34,7 +36,47 @@ pub struct NodeSettings {
     pub use_power: Db<Power>,
     pub carrier_band: CarrierBand,
 
+    /// Delay before a send enqueued by the node model happens
     pub reaction_time: Time,
+    /// Time allowed for processing a packet, used in the meshtastic retransmission delay
+    pub processing_time: Time,
+
+    /// Groups the node is a member of, for messages sent to a [`Destination::Group`].
+    pub groups: Vec<u16>,
+
+    /// Preamble length in symbols, see [`ScenarioNodeSettings::preamble_len`]
+    pub preamble_len: u16,
+    /// Whether the LoRa physical header is sent
+    pub explicit_header: bool,
+    /// Bytes the persistent store can hold, see [`Context::store_set`](super::Context::store_set)
+    pub store_bytes: usize,
+    /// Which transmissions count as channel activity, see [`UtilisationMode`]
+    #[serde(default)]
+    pub utilisation_mode: UtilisationMode,
+    /// Whether the radio scans every spreading factor rather than only listening on
+    /// [`Self::sf`], see [`Context::set_sf_scanning`](super::Context::set_sf_scanning)
+    #[serde(default)]
+    pub scan_sfs: bool,
+}
+
+impl NodeSettings {
+    /// Sets the spreading factor, bandwidth, coding rate and preamble length to the preset's.
+    pub fn apply_preset(&mut self, preset: ModemPreset) {
+        self.sf = preset.sf();
+        self.bandwidth = preset.bandwidth();
+        self.coding_rate = preset.coding_rate();
+        self.preamble_len = preset.preamble_len();
+    }
+
+    /// The preset these radio settings are, if they exactly match one.
+    pub fn preset(&self) -> Option<ModemPreset> {
+        ModemPreset::matching(self.sf, self.bandwidth, self.coding_rate, self.preamble_len)
+    }
+
+    /// Whether the radio can pick up transmissions sent with the spreading factor.
+    pub fn listens_on(&self, sf: i32) -> bool {
+        self.scan_sfs || self.sf == sf
+    }
 }
 
 impl From<ScenarioNodeSettings> for NodeSettings {
@@ -47,13 +89,55 @@ impl From<ScenarioNodeSettings> for NodeSettings {
             use_power: value.max_power,
             carrier_band: value.carrier_band,
             reaction_time: value.reaction_time,
+            processing_time: value.processing_time,
             coding_rate: value.coding_rate,
             is_gateway: value.is_gateway,
             movement_indicator: value.movement_indicator,
+            groups: value.groups,
+            preamble_len: value.preamble_len,
+            explicit_header: value.explicit_header,
+            store_bytes: value.store_bytes,
+            utilisation_mode: value.utilisation_mode,
+            scan_sfs: false,
         }
     }
 }
 
+/// Which transmissions a node counts as activity on the channel, for both
+/// [`Context::channel_utilisation`](super::Context::channel_utilisation) and
+/// [`Context::channel_in_use`](super::Context::channel_in_use).
+///
+/// The firmware's airtime.cpp adds all the airtime it logs to the channel utilisation: its own
+/// transmissions and the packets `RadioLibInterface::handleReceiveInterrupt` logs once a packet
+/// has been received, whether or not it decoded. Nothing the radio only sensed is logged there,
+/// so none of these exactly match the firmware, `CountDetectedPlusOwnTx` being the closest.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum UtilisationMode {
+    /// Every transmission strong enough for the node to detect, even ones it couldn't decode
+    /// because of interference. The node's own transmissions only count if it can detect
+    /// them, which nodes placed by position can but graph nodes without an edge to themselves
+    /// can't
+    #[default]
+    CountAllDetected,
+    /// Only transmissions the node went on to decode. The channel is in use while a
+    /// transmission strong enough to decode is on the air, as whether it will be isn't known yet
+    CountDecodedOnly,
+    /// Every detected transmission and always the node's own
+    CountDetectedPlusOwnTx,
+}
+
+impl UtilisationMode {
+    pub const ALL: [UtilisationMode; 3] = [
+        UtilisationMode::CountAllDetected,
+        UtilisationMode::CountDecodedOnly,
+        UtilisationMode::CountDetectedPlusOwnTx,
+    ];
+
+    pub fn is_default(&self) -> bool {
+        *self == UtilisationMode::default()
+    }
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum CarrierBand {
     B433,
@@ -104,9 +188,33 @@ impl Transmission {
     }
 }
 
+/// A packet one node sent or received, as a packet capture on the node would show it.
+/// Only recorded with [`RunOptions::traces`](super::RunOptions::traces) set.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TraceEntry {
+    /// When the node started sending the packet, or finished receiving it
+    pub time: Time,
+    pub direction: TraceDirection,
+    pub transmission_id: u32,
+    pub header: Header,
+    pub content: MessageContent,
+    /// Bytes of payload, not counting the header
+    pub size: i32,
+    /// Only for received packets
+    pub snr: Option<Db<f64>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TraceDirection {
+    Sent,
+    Received,
+}
+
 #[derive(Clone, Debug)]
 pub struct NotifyStatus {
     pub notification: Option<Notification>,
+    /// Passed back to the node model with the notification
+    pub tag: u64,
     pub at_time: Time,
 }
 
@@ -117,11 +225,16 @@ pub struct MessageInfo {
 
     /// Target node ids
     pub targets: Vec<usize>,
+
+    /// Group the message is sent to, in which case the targets are its members
+    pub group: Option<u16>,
 }
 
 impl MessageInfo {
     pub fn std_destination(&self) -> Destination {
-        if self.targets.len() == 1 {
+        if let Some(group) = self.group {
+            Destination::Group(group)
+        } else if self.targets.len() == 1 {
             Destination::Node(*self.targets.first().expect("checked length"))
         } else {
             Destination::Broadcast
@@ -173,6 +286,152 @@ pub enum SimAction {
         node_id: usize,
         on_thread: NodeThread,
     },
+    /// From the scenario's [`setting_changes`](crate::scenario::Scenario::setting_changes)
+    ChangeSetting {
+        node_id: usize,
+        change: SettingChange,
+    },
+}
+
+/// What a single call to [`Simulation::step`](super::Simulation::step) processed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StepResult {
+    /// There were no events left to process
+    Finished,
+    /// The next event was after the end of the simulation so the rest were dropped
+    ReachedEnd,
+    MessageGenerated {
+        node_id: usize,
+        message_id: usize,
+    },
+    /// `transmission_id` is `None` if the radio was already busy
+    SendAttempted {
+        node_id: usize,
+        transmission_id: Option<u32>,
+    },
+    ReceptionAttempted {
+        node_id: usize,
+        transmission_id: u32,
+        outcome: ReceptionOutcome,
+    },
+    /// `notified` is false if the notification was cancelled or moved to a later time
+    NotifyChecked {
+        node_id: usize,
+        notified: bool,
+    },
+    /// `applied` is false if the node couldn't make the change
+    SettingChanged {
+        node_id: usize,
+        applied: bool,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReceptionOutcome {
+    Received,
+    Blocked { blocking_transmission_id: u32 },
+    TooWeak,
+    WrongSf,
+}
+
+/// Why a node failed to receive a transmission, passed back to the transmitter
+/// when [`Scenario::reception_feedback`](crate::scenario::Scenario::reception_feedback) is set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReceptionFailure {
+    Blocked { blocking_transmission_id: u32 },
+    TooWeak,
+    /// The node was listening on a different spreading factor
+    WrongSf,
+}
+
+impl From<ReceptionFailure> for ReceptionOutcome {
+    fn from(value: ReceptionFailure) -> Self {
+        match value {
+            ReceptionFailure::Blocked {
+                blocking_transmission_id,
+            } => ReceptionOutcome::Blocked {
+                blocking_transmission_id,
+            },
+            ReceptionFailure::TooWeak => ReceptionOutcome::TooWeak,
+            ReceptionFailure::WrongSf => ReceptionOutcome::WrongSf,
+        }
+    }
+}
+
+impl StepResult {
+    /// Id of the node the step was for, `None` if it didn't process an event.
+    pub fn node_id(&self) -> Option<usize> {
+        match self {
+            StepResult::MessageGenerated { node_id, .. }
+            | StepResult::SendAttempted { node_id, .. }
+            | StepResult::ReceptionAttempted { node_id, .. }
+            | StepResult::NotifyChecked { node_id, .. }
+            | StepResult::SettingChanged { node_id, .. } => Some(*node_id),
+            StepResult::Finished | StepResult::ReachedEnd => None,
+        }
+    }
+
+    /// Id of the transmission this step put on air, if any.
+    pub fn started_transmission(&self) -> Option<u32> {
+        match self {
+            StepResult::SendAttempted {
+                transmission_id, ..
+            } => *transmission_id,
+            _ => None,
+        }
+    }
+}
+
+impl Display for StepResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StepResult::Finished => write!(f, "No events left"),
+            StepResult::ReachedEnd => write!(f, "Reached the end of the simulation"),
+            StepResult::MessageGenerated {
+                node_id,
+                message_id,
+            } => write!(f, "Node {} generated message {}", node_id, message_id),
+            StepResult::SendAttempted {
+                node_id,
+                transmission_id: Some(id),
+            } => write!(f, "Node {} started transmission {}", node_id, id),
+            StepResult::SendAttempted {
+                node_id,
+                transmission_id: None,
+            } => write!(f, "Node {} tried to send while its radio was busy", node_id),
+            StepResult::ReceptionAttempted {
+                node_id,
+                transmission_id,
+                outcome,
+            } => {
+                write!(f, "Transmission {} at node {}: ", transmission_id, node_id)?;
+                match outcome {
+                    ReceptionOutcome::Received => write!(f, "received"),
+                    ReceptionOutcome::Blocked {
+                        blocking_transmission_id,
+                    } => write!(f, "blocked by {}", blocking_transmission_id),
+                    ReceptionOutcome::TooWeak => write!(f, "too weak"),
+                    ReceptionOutcome::WrongSf => write!(f, "on another spreading factor"),
+                }
+            }
+            StepResult::NotifyChecked {
+                node_id,
+                notified: true,
+            } => write!(f, "Node {} was notified", node_id),
+            StepResult::NotifyChecked {
+                node_id,
+                notified: false,
+            } => write!(f, "Stale notification for node {}", node_id),
+            StepResult::SettingChanged {
+                node_id,
+                applied: true,
+            } => write!(f, "Node {} changed a setting", node_id),
+            StepResult::SettingChanged {
+                node_id,
+                applied: false,
+            } => write!(f, "Node {} couldn't make a scheduled change", node_id),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -183,7 +442,8 @@ pub struct LogItem {
     pub content: LogContent,
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+/// Ordered from most to least important.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub enum LogLevel {
     Error,
     Info,
@@ -191,6 +451,15 @@ pub enum LogLevel {
     Trace,
 }
 
+impl LogLevel {
+    pub const ALL: [LogLevel; 4] = [
+        LogLevel::Error,
+        LogLevel::Info,
+        LogLevel::Debug,
+        LogLevel::Trace,
+    ];
+}
+
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub enum LogSource {
     Simulation,
@@ -207,12 +476,122 @@ pub enum LogContent {
     TransmissionReceived {
         receiver_id: usize,
         transmission_id: u32,
+        /// Signal to noise ratio the transmission model worked out for the reception,
+        /// `None` in outputs from before it was logged.
+        #[serde(default)]
+        snr: Option<Db<f64>>,
+        /// Estimated received signal strength, the power arriving at the receiver.
+        /// `None` in outputs from before it was logged.
+        #[serde(default)]
+        rssi: Option<Dbm>,
     },
     TransmissionBlocked {
         receiver_id: usize,
         target_transmission_id: u32,
         blocking_transmission_id: u32,
     },
+    /// A generated message reached one of its targets for the first time,
+    /// logged straight after the [`LogContent::TransmissionReceived`] that carried it.
+    MessageDelivered {
+        message_id: usize,
+        receiver_id: usize,
+        transmission_id: u32,
+    },
+    /// A node model broke the contract of a [`Context`](super::Context) method,
+    /// only checked in strict mode
+    ContractViolation {
+        node_id: usize,
+        description: String,
+    },
+    /// A node picked how long to wait before transmitting the packet at the front of its queue.
+    /// The wait is weighted by the `snr` the packet was received with if it's a rebroadcast,
+    /// otherwise by the channel utilisation.
+    TransmitDelay {
+        node_id: usize,
+        snr: Option<Db<f64>>,
+        delay: Time,
+    },
+    /// A node picked the spreading factor to transmit with from the snr of the link it
+    /// expects to be weakest, `None` if it knew nothing about the link.
+    SpreadingFactorChosen {
+        node_id: usize,
+        sf: i32,
+        link_snr: Option<Db<f64>>,
+    },
+    /// A node dropped a rebroadcast from its relay queue before it was sent,
+    /// `waited` being how long it had been queued for.
+    RelayDropped {
+        node_id: usize,
+        packet: GlobalPacketId,
+        reason: RelayDropReason,
+        waited: Time,
+    },
+    /// A node held back a packet as the channel utilisation it saw was over its limit,
+    /// deferring it to try again later or dropping it if `deferred` is false, see
+    /// [`Meshtastic::with_utilisation_gating`](crate::node::Meshtastic::with_utilisation_gating)
+    UtilisationGated {
+        node_id: usize,
+        packet: GlobalPacketId,
+        utilisation: f64,
+        deferred: bool,
+    },
+    /// A scheduled change to a node's settings was made, or skipped if `applied` is false
+    /// as the node couldn't make it.
+    SettingChanged {
+        node_id: usize,
+        change: SettingChange,
+        applied: bool,
+    },
+    /// A node asked to be notified on a thread that already had a notification pending,
+    /// without overriding it, so the request did nothing.
+    /// Only logged with [`RunOptions::audit_notifications`](super::RunOptions::audit_notifications)
+    NotificationSuppressed {
+        node_id: usize,
+        thread: NodeThread,
+        reason: SuppressionReason,
+    },
+    /// A scheduled notification was skipped as the thread's notification had since been replaced,
+    /// delivered already or cleared by a restart.
+    /// Only logged with [`RunOptions::audit_notifications`](super::RunOptions::audit_notifications)
+    NotificationStale { node_id: usize, thread: NodeThread },
+}
+
+/// Why a request to be notified was ignored.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum SuppressionReason {
+    /// The thread already had a notification due at `due`
+    Pending { due: Time },
+}
+
+/// Why a relay queue dropped a rebroadcast.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RelayDropReason {
+    /// Waited longer than the queue's max age
+    TooOld,
+    /// Oldest in the queue when another was added past its max length
+    QueueFull,
+}
+
+impl LogContent {
+    /// Whether `node` sent, received or was blocked from receiving the transmission logged.
+    /// Always false for text.
+    pub fn involves_node(&self, node: usize) -> bool {
+        match self {
+            LogContent::Text(_) => false,
+            LogContent::TransmissionSent { sender_id, .. } => *sender_id == node,
+            LogContent::TransmissionReceived { receiver_id, .. }
+            | LogContent::TransmissionBlocked { receiver_id, .. }
+            | LogContent::MessageDelivered { receiver_id, .. } => *receiver_id == node,
+            LogContent::ContractViolation { node_id, .. }
+            | LogContent::TransmitDelay { node_id, .. }
+            | LogContent::SpreadingFactorChosen { node_id, .. }
+            | LogContent::RelayDropped { node_id, .. }
+            | LogContent::UtilisationGated { node_id, .. }
+            | LogContent::SettingChanged { node_id, .. }
+            | LogContent::NotificationSuppressed { node_id, .. }
+            | LogContent::NotificationStale { node_id, .. } => *node_id == node,
+        }
+    }
 }
 
 impl Display for LogContent {
@@ -230,11 +609,19 @@ impl Display for LogContent {
             LogContent::TransmissionReceived {
                 receiver_id,
                 transmission_id,
-            } => write!(
-                f,
-                "Received transmission {} at node {}",
-                transmission_id, receiver_id
-            ),
+                snr,
+                ..
+            } => {
+                write!(
+                    f,
+                    "Received transmission {} at node {}",
+                    transmission_id, receiver_id
+                )?;
+                match snr {
+                    Some(snr) => write!(f, " with SNR {:.1} dB", snr.as_db_float()),
+                    None => Ok(()),
+                }
+            }
             LogContent::TransmissionBlocked {
                 receiver_id,
                 target_transmission_id,
@@ -244,6 +631,113 @@ impl Display for LogContent {
                 "Tranmission {} blocked at {} by at least {}",
                 target_transmission_id, receiver_id, blocking_transmission_id,
             ),
+            LogContent::MessageDelivered {
+                message_id,
+                receiver_id,
+                transmission_id,
+            } => write!(
+                f,
+                "Message {} delivered to node {} by transmission {}",
+                message_id, receiver_id, transmission_id
+            ),
+            LogContent::ContractViolation {
+                node_id,
+                description,
+            } => write!(f, "Node {} broke a contract: {}", node_id, description),
+            LogContent::TransmitDelay {
+                node_id,
+                snr: Some(snr),
+                delay,
+            } => write!(
+                f,
+                "Node {} waiting {} to transmit, weighted by {}dB snr",
+                node_id,
+                delay,
+                snr.as_db_float()
+            ),
+            LogContent::TransmitDelay {
+                node_id,
+                snr: None,
+                delay,
+            } => write!(f, "Node {} waiting {} to transmit", node_id, delay),
+            LogContent::SpreadingFactorChosen {
+                node_id,
+                sf,
+                link_snr: Some(snr),
+            } => write!(
+                f,
+                "Node {} using SF{} for a link with {}dB snr",
+                node_id,
+                sf,
+                snr.as_db_float()
+            ),
+            LogContent::SpreadingFactorChosen {
+                node_id,
+                sf,
+                link_snr: None,
+            } => write!(f, "Node {} using SF{} for an unknown link", node_id, sf),
+            LogContent::RelayDropped {
+                node_id,
+                packet,
+                reason,
+                waited,
+            } => write!(
+                f,
+                "Node {} dropped relay of {:?} after {}, {}",
+                node_id, packet, waited, reason
+            ),
+            LogContent::UtilisationGated {
+                node_id,
+                packet,
+                utilisation,
+                deferred,
+            } => write!(
+                f,
+                "Node {} {} {:?} at {:.0}% channel utilisation",
+                node_id,
+                if *deferred { "deferred" } else { "dropped" },
+                packet,
+                utilisation * 100.0
+            ),
+            LogContent::SettingChanged {
+                node_id,
+                change,
+                applied: true,
+            } => write!(f, "Node {} changed to {}", node_id, change),
+            LogContent::SettingChanged {
+                node_id,
+                change,
+                applied: false,
+            } => write!(f, "Node {} couldn't change to {}", node_id, change),
+            LogContent::NotificationSuppressed {
+                node_id,
+                thread,
+                reason,
+            } => write!(
+                f,
+                "Node {} notification on {:?} suppressed, {}",
+                node_id, thread, reason
+            ),
+            LogContent::NotificationStale { node_id, thread } => {
+                write!(f, "Node {} stale notification on {:?} skipped", node_id, thread)
+            }
+        }
+    }
+}
+
+impl Display for SuppressionReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SuppressionReason::Pending { due } => write!(f, "one already due at {}", due),
+        }
+    }
+}
+
+impl Display for RelayDropReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RelayDropReason::TooOld => write!(f, "too old"),
+            RelayDropReason::QueueFull => write!(f, "queue full"),
         }
     }
 }
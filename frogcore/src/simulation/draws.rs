@@ -0,0 +1,239 @@
+//! Counting the values taken from the simulation rng, to find where a run stopped
+//! reproducing an earlier one.
+//!
+//! Runs with the same seed, scenario and model take the same values from the rng in the same
+//! order, so once a change to a model alters how many values one node takes, every value
+//! after it is different. Comparing draw counts finds that first point rather than wherever
+//! the outputs first happen to differ.
+
+use std::{fmt::Display, sync::Arc};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{node::NodeModel, scenario::Scenario, sim_file::SimOutput, units::Time};
+
+use super::{RunOptions, Simulation, data_structs::LogLevel, init_simulation, run_initialised};
+
+/// A node's draw totals after a step in which it took something from the rng.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct DrawCount {
+    /// Events processed when counted, 0 for draws made while initialising
+    pub step: u64,
+    pub time: Time,
+    pub node_id: usize,
+    /// See [`NodeStats::rng_draws`](crate::sim_file::NodeStats::rng_draws)
+    pub rng_draws: u64,
+    /// See [`NodeStats::model_draws`](crate::sim_file::NodeStats::model_draws)
+    pub model_draws: u64,
+}
+
+impl Display for DrawCount {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "node {} at {} with {} node and {} model draws",
+            self.node_id, self.time, self.rng_draws, self.model_draws
+        )
+    }
+}
+
+/// First point a run's draws differed from the ones expected of it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DrawDivergence {
+    /// When the earlier of the two counts was made, as the runs had diverged by then
+    pub time: Time,
+    /// Node the earlier of the two counts was for
+    pub node_id: usize,
+    /// `None` if the run drew more than expected
+    pub expected: Option<DrawCount>,
+    /// `None` if the run finished having drawn less than expected
+    pub actual: Option<DrawCount>,
+}
+
+impl Display for DrawDivergence {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let count = |x: Option<DrawCount>| x.map_or("nothing".to_string(), |x| x.to_string());
+        write!(
+            f,
+            "Rng draws diverged at node {} at {}, expected {} but got {}",
+            self.node_id,
+            self.time,
+            count(self.expected),
+            count(self.actual)
+        )
+    }
+}
+
+/// Same as [`run_simulation_with`](super::run_simulation_with) but also returns the draw
+/// counts, for a later run to be checked against with [`run_simulation_checking_draws`].
+pub fn run_simulation_recording_draws(
+    random_seed: u64,
+    scenario: impl Into<Arc<Scenario>>,
+    model: NodeModel,
+    options: RunOptions,
+) -> (SimOutput, Vec<DrawCount>) {
+    let mut sim = init_simulation(random_seed, scenario.into(), model, options);
+    let mut watch = DrawWatch::new(&mut sim, None);
+    let output = run_initialised(sim, random_seed, options.limits, Some(&mut watch)).0;
+
+    (output, watch.counts)
+}
+
+/// Same as [`run_simulation_with`](super::run_simulation_with) but compares the draw counts
+/// with `expected`, from [`run_simulation_recording_draws`].
+/// The first divergence is returned and logged as an error.
+pub fn run_simulation_checking_draws(
+    random_seed: u64,
+    scenario: impl Into<Arc<Scenario>>,
+    model: NodeModel,
+    options: RunOptions,
+    expected: &[DrawCount],
+) -> (SimOutput, Option<DrawDivergence>) {
+    let sim = init_simulation(random_seed, scenario.into(), model, options);
+    check_draws(sim, random_seed, options, expected)
+}
+
+fn check_draws(
+    mut sim: Simulation,
+    random_seed: u64,
+    options: RunOptions,
+    expected: &[DrawCount],
+) -> (SimOutput, Option<DrawDivergence>) {
+    let mut watch = DrawWatch::new(&mut sim, Some(expected));
+    let output = run_initialised(sim, random_seed, options.limits, Some(&mut watch)).0;
+
+    (output, watch.divergence)
+}
+
+/// Counts draws as a run goes, checking them against `expected` if there is one.
+pub(super) struct DrawWatch<'a> {
+    counts: Vec<DrawCount>,
+    /// Totals each node had when it was last counted, as (node, model) draws
+    last: Vec<(u64, u64)>,
+    expected: Option<&'a [DrawCount]>,
+    divergence: Option<DrawDivergence>,
+}
+
+impl<'a> DrawWatch<'a> {
+    /// Counts any draws made while the nodes were initialised.
+    fn new(sim: &mut Simulation, expected: Option<&'a [DrawCount]>) -> Self {
+        let nodes = sim.nodes.len();
+        let mut watch = Self {
+            counts: Vec::new(),
+            last: vec![(0, 0); nodes],
+            expected,
+            divergence: None,
+        };
+
+        for node_id in 0..nodes {
+            watch.after_step(sim, node_id);
+        }
+
+        watch
+    }
+
+    /// Call after each step with the node it was for.
+    pub(super) fn after_step(&mut self, sim: &mut Simulation, node_id: usize) {
+        let stats = sim.node_stats(node_id);
+        let totals = (stats.rng_draws, stats.model_draws);
+        if totals == self.last[node_id] {
+            return;
+        }
+        self.last[node_id] = totals;
+
+        let count = DrawCount {
+            step: sim.events_processed,
+            time: sim.sim_time,
+            node_id,
+            rng_draws: totals.0,
+            model_draws: totals.1,
+        };
+        self.counts.push(count);
+
+        if let Some(expected) = self.expected
+            && self.divergence.is_none()
+        {
+            let expected = expected.get(self.counts.len() - 1).copied();
+            if expected != Some(count) {
+                self.diverged(sim, expected, Some(count));
+            }
+        }
+    }
+
+    /// Call once the run has finished, to catch a run that drew less than expected.
+    pub(super) fn finish(&mut self, sim: &mut Simulation) {
+        if let Some(expected) = self.expected
+            && self.divergence.is_none()
+            && let Some(missing) = expected.get(self.counts.len()).copied()
+        {
+            self.diverged(sim, Some(missing), None);
+        }
+    }
+
+    fn diverged(
+        &mut self,
+        sim: &mut Simulation,
+        expected: Option<DrawCount>,
+        actual: Option<DrawCount>,
+    ) {
+        let earlier = match (expected, actual) {
+            (Some(expected), Some(actual)) if expected.step < actual.step => expected,
+            (_, Some(actual)) => actual,
+            (Some(expected), None) => expected,
+            (None, None) => unreachable!("only called once the counts differ"),
+        };
+
+        let divergence = DrawDivergence {
+            time: earlier.time,
+            node_id: earlier.node_id,
+            expected,
+            actual,
+        };
+        sim.log(divergence.to_string(), LogLevel::Error);
+        self.divergence = Some(divergence);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        node::{BasicFlood, ModelSelection},
+        scenario::ScenarioIdentity,
+        units::MINS,
+    };
+
+    fn scenario() -> Scenario {
+        ScenarioIdentity::for_test(12, 20, 5.0 * MINS, 2).create()
+    }
+
+    #[test]
+    fn divergence_is_found_at_the_perturbed_node() {
+        let model: NodeModel = ModelSelection::ProbabilisticFlood.into();
+        let (output, expected) =
+            run_simulation_recording_draws(1, scenario(), model.clone(), RunOptions::default());
+
+        assert!(expected.iter().any(|x| x.rng_draws > 0));
+        assert!(expected.iter().any(|x| x.model_draws > 0));
+        for (id, stats) in output.node_stats.iter().enumerate() {
+            let last = expected.iter().rev().find(|x| x.node_id == id);
+            let totals = last.map_or((0, 0), |x| (x.rng_draws, x.model_draws));
+            assert_eq!((stats.rng_draws, stats.model_draws), totals);
+        }
+
+        let options = RunOptions::default();
+        let (_, divergence) =
+            run_simulation_checking_draws(1, scenario(), model.clone(), options, &expected);
+        assert_eq!(divergence, None);
+
+        // Node 3 always rebroadcasts so never draws to decide whether to
+        let mut sim = init_simulation(1, scenario().into(), model, RunOptions::default());
+        sim.nodes[3] = BasicFlood::new().into();
+        let (output, divergence) = check_draws(sim, 1, RunOptions::default(), &expected);
+
+        let divergence = divergence.expect("node 3 stopped drawing");
+        assert_eq!(divergence.node_id, 3);
+        assert_eq!(divergence.expected.map(|x| x.node_id), Some(3));
+        assert!(output.logs.iter().any(|x| x.content.to_string() == divergence.to_string()));
+    }
+}
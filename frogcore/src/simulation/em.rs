@@ -1,5 +1,5 @@
 use super::*;
-use crate::{calculate_air_time, context};
+use crate::{calculate_air_time, context, node::HeaderSize};
 
 impl Simulation {
     /// Returns a new ID for a new transmission struct.
@@ -17,17 +17,17 @@ impl Simulation {
     }
 
     pub(super) fn active_transmissions(&self) -> impl Iterator<Item = &Transmission> {
-        self.em_field
+        self.recent
             .iter()
             .rev()
             .take_while(|x| x.end_time >= self.sim_time)
     }
 
-    pub(super) fn message_size(&self, message_content: &MessageContent) -> i32 {
+    pub(super) fn message_size(&self, header: &Header, message_content: &MessageContent) -> i32 {
         match message_content {
             MessageContent::GeneratedMessage(id) => self.test_messages[*id].size,
             MessageContent::NodeMessage(custom_content) => custom_content.size(),
-            MessageContent::Empty => 0,
+            MessageContent::Empty => self.scheduled(header).map_or(0, |x| x.size),
         }
     }
 
@@ -39,20 +39,49 @@ impl Simulation {
         sender_id: usize,
         header: Header,
         message_content: MessageContent,
-    ) {
+    ) -> Option<u32> {
         if self.is_transmitting(sender_id) {
-            let context = context!(self, sender_id);
-            self.nodes[sender_id]
-                .handle_error(context, NodeError::RadioBusyError(header, message_content));
+            if self.schedule.is_some() {
+                self.log(
+                    format!("Node {sender_id} was still transmitting so skipped {header:?}"),
+                    LogLevel::Error,
+                );
+            } else {
+                let context = context!(self, sender_id);
+                self.nodes[sender_id]
+                    .handle_error(context, NodeError::RadioBusyError(header, message_content));
+            }
 
-            return;
+            return None;
         }
 
         let transmission_id = self.new_trans_id();
 
         let settings = &self.node_settings[sender_id];
-        let message_size = self.message_size(&message_content);
-        let end_time = self.sim_time + calculate_air_time(message_size + header.size(), settings);
+        let message_size = self.message_size(&header, &message_content);
+        let air_time = calculate_air_time(message_size + header.size(), settings);
+        let end_time = self.sim_time + air_time;
+
+        if air_time > self.longest_air_time {
+            self.longest_air_time = air_time;
+        }
+
+        let stats = &mut self.node_stats[sender_id];
+        stats.transmissions += 1;
+        stats.tx_airtime += air_time.seconds();
+        stats.bytes_sent += (message_size + header.size()) as u64;
+
+        if let Some(traces) = &mut self.node_traces {
+            traces[sender_id].push(TraceEntry {
+                time: self.sim_time,
+                direction: TraceDirection::Sent,
+                transmission_id,
+                header: header.clone(),
+                content: message_content.clone(),
+                size: message_size,
+                snr: None,
+            });
+        }
 
         let transmission = Transmission {
             id: transmission_id,
@@ -70,7 +99,7 @@ impl Simulation {
         self.insert_transmission(transmission);
 
         // Loop over adj nodes and add recieve event
-        for id in self.graph.get_adj(sender_id) {
+        for id in self.scenario.map.get_adj(sender_id) {
             self.event_queue.push(SimEvent {
                 time: end_time,
                 action: SimAction::RecieveMessage {
@@ -89,18 +118,36 @@ impl Simulation {
                 transmission_id,
             },
         });
+
+        Some(transmission_id)
     }
 
-    /// Insert transmission into em_field based on its end_time
+    /// Insert transmission into the recent window based on its end_time
     pub(super) fn insert_transmission(&mut self, transmission: Transmission) {
         let insert_pos = self
-            .em_field
+            .recent
             .iter()
             .enumerate()
             .rev()
             .find(|(_, x)| x.end_time < transmission.end_time)
             .map_or(0, |(n, _)| n + 1);
 
-        self.em_field.insert(insert_pos, transmission);
+        self.recent.insert(insert_pos, transmission);
+        self.peak_recent = self.peak_recent.max(self.recent.len());
+    }
+
+    /// Moves transmissions that ended too long ago for any physics query to look at
+    /// from the recent window to the end of `em_field`, keeping it in end time order.
+    pub(super) fn prune_recent(&mut self) {
+        let look_back = if self.longest_air_time > RECENT_WINDOW {
+            self.longest_air_time
+        } else {
+            RECENT_WINDOW
+        };
+        let limit = self.sim_time - look_back;
+
+        while self.recent.front().is_some_and(|x| x.end_time < limit) {
+            self.em_field.extend(self.recent.pop_front());
+        }
     }
 }
@@ -1,10 +1,11 @@
-use std::{cell::RefCell, f64::consts::PI};
+use std::f64::consts::PI;
 
 use rand::Rng;
 pub use rand_distr::{Distribution, Normal, Uniform};
 use serde::{Deserialize, Serialize};
+use thiserror::Error;
 
-use crate::{units::*, SNR_MAX, SNR_MIN};
+use crate::{node_location::NodeLocation, units::*, SNR_MAX, SNR_MIN};
 
 use super::{data_structs::Transmission, Context};
 
@@ -74,6 +75,20 @@ macro_rules! pathloss_model {
 
 pathloss_model!(NoPathloss, AdjustedFreeSpacePathLoss, LinearPathLoss);
 
+impl PathlossModel {
+    /// One of each variant with typical settings, for choosing between them.
+    pub fn examples() -> Vec<PathlossModel> {
+        vec![
+            NoPathloss.into(),
+            free_space_path_loss().into(),
+            LinearPathLoss {
+                loss_rate: DbPerLength::from_db_per_metre(0.1),
+            }
+            .into(),
+        ]
+    }
+}
+
 trait ImplPathlossModel {
     fn power_at_reciever(
         &self,
@@ -199,9 +214,12 @@ impl Default for LogPathLoss {
 }
 
 pub enum TransmissionResult {
-    Success { snr: Db<f64> },
+    /// `rssi` is the power arriving at the receiver, before any clamping of the SNR.
+    Success { snr: Db<f64>, rssi: Dbm },
     TooWeak,
     Blocked { blocker_id: u32 },
+    /// The receiver was listening on, or scanning and locked on to, a different spreading factor
+    WrongSf,
 }
 
 macro_rules! transmission_model {
@@ -251,6 +269,22 @@ macro_rules! transmission_model {
                     )*
                 }
             }
+
+            /// Returns true if the transmission is strong enough to be read at the node
+            /// without any random fading or interference.
+            /// Ignores the node's settings, so callers check its carrier band and spreading factor.
+            pub fn in_range(
+                &self,
+                map: &NodeLocation,
+                at_node: usize,
+                transmission: &Transmission,
+            ) -> bool{
+                match self {
+                    $(
+                        TransmissionModel::$variant(inner) => inner.in_range(map, at_node, transmission),
+                    )*
+                }
+            }
         }
 
         $(
@@ -267,7 +301,163 @@ type PairWiseNormal = PairWiseCaptureEffect<Normal<f64>>;
 type PairWiseNone = PairWiseCaptureEffect<NoneDist>;
 type PairWiseUniform = PairWiseCaptureEffect<Uniform<f64>>;
 
-transmission_model!(PairWiseNormal, PairWiseNone, PairWiseUniform);
+transmission_model!(PairWiseNormal, PairWiseNone, PairWiseUniform, BernoulliLoss);
+
+impl TransmissionModel {
+    /// Path loss of the capture effect model underneath any [`BernoulliLoss`].
+    pub fn path_loss(&self) -> &PathlossModel {
+        match self {
+            TransmissionModel::PairWiseNormal(inner) => &inner.path_loss,
+            TransmissionModel::PairWiseNone(inner) => &inner.path_loss,
+            TransmissionModel::PairWiseUniform(inner) => &inner.path_loss,
+            TransmissionModel::BernoulliLoss(inner) => inner.inner.path_loss(),
+        }
+    }
+
+    /// One of each variant with typical settings, for choosing between them.
+    pub fn examples() -> Vec<TransmissionModel> {
+        let base = PairWiseCaptureEffect::default();
+
+        vec![
+            base.clone()
+                .with_fading(Normal::new(0.0, 4.0).expect("standard deviation is positive"))
+                .into(),
+            base.clone().into(),
+            base.clone()
+                .with_fading(Uniform::new(-4.0, 4.0).expect("range is not empty"))
+                .into(),
+            BernoulliLoss::new(base.into(), 0.1).into(),
+        ]
+    }
+
+    /// Fading of the capture effect model underneath any [`BernoulliLoss`].
+    pub fn fading(&self) -> FadingSettings {
+        match self {
+            TransmissionModel::PairWiseNormal(inner) => FadingSettings::Normal {
+                mean: inner.random_fading.mean(),
+                std_dev: inner.random_fading.std_dev(),
+            },
+            TransmissionModel::PairWiseNone(_) => FadingSettings::None,
+            TransmissionModel::PairWiseUniform(inner) => uniform_settings(&inner.random_fading),
+            TransmissionModel::BernoulliLoss(inner) => inner.inner.fading(),
+        }
+    }
+
+    /// Changes the fading of the capture effect model underneath any [`BernoulliLoss`],
+    /// keeping its path loss and noise temperature.
+    /// The model is left as it was if the settings aren't a valid distribution.
+    pub fn set_fading(&mut self, fading: FadingSettings) -> Result<(), FadingError> {
+        let base = match self {
+            TransmissionModel::PairWiseNormal(inner) => inner.clone().with_fading(NoneDist),
+            TransmissionModel::PairWiseNone(inner) => inner.clone(),
+            TransmissionModel::PairWiseUniform(inner) => inner.clone().with_fading(NoneDist),
+            TransmissionModel::BernoulliLoss(inner) => return inner.inner.set_fading(fading),
+        };
+
+        *self = match fading {
+            FadingSettings::None => base.into(),
+            // A negative standard deviation is allowed by `Normal` but means nothing here
+            FadingSettings::Normal { std_dev, .. } if std_dev < 0.0 => {
+                return Err(FadingError::StdDev);
+            }
+            FadingSettings::Normal { mean, std_dev } => {
+                let normal = Normal::new(mean, std_dev).map_err(|_| FadingError::StdDev)?;
+                base.with_fading(normal).into()
+            }
+            FadingSettings::Uniform { min, max } => {
+                let uniform = Uniform::new(min, max).map_err(|_| FadingError::Range)?;
+                base.with_fading(uniform).into()
+            }
+        };
+
+        Ok(())
+    }
+}
+
+/// Random fading of a [`PairWiseCaptureEffect`] by its parameters, in dB.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FadingSettings {
+    None,
+    Normal { mean: f64, std_dev: f64 },
+    /// From `min` up to but not including `max`
+    Uniform { min: f64, max: f64 },
+}
+
+#[derive(Debug, Error)]
+pub enum FadingError {
+    #[error("Standard deviation must be finite and not negative")]
+    StdDev,
+    #[error("Range must be finite and not empty")]
+    Range,
+}
+
+/// [`Uniform`] doesn't give its range back, but serialises as the bottom of it and its width.
+fn uniform_settings(uniform: &Uniform<f64>) -> FadingSettings {
+    #[derive(Deserialize)]
+    struct Stored {
+        low: f64,
+        scale: f64,
+    }
+
+    let stored: Stored = serde_json::to_value(uniform)
+        .and_then(serde_json::from_value)
+        .expect("uniform float distributions serialise as their low value and scale");
+
+    FadingSettings::Uniform {
+        min: stored.low,
+        max: stored.low + stored.scale,
+    }
+}
+
+/// Wraps another model, losing each reception it would let through with a fixed probability.
+/// For checking routing logic against simple independent losses rather than radio physics.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BernoulliLoss {
+    /// Decides detection, blocking, and which receptions could succeed
+    pub inner: Box<TransmissionModel>,
+
+    /// Chance of losing each otherwise successful reception, from 0 to 1
+    pub loss_probability: f64,
+}
+
+impl BernoulliLoss {
+    pub fn new(inner: TransmissionModel, loss_probability: f64) -> Self {
+        Self {
+            inner: Box::new(inner),
+            loss_probability,
+        }
+    }
+}
+
+impl ImplTransmissionModel for BernoulliLoss {
+    fn detected_at(&self, sim: &Context, at_node: usize, transmission: &Transmission) -> bool {
+        self.inner.detected_at(sim, at_node, transmission)
+    }
+
+    fn reception_at(
+        &self,
+        sim: &Context,
+        at_node: usize,
+        target: &Transmission,
+    ) -> TransmissionResult {
+        match self.inner.reception_at(sim, at_node, target) {
+            TransmissionResult::Success { .. }
+                if sim.model_rng().random::<f64>() < self.loss_probability =>
+            {
+                TransmissionResult::TooWeak
+            }
+            result => result,
+        }
+    }
+
+    fn detecting_any_at(&self, sim: &Context, at_node: usize) -> bool {
+        self.inner.detecting_any_at(sim, at_node)
+    }
+
+    fn in_range(&self, map: &NodeLocation, at_node: usize, transmission: &Transmission) -> bool {
+        self.inner.in_range(map, at_node, transmission)
+    }
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct NoneDist;
@@ -290,6 +480,8 @@ trait ImplTransmissionModel {
 
     // Returns true of the node can detect a broadcast (even under blocking interference) and false otherwise.
     fn detecting_any_at(&self, sim: &Context, at_node: usize) -> bool;
+
+    fn in_range(&self, map: &NodeLocation, at_node: usize, transmission: &Transmission) -> bool;
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -321,7 +513,6 @@ where
             path_loss: value.path_loss,
             noise_temp: value.noise_temp,
             random_fading: value.random_fading,
-            cached_power_at: Default::default(),
             db_noise_energy: Db::from_unit(BOLTZMANN * value.noise_temp),
         }
     }
@@ -342,9 +533,6 @@ where
 
     pub random_fading: C,
 
-    #[serde(skip)]
-    cached_power_at: RefCell<Vec<Vec<Option<Db<Power>>>>>,
-
     #[serde(default = "path")]
     db_noise_energy: Db<Energy>,
 }
@@ -398,6 +586,30 @@ where
             [-25.0, -25.0, -25.0, -24.0, -23.0, 1.0],
         ];
 
+        if !sim.settings.listens_on(transmission.sf) {
+            return TransmissionResult::WrongSf;
+        }
+
+        // A scanning radio locks on to the first preamble it detects and only demodulates that.
+        // Later ones on the same spreading factor can still capture it, as below.
+        if sim.settings.scan_sfs
+            && sim
+                .recent
+                .iter()
+                .rev()
+                .take_while(|x| x.end_time >= transmission.start_time)
+                .any(|x| {
+                    x.sf != transmission.sf
+                        && x.transmitter_id != at_node
+                        && (x.start_time < transmission.start_time
+                            || (x.start_time == transmission.start_time
+                                && x.id < transmission.id))
+                        && self.detected_at(sim, at_node, x)
+                })
+        {
+            return TransmissionResult::WrongSf;
+        }
+
         let target_power = self.power_at(sim, at_node, transmission);
         let snr = target_power - self.noise_power(transmission.bandwidth);
 
@@ -406,7 +618,7 @@ where
         }
 
         let maybe_blocker_id = sim
-            .em_field
+            .recent
             .iter()
             .rev()
             .take_while(|x| x.end_time >= transmission.start_time)
@@ -438,12 +650,15 @@ where
         } else {
             TransmissionResult::Success {
                 snr: snr.map(|x| x.clamp(SNR_MIN, SNR_MAX)),
+                rssi: target_power,
             }
         }
     }
 
     fn detected_at(&self, sim: &Context, at_node: usize, transmission: &Transmission) -> bool {
-        if sim.settings.carrier_band != transmission.carrier_band {
+        if sim.settings.carrier_band != transmission.carrier_band
+            || !sim.settings.listens_on(transmission.sf)
+        {
             return false;
         }
 
@@ -462,6 +677,23 @@ where
 
         false
     }
+
+    fn in_range(&self, map: &NodeLocation, at_node: usize, transmission: &Transmission) -> bool {
+        let Some(distance) =
+            map.distance_to(transmission.start_time, transmission.transmitter_id, at_node)
+        else {
+            return false;
+        };
+
+        let power = self.path_loss.power_at_reciever(
+            transmission.power,
+            transmission.carrier_band.wave_length(),
+            distance,
+        );
+        let snr = power - self.noise_power(transmission.bandwidth);
+
+        snr >= snr_read_threshold(transmission.sf)
+    }
 }
 
 impl<C> PairWiseCaptureEffect<C>
@@ -473,7 +705,6 @@ where
             path_loss,
             noise_temp,
             random_fading,
-            cached_power_at: Default::default(),
             db_noise_energy: Db::from_unit(BOLTZMANN * noise_temp),
         }
     }
@@ -483,7 +714,7 @@ where
         // We cache the value because it should not have different random effects
         // for the same transmission at node pair.
 
-        let mut cache = self.cached_power_at.borrow_mut();
+        let mut cache = sim.received_power.borrow_mut();
 
         //cache.get(&(target.id, at_node))
         if let Some(val) = cache
@@ -505,7 +736,7 @@ where
                 distance,
             );
 
-            let fading = self.random_fading.sample(&mut sim.rng.borrow_mut());
+            let fading = self.random_fading.sample(&mut *sim.model_rng());
 
             let final_power = target_power + Dbf::from_db_value(fading);
 
@@ -538,7 +769,9 @@ mod tests {
     };
 
     use super::{
-        snr_detect_threshold, snr_read_threshold, AdjustedFreeSpacePathLoss, ImplPathlossModel,
+        snr_detect_threshold, snr_read_threshold, AdjustedFreeSpacePathLoss, BernoulliLoss,
+        DbPerLength, FadingSettings, ImplPathlossModel, LinearPathLoss, NoneDist,
+        PairWiseCaptureEffect, PathlossModel, Temperature, TransmissionModel,
     };
 
     #[test]
@@ -594,4 +827,107 @@ mod tests {
             assert_eq!(expected[(sf - 5) as usize], detect_calculated);
         }
     }
+
+    #[test]
+    fn examples_cover_every_variant_and_round_trip() {
+        let pathloss = PathlossModel::examples();
+        let transmission = TransmissionModel::examples();
+
+        // Each example is a different variant
+        for (i, a) in pathloss.iter().enumerate() {
+            for b in &pathloss[i + 1..] {
+                assert_ne!(std::mem::discriminant(a), std::mem::discriminant(b));
+            }
+        }
+        for (i, a) in transmission.iter().enumerate() {
+            for b in &transmission[i + 1..] {
+                assert_ne!(std::mem::discriminant(a), std::mem::discriminant(b));
+            }
+        }
+
+        for model in pathloss {
+            let value = serde_value::to_value(&model).unwrap();
+            assert_eq!(value.deserialize_into::<PathlossModel>().unwrap(), model);
+        }
+        for model in transmission {
+            let value = serde_value::to_value(&model).unwrap();
+            assert_eq!(value.deserialize_into::<TransmissionModel>().unwrap(), model);
+        }
+    }
+
+    #[test]
+    fn changing_fading_keeps_other_settings() {
+        let path_loss: PathlossModel = LinearPathLoss {
+            loss_rate: DbPerLength::from_db_per_metre(0.3),
+        }
+        .into();
+        let noise_temp = Temperature::from_celsius(40.0);
+        let base = PairWiseCaptureEffect::new(path_loss.clone(), noise_temp, NoneDist);
+        let mut model: TransmissionModel = BernoulliLoss::new(base.into(), 0.2).into();
+        assert_eq!(model.fading(), FadingSettings::None);
+
+        model
+            .set_fading(FadingSettings::Uniform {
+                min: -3.0,
+                max: 5.0,
+            })
+            .unwrap();
+        let FadingSettings::Uniform { min, max } = model.fading() else {
+            panic!("fading is uniform");
+        };
+        assert_eq!(min, -3.0);
+        assert!((max - 5.0).abs() < 1e-9, "{max}");
+
+        // Invalid distributions leave the model alone
+        let before = model.clone();
+        let normal = |std_dev| FadingSettings::Normal { mean: 0.0, std_dev };
+        assert!(model.set_fading(normal(-1.0)).is_err());
+        assert_eq!(model, before);
+
+        model.set_fading(normal(6.0)).unwrap();
+        let json = serde_json::to_string(&model).unwrap();
+        let model: TransmissionModel = serde_json::from_str(&json).unwrap();
+        assert_eq!(model.fading(), normal(6.0));
+
+        let TransmissionModel::BernoulliLoss(loss) = &model else {
+            panic!("still loses packets");
+        };
+        assert_eq!(loss.loss_probability, 0.2);
+        assert_eq!(model.path_loss(), &path_loss);
+        let TransmissionModel::PairWiseNormal(inner) = loss.inner.as_ref() else {
+            panic!("fading is normal");
+        };
+        assert_eq!(inner.noise_temp, noise_temp);
+    }
+
+    #[test]
+    fn bernoulli_loss_converges_to_probability() {
+        use crate::{
+            node::ModelSelection,
+            scenario::{Scenario, ScenarioMessage},
+            simulation::run_simulation,
+            units::SECONDS,
+        };
+
+        const SENT: usize = 2000;
+        const LOSS: f64 = 0.3;
+
+        // Close enough that every reception would succeed without the loss
+        let scenario = Scenario {
+            model: BernoulliLoss::new(PairWiseCaptureEffect::default().into(), LOSS).into(),
+            messages: (0..SENT)
+                .map(|n| ScenarioMessage::new(0, vec![1], (2.0 * n as f64 + 1.0) * SECONDS, 20))
+                .collect(),
+            ..Scenario::test_pair()
+        };
+
+        let output = run_simulation(1, scenario, ModelSelection::NoRouting.into(), false);
+        let sent = output.node_stats[0].transmissions;
+        let received = output.node_stats[1].receptions;
+        assert_eq!(sent, SENT as u64);
+        assert_eq!(output.node_stats[1].too_weak, sent - received);
+
+        let measured = 1.0 - received as f64 / sent as f64;
+        assert!((measured - LOSS).abs() < 0.03, "measured loss {measured}");
+    }
 }
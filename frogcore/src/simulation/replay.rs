@@ -0,0 +1,178 @@
+//! Replaying a fixed schedule of transmissions with no routing, for checking the physics
+//! against real packet captures.
+
+use std::sync::Arc;
+
+use crate::{
+    node::{BasicFlood, BasicHeader, BasicHeaderInfo, Destination, Header},
+    scenario::Scenario,
+    sim_file::{ScheduledTransmission, SimOutput},
+};
+
+use super::{
+    MessageContent, RunLimits, RunOptions, Simulation,
+    data_structs::{LogLevel, NodeSettings, SimAction, SimEvent},
+    run_initialised,
+};
+
+/// Seed of the rng used by transmission models with random fading when replaying
+const REPLAY_SEED: u64 = 0;
+
+/// Sends every transmission in `schedule` at its time and works out the receptions,
+/// without any node models. The scenario's messages are ignored.
+///
+/// Each transmission uses the sender's settings from the scenario other than the spreading
+/// factor. Ones from nodes that aren't in the scenario or with a spreading factor outside
+/// 7 to 12 are logged as errors and skipped, as are ones from a node still transmitting.
+/// Receivers scan every spreading factor, see [`NodeSettings::scan_sfs`].
+pub fn run_replay(scenario: Scenario, schedule: Vec<ScheduledTransmission>) -> SimOutput {
+    let scenario = Arc::new(scenario);
+    let node_count = scenario.map.len();
+
+    // The node model is never called, there just has to be one
+    let mut sim = Simulation::new(
+        scenario.clone(),
+        scenario.get_settings().into_iter().map(|x| NodeSettings {
+            scan_sfs: true,
+            ..x.into()
+        }),
+        REPLAY_SEED,
+        BasicFlood::new().into(),
+        RunOptions::default(),
+    );
+
+    let (valid, invalid): (Vec<_>, Vec<_>) = schedule
+        .into_iter()
+        .partition(|x| x.node_id < node_count && (7..=12).contains(&x.sf));
+
+    for x in invalid {
+        sim.log(format!("Skipped invalid scheduled transmission {x:?}"), LogLevel::Error);
+    }
+
+    // The packet id is the transmission's index in the schedule
+    for (index, x) in valid.iter().enumerate() {
+        let header = BasicHeader::new(x.node_id, Destination::Broadcast, index as u32);
+
+        sim.event_queue.push(SimEvent {
+            time: x.time,
+            action: SimAction::SendMessage {
+                node_id: x.node_id,
+                header: header.into(),
+                message_content: MessageContent::Empty,
+            },
+        });
+    }
+    sim.schedule = Some(valid);
+
+    let mut output = run_initialised(sim, REPLAY_SEED, RunLimits::default(), None).0;
+    output.complete_identity.model_id = "Replay".into();
+    output
+}
+
+impl Simulation {
+    /// The scheduled transmission a header is for, when replaying.
+    pub(super) fn scheduled(&self, header: &Header) -> Option<&ScheduledTransmission> {
+        let schedule = self.schedule.as_ref()?;
+        schedule.get(header.packet_id() as usize)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        node_location::{Edge, Graph, NodeLocation},
+        sim_file::read_schedule,
+        simulation::data_structs::LogContent,
+        units::METRES,
+    };
+
+    /// Nodes 0 and 1 can only reach node 2, node 0 being much closer to it.
+    fn near_and_far() -> Scenario {
+        let edge = |to, metres| Edge {
+            to,
+            weight: metres * METRES,
+        };
+
+        Scenario::for_test(NodeLocation::Graph(Graph::new(vec![
+                vec![edge(2, 100.0)],
+                vec![edge(2, 3000.0)],
+                vec![edge(0, 100.0), edge(1, 3000.0)],
+            ])))
+    }
+
+    #[test]
+    fn overlapping_replay_is_captured() {
+        let schedule = read_schedule(
+            "node_id, time, sf, size\n\
+             1, 1.0, 7, 40\n\
+             0, 1.01, 7, 40\n\
+             0, 1.02, 13, 40\n"
+                .as_bytes(),
+        )
+        .unwrap();
+        assert_eq!(schedule.len(), 3);
+
+        let output = run_replay(near_and_far(), schedule);
+        assert_eq!(output.complete_identity.model_id, "Replay");
+        assert_eq!(output.transmissions.len(), 2);
+        assert!(output.transmissions.iter().all(|x| x.sf == 7));
+
+        let far = output.transmissions.iter().find(|x| x.transmitter_id == 1);
+        let near = output.transmissions.iter().find(|x| x.transmitter_id == 0);
+        let (far, near) = (far.unwrap().id, near.unwrap().id);
+
+        let verdicts: Vec<_> = output
+            .logs
+            .iter()
+            .filter_map(|x| match x.content {
+                LogContent::TransmissionReceived {
+                    receiver_id,
+                    transmission_id,
+                    ..
+                } => Some((receiver_id, transmission_id, None)),
+                LogContent::TransmissionBlocked {
+                    receiver_id,
+                    target_transmission_id,
+                    blocking_transmission_id,
+                } => Some((
+                    receiver_id,
+                    target_transmission_id,
+                    Some(blocking_transmission_id),
+                )),
+                _ => None,
+            })
+            .collect();
+
+        // The stronger one is captured even though it started second
+        assert_eq!(verdicts, vec![(2, far, Some(near)), (2, near, None)]);
+        assert_eq!(output.node_stats[2].receptions, 1);
+        assert_eq!(output.node_stats[2].blocked, 1);
+
+        // The invalid one is skipped
+        assert!(
+            output
+                .logs
+                .iter()
+                .any(|x| x.log_level == LogLevel::Error && x.content.to_string().contains("sf: 13"))
+        );
+    }
+
+    #[test]
+    fn scanning_receiver_keeps_the_first_sf() {
+        let schedule = read_schedule(
+            "node_id, time, sf, size\n\
+             0, 1.0, 12, 40\n\
+             1, 1.01, 7, 40\n"
+                .as_bytes(),
+        )
+        .unwrap();
+
+        let output = run_replay(near_and_far(), schedule);
+
+        // Node 2 hears both spreading factors but is already locked on to node 0's
+        assert_eq!(output.node_stats[2].receptions, 1);
+        assert_eq!(output.node_stats[2].wrong_sf, 1);
+        assert_eq!(output.node_stats[2].blocked, 0);
+    }
+}
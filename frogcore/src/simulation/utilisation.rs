@@ -0,0 +1,418 @@
+//! Channel utilisation as each node observes it.
+//!
+//! Every node keeps the airtime it has detected in rolling periods, the same way airtime.cpp
+//! in the meshtastic firmware does, so working out the utilisation only has to look at
+//! transmissions started since the node last asked rather than everything still in range.
+
+use std::collections::VecDeque;
+
+use crate::Time;
+
+use super::{Context, data_structs::UtilisationMode};
+
+// Constants taken from airtime.cpp
+const CHANNEL_UTILIZATION_PERIODS: i64 = 6;
+const UTIL_PERIOD_LENGTH: Time = Time::from_seconds(10.0);
+
+/// Detected airtime at one node, updated each time the node checks the channel utilisation.
+///
+/// Overlapping transmissions are only counted once, so the detected intervals that can still
+/// overlap new ones are kept merged alongside the period totals.
+#[derive(Debug, Clone)]
+pub(super) struct ChannelUsage {
+    /// Transmissions with a lower id have already been looked at
+    next_id: u32,
+    /// Sim time of the last update
+    updated_at: Time,
+    /// Detected transmissions that were still going at the last update, as (start, end)
+    active: Vec<(Time, Time)>,
+    /// Disjoint intervals of finished detections, ordered by start time
+    finished: VecDeque<(Time, Time)>,
+    /// Period that the front of `periods` is for
+    first_period: i64,
+    /// Airtime of finished detections in each period
+    periods: VecDeque<Time>,
+}
+
+impl Default for ChannelUsage {
+    fn default() -> Self {
+        Self {
+            next_id: 0,
+            updated_at: Time::from_seconds(0.0),
+            active: Vec::new(),
+            finished: VecDeque::new(),
+            first_period: 0,
+            periods: VecDeque::new(),
+        }
+    }
+}
+
+fn period_of(time: Time) -> i64 {
+    (time / UTIL_PERIOD_LENGTH).floor() as i64
+}
+
+fn period_start(period: i64) -> Time {
+    period as f64 * UTIL_PERIOD_LENGTH
+}
+
+impl ChannelUsage {
+    /// Returns the proportion of the window that the node detected activity in,
+    /// see [`Context::channel_utilisation`].
+    ///
+    /// Must only be called with the [`Context`] of the node this is for.
+    pub(super) fn utilisation(&mut self, sim: &Context) -> f64 {
+        let now = sim.sim_time;
+        let first_period = period_of(now) - (CHANNEL_UTILIZATION_PERIODS - 1);
+        let window_start = period_start(first_period);
+
+        self.forget_before(first_period);
+        self.observe_new(sim, window_start);
+
+        // Detections that finished since the last update can go into the periods,
+        // the rest only count up to now
+        let mut ongoing = Vec::new();
+        for (start, end) in std::mem::take(&mut self.active) {
+            if end <= now {
+                self.add_finished(start.max(window_start), end);
+            } else {
+                ongoing.push((start, end));
+            }
+        }
+        self.active = ongoing;
+
+        let mut total: Time = self.periods.iter().copied().sum();
+        total = total + self.ongoing_airtime(window_start, now);
+
+        let out = total / (now - window_start);
+
+        // TEST
+        assert!((0.0..=1.00001).contains(&out), "value was {}", out);
+
+        out
+    }
+
+    /// Checks which of the transmissions started since the last update the node can detect.
+    fn observe_new(&mut self, sim: &Context, window_start: Time) {
+        // New transmissions started after the last update so can't have ended before it
+        let new = sim
+            .recent
+            .iter()
+            .rev()
+            .take_while(|x| x.end_time >= self.updated_at)
+            .filter(|x| x.id >= self.next_id);
+
+        let mut next_id = self.next_id;
+        for x in new {
+            next_id = next_id.max(x.id + 1);
+            if x.end_time < window_start {
+                continue;
+            }
+
+            let counted = match sim.settings.utilisation_mode {
+                UtilisationMode::CountAllDetected => {
+                    sim.transmission.detected_at(sim, sim.node_id, x)
+                }
+                // Added by `add_decoded` once they have been received
+                UtilisationMode::CountDecodedOnly => false,
+                UtilisationMode::CountDetectedPlusOwnTx => {
+                    x.transmitter_id == sim.node_id
+                        || sim.transmission.detected_at(sim, sim.node_id, x)
+                }
+            };
+
+            if counted {
+                self.active.push((x.start_time, x.end_time));
+            }
+        }
+
+        self.next_id = next_id;
+        self.updated_at = sim.sim_time;
+    }
+
+    /// Counts a transmission the node has just decoded, for [`UtilisationMode::CountDecodedOnly`].
+    pub(super) fn add_decoded(&mut self, start: Time, end: Time) {
+        self.active.push((start, end));
+    }
+
+    /// Drops everything from before `first_period` as it's out of the window for good.
+    fn forget_before(&mut self, first_period: i64) {
+        while self.first_period < first_period && !self.periods.is_empty() {
+            self.periods.pop_front();
+            self.first_period += 1;
+        }
+
+        if self.periods.is_empty() {
+            self.first_period = first_period;
+        }
+
+        let window_start = period_start(first_period);
+        while self.finished.front().is_some_and(|x| x.1 < window_start) {
+            self.finished.pop_front();
+        }
+    }
+
+    /// Adds a finished detection, counting only the parts not already covered.
+    fn add_finished(&mut self, start: Time, end: Time) {
+        if end <= start {
+            return;
+        }
+
+        let (from, to) = self.overlapping(start, end);
+
+        let mut gaps = Vec::new();
+        self.for_each_gap(from..to, start, end, |a, b| gaps.push((a, b)));
+        for (a, b) in gaps {
+            self.add_airtime(a, b);
+        }
+
+        let merged = if from < to {
+            (self.finished[from].0.min(start), self.finished[to - 1].1.max(end))
+        } else {
+            (start, end)
+        };
+
+        self.finished.drain(from..to);
+        self.finished.insert(from, merged);
+    }
+
+    /// Airtime of detections that are still going, not counting time already
+    /// covered by finished ones.
+    fn ongoing_airtime(&self, window_start: Time, now: Time) -> Time {
+        // They all run until now so together they cover from the earliest start
+        let start = self
+            .active
+            .iter()
+            .map(|x| x.0.max(window_start))
+            .reduce(Time::min);
+
+        let Some(start) = start.filter(|x| *x < now) else {
+            return Time::from_seconds(0.0);
+        };
+
+        let mut total = Time::from_seconds(0.0);
+        let (from, to) = self.overlapping(start, now);
+        self.for_each_gap(from..to, start, now, |a, b| total = total + (b - a));
+
+        total
+    }
+
+    /// Index range of the finished intervals that touch `start..end`.
+    fn overlapping(&self, start: Time, end: Time) -> (usize, usize) {
+        let to = self.finished.partition_point(|x| x.0 <= end);
+        let mut from = to;
+        while from > 0 && self.finished[from - 1].1 >= start {
+            from -= 1;
+        }
+
+        (from, to)
+    }
+
+    /// Calls `gap` with each part of `start..end` not covered by the finished intervals in `range`.
+    fn for_each_gap(
+        &self,
+        range: std::ops::Range<usize>,
+        start: Time,
+        end: Time,
+        mut gap: impl FnMut(Time, Time),
+    ) {
+        let mut cursor = start;
+        for &(covered_start, covered_end) in self.finished.range(range) {
+            if covered_start > cursor {
+                gap(cursor, covered_start);
+            }
+            cursor = cursor.max(covered_end);
+        }
+
+        if end > cursor {
+            gap(cursor, end);
+        }
+    }
+
+    /// Adds airtime to the periods it falls in.
+    fn add_airtime(&mut self, mut start: Time, end: Time) {
+        while start < end {
+            let period = period_of(start);
+            let piece_end = end.min(period_start(period + 1));
+
+            if period >= self.first_period {
+                let index = (period - self.first_period) as usize;
+                if self.periods.len() <= index {
+                    self.periods.resize(index + 1, Time::from_seconds(0.0));
+                }
+                self.periods[index] = self.periods[index] + (piece_end - start);
+            }
+
+            start = piece_end;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::*;
+    use crate::{
+        context,
+        node::ModelSelection,
+        scenario::{Scenario, ScenarioIdentity},
+        simulation::{
+            RunOptions, Simulation, init_simulation,
+            models::{PairWiseCaptureEffect, TransmissionModel},
+        },
+        units::{MINS, SECONDS},
+    };
+
+    /// How utilisation was worked out before it was cached,
+    /// going back over every transmission in the window on each call.
+    fn rescanned_utilisation(sim: &Context) -> f64 {
+        let full_periods = (CHANNEL_UTILIZATION_PERIODS - 1) as f64;
+        let look_back_time = full_periods * UTIL_PERIOD_LENGTH + sim.sim_time % UTIL_PERIOD_LENGTH;
+
+        let limit_time = sim.sim_time - look_back_time;
+
+        let mut end_clamp = sim.sim_time;
+        let start_clamp = limit_time;
+        let mut total = Time::from_seconds(0.0);
+
+        let observation_range = sim
+            .recent
+            .iter()
+            .rev()
+            .take_while(|x| x.end_time >= limit_time)
+            .filter(|x| sim.transmission.detected_at(sim, sim.node_id, x));
+
+        for x in observation_range {
+            if x.start_time < end_clamp {
+                total = total + x.end_time.min(end_clamp) - x.start_time.max(start_clamp);
+                end_clamp = x.start_time;
+
+                if end_clamp < start_clamp {
+                    break;
+                }
+            }
+        }
+
+        total / look_back_time
+    }
+
+    fn busy_scenario(nodes: usize) -> Scenario {
+        ScenarioIdentity::for_test(nodes, nodes * 10, 5.0 * MINS, 3).create()
+    }
+
+    fn busy_simulation(nodes: usize, model: TransmissionModel) -> Simulation {
+        let mut scenario = busy_scenario(nodes);
+        scenario.model = model;
+
+        let model = ModelSelection::Meshtastic.into();
+        init_simulation(1, scenario.into(), model, RunOptions::default())
+    }
+
+    #[test]
+    fn cached_utilisation_matches_rescanning() {
+        for model in TransmissionModel::examples() {
+            let mut sim = busy_simulation(12, model);
+            let mut step = 0;
+            let mut busiest: f64 = 0.0;
+
+            while !sim.finished() {
+                sim.step();
+                step += 1;
+
+                // Nodes check at different rates so some go a long time between updates
+                for node in 0..12 {
+                    if step % (node * node * 5 + 1) != 0 {
+                        continue;
+                    }
+
+                    let context = context!(sim, node);
+                    let expected = rescanned_utilisation(&context);
+                    let cached = context.channel_utilisation();
+
+                    assert!(
+                        (expected - cached).abs() < 1e-9,
+                        "node {node} at {}: expected {expected} got {cached}",
+                        sim.sim_time
+                    );
+                    busiest = busiest.max(cached);
+                }
+            }
+
+            assert!(busiest > 0.1, "channel was never busy");
+        }
+    }
+
+    #[test]
+    #[ignore = "depends on wall-clock timing, run with --ignored"]
+    fn cached_utilisation_is_faster() {
+        let mut sim = busy_simulation(20, PairWiseCaptureEffect::default().into());
+        let mut rescanning = Duration::ZERO;
+        let mut cached = Duration::ZERO;
+        let mut step = 0;
+
+        while !sim.finished() {
+            sim.step();
+            step += 1;
+
+            if step % 10 != 0 {
+                continue;
+            }
+
+            for node in 0..20 {
+                let context = context!(sim, node);
+
+                // Rescanning first so the detections it looks up are cached for both
+                let start = Instant::now();
+                let expected = rescanned_utilisation(&context);
+                rescanning += start.elapsed();
+
+                let start = Instant::now();
+                let value = context.channel_utilisation();
+                cached += start.elapsed();
+
+                assert!((expected - value).abs() < 1e-9);
+            }
+        }
+
+        assert!(cached < rescanning, "rescanning: {rescanning:?}, cached: {cached:?}");
+    }
+
+    #[test]
+    fn utilisation_modes_diverge_under_blocking() {
+        // Each mode is sampled at the same times, since the modes can change what gets drawn
+        let run = |mode| {
+            let mut scenario = busy_scenario(12);
+            scenario.model = PairWiseCaptureEffect::default().into();
+            scenario
+                .settings
+                .iter_mut()
+                .for_each(|x| x.utilisation_mode = mode);
+            let model = ModelSelection::BasicFlood.into();
+            let mut sim = init_simulation(1, scenario.into(), model, RunOptions::default());
+
+            let mut total = 0.0;
+            let mut next_sample = 5.0 * SECONDS;
+            while !sim.finished() {
+                sim.step();
+                if sim.sim_time < next_sample {
+                    continue;
+                }
+                next_sample = sim.sim_time + 5.0 * SECONDS;
+                total += (0..12)
+                    .map(|node| context!(sim, node).channel_utilisation())
+                    .sum::<f64>();
+            }
+
+            let blocked: u64 = sim.node_stats.iter().map(|x| x.blocked).sum();
+            assert!(blocked > 100, "only {blocked} receptions were blocked");
+            total
+        };
+        let totals = UtilisationMode::ALL.map(run);
+
+        // Blocked receptions only drop out when counting decodes,
+        // and graph nodes only hear themselves when their own transmissions are added
+        let [all, decoded, own] = totals;
+        assert!(decoded < all * 0.9, "{totals:?}");
+        assert!(own > all * 1.1, "{totals:?}");
+    }
+}
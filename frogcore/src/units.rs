@@ -1,11 +1,15 @@
 use std::{
+    cmp::Ordering,
     fmt::Display,
+    hash::{Hash, Hasher},
     iter::Sum,
     marker::PhantomData,
     ops::{Add, Div, Mul, Neg, Rem, Sub},
+    str::FromStr,
 };
 
 use serde::{Deserialize, Serialize};
+use thiserror::Error;
 
 pub trait Unit: Into<f64> {
     fn inner(self) -> f64 {
@@ -134,10 +138,54 @@ macro_rules! Quantity {
             pub fn max(self, other: Self) -> Self {
                 Self(self.0.max(other.0))
             }
+
+            /// Orders the underlying floats with [`f64::total_cmp`], so unlike `partial_cmp`
+            /// it never fails. Use it for sorting and for `min_by` and `max_by`.
+            pub fn total_cmp(&self, other: &Self) -> Ordering {
+                self.0.total_cmp(&other.0)
+            }
         }
     };
 }
 
+/// Wraps a quantity so it is [`Ord`] and [`Hash`], for keying maps and sets with it
+/// or sorting by it.
+///
+/// Compares with the quantity's `total_cmp` and hashes the bit pattern, so the two agree:
+/// NaN is equal to itself and sorts after everything else (or before, if negative),
+/// and `-0.0` sorts before `0.0` rather than being equal to it.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct TotalOrd<T>(pub T);
+
+pub type OrdTime = TotalOrd<Time>;
+pub type OrdLength = TotalOrd<Length>;
+
+impl<T: Unit + Copy> PartialEq for TotalOrd<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<T: Unit + Copy> Eq for TotalOrd<T> {}
+
+impl<T: Unit + Copy> PartialOrd for TotalOrd<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T: Unit + Copy> Ord for TotalOrd<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.inner().total_cmp(&other.0.inner())
+    }
+}
+
+impl<T: Unit + Copy> Hash for TotalOrd<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.inner().to_bits().hash(state);
+    }
+}
+
 macro_rules! DivRelation {
     ($top:ident, $bottom:ident, $result:ident) => {
         impl Div<$bottom> for $top {
@@ -174,6 +222,55 @@ macro_rules! DivRelation {
     };
 }
 
+/// Implements [`FromStr`] for a quantity given a list of unit suffixes and their
+/// multiplier to the base unit. A number with no unit is taken as the base unit.
+macro_rules! ParseUnits {
+    ($name: ident, $( $suffix: pat => $factor: expr ),+ $(,)?) => {
+        impl FromStr for $name {
+            type Err = ParseUnitError;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                let (value, unit) = split_unit(s)?;
+
+                let factor = match unit {
+                    "" => 1.0,
+                    $( $suffix => $factor, )+
+                    _ => {
+                        return Err(ParseUnitError::UnknownUnit {
+                            unit: unit.to_owned(),
+                            quantity: stringify!($name),
+                        });
+                    }
+                };
+
+                Ok($name(value * factor))
+            }
+        }
+    };
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum ParseUnitError {
+    #[error("No number found in \"{0}\"")]
+    NoNumber(String),
+    #[error("Unknown unit \"{unit}\" for {quantity}")]
+    UnknownUnit { unit: String, quantity: &'static str },
+}
+
+/// Splits a string such as `"5 km"` into the leading number and the trimmed unit.
+fn split_unit(s: &str) -> Result<(f64, &str), ParseUnitError> {
+    let s = s.trim();
+
+    (0..=s.len())
+        .rev()
+        .filter(|&i| s.is_char_boundary(i))
+        .find_map(|i| {
+            let value = s[..i].trim().parse::<f64>().ok()?;
+            Some((value, s[i..].trim()))
+        })
+        .ok_or_else(|| ParseUnitError::NoNumber(s.to_owned()))
+}
+
 #[allow(unused_macros)]
 macro_rules! MulRelation {
     ($left: ident, $right: ident, $result: ident) => {
@@ -240,6 +337,11 @@ where
         self.0
     }
 
+    /// Orders the decibel values the same way as the quantities' `total_cmp`.
+    pub fn total_cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+
     fn from_linear(val: f64) -> Self {
         let log = 10.0 * val.log10();
         Db::from(log)
@@ -293,6 +395,8 @@ impl Length {
     }
 }
 
+ParseUnits!(Length, "m" => 1.0, "km" => 1000.0);
+
 Quantity!(Time);
 pub const HOURS: Time = Time::from_seconds(60.0 * 60.0);
 pub const MINS : Time = Time::from_seconds(60.0);
@@ -319,6 +423,13 @@ impl Time {
     }
 }
 
+ParseUnits!(Time,
+    "ms" => 0.001,
+    "s" => 1.0,
+    "min" | "mins" => 60.0,
+    "h" | "hours" => 60.0 * 60.0,
+);
+
 Quantity!(Mass);
 Quantity!(Temperature);
 
@@ -410,6 +521,8 @@ impl Speed {
     }
 }
 
+ParseUnits!(Speed, "mps" | "m/s" => 1.0, "km/h" | "kph" => 1000.0 / (60.0 * 60.0));
+
 DivRelation!(Speed, Frequency, Length);
 MulRelation!(Speed, Time, Length);
 
@@ -419,3 +532,41 @@ MulRelation!(EnergyPerTemprature, Temperature, Energy);
 MulRelation!(Energy, Frequency, Power);
 DivRelation!(f64, Frequency, Time);
 DivRelation!(Dbf, Length, DbPerLength);
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    #[test]
+    fn parse_units() {
+        assert_eq!("5 km".parse(), Ok(5.0 * KM));
+        assert_eq!("500m".parse(), Ok(500.0 * METRES));
+        assert_eq!("250".parse(), Ok(250.0 * METRES));
+        assert_eq!(" 2.5 min ".parse(), Ok(2.5 * MINS));
+        assert_eq!("1e3 ms".parse(), Ok(1.0 * SECONDS));
+        assert_eq!("1.35 mps".parse(), Ok(1.35 * MPS));
+
+        assert!("5 parsecs".parse::<Length>().is_err());
+        assert!("km".parse::<Length>().is_err());
+    }
+
+    #[test]
+    fn total_ordering_handles_nan() {
+        let mut times = [2.0 * SECONDS, Time::from_seconds(f64::NAN), -1.0 * SECONDS];
+        times.sort_by(Time::total_cmp);
+        assert_eq!(&times[..2], [-1.0 * SECONDS, 2.0 * SECONDS]);
+        assert!(times[2].seconds().is_nan());
+
+        let lengths = [5.0 * METRES, 1.0 * KM];
+        assert_eq!(lengths.into_iter().min_by(Length::total_cmp), Some(5.0 * METRES));
+
+        // NaN keys can be found again, and the two zeros stay apart
+        let nan: OrdTime = TotalOrd(Time::from_seconds(f64::NAN));
+        let keys = HashSet::from([nan, TotalOrd(0.0 * SECONDS), TotalOrd(-0.0 * SECONDS)]);
+        assert_eq!(keys.len(), 3);
+        assert!(keys.contains(&TotalOrd(Time::from_seconds(f64::NAN))));
+        assert!(TotalOrd(-0.0 * SECONDS) < TotalOrd(0.0 * SECONDS));
+    }
+}
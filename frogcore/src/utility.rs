@@ -1,5 +1,12 @@
 use std::{cmp::Ordering, collections::BinaryHeap};
 
+/// 64 bit FNV-1a hash. Doesn't change between builds, unlike the standard library hashers.
+pub fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
 pub(crate) fn n_min<T>(list: &[T], num: usize) -> Vec<usize>
 where
     T: Copy,
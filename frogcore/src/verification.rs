@@ -1,7 +1,25 @@
 //! Verifications / tests to be run on simulation results to make sure the simulator is working correctly.
-//! Each public function, other than [`verify_all`], represents some property that should hold for all simulation results.
+//! Each public function, other than [`verify_all`] and [`failed_checks`], represents some property that should hold for all simulation results.
 
-use crate::{analysis::CompleteAnalysis, simulation::data_structs::LogContent};
+use std::{
+    fmt,
+    panic::{AssertUnwindSafe, catch_unwind},
+};
+
+use crate::{
+    analysis::CompleteAnalysis,
+    node::{BasicHeaderInfo, Destination, NodeModel},
+    scenario::Scenario,
+    simulation::{
+        MessageContent, RunOptions, SIM_END,
+        data_structs::{LogContent, LogItem},
+        run_simulation_with_state,
+    },
+    units::{HOURS, Time},
+};
+
+/// Default duty cycle limit for the EU868 band.
+pub const EU868_DUTY_CYCLE: f64 = 0.01;
 
 pub fn verify_all(analysis: &CompleteAnalysis) -> bool {
     no_overlapping_transmission(analysis)
@@ -9,6 +27,23 @@ pub fn verify_all(analysis: &CompleteAnalysis) -> bool {
         && no_transmission_and_reception_at_same_time(analysis)
 }
 
+/// The checks made by [`verify_all`] which fail.
+pub fn failed_checks(analysis: &CompleteAnalysis) -> Vec<HarnessCheck> {
+    let mut failed = Vec::new();
+
+    if !no_overlapping_transmission(analysis) {
+        failed.push(HarnessCheck::NoOverlappingTransmission);
+    }
+    if !no_overlapping_reception(analysis) {
+        failed.push(HarnessCheck::NoOverlappingReception);
+    }
+    if !no_transmission_and_reception_at_same_time(analysis) {
+        failed.push(HarnessCheck::NoTransmissionAndReceptionAtSameTime);
+    }
+
+    failed
+}
+
 /// No node can make more than one transmission at a time.
 ///
 /// For all non-equal transmissions if they have the same sender they cannot overlap.
@@ -38,6 +73,7 @@ pub fn no_overlapping_reception(analysis: &CompleteAnalysis) -> bool {
             LogContent::TransmissionReceived {
                 receiver_id,
                 transmission_id,
+                ..
             } => Some((
                 receiver_id,
                 analysis
@@ -85,7 +121,7 @@ fn transmission_recievers(analysis: &CompleteAnalysis) -> Vec<Vec<usize>> {
     analysis.transmissions.iter().map(|x| {
         analysis.sim_events.iter().filter_map(|event| {
             match event.content {
-                LogContent::TransmissionReceived { receiver_id, transmission_id } => (transmission_id == x.id).then_some(receiver_id),
+                LogContent::TransmissionReceived { receiver_id, transmission_id, .. } => (transmission_id == x.id).then_some(receiver_id),
                 _ => None,
             }
         }).collect()
@@ -105,3 +141,475 @@ fn overlapping_transmissions(analysis: &CompleteAnalysis) -> Vec<(usize, usize)>
 
     output
 }
+
+/// The busiest sliding one hour window found by [`max_duty_cycle`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DutyCycleReport {
+    pub node_id: usize,
+    pub window_start: Time,
+    /// Proportion of the window spent transmitting.
+    pub utilisation: f64,
+}
+
+impl fmt::Display for DutyCycleReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "node {} transmitted for {:.2}% of the hour from {:.1}s",
+            self.node_id,
+            self.utilisation * 100.0,
+            self.window_start
+        )
+    }
+}
+
+/// No node transmits for more than `limit` of any one hour window.
+/// Returns the busiest window if it goes over.
+///
+/// `limit` is a proportion, see [`EU868_DUTY_CYCLE`] for the usual value.
+pub fn duty_cycle_within(analysis: &CompleteAnalysis, limit: f64) -> Result<(), DutyCycleReport> {
+    match max_duty_cycle(analysis) {
+        Some(worst) if worst.utilisation > limit => Err(worst),
+        _ => Ok(()),
+    }
+}
+
+/// Finds the node and one hour window with the highest transmit airtime.
+/// Returns `None` if there were no transmissions.
+pub fn max_duty_cycle(analysis: &CompleteAnalysis) -> Option<DutyCycleReport> {
+    let mut worst: Option<DutyCycleReport> = None;
+
+    for node_id in 0..analysis.node_settings.len() {
+        let mut node_trans: Vec<_> = analysis
+            .transmissions
+            .iter()
+            .filter(|x| x.transmitter_id == node_id)
+            .collect();
+
+        node_trans.sort_by(|a, b| a.start_time.total_cmp(&b.start_time));
+
+        // The busiest window always starts at the start of some transmission
+        for (n, first) in node_trans.iter().enumerate() {
+            let window_start = first.start_time;
+            let window_end = window_start + HOURS;
+
+            let airtime: f64 = node_trans[n..]
+                .iter()
+                .take_while(|x| x.start_time < window_end)
+                .map(|x| {
+                    let end = if x.end_time < window_end {
+                        x.end_time
+                    } else {
+                        window_end
+                    };
+                    (end - x.start_time).seconds()
+                })
+                .sum();
+
+            let utilisation = airtime / HOURS.seconds();
+
+            if worst.is_none_or(|x| utilisation > x.utilisation) {
+                worst = Some(DutyCycleReport {
+                    node_id,
+                    window_start,
+                    utilisation,
+                });
+            }
+        }
+    }
+
+    worst
+}
+
+/// A property checked by [`model_harness`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HarnessCheck {
+    /// The simulation ran to completion without panicking.
+    /// Notifying a thread that was never registered will fail this check.
+    NoPanic,
+    NoOverlappingTransmission,
+    NoOverlappingReception,
+    NoTransmissionAndReceptionAtSameTime,
+    /// No transmission is addressed to the node that sent it.
+    NoTransmissionToSelf,
+    /// Every generated message is transmitted at least once by the node that generated it.
+    GeneratedMessagesTransmitted,
+    /// Every node state survives a round trip through `serde_value`, as used by the gui inspector.
+    StateRoundTrip,
+    /// No [`LogContent::ContractViolation`] was logged, the harness runs in strict mode.
+    NoContractViolations,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CheckOutcome {
+    Passed,
+    Failed(String),
+    /// The check could not be run, usually because the simulation panicked.
+    Skipped,
+}
+
+/// The outcome of every [`HarnessCheck`] for one scenario and seed.
+#[derive(Debug, Clone)]
+pub struct HarnessRun {
+    /// Index into the scenarios passed to [`model_harness`].
+    pub scenario_index: usize,
+    pub seed: u64,
+    pub outcomes: Vec<(HarnessCheck, CheckOutcome)>,
+}
+
+#[derive(Debug, Clone)]
+pub struct HarnessReport {
+    pub runs: Vec<HarnessRun>,
+}
+
+impl HarnessReport {
+    pub fn passed(&self) -> bool {
+        self.failures().next().is_none()
+    }
+
+    /// Every failed check along with the scenario index and seed it failed on.
+    pub fn failures(&self) -> impl Iterator<Item = (usize, u64, HarnessCheck, &String)> {
+        self.runs.iter().flat_map(|run| {
+            run.outcomes
+                .iter()
+                .filter_map(|(check, outcome)| match outcome {
+                    CheckOutcome::Failed(reason) => {
+                        Some((run.scenario_index, run.seed, *check, reason))
+                    }
+                    _ => None,
+                })
+        })
+    }
+}
+
+/// Runs `model` on every combination of `scenarios` and `seeds` and checks that it keeps to the
+/// contracts the simulator expects of a node model.
+/// Intended for checking new implementations of [`ImplNodeModel`](crate::node::ImplNodeModel).
+pub fn model_harness(model: NodeModel, scenarios: &[Scenario], seeds: &[u64]) -> HarnessReport {
+    let mut runs = Vec::new();
+
+    for (scenario_index, scenario) in scenarios.iter().enumerate() {
+        for &seed in seeds {
+            runs.push(HarnessRun {
+                scenario_index,
+                seed,
+                outcomes: harness_run(&model, scenario, seed),
+            });
+        }
+    }
+
+    HarnessReport { runs }
+}
+
+fn harness_run(
+    model: &NodeModel,
+    scenario: &Scenario,
+    seed: u64,
+) -> Vec<(HarnessCheck, CheckOutcome)> {
+    use HarnessCheck::*;
+
+    let options = RunOptions {
+        strict: true,
+        ..RunOptions::default()
+    };
+    let result = catch_unwind(AssertUnwindSafe(|| {
+        run_simulation_with_state(seed, scenario.clone(), model.clone(), options)
+    }));
+
+    let (output, nodes) = match result {
+        Ok(inner) => inner,
+        Err(payload) => {
+            let reason = payload
+                .downcast_ref::<&str>()
+                .map(|x| x.to_string())
+                .or_else(|| payload.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "Unknown panic".to_owned());
+
+            return vec![
+                (NoPanic, CheckOutcome::Failed(reason)),
+                (NoOverlappingTransmission, CheckOutcome::Skipped),
+                (NoOverlappingReception, CheckOutcome::Skipped),
+                (NoTransmissionAndReceptionAtSameTime, CheckOutcome::Skipped),
+                (NoTransmissionToSelf, CheckOutcome::Skipped),
+                (GeneratedMessagesTransmitted, CheckOutcome::Skipped),
+                (StateRoundTrip, CheckOutcome::Skipped),
+                (NoContractViolations, CheckOutcome::Skipped),
+            ];
+        }
+    };
+
+    let violations = contract_violations(&output.logs);
+    let analysis = CompleteAnalysis::new(output, scenario.clone());
+
+    let as_outcome = |passed: bool, reason: &str| {
+        if passed {
+            CheckOutcome::Passed
+        } else {
+            CheckOutcome::Failed(reason.to_owned())
+        }
+    };
+
+    vec![
+        (NoPanic, CheckOutcome::Passed),
+        (
+            NoOverlappingTransmission,
+            as_outcome(
+                no_overlapping_transmission(&analysis),
+                "Overlapping transmission",
+            ),
+        ),
+        (
+            NoOverlappingReception,
+            as_outcome(no_overlapping_reception(&analysis), "Overlapping reception"),
+        ),
+        (
+            NoTransmissionAndReceptionAtSameTime,
+            as_outcome(
+                no_transmission_and_reception_at_same_time(&analysis),
+                "Transmission and reception at the same time",
+            ),
+        ),
+        (NoTransmissionToSelf, no_transmission_to_self(&analysis)),
+        (
+            GeneratedMessagesTransmitted,
+            generated_messages_transmitted(&analysis, scenario),
+        ),
+        (StateRoundTrip, state_round_trip(&nodes)),
+        (NoContractViolations, violations),
+    ]
+}
+
+fn contract_violations(logs: &[LogItem]) -> CheckOutcome {
+    let violations: Vec<String> = logs
+        .iter()
+        .filter(|x| matches!(x.content, LogContent::ContractViolation { .. }))
+        .map(|x| format!("{} at {}", x.content, x.time))
+        .collect();
+
+    match violations.first() {
+        None => CheckOutcome::Passed,
+        Some(first) => CheckOutcome::Failed(format!(
+            "{} contract violations, the first being: {first}",
+            violations.len()
+        )),
+    }
+}
+
+fn no_transmission_to_self(analysis: &CompleteAnalysis) -> CheckOutcome {
+    let to_self = analysis
+        .transmissions
+        .iter()
+        .find(|x| match x.header.dest() {
+            Destination::Node(id) => id == x.transmitter_id,
+            Destination::Broadcast | Destination::Group(_) => false,
+        });
+
+    match to_self {
+        Some(trans) => CheckOutcome::Failed(format!(
+            "Transmission {} was addressed to its sender {}",
+            trans.id, trans.transmitter_id
+        )),
+        None => CheckOutcome::Passed,
+    }
+}
+
+fn generated_messages_transmitted(
+    analysis: &CompleteAnalysis,
+    scenario: &Scenario,
+) -> CheckOutcome {
+    let missing: Vec<usize> = scenario
+        .messages
+        .iter()
+        .enumerate()
+        .filter(|(_, message)| message.generate_time < SIM_END)
+        .filter(|(message_id, message)| {
+            !analysis.transmissions.iter().any(|x| {
+                x.transmitter_id == message.sender
+                    && matches!(x.message_content, MessageContent::GeneratedMessage(id) if id == *message_id)
+            })
+        })
+        .map(|(message_id, _)| message_id)
+        .collect();
+
+    if missing.is_empty() {
+        CheckOutcome::Passed
+    } else {
+        CheckOutcome::Failed(format!(
+            "Messages never transmitted by their origin: {missing:?}"
+        ))
+    }
+}
+
+fn state_round_trip(nodes: &[NodeModel]) -> CheckOutcome {
+    for (node_id, node) in nodes.iter().enumerate() {
+        let value = match serde_value::to_value(node) {
+            Ok(value) => value,
+            Err(e) => {
+                return CheckOutcome::Failed(format!("Node {node_id} failed to serialize: {e}"));
+            }
+        };
+
+        let round_trip: NodeModel = match value.clone().deserialize_into() {
+            Ok(inner) => inner,
+            Err(e) => {
+                return CheckOutcome::Failed(format!("Node {node_id} failed to deserialize: {e}"));
+            }
+        };
+
+        let round_trip = serde_value::to_value(&round_trip).ok().map(sort_seqs);
+        if round_trip != Some(sort_seqs(value)) {
+            return CheckOutcome::Failed(format!("Node {node_id} changed after a round trip"));
+        }
+    }
+
+    CheckOutcome::Passed
+}
+
+/// Hash based collections serialize in an arbitrary order so sequences are
+/// sorted before comparing round tripped values.
+fn sort_seqs(value: serde_value::Value) -> serde_value::Value {
+    use serde_value::Value;
+
+    match value {
+        Value::Seq(values) => {
+            let mut values: Vec<_> = values.into_iter().map(sort_seqs).collect();
+            values.sort();
+            Value::Seq(values)
+        }
+        Value::Map(map) => Value::Map(
+            map.into_iter()
+                .map(|(key, value)| (sort_seqs(key), sort_seqs(value)))
+                .collect(),
+        ),
+        Value::Option(inner) => Value::Option(inner.map(|x| Box::new(sort_seqs(*x)))),
+        Value::Newtype(inner) => Value::Newtype(Box::new(sort_seqs(*inner))),
+        other => other,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        node::{MODEL_LIST, ModelSelection, NoRouting},
+        node_location::{NodeLocation, Point, Points, Timepoint},
+        scenario::{ScenarioIdentity, ScenarioMessage, ScenarioNodeSettings},
+        simulation::{data_structs::LogSource, run_simulation},
+        units::{METRES, MINS, SECONDS},
+    };
+
+    fn small_scenario() -> Scenario {
+        ScenarioIdentity::for_test(8, 10, 2.0 * MINS, 42).create()
+    }
+
+    /// The built in models that can fail [`HarnessCheck::GeneratedMessagesTransmitted`] by
+    /// design, and the node log that shows why a message wasn't sent.
+    ///
+    /// - Models that send straight away with [`Context::enqueue_send`] drop the packet on a
+    ///   [`NodeError::RadioBusyError`], so miss messages generated while transmitting.
+    /// - [`Trickle`](crate::node::Trickle) only sends the newest message it knows of, so
+    ///   misses one superseded by a newer message before its turn to transmit came.
+    ///
+    /// [`Context::enqueue_send`]: crate::simulation::Context::enqueue_send
+    /// [`NodeError::RadioBusyError`]: crate::simulation::NodeError::RadioBusyError
+    fn missed_message_log(model: ModelSelection) -> Option<fn(&str) -> bool> {
+        match model {
+            ModelSelection::BasicFlood
+            | ModelSelection::StackFlood
+            | ModelSelection::AdaptiveSf => Some(|text| text == "Radio busy error"),
+            ModelSelection::Trickle => Some(|text| text.starts_with("Heard newer")),
+            _ => None,
+        }
+    }
+
+    /// Asserts every generated message `model` didn't send is explained by its node logs,
+    /// see [`missed_message_log`].
+    fn assert_missing_explained(model: ModelSelection, scenario: &Scenario, seed: u64) {
+        let explains = missed_message_log(model).expect("model should send every message");
+        let output = run_simulation(seed, scenario.clone(), model.into(), true);
+
+        for (message_id, message) in scenario.messages.iter().enumerate() {
+            let sent = output.transmissions.iter().any(|x| {
+                x.transmitter_id == message.sender
+                    && matches!(x.message_content, MessageContent::GeneratedMessage(id)
+                        if id == message_id)
+            });
+            if sent || message.generate_time >= SIM_END {
+                continue;
+            }
+
+            let explained = output.logs.iter().any(|x| {
+                matches!(x.source, LogSource::Node(id) if id == message.sender)
+                    && x.time >= message.generate_time
+                    && matches!(&x.content, LogContent::Text(text) if explains(text))
+            });
+            assert!(explained, "{model:?} seed {seed} didn't send message {message_id}");
+        }
+    }
+
+    #[test]
+    fn builtin_models_pass_harness() {
+        let scenarios = [small_scenario()];
+
+        for model in MODEL_LIST {
+            let report = model_harness(model.into(), &scenarios, &[1, 2, 3]);
+            assert_eq!(report.runs.len(), 3);
+
+            let expected = |check: HarnessCheck| match check {
+                HarnessCheck::GeneratedMessagesTransmitted => missed_message_log(model).is_some(),
+                _ => false,
+            };
+            let failures: Vec<_> = report
+                .failures()
+                .filter(|(.., check, _)| !expected(*check))
+                .collect();
+            assert!(failures.is_empty(), "{model:?} failed: {failures:?}");
+
+            for (scenario_index, seed, check, _) in report.failures() {
+                if check == HarnessCheck::GeneratedMessagesTransmitted {
+                    assert_missing_explained(model, &scenarios[scenario_index], seed);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn duty_cycle_exceeded() {
+        let settings = ScenarioNodeSettings::default();
+
+        // Repeat a large message until node 0 has roughly 60s of airtime
+        let size = 200 + crate::node::BasicHeader::SIZE;
+        let airtime = crate::calculate_air_time(size, &settings.clone().into());
+        let repeats = (60.0 / airtime.seconds()).ceil() as u32;
+
+        let scenario = Scenario {
+            messages: vec![
+                ScenarioMessage::new(0, vec![1], 1.0 * SECONDS, 200)
+                    .with_repeats(repeats, 20.0 * SECONDS),
+            ],
+            settings: vec![settings.clone(), settings],
+            ..Scenario::for_test(NodeLocation::Points(Points::new(vec![Timepoint {
+                time: 0.0 * SECONDS,
+                node_points: vec![
+                    Point {
+                        x: 0.0 * METRES,
+                        y: 0.0 * METRES,
+                    },
+                    Point {
+                        x: 100.0 * METRES,
+                        y: 0.0 * METRES,
+                    },
+                ],
+            }])))
+        };
+
+        let output = run_simulation(1, scenario.clone(), NoRouting::new().into(), false);
+        let analysis = CompleteAnalysis::new(output, scenario);
+
+        let worst = max_duty_cycle(&analysis).unwrap();
+        assert_eq!(worst.node_id, 0);
+        assert!(worst.utilisation >= 60.0 / 3600.0 * 0.99);
+        assert_eq!(duty_cycle_within(&analysis, EU868_DUTY_CYCLE), Err(worst));
+        assert_eq!(duty_cycle_within(&analysis, 0.05), Ok(()));
+    }
+}
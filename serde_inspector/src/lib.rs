@@ -1,14 +1,523 @@
+use std::collections::HashMap;
+
 use egui::{CollapsingHeader, Widget};
-use serde_value::Value;
+use serde::Serialize;
+use serde_value::SerializerError;
 
-pub use serde_value::to_value;
+pub use serde_value::{Value, to_value};
 
 pub fn any_inspector(id: u64, mut data: Value, ui: &mut egui::Ui) {
-    value_to_gui(ui, &mut data, false, 0, 0, id);
+    let cx = Context {
+        editable: false,
+        id,
+        hints: &EditorHints::default(),
+    };
+    value_to_gui(ui, &mut data, &cx, 0, 0, "");
 }
 
 pub fn any_editor(id: u64, data: &mut Value, ui: &mut egui::Ui) {
-    value_to_gui(ui, data, true, 0, 0, id);
+    any_editor_with_hints(id, data, &EditorHints::default(), ui);
+}
+
+/// Same as [`any_editor`] but with [`EditorHints`] for the values the editor can't work out.
+pub fn any_editor_with_hints(id: u64, data: &mut Value, hints: &EditorHints, ui: &mut egui::Ui) {
+    let cx = Context {
+        editable: true,
+        id,
+        hints,
+    };
+    value_to_gui(ui, data, &cx, 0, 0, "");
+}
+
+/// Shows `new` laid out like [`any_inspector`] with the leaves that differ from `old`
+/// highlighted as `old → new`. Subtrees without changes start collapsed
+/// and the headers of those with changes say how many there are.
+pub fn diff_inspector(id: u64, old: &Value, new: &Value, ui: &mut egui::Ui) {
+    match count_changes(old, new) {
+        0 => ui.label("No changes"),
+        1 => ui.label("1 change"),
+        n => ui.label(format!("{n} changes")),
+    };
+    diff_to_gui(ui, Some(old), Some(new), id, "");
+}
+
+/// Paths of the leaves that differ between `old` and `new`, in the form [`EditorHints`] uses.
+/// Entries only one side has, and `Option`s switching between `None` and `Some`,
+/// are one change at the entry's path.
+pub fn changed_paths(old: &Value, new: &Value) -> Vec<String> {
+    let mut paths = Vec::new();
+    visit_changes(Some(old), Some(new), "", &mut |path| {
+        paths.push(path.to_owned())
+    });
+    paths
+}
+
+fn count_changes(old: &Value, new: &Value) -> usize {
+    let mut count = 0;
+    visit_changes(Some(old), Some(new), "", &mut |_| count += 1);
+    count
+}
+
+/// Looks through newtypes, and `Option`s present on both sides, so they compare like what
+/// they hold.
+fn diff_inner<'a>(
+    old: Option<&'a Value>,
+    new: Option<&'a Value>,
+) -> (Option<&'a Value>, Option<&'a Value>) {
+    match (old, new) {
+        (Some(Value::Newtype(old)), new) => diff_inner(Some(old), new),
+        (old, Some(Value::Newtype(new))) => diff_inner(old, Some(new)),
+        (Some(Value::Option(Some(old))), Some(Value::Option(Some(new)))) => {
+            diff_inner(Some(old), Some(new))
+        }
+        pair => pair,
+    }
+}
+
+fn visit_changes(
+    old: Option<&Value>,
+    new: Option<&Value>,
+    path: &str,
+    found: &mut impl FnMut(&str),
+) {
+    match diff_inner(old, new) {
+        (Some(Value::Map(old)), Some(Value::Map(new))) => {
+            for name in old
+                .keys()
+                .chain(new.keys().filter(|x| !old.contains_key(x)))
+            {
+                let path = join_path(path, &value_to_string(name));
+                visit_changes(old.get(name), new.get(name), &path, found);
+            }
+        }
+        (Some(Value::Seq(old)), Some(Value::Seq(new))) => {
+            for index in 0..old.len().max(new.len()) {
+                let path = join_path(path, &index.to_string());
+                visit_changes(old.get(index), new.get(index), &path, found);
+            }
+        }
+        (old, new) => {
+            if old != new {
+                found(path);
+            }
+        }
+    }
+}
+
+fn diff_to_gui(ui: &mut egui::Ui, old: Option<&Value>, new: Option<&Value>, id: u64, path: &str) {
+    match diff_inner(old, new) {
+        (Some(Value::Map(old)), Some(Value::Map(new))) => {
+            for name in old
+                .keys()
+                .chain(new.keys().filter(|x| !old.contains_key(x)))
+            {
+                let label = value_to_string(name);
+                let path = join_path(path, &label);
+                diff_entry(ui, &label, old.get(name), new.get(name), id, &path);
+            }
+        }
+        (Some(Value::Seq(old)), Some(Value::Seq(new))) => {
+            for index in 0..old.len().max(new.len()) {
+                let path = join_path(path, &index.to_string());
+                let name = format!("Entry {index}");
+                diff_entry(ui, &name, old.get(index), new.get(index), id, &path);
+            }
+        }
+        (old, new) => diff_leaf(ui, old, new),
+    }
+}
+
+fn diff_entry(
+    ui: &mut egui::Ui,
+    name: &str,
+    old: Option<&Value>,
+    new: Option<&Value>,
+    id: u64,
+    path: &str,
+) {
+    let is_tree = matches!(
+        diff_inner(old, new),
+        (Some(Value::Map(_)), Some(Value::Map(_))) | (Some(Value::Seq(_)), Some(Value::Seq(_)))
+    );
+
+    if !is_tree {
+        ui.horizontal(|ui| {
+            ui.label(name);
+            diff_leaf(ui, old, new);
+        });
+        return;
+    }
+
+    let mut changes = 0;
+    visit_changes(old, new, path, &mut |_| changes += 1);
+
+    let title = match changes {
+        0 => name.to_owned(),
+        n => format!("{name} ({n} changed)"),
+    };
+
+    CollapsingHeader::new(title)
+        .id_salt(("serde_inspector_diff", id, path))
+        .default_open(changes > 0)
+        .show(ui, |ui| diff_to_gui(ui, old, new, id, path));
+}
+
+fn diff_leaf(ui: &mut egui::Ui, old: Option<&Value>, new: Option<&Value>) {
+    let (old, new) = diff_inner(old, new);
+    let text = |value: Option<&Value>| match value {
+        None => "-".to_owned(),
+        Some(Value::Map(map)) => format!("{{{} fields}}", map.len()),
+        Some(Value::Seq(values)) => format!("[{} entries]", values.len()),
+        Some(value) => value_to_string(value),
+    };
+
+    if old == new {
+        ui.label(text(new));
+    } else {
+        let colour = ui.visuals().warn_fg_color;
+        ui.colored_label(colour, format!("{} → {}", text(old), text(new)));
+    }
+}
+
+/// What the editor needs to know beyond the value being edited: the values given to
+/// `Option`s when they are switched on and to the first element added to an empty sequence,
+/// the variants enum fields can be switched between and the ranges and units of numbers.
+///
+/// Keyed by field path, map keys and sequence indices joined with `.` such as
+/// `"ConnectedGraph.emergency_time"`. An `Option` directly inside another `Option`
+/// has `?` added to the path and a sequence's new elements have `[]` added.
+/// A [`FieldMeta`] for `"weights[]"` applies to every element of `weights`.
+/// Lookups fall back to the last part of the path,
+/// so a default for `"emergency_time"` applies wherever that field appears.
+#[derive(Debug, Clone, Default)]
+pub struct EditorHints {
+    defaults: HashMap<String, Value>,
+    enums: HashMap<String, EnumSchema>,
+    fields: HashMap<String, FieldMeta>,
+}
+
+impl EditorHints {
+    pub fn new() -> EditorHints {
+        EditorHints::default()
+    }
+
+    pub fn insert_default(&mut self, path: impl Into<String>, value: Value) {
+        self.defaults.insert(path.into(), value);
+    }
+
+    pub fn insert_enum(&mut self, path: impl Into<String>, schema: EnumSchema) {
+        self.enums.insert(path.into(), schema);
+    }
+
+    pub fn insert_field(&mut self, path: impl Into<String>, meta: FieldMeta) {
+        self.fields.insert(path.into(), meta);
+    }
+
+    /// Defaults for every `Option` that is `Some` and sequence that isn't empty in `example`,
+    /// with numbers set to zero, strings emptied and sequences and maps cleared.
+    pub fn from_example(example: &Value) -> EditorHints {
+        fn collect(value: &Value, path: &str, out: &mut EditorHints) {
+            match value {
+                Value::Option(Some(inner)) => {
+                    out.insert_default(path, zeroed(inner));
+                    collect(inner, &option_inner_path(path, inner), out);
+                }
+                Value::Newtype(inner) => collect(inner, path, out),
+                Value::Seq(values) => {
+                    if let Some(first) = values.first() {
+                        out.insert_default(format!("{path}[]"), zeroed(first));
+                    }
+
+                    values.iter().enumerate().for_each(|(index, x)| {
+                        collect(x, &join_path(path, &index.to_string()), out)
+                    })
+                }
+                Value::Map(map) => map.iter().for_each(|(name, x)| {
+                    collect(x, &join_path(path, &value_to_string(name)), out)
+                }),
+                _ => (),
+            }
+        }
+
+        let mut out = EditorHints::new();
+        collect(example, "", &mut out);
+        out
+    }
+
+    pub fn get_default(&self, path: &str) -> Option<&Value> {
+        lookup(&self.defaults, path)
+    }
+
+    pub fn get_enum(&self, path: &str) -> Option<&EnumSchema> {
+        lookup(&self.enums, path)
+    }
+
+    pub fn get_field(&self, path: &str) -> Option<&FieldMeta> {
+        lookup(&self.fields, path).or_else(|| {
+            let (parent, index) = path.rsplit_once('.').unwrap_or(("", path));
+            index.parse::<usize>().ok()?;
+            lookup(&self.fields, &format!("{parent}[]"))
+        })
+    }
+}
+
+fn lookup<'a, T>(map: &'a HashMap<String, T>, path: &str) -> Option<&'a T> {
+    map.get(path).or_else(|| {
+        let field = path.rsplit('.').next()?;
+        map.get(field)
+    })
+}
+
+/// How a number is edited: the range it is kept in, how fast dragging changes it,
+/// the step it snaps to and the unit shown after it.
+/// Bounds and steps apply to the number as serialized, so units wrapped in a newtype
+/// are constrained in whatever unit they store.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FieldMeta {
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    pub step: Option<f64>,
+    pub suffix: String,
+    pub speed: Option<f64>,
+}
+
+impl FieldMeta {
+    pub fn new() -> FieldMeta {
+        FieldMeta::default()
+    }
+
+    pub fn range(mut self, min: f64, max: f64) -> FieldMeta {
+        self.min = Some(min);
+        self.max = Some(max);
+        self
+    }
+
+    pub fn min(mut self, min: f64) -> FieldMeta {
+        self.min = Some(min);
+        self
+    }
+
+    pub fn max(mut self, max: f64) -> FieldMeta {
+        self.max = Some(max);
+        self
+    }
+
+    pub fn step(mut self, step: f64) -> FieldMeta {
+        self.step = Some(step);
+        self
+    }
+
+    pub fn suffix(mut self, suffix: impl Into<String>) -> FieldMeta {
+        self.suffix = suffix.into();
+        self
+    }
+
+    pub fn speed(mut self, speed: f64) -> FieldMeta {
+        self.speed = Some(speed);
+        self
+    }
+
+    fn clamp(&self, x: f64) -> f64 {
+        let x = match self.step {
+            Some(step) if step > 0.0 => (x / step).round() * step,
+            _ => x,
+        };
+        x.max(self.min.unwrap_or(f64::NEG_INFINITY))
+            .min(self.max.unwrap_or(f64::INFINITY))
+    }
+
+    fn apply<'a>(&self, drag: egui::DragValue<'a>) -> egui::DragValue<'a> {
+        let min = self.min.unwrap_or(f64::NEG_INFINITY);
+        let max = self.max.unwrap_or(f64::INFINITY);
+        let mut drag = drag.range(min..=max).suffix(&self.suffix);
+
+        if let Some(speed) = self.speed {
+            drag = drag.speed(speed);
+        }
+        if let Some(step) = self.step {
+            drag = drag.fixed_decimals(decimals(step));
+        }
+        drag
+    }
+}
+
+/// Decimal places needed to show multiples of `step`.
+fn decimals(step: f64) -> usize {
+    (0..6)
+        .find(|&n| (step * 10f64.powi(n as i32)).fract().abs() < 1e-9)
+        .unwrap_or(6)
+}
+
+/// Keeps a number, or a number wrapped in newtypes, within `meta`'s range and step.
+/// Returns whether the value changed.
+pub fn clamp_numeric(value: &mut Value, meta: &FieldMeta) -> bool {
+    macro_rules! clamp {
+        ($val:expr, $ty:ty) => {{
+            let clamped = meta.clamp(*$val as f64) as $ty;
+            let changed = clamped != *$val;
+            *$val = clamped;
+            changed
+        }};
+    }
+
+    match value {
+        Value::U8(val) => clamp!(val, u8),
+        Value::U16(val) => clamp!(val, u16),
+        Value::U32(val) => clamp!(val, u32),
+        Value::U64(val) => clamp!(val, u64),
+        Value::I8(val) => clamp!(val, i8),
+        Value::I16(val) => clamp!(val, i16),
+        Value::I32(val) => clamp!(val, i32),
+        Value::I64(val) => clamp!(val, i64),
+        Value::F32(val) => clamp!(val, f32),
+        Value::F64(val) => clamp!(val, f64),
+        Value::Newtype(inner) => clamp_numeric(inner, meta),
+        _ => false,
+    }
+}
+
+/// Variants an enum field can be switched between, each with the value it starts as.
+#[derive(Debug, Clone, Default)]
+pub struct EnumSchema {
+    variants: Vec<(String, Value)>,
+}
+
+impl EnumSchema {
+    pub fn new() -> EnumSchema {
+        EnumSchema::default()
+    }
+
+    /// Schema with one variant for each example, named by how serde tags it.
+    /// Examples that don't serialize like an enum variant are left out.
+    pub fn from_examples<T: Serialize>(
+        examples: impl IntoIterator<Item = T>,
+    ) -> Result<EnumSchema, SerializerError> {
+        let mut schema = EnumSchema::new();
+
+        for example in examples {
+            let value = to_value(example)?;
+            if let Some(name) = variant_name(&value) {
+                schema.insert(name.to_owned(), value);
+            }
+        }
+
+        Ok(schema)
+    }
+
+    /// Adds a variant by name along with the whole value it serializes to.
+    pub fn insert(&mut self, name: impl Into<String>, value: Value) {
+        self.variants.push((name.into(), value));
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.variants.iter().map(|(name, _)| name.as_str())
+    }
+}
+
+/// Name of the enum variant `value` holds, if it is shaped like one.
+/// Unit variants serialize as their name and the rest as a map with their name as the only key.
+pub fn variant_name(value: &Value) -> Option<&str> {
+    match value {
+        Value::String(name) => Some(name),
+        Value::Map(map) if map.len() == 1 => match map.keys().next() {
+            Some(Value::String(name)) => Some(name),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Replaces `value` with the starting value of the variant called `name`.
+/// Does nothing and returns false if `schema` has no such variant.
+pub fn switch_variant(value: &mut Value, schema: &EnumSchema, name: &str) -> bool {
+    match schema.variants.iter().find(|(variant, _)| variant == name) {
+        Some((_, default)) => {
+            *value = default.clone();
+            true
+        }
+        None => false,
+    }
+}
+
+/// Same shape as `value` with numbers set to zero, strings emptied,
+/// sequences and maps cleared and options set to `None`.
+pub fn zeroed(value: &Value) -> Value {
+    match value {
+        Value::Bool(_) => Value::Bool(false),
+        Value::U8(_) => Value::U8(0),
+        Value::U16(_) => Value::U16(0),
+        Value::U32(_) => Value::U32(0),
+        Value::U64(_) => Value::U64(0),
+        Value::I8(_) => Value::I8(0),
+        Value::I16(_) => Value::I16(0),
+        Value::I32(_) => Value::I32(0),
+        Value::I64(_) => Value::I64(0),
+        Value::F32(_) => Value::F32(0.0),
+        Value::F64(_) => Value::F64(0.0),
+        Value::Char(_) => Value::Char(' '),
+        Value::String(_) => Value::String(String::new()),
+        Value::Unit => Value::Unit,
+        Value::Option(_) => Value::Option(None),
+        Value::Newtype(inner) => Value::Newtype(Box::new(zeroed(inner))),
+        Value::Seq(_) => Value::Seq(Vec::new()),
+        // Struct fields are kept as they are needed to deserialize
+        Value::Map(map) => Value::Map(
+            map.iter()
+                .map(|(name, x)| (name.clone(), zeroed(x)))
+                .collect(),
+        ),
+        Value::Bytes(_) => Value::Bytes(Vec::new()),
+    }
+}
+
+/// Switches an `Option` value between `None` and `Some`, filling a new `Some` with `default`.
+/// Returns false if `value` isn't an `Option` or there was no default to switch it on with.
+pub fn set_option(value: &mut Value, present: bool, default: Option<&Value>) -> bool {
+    let Value::Option(inner) = value else {
+        return false;
+    };
+
+    match (present, inner.is_some(), default) {
+        (false, _, _) => *inner = None,
+        (true, true, _) => (),
+        (true, false, Some(default)) => *inner = Some(Box::new(default.clone())),
+        (true, false, None) => return false,
+    }
+
+    true
+}
+
+/// Adds a copy of the last element to the end of a sequence, or `default` if it is empty.
+/// Returns false if nothing could be added.
+pub fn push_element(values: &mut Vec<Value>, default: Option<&Value>) -> bool {
+    match values.last().or(default) {
+        Some(element) => {
+            values.push(element.clone());
+            true
+        }
+        None => false,
+    }
+}
+
+/// Settings shared by every level of the inspector.
+struct Context<'a> {
+    editable: bool,
+    id: u64,
+    hints: &'a EditorHints,
+}
+
+fn join_path(path: &str, part: &str) -> String {
+    match path {
+        "" => part.to_owned(),
+        _ => format!("{path}.{part}"),
+    }
+}
+
+fn option_inner_path(path: &str, inner: &Value) -> String {
+    match inner {
+        Value::Option(_) => format!("{path}?"),
+        _ => path.to_owned(),
+    }
 }
 
 pub struct AnyInspector {
@@ -24,7 +533,12 @@ impl AnyInspector {
 
 impl Widget for &mut AnyInspector {
     fn ui(self, ui: &mut egui::Ui) -> egui::Response {
-        value_to_gui(ui, &mut self.data, false, 0, 0, self.id);
+        let cx = Context {
+            editable: false,
+            id: self.id,
+            hints: &EditorHints::default(),
+        };
+        value_to_gui(ui, &mut self.data, &cx, 0, 0, "");
         ui.response()
     }
 }
@@ -44,51 +558,54 @@ macro_rules! numeric_type {
     };
 }
 
-fn numeric_editor(ui: &mut egui::Ui, value: &mut Value) {
-    match value {
-        Value::U8(val) => {
-            ui.add(egui::DragValue::new(val));
-        }
-        Value::U16(val) => {
-            ui.add(egui::DragValue::new(val));
-        }
-        Value::U32(val) => {
-            ui.add(egui::DragValue::new(val));
-        }
-        Value::U64(val) => {
-            ui.add(egui::DragValue::new(val));
-        }
-        Value::I8(val) => {
-            ui.add(egui::DragValue::new(val));
-        }
-        Value::I16(val) => {
-            ui.add(egui::DragValue::new(val));
-        }
-        Value::I32(val) => {
-            ui.add(egui::DragValue::new(val));
-        }
-        Value::I64(val) => {
-            ui.add(egui::DragValue::new(val));
-        }
-        Value::F32(val) => {
-            ui.add(egui::DragValue::new(val));
-        }
-        Value::F64(val) => {
-            ui.add(egui::DragValue::new(val));
-        }
+fn numeric_editor(ui: &mut egui::Ui, value: &mut Value, meta: Option<&FieldMeta>) {
+    let drag = match value {
+        Value::U8(val) => egui::DragValue::new(val),
+        Value::U16(val) => egui::DragValue::new(val),
+        Value::U32(val) => egui::DragValue::new(val),
+        Value::U64(val) => egui::DragValue::new(val),
+        Value::I8(val) => egui::DragValue::new(val),
+        Value::I16(val) => egui::DragValue::new(val),
+        Value::I32(val) => egui::DragValue::new(val),
+        Value::I64(val) => egui::DragValue::new(val),
+        Value::F32(val) => egui::DragValue::new(val),
+        Value::F64(val) => egui::DragValue::new(val),
         _ => panic!(),
+    };
+
+    let drag = match meta {
+        Some(meta) => meta.apply(drag),
+        None => drag,
+    };
+
+    // Typed in values skip the drag range, so they are clamped here as well
+    if ui.add(drag).changed()
+        && let Some(meta) = meta
+    {
+        clamp_numeric(value, meta);
     }
 }
 
 fn value_to_gui(
     ui: &mut egui::Ui,
     value: &mut Value,
-    editable: bool,
+    cx: &Context,
     mut depth: u64,
     seq: usize,
-    id: u64,
+    path: &str,
 ) {
+    let editable = cx.editable;
     depth += 1;
+
+    if editable && let Some(schema) = cx.hints.get_enum(path) {
+        variant_selector(ui, value, schema, cx, path);
+
+        // Unit variants have nothing more to show than the selector
+        if matches!(value, Value::String(_)) {
+            return;
+        }
+    }
+
     match value {
         Value::Bool(inner) => {
             if editable {
@@ -102,13 +619,14 @@ fn value_to_gui(
         }
         numeric_type!() => {
             if editable {
-                numeric_editor(ui, value);
+                numeric_editor(ui, value, cx.hints.get_field(path));
             } else {
                 ui.label(format!("{value:?}"));
             }
         }
         Value::String(inner) => {
             if editable {
+                ui.text_edit_singleline(inner);
             } else {
                 ui.label(inner.to_string());
             }
@@ -122,10 +640,61 @@ fn value_to_gui(
             ui.label(string);
         }
         Value::Unit => {}
+        Value::Option(_) if editable => {
+            option_editor(ui, value, cx, path);
+
+            if let Value::Option(Some(ni)) = value {
+                let inner_path = option_inner_path(path, ni);
+                value_to_gui(ui, ni, cx, depth, 0, &inner_path);
+            }
+        }
         Value::Option(value) => match value {
-            Some(ni) => value_to_gui(ui, ni, editable, depth, 0, id),
+            Some(ni) => value_to_gui(ui, ni, cx, depth, 0, path),
             None => (),
         },
+        Value::Seq(values) if editable => {
+            let mut remove = None;
+
+            for (index, value) in values.iter_mut().enumerate() {
+                let path = join_path(path, &index.to_string());
+
+                if is_primitive(value) {
+                    ui.horizontal(|ui| {
+                        ui.label(format!("{index}:"));
+                        value_to_gui(ui, value, cx, depth, index, &path);
+                        if ui.small_button("✕").clicked() {
+                            remove = Some(index);
+                        }
+                    });
+                } else {
+                    ui.horizontal(|ui| {
+                        ui.label(format!("Entry {index}:"));
+                        if ui.small_button("✕").clicked() {
+                            remove = Some(index);
+                        }
+                    });
+                    value_to_gui(ui, value, cx, depth, index, &path);
+                    ui.separator();
+                }
+            }
+
+            // Removed after drawing so the labels don't shift part way through a frame
+            if let Some(index) = remove {
+                values.remove(index);
+            }
+
+            let default = cx.hints.get_default(&format!("{path}[]"));
+            let add = ui
+                .add_enabled(
+                    !values.is_empty() || default.is_some(),
+                    egui::Button::new("+"),
+                )
+                .on_disabled_hover_text("No default value is known for this sequence");
+
+            if add.clicked() {
+                push_element(values, default);
+            }
+        }
         Value::Seq(values) => {
             if values.iter().all(|x| {
                 matches!(
@@ -146,7 +715,8 @@ fn value_to_gui(
             } else {
                 values.iter_mut().enumerate().for_each(|(index, value)| {
                     ui.label(format!("Entry {index}:"));
-                    value_to_gui(ui, value, editable, depth, index, id);
+                    let path = join_path(path, &index.to_string());
+                    value_to_gui(ui, value, cx, depth, index, &path);
                     ui.separator();
                 });
             }
@@ -155,24 +725,100 @@ fn value_to_gui(
             map.iter_mut()
                 .enumerate()
                 .for_each(|(index, (name, value))| {
-                    map_match(ui, editable, depth, seq, id, index, name, value)
+                    let path = join_path(path, &value_to_string(name));
+                    map_match(ui, cx, depth, seq, index, name, value, &path)
                 });
         }
         Value::Newtype(value) => {
-            value_to_gui(ui, value, editable, depth, 0, id);
+            value_to_gui(ui, value, cx, depth, 0, path);
         }
     };
 }
 
+fn variant_selector(
+    ui: &mut egui::Ui,
+    value: &mut Value,
+    schema: &EnumSchema,
+    cx: &Context,
+    path: &str,
+) {
+    let current = variant_name(value).unwrap_or_default().to_owned();
+    let mut selected = current.clone();
+
+    egui::ComboBox::from_id_salt(("serde_inspector_enum", cx.id, path))
+        .selected_text(&current)
+        .show_ui(ui, |ui| {
+            for name in schema.names() {
+                ui.selectable_value(&mut selected, name.to_owned(), name);
+            }
+        });
+
+    if selected != current {
+        switch_variant(value, schema, &selected);
+    }
+}
+
+/// Checkbox switching an `Option` on and off.
+/// The value switched off is remembered so switching it back on restores it.
+fn option_editor(ui: &mut egui::Ui, value: &mut Value, cx: &Context, path: &str) {
+    let memory_id = egui::Id::new(("serde_inspector_option", cx.id, path));
+
+    let Value::Option(inner) = value else {
+        return;
+    };
+
+    let mut present = inner.is_some();
+    let default = match inner {
+        Some(_) => None,
+        None => ui
+            .data(|data| data.get_temp::<Value>(memory_id))
+            .or_else(|| cx.hints.get_default(path).cloned()),
+    };
+
+    let response = ui
+        .add_enabled(
+            present || default.is_some(),
+            egui::Checkbox::new(&mut present, "Some"),
+        )
+        .on_disabled_hover_text("No default value is known for this field");
+
+    if !response.changed() {
+        return;
+    }
+
+    if let Some(previous) = inner.as_deref() {
+        ui.data_mut(|data| data.insert_temp(memory_id, previous.clone()));
+    }
+
+    set_option(value, present, default.as_ref());
+}
+
+fn is_primitive(value: &Value) -> bool {
+    match value {
+        Value::Newtype(inner) => is_primitive(inner),
+        Value::Unit | Value::Bool(_) | numeric_type!() | Value::String(_) | Value::Char(_) => true,
+        _ => false,
+    }
+}
+
+/// Whether an editable option fits on one line next to its name.
+fn is_inline_option(value: &Value) -> bool {
+    match value {
+        Value::Option(None) => true,
+        Value::Option(Some(inner)) | Value::Newtype(inner) => is_inline_option(inner),
+        _ => is_primitive(value),
+    }
+}
+
 fn map_match(
     ui: &mut egui::Ui,
-    editable: bool,
+    cx: &Context,
     depth: u64,
     seq: usize,
-    id: u64,
     index: usize,
     name: &Value,
     value: &mut Value,
+    path: &str,
 ) {
     match value {
         Value::Unit
@@ -183,21 +829,27 @@ fn map_match(
         | Value::Bytes(_) => {
             ui.horizontal(|ui| {
                 ui.label(value_to_string(name));
-                value_to_gui(ui, value, editable, depth, 0, id);
+                value_to_gui(ui, value, cx, depth, 0, path);
+            });
+        }
+        Value::Option(_) if cx.editable && is_inline_option(value) => {
+            ui.horizontal(|ui| {
+                ui.label(value_to_string(name));
+                value_to_gui(ui, value, cx, depth, 0, path);
             });
         }
         Value::Map(_) | Value::Seq(_) | Value::Option(_) => {
             let name_str = value_to_string(name);
-            let id_str = format!("{name_str}{depth}a{index}a{seq}a{id}");
+            let id_str = format!("{name_str}{depth}a{index}a{seq}a{}", cx.id);
             CollapsingHeader::new(&name_str)
                 .id_salt(id_str)
                 .show(ui, |ui| {
-                    value_to_gui(ui, value, editable, depth, 0, id);
+                    value_to_gui(ui, value, cx, depth, 0, path);
                 });
         }
         Value::Newtype(inner) => {
             // Transparently pass the inner value through
-            map_match(ui, editable, depth, seq, id, index, name, inner);
+            map_match(ui, cx, depth, seq, index, name, inner, path);
         }
     }
 }
@@ -217,3 +869,291 @@ fn value_to_string(value: &Value) -> String {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+
+    use super::*;
+
+    #[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+    struct Inner {
+        count: u32,
+        name: String,
+        weights: Vec<f64>,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct Settings {
+        time: Option<f64>,
+        inner: Option<Inner>,
+        nested: Option<Option<f64>>,
+    }
+
+    fn field<'a>(value: &'a mut Value, name: &str) -> &'a mut Value {
+        let Value::Map(map) = value else {
+            panic!("expected a struct");
+        };
+        map.get_mut(&Value::String(name.to_owned())).unwrap()
+    }
+
+    #[test]
+    fn toggling_options_deserializes() {
+        let example = Settings {
+            time: Some(5.0),
+            inner: Some(Inner {
+                count: 3,
+                name: "example".to_owned(),
+                weights: vec![1.0],
+            }),
+            nested: Some(Some(2.0)),
+        };
+        let defaults = EditorHints::from_example(&to_value(&example).unwrap());
+
+        let mut value = to_value(Settings {
+            time: None,
+            inner: None,
+            nested: None,
+        })
+        .unwrap();
+
+        for path in ["time", "inner", "nested"] {
+            assert!(set_option(
+                field(&mut value, path),
+                true,
+                defaults.get_default(path)
+            ));
+        }
+
+        // The inner option of a nested option starts off
+        let nested = field(&mut value, "nested");
+        let Value::Option(Some(inner)) = nested else {
+            panic!("nested option should be on");
+        };
+        assert!(set_option(inner, true, defaults.get_default("nested?")));
+
+        let settings: Settings = value.clone().deserialize_into().unwrap();
+        assert_eq!(
+            settings,
+            Settings {
+                time: Some(0.0),
+                inner: Some(Inner::default()),
+                nested: Some(Some(0.0)),
+            }
+        );
+
+        assert!(set_option(field(&mut value, "inner"), false, None));
+        let settings: Settings = value.deserialize_into().unwrap();
+        assert_eq!(settings.inner, None);
+    }
+
+    #[test]
+    fn missing_default_leaves_option_off() {
+        let mut value = to_value(None::<f64>).unwrap();
+
+        assert!(!set_option(&mut value, true, None));
+        assert_eq!(value, Value::Option(None));
+    }
+
+    #[test]
+    fn defaults_fall_back_to_field_name() {
+        let mut defaults = EditorHints::new();
+        defaults.insert_default("emergency_time", Value::F64(60.0));
+
+        assert_eq!(
+            defaults.get_default("Generator.emergency_time"),
+            Some(&Value::F64(60.0))
+        );
+        assert_eq!(defaults.get_default("Generator.other"), None);
+    }
+
+    #[test]
+    fn sequences_round_trip_after_editing() {
+        let mut value = to_value(vec![1.0, 0.5, 0.1]).unwrap();
+        let Value::Seq(values) = &mut value else {
+            panic!("expected a sequence");
+        };
+
+        assert!(push_element(values, None));
+        values.remove(0);
+
+        let numbers: Vec<f64> = value.deserialize_into().unwrap();
+        assert_eq!(numbers, vec![0.5, 0.1, 0.1]);
+
+        let mut value = to_value(Vec::<String>::new()).unwrap();
+        let Value::Seq(values) = &mut value else {
+            panic!("expected a sequence");
+        };
+
+        assert!(!push_element(values, None));
+        assert!(push_element(values, Some(&Value::String("a".to_owned()))));
+        assert!(push_element(values, None));
+        values[1] = Value::String("b".to_owned());
+
+        let strings: Vec<String> = value.deserialize_into().unwrap();
+        assert_eq!(strings, vec!["a".to_owned(), "b".to_owned()]);
+    }
+
+    #[test]
+    fn example_gives_sequence_element_defaults() {
+        let example = to_value(Inner {
+            count: 1,
+            name: "example".to_owned(),
+            weights: vec![2.0, 3.0],
+        })
+        .unwrap();
+        let defaults = EditorHints::from_example(&example);
+
+        assert_eq!(defaults.get_default("weights[]"), Some(&Value::F64(0.0)));
+    }
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    enum Shape {
+        Empty,
+        Circle(f64),
+        Rect { width: f64, height: f64 },
+    }
+
+    #[test]
+    fn switching_variants_round_trips() {
+        let schema = EnumSchema::from_examples([
+            Shape::Empty,
+            Shape::Circle(1.0),
+            Shape::Rect {
+                width: 2.0,
+                height: 3.0,
+            },
+        ])
+        .unwrap();
+        assert_eq!(
+            schema.names().collect::<Vec<_>>(),
+            vec!["Empty", "Circle", "Rect"]
+        );
+
+        let mut value = to_value(Shape::Circle(5.0)).unwrap();
+        assert_eq!(variant_name(&value), Some("Circle"));
+
+        for (name, expected) in [
+            (
+                "Rect",
+                Shape::Rect {
+                    width: 2.0,
+                    height: 3.0,
+                },
+            ),
+            ("Empty", Shape::Empty),
+            ("Circle", Shape::Circle(1.0)),
+        ] {
+            assert!(switch_variant(&mut value, &schema, name));
+            assert_eq!(variant_name(&value), Some(name));
+
+            let shape: Shape = value.clone().deserialize_into().unwrap();
+            assert_eq!(shape, expected);
+        }
+
+        assert!(!switch_variant(&mut value, &schema, "Triangle"));
+        assert_eq!(variant_name(&value), Some("Circle"));
+    }
+
+    #[test]
+    fn out_of_range_drags_are_clamped() {
+        let meta = FieldMeta::new().range(7.0, 12.0);
+
+        let mut sf = Value::I32(15);
+        assert!(clamp_numeric(&mut sf, &meta));
+        assert_eq!(sf, Value::I32(12));
+
+        let mut sf = Value::I32(3);
+        assert!(clamp_numeric(&mut sf, &meta));
+        assert_eq!(sf, Value::I32(7));
+
+        let mut sf = Value::I32(9);
+        assert!(!clamp_numeric(&mut sf, &meta));
+        assert_eq!(sf, Value::I32(9));
+
+        // Unsigned values can't go below zero even with a negative minimum
+        let mut count = Value::U64(0);
+        assert!(!clamp_numeric(&mut count, &FieldMeta::new().min(-5.0)));
+        assert_eq!(count, Value::U64(0));
+
+        // Quantities serialize as a newtype around the number
+        let meta = FieldMeta::new().min(1.0).step(0.5).suffix(" m");
+        let mut length = Value::Newtype(Box::new(Value::F64(-20.0)));
+        assert!(clamp_numeric(&mut length, &meta));
+        assert_eq!(length, Value::Newtype(Box::new(Value::F64(1.0))));
+
+        let mut length = Value::Newtype(Box::new(Value::F64(3.3)));
+        assert!(clamp_numeric(&mut length, &meta));
+        assert_eq!(length, Value::Newtype(Box::new(Value::F64(3.5))));
+
+        assert!(!clamp_numeric(&mut Value::String("x".to_owned()), &meta));
+    }
+
+    #[test]
+    fn field_meta_applies_to_sequence_elements() {
+        let mut hints = EditorHints::new();
+        hints.insert_field("chances[]", FieldMeta::new().range(0.0, 1.0));
+        hints.insert_field("sf", FieldMeta::new().range(7.0, 12.0));
+
+        assert!(hints.get_field("Generator.chances.2").is_some());
+        assert!(hints.get_field("chances.0").is_some());
+        assert!(hints.get_field("Generator.nodes.sf").is_some());
+        assert!(hints.get_field("Generator.nodes.2").is_none());
+    }
+
+    #[test]
+    fn diff_finds_changed_leaves() {
+        let old = Settings {
+            time: Some(5.0),
+            inner: Some(Inner {
+                count: 3,
+                name: "a".to_owned(),
+                weights: vec![1.0, 2.0, 3.0],
+            }),
+            nested: None,
+        };
+        let old_value = to_value(&old).unwrap();
+        assert!(changed_paths(&old_value, &old_value).is_empty());
+
+        // Nested maps and sequences report each changed leaf,
+        // with entries past the end of the shorter sequence counting once each
+        let mut new = old.clone();
+        let inner = new.inner.as_mut().unwrap();
+        inner.name = "b".to_owned();
+        inner.weights = vec![1.0, 4.0, 3.0, 5.0, 6.0];
+        let mut paths = changed_paths(&old_value, &to_value(&new).unwrap());
+        paths.sort();
+        assert_eq!(
+            paths,
+            vec![
+                "inner.name",
+                "inner.weights.1",
+                "inner.weights.3",
+                "inner.weights.4"
+            ]
+        );
+
+        let mut shorter = old.clone();
+        shorter.inner.as_mut().unwrap().weights.clear();
+        assert_eq!(
+            changed_paths(&old_value, &to_value(&shorter).unwrap()).len(),
+            3
+        );
+
+        // Options switching on or off are one change however much they hold
+        let mut new = old.clone();
+        new.time = None;
+        new.inner = None;
+        new.nested = Some(Some(1.0));
+        let mut paths = changed_paths(&old_value, &to_value(&new).unwrap());
+        paths.sort();
+        assert_eq!(paths, vec!["inner", "nested", "time"]);
+
+        let mut new = old.clone();
+        new.nested = Some(None);
+        assert_eq!(
+            changed_paths(&old_value, &to_value(&new).unwrap()),
+            vec!["nested"]
+        );
+    }
+}